@@ -48,6 +48,8 @@ export default defineConfig({
         items: [
           { text: 'Ingest API', link: '/guides/ingest-api' },
           { text: 'Process cleanup', link: '/guides/process-cleanup' },
+          { text: 'Automation socket', link: '/guides/automation' },
+          { text: 'Desktop plugins', link: '/guides/plugins' },
           { text: 'Visualizer plugins', link: '/visualizer-plugins' },
         ],
       },
@@ -26,6 +26,7 @@ import { useDocumentTitle } from '@/hooks/use-document-title';
 import { useGlobalShortcuts } from '@/hooks/use-global-shortcuts';
 import { useRouteSync } from '@/hooks/use-route-sync';
 import { useTauriAnnotatorEvents } from '@/hooks/use-tauri-annotator-events';
+import { useTauriSandbox } from '@/hooks/use-tauri-sandbox';
 import { useThreadHistoryTracker } from '@/hooks/use-thread-history-tracker';
 import { useWS } from '@/hooks/use-ws';
 import { resolveLeftPaneOpen } from '@/lib/app-layout';
@@ -238,6 +239,7 @@ export function App() {
   useGlobalShortcuts(toggleCommandPalette, toggleFileSearch, toggleTextSearch);
   useThreadHistoryTracker();
   useTauriAnnotatorEvents();
+  useTauriSandbox();
 
   // Terminals live as native dockview tabs below the center pane.
   const terminalDockview = useTerminalDockview();
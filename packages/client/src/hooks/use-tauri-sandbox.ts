@@ -0,0 +1,31 @@
+import { useEffect } from 'react';
+
+import { useProjectStore } from '@/stores/project-store';
+
+const isTauri = !!(window as unknown as { __TAURI_INTERNALS__: unknown }).__TAURI_INTERNALS__;
+const isPreviewWindow = !!(window as unknown as { __PREVIEW_MODE__: unknown }).__PREVIEW_MODE__;
+
+/**
+ * Keeps the Rust path sandbox in sync with the project list. Every fs, PTY,
+ * watcher, search and forge command rejects paths outside the registered
+ * project roots (and their worktree directories), so this must run before
+ * any of them are used.
+ *
+ * No-op outside Tauri or in the preview window.
+ */
+export function useTauriSandbox() {
+  const paths = useProjectStore((s) => s.projects.map((p) => p.path).join('\n'));
+
+  useEffect(() => {
+    if (!isTauri || isPreviewWindow) return;
+    const projects = paths ? paths.split('\n') : [];
+
+    (async () => {
+      const { invoke } = await import('@tauri-apps/api/core');
+      const result = await invoke<{ roots: string[]; rejected: string[] }>('set_project_roots', {
+        projects,
+      });
+      for (const reason of result.rejected) console.warn('[sandbox]', reason);
+    })().catch(console.error);
+  }, [paths]);
+}
@@ -156,6 +156,25 @@ describe('worktree operations', () => {
       }
     });
 
+    test('refuses a worktree with uncommitted files unless acknowledged', async () => {
+      const createResult = await createWorktree(repoPath, 'dirty');
+      expect(createResult.isOk()).toBe(true);
+
+      if (createResult.isOk()) {
+        writeFileSync(resolve(createResult.value, 'notes.txt'), 'unsaved');
+        const blocked = await removeWorktree(repoPath, createResult.value);
+        expect(blocked.isErr()).toBe(true);
+        if (blocked.isErr()) expect(blocked.error.type).toBe('CONFLICT');
+        expect(existsSync(createResult.value)).toBe(true);
+
+        const confirmed = await removeWorktree(repoPath, createResult.value, {
+          confirmations: { allowUncommitted: true },
+        });
+        expect(confirmed.isOk()).toBe(true);
+        expect(existsSync(createResult.value)).toBe(false);
+      }
+    });
+
     test('does not throw for non-existent worktree', async () => {
       // Should not throw because reject=false
       await removeWorktree(repoPath, '/nonexistent/path');
@@ -133,6 +133,7 @@ export {
   pruneOrphanWorktrees,
   checkWorktreePathInProject,
   WORKTREE_DIR_NAME,
+  type RemoveWorktreeOptions,
   type WorktreeInfo,
   type WorktreePreview,
 } from './worktree.js';
@@ -95,6 +95,18 @@ export interface NativeBranchSummaryResult {
   isMergedIntoBase: boolean;
 }
 
+export interface NativeSafetyConfirmations {
+  allowUncommitted?: boolean;
+  allowUnpushed?: boolean;
+  allowUnsnapshotted?: boolean;
+  allowRemoteOverwrite?: boolean;
+}
+
+export interface NativeSafetyCheckResult {
+  ok: boolean;
+  warnings: Array<{ kind: string; count: number; message: string; confirmFlag: string }>;
+}
+
 export interface NativeGitModule {
   ping(): string;
   getStatusSummary(
@@ -132,6 +144,13 @@ export interface NativeGitModule {
     branch: string,
   ): Promise<NativeBranchSummaryResult>;
   blameFile(filePath: string): Promise<NativeBlameResult>;
+  checkGitSafety(
+    cwd: string,
+    operation: string,
+    runId?: string | null,
+    confirmations?: NativeSafetyConfirmations | null,
+    paths?: string[] | null,
+  ): Promise<NativeSafetyCheckResult>;
 }
 
 // Heavy I/O ops (status scan, diff scan) — limit concurrent disk reads
@@ -181,6 +200,13 @@ export interface PooledNativeGitModule {
     branch: string,
   ): Promise<NativeBranchSummaryResult>;
   blameFile(filePath: string): Promise<NativeBlameResult>;
+  checkGitSafety(
+    cwd: string,
+    operation: string,
+    runId?: string | null,
+    confirmations?: NativeSafetyConfirmations | null,
+    paths?: string[] | null,
+  ): Promise<NativeSafetyCheckResult>;
 }
 
 function createPooledModule(mod: NativeGitModule): PooledNativeGitModule {
@@ -209,6 +235,7 @@ function createPooledModule(mod: NativeGitModule): PooledNativeGitModule {
     listUnmergedFiles: (...args) => lightPool(() => mod.listUnmergedFiles(...args)),
     getBranchSummary: (...args) => heavyPool(() => mod.getBranchSummary(...args)),
     blameFile: (...args) => lightPool(() => mod.blameFile(...args)),
+    checkGitSafety: (...args) => heavyPool(() => mod.checkGitSafety(...args)),
   };
 }
 
@@ -2,11 +2,12 @@ import { existsSync, readFileSync, realpathSync } from 'fs';
 import { mkdir, rm, stat } from 'fs/promises';
 import { resolve, dirname, basename, normalize, join, sep } from 'path';
 
-import { badRequest, internal, type DomainError } from '@funny/shared/errors';
+import { badRequest, conflict, internal, type DomainError } from '@funny/shared/errors';
 import { ResultAsync } from 'neverthrow';
 
 import type { SetupProgressFn } from '../ports/setup-progress.js';
 import { git } from './base.js';
+import { getNativeGit, type NativeSafetyConfirmations } from './native.js';
 import { gitRead, gitWrite } from './process.js';
 
 /**
@@ -343,15 +344,80 @@ function assertWorktreeInProjectBase(
   return null;
 }
 
+export interface RemoveWorktreeOptions {
+  /** Risks the caller acknowledged; any other one blocks the removal. */
+  confirmations?: NativeSafetyConfirmations;
+}
+
+/**
+ * The `remove_worktree` safety check of @funny/native-git: uncommitted files
+ * and commits no other branch or remote has. Without the native module the
+ * same two checks run through the CLI. Returns the unacknowledged warnings.
+ */
+async function worktreeRemovalWarnings(
+  worktreePath: string,
+  confirmations: NativeSafetyConfirmations,
+): Promise<string[]> {
+  const native = getNativeGit();
+  if (native) {
+    const check = await native.checkGitSafety(worktreePath, 'remove_worktree', null, confirmations);
+    return check.warnings.map((w) => `${w.message} (confirm with ${w.confirmFlag})`);
+  }
+
+  const warnings: string[] = [];
+  if (!confirmations.allowUncommitted) {
+    const status = await gitRead(['status', '--porcelain', '--untracked-files=all'], {
+      cwd: worktreePath,
+    });
+    const dirty = status.stdout.split('\n').filter(Boolean).length;
+    if (dirty > 0) {
+      warnings.push(
+        `${dirty} file(s) have uncommitted changes that will be lost (confirm with allowUncommitted)`,
+      );
+    }
+  }
+  if (!confirmations.allowUnpushed) {
+    const branch = await gitRead(['symbolic-ref', '--short', 'HEAD'], {
+      cwd: worktreePath,
+      reject: false,
+    });
+    const ownBranch = branch.exitCode === 0 ? [`--exclude=${branch.stdout.trim()}`] : [];
+    const count = await gitRead(
+      ['rev-list', '--count', 'HEAD', '--not', '--remotes', ...ownBranch, '--branches'],
+      { cwd: worktreePath, reject: false },
+    );
+    const unpushed = parseInt(count.stdout.trim(), 10) || 0;
+    if (unpushed > 0) {
+      warnings.push(
+        `${unpushed} commit(s) are on no other branch and have not been pushed (confirm with allowUnpushed)`,
+      );
+    }
+  }
+  return warnings;
+}
+
+/**
+ * Remove a worktree of `projectPath` and its untracked files. Refused while
+ * it has uncommitted files or unpushed commits, unless `options` acknowledges
+ * them; a worktree whose directory is already gone is just pruned.
+ */
 export function removeWorktree(
   projectPath: string,
   worktreePath: string,
+  options: RemoveWorktreeOptions = {},
 ): ResultAsync<void, DomainError> {
   return ResultAsync.fromPromise(
     (async () => {
       const containmentErr = assertWorktreeInProjectBase(projectPath, worktreePath);
       if (containmentErr) throw containmentErr;
 
+      if (existsSync(worktreePath)) {
+        const warnings = await worktreeRemovalWarnings(worktreePath, options.confirmations ?? {});
+        if (warnings.length > 0) {
+          throw conflict(`Worktree removal blocked by safety check: ${warnings.join('; ')}`);
+        }
+      }
+
       const result = await gitWrite(['worktree', 'remove', '-f', worktreePath], {
         cwd: projectPath,
         reject: false,
@@ -33,6 +33,46 @@ export interface BlameHunk {
   summary: string;
 }
 
+/** Attribution for a single line of the blamed (HEAD) file. */
+export interface BlameLine {
+  /** Line number, 1-based. */
+  line: number;
+  commitHash: string;
+  shortHash: string;
+  author: string;
+  authorEmail: string;
+  /**
+   * Author time in seconds since the Unix epoch, so the UI can compare how
+   * recently each line was touched.
+   */
+  timestamp: number;
+  relativeDate: string;
+  summary: string;
+}
+
+/**
+ * Per-line variant of `blame_file`, optionally restricted to a line range so
+ * the review panel only pays for the lines it is showing. Same HEAD-vs-working
+ * tree caveat as `blame_file`.
+ */
+export declare function blameLines(
+  filePath: string,
+  options?: BlameLinesOptions | undefined | null,
+): Promise<BlameLinesResult>;
+
+export interface BlameLinesOptions {
+  /** First line to blame, 1-based inclusive. Defaults to the first line. */
+  startLine?: number;
+  /** Last line to blame, 1-based inclusive. Defaults to the last line. */
+  endLine?: number;
+}
+
+export interface BlameLinesResult {
+  lines: Array<BlameLine>;
+  /** Number of lines in the blamed (HEAD) version of the file. */
+  blamedLineCount: number;
+}
+
 export interface BlameResult {
   hunks: Array<BlameHunk>;
   /**
@@ -62,6 +102,25 @@ export interface BranchSummaryResult {
   isMergedIntoBase: boolean;
 }
 
+/**
+ * Assess a destructive operation before running it. `operation` is one of
+ * `discard`, `remove_worktree` or `force_push`. Checks cover uncommitted
+ * files, commits no other branch or remote has, agent changes newer than
+ * the run's last snapshot (when `run_id` is given) and, for force pushes,
+ * remote-only commits. `paths` limits the uncommitted-files check of a
+ * `discard` to the paths being discarded. Warnings already acknowledged in
+ * `confirmations` are omitted, so callers proceed only when `ok` is true.
+ * `discard_changes`, `remove_worktree` and force pushes run this check
+ * themselves.
+ */
+export declare function checkGitSafety(
+  cwd: string,
+  operation: string,
+  runId?: string | undefined | null,
+  confirmations?: SafetyConfirmations | undefined | null,
+  paths?: Array<string> | undefined | null,
+): Promise<SafetyCheckResult>;
+
 /**
  * For each path in `paths`, return the subset that is ignored by .gitignore /
  * info/exclude / core.excludesFile. Mirrors `git check-ignore --stdin`.
@@ -72,6 +131,28 @@ export interface BranchSummaryResult {
  */
 export declare function checkIgnore(cwd: string, paths: Array<string>): Promise<Array<string>>;
 
+/**
+ * Report a tool call of an agent run, before it executes, and snapshot the
+ * worktree if `policy` makes it a checkpoint: `writes` tells whether the
+ * call writes files. Returns the snapshot, or `None` when none was due. Call
+ * counts are kept in memory until the run's snapshots are deleted.
+ */
+export declare function checkpoint(
+  cwd: string,
+  runId: string,
+  tool: string,
+  writes: boolean,
+  policy: CheckpointPolicy,
+): Promise<GitSnapshot | null>;
+
+/** When `checkpoint` takes a snapshot during an agent run. */
+export interface CheckpointPolicy {
+  /** Snapshot on every Nth tool call of the run; unset or 0 never does. */
+  everyToolCalls?: number;
+  /** Snapshot before every tool call that writes files. */
+  beforeWrites?: boolean;
+}
+
 export interface CommitFileEntry {
   path: string;
   status: string;
@@ -79,6 +160,44 @@ export interface CommitFileEntry {
   deletions: number;
 }
 
+/**
+ * Record the current worktree (tracked changes plus untracked, non-ignored
+ * files) as a snapshot commit without touching the index, HEAD or any
+ * branch. Returns the run's previous snapshot unchanged if the tree is
+ * identical to it. Snapshots are private to the worktree they were taken
+ * in. Use `checkpoint` to take them on a `CheckpointPolicy` instead.
+ */
+export declare function createSnapshot(
+  cwd: string,
+  runId?: string | undefined | null,
+  label?: string | undefined | null,
+): Promise<GitSnapshot | null>;
+
+/** Remove a run's baseline ref and touch log. */
+export declare function deleteRunManifest(cwd: string, runId: string): Promise<void>;
+
+/**
+ * Delete snapshots: a single one by ref name, or every snapshot of a run,
+ * which also resets its `checkpoint` count. Returns the number of refs
+ * removed.
+ */
+export declare function deleteSnapshots(
+  cwd: string,
+  runId?: string | undefined | null,
+  refName?: string | undefined | null,
+): Promise<number>;
+
+/**
+ * Throw away the changes to `paths` (repo-relative): tracked files go back
+ * to HEAD, index included, and untracked ones are deleted. Runs the
+ * `discard` safety check first and needs `confirm`.
+ */
+export declare function discardChanges(
+  cwd: string,
+  paths: Array<string>,
+  options?: GitDestructiveOptions | undefined | null,
+): Promise<void>;
+
 export interface DiffSummaryResult {
   files: Array<FileDiffSummaryItem>;
   total: number;
@@ -93,6 +212,40 @@ export interface FileDiffSummaryItem {
   deletions: number;
 }
 
+export interface FilePreview {
+  path: string;
+  source: string;
+  /**
+   * False when the file doesn't exist in the requested source (e.g. a file
+   * added in the worktree, previewed at `HEAD`).
+   */
+  exists: boolean;
+  /** Full size of the file in bytes. */
+  size: number;
+  binary: boolean;
+  /** Best-effort MIME type from magic bytes or the extension. */
+  mime?: string;
+  /** Language id for syntax highlighting (`rust`, `typescript`, …). */
+  language?: string;
+  /** Leading part of a text file, cut at a line boundary. */
+  text?: string;
+  /** True when `text` doesn't cover the whole file. */
+  truncated: boolean;
+  image?: ImagePreview;
+}
+
+export interface FilePreviewOptions {
+  /**
+   * `worktree` (default), `index`, or any revision (`HEAD`, a commit hash,
+   * `abc123^`) to read the file as of that commit.
+   */
+  source?: string;
+  maxBytes?: number;
+  maxLines?: number;
+  /** Longest edge of the image thumbnail. 0 disables thumbnails. */
+  thumbnailSize?: number;
+}
+
 export declare function getBranchSummary(
   cwd: string,
   baseBranch: string,
@@ -113,12 +266,29 @@ export declare function getCurrentBranch(cwd: string): Promise<string | null>;
 
 export declare function getDefaultBranch(cwd: string): Promise<string | null>;
 
+/**
+ * `scope` limits the summary to a repo-relative subdirectory, so an agent
+ * working on one package of a monorepo only sees its own changes.
+ */
 export declare function getDiffSummary(
   cwd: string,
   excludePatterns?: Array<string> | undefined | null,
   maxFiles?: number | undefined | null,
+  scope?: string | undefined | null,
 ): Promise<DiffSummaryResult>;
 
+/**
+ * Something to show for a file in the review UI where a diff isn't useful:
+ * the head of a large text file with its language, or format, dimensions and
+ * a thumbnail for images. Works on the worktree, the index or any revision,
+ * so both sides of a binary diff can be previewed.
+ */
+export declare function getFilePreview(
+  cwd: string,
+  filePath: string,
+  options?: FilePreviewOptions | undefined | null,
+): Promise<FilePreview>;
+
 /** Full-context diff (equivalent to git diff -U99999). Shows all lines of the file. */
 export declare function getFullContextFileDiff(
   cwd: string,
@@ -131,8 +301,25 @@ export declare function getLog(
   limit?: number | undefined | null,
 ): Promise<Array<GitLogEntry>>;
 
+/**
+ * Query the history of HEAD with optional path / author filters, returning
+ * per-commit change stats against the first parent (the empty tree for root
+ * commits). Stats cover the whole commit, not just the filtered `path`.
+ */
+export declare function getLogDetailed(
+  cwd: string,
+  options?: GitLogQuery | undefined | null,
+): Promise<Array<GitLogDetailedEntry>>;
+
 export declare function getRemoteUrl(cwd: string): Promise<string | null>;
 
+/**
+ * Describe how a working tree is laid out: whether it is a linked worktree
+ * or a submodule, its sparse-checkout configuration, and the state of every
+ * submodule registered in `.gitmodules`.
+ */
+export declare function getRepoLayout(cwd: string): Promise<GitRepoLayout>;
+
 export declare function getSingleFileDiff(
   cwd: string,
   filePath: string,
@@ -149,14 +336,50 @@ export declare function getStashList(cwd: string): Promise<Array<StashEntry>>;
 
 export declare function getStashShow(cwd: string, stashRef: string): Promise<Array<StashFileEntry>>;
 
+/**
+ * `scope` restricts the file and line counts to a repo-relative
+ * subdirectory (e.g. one package of a monorepo); commit counts always cover
+ * the whole branch.
+ */
 export declare function getStatusSummary(
   worktreeCwd: string,
   baseBranch?: string | undefined | null,
   projectCwd?: string | undefined | null,
+  scope?: string | undefined | null,
 ): Promise<GitStatusSummary>;
 
 export declare function getUnpushedHashes(cwd: string): Promise<Array<string>>;
 
+export declare function getUpstream(cwd: string): Promise<GitUpstream | null>;
+
+/** Options of the destructive operations that run the guard themselves. */
+export interface GitDestructiveOptions {
+  /**
+   * Must be set: the caller asked the user (or was told to go ahead) and
+   * did not just forward a request. Without it nothing is touched.
+   */
+  confirm?: boolean;
+  /** Agent run whose snapshots count as saved work for `allowUnsnapshotted`. */
+  runId?: string;
+  /** Warnings the user acknowledged; any other one blocks the operation. */
+  confirmations?: SafetyConfirmations;
+}
+
+export interface GitLogDetailedEntry {
+  hash: string;
+  shortHash: string;
+  author: string;
+  authorEmail: string;
+  /** Author time in seconds since the Unix epoch. */
+  timestamp: number;
+  relativeDate: string;
+  subject: string;
+  body: string;
+  filesChanged: number;
+  additions: number;
+  deletions: number;
+}
+
 export interface GitLogEntry {
   hash: string;
   shortHash: string;
@@ -166,6 +389,83 @@ export interface GitLogEntry {
   body: string;
 }
 
+/**
+ * Filters for `get_log_detailed`. All fields are optional; an empty options
+ * object behaves like `get_log` with stats attached.
+ */
+export interface GitLogQuery {
+  /** Maximum number of commits to return (default 50). */
+  limit?: number;
+  /**
+   * Only include commits that changed this repo-relative file or directory
+   * relative to their first parent (like `git log -- <path>`).
+   */
+  path?: string;
+  /**
+   * Case-insensitive substring matched against the author name and email
+   * (like `git log --author`, minus regex support).
+   */
+  author?: string;
+}
+
+export interface GitPushOptions {
+  /** Remote to push to. Defaults to the branch's configured remote, then `origin`. */
+  remote?: string;
+  /** Local branch to push. Defaults to the current branch. */
+  branch?: string;
+  /** Pass `--set-upstream` so later pulls/pushes track the pushed branch. */
+  setUpstream?: boolean;
+  /** Pass `--force-with-lease` (never a bare `--force`). */
+  forceWithLease?: boolean;
+  /**
+   * Acknowledged risks. A force push that would drop remote-only commits is
+   * refused unless `allow_remote_overwrite` is set.
+   */
+  confirmations?: SafetyConfirmations;
+}
+
+export interface GitPushResult {
+  remote: string;
+  branch: string;
+  upstreamSet: boolean;
+  /** Combined `git push --porcelain` output, for display in the UI. */
+  output: string;
+}
+
+export interface GitRemote {
+  name: string;
+  fetchUrl?: string;
+  /** Explicit `remote.<name>.pushurl`, falling back to the fetch URL. */
+  pushUrl?: string;
+}
+
+export interface GitRepoLayout {
+  /** Absolute path of the working tree root. */
+  workdir: string;
+  /** True for worktrees created with `git worktree add`. */
+  isLinkedWorktree: boolean;
+  /** True when this working tree is itself a submodule of another repo. */
+  isSubmodule: boolean;
+  sparseCheckout: boolean;
+  /** True when sparse checkout uses cone mode (plain directory list). */
+  sparseCone: boolean;
+  /** Directories (cone mode) or patterns (non-cone) included in the checkout. */
+  sparsePatterns: Array<string>;
+  submodules: Array<GitSubmoduleInfo>;
+}
+
+export interface GitSnapshot {
+  hash: string;
+  refName: string;
+  runId: string;
+  label: string;
+  /** Creation time in milliseconds since the Unix epoch. */
+  createdAt: number;
+  relativeDate: string;
+  /** HEAD at the time of the snapshot, if the branch had any commits. */
+  baseCommit?: string;
+}
+
 export interface GitStatusSummary {
   dirtyFileCount: number;
   unpushedCommitCount: number;
@@ -176,6 +476,48 @@ export interface GitStatusSummary {
   linesDeleted: number;
 }
 
+export interface GitSubmoduleInfo {
+  name: string;
+  /** Path relative to the superproject's working tree. */
+  path: string;
+  url?: string;
+  /** Commit recorded in the superproject's index. */
+  indexCommit?: string;
+  /** Commit currently checked out in the submodule, if it is initialized. */
+  headCommit?: string;
+  /** True once the submodule repository has been cloned and checked out. */
+  initialized: boolean;
+  /**
+   * True if the submodule's working tree has uncommitted changes, or its
+   * checked-out commit differs from the one recorded in the index.
+   */
+  dirty: boolean;
+}
+
+/**
+ * Tracking information for the current branch, as configured by
+ * `branch.<name>.remote` / `branch.<name>.merge`.
+ */
+export interface GitUpstream {
+  branch: string;
+  remote: string;
+  /** Short name of the remote branch, e.g. `main` for `refs/heads/main`. */
+  remoteBranch: string;
+  /** Commits on the local branch that the upstream does not have. */
+  ahead: number;
+  /** Commits on the upstream that the local branch does not have. */
+  behind: number;
+}
+
+export interface ImagePreview {
+  /** `png`, `jpeg`, `gif`, `webp`, `bmp` or `ico`. */
+  format: string;
+  width: number;
+  height: number;
+  /** `data:image/png;base64,…` thumbnail, when the image could be decoded. */
+  thumbnail?: string;
+}
+
 export declare function listBranches(cwd: string): Promise<Array<string>>;
 
 export declare function listBranchesDetailed(cwd: string): Promise<Array<BranchDetailedInfo>>;
@@ -196,6 +538,14 @@ export interface ListFilesOptions {
   includeIgnored?: boolean;
 }
 
+export declare function listRemotes(cwd: string): Promise<Array<GitRemote>>;
+
+/** List snapshots, newest first, optionally restricted to one run. */
+export declare function listSnapshots(
+  cwd: string,
+  runId?: string | undefined | null,
+): Promise<Array<GitSnapshot>>;
+
 /**
  * List repo-relative paths that have any unmerged (conflict) index entries
  * (stage 1, 2, or 3). Mirrors `git ls-files --unmerged` minus the mode /
@@ -207,8 +557,178 @@ export declare function listUnmergedFiles(cwd: string): Promise<Array<string>>;
 /** Simple ping function to verify the native module loads correctly. */
 export declare function ping(): string;
 
+/**
+ * Get a freshly created worktree ready for an agent: initialize submodules
+ * and/or narrow the checkout to the directories the agent works in. Returns
+ * the resulting layout.
+ */
+export declare function prepareWorktree(
+  cwd: string,
+  options?: PrepareWorktreeOptions | undefined | null,
+): Promise<GitRepoLayout>;
+
+export interface PrepareWorktreeOptions {
+  /**
+   * Run `git submodule update --init --recursive`. Fresh worktrees start with
+   * empty submodule directories.
+   */
+  initSubmodules?: boolean;
+  /**
+   * Restrict the checkout to these directories with cone-mode sparse
+   * checkout. An empty list disables sparse checkout again.
+   */
+  sparsePaths?: Array<string>;
+}
+
+/**
+ * Push a branch with the system `git` so the user's configured credential
+ * helper (osxkeychain, manager, libsecret, `gh auth git-credential`, …) and
+ * SSH agent are used exactly as they would be from a terminal.
+ *
+ * Interactive prompts are disabled (`GIT_TERMINAL_PROMPT=0`): with no TTY a
+ * prompt would block the libuv worker forever, so missing credentials surface
+ * as an error instead.
+ */
+export declare function pushBranch(
+  cwd: string,
+  options?: GitPushOptions | undefined | null,
+): Promise<GitPushResult>;
+
+/**
+ * Attribute repo-relative `paths` to a tool call (e.g. `Edit`, `Write`,
+ * `apply_patch`) of the run.
+ */
+export declare function recordRunTouches(
+  cwd: string,
+  runId: string,
+  tool: string,
+  paths: Array<string>,
+): Promise<void>;
+
+/**
+ * `git worktree remove --force` a linked worktree of the repository at
+ * `cwd`, with its untracked files. Runs the `remove_worktree` safety check
+ * on it first and needs `confirm`.
+ */
+export declare function removeWorktree(
+  cwd: string,
+  worktreePath: string,
+  options?: GitDestructiveOptions | undefined | null,
+): Promise<void>;
+
 export declare function resetSoft(cwd: string): Promise<void>;
 
+/**
+ * Roll the working tree back to a snapshot. A backup snapshot of the current
+ * state is taken first (under the `restore-backup` run), files created after
+ * the snapshot are removed, and every file in the snapshot is checked out.
+ * The index, HEAD and ignored files are left alone. `hash` must be one of
+ * this worktree's snapshots.
+ */
+export declare function restoreSnapshot(cwd: string, hash: string): Promise<RestoreSnapshotResult>;
+
+export interface RestoreSnapshotResult {
+  /**
+   * Snapshot taken of the worktree right before restoring, so the restore
+   * itself can be undone.
+   */
+  backup?: GitSnapshot;
+  /** Repo-relative files that did not exist in the snapshot and were removed. */
+  removed: Array<string>;
+  /**
+   * Files that should have been removed but could not be, each with the
+   * reason. The rest of the restore still happened.
+   */
+  failed: Array<string>;
+}
+
+/**
+ * Everything that changed in the worktree since the run's baseline, with
+ * before/after blob ids, merged with the paths its tool calls reported.
+ * Changes are computed from file contents, so edits made by formatters or
+ * generators are included even though no tool call names them.
+ */
+export declare function runManifest(cwd: string, runId: string): Promise<RunManifest>;
+
+export interface RunManifest {
+  runId: string;
+  baseCommit: string;
+  entries: Array<RunManifestEntry>;
+  /**
+   * Changed files no tool call accounts for — typically formatters,
+   * generators or package managers the agent ran through a shell.
+   */
+  unattributedCount: number;
+}
+
+export interface RunManifestBase {
+  runId: string;
+  baseCommit: string;
+  baseTree: string;
+  /** When the baseline was recorded, in milliseconds since the Unix epoch. */
+  createdAt: number;
+}
+
+export interface RunManifestEntry {
+  path: string;
+  /**
+   * `added`, `modified`, `deleted`, `type_changed`, or `unchanged` for files
+   * a tool reported touching that ended up identical to the baseline.
+   */
+  status: string;
+  /** Git blob id of the file at the start of the run. */
+  beforeHash?: string;
+  /** Git blob id of the file now. */
+  afterHash?: string;
+  /** Tools that reported touching this path, in first-seen order. */
+  tools: Array<string>;
+}
+
+export interface SafetyCheckResult {
+  /** True when there are no unacknowledged warnings. */
+  ok: boolean;
+  warnings: Array<SafetyWarning>;
+}
+
+/**
+ * Flags a caller passes to acknowledge specific risks. Each warning returned
+ * by `check_git_safety` names the flag that would silence it.
+ */
+export interface SafetyConfirmations {
+  /** Proceed even though the worktree has uncommitted changes. */
+  allowUncommitted?: boolean;
+  /** Proceed even though the branch has commits no other branch or remote has. */
+  allowUnpushed?: boolean;
+  /** Proceed even though the agent run's latest changes are not snapshotted. */
+  allowUnsnapshotted?: boolean;
+  /** Proceed with a force push that drops commits only the remote has. */
+  allowRemoteOverwrite?: boolean;
+}
+
+export interface SafetyWarning {
+  /**
+   * `uncommitted_changes`, `unpushed_commits`, `unsnapshotted_changes` or
+   * `remote_commits_overwritten`.
+   */
+  kind: string;
+  /** Number of files or commits at risk. */
+  count: number;
+  message: string;
+  /** camelCase name of the `SafetyConfirmations` flag that acknowledges this. */
+  confirmFlag: string;
+}
+
+/**
+ * Record the worktree (tracked plus untracked, non-ignored files) as the
+ * baseline for `run_id`. Call when the agent run starts. An existing
+ * baseline is kept unless `reset` is set, so resumed runs keep their origin.
+ */
+export declare function startRunManifest(
+  cwd: string,
+  runId: string,
+  reset?: boolean | undefined | null,
+): Promise<RunManifestBase>;
+
 export interface StashEntry {
   index: string;
   message: string;
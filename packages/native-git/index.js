@@ -779,7 +779,14 @@ if (!nativeBinding) {
 
 module.exports = nativeBinding;
 module.exports.blameFile = nativeBinding.blameFile;
+module.exports.blameLines = nativeBinding.blameLines;
+module.exports.checkGitSafety = nativeBinding.checkGitSafety;
 module.exports.checkIgnore = nativeBinding.checkIgnore;
+module.exports.checkpoint = nativeBinding.checkpoint;
+module.exports.createSnapshot = nativeBinding.createSnapshot;
+module.exports.deleteRunManifest = nativeBinding.deleteRunManifest;
+module.exports.deleteSnapshots = nativeBinding.deleteSnapshots;
+module.exports.discardChanges = nativeBinding.discardChanges;
 module.exports.getBranchSummary = nativeBinding.getBranchSummary;
 module.exports.getCommitBody = nativeBinding.getCommitBody;
 module.exports.getCommitFileDiff = nativeBinding.getCommitFileDiff;
@@ -787,18 +794,31 @@ module.exports.getCommitFiles = nativeBinding.getCommitFiles;
 module.exports.getCurrentBranch = nativeBinding.getCurrentBranch;
 module.exports.getDefaultBranch = nativeBinding.getDefaultBranch;
 module.exports.getDiffSummary = nativeBinding.getDiffSummary;
+module.exports.getFilePreview = nativeBinding.getFilePreview;
 module.exports.getFullContextFileDiff = nativeBinding.getFullContextFileDiff;
 module.exports.getLog = nativeBinding.getLog;
+module.exports.getLogDetailed = nativeBinding.getLogDetailed;
 module.exports.getRemoteUrl = nativeBinding.getRemoteUrl;
+module.exports.getRepoLayout = nativeBinding.getRepoLayout;
 module.exports.getSingleFileDiff = nativeBinding.getSingleFileDiff;
 module.exports.getStashFileDiff = nativeBinding.getStashFileDiff;
 module.exports.getStashList = nativeBinding.getStashList;
 module.exports.getStashShow = nativeBinding.getStashShow;
 module.exports.getStatusSummary = nativeBinding.getStatusSummary;
 module.exports.getUnpushedHashes = nativeBinding.getUnpushedHashes;
+module.exports.getUpstream = nativeBinding.getUpstream;
 module.exports.listBranches = nativeBinding.listBranches;
 module.exports.listBranchesDetailed = nativeBinding.listBranchesDetailed;
 module.exports.listFiles = nativeBinding.listFiles;
+module.exports.listRemotes = nativeBinding.listRemotes;
+module.exports.listSnapshots = nativeBinding.listSnapshots;
 module.exports.listUnmergedFiles = nativeBinding.listUnmergedFiles;
 module.exports.ping = nativeBinding.ping;
+module.exports.prepareWorktree = nativeBinding.prepareWorktree;
+module.exports.pushBranch = nativeBinding.pushBranch;
+module.exports.recordRunTouches = nativeBinding.recordRunTouches;
+module.exports.removeWorktree = nativeBinding.removeWorktree;
 module.exports.resetSoft = nativeBinding.resetSoft;
+module.exports.restoreSnapshot = nativeBinding.restoreSnapshot;
+module.exports.runManifest = nativeBinding.runManifest;
+module.exports.startRunManifest = nativeBinding.startRunManifest;
@@ -39,6 +39,8 @@ pub struct BlameResult {
 struct CommitMeta {
   short_hash: String,
   author: String,
+  author_email: String,
+  timestamp: i64,
   relative_date: String,
   summary: String,
 }
@@ -53,6 +55,8 @@ fn resolve_commit_meta(repo: &gix::Repository, id: ObjectId) -> CommitMeta {
       return CommitMeta {
         short_hash,
         author: String::new(),
+        author_email: String::new(),
+        timestamp: 0,
         relative_date: String::new(),
         summary: String::new(),
       };
@@ -64,6 +68,10 @@ fn resolve_commit_meta(repo: &gix::Repository, id: ObjectId) -> CommitMeta {
     .as_ref()
     .map(|a| a.name.to_string())
     .unwrap_or_default();
+  let author_email = author_sig
+    .as_ref()
+    .map(|a| a.email.to_string())
+    .unwrap_or_default();
   let time_seconds = author_sig
     .as_ref()
     .and_then(|a| a.time().ok())
@@ -77,6 +85,8 @@ fn resolve_commit_meta(repo: &gix::Repository, id: ObjectId) -> CommitMeta {
   CommitMeta {
     short_hash,
     author,
+    author_email,
+    timestamp: time_seconds,
     relative_date,
     summary,
   }
@@ -93,20 +103,14 @@ fn count_lines(blob: &[u8]) -> u32 {
   (newlines + trailing) as u32
 }
 
-/// Blame the file at the absolute path `file_path` against the current HEAD
-/// commit and return per-hunk attribution.
-///
-/// The repository is discovered by walking up from the file's directory, so the
-/// caller does not need to know the repo / worktree root — only a canonical
-/// absolute path (which the route has already scope-checked).
-///
-/// **Working-tree caveat:** gix blames the file as it exists at HEAD, not the
-/// working copy. When the open file has uncommitted edits, line numbers can
-/// drift and freshly added lines have no entry. Callers should treat lines past
-/// `blamed_line_count` (or unmatched lines) as "not committed yet".
-#[napi]
-pub async fn blame_file(file_path: String) -> napi::Result<BlameResult> {
-  let abs = PathBuf::from(&file_path);
+/// Discover the repository containing the absolute path `file_path` and blame
+/// the file against HEAD with the given options. Returns the repository too so
+/// callers can resolve commit metadata for the outcome's entries.
+fn run_blame(
+  file_path: &str,
+  options: gix::repository::blame_file::Options,
+) -> napi::Result<(gix::Repository, gix::blame::Outcome)> {
+  let abs = PathBuf::from(file_path);
   let dir = abs
     .parent()
     .ok_or_else(|| napi::Error::from_reason("File path has no parent directory".to_string()))?;
@@ -128,15 +132,34 @@ pub async fn blame_file(file_path: String) -> napi::Result<BlameResult> {
   let rel = rel.to_string_lossy().replace('\\', "/");
   let path_bstr: &BStr = BStr::new(rel.as_bytes());
 
-  let head = repo
+  let suspect = repo
     .head_commit()
-    .map_err(|e| napi::Error::from_reason(format!("Failed to get HEAD commit: {e}")))?;
-  let suspect = head.id().detach();
+    .map_err(|e| napi::Error::from_reason(format!("Failed to get HEAD commit: {e}")))?
+    .id()
+    .detach();
 
   let outcome = repo
-    .blame_file(path_bstr, suspect, Default::default())
+    .blame_file(path_bstr, suspect, options)
     .map_err(|e| napi::Error::from_reason(format!("Failed to blame '{}': {e}", rel)))?;
 
+  Ok((repo, outcome))
+}
+
+/// Blame the file at the absolute path `file_path` against the current HEAD
+/// commit and return per-hunk attribution.
+///
+/// The repository is discovered by walking up from the file's directory, so the
+/// caller does not need to know the repo / worktree root — only a canonical
+/// absolute path (which the route has already scope-checked).
+///
+/// **Working-tree caveat:** gix blames the file as it exists at HEAD, not the
+/// working copy. When the open file has uncommitted edits, line numbers can
+/// drift and freshly added lines have no entry. Callers should treat lines past
+/// `blamed_line_count` (or unmatched lines) as "not committed yet".
+#[napi]
+pub async fn blame_file(file_path: String) -> napi::Result<BlameResult> {
+  let (repo, outcome) = run_blame(&file_path, Default::default())?;
+
   let blamed_line_count = count_lines(&outcome.blob);
 
   let mut meta_cache: HashMap<ObjectId, CommitMeta> = HashMap::new();
@@ -165,3 +188,95 @@ pub async fn blame_file(file_path: String) -> napi::Result<BlameResult> {
     blamed_line_count,
   })
 }
+
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct BlameLinesOptions {
+  /// First line to blame, 1-based inclusive. Defaults to the first line.
+  pub start_line: Option<u32>,
+  /// Last line to blame, 1-based inclusive. Defaults to the last line.
+  pub end_line: Option<u32>,
+}
+
+/// Attribution for a single line of the blamed (HEAD) file.
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct BlameLine {
+  /// Line number, 1-based.
+  pub line: u32,
+  pub commit_hash: String,
+  pub short_hash: String,
+  pub author: String,
+  pub author_email: String,
+  /// Author time in seconds since the Unix epoch, so the UI can compare how
+  /// recently each line was touched.
+  pub timestamp: i64,
+  pub relative_date: String,
+  pub summary: String,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct BlameLinesResult {
+  pub lines: Vec<BlameLine>,
+  /// Number of lines in the blamed (HEAD) version of the file.
+  pub blamed_line_count: u32,
+}
+
+/// Per-line variant of `blame_file`, optionally restricted to a line range so
+/// the review panel only pays for the lines it is showing. Same HEAD-vs-working
+/// tree caveat as `blame_file`.
+#[napi]
+pub async fn blame_lines(
+  file_path: String,
+  options: Option<BlameLinesOptions>,
+) -> napi::Result<BlameLinesResult> {
+  let options = options.unwrap_or_default();
+
+  let mut blame_options = gix::repository::blame_file::Options::default();
+  if options.start_line.is_some() || options.end_line.is_some() {
+    let start = options.start_line.unwrap_or(1).max(1);
+    let end = options.end_line.unwrap_or(u32::MAX);
+    if end < start {
+      return Err(napi::Error::from_reason(format!(
+        "Invalid blame range: {start}-{end}"
+      )));
+    }
+    blame_options.ranges = gix::blame::BlameRanges::from_one_based_inclusive_range(start..=end)
+      .map_err(|e| napi::Error::from_reason(format!("Invalid blame range: {e}")))?;
+  }
+
+  let (repo, outcome) = run_blame(&file_path, blame_options)?;
+  let blamed_line_count = count_lines(&outcome.blob);
+
+  let mut meta_cache: HashMap<ObjectId, CommitMeta> = HashMap::new();
+  let mut lines: Vec<BlameLine> = Vec::new();
+
+  for entry in &outcome.entries {
+    let id = entry.commit_id;
+    let meta = meta_cache
+      .entry(id)
+      .or_insert_with(|| resolve_commit_meta(&repo, id));
+    let commit_hash = id.to_string();
+
+    for offset in 0..entry.len.get() {
+      lines.push(BlameLine {
+        line: entry.start_in_blamed_file + offset + 1,
+        commit_hash: commit_hash.clone(),
+        short_hash: meta.short_hash.clone(),
+        author: meta.author.clone(),
+        author_email: meta.author_email.clone(),
+        timestamp: meta.timestamp,
+        relative_date: meta.relative_date.clone(),
+        summary: meta.summary.clone(),
+      });
+    }
+  }
+
+  lines.sort_by_key(|l| l.line);
+
+  Ok(BlameLinesResult {
+    lines,
+    blamed_line_count,
+  })
+}
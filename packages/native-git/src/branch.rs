@@ -25,12 +25,10 @@ pub async fn list_branches(cwd: String) -> napi::Result<Vec<String>> {
     let mut seen = std::collections::HashSet::new();
     let mut branches: Vec<String> = Vec::new();
 
-    for reference in local_refs {
-      if let Ok(r) = reference {
-        let name = r.name().shorten().to_string();
-        seen.insert(name.clone());
-        branches.push(name);
-      }
+    for r in local_refs.flatten() {
+      let name = r.name().shorten().to_string();
+      seen.insert(name.clone());
+      branches.push(name);
     }
 
     // Always include remote branches that don't exist locally
@@ -42,17 +40,15 @@ pub async fn list_branches(cwd: String) -> napi::Result<Vec<String>> {
       .remote_branches()
       .map_err(|e| napi::Error::from_reason(format!("Failed to list remote branches: {e}")))?;
 
-    for reference in remote_refs {
-      if let Ok(r) = reference {
-        let name = r.name().shorten().to_string();
-        if name.contains("HEAD") {
-          continue;
-        }
-        // Only include branches with origin/ prefix, strip it
-        if let Some(stripped) = name.strip_prefix("origin/") {
-          if !stripped.is_empty() && seen.insert(stripped.to_string()) {
-            branches.push(stripped.to_string());
-          }
+    for r in remote_refs.flatten() {
+      let name = r.name().shorten().to_string();
+      if name.contains("HEAD") {
+        continue;
+      }
+      // Only include branches with origin/ prefix, strip it
+      if let Some(stripped) = name.strip_prefix("origin/") {
+        if !stripped.is_empty() && seen.insert(stripped.to_string()) {
+          branches.push(stripped.to_string());
         }
       }
     }
@@ -95,10 +91,8 @@ pub async fn get_default_branch(cwd: String) -> napi::Result<Option<String>> {
       .map_err(|e| napi::Error::from_reason(format!("Failed to list branches: {e}")))?;
 
     let mut branch_names: Vec<String> = Vec::new();
-    for reference in local_refs {
-      if let Ok(r) = reference {
-        branch_names.push(r.name().shorten().to_string());
-      }
+    for r in local_refs.flatten() {
+      branch_names.push(r.name().shorten().to_string());
     }
 
     if branch_names.contains(&"main".to_string()) {
@@ -147,10 +141,8 @@ pub async fn list_branches_detailed(cwd: String) -> napi::Result<Vec<BranchDetai
     let local_refs = refs
       .local_branches()
       .map_err(|e| napi::Error::from_reason(format!("Failed to list local branches: {e}")))?;
-    for reference in local_refs {
-      if let Ok(r) = reference {
-        local_set.insert(r.name().shorten().to_string());
-      }
+    for r in local_refs.flatten() {
+      local_set.insert(r.name().shorten().to_string());
     }
 
     // Collect remote branches
@@ -160,16 +152,14 @@ pub async fn list_branches_detailed(cwd: String) -> napi::Result<Vec<BranchDetai
     let remote_refs = refs2
       .remote_branches()
       .map_err(|e| napi::Error::from_reason(format!("Failed to list remote branches: {e}")))?;
-    for reference in remote_refs {
-      if let Ok(r) = reference {
-        let name = r.name().shorten().to_string();
-        if name.contains("HEAD") {
-          continue;
-        }
-        if let Some(stripped) = name.strip_prefix("origin/") {
-          if !stripped.is_empty() {
-            remote_set.insert(stripped.to_string());
-          }
+    for r in remote_refs.flatten() {
+      let name = r.name().shorten().to_string();
+      if name.contains("HEAD") {
+        continue;
+      }
+      if let Some(stripped) = name.strip_prefix("origin/") {
+        if !stripped.is_empty() {
+          remote_set.insert(stripped.to_string());
         }
       }
     }
@@ -183,7 +173,7 @@ pub async fn list_branches_detailed(cwd: String) -> napi::Result<Vec<BranchDetai
         name,
       })
       .collect();
-    branches.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    branches.sort_by_key(|a| a.name.to_lowercase());
 
     if branches.is_empty() {
       if let Ok(Some(head_ref)) = repo.head_ref() {
@@ -1,10 +1,11 @@
 use std::collections::HashSet;
 use std::path::Path;
 
-use gix::bstr::{BString, ByteSlice};
+use gix::bstr::ByteSlice;
 
 use crate::blob_diff::count_diff_lines;
 use crate::commit_info::count_lines;
+use crate::layout::{in_scope, normalize_scope, scope_pathspec};
 use crate::repo_cache::with_repo;
 
 /// Skip line-counting for untracked/working-tree files larger than this.
@@ -74,12 +75,17 @@ fn read_worktree_file(worktree_path: &Path, rel_path: &str) -> Option<Vec<u8>> {
   std::fs::read(&disk_path).ok()
 }
 
+/// `scope` limits the summary to a repo-relative subdirectory, so an agent
+/// working on one package of a monorepo only sees its own changes.
 #[napi]
 pub async fn get_diff_summary(
   cwd: String,
   exclude_patterns: Option<Vec<String>>,
   max_files: Option<u32>,
+  scope: Option<String>,
 ) -> napi::Result<DiffSummaryResult> {
+  let scope = normalize_scope(scope.as_deref())?;
+  let scope = scope.as_deref();
   with_repo(&cwd, |repo| {
     let exclude = exclude_patterns.unwrap_or_default();
     let max = max_files.unwrap_or(0) as usize;
@@ -110,9 +116,8 @@ pub async fn get_diff_summary(
       });
 
     // into_index_worktree_iter takes pathspec patterns (empty = all files)
-    let empty_patterns: Vec<BString> = Vec::new();
     let status_iter = status_platform
-      .into_index_worktree_iter(empty_patterns)
+      .into_index_worktree_iter(scope_pathspec(scope))
       .map_err(|e| napi::Error::from_reason(format!("Failed to iterate status: {e}")))?;
 
     let mut all_files: Vec<FileDiffSummaryItem> = Vec::new();
@@ -154,7 +159,7 @@ pub async fn get_diff_summary(
         }
       };
 
-      if !exclude.is_empty() && matches_any_pattern(&path, &exclude) {
+      if !in_scope(&path, scope) || (!exclude.is_empty() && matches_any_pattern(&path, &exclude)) {
         continue;
       }
 
@@ -216,7 +221,7 @@ pub async fn get_diff_summary(
       let path_str = entry.path(&index).to_str_lossy().to_string();
 
       // Skip files already reported as worktree changes
-      if worktree_changed_paths.contains(&path_str) {
+      if worktree_changed_paths.contains(&path_str) || !in_scope(&path_str, scope) {
         continue;
       }
 
@@ -279,7 +284,10 @@ pub async fn get_diff_summary(
         continue;
       }
       let path_str = entry.path(&index).to_str_lossy().to_string();
-      if worktree_changed_paths.contains(&path_str) || already_reported.contains(&path_str) {
+      if worktree_changed_paths.contains(&path_str)
+        || already_reported.contains(&path_str)
+        || !in_scope(&path_str, scope)
+      {
         continue;
       }
       let nested = cwd_path.join(&path_str);
@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use gix::bstr::ByteSlice;
 
@@ -41,6 +41,7 @@ fn split_lines(data: &[u8]) -> Vec<&[u8]> {
 
 /// Format changes as unified diff hunks with context lines.
 /// Output matches `git diff` format so the client-side parser works.
+#[allow(clippy::too_many_arguments)]
 fn format_unified_diff(
   old_lines: &[&[u8]],
   new_lines: &[&[u8]],
@@ -296,7 +297,7 @@ fn diff_staged_file_ctx(repo: &gix::Repository, file_path: &str, context_lines:
 
 fn diff_unstaged_file_ctx(
   repo: &gix::Repository,
-  worktree_path: &PathBuf,
+  worktree_path: &Path,
   file_path: &str,
   index: &gix::index::File,
   context_lines: u32,
@@ -337,7 +338,7 @@ fn diff_unstaged_file_ctx(
   ))
 }
 
-fn diff_untracked_file_ctx(worktree_path: &PathBuf, file_path: &str, context_lines: u32) -> napi::Result<String> {
+fn diff_untracked_file_ctx(worktree_path: &Path, file_path: &str, context_lines: u32) -> napi::Result<String> {
   let disk_path = worktree_path.join(file_path);
 
   // Size guard
@@ -0,0 +1,223 @@
+use std::path::Path;
+
+use gix::bstr::{BString, ByteSlice};
+
+use crate::repo_cache::{evict_repo, with_repo};
+use crate::snapshot::git;
+
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct GitSubmoduleInfo {
+  pub name: String,
+  /// Path relative to the superproject's working tree.
+  pub path: String,
+  pub url: Option<String>,
+  /// Commit recorded in the superproject's index.
+  pub index_commit: Option<String>,
+  /// Commit currently checked out in the submodule, if it is initialized.
+  pub head_commit: Option<String>,
+  /// True once the submodule repository has been cloned and checked out.
+  pub initialized: bool,
+  /// True if the submodule's working tree has uncommitted changes, or its
+  /// checked-out commit differs from the one recorded in the index.
+  pub dirty: bool,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct GitRepoLayout {
+  /// Absolute path of the working tree root.
+  pub workdir: String,
+  /// True for worktrees created with `git worktree add`.
+  pub is_linked_worktree: bool,
+  /// True when this working tree is itself a submodule of another repo.
+  pub is_submodule: bool,
+  pub sparse_checkout: bool,
+  /// True when sparse checkout uses cone mode (plain directory list).
+  pub sparse_cone: bool,
+  /// Directories (cone mode) or patterns (non-cone) included in the checkout.
+  pub sparse_patterns: Vec<String>,
+  pub submodules: Vec<GitSubmoduleInfo>,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct PrepareWorktreeOptions {
+  /// Run `git submodule update --init --recursive`. Fresh worktrees start with
+  /// empty submodule directories.
+  pub init_submodules: Option<bool>,
+  /// Restrict the checkout to these directories with cone-mode sparse
+  /// checkout. An empty list disables sparse checkout again.
+  pub sparse_paths: Option<Vec<String>>,
+}
+
+/// Normalize a caller-supplied scope (a repo-relative subdirectory such as
+/// `packages/api`) into a form without leading `./` or trailing `/`.
+/// Returns `None` for an empty scope or the repo root.
+pub(crate) fn normalize_scope(scope: Option<&str>) -> napi::Result<Option<String>> {
+  let raw = match scope {
+    Some(s) => s.trim().replace('\\', "/"),
+    None => return Ok(None),
+  };
+  let parts: Vec<&str> = raw
+    .split('/')
+    .filter(|p| !p.is_empty() && *p != ".")
+    .collect();
+  if parts.contains(&"..") || raw.starts_with('/') {
+    return Err(napi::Error::from_reason(format!(
+      "Scope must be a path inside the repository: {raw}"
+    )));
+  }
+  if parts.is_empty() {
+    return Ok(None);
+  }
+  Ok(Some(parts.join("/")))
+}
+
+/// True if the repo-relative `path` is the scope directory itself or lies below it.
+pub(crate) fn in_scope(path: &str, scope: Option<&str>) -> bool {
+  match scope {
+    None => true,
+    Some(dir) => {
+      path == dir || (path.len() > dir.len() && path.starts_with(dir) && path.as_bytes()[dir.len()] == b'/')
+    }
+  }
+}
+
+/// Pathspec patterns for gix status iterators. `:(top)` anchors the pattern at
+/// the working tree root regardless of the process CWD, which gix would
+/// otherwise use as the pathspec prefix.
+pub(crate) fn scope_pathspec(scope: Option<&str>) -> Vec<BString> {
+  match scope {
+    Some(dir) => vec![BString::from(format!(":(top){dir}"))],
+    None => Vec::new(),
+  }
+}
+
+fn submodule_info(submodule: &gix::Submodule<'_>) -> Option<GitSubmoduleInfo> {
+  let path = submodule.path().ok()?.to_str_lossy().to_string();
+  let url = submodule.url().ok().map(|u| u.to_bstring().to_string());
+  let index_commit = submodule.index_id().ok().flatten();
+  let initialized = submodule
+    .state()
+    .map(|s| s.repository_exists && s.worktree_checkout)
+    .unwrap_or(false);
+
+  let (head_commit, dirty) = match submodule.open() {
+    Ok(Some(sub_repo)) if initialized => {
+      let head = sub_repo.head_id().ok().map(|id| id.detach());
+      let moved = matches!((head, index_commit), (Some(h), Some(i)) if h != i);
+      let worktree = submodule.work_dir().ok();
+      // Shell out for the dirty check, as `is_nested_repo_dirty` does for
+      // gitlinks: it's the only check that honors the submodule's own config.
+      let changed = worktree
+        .map(|wd| git(&wd, &["status", "--porcelain"], None).map(|o| !o.is_empty()).unwrap_or(false))
+        .unwrap_or(false);
+      (head.map(|h| h.to_string()), moved || changed)
+    }
+    _ => (None, false),
+  };
+
+  Some(GitSubmoduleInfo {
+    name: submodule.name().to_str_lossy().to_string(),
+    path,
+    url,
+    index_commit: index_commit.map(|id| id.to_string()),
+    head_commit,
+    initialized,
+    dirty,
+  })
+}
+
+fn read_layout(cwd: &str) -> napi::Result<GitRepoLayout> {
+  let (workdir, kind, sparse_checkout, sparse_cone, submodules) = with_repo(cwd, |repo| {
+    let workdir = repo
+      .workdir()
+      .ok_or_else(|| napi::Error::from_reason("Repository has no working tree".to_string()))?
+      .to_string_lossy()
+      .to_string();
+    let config = repo.config_snapshot();
+    let sparse_checkout = config.boolean("core.sparseCheckout").unwrap_or(false);
+    let sparse_cone = sparse_checkout && config.boolean("core.sparseCheckoutCone").unwrap_or(true);
+    let submodules: Vec<GitSubmoduleInfo> = repo
+      .submodules()
+      .map_err(|e| napi::Error::from_reason(format!("Failed to read .gitmodules: {e}")))?
+      .map(|iter| iter.filter_map(|sm| submodule_info(&sm)).collect())
+      .unwrap_or_default();
+    Ok((workdir, repo.kind(), sparse_checkout, sparse_cone, submodules))
+  })?;
+
+  // `git sparse-checkout list` resolves the per-worktree pattern file for us.
+  let sparse_patterns = if sparse_checkout {
+    git(Path::new(cwd), &["sparse-checkout", "list"], None)
+      .map(|out| out.lines().map(|l| l.to_string()).filter(|l| !l.is_empty()).collect())
+      .unwrap_or_default()
+  } else {
+    Vec::new()
+  };
+
+  Ok(GitRepoLayout {
+    workdir,
+    is_linked_worktree: matches!(kind, gix::repository::Kind::LinkedWorkTree),
+    is_submodule: matches!(kind, gix::repository::Kind::Submodule),
+    sparse_checkout,
+    sparse_cone,
+    sparse_patterns,
+    submodules,
+  })
+}
+
+/// Describe how a working tree is laid out: whether it is a linked worktree
+/// or a submodule, its sparse-checkout configuration, and the state of every
+/// submodule registered in `.gitmodules`.
+#[napi]
+pub async fn get_repo_layout(cwd: String) -> napi::Result<GitRepoLayout> {
+  read_layout(&cwd)
+}
+
+/// Get a freshly created worktree ready for an agent: initialize submodules
+/// and/or narrow the checkout to the directories the agent works in. Returns
+/// the resulting layout.
+#[napi]
+pub async fn prepare_worktree(
+  cwd: String,
+  options: Option<PrepareWorktreeOptions>,
+) -> napi::Result<GitRepoLayout> {
+  let options = options.unwrap_or_default();
+  let cwd_path = Path::new(&cwd);
+
+  if let Some(paths) = options.sparse_paths {
+    let mut dirs = Vec::new();
+    for p in &paths {
+      if let Some(dir) = normalize_scope(Some(p))? {
+        dirs.push(dir);
+      }
+    }
+    if dirs.is_empty() {
+      git(cwd_path, &["sparse-checkout", "disable"], None)?;
+    } else {
+      let mut args = vec!["sparse-checkout", "set", "--cone", "--"];
+      args.extend(dirs.iter().map(|d| d.as_str()));
+      git(cwd_path, &args, None)?;
+    }
+  }
+
+  if options.init_submodules.unwrap_or(false) {
+    let output = std::process::Command::new("git")
+      .args(["submodule", "update", "--init", "--recursive"])
+      .current_dir(cwd_path)
+      .env("GIT_TERMINAL_PROMPT", "0")
+      .output()
+      .map_err(|e| napi::Error::from_reason(format!("Failed to run git submodule: {e}")))?;
+    if !output.status.success() {
+      evict_repo(&cwd);
+      return Err(napi::Error::from_reason(format!(
+        "git submodule update failed: {}",
+        String::from_utf8_lossy(&output.stderr).trim()
+      )));
+    }
+  }
+
+  evict_repo(&cwd);
+  read_layout(&cwd)
+}
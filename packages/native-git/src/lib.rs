@@ -15,6 +15,12 @@ mod list_files;
 mod stash;
 mod stage_helpers;
 mod branch_summary;
+mod remote;
+mod snapshot;
+mod safety;
+mod layout;
+mod preview;
+mod manifest;
 
 pub use status_summary::*;
 pub use diff_summary::*;
@@ -28,6 +34,12 @@ pub use list_files::*;
 pub use stash::*;
 pub use stage_helpers::*;
 pub use branch_summary::*;
+pub use remote::*;
+pub use snapshot::*;
+pub use safety::*;
+pub use layout::*;
+pub use preview::*;
+pub use manifest::*;
 
 /// Simple ping function to verify the native module loads correctly.
 #[napi]
@@ -95,7 +95,7 @@ impl gix::dir::walk::Delegate for ListDelegate {
     // is `/`-joined from the walk root.
     let path_cow = entry.rela_path.to_str_lossy();
     let last = path_cow.rsplit('/').next().unwrap_or(path_cow.as_ref());
-    if HEAVY_DIRS.iter().any(|d| *d == last) {
+    if HEAVY_DIRS.contains(&last) {
       return false;
     }
     entry.status.can_recurse(
@@ -132,6 +132,179 @@ pub async fn get_log(cwd: String, limit: Option<u32>) -> napi::Result<Vec<GitLog
   })
 }
 
+/// Filters for `get_log_detailed`. All fields are optional; an empty options
+/// object behaves like `get_log` with stats attached.
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct GitLogQuery {
+  /// Maximum number of commits to return (default 50).
+  pub limit: Option<u32>,
+  /// Only include commits that changed this repo-relative file or directory
+  /// relative to their first parent (like `git log -- <path>`).
+  pub path: Option<String>,
+  /// Case-insensitive substring matched against the author name and email
+  /// (like `git log --author`, minus regex support).
+  pub author: Option<String>,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct GitLogDetailedEntry {
+  pub hash: String,
+  pub short_hash: String,
+  pub author: String,
+  pub author_email: String,
+  /// Author time in seconds since the Unix epoch.
+  pub timestamp: i64,
+  pub relative_date: String,
+  pub subject: String,
+  pub body: String,
+  pub files_changed: u32,
+  pub additions: u32,
+  pub deletions: u32,
+}
+
+/// Default limit for `get_log_detailed`. Higher than `get_log` because the
+/// task history view pages through everything an agent committed.
+const DEFAULT_DETAILED_LIMIT: u32 = 50;
+
+/// Upper bound on commits visited while filtering, so a path/author filter that
+/// matches nothing cannot walk an entire large history.
+const MAX_DETAILED_WALK: usize = 10_000;
+
+fn tree_entry_id(tree: &gix::Tree<'_>, path: &str) -> Option<gix::ObjectId> {
+  tree
+    .lookup_entry_by_path(path)
+    .ok()
+    .flatten()
+    .map(|entry| entry.oid().to_owned())
+}
+
+/// Query the history of HEAD with optional path / author filters, returning
+/// per-commit change stats against the first parent (the empty tree for root
+/// commits). Stats cover the whole commit, not just the filtered `path`.
+#[napi]
+pub async fn get_log_detailed(
+  cwd: String,
+  options: Option<GitLogQuery>,
+) -> napi::Result<Vec<GitLogDetailedEntry>> {
+  let options = options.unwrap_or_default();
+  let max = options.limit.unwrap_or(DEFAULT_DETAILED_LIMIT) as usize;
+  let path = options
+    .path
+    .as_deref()
+    .map(|p| p.trim_matches('/').replace('\\', "/"))
+    .filter(|p| !p.is_empty());
+  let author_filter = options
+    .author
+    .as_deref()
+    .map(|a| a.trim().to_lowercase())
+    .filter(|a| !a.is_empty());
+
+  with_repo(&cwd, |repo| {
+    let head_commit = match repo.head_commit() {
+      Ok(c) => c,
+      // Unborn HEAD (fresh repo) has no history yet.
+      Err(_) => return Ok(Vec::new()),
+    };
+
+    let mut entries: Vec<GitLogDetailedEntry> = Vec::with_capacity(max.min(256));
+
+    let iter = repo
+      .rev_walk([head_commit.id()])
+      .all()
+      .map_err(|e| napi::Error::from_reason(format!("Failed to start rev walk: {e}")))?;
+
+    for (visited, commit_info) in iter.enumerate() {
+      if entries.len() >= max || visited >= MAX_DETAILED_WALK {
+        break;
+      }
+      let info = commit_info
+        .map_err(|e| napi::Error::from_reason(format!("Rev walk error: {e}")))?;
+      let commit = info
+        .object()
+        .map_err(|e| napi::Error::from_reason(format!("Failed to read commit: {e}")))?;
+
+      let author_sig = commit.author().ok();
+      let author_name = author_sig
+        .as_ref()
+        .map(|a| a.name.to_string())
+        .unwrap_or_default();
+      let author_email = author_sig
+        .as_ref()
+        .map(|a| a.email.to_string())
+        .unwrap_or_default();
+
+      if let Some(needle) = &author_filter {
+        if !author_name.to_lowercase().contains(needle.as_str())
+          && !author_email.to_lowercase().contains(needle.as_str())
+        {
+          continue;
+        }
+      }
+
+      let tree = commit
+        .tree()
+        .map_err(|e| napi::Error::from_reason(format!("Failed to get commit tree: {e}")))?;
+      let parent_tree = commit
+        .parent_ids()
+        .next()
+        .and_then(|pid| pid.object().ok())
+        .and_then(|obj| obj.try_into_commit().ok())
+        .and_then(|pc| pc.tree().ok())
+        .unwrap_or_else(|| repo.empty_tree());
+
+      if let Some(path) = &path {
+        if tree_entry_id(&tree, path) == tree_entry_id(&parent_tree, path) {
+          continue;
+        }
+      }
+
+      let stats = parent_tree
+        .changes()
+        .map_err(|e| napi::Error::from_reason(format!("Failed to prepare tree diff: {e}")))?
+        .options(|opts| {
+          opts.track_rewrites(None);
+        })
+        .stats(&tree)
+        .map_err(|e| napi::Error::from_reason(format!("Failed to diff trees: {e}")))?;
+
+      let hash = commit.id().to_string();
+      let short_hash = hash[..7.min(hash.len())].to_string();
+
+      let timestamp = author_sig
+        .as_ref()
+        .and_then(|a| a.time().ok())
+        .map(|t| t.seconds)
+        .unwrap_or(0);
+
+      let raw_message = commit.message_raw_sloppy();
+      let full = raw_message.to_str_lossy();
+      let subject = full.lines().next().unwrap_or("").trim().to_string();
+      let body = match full.find('\n') {
+        Some(idx) => full[idx + 1..].trim().to_string(),
+        None => String::new(),
+      };
+
+      entries.push(GitLogDetailedEntry {
+        hash,
+        short_hash,
+        author: author_name,
+        author_email,
+        timestamp,
+        relative_date: format_relative_date(timestamp),
+        subject,
+        body,
+        files_changed: stats.files_changed as u32,
+        additions: stats.lines_added as u32,
+        deletions: stats.lines_removed as u32,
+      });
+    }
+
+    Ok(entries)
+  })
+}
+
 #[napi]
 pub async fn get_commit_body(cwd: String, hash: String) -> napi::Result<String> {
   with_repo(&cwd, |repo| {
@@ -174,11 +347,9 @@ pub async fn get_unpushed_hashes(cwd: String) -> napi::Result<Vec<String>> {
     };
 
     let mut remote_tips: Vec<gix::ObjectId> = Vec::new();
-    for r in remote_refs {
-      if let Ok(r) = r {
-        if let Ok(peeled) = r.into_fully_peeled_id() {
-          remote_tips.push(peeled.detach());
-        }
+    for r in remote_refs.flatten() {
+      if let Ok(peeled) = r.into_fully_peeled_id() {
+        remote_tips.push(peeled.detach());
       }
     }
 
@@ -0,0 +1,246 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::repo_cache::evict_repo;
+use crate::snapshot::{git, now_millis, sanitize_run_id, write_worktree_tree};
+
+/// Each run's baseline is a commit at `refs/funny/manifests/<run_id>/base`
+/// whose tree is the worktree as it was when the run started.
+const MANIFEST_REF_PREFIX: &str = "refs/funny/manifests/";
+
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct RunManifestBase {
+  pub run_id: String,
+  pub base_commit: String,
+  pub base_tree: String,
+  /// When the baseline was recorded, in milliseconds since the Unix epoch.
+  pub created_at: i64,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct RunManifestEntry {
+  pub path: String,
+  /// `added`, `modified`, `deleted`, `type_changed`, or `unchanged` for files
+  /// a tool reported touching that ended up identical to the baseline.
+  pub status: String,
+  /// Git blob id of the file at the start of the run.
+  pub before_hash: Option<String>,
+  /// Git blob id of the file now.
+  pub after_hash: Option<String>,
+  /// Tools that reported touching this path, in first-seen order.
+  pub tools: Vec<String>,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct RunManifest {
+  pub run_id: String,
+  pub base_commit: String,
+  pub entries: Vec<RunManifestEntry>,
+  /// Changed files no tool call accounts for — typically formatters,
+  /// generators or package managers the agent ran through a shell.
+  pub unattributed_count: u32,
+}
+
+fn base_ref(run_id: &str) -> String {
+  format!("{}{}/base", MANIFEST_REF_PREFIX, sanitize_run_id(run_id))
+}
+
+/// Per-worktree log of tool touches. Lives in the git dir so it never shows
+/// up as an untracked file.
+fn touch_log_path(cwd: &Path, run_id: &str) -> napi::Result<PathBuf> {
+  let rel = format!("funny/manifests/{}.log", sanitize_run_id(run_id));
+  Ok(PathBuf::from(git(
+    cwd,
+    &["rev-parse", "--path-format=absolute", "--git-path", &rel],
+    None,
+  )?))
+}
+
+fn read_base(cwd: &Path, run_id: &str) -> napi::Result<Option<RunManifestBase>> {
+  let ref_name = base_ref(run_id);
+  let commit = match git(cwd, &["rev-parse", "--verify", "--quiet", &ref_name], None) {
+    Ok(c) if !c.is_empty() => c,
+    _ => return Ok(None),
+  };
+  let info = git(cwd, &["show", "-s", "--format=%T %ct", &commit], None)?;
+  let mut parts = info.split_whitespace();
+  let base_tree = parts.next().unwrap_or_default().to_string();
+  let created_at = parts.next().and_then(|t| t.parse::<i64>().ok()).unwrap_or(0) * 1000;
+  Ok(Some(RunManifestBase {
+    run_id: run_id.to_string(),
+    base_commit: commit,
+    base_tree,
+    created_at,
+  }))
+}
+
+/// Record the worktree (tracked plus untracked, non-ignored files) as the
+/// baseline for `run_id`. Call when the agent run starts. An existing
+/// baseline is kept unless `reset` is set, so resumed runs keep their origin.
+#[napi]
+pub async fn start_run_manifest(
+  cwd: String,
+  run_id: String,
+  reset: Option<bool>,
+) -> napi::Result<RunManifestBase> {
+  let cwd_path = Path::new(&cwd);
+  if !reset.unwrap_or(false) {
+    if let Some(base) = read_base(cwd_path, &run_id)? {
+      return Ok(base);
+    }
+  }
+
+  let tree = write_worktree_tree(cwd_path)?;
+  let message = format!("run manifest base for {run_id}");
+  let commit = git(cwd_path, &["commit-tree", &tree, "-m", &message], None)?;
+  git(cwd_path, &["update-ref", "-m", "funny run manifest", &base_ref(&run_id), &commit], None)?;
+  let log = touch_log_path(cwd_path, &run_id)?;
+  let _ = std::fs::remove_file(&log);
+  evict_repo(&cwd);
+
+  Ok(RunManifestBase {
+    run_id,
+    base_commit: commit,
+    base_tree: tree,
+    created_at: now_millis(),
+  })
+}
+
+/// Attribute repo-relative `paths` to a tool call (e.g. `Edit`, `Write`,
+/// `apply_patch`) of the run.
+#[napi]
+pub async fn record_run_touches(
+  cwd: String,
+  run_id: String,
+  tool: String,
+  paths: Vec<String>,
+) -> napi::Result<()> {
+  let log = touch_log_path(Path::new(&cwd), &run_id)?;
+  if let Some(dir) = log.parent() {
+    std::fs::create_dir_all(dir)
+      .map_err(|e| napi::Error::from_reason(format!("Failed to create manifest dir: {e}")))?;
+  }
+  let tool = tool.replace(['\t', '\n'], " ");
+  let mut lines = String::new();
+  for path in paths.iter().filter(|p| !p.contains(['\t', '\n'])) {
+    lines.push_str(&format!("{}\t{}\t{}\n", now_millis(), tool, path.trim_start_matches("./")));
+  }
+  std::fs::OpenOptions::new()
+    .create(true)
+    .append(true)
+    .open(&log)
+    .and_then(|mut f| f.write_all(lines.as_bytes()))
+    .map_err(|e| napi::Error::from_reason(format!("Failed to record touches: {e}")))
+}
+
+fn read_touches(log: &Path) -> BTreeMap<String, Vec<String>> {
+  let mut touches: BTreeMap<String, Vec<String>> = BTreeMap::new();
+  let content = std::fs::read_to_string(log).unwrap_or_default();
+  for line in content.lines() {
+    let mut parts = line.splitn(3, '\t');
+    let (_, tool, path) = match (parts.next(), parts.next(), parts.next()) {
+      (Some(ts), Some(tool), Some(path)) => (ts, tool, path),
+      _ => continue,
+    };
+    let tools = touches.entry(path.to_string()).or_default();
+    if !tools.iter().any(|t| t == tool) {
+      tools.push(tool.to_string());
+    }
+  }
+  touches
+}
+
+fn null_to_none(hash: &str) -> Option<String> {
+  if hash.bytes().all(|b| b == b'0') {
+    None
+  } else {
+    Some(hash.to_string())
+  }
+}
+
+/// Everything that changed in the worktree since the run's baseline, with
+/// before/after blob ids, merged with the paths its tool calls reported.
+/// Changes are computed from file contents, so edits made by formatters or
+/// generators are included even though no tool call names them.
+#[napi]
+pub async fn run_manifest(cwd: String, run_id: String) -> napi::Result<RunManifest> {
+  let cwd_path = Path::new(&cwd);
+  let base = read_base(cwd_path, &run_id)?.ok_or_else(|| {
+    napi::Error::from_reason(format!("No manifest baseline for run {run_id}"))
+  })?;
+  let current_tree = write_worktree_tree(cwd_path)?;
+  let touches = read_touches(&touch_log_path(cwd_path, &run_id)?);
+
+  // `:old_mode new_mode old_sha new_sha status\0path\0`, one record per file.
+  let raw = git(
+    cwd_path,
+    &["diff-tree", "-r", "-z", "--no-renames", "--raw", &base.base_tree, &current_tree],
+    None,
+  )?;
+  let mut entries: Vec<RunManifestEntry> = Vec::new();
+  let mut fields = raw.split('\0').filter(|f| !f.is_empty());
+  while let (Some(meta), Some(path)) = (fields.next(), fields.next()) {
+    let parts: Vec<&str> = meta.trim_start_matches(':').split(' ').collect();
+    if parts.len() < 5 {
+      continue;
+    }
+    let status = match parts[4] {
+      "A" => "added",
+      "D" => "deleted",
+      "T" => "type_changed",
+      _ => "modified",
+    };
+    entries.push(RunManifestEntry {
+      path: path.to_string(),
+      status: status.to_string(),
+      before_hash: null_to_none(parts[2]),
+      after_hash: null_to_none(parts[3]),
+      tools: touches.get(path).cloned().unwrap_or_default(),
+    });
+  }
+
+  let changed: BTreeSet<String> = entries.iter().map(|e| e.path.clone()).collect();
+  for (path, tools) in &touches {
+    if changed.contains(path) {
+      continue;
+    }
+    let spec = format!("{current_tree}:{path}");
+    let hash = git(cwd_path, &["rev-parse", "--verify", "--quiet", &spec], None).ok();
+    entries.push(RunManifestEntry {
+      path: path.clone(),
+      status: "unchanged".to_string(),
+      before_hash: hash.clone(),
+      after_hash: hash,
+      tools: tools.clone(),
+    });
+  }
+  entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+  let unattributed_count = entries
+    .iter()
+    .filter(|e| e.status != "unchanged" && e.tools.is_empty())
+    .count() as u32;
+
+  Ok(RunManifest {
+    run_id,
+    base_commit: base.base_commit,
+    entries,
+    unattributed_count,
+  })
+}
+
+/// Remove a run's baseline ref and touch log.
+#[napi]
+pub async fn delete_run_manifest(cwd: String, run_id: String) -> napi::Result<()> {
+  let cwd_path = Path::new(&cwd);
+  if read_base(cwd_path, &run_id)?.is_some() {
+    git(cwd_path, &["update-ref", "-d", &base_ref(&run_id)], None)?;
+  }
+  let _ = std::fs::remove_file(touch_log_path(cwd_path, &run_id)?);
+  evict_repo(&cwd);
+  Ok(())
+}
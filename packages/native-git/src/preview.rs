@@ -0,0 +1,403 @@
+use std::io::{Cursor, Read};
+use std::path::Path;
+
+use base64::Engine;
+use gix::bstr::ByteSlice;
+
+use crate::repo_cache::with_repo;
+
+/// Default number of bytes of a text file included in a preview.
+const DEFAULT_PREVIEW_BYTES: u32 = 256 * 1024;
+
+/// Default number of lines of a text file included in a preview.
+const DEFAULT_PREVIEW_LINES: u32 = 2000;
+
+/// Images larger than this are only probed for format and dimensions.
+const MAX_THUMBNAIL_SOURCE_BYTES: usize = 20 * 1024 * 1024;
+
+/// Longest edge of generated thumbnails, in pixels.
+const DEFAULT_THUMBNAIL_SIZE: u32 = 256;
+
+/// Decoder allocation cap, so a crafted image can't exhaust memory.
+const MAX_DECODE_ALLOC: u64 = 256 * 1024 * 1024;
+
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct FilePreviewOptions {
+  /// `worktree` (default), `index`, or any revision (`HEAD`, a commit hash,
+  /// `abc123^`) to read the file as of that commit.
+  pub source: Option<String>,
+  pub max_bytes: Option<u32>,
+  pub max_lines: Option<u32>,
+  /// Longest edge of the image thumbnail. 0 disables thumbnails.
+  pub thumbnail_size: Option<u32>,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct ImagePreview {
+  /// `png`, `jpeg`, `gif`, `webp`, `bmp` or `ico`.
+  pub format: String,
+  pub width: u32,
+  pub height: u32,
+  /// `data:image/png;base64,…` thumbnail, when the image could be decoded.
+  pub thumbnail: Option<String>,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct FilePreview {
+  pub path: String,
+  pub source: String,
+  /// False when the file doesn't exist in the requested source (e.g. a file
+  /// added in the worktree, previewed at `HEAD`).
+  pub exists: bool,
+  /// Full size of the file in bytes.
+  pub size: i64,
+  pub binary: bool,
+  /// Best-effort MIME type from magic bytes or the extension.
+  pub mime: Option<String>,
+  /// Language id for syntax highlighting (`rust`, `typescript`, …).
+  pub language: Option<String>,
+  /// Leading part of a text file, cut at a line boundary.
+  pub text: Option<String>,
+  /// True when `text` doesn't cover the whole file.
+  pub truncated: bool,
+  pub image: Option<ImagePreview>,
+}
+
+/// Same heuristic as `file_diff::is_binary` (NUL in the first 8 KB).
+fn is_binary(data: &[u8]) -> bool {
+  data[..data.len().min(8192)].contains(&0)
+}
+
+fn extension(path: &str) -> String {
+  Path::new(path)
+    .extension()
+    .map(|e| e.to_string_lossy().to_lowercase())
+    .unwrap_or_default()
+}
+
+/// Map a file name (and, failing that, a shebang line) to a highlighter
+/// language id.
+fn detect_language(path: &str, head: &[u8]) -> Option<String> {
+  let name = Path::new(path)
+    .file_name()
+    .map(|n| n.to_string_lossy().to_string())
+    .unwrap_or_default();
+  let by_name = match name.as_str() {
+    "Dockerfile" | "Containerfile" => Some("dockerfile"),
+    "Makefile" | "GNUmakefile" => Some("makefile"),
+    "CMakeLists.txt" => Some("cmake"),
+    "Cargo.lock" | "Pipfile" => Some("toml"),
+    ".gitignore" | ".dockerignore" | ".npmignore" => Some("ignore"),
+    ".bashrc" | ".zshrc" | ".profile" => Some("shell"),
+    _ => None,
+  };
+  if let Some(lang) = by_name {
+    return Some(lang.to_string());
+  }
+
+  let by_ext = match extension(path).as_str() {
+    "rs" => "rust",
+    "ts" | "mts" | "cts" => "typescript",
+    "tsx" => "tsx",
+    "js" | "mjs" | "cjs" => "javascript",
+    "jsx" => "jsx",
+    "json" | "jsonc" => "json",
+    "md" | "markdown" | "mdx" => "markdown",
+    "py" | "pyi" => "python",
+    "go" => "go",
+    "java" => "java",
+    "kt" | "kts" => "kotlin",
+    "swift" => "swift",
+    "c" | "h" => "c",
+    "cc" | "cpp" | "cxx" | "hpp" | "hh" => "cpp",
+    "cs" => "csharp",
+    "rb" => "ruby",
+    "php" => "php",
+    "sh" | "bash" | "zsh" => "shell",
+    "ps1" => "powershell",
+    "toml" => "toml",
+    "yaml" | "yml" => "yaml",
+    "xml" | "plist" => "xml",
+    "html" | "htm" => "html",
+    "css" => "css",
+    "scss" => "scss",
+    "svg" => "xml",
+    "sql" => "sql",
+    "vue" => "vue",
+    "svelte" => "svelte",
+    "lua" => "lua",
+    "dart" => "dart",
+    "ex" | "exs" => "elixir",
+    "zig" => "zig",
+    "proto" => "protobuf",
+    "graphql" | "gql" => "graphql",
+    "kdl" => "kdl",
+    _ => "",
+  };
+  if !by_ext.is_empty() {
+    return Some(by_ext.to_string());
+  }
+
+  let first_line = head.lines().next()?.to_str_lossy();
+  let interpreter = first_line.strip_prefix("#!")?;
+  let lang = if interpreter.contains("python") {
+    "python"
+  } else if ["node", "bun", "deno"].iter().any(|js| interpreter.contains(js)) {
+    "javascript"
+  } else if interpreter.contains("ruby") {
+    "ruby"
+  } else if interpreter.contains("sh") {
+    "shell"
+  } else {
+    return None;
+  };
+  Some(lang.to_string())
+}
+
+fn detect_mime(path: &str, head: &[u8]) -> Option<String> {
+  const MAGIC: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"BM", "image/bmp"),
+    (b"\x00\x00\x01\x00", "image/x-icon"),
+    (b"%PDF-", "application/pdf"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"\x1f\x8b", "application/gzip"),
+    (b"\x7fELF", "application/x-elf"),
+    (b"\x00asm", "application/wasm"),
+    (b"SQLite format 3\x00", "application/vnd.sqlite3"),
+    (b"wOFF", "font/woff"),
+    (b"wOF2", "font/woff2"),
+  ];
+  for (magic, mime) in MAGIC {
+    if head.starts_with(magic) {
+      return Some(mime.to_string());
+    }
+  }
+  if head.len() >= 12 && &head[..4] == b"RIFF" && &head[8..12] == b"WEBP" {
+    return Some("image/webp".to_string());
+  }
+  let by_ext = match extension(path).as_str() {
+    "svg" => "image/svg+xml",
+    "json" => "application/json",
+    "md" | "markdown" => "text/markdown",
+    "html" | "htm" => "text/html",
+    "css" => "text/css",
+    "ttf" => "font/ttf",
+    "otf" => "font/otf",
+    "mp3" => "audio/mpeg",
+    "mp4" => "video/mp4",
+    "mov" => "video/quicktime",
+    _ => return None,
+  };
+  Some(by_ext.to_string())
+}
+
+/// Cut `data` to at most `max_bytes` / `max_lines`, ending on a line boundary
+/// (or a char boundary for a single huge line).
+fn truncate_text(data: &[u8], max_bytes: usize, max_lines: usize) -> (String, bool) {
+  let mut end = data.len().min(max_bytes);
+  let line_limit = data[..end]
+    .iter()
+    .enumerate()
+    .filter(|(_, &b)| b == b'\n')
+    .nth(max_lines.saturating_sub(1));
+  if let Some((i, _)) = line_limit {
+    end = i + 1;
+  } else if end < data.len() {
+    if let Some(i) = data[..end].rfind_byte(b'\n') {
+      end = i + 1;
+    } else {
+      while end > 0 && (data[end] & 0xC0) == 0x80 {
+        end -= 1;
+      }
+    }
+  }
+  let truncated = end < data.len();
+  (String::from_utf8_lossy(&data[..end]).into_owned(), truncated)
+}
+
+fn image_preview(data: &[u8], complete: bool, thumbnail_size: u32) -> Option<ImagePreview> {
+  let reader = image::ImageReader::new(Cursor::new(data)).with_guessed_format().ok()?;
+  let format = reader.format()?;
+  let format_name = match format {
+    image::ImageFormat::Png => "png",
+    image::ImageFormat::Jpeg => "jpeg",
+    image::ImageFormat::Gif => "gif",
+    image::ImageFormat::WebP => "webp",
+    image::ImageFormat::Bmp => "bmp",
+    image::ImageFormat::Ico => "ico",
+    _ => return None,
+  };
+  let (width, height) = reader.into_dimensions().ok()?;
+
+  let thumbnail = if complete && thumbnail_size > 0 && data.len() <= MAX_THUMBNAIL_SOURCE_BYTES {
+    let mut reader = image::ImageReader::new(Cursor::new(data));
+    reader.set_format(format);
+    let mut limits = image::Limits::default();
+    limits.max_alloc = Some(MAX_DECODE_ALLOC);
+    reader.limits(limits);
+    reader.decode().ok().and_then(|img| {
+      let thumb = img.thumbnail(thumbnail_size, thumbnail_size);
+      let mut png = Vec::new();
+      thumb.write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png).ok()?;
+      Some(format!(
+        "data:image/png;base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(png)
+      ))
+    })
+  } else {
+    None
+  };
+
+  Some(ImagePreview {
+    format: format_name.to_string(),
+    width,
+    height,
+    thumbnail,
+  })
+}
+
+/// File bytes from the requested source plus the file's full size. Worktree
+/// reads stop at `limit` bytes; blobs come out of the object database whole.
+fn read_source(
+  repo: &gix::Repository,
+  cwd: &str,
+  file_path: &str,
+  source: &str,
+  limit: usize,
+) -> napi::Result<Option<(Vec<u8>, u64)>> {
+  match source {
+    "worktree" => {
+      let full = Path::new(cwd).join(file_path);
+      let meta = match std::fs::metadata(&full) {
+        Ok(m) if m.is_file() => m,
+        _ => return Ok(None),
+      };
+      let mut data = Vec::new();
+      std::fs::File::open(&full)
+        .and_then(|f| f.take(limit as u64).read_to_end(&mut data))
+        .map_err(|e| napi::Error::from_reason(format!("Failed to read file: {e}")))?;
+      Ok(Some((data, meta.len())))
+    }
+    "index" => {
+      let index = repo
+        .open_index()
+        .map_err(|e| napi::Error::from_reason(format!("Failed to open index: {e}")))?;
+      let id = index
+        .entries()
+        .iter()
+        .find(|e| e.path(&index).to_str_lossy() == file_path)
+        .map(|e| e.id);
+      Ok(id.and_then(|id| repo.find_object(id).ok()).map(|obj| {
+        let data = obj.detach().data;
+        let size = data.len() as u64;
+        (data, size)
+      }))
+    }
+    rev => {
+      let commit = repo
+        .rev_parse_single(rev)
+        .map_err(|e| napi::Error::from_reason(format!("Failed to parse revision: {e}")))?
+        .object()
+        .map_err(|e| napi::Error::from_reason(format!("Failed to read object: {e}")))?
+        .peel_to_commit()
+        .map_err(|e| napi::Error::from_reason(format!("Not a commit: {e}")))?;
+      let tree = commit
+        .tree()
+        .map_err(|e| napi::Error::from_reason(format!("Failed to get tree: {e}")))?;
+      Ok(
+        tree
+          .lookup_entry_by_path(file_path)
+          .ok()
+          .flatten()
+          .filter(|entry| entry.mode().is_blob())
+          .and_then(|entry| entry.object().ok())
+          .map(|obj| {
+            let data = obj.detach().data;
+            let size = data.len() as u64;
+            (data, size)
+          }),
+      )
+    }
+  }
+}
+
+/// Something to show for a file in the review UI where a diff isn't useful:
+/// the head of a large text file with its language, or format, dimensions and
+/// a thumbnail for images. Works on the worktree, the index or any revision,
+/// so both sides of a binary diff can be previewed.
+#[napi]
+pub async fn get_file_preview(
+  cwd: String,
+  file_path: String,
+  options: Option<FilePreviewOptions>,
+) -> napi::Result<FilePreview> {
+  let options = options.unwrap_or_default();
+  let source = options.source.clone().unwrap_or_else(|| "worktree".to_string());
+  let max_bytes = options.max_bytes.unwrap_or(DEFAULT_PREVIEW_BYTES) as usize;
+  let max_lines = options.max_lines.unwrap_or(DEFAULT_PREVIEW_LINES) as usize;
+  let thumbnail_size = options.thumbnail_size.unwrap_or(DEFAULT_THUMBNAIL_SIZE);
+  // Images need the whole file to decode; text only needs the preview part.
+  let read_limit = max_bytes.max(MAX_THUMBNAIL_SOURCE_BYTES);
+
+  let read = with_repo(&cwd, |repo| read_source(repo, &cwd, &file_path, &source, read_limit))?;
+  let (data, size) = match read {
+    Some(r) => r,
+    None => {
+      return Ok(FilePreview {
+        path: file_path,
+        source,
+        exists: false,
+        size: 0,
+        binary: false,
+        mime: None,
+        language: None,
+        text: None,
+        truncated: false,
+        image: None,
+      });
+    }
+  };
+
+  let head = &data[..data.len().min(8192)];
+  let mime = detect_mime(&file_path, head);
+  let binary = is_binary(&data);
+
+  let complete = data.len() as u64 == size;
+  // SVG is text and is previewed as such.
+  let raster = mime
+    .as_deref()
+    .map(|m| m.starts_with("image/") && m != "image/svg+xml")
+    .unwrap_or(false);
+  let image = if raster {
+    image_preview(&data, complete, thumbnail_size)
+  } else {
+    None
+  };
+
+  let (text, truncated, language) = if binary {
+    (None, false, None)
+  } else {
+    let (text, cut) = truncate_text(&data, max_bytes, max_lines);
+    (Some(text), cut || !complete, detect_language(&file_path, head))
+  };
+
+  Ok(FilePreview {
+    path: file_path,
+    source,
+    exists: true,
+    size: size as i64,
+    binary,
+    mime,
+    language,
+    text,
+    truncated,
+    image,
+  })
+}
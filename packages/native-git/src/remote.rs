@@ -0,0 +1,263 @@
+use std::path::Path;
+
+use crate::repo_cache::{evict_repo, with_repo};
+use crate::safety::{blocked, evaluate_safety, DestructiveOp, SafetyConfirmations};
+
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct GitRemote {
+  pub name: String,
+  pub fetch_url: Option<String>,
+  /// Explicit `remote.<name>.pushurl`, falling back to the fetch URL.
+  pub push_url: Option<String>,
+}
+
+/// Tracking information for the current branch, as configured by
+/// `branch.<name>.remote` / `branch.<name>.merge`.
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct GitUpstream {
+  pub branch: String,
+  pub remote: String,
+  /// Short name of the remote branch, e.g. `main` for `refs/heads/main`.
+  pub remote_branch: String,
+  /// Commits on the local branch that the upstream does not have.
+  pub ahead: u32,
+  /// Commits on the upstream that the local branch does not have.
+  pub behind: u32,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct GitPushOptions {
+  /// Remote to push to. Defaults to the branch's configured remote, then `origin`.
+  pub remote: Option<String>,
+  /// Local branch to push. Defaults to the current branch.
+  pub branch: Option<String>,
+  /// Pass `--set-upstream` so later pulls/pushes track the pushed branch.
+  pub set_upstream: Option<bool>,
+  /// Pass `--force-with-lease` (never a bare `--force`).
+  pub force_with_lease: Option<bool>,
+  /// Acknowledged risks. A force push that would drop remote-only commits is
+  /// refused unless `allow_remote_overwrite` is set.
+  pub confirmations: Option<SafetyConfirmations>,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct GitPushResult {
+  pub remote: String,
+  pub branch: String,
+  pub upstream_set: bool,
+  /// Combined `git push --porcelain` output, for display in the UI.
+  pub output: String,
+}
+
+#[napi]
+pub async fn list_remotes(cwd: String) -> napi::Result<Vec<GitRemote>> {
+  with_repo(&cwd, |repo| {
+    let config = repo.config_snapshot();
+    let mut remotes = Vec::new();
+    for name in repo.remote_names() {
+      let name = name.to_string();
+      let fetch_url = config
+        .string(format!("remote.{name}.url").as_str())
+        .map(|v| v.to_string());
+      let push_url = config
+        .string(format!("remote.{name}.pushurl").as_str())
+        .map(|v| v.to_string())
+        .or_else(|| fetch_url.clone());
+      remotes.push(GitRemote {
+        name,
+        fetch_url,
+        push_url,
+      });
+    }
+    Ok(remotes)
+  })
+}
+
+/// Run `git rev-list --left-right --count HEAD...<upstream>` and return
+/// `(ahead, behind)`. Shells out for the same reason as
+/// `status_summary::rev_list_count`: gix's walk miscounts across merges.
+fn ahead_behind(cwd: &Path, upstream: &str) -> (u32, u32) {
+  let range = format!("HEAD...{upstream}");
+  std::process::Command::new("git")
+    .args(["rev-list", "--left-right", "--count", range.as_str()])
+    .current_dir(cwd)
+    .output()
+    .ok()
+    .and_then(|o| {
+      if !o.status.success() {
+        return None;
+      }
+      let text = String::from_utf8(o.stdout).ok()?;
+      let mut parts = text.split_whitespace();
+      let ahead = parts.next()?.parse::<u32>().ok()?;
+      let behind = parts.next()?.parse::<u32>().ok()?;
+      Some((ahead, behind))
+    })
+    .unwrap_or((0, 0))
+}
+
+#[napi]
+pub async fn get_upstream(cwd: String) -> napi::Result<Option<GitUpstream>> {
+  let tracking = with_repo(&cwd, |repo| {
+    let head_ref = repo
+      .head_ref()
+      .map_err(|e| napi::Error::from_reason(format!("Failed to get HEAD: {e}")))?;
+    let branch = match head_ref {
+      Some(r) => r.name().shorten().to_string(),
+      None => return Ok(None),
+    };
+
+    let config = repo.config_snapshot();
+    let remote = config.string(format!("branch.{branch}.remote").as_str());
+    let merge = config.string(format!("branch.{branch}.merge").as_str());
+    match (remote, merge) {
+      (Some(remote), Some(merge)) => {
+        let merge = merge.to_string();
+        let remote_branch = merge
+          .strip_prefix("refs/heads/")
+          .unwrap_or(&merge)
+          .to_string();
+        Ok(Some((branch, remote.to_string(), remote_branch)))
+      }
+      _ => Ok(None),
+    }
+  })?;
+
+  let (branch, remote, remote_branch) = match tracking {
+    Some(t) => t,
+    None => return Ok(None),
+  };
+
+  // `branch.<name>.remote = .` means the upstream is a local branch.
+  let upstream_ref = if remote == "." {
+    remote_branch.clone()
+  } else {
+    format!("{remote}/{remote_branch}")
+  };
+  let (ahead, behind) = ahead_behind(Path::new(&cwd), &upstream_ref);
+
+  Ok(Some(GitUpstream {
+    branch,
+    remote,
+    remote_branch,
+    ahead,
+    behind,
+  }))
+}
+
+/// Reject a push target that isn't a configured remote and a well-formed
+/// branch name. Both end up on `git push`'s command line, where a value like
+/// `--receive-pack=<cmd>` would run a program.
+fn validate_push_target(repo: &gix::Repository, remote: &str, branch: &str) -> napi::Result<()> {
+  if remote.starts_with('-') || !repo.remote_names().iter().any(|name| name.as_ref() == remote) {
+    return Err(napi::Error::from_reason(format!(
+      "Cannot push: {remote:?} is not a configured remote"
+    )));
+  }
+  let full_name = format!("refs/heads/{branch}");
+  if branch.starts_with('-') || gix::validate::reference::branch_name(full_name.as_str().into()).is_err() {
+    return Err(napi::Error::from_reason(format!(
+      "Cannot push: {branch:?} is not a valid branch name"
+    )));
+  }
+  Ok(())
+}
+
+/// Push a branch with the system `git` so the user's configured credential
+/// helper (osxkeychain, manager, libsecret, `gh auth git-credential`, …) and
+/// SSH agent are used exactly as they would be from a terminal.
+///
+/// Interactive prompts are disabled (`GIT_TERMINAL_PROMPT=0`): with no TTY a
+/// prompt would block the libuv worker forever, so missing credentials surface
+/// as an error instead.
+#[napi]
+pub async fn push_branch(
+  cwd: String,
+  options: Option<GitPushOptions>,
+) -> napi::Result<GitPushResult> {
+  let options = options.unwrap_or_default();
+
+  let (remote, branch) = with_repo(&cwd, |repo| {
+    let branch = match options.branch.clone() {
+      Some(b) => b,
+      None => repo
+        .head_ref()
+        .map_err(|e| napi::Error::from_reason(format!("Failed to get HEAD: {e}")))?
+        .map(|r| r.name().shorten().to_string())
+        .ok_or_else(|| {
+          napi::Error::from_reason("Cannot push: HEAD is detached and no branch was given".to_string())
+        })?,
+    };
+    let remote = match options.remote.clone() {
+      Some(r) => r,
+      None => repo
+        .config_snapshot()
+        .string(format!("branch.{branch}.remote").as_str())
+        .map(|v| v.to_string())
+        .filter(|r| r != ".")
+        .unwrap_or_else(|| "origin".to_string()),
+    };
+    validate_push_target(repo, &remote, &branch)?;
+    Ok((remote, branch))
+  })?;
+
+  let force = options.force_with_lease.unwrap_or(false);
+  if force {
+    let confirmations = options.confirmations.clone().unwrap_or_default();
+    let check = evaluate_safety(
+      &cwd,
+      DestructiveOp::ForcePush,
+      Some(&branch),
+      Some(&remote),
+      None,
+      &[],
+      &confirmations,
+    )?;
+    if !check.ok {
+      return Err(blocked("Force push", &check));
+    }
+  }
+
+  let set_upstream = options.set_upstream.unwrap_or(false);
+  let mut args: Vec<String> = vec!["push".to_string(), "--porcelain".to_string()];
+  if set_upstream {
+    args.push("--set-upstream".to_string());
+  }
+  if force {
+    args.push("--force-with-lease".to_string());
+  }
+  // Both were validated above; `--` keeps them from ever being read as options.
+  args.push("--".to_string());
+  args.push(remote.clone());
+  args.push(format!("refs/heads/{branch}:refs/heads/{branch}"));
+
+  let output = std::process::Command::new("git")
+    .args(&args)
+    .current_dir(&cwd)
+    .env("GIT_TERMINAL_PROMPT", "0")
+    .output()
+    .map_err(|e| napi::Error::from_reason(format!("Failed to run git push: {e}")));
+
+  // Pushing updates refs/remotes/<remote>/<branch>; drop the cached handle so
+  // the next status read sees it.
+  evict_repo(&cwd);
+
+  let output = output?;
+  let stdout = String::from_utf8_lossy(&output.stdout);
+  let stderr = String::from_utf8_lossy(&output.stderr);
+  if !output.status.success() {
+    let detail = if stderr.trim().is_empty() { stdout.trim() } else { stderr.trim() };
+    return Err(napi::Error::from_reason(format!("git push failed: {detail}")));
+  }
+
+  Ok(GitPushResult {
+    remote,
+    branch,
+    upstream_set: set_upstream,
+    output: format!("{}{}", stdout, stderr).trim().to_string(),
+  })
+}
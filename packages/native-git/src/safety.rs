@@ -0,0 +1,376 @@
+use std::path::{Path, PathBuf};
+
+use crate::repo_cache::{evict_repo, with_repo};
+use crate::snapshot::{git, list_snapshots_inner, write_worktree_tree};
+
+/// Flags a caller passes to acknowledge specific risks. Each warning returned
+/// by `check_git_safety` names the flag that would silence it.
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct SafetyConfirmations {
+  /// Proceed even though the worktree has uncommitted changes.
+  pub allow_uncommitted: Option<bool>,
+  /// Proceed even though the branch has commits no other branch or remote has.
+  pub allow_unpushed: Option<bool>,
+  /// Proceed even though the agent run's latest changes are not snapshotted.
+  pub allow_unsnapshotted: Option<bool>,
+  /// Proceed with a force push that drops commits only the remote has.
+  pub allow_remote_overwrite: Option<bool>,
+}
+
+/// Options of the destructive operations that run the guard themselves.
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct GitDestructiveOptions {
+  /// Must be set: the caller asked the user (or was told to go ahead) and
+  /// did not just forward a request. Without it nothing is touched.
+  pub confirm: Option<bool>,
+  /// Agent run whose snapshots count as saved work for `allowUnsnapshotted`.
+  pub run_id: Option<String>,
+  /// Warnings the user acknowledged; any other one blocks the operation.
+  pub confirmations: Option<SafetyConfirmations>,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct SafetyWarning {
+  /// `uncommitted_changes`, `unpushed_commits`, `unsnapshotted_changes` or
+  /// `remote_commits_overwritten`.
+  pub kind: String,
+  /// Number of files or commits at risk.
+  pub count: u32,
+  pub message: String,
+  /// camelCase name of the `SafetyConfirmations` flag that acknowledges this.
+  pub confirm_flag: String,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct SafetyCheckResult {
+  /// True when there are no unacknowledged warnings.
+  pub ok: bool,
+  pub warnings: Vec<SafetyWarning>,
+}
+
+/// Destructive operations the guard knows how to assess.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DestructiveOp {
+  /// Discarding working-tree changes (`git checkout -- .`, `git clean`).
+  Discard,
+  /// `git worktree remove`, which also drops the worktree's untracked files.
+  RemoveWorktree,
+  /// `git push --force-with-lease`.
+  ForcePush,
+}
+
+impl DestructiveOp {
+  fn parse(op: &str) -> napi::Result<Self> {
+    match op {
+      "discard" => Ok(Self::Discard),
+      "remove_worktree" => Ok(Self::RemoveWorktree),
+      "force_push" => Ok(Self::ForcePush),
+      other => Err(napi::Error::from_reason(format!(
+        "Unknown operation '{other}' (expected discard, remove_worktree or force_push)"
+      ))),
+    }
+  }
+}
+
+fn count_or_zero(cwd: &Path, args: &[&str]) -> u32 {
+  git(cwd, args, None)
+    .ok()
+    .and_then(|out| out.parse::<u32>().ok())
+    .unwrap_or(0)
+}
+
+/// Number of non-ignored paths that differ from HEAD (staged, unstaged or
+/// untracked), as `git status --porcelain` reports them, limited to `paths`
+/// (repo-relative pathspecs) unless it is empty.
+fn dirty_path_count(cwd: &Path, paths: &[String]) -> napi::Result<u32> {
+  let mut args = vec!["status", "--porcelain=v1", "-z", "--untracked-files=all", "--"];
+  args.extend(paths.iter().map(String::as_str));
+  let out = git(cwd, &args, None)?;
+  let mut count = 0;
+  let mut entries = out.split('\0').filter(|e| !e.is_empty());
+  while let Some(entry) = entries.next() {
+    count += 1;
+    // Renames/copies carry the original path as an extra NUL-separated field.
+    if entry.starts_with('R') || entry.starts_with('C') {
+      entries.next();
+    }
+  }
+  Ok(count)
+}
+
+/// Whether the worktree differs from the newest snapshot of `run_id`.
+fn has_unsnapshotted_changes(cwd: &str, run_id: &str) -> napi::Result<bool> {
+  let latest = with_repo(cwd, |repo| Ok(list_snapshots_inner(repo, Some(run_id))?.into_iter().next()))?;
+  let cwd_path = Path::new(cwd);
+  let current_tree = write_worktree_tree(cwd_path)?;
+  match latest {
+    Some(snapshot) => {
+      let snap_tree = git(cwd_path, &["rev-parse", &format!("{}^{{tree}}", snapshot.hash)], None)?;
+      Ok(snap_tree != current_tree)
+    }
+    None => {
+      // No snapshot yet: only a problem if the worktree differs from HEAD.
+      let head_tree = git(cwd_path, &["rev-parse", "HEAD^{tree}"], None).unwrap_or_default();
+      Ok(head_tree != current_tree)
+    }
+  }
+}
+
+pub(crate) fn evaluate_safety(
+  cwd: &str,
+  op: DestructiveOp,
+  branch: Option<&str>,
+  remote: Option<&str>,
+  run_id: Option<&str>,
+  paths: &[String],
+  confirmations: &SafetyConfirmations,
+) -> napi::Result<SafetyCheckResult> {
+  let cwd_path = Path::new(cwd);
+  let mut warnings = Vec::new();
+
+  if matches!(op, DestructiveOp::Discard | DestructiveOp::RemoveWorktree)
+    && !confirmations.allow_uncommitted.unwrap_or(false)
+  {
+    let dirty = dirty_path_count(cwd_path, paths)?;
+    if dirty > 0 {
+      warnings.push(SafetyWarning {
+        kind: "uncommitted_changes".to_string(),
+        count: dirty,
+        message: format!("{dirty} file(s) have uncommitted changes that will be lost"),
+        confirm_flag: "allowUncommitted".to_string(),
+      });
+    }
+  }
+
+  if op == DestructiveOp::RemoveWorktree && !confirmations.allow_unpushed.unwrap_or(false) {
+    // Commits only this worktree's branch has: removing the worktree is
+    // usually followed by deleting that branch.
+    let own_branch = git(cwd_path, &["symbolic-ref", "--short", "HEAD"], None).ok();
+    let exclude = own_branch.map(|branch| format!("--exclude={branch}"));
+    let mut args = vec!["rev-list", "--count", "HEAD", "--not", "--remotes"];
+    args.extend(exclude.as_deref());
+    args.push("--branches");
+    let unpushed = count_or_zero(cwd_path, &args);
+    if unpushed > 0 {
+      warnings.push(SafetyWarning {
+        kind: "unpushed_commits".to_string(),
+        count: unpushed,
+        message: format!("{unpushed} commit(s) are on no other branch and have not been pushed"),
+        confirm_flag: "allowUnpushed".to_string(),
+      });
+    }
+  }
+
+  if op != DestructiveOp::ForcePush && !confirmations.allow_unsnapshotted.unwrap_or(false) {
+    if let Some(run_id) = run_id {
+      if has_unsnapshotted_changes(cwd, run_id)? {
+        warnings.push(SafetyWarning {
+          kind: "unsnapshotted_changes".to_string(),
+          count: 1,
+          message: "The agent's latest changes are not captured in a snapshot".to_string(),
+          confirm_flag: "allowUnsnapshotted".to_string(),
+        });
+      }
+    }
+  }
+
+  if op == DestructiveOp::ForcePush && !confirmations.allow_remote_overwrite.unwrap_or(false) {
+    let branch = match branch {
+      Some(b) => b.to_string(),
+      None => git(cwd_path, &["symbolic-ref", "--short", "HEAD"], None)?,
+    };
+    let remote = remote.unwrap_or("origin");
+    let remote_ref = format!("refs/remotes/{remote}/{branch}");
+    // Commits the remote has that the local branch would replace.
+    let range = format!("refs/heads/{branch}..{remote_ref}");
+    let overwritten = count_or_zero(cwd_path, &["rev-list", "--count", range.as_str()]);
+    if overwritten > 0 {
+      warnings.push(SafetyWarning {
+        kind: "remote_commits_overwritten".to_string(),
+        count: overwritten,
+        message: format!("Force pushing will drop {overwritten} commit(s) that exist only on {remote}/{branch}"),
+        confirm_flag: "allowRemoteOverwrite".to_string(),
+      });
+    }
+  }
+
+  Ok(SafetyCheckResult {
+    ok: warnings.is_empty(),
+    warnings,
+  })
+}
+
+/// The error for an operation whose check found unacknowledged warnings.
+pub(crate) fn blocked(what: &str, check: &SafetyCheckResult) -> napi::Error {
+  let details: Vec<String> = check
+    .warnings
+    .iter()
+    .map(|w| format!("{} (confirm with {})", w.message, w.confirm_flag))
+    .collect();
+  napi::Error::from_reason(format!("{what} blocked by safety check: {}", details.join("; ")))
+}
+
+/// Refuse `op` on `paths` of `cwd` (all of it when empty) unless
+/// `options.confirm` is set and the safety check passes with the given
+/// confirmations.
+fn require_confirmed(
+  cwd: &str,
+  op: DestructiveOp,
+  what: &str,
+  paths: &[String],
+  options: &GitDestructiveOptions,
+) -> napi::Result<()> {
+  if !options.confirm.unwrap_or(false) {
+    return Err(napi::Error::from_reason(format!(
+      "{what} needs explicit confirmation (confirm: true)"
+    )));
+  }
+  let confirmations = options.confirmations.clone().unwrap_or_default();
+  let check = evaluate_safety(
+    cwd,
+    op,
+    None,
+    None,
+    options.run_id.as_deref(),
+    paths,
+    &confirmations,
+  )?;
+  if !check.ok {
+    return Err(blocked(what, &check));
+  }
+  Ok(())
+}
+
+/// Assess a destructive operation before running it. `operation` is one of
+/// `discard`, `remove_worktree` or `force_push`. Checks cover uncommitted
+/// files, commits no other branch or remote has, agent changes newer than
+/// the run's last snapshot (when `run_id` is given) and, for force pushes,
+/// remote-only commits. `paths` limits the uncommitted-files check of a
+/// `discard` to the paths being discarded. Warnings already acknowledged in
+/// `confirmations` are omitted, so callers proceed only when `ok` is true.
+/// `discard_changes`, `remove_worktree` and force pushes run this check
+/// themselves.
+#[napi]
+pub async fn check_git_safety(
+  cwd: String,
+  operation: String,
+  run_id: Option<String>,
+  confirmations: Option<SafetyConfirmations>,
+  paths: Option<Vec<String>>,
+) -> napi::Result<SafetyCheckResult> {
+  let op = DestructiveOp::parse(&operation)?;
+  evaluate_safety(
+    &cwd,
+    op,
+    None,
+    None,
+    run_id.as_deref(),
+    &paths.unwrap_or_default(),
+    &confirmations.unwrap_or_default(),
+  )
+}
+
+/// Throw away the changes to `paths` (repo-relative): tracked files go back
+/// to HEAD, index included, and untracked ones are deleted. Runs the
+/// `discard` safety check first and needs `confirm`.
+#[napi]
+pub async fn discard_changes(
+  cwd: String,
+  paths: Vec<String>,
+  options: Option<GitDestructiveOptions>,
+) -> napi::Result<()> {
+  if paths.is_empty() {
+    return Err(napi::Error::from_reason("No paths to discard".to_string()));
+  }
+  require_confirmed(
+    &cwd,
+    DestructiveOp::Discard,
+    "Discard",
+    &paths,
+    &options.unwrap_or_default(),
+  )?;
+
+  let cwd_path = Path::new(&cwd);
+  let mut args = vec!["ls-files", "--others", "--exclude-standard", "-z", "--"];
+  args.extend(paths.iter().map(String::as_str));
+  let untracked: Vec<String> = git(cwd_path, &args, None)?
+    .split('\0')
+    .filter(|p| !p.is_empty())
+    .map(str::to_string)
+    .collect();
+  let tracked: Vec<&str> = paths
+    .iter()
+    .filter(|p| !untracked.contains(p))
+    .map(String::as_str)
+    .collect();
+
+  let result = (|| {
+    if !tracked.is_empty() {
+      let mut args = vec!["restore", "--source=HEAD", "--staged", "--worktree", "--"];
+      args.extend(&tracked);
+      git(cwd_path, &args, None)?;
+    }
+    if !untracked.is_empty() {
+      let mut args = vec!["clean", "-f", "-q", "--"];
+      args.extend(untracked.iter().map(String::as_str));
+      git(cwd_path, &args, None)?;
+    }
+    Ok(())
+  })();
+  evict_repo(&cwd);
+  result
+}
+
+/// Registered linked worktrees of the repository at `cwd`, canonicalized.
+/// The main worktree (listed first) is left out.
+fn linked_worktrees(cwd: &Path) -> napi::Result<Vec<PathBuf>> {
+  let out = git(cwd, &["worktree", "list", "--porcelain"], None)?;
+  Ok(
+    out
+      .lines()
+      .filter_map(|l| l.strip_prefix("worktree "))
+      .skip(1)
+      .map(|p| std::fs::canonicalize(p).unwrap_or_else(|_| PathBuf::from(p)))
+      .collect(),
+  )
+}
+
+/// `git worktree remove --force` a linked worktree of the repository at
+/// `cwd`, with its untracked files. Runs the `remove_worktree` safety check
+/// on it first and needs `confirm`.
+#[napi]
+pub async fn remove_worktree(
+  cwd: String,
+  worktree_path: String,
+  options: Option<GitDestructiveOptions>,
+) -> napi::Result<()> {
+  let target = std::fs::canonicalize(&worktree_path)
+    .map_err(|e| napi::Error::from_reason(format!("Cannot remove worktree {worktree_path}: {e}")))?;
+  if !linked_worktrees(Path::new(&cwd))?.contains(&target) {
+    return Err(napi::Error::from_reason(format!(
+      "{worktree_path} is not a linked worktree of {cwd}"
+    )));
+  }
+  let target_str = target.to_string_lossy().to_string();
+  require_confirmed(
+    &target_str,
+    DestructiveOp::RemoveWorktree,
+    "Worktree removal",
+    &[],
+    &options.unwrap_or_default(),
+  )?;
+
+  let result = git(
+    Path::new(&cwd),
+    &["worktree", "remove", "--force", "--", &target_str],
+    None,
+  );
+  evict_repo(&target_str);
+  evict_repo(&worktree_path);
+  evict_repo(&cwd);
+  result.map(|_| ())
+}
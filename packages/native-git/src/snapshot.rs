@@ -0,0 +1,367 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use gix::bstr::ByteSlice;
+
+use crate::log::format_relative_date;
+use crate::repo_cache::{evict_repo, with_repo};
+
+/// Namespace for snapshot refs. Each snapshot is a commit stored at
+/// `refs/worktree/funny/snapshots/<run_id>/<millis>`; keeping them under refs
+/// (instead of loose objects like `git stash create`) protects them from
+/// `git gc`. `refs/worktree/` refs are private to their worktree, so linked
+/// worktrees of one repository never see each other's snapshots.
+const SNAPSHOT_REF_PREFIX: &str = "refs/worktree/funny/snapshots/";
+
+/// Where snapshots used to live, shared by all worktrees. They are no longer
+/// listed or restored, only deleted.
+const LEGACY_SNAPSHOT_REF_PREFIX: &str = "refs/funny/snapshots/";
+
+/// Run id used when the caller does not scope a snapshot to an agent run.
+const DEFAULT_RUN_ID: &str = "manual";
+
+/// Tool calls reported to `checkpoint`, per worktree and run.
+static TOOL_CALLS: Mutex<BTreeMap<(String, String), u32>> = Mutex::new(BTreeMap::new());
+
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct GitSnapshot {
+  pub hash: String,
+  pub ref_name: String,
+  pub run_id: String,
+  pub label: String,
+  /// Creation time in milliseconds since the Unix epoch.
+  pub created_at: i64,
+  pub relative_date: String,
+  /// HEAD at the time of the snapshot, if the branch had any commits.
+  pub base_commit: Option<String>,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct RestoreSnapshotResult {
+  /// Snapshot taken of the worktree right before restoring, so the restore
+  /// itself can be undone.
+  pub backup: Option<GitSnapshot>,
+  /// Repo-relative files that did not exist in the snapshot and were removed.
+  pub removed: Vec<String>,
+  /// Files that should have been removed but could not be, each with the
+  /// reason. The rest of the restore still happened.
+  pub failed: Vec<String>,
+}
+
+/// When `checkpoint` takes a snapshot during an agent run.
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct CheckpointPolicy {
+  /// Snapshot on every Nth tool call of the run; unset or 0 never does.
+  pub every_tool_calls: Option<u32>,
+  /// Snapshot before every tool call that writes files.
+  pub before_writes: Option<bool>,
+}
+
+/// Run a git command and return trimmed stdout, mapping failures to napi errors.
+pub(crate) fn git(cwd: &Path, args: &[&str], index_file: Option<&Path>) -> napi::Result<String> {
+  let mut cmd = std::process::Command::new("git");
+  cmd.args(args).current_dir(cwd);
+  if let Some(index) = index_file {
+    cmd.env("GIT_INDEX_FILE", index);
+  }
+  let output = cmd
+    .output()
+    .map_err(|e| napi::Error::from_reason(format!("Failed to run git {}: {e}", args[0])))?;
+  if !output.status.success() {
+    return Err(napi::Error::from_reason(format!(
+      "git {} failed: {}",
+      args[0],
+      String::from_utf8_lossy(&output.stderr).trim()
+    )));
+  }
+  Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Keep run ids usable as a single ref path component.
+pub(crate) fn sanitize_run_id(run_id: &str) -> String {
+  let cleaned: String = run_id
+    .chars()
+    .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+    .collect();
+  let cleaned = cleaned.trim_matches('-').to_string();
+  if cleaned.is_empty() {
+    DEFAULT_RUN_ID.to_string()
+  } else {
+    cleaned
+  }
+}
+
+pub(crate) fn now_millis() -> i64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_millis() as i64)
+    .unwrap_or(0)
+}
+
+/// Stage the entire worktree into a throwaway index and write it as a tree.
+///
+/// The real index is copied first so unchanged files keep their cached stat
+/// data (making `add -A` cheap), then `git add -A` picks up modified, deleted
+/// and untracked files while honoring .gitignore / info/exclude. The user's
+/// index and working tree are never touched.
+pub(crate) fn write_worktree_tree(cwd: &Path) -> napi::Result<String> {
+  let real_index = PathBuf::from(git(
+    cwd,
+    &["rev-parse", "--path-format=absolute", "--git-path", "index"],
+    None,
+  )?);
+  let temp_index = std::env::temp_dir().join(format!(
+    "funny-snapshot-{}-{}.index",
+    std::process::id(),
+    now_millis()
+  ));
+  if real_index.exists() {
+    std::fs::copy(&real_index, &temp_index)
+      .map_err(|e| napi::Error::from_reason(format!("Failed to copy index: {e}")))?;
+  }
+
+  let result = git(cwd, &["add", "-A", "--", ":/"], Some(&temp_index))
+    .and_then(|_| git(cwd, &["write-tree"], Some(&temp_index)));
+  let _ = std::fs::remove_file(&temp_index);
+  result
+}
+
+fn read_snapshot(repo: &gix::Repository, reference: gix::Reference<'_>) -> Option<GitSnapshot> {
+  let ref_name = reference.name().as_bstr().to_str_lossy().to_string();
+  let (run_id, created_at) = {
+    let rest = ref_name.strip_prefix(SNAPSHOT_REF_PREFIX)?;
+    let (run_id, millis) = rest.rsplit_once('/')?;
+    (run_id.to_string(), millis.parse::<i64>().ok()?)
+  };
+
+  let id = reference.into_fully_peeled_id().ok()?.detach();
+  let commit = repo.find_commit(id).ok()?;
+  let raw = commit.message_raw_sloppy();
+  let label = raw
+    .to_str_lossy()
+    .lines()
+    .next()
+    .unwrap_or("")
+    .trim()
+    .to_string();
+  let base_commit = commit.parent_ids().next().map(|p| p.to_string());
+
+  Some(GitSnapshot {
+    hash: id.to_string(),
+    ref_name,
+    run_id,
+    label,
+    created_at,
+    relative_date: format_relative_date(created_at / 1000),
+    base_commit,
+  })
+}
+
+pub(crate) fn list_snapshots_inner(repo: &gix::Repository, run_id: Option<&str>) -> napi::Result<Vec<GitSnapshot>> {
+  let prefix = match run_id {
+    Some(id) => format!("{}{}/", SNAPSHOT_REF_PREFIX, sanitize_run_id(id)),
+    None => SNAPSHOT_REF_PREFIX.to_string(),
+  };
+  let refs = repo
+    .references()
+    .map_err(|e| napi::Error::from_reason(format!("Failed to get references: {e}")))?;
+  let iter = refs
+    .prefixed(prefix.as_str())
+    .map_err(|e| napi::Error::from_reason(format!("Failed to list snapshots: {e}")))?;
+
+  let mut snapshots: Vec<GitSnapshot> = iter
+    .flatten()
+    .filter_map(|r| read_snapshot(repo, r))
+    .collect();
+  // Newest first, like the stash list.
+  snapshots.sort_by_key(|s| std::cmp::Reverse(s.created_at));
+  Ok(snapshots)
+}
+
+fn create_snapshot_inner(cwd: &str, run_id: &str, label: &str) -> napi::Result<Option<GitSnapshot>> {
+  let cwd_path = Path::new(cwd);
+  let tree = write_worktree_tree(cwd_path)?;
+
+  // Skip the commit when nothing changed since the run's previous snapshot —
+  // checkpoints tend to fire on every agent turn.
+  let (head, previous) = with_repo(cwd, |repo| {
+    let head = repo.head_id().ok().map(|id| id.to_string());
+    let previous = list_snapshots_inner(repo, Some(run_id))?.into_iter().next();
+    Ok((head, previous))
+  })?;
+  if let Some(prev) = previous {
+    let prev_tree = git(cwd_path, &["rev-parse", &format!("{}^{{tree}}", prev.hash)], None)?;
+    if prev_tree == tree {
+      return Ok(Some(prev));
+    }
+  }
+
+  let message = if label.trim().is_empty() { "snapshot" } else { label.trim() };
+  let mut args = vec!["commit-tree", tree.as_str(), "-m", message];
+  if let Some(head) = &head {
+    args.push("-p");
+    args.push(head.as_str());
+  }
+  let commit = git(cwd_path, &args, None)?;
+
+  let ref_name = format!("{}{}/{}", SNAPSHOT_REF_PREFIX, sanitize_run_id(run_id), now_millis());
+  git(cwd_path, &["update-ref", "-m", "funny snapshot", &ref_name, &commit], None)?;
+  evict_repo(cwd);
+
+  with_repo(cwd, |repo| {
+    let reference = repo
+      .find_reference(ref_name.as_str())
+      .map_err(|e| napi::Error::from_reason(format!("Failed to read snapshot ref: {e}")))?;
+    Ok(read_snapshot(repo, reference))
+  })
+}
+
+/// Record the current worktree (tracked changes plus untracked, non-ignored
+/// files) as a snapshot commit without touching the index, HEAD or any
+/// branch. Returns the run's previous snapshot unchanged if the tree is
+/// identical to it. Snapshots are private to the worktree they were taken
+/// in. Use `checkpoint` to take them on a `CheckpointPolicy` instead.
+#[napi]
+pub async fn create_snapshot(
+  cwd: String,
+  run_id: Option<String>,
+  label: Option<String>,
+) -> napi::Result<Option<GitSnapshot>> {
+  create_snapshot_inner(
+    &cwd,
+    run_id.as_deref().unwrap_or(DEFAULT_RUN_ID),
+    label.as_deref().unwrap_or(""),
+  )
+}
+
+/// Report a tool call of an agent run, before it executes, and snapshot the
+/// worktree if `policy` makes it a checkpoint: `writes` tells whether the
+/// call writes files. Returns the snapshot, or `None` when none was due. Call
+/// counts are kept in memory until the run's snapshots are deleted.
+#[napi]
+pub async fn checkpoint(
+  cwd: String,
+  run_id: String,
+  tool: String,
+  writes: bool,
+  policy: CheckpointPolicy,
+) -> napi::Result<Option<GitSnapshot>> {
+  let calls = {
+    let mut counts = TOOL_CALLS
+      .lock()
+      .map_err(|_| napi::Error::from_reason("Checkpoint counters are poisoned".to_string()))?;
+    let count = counts.entry((cwd.clone(), sanitize_run_id(&run_id))).or_insert(0);
+    *count += 1;
+    *count
+  };
+
+  let label = if writes && policy.before_writes.unwrap_or(false) {
+    format!("before {tool}")
+  } else {
+    match policy.every_tool_calls {
+      Some(every) if every > 0 && calls % every == 0 => format!("before tool call {calls} ({tool})"),
+      _ => return Ok(None),
+    }
+  };
+  create_snapshot_inner(&cwd, &run_id, &label)
+}
+
+/// List snapshots, newest first, optionally restricted to one run.
+#[napi]
+pub async fn list_snapshots(cwd: String, run_id: Option<String>) -> napi::Result<Vec<GitSnapshot>> {
+  with_repo(&cwd, |repo| list_snapshots_inner(repo, run_id.as_deref()))
+}
+
+/// Roll the working tree back to a snapshot. A backup snapshot of the current
+/// state is taken first (under the `restore-backup` run), files created after
+/// the snapshot are removed, and every file in the snapshot is checked out.
+/// The index, HEAD and ignored files are left alone. `hash` must be one of
+/// this worktree's snapshots.
+#[napi]
+pub async fn restore_snapshot(cwd: String, hash: String) -> napi::Result<RestoreSnapshotResult> {
+  let cwd_path = Path::new(&cwd);
+  let commit = git(cwd_path, &["rev-parse", "--verify", "--end-of-options", &format!("{hash}^{{commit}}")], None)?;
+  let known = with_repo(&cwd, |repo| {
+    Ok(list_snapshots_inner(repo, None)?.iter().any(|s| s.hash == commit))
+  })?;
+  if !known {
+    return Err(napi::Error::from_reason(format!("{hash} is not a snapshot of this worktree")));
+  }
+  let target_tree = git(cwd_path, &["rev-parse", "--verify", &format!("{commit}^{{tree}}")], None)?;
+
+  let backup = create_snapshot_inner(&cwd, "restore-backup", &format!("before restoring {commit}"))?;
+  let current_tree = write_worktree_tree(cwd_path)?;
+
+  // Files present now but absent from the snapshot.
+  let added = git(
+    cwd_path,
+    &["diff-tree", "-r", "-z", "--name-only", "--diff-filter=A", &target_tree, &current_tree],
+    None,
+  )?;
+  let workdir = PathBuf::from(git(cwd_path, &["rev-parse", "--show-toplevel"], None)?);
+  let mut removed = Vec::new();
+  let mut failed = Vec::new();
+  for rel in added.split('\0').filter(|p| !p.is_empty()) {
+    match std::fs::remove_file(workdir.join(rel)) {
+      Ok(()) => removed.push(rel.to_string()),
+      Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+      Err(e) => failed.push(format!("{rel}: {e}")),
+    }
+  }
+
+  git(cwd_path, &["restore", "--source", &commit, "--worktree", "--", ":/"], None)?;
+  evict_repo(&cwd);
+
+  Ok(RestoreSnapshotResult {
+    backup,
+    removed,
+    failed,
+  })
+}
+
+/// Delete snapshots: a single one by ref name, or every snapshot of a run,
+/// which also resets its `checkpoint` count. Returns the number of refs
+/// removed.
+#[napi]
+pub async fn delete_snapshots(
+  cwd: String,
+  run_id: Option<String>,
+  ref_name: Option<String>,
+) -> napi::Result<u32> {
+  let targets: Vec<String> = match ref_name {
+    Some(name) if name.starts_with(SNAPSHOT_REF_PREFIX) || name.starts_with(LEGACY_SNAPSHOT_REF_PREFIX) => {
+      vec![name]
+    }
+    Some(name) => {
+      return Err(napi::Error::from_reason(format!("Not a snapshot ref: {name}")));
+    }
+    None => {
+      let run_id = run_id.ok_or_else(|| {
+        napi::Error::from_reason("delete_snapshots needs a run_id or ref_name".to_string())
+      })?;
+      if let Ok(mut counts) = TOOL_CALLS.lock() {
+        counts.remove(&(cwd.clone(), sanitize_run_id(&run_id)));
+      }
+      with_repo(&cwd, |repo| {
+        Ok(
+          list_snapshots_inner(repo, Some(&run_id))?
+            .into_iter()
+            .map(|s| s.ref_name)
+            .collect(),
+        )
+      })?
+    }
+  };
+
+  let cwd_path = Path::new(&cwd);
+  for name in &targets {
+    git(cwd_path, &["update-ref", "-d", name], None)?;
+  }
+  evict_repo(&cwd);
+  Ok(targets.len() as u32)
+}
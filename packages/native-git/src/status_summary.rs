@@ -1,9 +1,10 @@
 use std::path::{Path, PathBuf};
 
-use gix::bstr::{BString, ByteSlice};
+use gix::bstr::ByteSlice;
 use gix::worktree::stack::state::attributes::Source as AttrSource;
 
 use crate::blob_diff::count_diff_lines;
+use crate::layout::{in_scope, normalize_scope, scope_pathspec};
 use crate::repo_cache::with_repo;
 
 const MAX_UNTRACKED_TO_COUNT: usize = 200;
@@ -181,12 +182,19 @@ struct StatusPhaseResult {
   branch_name: Option<String>,
 }
 
+/// `scope` restricts the file and line counts to a repo-relative
+/// subdirectory (e.g. one package of a monorepo); commit counts always cover
+/// the whole branch.
 #[napi]
 pub async fn get_status_summary(
   worktree_cwd: String,
   base_branch: Option<String>,
   project_cwd: Option<String>,
+  scope: Option<String>,
 ) -> napi::Result<GitStatusSummary> {
+  let scope = normalize_scope(scope.as_deref())?;
+  let scope = scope.as_deref();
+
   // Phase 1 + 2a: status scan, line counting, branch analysis — all from worktree repo
   let phase1 = with_repo(&worktree_cwd, |repo| {
     let worktree_path = PathBuf::from(&worktree_cwd);
@@ -229,9 +237,8 @@ pub async fn get_status_summary(
         check_dirty: true,
       });
 
-    let empty_patterns: Vec<BString> = Vec::new();
     let status_iter = status_platform
-      .into_index_worktree_iter(empty_patterns)
+      .into_index_worktree_iter(scope_pathspec(scope))
       .map_err(|e| napi::Error::from_reason(format!("Failed to iterate status: {e}")))?;
 
     let mut dirty_file_count: u32 = 0;
@@ -246,6 +253,9 @@ pub async fn get_status_summary(
       let entry = entry
         .map_err(|e| napi::Error::from_reason(format!("Status iteration error: {e}")))?;
 
+      if !in_scope(&entry.rela_path().to_str_lossy(), scope) {
+        continue;
+      }
       dirty_file_count += 1;
 
       match &entry {
@@ -277,7 +287,7 @@ pub async fn get_status_summary(
 
     for idx_entry in index.entries().iter() {
       let path_str = idx_entry.path(&index).to_str_lossy().to_string();
-      if worktree_changed_paths.contains(&path_str) {
+      if worktree_changed_paths.contains(&path_str) || !in_scope(&path_str, scope) {
         continue;
       }
 
@@ -353,7 +363,7 @@ pub async fn get_status_summary(
         continue;
       }
       let path_str = idx_entry.path(&index).to_str_lossy().to_string();
-      if worktree_changed_paths.contains(&path_str) {
+      if worktree_changed_paths.contains(&path_str) || !in_scope(&path_str, scope) {
         continue;
       }
       let nested = worktree_path.join(&path_str);
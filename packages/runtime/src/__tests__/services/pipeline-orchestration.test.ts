@@ -5,7 +5,7 @@
  * pipeline engine. The new architecture uses git-pipelines.ts for
  * the review-fix sub-pipeline, which startPipelineRun lazy-imports.
  */
-import { okAsync } from 'neverthrow';
+import { errAsync, okAsync } from 'neverthrow';
 import { describe, test, expect, vi, beforeEach } from 'vitest';
 
 // ── Mocks ─────────────────────────────────────────────────────
@@ -361,6 +361,27 @@ describe('Pipeline Orchestration', () => {
       });
     });
 
+    test('keeps a reviewer worktree the safety check refuses to remove', async () => {
+      vi.mocked(tm.getThread).mockResolvedValue({
+        id: 'rev-1',
+        projectId: 'proj-1',
+        userId: 'user-1',
+        sessionId: null,
+        mode: 'worktree',
+        worktreePath: '/tmp/repo/.worktrees/rev',
+        branch: 'review/fix',
+      });
+      const { removeWorktree, removeBranch } = await import('@funny/core/git');
+      vi.mocked(removeWorktree).mockReturnValueOnce(
+        errAsync({ type: 'CONFLICT', message: 'Worktree removal blocked by safety check' }),
+      );
+
+      await cleanupReviewerThread('rev-1', 'proj-1');
+
+      expect(removeBranch).not.toHaveBeenCalled();
+      expect(tm.updateThread).toHaveBeenCalledWith('rev-1', { archived: 1 });
+    });
+
     test('archives local reviewer thread without git cleanup', async () => {
       vi.mocked(tm.getThread).mockResolvedValue({
         id: 'rev-local',
@@ -267,6 +267,26 @@ describe('updateThread — archive worktree cleanup', () => {
       expect.objectContaining({ archived: 1, worktreePath: null, branch: null }),
     );
   });
+
+  test('keeps worktree and branch when the safety check blocks the removal', async () => {
+    mocks.removeWorktree.mockReturnValue({
+      match: (_ok: () => boolean, fail: (e: unknown) => boolean) =>
+        fail({ type: 'CONFLICT', message: 'Worktree removal blocked by safety check' }),
+    });
+
+    const result = await updateThread({ threadId: 't-1', userId: 'u-1', archived: true });
+
+    expect(result.isOk()).toBe(true);
+    expect(mocks.removeBranch).not.toHaveBeenCalled();
+    expect(mocks.tm.updateThread).toHaveBeenCalledWith(
+      't-1',
+      expect.not.objectContaining({ worktreePath: null }),
+    );
+    expect(mocks.tm.updateThread).toHaveBeenCalledWith(
+      't-1',
+      expect.objectContaining({ archived: 1 }),
+    );
+  });
 });
 
 describe('updateThread — auto-start idle thread', () => {
@@ -559,17 +559,24 @@ export function merge(params: MergeParams): ResultAsync<string, DomainError> {
                 }
               }
 
-              if (params.cleanup && thread.worktreePath) {
-                await removeWorktree(project.path, thread.worktreePath).match(
-                  () => undefined,
-                  (e) =>
-                    log.warn('Worktree directory could not be removed (will be orphaned)', {
-                      namespace: 'git',
-                      worktreePath: thread.worktreePath,
-                      error: String(e),
-                    }),
-                );
+              // Uncommitted files in the worktree block the removal; the merged
+              // commits are on the target branch by now.
+              const removed =
+                params.cleanup && thread.worktreePath
+                  ? await removeWorktree(project.path, thread.worktreePath).match(
+                      () => true,
+                      (e) => {
+                        log.warn('Worktree could not be removed, keeping the thread on it', {
+                          namespace: 'git',
+                          worktreePath: thread.worktreePath,
+                          error: String(e),
+                        });
+                        return false;
+                      },
+                    )
+                  : false;
 
+              if (removed) {
                 await removeBranch(project.path, sourceBranch).match(
                   () => undefined,
                   (e) =>
@@ -470,17 +470,21 @@ export async function cleanupReviewerThread(
   const project = await getServices().projects.getProject(projectId);
   if (!project) return;
 
+  // A reviewer that left unsaved work keeps its worktree and branch.
+  let removed = true;
   if (reviewerThread.worktreePath && reviewerThread.mode === 'worktree') {
     const { removeWorktree, removeBranch } = await import('@funny/core/git');
-    await removeWorktree(project.path, reviewerThread.worktreePath).match(
-      () => undefined,
-      (e) =>
+    removed = await removeWorktree(project.path, reviewerThread.worktreePath).match(
+      () => true,
+      (e) => {
         log.warn('Pipeline: failed to remove reviewer worktree', {
           namespace: 'pipeline',
           error: String(e),
-        }),
+        });
+        return false;
+      },
     );
-    if (reviewerThread.branch) {
+    if (removed && reviewerThread.branch) {
       await removeBranch(project.path, reviewerThread.branch).match(
         () => undefined,
         (e) =>
@@ -494,8 +498,7 @@ export async function cleanupReviewerThread(
 
   await tm.updateThread(reviewerThreadId, {
     archived: 1,
-    worktreePath: null,
-    branch: null,
+    ...(removed ? { worktreePath: null, branch: null } : {}),
   });
 
   log.info('Pipeline: reviewer thread cleaned up', {
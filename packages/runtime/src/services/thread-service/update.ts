@@ -95,21 +95,29 @@ async function updateThreadImpl(params: UpdateThreadParams) {
       thread.userId,
     );
     const archivePath = archivePathResult.isOk() ? archivePathResult.value : undefined;
+    // Unsaved work blocks the removal; the archived thread then keeps its
+    // worktree and branch.
+    let removed = true;
     if (archivePath) {
       await stopCommandsByCwd(thread.worktreePath).catch(() => {});
-      await removeWorktree(archivePath, thread.worktreePath).match(
-        () => undefined,
-        (e) => log.warn('Failed to remove worktree', { namespace: 'cleanup', error: String(e) }),
+      removed = await removeWorktree(archivePath, thread.worktreePath).match(
+        () => true,
+        (e) => {
+          log.warn('Failed to remove worktree', { namespace: 'cleanup', error: String(e) });
+          return false;
+        },
       );
-      if (thread.branch) {
+      if (removed && thread.branch) {
         await removeBranch(archivePath, thread.branch).match(
           () => undefined,
           (e) => log.warn('Failed to remove branch', { namespace: 'cleanup', error: String(e) }),
         );
       }
     }
-    updates.worktreePath = null;
-    updates.branch = null;
+    if (removed) {
+      updates.worktreePath = null;
+      updates.branch = null;
+    }
     await getServices().messageQueue.clearQueue(params.threadId);
     cleanupThreadState(params.threadId);
   }
@@ -367,11 +375,20 @@ async function deleteThreadImpl(threadId: string): Promise<void> {
     );
     const deletePath = deletePathResult.isOk() ? deletePathResult.value : undefined;
     if (deletePath) {
-      await removeWorktree(deletePath, thread.worktreePath).match(
-        () => undefined,
-        (e) => log.warn('Failed to remove worktree', { namespace: 'cleanup', error: String(e) }),
+      // Unsaved work blocks the removal and keeps the branch; the worktree
+      // stays on disk for the user to recover.
+      const removed = await removeWorktree(deletePath, thread.worktreePath).match(
+        () => true,
+        (e) => {
+          log.warn('Kept worktree of deleted thread', {
+            namespace: 'cleanup',
+            worktreePath: thread.worktreePath,
+            error: String(e),
+          });
+          return false;
+        },
       );
-      if (thread.branch) {
+      if (removed && thread.branch) {
         await removeBranch(deletePath, thread.branch).match(
           () => undefined,
           (e) => log.warn('Failed to remove branch', { namespace: 'cleanup', error: String(e) }),
@@ -0,0 +1,277 @@
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+/// Where counts were queued before the database. Imported by the first
+/// migration, then removed.
+const LEGACY_QUEUE_FILE: &str = "analytics-queue.json";
+
+/// Where opted-in batches go. Builds without it never upload.
+const ENDPOINT: Option<&str> = option_env!("FUNNY_TELEMETRY_URL");
+
+/// Bumped whenever the shape of `Batch` changes.
+const SCHEMA_VERSION: u32 = 1;
+
+const UPLOAD_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Days of counts kept locally; older ones are dropped, sent or not.
+const MAX_DAYS: i64 = 30;
+
+/// Distinct names counted per day, so a misbehaving caller can't grow the
+/// queue without bound.
+const MAX_NAMES_PER_DAY: usize = 200;
+
+/// Counts for one UTC day. Only names and counts are recorded: no paths,
+/// project names, prompts or identifiers of any kind.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DayCounts {
+    pub features: BTreeMap<String, u32>,
+    pub errors: BTreeMap<String, u32>,
+}
+
+/// Exactly what gets uploaded.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Batch {
+    pub schema: u32,
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    /// `YYYY-MM-DD` → counts. Only finished days are sent.
+    pub days: BTreeMap<String, DayCounts>,
+}
+
+/// Copy counts from the JSON queue that predates the database.
+pub(crate) fn import_legacy(app: &AppHandle, conn: &Connection) {
+    let path = match app.path().app_data_dir() {
+        Ok(dir) => dir.join(LEGACY_QUEUE_FILE),
+        Err(_) => return,
+    };
+    let days: BTreeMap<String, DayCounts> = match std::fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
+        Err(_) => return,
+    };
+    for (day, counts) in &days {
+        for (kind, names) in [("feature", &counts.features), ("error", &counts.errors)] {
+            for (name, count) in names {
+                let _ = conn.execute(
+                    "INSERT OR IGNORE INTO usage_counts (day, kind, name, count)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![day, kind, name, count],
+                );
+            }
+        }
+    }
+    if let Err(e) = std::fs::remove_file(&path) {
+        tracing::debug!("failed to remove {}: {e}", path.display());
+    }
+}
+
+fn today() -> String {
+    chrono::Utc::now().format("%Y-%m-%d").to_string()
+}
+
+/// Names are fixed identifiers like `terminal.split` or `forge.create_pr`,
+/// never free text.
+fn check_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '.' | '_' | '-'));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("invalid telemetry name {name:?}"))
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Feature,
+    Error,
+}
+
+impl Kind {
+    fn column(self) -> &'static str {
+        match self {
+            Kind::Feature => "feature",
+            Kind::Error => "error",
+        }
+    }
+}
+
+fn record(app: &AppHandle, kind: Kind, name: &str) {
+    if check_name(name).is_err() {
+        return;
+    }
+    // try_with: errors are also recorded from the panic hook, which must not
+    // block on a lock the panicking thread may hold.
+    let result = crate::db::try_with(app, |conn| {
+        conn.execute(
+            "INSERT INTO usage_counts (day, kind, name, count)
+             SELECT ?1, ?2, ?3, 1
+             WHERE (SELECT COUNT(*) FROM usage_counts WHERE day = ?1 AND kind = ?2) < ?4
+                OR EXISTS (SELECT 1 FROM usage_counts WHERE day = ?1 AND kind = ?2 AND name = ?3)
+             ON CONFLICT (day, kind, name) DO UPDATE SET count = count + 1",
+            params![today(), kind.column(), name, MAX_NAMES_PER_DAY],
+        )
+    });
+    if let Err(e) = result {
+        tracing::debug!("usage count for {name} not recorded: {e}");
+    }
+}
+
+/// Count a use of a feature.
+pub(crate) fn record_feature(app: &AppHandle, name: &str) {
+    record(app, Kind::Feature, name);
+}
+
+/// Count an error by kind, e.g. `panic` or `server_exit`.
+pub(crate) fn record_error(app: &AppHandle, kind: &str) {
+    record(app, Kind::Error, kind);
+}
+
+fn counts_where(
+    conn: &Connection,
+    condition: &str,
+    day: &str,
+) -> rusqlite::Result<BTreeMap<String, DayCounts>> {
+    let mut statement = conn.prepare(&format!(
+        "SELECT day, kind, name, count FROM usage_counts WHERE day {condition} ?1"
+    ))?;
+    let mut rows = statement.query(params![day])?;
+    let mut days: BTreeMap<String, DayCounts> = BTreeMap::new();
+    while let Some(row) = rows.next()? {
+        let counts = days.entry(row.get(0)?).or_default();
+        let names = match row.get::<_, String>(1)?.as_str() {
+            "error" => &mut counts.errors,
+            _ => &mut counts.features,
+        };
+        names.insert(row.get(2)?, row.get(3)?);
+    }
+    Ok(days)
+}
+
+/// The batch the next upload would send: every finished day in the queue.
+fn pending_batch(app: &AppHandle) -> Result<Batch, String> {
+    Ok(Batch {
+        schema: SCHEMA_VERSION,
+        app_version: app.package_info().version.to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        days: crate::db::with(app, |conn| counts_where(conn, "<", &today()))?,
+    })
+}
+
+/// Drop days past `MAX_DAYS`, sent or not.
+fn prune(app: &AppHandle) -> Result<(), String> {
+    let oldest = (chrono::Utc::now() - chrono::Duration::days(MAX_DAYS))
+        .format("%Y-%m-%d")
+        .to_string();
+    crate::db::with(app, |conn| {
+        conn.execute("DELETE FROM usage_counts WHERE day < ?1", params![oldest])
+    })
+    .map(|_| ())
+}
+
+fn opted_in(app: &AppHandle) -> bool {
+    crate::settings::current(app).telemetry.opt_in
+}
+
+/// Send finished days if the user opted in, and drop them once accepted.
+fn upload(app: &AppHandle) -> Result<(), String> {
+    let endpoint = match ENDPOINT {
+        Some(endpoint) if opted_in(app) => endpoint,
+        _ => return Ok(()),
+    };
+    let batch = pending_batch(app)?;
+    let last_day = match batch.days.keys().next_back() {
+        Some(day) => day.clone(),
+        None => return Ok(()),
+    };
+    ureq::post(endpoint)
+        .timeout(Duration::from_secs(30))
+        .send_json(&batch)
+        .map_err(|e| e.to_string())?;
+
+    crate::db::with(app, |conn| {
+        conn.execute(
+            "DELETE FROM usage_counts WHERE day <= ?1",
+            params![last_day],
+        )
+    })?;
+    tracing::info!("sent usage counts for {} days", batch.days.len());
+    Ok(())
+}
+
+/// Start the hourly upload loop. Called once from `setup`; it does nothing
+/// until the user opts in.
+pub fn init(app: &AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || loop {
+        if let Err(e) = prune(&app) {
+            tracing::debug!("failed to prune usage counts: {e}");
+        }
+        std::thread::sleep(UPLOAD_INTERVAL);
+        if let Err(e) = upload(&app) {
+            tracing::debug!("telemetry upload failed: {e}");
+        }
+    });
+}
+
+/// Count a use of `feature` from the frontend.
+#[tauri::command]
+pub fn telemetry_record(
+    window: WebviewWindow,
+    app: AppHandle,
+    feature: String,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    check_name(&feature)?;
+    record_feature(&app, &feature);
+    Ok(())
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TelemetryPreview {
+    pub opted_in: bool,
+    /// `None` in builds that have nowhere to send to.
+    pub endpoint: Option<String>,
+    /// The exact body of the next upload.
+    pub batch: Batch,
+    /// Today's counts so far; sent once the day is over.
+    pub today: DayCounts,
+}
+
+/// Show exactly what would be sent, whether or not the user opted in.
+#[tauri::command]
+pub fn telemetry_preview(
+    window: WebviewWindow,
+    app: AppHandle,
+) -> Result<TelemetryPreview, String> {
+    crate::require_window(&window, "main")?;
+    let batch = pending_batch(&app)?;
+    let today = crate::db::with(&app, |conn| counts_where(conn, "=", &today()))?
+        .into_values()
+        .next()
+        .unwrap_or_default();
+    Ok(TelemetryPreview {
+        opted_in: opted_in(&app),
+        endpoint: ENDPOINT.map(str::to_string),
+        batch,
+        today,
+    })
+}
+
+/// Forget everything queued locally.
+#[tauri::command]
+pub fn telemetry_clear(window: WebviewWindow, app: AppHandle) -> Result<(), String> {
+    crate::require_window(&window, "main")?;
+    crate::db::with(&app, |conn| conn.execute("DELETE FROM usage_counts", []))?;
+    Ok(())
+}
@@ -0,0 +1,269 @@
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::menu::{Menu, MenuItem, PredefinedMenuItem, Submenu};
+use tauri::{AppHandle, Emitter, Manager, State, WebviewWindow, Wry};
+
+use crate::instance::OpenRequest;
+
+const STORE_FILE: &str = "recent-projects.json";
+/// Projects listed under Open Recent.
+const MAX_RECENT: usize = 10;
+
+const MENU_NEW_TERMINAL: &str = "menu:new-terminal";
+const MENU_NEW_TASK: &str = "menu:new-task";
+const MENU_RESTART_SERVER: &str = "menu:restart-server";
+const MENU_CLEAR_RECENT: &str = "menu:clear-recent";
+const MENU_RECENT_PREFIX: &str = "menu:recent:";
+
+/// Projects opened lately, most recent first, for the Open Recent menu.
+pub struct AppMenu {
+    recent: Mutex<Option<Vec<String>>>,
+}
+
+impl AppMenu {
+    pub fn new() -> Self {
+        Self {
+            recent: Mutex::new(None),
+        }
+    }
+}
+
+fn store_path(app: &AppHandle) -> Option<PathBuf> {
+    app.path()
+        .app_data_dir()
+        .ok()
+        .map(|dir| dir.join(STORE_FILE))
+}
+
+fn load(app: &AppHandle) -> Vec<String> {
+    store_path(app)
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save(app: &AppHandle, recent: &[String]) -> Result<(), String> {
+    let path = store_path(app).ok_or("no app data directory")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_vec_pretty(recent).map_err(|e| e.to_string())?;
+    std::fs::write(path, json).map_err(|e| e.to_string())
+}
+
+fn recent_submenu(app: &AppHandle, recent: &[String]) -> tauri::Result<Submenu<Wry>> {
+    let submenu = Submenu::new(app, "Open Recent", true)?;
+    // Ids index into `recent`; projects that are gone are skipped.
+    let existing: Vec<(usize, &String)> = recent
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| Path::new(p).is_dir())
+        .collect();
+    if existing.is_empty() {
+        submenu.append(&MenuItem::with_id(
+            app,
+            "menu:no-recent",
+            "No Recent Projects",
+            false,
+            None::<&str>,
+        )?)?;
+        return Ok(submenu);
+    }
+    for (i, project) in existing {
+        submenu.append(&MenuItem::with_id(
+            app,
+            format!("{MENU_RECENT_PREFIX}{i}"),
+            project.as_str(),
+            true,
+            None::<&str>,
+        )?)?;
+    }
+    submenu.append(&PredefinedMenuItem::separator(app)?)?;
+    submenu.append(&MenuItem::with_id(
+        app,
+        MENU_CLEAR_RECENT,
+        "Clear Menu",
+        true,
+        None::<&str>,
+    )?)?;
+    Ok(submenu)
+}
+
+fn build_menu(app: &AppHandle, recent: &[String]) -> tauri::Result<Menu<Wry>> {
+    let item = |id: &str, label: &str| MenuItem::with_id(app, id, label, true, None::<&str>);
+    let file = Submenu::with_items(
+        app,
+        "File",
+        true,
+        &[
+            &item(MENU_NEW_TERMINAL, "New Terminal")?,
+            &item(MENU_NEW_TASK, "New Task…")?,
+            &PredefinedMenuItem::separator(app)?,
+            &recent_submenu(app, recent)?,
+            &PredefinedMenuItem::separator(app)?,
+            &item(MENU_RESTART_SERVER, "Restart Server")?,
+            &PredefinedMenuItem::separator(app)?,
+            &PredefinedMenuItem::close_window(app, None)?,
+        ],
+    )?;
+    // Without these the webviews lose copy and paste on macOS.
+    let edit = Submenu::with_items(
+        app,
+        "Edit",
+        true,
+        &[
+            &PredefinedMenuItem::undo(app, None)?,
+            &PredefinedMenuItem::redo(app, None)?,
+            &PredefinedMenuItem::separator(app)?,
+            &PredefinedMenuItem::cut(app, None)?,
+            &PredefinedMenuItem::copy(app, None)?,
+            &PredefinedMenuItem::paste(app, None)?,
+            &PredefinedMenuItem::select_all(app, None)?,
+        ],
+    )?;
+    let window = Submenu::with_items(
+        app,
+        "Window",
+        true,
+        &[
+            &PredefinedMenuItem::minimize(app, None)?,
+            &PredefinedMenuItem::maximize(app, None)?,
+            &PredefinedMenuItem::fullscreen(app, None)?,
+        ],
+    )?;
+    let menu = Menu::new(app)?;
+    #[cfg(target_os = "macos")]
+    menu.append(&Submenu::with_items(
+        app,
+        "funny",
+        true,
+        &[
+            &PredefinedMenuItem::about(app, None, None)?,
+            &PredefinedMenuItem::separator(app)?,
+            &PredefinedMenuItem::services(app, None)?,
+            &PredefinedMenuItem::separator(app)?,
+            &PredefinedMenuItem::hide(app, None)?,
+            &PredefinedMenuItem::hide_others(app, None)?,
+            &PredefinedMenuItem::show_all(app, None)?,
+            &PredefinedMenuItem::separator(app)?,
+            &PredefinedMenuItem::quit(app, None)?,
+        ],
+    )?)?;
+    menu.append(&file)?;
+    menu.append(&edit)?;
+    menu.append(&window)?;
+    Ok(menu)
+}
+
+/// The app menu at startup. Passed to `Builder::menu`.
+pub fn build(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
+    build_menu(app, &load(app))
+}
+
+/// Change the recent projects with `edit`, then save them and rebuild the
+/// menu.
+fn update_recent(app: &AppHandle, edit: impl FnOnce(&mut Vec<String>)) -> Result<(), String> {
+    let state = app.state::<AppMenu>();
+    let mut guard = state.recent.lock().map_err(|e| e.to_string())?;
+    let recent = guard.get_or_insert_with(|| load(app));
+    edit(recent);
+    recent.truncate(MAX_RECENT);
+    save(app, recent)?;
+    let menu = build_menu(app, recent).map_err(|e| e.to_string())?;
+    app.set_menu(menu).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Move `project` to the top of Open Recent.
+pub(crate) fn project_opened(app: &AppHandle, project: &str) {
+    let result = update_recent(app, |recent| {
+        recent.retain(|p| p != project);
+        recent.insert(0, project.to_string());
+    });
+    if let Err(e) = result {
+        tracing::warn!("failed to update recent projects: {e}");
+    }
+}
+
+/// The focused app window, else the main one, for menu items that act on
+/// "the current window".
+fn focused_window(app: &AppHandle) -> Option<WebviewWindow> {
+    app.webview_windows()
+        .into_values()
+        .find(|w| crate::require_app_window(w).is_ok() && w.is_focused().unwrap_or(false))
+        .or_else(|| app.get_webview_window("main"))
+}
+
+fn on_menu_event(app: &AppHandle, id: &str) {
+    match id {
+        // Terminals and tasks are created by the frontend, which knows the
+        // project and the terminal size.
+        MENU_NEW_TERMINAL | MENU_NEW_TASK => {
+            if let Some(window) = focused_window(app) {
+                let _ = window.emit_to(window.label(), id, ());
+            }
+        }
+        MENU_RESTART_SERVER => {
+            let app = app.clone();
+            std::thread::spawn(move || {
+                if let Err(e) = crate::updater::restart_server(&app) {
+                    tracing::warn!("failed to restart the server: {e}");
+                }
+            });
+        }
+        MENU_CLEAR_RECENT => {
+            if let Err(e) = update_recent(app, Vec::clear) {
+                tracing::warn!("failed to clear recent projects: {e}");
+            }
+        }
+        _ => {
+            let Some(index) = id
+                .strip_prefix(MENU_RECENT_PREFIX)
+                .and_then(|i| i.parse::<usize>().ok())
+            else {
+                return;
+            };
+            let project = app
+                .state::<AppMenu>()
+                .recent
+                .lock()
+                .ok()
+                .and_then(|mut recent| recent.get_or_insert_with(|| load(app)).get(index).cloned());
+            if let Some(project) = project {
+                crate::instance::dispatch(
+                    app,
+                    Some(OpenRequest {
+                        project: Some(project),
+                        task: None,
+                    }),
+                );
+            }
+        }
+    }
+}
+
+/// Route clicks on app menu items. Called once from `setup`.
+pub fn init(app: &AppHandle) {
+    app.on_menu_event(|app, event| {
+        let id = event.id().as_ref();
+        if id.starts_with("menu:") {
+            on_menu_event(app, id);
+        }
+    });
+}
+
+/// The frontend opened `project` in the main window; list it first under
+/// Open Recent. Project windows and projects opened from outside are noted
+/// already.
+#[tauri::command]
+pub fn recent_project_opened(
+    window: WebviewWindow,
+    app: AppHandle,
+    state: State<'_, crate::sandbox::Sandbox>,
+    project: String,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    let root = state.check_dir(&project)?;
+    project_opened(&app, &root.to_string_lossy());
+    Ok(())
+}
@@ -0,0 +1,247 @@
+use rusqlite::types::Type;
+use rusqlite::{params, params_from_iter, Connection, ToSql};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+/// Where entries were kept before the database, one JSONL file per month.
+/// Imported once by the first migration and otherwise left alone.
+const LEGACY_DIR: &str = "audit";
+
+/// Default and maximum number of entries returned by `audit_query`.
+const DEFAULT_LIMIT: usize = 500;
+const MAX_LIMIT: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AuditKind {
+    FileWrite,
+    FileDelete,
+    FileMove,
+    FileCopy,
+    Command,
+    GitMutation,
+    /// A command policy decision.
+    Permission,
+    /// A connection refused by the egress proxy.
+    Network,
+    /// Likely prompt injection found in content bound for an agent.
+    Injection,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Outcome {
+    Ok,
+    Error,
+    /// Allowed by policy without asking.
+    Allowed,
+    Denied,
+    /// Flagged, then approved by the user.
+    Approved,
+    /// Flagged, then rejected by the user or timed out.
+    Rejected,
+}
+
+/// One line of the audit log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub timestamp: String,
+    pub kind: AuditKind,
+    /// The agent run acting, or `None` for the user.
+    pub agent: Option<String>,
+    /// Project root the operation happened in.
+    pub project: Option<String>,
+    /// The path, command or ref acted on.
+    pub target: String,
+    pub outcome: Outcome,
+    pub detail: Option<String>,
+}
+
+/// What callers hand to `record`; the timestamp is added there.
+pub(crate) struct AuditEvent<'a> {
+    pub kind: AuditKind,
+    pub agent: Option<&'a str>,
+    pub project: Option<&'a str>,
+    pub target: &'a str,
+    pub outcome: Outcome,
+    pub detail: Option<String>,
+}
+
+/// The serde name of a unit variant, e.g. `fileWrite`.
+pub(crate) fn name_of<T: Serialize>(value: T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+pub(crate) fn parse<T: serde::de::DeserializeOwned>(name: String) -> rusqlite::Result<T> {
+    serde_json::from_value(serde_json::Value::String(name))
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, Type::Text, Box::new(e)))
+}
+
+fn insert(conn: &Connection, entry: &AuditEntry) -> rusqlite::Result<usize> {
+    conn.execute(
+        "INSERT INTO audit_events (timestamp, kind, agent, project, target, outcome, detail)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            entry.timestamp,
+            name_of(entry.kind),
+            entry.agent,
+            entry.project,
+            entry.target,
+            name_of(entry.outcome),
+            entry.detail,
+        ],
+    )
+}
+
+/// Append `event` to the `audit_events` table, which nothing in the app
+/// updates or deletes. Failures are logged, never returned: the operation itself
+/// already happened.
+pub(crate) fn record(app: &AppHandle, event: AuditEvent<'_>) {
+    let entry = AuditEntry {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        kind: event.kind,
+        agent: event.agent.map(str::to_string),
+        project: event.project.map(str::to_string),
+        target: event.target.to_string(),
+        outcome: event.outcome,
+        detail: event.detail,
+    };
+    if let Err(e) = crate::db::with(app, |conn| insert(conn, &entry)) {
+        tracing::error!("failed to write audit entry for {}: {e}", entry.target);
+    }
+}
+
+/// Copy entries from the JSONL files that predate the database.
+pub(crate) fn import_legacy(app: &AppHandle, conn: &Connection) {
+    let dir = match app.path().app_data_dir() {
+        Ok(dir) => dir.join(LEGACY_DIR),
+        Err(_) => return,
+    };
+    let mut files: Vec<PathBuf> = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|ext| ext == "jsonl"))
+            .collect(),
+        Err(_) => return,
+    };
+    // `audit-YYYY-MM` sorts chronologically.
+    files.sort();
+    let mut imported = 0;
+    for file in files {
+        let content = match std::fs::read_to_string(&file) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+        // A torn last line from a crash is skipped.
+        for entry in content
+            .lines()
+            .filter_map(|line| serde_json::from_str::<AuditEntry>(line).ok())
+        {
+            if insert(conn, &entry).is_ok() {
+                imported += 1;
+            }
+        }
+    }
+    tracing::info!("imported {imported} audit entries from {}", dir.display());
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditFilter {
+    /// RFC 3339 bounds, inclusive.
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub kinds: Option<Vec<AuditKind>>,
+    pub agent: Option<String>,
+    pub project: Option<String>,
+    /// Only entries whose target contains this.
+    pub target: Option<String>,
+    pub limit: Option<u32>,
+}
+
+/// Search the audit log, newest first.
+#[tauri::command]
+pub async fn audit_query(
+    window: WebviewWindow,
+    app: AppHandle,
+    filter: Option<AuditFilter>,
+) -> Result<Vec<AuditEntry>, String> {
+    crate::require_app_window(&window)?;
+    let filter = filter.unwrap_or_default();
+    let limit = filter
+        .limit
+        .map(|l| l as usize)
+        .unwrap_or(DEFAULT_LIMIT)
+        .min(MAX_LIMIT);
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut clauses: Vec<String> = Vec::new();
+        let mut values: Vec<Box<dyn ToSql>> = Vec::new();
+        let mut bind = |clause: &str, value: Box<dyn ToSql>| {
+            values.push(value);
+            clauses.push(clause.replace('?', &format!("?{}", values.len())));
+        };
+        // RFC 3339 timestamps in UTC compare correctly as strings.
+        if let Some(since) = filter.since {
+            bind("timestamp >= ?", Box::new(since));
+        }
+        if let Some(until) = filter.until {
+            bind("timestamp <= ?", Box::new(until));
+        }
+        if let Some(agent) = filter.agent {
+            bind("agent = ?", Box::new(agent));
+        }
+        if let Some(project) = filter.project {
+            bind("project = ?", Box::new(project));
+        }
+        if let Some(target) = filter.target {
+            bind("instr(target, ?) > 0", Box::new(target));
+        }
+        if let Some(kinds) = filter.kinds {
+            let names: Vec<String> = kinds.into_iter().map(name_of).collect();
+            bind(
+                "kind IN (SELECT value FROM json_each(?))",
+                Box::new(serde_json::to_string(&names).unwrap_or_default()),
+            );
+        }
+        let sql = format!(
+            "SELECT timestamp, kind, agent, project, target, outcome, detail
+             FROM audit_events {} ORDER BY timestamp DESC, id DESC LIMIT {limit}",
+            if clauses.is_empty() {
+                String::new()
+            } else {
+                format!("WHERE {}", clauses.join(" AND "))
+            }
+        );
+        crate::db::with(&app, |conn| {
+            let mut statement = conn.prepare(&sql)?;
+            let rows = statement.query_map(params_from_iter(values.iter()), |row| {
+                Ok(AuditEntry {
+                    timestamp: row.get(0)?,
+                    kind: parse(row.get(1)?)?,
+                    agent: row.get(2)?,
+                    project: row.get(3)?,
+                    target: row.get(4)?,
+                    outcome: parse(row.get(5)?)?,
+                    detail: row.get(6)?,
+                })
+            })?;
+            rows.collect()
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Where the audit log lives — the app database — for "show in folder".
+#[tauri::command]
+pub fn audit_log_dir(window: WebviewWindow, app: AppHandle) -> Result<String, String> {
+    crate::require_app_window(&window)?;
+    crate::db::db_path(&app)
+        .and_then(|path| path.parent().map(|dir| dir.to_string_lossy().to_string()))
+        .ok_or_else(|| "no app data directory".to_string())
+}
@@ -0,0 +1,683 @@
+use rusqlite::OptionalExtension;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager, State, WebviewWindow};
+
+use crate::history::RunFilter;
+use crate::intake::IntakeRequest;
+use crate::layouts::LayoutTerminal;
+use crate::pty::PtyManager;
+
+/// Requests longer than this close the connection.
+const MAX_LINE_BYTES: u64 = 1024 * 1024;
+#[cfg(unix)]
+const ACCEPT_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(200);
+
+// JSON-RPC 2.0 error codes.
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// The method ran and failed; `message` says why.
+const APP_ERROR: i64 = -32000;
+
+/// The local socket `funnyctl` talks to. Off unless switched on.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AutomationSettings {
+    pub enabled: bool,
+}
+
+/// Where the socket is: `<app data>/automation/funny.sock`, in a directory
+/// only the user can enter, or a per-user named pipe on Windows.
+pub(crate) fn endpoint(app: &AppHandle) -> Option<PathBuf> {
+    #[cfg(unix)]
+    {
+        let dir = app.path().app_data_dir().ok()?;
+        Some(dir.join("automation").join("funny.sock"))
+    }
+    #[cfg(windows)]
+    {
+        let _ = app;
+        let user = std::env::var("USERNAME").unwrap_or_default();
+        Some(PathBuf::from(format!(r"\\.\pipe\funny-automation-{user}")))
+    }
+}
+
+/// The running listener, if any, and the clients tailing each terminal.
+pub struct Automation {
+    running: Mutex<Option<Arc<AtomicBool>>>,
+    tails: Mutex<HashMap<String, Vec<Sender<Value>>>>,
+}
+
+impl Automation {
+    pub fn new() -> Self {
+        Self {
+            running: Mutex::new(None),
+            tails: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Clients following terminal output.
+    pub(crate) fn tail_count(&self) -> usize {
+        self.tails
+            .lock()
+            .map(|tails| tails.values().map(Vec::len).sum())
+            .unwrap_or(0)
+    }
+}
+
+/// What a method hands back: a result, or for `terminals.tail` a stream of
+/// notifications that follows it.
+enum Reply {
+    Value(Value),
+    Tail(Receiver<Value>),
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+/// Pass terminal output to the clients tailing it. Called from the PTY reader
+/// with the text the webview gets, secrets already masked.
+pub(crate) fn terminal_output(app: &AppHandle, id: &str, data: &str) {
+    let state = match app.try_state::<Automation>() {
+        Some(state) => state,
+        None => return,
+    };
+    let mut tails = match state.tails.lock() {
+        Ok(tails) => tails,
+        Err(_) => return,
+    };
+    if let Some(senders) = tails.get_mut(id) {
+        let message = notification("terminal.output", json!({ "id": id, "data": data }));
+        senders.retain(|sender| sender.send(message.clone()).is_ok());
+        if senders.is_empty() {
+            tails.remove(id);
+        }
+    }
+}
+
+/// End the tails of a terminal that exited.
+pub(crate) fn terminal_exited(app: &AppHandle, id: &str) {
+    let senders = app
+        .try_state::<Automation>()
+        .and_then(|state| state.tails.lock().ok()?.remove(id));
+    for sender in senders.into_iter().flatten() {
+        let _ = sender.send(notification("terminal.exit", json!({ "id": id })));
+    }
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl From<String> for RpcError {
+    fn from(message: String) -> Self {
+        Self {
+            code: APP_ERROR,
+            message,
+        }
+    }
+}
+
+fn params<T: serde::de::DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    let params = if params.is_null() { json!({}) } else { params };
+    serde_json::from_value(params).map_err(|e| RpcError {
+        code: INVALID_PARAMS,
+        message: e.to_string(),
+    })
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SpawnParams {
+    project: String,
+    #[serde(flatten)]
+    terminal: LayoutTerminal,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WriteParams {
+    id: String,
+    data: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TaskParams {
+    id: i64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IdParams {
+    id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OpenParams {
+    project: String,
+    task: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TaskListParams {
+    status: Option<crate::intake::IntakeStatus>,
+}
+
+fn main_window(app: &AppHandle) -> Result<WebviewWindow, String> {
+    app.get_webview_window("main")
+        .ok_or_else(|| "the main window is not open".to_string())
+}
+
+/// Run one method. They mirror the app's commands; terminals open in the
+/// main window, and agents are queued like tasks posted to the intake
+/// endpoint because the frontend starts them through the server.
+fn call(app: &AppHandle, method: &str, raw: Value) -> Result<Reply, RpcError> {
+    let value = |v: Result<Value, serde_json::Error>| {
+        v.map(Reply::Value)
+            .map_err(|e| RpcError::from(e.to_string()))
+    };
+    match method {
+        "status" => {
+            let terminals = app
+                .state::<PtyManager>()
+                .snapshot()
+                .map(|t| t.len())
+                .unwrap_or_default();
+            Ok(Reply::Value(json!({
+                "version": app.package_info().version.to_string(),
+                "terminals": terminals,
+                "queuedTasks": crate::intake::queued(app)?,
+            })))
+        }
+        "projects.open" => {
+            let p: OpenParams = params(raw)?;
+            let path = std::fs::canonicalize(&p.project)
+                .map_err(|e| format!("invalid project {}: {e}", p.project))?;
+            if !path.is_dir() {
+                return Err(format!("{} is not a directory", p.project).into());
+            }
+            crate::instance::dispatch(
+                app,
+                Some(crate::instance::OpenRequest {
+                    project: Some(path.to_string_lossy().to_string()),
+                    task: p.task,
+                }),
+            );
+            Ok(Reply::Value(Value::Null))
+        }
+        "terminals.list" => value(serde_json::to_value(
+            app.state::<PtyManager>().snapshot().unwrap_or_default(),
+        )),
+        "terminals.spawn" => {
+            let p: SpawnParams = params(raw)?;
+            let window = main_window(app)?;
+            let root = app
+                .state::<crate::sandbox::Sandbox>()
+                .check_dir(&p.project)?;
+            let (mut opened, errors) = crate::layouts::open_terminals(
+                app,
+                &window,
+                &root.to_string_lossy(),
+                &[p.terminal],
+                "automation",
+            );
+            let terminal = match opened.pop() {
+                Some(terminal) => terminal,
+                None => return Err(errors.join("; ").into()),
+            };
+            // The frontend only shows terminals it knows about.
+            let _ = window.emit("automation:terminal-opened", terminal.clone());
+            value(serde_json::to_value(terminal))
+        }
+        "terminals.write" => {
+            let p: WriteParams = params(raw)?;
+            let state = app.state::<PtyManager>();
+            let window = state
+                .window_of(&p.id)
+                .and_then(|label| app.get_webview_window(&label))
+                .ok_or_else(|| format!("no terminal {}", p.id))?;
+            crate::pty::write(&state, &window, &p.id, &p.data)?;
+            Ok(Reply::Value(Value::Null))
+        }
+        "terminals.tail" => {
+            let p: IdParams = params(raw)?;
+            if !app.state::<PtyManager>().is_open(&p.id) {
+                return Err(format!("no terminal {}", p.id).into());
+            }
+            let (sender, receiver) = mpsc::channel();
+            app.state::<Automation>()
+                .tails
+                .lock()
+                .map_err(|e| e.to_string())?
+                .entry(p.id)
+                .or_default()
+                .push(sender);
+            Ok(Reply::Tail(receiver))
+        }
+        "agents.list" => {
+            let filter: RunFilter = params(raw)?;
+            value(serde_json::to_value(crate::history::runs(app, &filter)?))
+        }
+        "agents.start" => {
+            let request: IntakeRequest = params(raw)?;
+            value(serde_json::to_value(crate::intake::enqueue(
+                app, request, "funnyctl",
+            )?))
+        }
+        "agents.stop" => {
+            let p: IdParams = params(raw)?;
+            let known = crate::db::with(app, |conn| {
+                conn.query_row(
+                    "SELECT 1 FROM agent_runs WHERE id = ?1",
+                    [&p.id],
+                    |_| Ok(()),
+                )
+                .optional()
+            })?;
+            if known.is_none() {
+                return Err(format!("no agent run {}", p.id).into());
+            }
+            // The frontend stops it through the server.
+            main_window(app)?
+                .emit("automation:stop-agent", &p.id)
+                .map_err(|e| e.to_string())?;
+            Ok(Reply::Value(Value::Null))
+        }
+        "tasks.list" => {
+            let p: TaskListParams = params(raw)?;
+            value(serde_json::to_value(crate::intake::list(app, p.status)?))
+        }
+        "tasks.get" => {
+            let p: TaskParams = params(raw)?;
+            let task =
+                crate::intake::load(app, p.id)?.ok_or_else(|| format!("no task {}", p.id))?;
+            value(serde_json::to_value(task))
+        }
+        _ => Err(RpcError {
+            code: METHOD_NOT_FOUND,
+            message: format!("no method {method}"),
+        }),
+    }
+}
+
+/// Answer one JSON-RPC request line, and say whether notifications follow.
+/// Notifications (no `id`) get no answer.
+fn answer(app: &AppHandle, line: &str) -> (Option<Value>, Option<Receiver<Value>>) {
+    let request: Value = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => {
+            let error = json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": { "code": PARSE_ERROR, "message": e.to_string() },
+            });
+            return (Some(error), None);
+        }
+    };
+    let id = request.get("id").cloned();
+    let method = match request.get("method").and_then(Value::as_str) {
+        Some(method) if request.get("jsonrpc") == Some(&json!("2.0")) => method,
+        _ => {
+            let error = json!({
+                "jsonrpc": "2.0",
+                "id": id.unwrap_or(Value::Null),
+                "error": { "code": INVALID_REQUEST, "message": "not a JSON-RPC 2.0 request" },
+            });
+            return (Some(error), None);
+        }
+    };
+    // Params can carry tokens or file contents, so only these are logged.
+    let logged_id = id.clone().unwrap_or(Value::Null);
+    tracing::debug!("automation request: {method} (id {logged_id})");
+    let result = call(
+        app,
+        method,
+        request.get("params").cloned().unwrap_or(Value::Null),
+    );
+    let (result, tail) = match result {
+        Ok(Reply::Value(value)) => (Ok(value), None),
+        Ok(Reply::Tail(receiver)) => (Ok(Value::Null), Some(receiver)),
+        Err(e) => (Err(e), None),
+    };
+    let id = match id {
+        Some(id) => id,
+        None => return (None, tail),
+    };
+    let response = match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": e.code, "message": e.message },
+        }),
+    };
+    (Some(response), tail)
+}
+
+/// Serve one client: a request per line, a response per line, until it
+/// hangs up. After `terminals.tail` the connection only carries the
+/// terminal's output, until it exits or the client goes away.
+fn handle(app: &AppHandle, stream: impl Read + Write + Send) {
+    let mut reader = BufReader::new(stream);
+    loop {
+        let mut line = String::new();
+        match (&mut reader).take(MAX_LINE_BYTES).read_line(&mut line) {
+            Ok(0) | Err(_) => return,
+            Ok(_) if !line.ends_with('\n') && line.len() as u64 >= MAX_LINE_BYTES => return,
+            Ok(_) => {}
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (response, tail) = answer(app, &line);
+        let stream = reader.get_mut();
+        if let Some(response) = response {
+            let sent = writeln!(stream, "{response}").and_then(|_| stream.flush());
+            if sent.is_err() {
+                return;
+            }
+        }
+        if let Some(tail) = tail {
+            for message in tail {
+                let sent = writeln!(stream, "{message}").and_then(|_| stream.flush());
+                if sent.is_err() {
+                    return;
+                }
+            }
+            return;
+        }
+    }
+}
+
+#[cfg(unix)]
+fn listen(app: AppHandle, path: PathBuf, stop: Arc<AtomicBool>) -> Result<(), String> {
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::UnixListener;
+
+    let dir = path.parent().ok_or("no socket directory")?;
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))
+        .map_err(|e| e.to_string())?;
+    // A socket left behind by a crash would make `bind` fail.
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path).map_err(|e| format!("{}: {e}", path.display()))?;
+    listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+    std::thread::spawn(move || {
+        while !stop.load(Ordering::Relaxed) {
+            match listener.accept() {
+                Ok((stream, _)) => {
+                    let _ = stream.set_nonblocking(false);
+                    let app = app.clone();
+                    std::thread::spawn(move || handle(&app, stream));
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    std::thread::sleep(ACCEPT_POLL_INTERVAL);
+                }
+                Err(e) => {
+                    tracing::warn!("automation: accept failed: {e}");
+                    std::thread::sleep(ACCEPT_POLL_INTERVAL);
+                }
+            }
+        }
+        let _ = std::fs::remove_file(&path);
+    });
+    Ok(())
+}
+
+/// SDDL for a DACL that admits only the user this process runs as.
+#[cfg(windows)]
+fn current_user_only() -> std::io::Result<Vec<u16>> {
+    use windows_sys::Win32::Foundation::{CloseHandle, LocalFree, HANDLE};
+    use windows_sys::Win32::Security::Authorization::ConvertSidToStringSidW;
+    use windows_sys::Win32::Security::{GetTokenInformation, TokenUser, TOKEN_QUERY, TOKEN_USER};
+    use windows_sys::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken};
+
+    let mut token: HANDLE = std::ptr::null_mut();
+    if unsafe { OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) } == 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let mut len = 0u32;
+    unsafe { GetTokenInformation(token, TokenUser, std::ptr::null_mut(), 0, &mut len) };
+    // u64s keep the buffer aligned for TOKEN_USER.
+    let mut buffer = vec![0u64; (len as usize).div_ceil(8)];
+    let read =
+        unsafe { GetTokenInformation(token, TokenUser, buffer.as_mut_ptr().cast(), len, &mut len) }
+            != 0;
+    let error = std::io::Error::last_os_error();
+    unsafe { CloseHandle(token) };
+    if !read {
+        return Err(error);
+    }
+    let user = unsafe { &*buffer.as_ptr().cast::<TOKEN_USER>() };
+    let mut sid: *mut u16 = std::ptr::null_mut();
+    if unsafe { ConvertSidToStringSidW(user.User.Sid, &mut sid) } == 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let sid_text = unsafe {
+        let len = (0..).take_while(|&i| *sid.add(i) != 0).count();
+        String::from_utf16_lossy(std::slice::from_raw_parts(sid, len))
+    };
+    unsafe { LocalFree(sid.cast()) };
+    // Protected, so nothing is inherited: generic all to the user, no one else.
+    Ok(format!("D:P(A;;GA;;;{sid_text})")
+        .encode_utf16()
+        .chain(Some(0))
+        .collect())
+}
+
+/// A new instance of the pipe, with the `sddl` DACL. `first` makes creation
+/// fail if the pipe already exists, so another process can't have taken the
+/// name for clients to connect to.
+#[cfg(windows)]
+fn create_pipe(name: &[u16], sddl: &[u16], first: bool) -> std::io::Result<std::fs::File> {
+    use std::os::windows::io::FromRawHandle;
+    use windows_sys::Win32::Foundation::{LocalFree, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::Security::Authorization::{
+        ConvertStringSecurityDescriptorToSecurityDescriptorW, SDDL_REVISION_1,
+    };
+    use windows_sys::Win32::Security::{PSECURITY_DESCRIPTOR, SECURITY_ATTRIBUTES};
+    use windows_sys::Win32::Storage::FileSystem::{
+        FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_DUPLEX,
+    };
+    use windows_sys::Win32::System::Pipes::{
+        CreateNamedPipeW, PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE,
+        PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+    };
+
+    let mut descriptor: PSECURITY_DESCRIPTOR = std::ptr::null_mut();
+    let converted = unsafe {
+        ConvertStringSecurityDescriptorToSecurityDescriptorW(
+            sddl.as_ptr(),
+            SDDL_REVISION_1,
+            &mut descriptor,
+            std::ptr::null_mut(),
+        )
+    } != 0;
+    if !converted {
+        return Err(std::io::Error::last_os_error());
+    }
+    let attributes = SECURITY_ATTRIBUTES {
+        nLength: std::mem::size_of::<SECURITY_ATTRIBUTES>() as u32,
+        lpSecurityDescriptor: descriptor,
+        bInheritHandle: 0,
+    };
+    let open_mode = if first {
+        PIPE_ACCESS_DUPLEX | FILE_FLAG_FIRST_PIPE_INSTANCE
+    } else {
+        PIPE_ACCESS_DUPLEX
+    };
+    let handle = unsafe {
+        CreateNamedPipeW(
+            name.as_ptr(),
+            open_mode,
+            PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+            PIPE_UNLIMITED_INSTANCES,
+            64 * 1024,
+            64 * 1024,
+            0,
+            &attributes,
+        )
+    };
+    let error = std::io::Error::last_os_error();
+    unsafe { LocalFree(descriptor) };
+    if handle == INVALID_HANDLE_VALUE {
+        return Err(error);
+    }
+    // Owning the handle closes it when the client is done.
+    Ok(unsafe { std::fs::File::from_raw_handle(handle as _) })
+}
+
+/// Wait for a client on `pipe`.
+#[cfg(windows)]
+fn connect_pipe(pipe: &std::fs::File) -> std::io::Result<()> {
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Foundation::{GetLastError, ERROR_PIPE_CONNECTED};
+    use windows_sys::Win32::System::Pipes::ConnectNamedPipe;
+
+    let handle = pipe.as_raw_handle() as _;
+    let connected = unsafe { ConnectNamedPipe(handle, std::ptr::null_mut()) } != 0
+        || unsafe { GetLastError() } == ERROR_PIPE_CONNECTED;
+    if connected {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+#[cfg(windows)]
+fn listen(app: AppHandle, path: PathBuf, stop: Arc<AtomicBool>) -> Result<(), String> {
+    let name: Vec<u16> = path
+        .to_string_lossy()
+        .encode_utf16()
+        .chain(Some(0))
+        .collect();
+    let sddl = current_user_only().map_err(|e| format!("cannot secure {}: {e}", path.display()))?;
+    std::thread::spawn(move || {
+        // The next instance is opened before a connected one is handed off,
+        // so while this runs one of ours always holds the name. Without one
+        // open, creation is `first` and fails if someone else holds it.
+        let mut pending: Option<std::fs::File> = None;
+        // Waiting for a client blocks; `stop` connects once to wake it.
+        while !stop.load(Ordering::Relaxed) {
+            let pipe = match pending.take() {
+                Some(pipe) => pipe,
+                None => match create_pipe(&name, &sddl, true) {
+                    Ok(pipe) => pipe,
+                    Err(e) => {
+                        tracing::warn!("automation: pipe failed: {e}");
+                        std::thread::sleep(std::time::Duration::from_secs(1));
+                        continue;
+                    }
+                },
+            };
+            if let Err(e) = connect_pipe(&pipe) {
+                tracing::warn!("automation: pipe failed: {e}");
+                std::thread::sleep(std::time::Duration::from_secs(1));
+                continue;
+            }
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+            pending = create_pipe(&name, &sddl, false)
+                .inspect_err(|e| tracing::warn!("automation: pipe failed: {e}"))
+                .ok();
+            let app = app.clone();
+            std::thread::spawn(move || handle(&app, pipe));
+        }
+    });
+    Ok(())
+}
+
+fn shut_down(app: &AppHandle, stop: &AtomicBool) {
+    stop.store(true, Ordering::Relaxed);
+    #[cfg(windows)]
+    if let Some(path) = endpoint(app) {
+        let _ = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path);
+    }
+    #[cfg(unix)]
+    let _ = app;
+}
+
+/// Start or stop the socket to match `settings`.
+pub(crate) fn configure(app: &AppHandle, settings: &AutomationSettings) {
+    let automation = match app.try_state::<Automation>() {
+        Some(automation) => automation,
+        None => return,
+    };
+    let mut running = match automation.running.lock() {
+        Ok(running) => running,
+        Err(_) => return,
+    };
+    if settings.enabled == running.is_some() {
+        return;
+    }
+    if let Some(stop) = running.take() {
+        shut_down(app, &stop);
+        tracing::info!("automation socket stopped");
+        return;
+    }
+    let path = match endpoint(app) {
+        Some(path) => path,
+        None => return,
+    };
+    let stop = Arc::new(AtomicBool::new(false));
+    match listen(app.clone(), path.clone(), stop.clone()) {
+        Ok(()) => {
+            tracing::info!("automation socket listening on {}", path.display());
+            *running = Some(stop);
+        }
+        Err(e) => tracing::error!("automation socket failed to start: {e}"),
+    }
+}
+
+/// Open the socket if the settings ask for it. Called once from `setup`.
+pub fn init(app: &AppHandle) {
+    configure(app, &crate::settings::current(app).automation);
+}
+
+pub(crate) fn stop(app: &AppHandle, state: &Automation) {
+    if let Ok(mut running) = state.running.lock() {
+        if let Some(stop) = running.take() {
+            shut_down(app, &stop);
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutomationInfo {
+    pub enabled: bool,
+    pub listening: bool,
+    /// Socket path or pipe name, for `FUNNY_SOCKET`.
+    pub endpoint: Option<String>,
+}
+
+#[tauri::command]
+pub fn automation_status(
+    window: WebviewWindow,
+    app: AppHandle,
+    state: State<'_, Automation>,
+) -> Result<AutomationInfo, String> {
+    crate::require_window(&window, "main")?;
+    Ok(AutomationInfo {
+        enabled: crate::settings::current(&app).automation.enabled,
+        listening: state.running.lock().map_err(|e| e.to_string())?.is_some(),
+        endpoint: endpoint(&app).map(|p| p.to_string_lossy().to_string()),
+    })
+}
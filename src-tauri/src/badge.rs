@@ -0,0 +1,50 @@
+use tauri::window::{ProgressBarState, ProgressBarStatus};
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+/// Show `count` (agents awaiting input) on the dock / launcher icon, or clear
+/// the badge for `0`. Windows has no badge count; the taskbar progress is the
+/// indicator there.
+pub(crate) fn set_badge(app: &AppHandle, count: u32) -> Result<(), String> {
+    let main = match app.get_webview_window("main") {
+        Some(main) => main,
+        None => return Ok(()),
+    };
+    if cfg!(windows) {
+        return Ok(());
+    }
+    let count = (count > 0).then_some(count as i64);
+    main.set_badge_count(count).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn set_dock_badge(
+    window: WebviewWindow,
+    app: AppHandle,
+    count: Option<u32>,
+) -> Result<(), String> {
+    crate::require_window(&window, "main")?;
+    set_badge(&app, count.unwrap_or(0))
+}
+
+/// Show overall progress (0–100) of long-running orchestrated tasks on the
+/// taskbar button (Windows) or dock icon (macOS). Without `progress` the bar
+/// is indeterminate; `status: "none"` hides it.
+#[tauri::command]
+pub fn set_taskbar_progress(
+    window: WebviewWindow,
+    progress: Option<u32>,
+    status: Option<ProgressBarStatus>,
+) -> Result<(), String> {
+    crate::require_window(&window, "main")?;
+    let status = status.unwrap_or(if progress.is_some() {
+        ProgressBarStatus::Normal
+    } else {
+        ProgressBarStatus::Indeterminate
+    });
+    window
+        .set_progress_bar(ProgressBarState {
+            status: Some(status),
+            progress: progress.map(|p| p.min(100) as u64),
+        })
+        .map_err(|e| e.to_string())
+}
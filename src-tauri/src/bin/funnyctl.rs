@@ -0,0 +1,277 @@
+//! Script a running funny from the shell over its automation socket.
+//! Switch the socket on in the settings (`automation.enabled`) first; see
+//! `docs/guides/automation.md` for the methods.
+
+use serde_json::{json, Map, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// The app's bundle identifier, which names its data directory.
+const IDENTIFIER: &str = "com.funny.app";
+
+const USAGE: &str = "usage: funnyctl <command> [args]
+
+commands:
+  status
+  open <project> [--task ID]
+  list-terminals
+  list-agents [--project DIR] [--status STATUS] [--limit N]
+  spawn-terminal <project> [--cwd DIR] [--title TITLE] [--] [COMMAND...]
+  write <terminal> <text>
+  tail <terminal>
+  start-agent <project> <prompt>
+  start-agent <project> --template ID [--var NAME=VALUE]...
+  start-agent <project> --issue github|linear|jira:ID
+  stop-agent <run>
+  list-tasks [--status STATUS]
+  task <id>
+  call <method> [params as JSON]
+
+The socket is FUNNY_SOCKET when set, else the app's default location.";
+
+fn socket_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("FUNNY_SOCKET") {
+        return Some(PathBuf::from(path));
+    }
+    if cfg!(windows) {
+        let user = std::env::var("USERNAME").unwrap_or_default();
+        return Some(PathBuf::from(format!(r"\\.\pipe\funny-automation-{user}")));
+    }
+    let home = PathBuf::from(std::env::var_os("HOME")?);
+    let data = if cfg!(target_os = "macos") {
+        home.join("Library/Application Support")
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| home.join(".local/share"))
+    };
+    Some(data.join(IDENTIFIER).join("automation").join("funny.sock"))
+}
+
+#[cfg(unix)]
+fn connect(path: &PathBuf) -> std::io::Result<impl std::io::Read + Write> {
+    std::os::unix::net::UnixStream::connect(path)
+}
+
+#[cfg(windows)]
+fn connect(path: &PathBuf) -> std::io::Result<impl std::io::Read + Write> {
+    std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+}
+
+/// Send one request and read its response, keeping the connection for any
+/// notifications that follow.
+fn session(
+    method: &str,
+    params: Value,
+) -> Result<(Value, BufReader<impl std::io::Read + Write>), String> {
+    let path = socket_path().ok_or("cannot tell where the socket is; set FUNNY_SOCKET")?;
+    let stream = connect(&path).map_err(|e| {
+        format!(
+            "cannot reach funny at {}: {e} (is it running with automation enabled?)",
+            path.display()
+        )
+    })?;
+    let mut reader = BufReader::new(stream);
+    let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+    writeln!(reader.get_mut(), "{body}").map_err(|e| e.to_string())?;
+    reader.get_mut().flush().map_err(|e| e.to_string())?;
+    let mut line = String::new();
+    reader.read_line(&mut line).map_err(|e| e.to_string())?;
+    let response: Value =
+        serde_json::from_str(&line).map_err(|e| format!("bad response from funny: {e}"))?;
+    match response.get("error") {
+        Some(error) => Err(error["message"]
+            .as_str()
+            .unwrap_or("unknown error")
+            .to_string()),
+        None => Ok((
+            response.get("result").cloned().unwrap_or(Value::Null),
+            reader,
+        )),
+    }
+}
+
+fn request(method: &str, params: Value) -> Result<Value, String> {
+    session(method, params).map(|(result, _)| result)
+}
+
+/// Copy a terminal's output to stdout until it exits.
+fn tail(id: &str) -> Result<Value, String> {
+    let (_, reader) = session("terminals.tail", json!({ "id": id }))?;
+    let mut stdout = std::io::stdout();
+    for line in reader.lines() {
+        let message: Value = match line.map(|l| serde_json::from_str(&l)) {
+            Ok(Ok(message)) => message,
+            _ => break,
+        };
+        match message["method"].as_str() {
+            Some("terminal.output") => {
+                let data = message["params"]["data"].as_str().unwrap_or_default();
+                let _ = stdout.write_all(data.as_bytes());
+                let _ = stdout.flush();
+            }
+            Some("terminal.exit") => break,
+            _ => {}
+        }
+    }
+    Ok(Value::Null)
+}
+
+/// A project path as the app expects it: absolute, since the app doesn't
+/// share this shell's working directory.
+fn project_path(path: &str) -> Result<String, String> {
+    std::fs::canonicalize(path)
+        .map(|p| p.to_string_lossy().to_string())
+        .map_err(|e| format!("{path}: {e}"))
+}
+
+/// `--name value` options and the remaining positional arguments.
+fn options(args: &[String], names: &[&str]) -> Result<(Map<String, Value>, Vec<String>), String> {
+    let mut found = Map::new();
+    let mut rest = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.strip_prefix("--") {
+            Some("") => {
+                rest.extend(args.by_ref().cloned());
+            }
+            Some(name) if names.contains(&name) => {
+                let value = args.next().ok_or(format!("--{name} needs a value"))?;
+                found.insert(name.to_string(), Value::from(value.as_str()));
+            }
+            Some(name) => return Err(format!("unknown option --{name}")),
+            None => rest.push(arg.clone()),
+        }
+    }
+    Ok((found, rest))
+}
+
+fn start_agent(args: &[String]) -> Result<Value, String> {
+    let (project, args) = args.split_first().ok_or(USAGE)?;
+    let mut params = json!({ "project": project_path(project)? });
+    let mut vars = Map::new();
+    let mut prompt = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--template" => params["template"] = json!(args.next().ok_or(USAGE)?),
+            "--var" => {
+                let var = args.next().ok_or(USAGE)?;
+                let (name, value) = var
+                    .split_once('=')
+                    .ok_or(format!("--var {var}: use NAME=VALUE"))?;
+                vars.insert(name.to_string(), json!(value));
+            }
+            "--issue" => {
+                let issue = args.next().ok_or(USAGE)?;
+                let (provider, id) = issue
+                    .split_once(':')
+                    .ok_or(format!("--issue {issue}: use PROVIDER:ID"))?;
+                params["issue"] = json!({ "provider": provider, "id": id });
+            }
+            "--title" => params["title"] = json!(args.next().ok_or(USAGE)?),
+            _ => prompt.push(arg.as_str()),
+        }
+    }
+    if !prompt.is_empty() {
+        params["prompt"] = json!(prompt.join(" "));
+    }
+    if !vars.is_empty() {
+        params["vars"] = Value::Object(vars);
+    }
+    request("agents.start", params)
+}
+
+fn run(args: &[String]) -> Result<Value, String> {
+    let (command, args) = args.split_first().ok_or(USAGE)?;
+    match command.as_str() {
+        "status" => request("status", Value::Null),
+        "open" => {
+            let (mut params, rest) = options(args, &["task"])?;
+            let project = match rest.as_slice() {
+                [project] => project_path(project)?,
+                _ => return Err(USAGE.to_string()),
+            };
+            params.insert("project".to_string(), json!(project));
+            request("projects.open", Value::Object(params))
+        }
+        "list-terminals" => request("terminals.list", Value::Null),
+        "list-agents" => {
+            let (mut filter, _) = options(args, &["project", "status", "limit"])?;
+            if let Some(project) = filter.get("project").and_then(Value::as_str) {
+                let project = project_path(project)?;
+                filter.insert("project".to_string(), json!(project));
+            }
+            if let Some(limit) = filter.get("limit").and_then(Value::as_str) {
+                let limit: u32 = limit.parse().map_err(|_| "--limit must be a number")?;
+                filter.insert("limit".to_string(), json!(limit));
+            }
+            request("agents.list", Value::Object(filter))
+        }
+        "spawn-terminal" => {
+            let (mut params, rest) = options(args, &["cwd", "title"])?;
+            let (project, command) = rest.split_first().ok_or(USAGE)?;
+            params.insert("project".to_string(), json!(project_path(project)?));
+            if !command.is_empty() {
+                params.insert("command".to_string(), json!(command.join(" ")));
+            }
+            request("terminals.spawn", Value::Object(params))
+        }
+        "write" => match args {
+            [id, text] => request("terminals.write", json!({ "id": id, "data": text })),
+            _ => Err(USAGE.to_string()),
+        },
+        "tail" => match args {
+            [id] => tail(id),
+            _ => Err(USAGE.to_string()),
+        },
+        "start-agent" => start_agent(args),
+        "stop-agent" => match args {
+            [id] => request("agents.stop", json!({ "id": id })),
+            _ => Err(USAGE.to_string()),
+        },
+        "list-tasks" => {
+            let (filter, _) = options(args, &["status"])?;
+            request("tasks.list", Value::Object(filter))
+        }
+        "task" => {
+            let id: i64 = args.first().and_then(|id| id.parse().ok()).ok_or(USAGE)?;
+            request("tasks.get", json!({ "id": id }))
+        }
+        "call" => {
+            let (method, params) = args.split_first().ok_or(USAGE)?;
+            let params = match params.first() {
+                Some(params) => serde_json::from_str(params).map_err(|e| format!("params: {e}"))?,
+                None => Value::Null,
+            };
+            request(method, params)
+        }
+        "help" | "--help" | "-h" => {
+            println!("{USAGE}");
+            Ok(Value::Null)
+        }
+        other => Err(format!("unknown command {other}\n\n{USAGE}")),
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match run(&args) {
+        Ok(Value::Null) => ExitCode::SUCCESS,
+        Ok(result) => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&result).unwrap_or_default()
+            );
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("funnyctl: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
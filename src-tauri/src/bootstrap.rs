@@ -0,0 +1,127 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, State, WebviewWindow};
+
+use crate::exec::{ExecEvent, ExecOptions};
+use crate::jobs::JobInfo;
+use crate::sandbox::Sandbox;
+
+/// Installs can take a while; each command gets this long.
+const STEP_TIMEOUT_MS: u64 = 30 * 60 * 1000;
+
+/// Output kept for the error of a failed step.
+const OUTPUT_TAIL: usize = 2000;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BootstrapStep {
+    pub command: Vec<String>,
+    pub elapsed_ms: u64,
+}
+
+/// The `job:finished` result of a bootstrap: its steps, all succeeded.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BootstrapResult {
+    pub worktree: String,
+    pub steps: Vec<BootstrapStep>,
+}
+
+/// Run `root`'s bootstrap commands in its new worktree `worktree` as a
+/// `worktree.bootstrap` job, progress counted in commands. `None` if the
+/// project has none. The commands come from the repo, so they only run once
+/// the user trusts the project (`project_trust`). The job fails at the
+/// first command that does.
+pub(crate) fn start(
+    app: &AppHandle,
+    window: &WebviewWindow,
+    root: &Path,
+    worktree: &Path,
+) -> Result<Option<JobInfo>, String> {
+    let Some(config) = crate::project_config::get(app, &root.to_string_lossy()) else {
+        return Ok(None);
+    };
+    if config.bootstrap.is_empty() {
+        return Ok(None);
+    }
+    if !config.trusted {
+        return Err(format!(
+            "{} is not trusted to run its bootstrap commands; trust the project to run them",
+            root.display()
+        ));
+    }
+    let label = worktree
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let (worker, dir) = (app.clone(), worktree.to_path_buf());
+    let options = ExecOptions {
+        env: config.env,
+        timeout_ms: Some(STEP_TIMEOUT_MS),
+    };
+    let commands = config.bootstrap;
+    crate::jobs::spawn(app, window, "worktree.bootstrap", label, move |job| {
+        let total = commands.len() as u64;
+        let mut steps = Vec::new();
+        for (i, argv) in commands.into_iter().enumerate() {
+            job.check()?;
+            let line = argv.join(" ");
+            job.progress(i as u64, Some(total), Some(&line));
+            let mut output = String::new();
+            let result = crate::exec::execute_blocking(
+                &worker,
+                &dir,
+                &argv,
+                &options,
+                None,
+                job.token(),
+                &mut |event| {
+                    if let ExecEvent::Stdout(text) | ExecEvent::Stderr(text) = event {
+                        output.push_str(&text);
+                        if output.len() > 2 * OUTPUT_TAIL {
+                            output = crate::verify::tail(&output, OUTPUT_TAIL).to_string();
+                        }
+                    }
+                },
+            )?;
+            if result.exit_code != Some(0) {
+                let status = match result.exit_code {
+                    _ if result.timed_out => "timed out".to_string(),
+                    Some(code) => format!("exited with {code}"),
+                    None => "was killed".to_string(),
+                };
+                let output =
+                    crate::redact::redact_tokens(crate::verify::tail(&output, OUTPUT_TAIL));
+                tracing::warn!("bootstrap of {} failed: `{line}` {status}", dir.display());
+                return Err(format!("`{line}` {status}\n{}", output.trim()));
+            }
+            steps.push(BootstrapStep {
+                command: argv,
+                elapsed_ms: result.elapsed_ms,
+            });
+        }
+        job.progress(total, Some(total), None);
+        tracing::info!("bootstrapped {}", dir.display());
+        Ok(BootstrapResult {
+            worktree: dir.to_string_lossy().to_string(),
+            steps,
+        })
+    })
+    .map(Some)
+}
+
+/// Run `project`'s bootstrap commands in `worktree` again, e.g. after a
+/// failed one was fixed. `None` if the project has none.
+#[tauri::command]
+pub fn worktree_bootstrap(
+    window: WebviewWindow,
+    app: AppHandle,
+    sandbox: State<'_, Sandbox>,
+    project: String,
+    worktree: String,
+) -> Result<Option<JobInfo>, String> {
+    crate::require_app_window(&window)?;
+    let root = sandbox.check_dir(&project)?;
+    let worktree: PathBuf = sandbox.check_dir(&worktree)?;
+    start(&app, &window, &root, &worktree)
+}
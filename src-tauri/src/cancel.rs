@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+/// Set once the request it belongs to is cancelled. Long-running work checks
+/// it between steps.
+#[derive(Clone, Default)]
+pub(crate) struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// The underlying flag, for code that polls an `AtomicBool`.
+    pub(crate) fn flag(&self) -> &AtomicBool {
+        &self.0
+    }
+
+    /// `Err` once cancelled, for `?` at checkpoints.
+    pub(crate) fn check(&self) -> Result<(), String> {
+        if self.is_cancelled() {
+            Err("cancelled".to_string())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Tokens of in-flight requests by the id the frontend gave them, so one
+/// `cancel` command can stop a search, a diff, a file operation or a job.
+pub struct Cancellation {
+    requests: Mutex<HashMap<String, CancelToken>>,
+}
+
+impl Cancellation {
+    pub fn new() -> Self {
+        Self {
+            requests: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+/// Keeps a request cancellable while alive; dropping it forgets the id.
+pub(crate) struct CancelGuard {
+    app: AppHandle,
+    id: Option<String>,
+    token: CancelToken,
+}
+
+impl CancelGuard {
+    pub(crate) fn token(&self) -> CancelToken {
+        self.token.clone()
+    }
+}
+
+impl Drop for CancelGuard {
+    fn drop(&mut self) {
+        let id = match &self.id {
+            Some(id) => id,
+            None => return,
+        };
+        if let Ok(mut requests) = self.app.state::<Cancellation>().requests.lock() {
+            // A newer request may have taken over the id.
+            if requests
+                .get(id)
+                .is_some_and(|t| Arc::ptr_eq(&t.0, &self.token.0))
+            {
+                requests.remove(id);
+            }
+        }
+    }
+}
+
+/// Make request `id` cancellable. Registering an id that is still in flight
+/// cancels the earlier request, so re-issuing a search supersedes it.
+/// Without an id the work simply can't be cancelled.
+pub(crate) fn register(app: &AppHandle, id: Option<&str>) -> CancelGuard {
+    let token = CancelToken::default();
+    if let Some(id) = id {
+        if let Ok(mut requests) = app.state::<Cancellation>().requests.lock() {
+            if let Some(previous) = requests.insert(id.to_string(), token.clone()) {
+                previous.cancel();
+            }
+        }
+    }
+    CancelGuard {
+        app: app.clone(),
+        id: id.map(str::to_string),
+        token,
+    }
+}
+
+/// Cancel request `id`. Returns false if nothing with that id is running.
+pub(crate) fn cancel_request(app: &AppHandle, id: &str) -> bool {
+    match app.state::<Cancellation>().requests.lock() {
+        Ok(requests) => match requests.get(id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        },
+        Err(_) => false,
+    }
+}
+
+/// Cancel everything in flight — called on app exit.
+pub fn cancel_all(state: &Cancellation) {
+    if let Ok(requests) = state.requests.lock() {
+        for token in requests.values() {
+            token.cancel();
+        }
+    }
+}
+
+/// Abort the request started with `request_id`. Returns whether it was
+/// still running; the request itself fails with `cancelled`.
+#[tauri::command]
+pub fn cancel(window: WebviewWindow, app: AppHandle, request_id: String) -> Result<bool, String> {
+    crate::require_app_window(&window)?;
+    Ok(cancel_request(&app, &request_id))
+}
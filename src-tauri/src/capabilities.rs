@@ -0,0 +1,322 @@
+use serde::Serialize;
+use std::process::Command;
+use std::time::Duration;
+use tauri::WebviewWindow;
+
+/// Local model servers get this long to answer before they count as down.
+const SERVER_TIMEOUT: Duration = Duration::from_millis(800);
+
+/// Where the local model servers listen by default.
+const OLLAMA_URL: &str = "http://127.0.0.1:11434";
+const LLAMA_CPP_URL: &str = "http://127.0.0.1:8080";
+
+const MIB: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Cpu {
+    pub model: Option<String>,
+    /// Logical cores available to the app.
+    pub cores: usize,
+    pub arch: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Memory {
+    pub total_bytes: u64,
+    /// `None` where the OS doesn't say cheaply (macOS).
+    pub available_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Gpu {
+    pub name: String,
+    /// Dedicated memory; `None` when unknown or, on Apple silicon, shared
+    /// with the system (see `unified_memory`).
+    pub vram_bytes: Option<u64>,
+    pub vram_used_bytes: Option<u64>,
+    /// Models can use system memory, as on Apple silicon.
+    pub unified_memory: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalModel {
+    pub name: String,
+    pub size_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelServer {
+    pub name: String,
+    pub url: String,
+    /// The executable is on PATH.
+    pub installed: bool,
+    /// Answered on `url` just now.
+    pub running: bool,
+    /// Models it has available, for servers that list them (ollama).
+    pub models: Vec<LocalModel>,
+}
+
+/// What this machine offers local models.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemCapabilities {
+    pub cpu: Cpu,
+    pub memory: Option<Memory>,
+    pub gpus: Vec<Gpu>,
+    pub servers: Vec<ModelServer>,
+}
+
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    crate::forge::run_capture(Command::new(program).args(args), None).ok()
+}
+
+fn cpu() -> Cpu {
+    let model = if cfg!(target_os = "macos") {
+        output("sysctl", &["-n", "machdep.cpu.brand_string"])
+    } else if cfg!(windows) {
+        std::env::var("PROCESSOR_IDENTIFIER").ok()
+    } else {
+        std::fs::read_to_string("/proc/cpuinfo")
+            .ok()
+            .and_then(|info| {
+                info.lines().find_map(|line| {
+                    let (key, value) = line.split_once(':')?;
+                    (key.trim() == "model name").then(|| value.trim().to_string())
+                })
+            })
+    };
+    Cpu {
+        model,
+        cores: std::thread::available_parallelism().map_or(1, |n| n.get()),
+        arch: std::env::consts::ARCH.to_string(),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn memory() -> Option<Memory> {
+    let info = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let field = |name: &str| {
+        info.lines().find_map(|line| {
+            let kib = line.strip_prefix(name)?.trim().strip_suffix("kB")?;
+            kib.trim().parse::<u64>().ok().map(|kib| kib * 1024)
+        })
+    };
+    Some(Memory {
+        total_bytes: field("MemTotal:")?,
+        available_bytes: field("MemAvailable:"),
+    })
+}
+
+#[cfg(target_os = "macos")]
+fn memory() -> Option<Memory> {
+    Some(Memory {
+        total_bytes: output("sysctl", &["-n", "hw.memsize"])?.parse().ok()?,
+        available_bytes: None,
+    })
+}
+
+#[cfg(windows)]
+fn memory() -> Option<Memory> {
+    use windows_sys::Win32::System::SystemInformation::{GlobalMemoryStatusEx, MEMORYSTATUSEX};
+
+    // SAFETY: the struct is plain data, sized as the call requires.
+    let mut status: MEMORYSTATUSEX = unsafe { std::mem::zeroed() };
+    status.dwLength = std::mem::size_of::<MEMORYSTATUSEX>() as u32;
+    if unsafe { GlobalMemoryStatusEx(&mut status) } == 0 {
+        return None;
+    }
+    Some(Memory {
+        total_bytes: status.ullTotalPhys,
+        available_bytes: Some(status.ullAvailPhys),
+    })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn memory() -> Option<Memory> {
+    None
+}
+
+/// NVIDIA GPUs, from `nvidia-smi`.
+fn nvidia_gpus() -> Vec<Gpu> {
+    let Some(smi) = crate::diagnostics::find_executable("nvidia-smi") else {
+        return Vec::new();
+    };
+    let Ok(csv) = crate::forge::run_capture(
+        Command::new(smi).args([
+            "--query-gpu=name,memory.total,memory.used",
+            "--format=csv,noheader,nounits",
+        ]),
+        None,
+    ) else {
+        return Vec::new();
+    };
+    csv.lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let mib = |i: usize| fields.get(i)?.parse::<u64>().ok().map(|m| m * MIB);
+            Some(Gpu {
+                name: fields.first().filter(|n| !n.is_empty())?.to_string(),
+                vram_bytes: mib(1),
+                vram_used_bytes: mib(2),
+                unified_memory: false,
+            })
+        })
+        .collect()
+}
+
+/// Displays from `system_profiler`: VRAM is given for discrete GPUs, Apple
+/// silicon shares system memory.
+#[cfg(target_os = "macos")]
+fn other_gpus() -> Vec<Gpu> {
+    let Some(json) = output("system_profiler", &["SPDisplaysDataType", "-json"]) else {
+        return Vec::new();
+    };
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap_or_default();
+    let vram = |text: &str| {
+        let (amount, unit) = text.split_once(' ')?;
+        let amount: u64 = amount.parse().ok()?;
+        Some(match unit.trim() {
+            "GB" => amount * 1024 * MIB,
+            _ => amount * MIB,
+        })
+    };
+    value["SPDisplaysDataType"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|gpu| {
+            let name = gpu["sppci_model"].as_str()?.to_string();
+            let vram_bytes = gpu["spdisplays_vram"]
+                .as_str()
+                .or(gpu["spdisplays_vram_shared"].as_str())
+                .and_then(vram);
+            Some(Gpu {
+                unified_memory: name.starts_with("Apple"),
+                vram_bytes: if name.starts_with("Apple") {
+                    None
+                } else {
+                    vram_bytes
+                },
+                vram_used_bytes: None,
+                name,
+            })
+        })
+        .collect()
+}
+
+/// AMD and Intel cards the kernel driver reports VRAM for; NVIDIA ones are
+/// left to `nvidia-smi`.
+#[cfg(target_os = "linux")]
+fn other_gpus() -> Vec<Gpu> {
+    let read = |path: &std::path::Path| {
+        std::fs::read_to_string(path)
+            .ok()
+            .map(|s| s.trim().to_string())
+    };
+    let Ok(cards) = std::fs::read_dir("/sys/class/drm") else {
+        return Vec::new();
+    };
+    let mut cards: Vec<_> = cards
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("card") && !n.contains('-'))
+        })
+        .collect();
+    cards.sort();
+    cards
+        .iter()
+        .filter_map(|card| {
+            let device = card.join("device");
+            let vendor = read(&device.join("vendor"))?;
+            let name = match vendor.as_str() {
+                "0x1002" => "AMD GPU",
+                "0x8086" => "Intel GPU",
+                _ => return None,
+            };
+            let bytes = |file: &str| read(&device.join(file))?.parse::<u64>().ok();
+            Some(Gpu {
+                name: read(&device.join("product_name")).unwrap_or_else(|| name.to_string()),
+                vram_bytes: bytes("mem_info_vram_total"),
+                vram_used_bytes: bytes("mem_info_vram_used"),
+                unified_memory: false,
+            })
+        })
+        .collect()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn other_gpus() -> Vec<Gpu> {
+    Vec::new()
+}
+
+fn ollama(agent: &ureq::Agent) -> ModelServer {
+    let tags = agent
+        .get(&format!("{OLLAMA_URL}/api/tags"))
+        .call()
+        .ok()
+        .and_then(|response| response.into_json::<serde_json::Value>().ok());
+    let models = tags
+        .as_ref()
+        .and_then(|tags| tags["models"].as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|model| {
+            Some(LocalModel {
+                name: model["name"].as_str()?.to_string(),
+                size_bytes: model["size"].as_u64(),
+            })
+        })
+        .collect();
+    ModelServer {
+        name: "ollama".to_string(),
+        url: OLLAMA_URL.to_string(),
+        installed: crate::diagnostics::find_executable("ollama").is_some(),
+        running: tags.is_some(),
+        models,
+    }
+}
+
+fn llama_cpp(agent: &ureq::Agent) -> ModelServer {
+    // `/health` answers 503 while the model loads, which still means running.
+    let running = match agent.get(&format!("{LLAMA_CPP_URL}/health")).call() {
+        Ok(_) => true,
+        Err(ureq::Error::Status(code, _)) => code == 503,
+        Err(_) => false,
+    };
+    ModelServer {
+        name: "llama.cpp".to_string(),
+        url: LLAMA_CPP_URL.to_string(),
+        installed: crate::diagnostics::find_executable("llama-server").is_some(),
+        running,
+        models: Vec::new(),
+    }
+}
+
+/// CPU, memory and GPUs of this machine and which local model servers
+/// (ollama, llama.cpp's `llama-server`) are up on their default ports, to
+/// check an agent profile that uses a local model before launching it.
+#[tauri::command]
+pub async fn system_capabilities(window: WebviewWindow) -> Result<SystemCapabilities, String> {
+    crate::require_app_window(&window)?;
+    tauri::async_runtime::spawn_blocking(|| {
+        let agent = ureq::AgentBuilder::new().timeout(SERVER_TIMEOUT).build();
+        let mut gpus = nvidia_gpus();
+        gpus.extend(other_gpus());
+        Ok(SystemCapabilities {
+            cpu: cpu(),
+            memory: memory(),
+            gpus,
+            servers: vec![ollama(&agent), llama_cpp(&agent)],
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,410 @@
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+use crate::audit::{self, AuditEvent, AuditKind, Outcome};
+use crate::forge::run_capture;
+use crate::sandbox::{Sandbox, WORKTREE_DIR_NAME};
+
+/// The first cleanup waits this long after startup, out of the way of
+/// opening projects.
+const FIRST_DELAY: Duration = Duration::from_secs(10 * 60);
+const INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// How much old work to keep. Unset limits keep everything.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RetentionSettings {
+    /// Remove thread worktrees whose branch is merged and that saw no
+    /// commits or checkouts for this many days. Worktrees with uncommitted
+    /// changes are always kept.
+    pub merged_worktree_days: Option<u32>,
+    /// Drop the oldest messages of finished runs' transcripts past this
+    /// size, keeping the first, the prompt.
+    pub max_transcript_mb: Option<u32>,
+    /// Crash reports to keep, newest first.
+    pub max_crash_reports: Option<u32>,
+}
+
+impl Default for RetentionSettings {
+    fn default() -> Self {
+        Self {
+            merged_worktree_days: None,
+            max_transcript_mb: None,
+            max_crash_reports: Some(50),
+        }
+    }
+}
+
+pub(crate) fn validate_settings(settings: &RetentionSettings) -> Result<(), String> {
+    let limits = [
+        ("mergedWorktreeDays", settings.merged_worktree_days),
+        ("maxTranscriptMb", settings.max_transcript_mb),
+        ("maxCrashReports", settings.max_crash_reports),
+    ];
+    for (name, limit) in limits {
+        if limit == Some(0) {
+            return Err(format!("retention {name} must be at least 1, or unset"));
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StaleWorktree {
+    pub project: String,
+    pub path: String,
+    pub branch: String,
+    /// RFC 3339; the later of the last commit and the last checkout.
+    pub last_active: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OversizedTranscript {
+    pub run_id: String,
+    pub title: Option<String>,
+    pub bytes: u64,
+    /// What trimming it frees.
+    pub freed_bytes: u64,
+    /// Messages after the first, up to and including this one, are dropped.
+    #[serde(skip)]
+    cutoff: i64,
+    #[serde(skip)]
+    first: i64,
+}
+
+/// What a cleanup removes under the current retention settings.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CleanupPlan {
+    pub worktrees: Vec<StaleWorktree>,
+    pub transcripts: Vec<OversizedTranscript>,
+    pub crash_reports: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CleanupReport {
+    /// What was removed; failures are left out and listed in `errors`.
+    pub removed: CleanupPlan,
+    pub errors: Vec<String>,
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<String, String> {
+    // Cleanup runs unattended; it must not rewrite indexes or take locks
+    // a user's git command would trip over.
+    run_capture(
+        Command::new("git")
+            .arg("--no-optional-locks")
+            .args(args)
+            .current_dir(dir),
+        None,
+    )
+}
+
+fn unix_seconds(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// When `worktree` last got a commit or had its index written.
+fn last_active(worktree: &Path) -> Option<i64> {
+    let committed = git(worktree, &["log", "-1", "--format=%ct"])
+        .ok()
+        .and_then(|out| out.trim().parse::<i64>().ok());
+    let indexed = git(worktree, &["rev-parse", "--git-path", "index"])
+        .ok()
+        .and_then(|index| std::fs::metadata(worktree.join(index.trim())).ok())
+        .and_then(|meta| meta.modified().ok())
+        .map(unix_seconds);
+    committed.max(indexed)
+}
+
+/// `(path, branch ref)` of the worktrees of `root` that aren't locked.
+fn list_worktrees(root: &Path) -> Result<Vec<(PathBuf, String)>, String> {
+    let porcelain = git(root, &["worktree", "list", "--porcelain"])?;
+    let mut worktrees = Vec::new();
+    for block in porcelain.split("\n\n") {
+        let mut path = None;
+        let mut branch = None;
+        let mut locked = false;
+        for line in block.lines() {
+            if let Some(p) = line.strip_prefix("worktree ") {
+                path = Some(PathBuf::from(p));
+            } else if let Some(b) = line.strip_prefix("branch ") {
+                branch = Some(b.to_string());
+            } else if line == "locked" || line.starts_with("locked ") {
+                locked = true;
+            }
+        }
+        if let (Some(path), Some(branch), false) = (path, branch, locked) {
+            worktrees.push((path, branch));
+        }
+    }
+    Ok(worktrees)
+}
+
+/// Clean worktrees of `root`'s threads, merged into its checked-out branch
+/// and idle for `days`.
+fn stale_worktrees(root: &Path, days: u32) -> Result<Vec<StaleWorktree>, String> {
+    let base = match (root.parent(), root.file_name()) {
+        (Some(parent), Some(name)) => parent.join(WORKTREE_DIR_NAME).join(name),
+        _ => return Ok(Vec::new()),
+    };
+    let base = match std::fs::canonicalize(&base) {
+        Ok(base) => base,
+        Err(_) => return Ok(Vec::new()),
+    };
+    let merged: HashSet<String> =
+        git(root, &["branch", "--merged", "HEAD", "--format=%(refname)"])?
+            .lines()
+            .map(str::to_string)
+            .collect();
+    let cutoff = unix_seconds(SystemTime::now()) - i64::from(days) * 24 * 60 * 60;
+    let mut stale = Vec::new();
+    for (path, branch) in list_worktrees(root)? {
+        // Missing worktrees fail here; `git worktree prune` is for those.
+        let path = match std::fs::canonicalize(&path) {
+            Ok(path) if path.starts_with(&base) => path,
+            _ => continue,
+        };
+        if !merged.contains(&branch) {
+            continue;
+        }
+        let active = match last_active(&path) {
+            Some(active) if active < cutoff => active,
+            _ => continue,
+        };
+        let clean = git(&path, &["status", "--porcelain"]).is_ok_and(|s| s.trim().is_empty());
+        if !clean {
+            continue;
+        }
+        stale.push(StaleWorktree {
+            project: root.to_string_lossy().to_string(),
+            path: path.to_string_lossy().to_string(),
+            branch: branch
+                .strip_prefix("refs/heads/")
+                .unwrap_or(&branch)
+                .to_string(),
+            last_active: chrono::DateTime::from_timestamp(active, 0)
+                .map(|t| t.to_rfc3339())
+                .unwrap_or_default(),
+        });
+    }
+    Ok(stale)
+}
+
+/// Where to cut run `run_id`'s transcript to get it under `max_bytes`.
+fn trim_point(
+    conn: &Connection,
+    run_id: &str,
+    max_bytes: u64,
+) -> rusqlite::Result<Option<(i64, i64, u64)>> {
+    let mut statement = conn.prepare(
+        "SELECT seq, length(CAST(content AS BLOB)) FROM transcript_entries
+         WHERE run_id = ?1 ORDER BY seq",
+    )?;
+    let entries: Vec<(i64, u64)> = statement
+        .query_map(params![run_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    let first = match entries.first() {
+        Some((seq, _)) => *seq,
+        None => return Ok(None),
+    };
+    let mut total: u64 = entries.iter().map(|(_, bytes)| bytes).sum();
+    let mut cut = None;
+    let mut freed = 0;
+    for (seq, bytes) in &entries[1..] {
+        if total <= max_bytes {
+            break;
+        }
+        total -= bytes;
+        freed += bytes;
+        cut = Some(*seq);
+    }
+    Ok(cut.map(|cutoff| (first, cutoff, freed)))
+}
+
+fn oversized_transcripts(app: &AppHandle, max_mb: u32) -> Result<Vec<OversizedTranscript>, String> {
+    let max_bytes = u64::from(max_mb) * 1024 * 1024;
+    crate::db::with(app, |conn| {
+        let mut statement = conn.prepare(
+            "SELECT r.id, r.title, SUM(length(CAST(t.content AS BLOB))) AS bytes
+             FROM transcript_entries t JOIN agent_runs r ON r.id = t.run_id
+             WHERE r.finished_at IS NOT NULL
+             GROUP BY r.id HAVING bytes > ?1
+             ORDER BY bytes DESC",
+        )?;
+        let runs: Vec<(String, Option<String>, u64)> = statement
+            .query_map(params![max_bytes], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        let mut oversized = Vec::new();
+        for (run_id, title, bytes) in runs {
+            if let Some((first, cutoff, freed_bytes)) = trim_point(conn, &run_id, max_bytes)? {
+                oversized.push(OversizedTranscript {
+                    run_id,
+                    title,
+                    bytes,
+                    freed_bytes,
+                    cutoff,
+                    first,
+                });
+            }
+        }
+        Ok(oversized)
+    })
+}
+
+fn plan(app: &AppHandle, settings: &RetentionSettings) -> Result<CleanupPlan, String> {
+    let mut plan = CleanupPlan::default();
+    if let Some(days) = settings.merged_worktree_days {
+        for project in app.state::<Sandbox>().projects() {
+            match stale_worktrees(&project, days) {
+                Ok(stale) => plan.worktrees.extend(stale),
+                Err(e) => tracing::debug!("no worktree cleanup for {}: {e}", project.display()),
+            }
+        }
+    }
+    if let Some(max_mb) = settings.max_transcript_mb {
+        plan.transcripts = oversized_transcripts(app, max_mb)?;
+    }
+    if let Some(keep) = settings.max_crash_reports {
+        plan.crash_reports = crate::crash::summaries(app)?
+            .into_iter()
+            .skip(keep as usize)
+            .map(|report| report.id)
+            .collect();
+    }
+    Ok(plan)
+}
+
+fn remove_worktree(app: &AppHandle, worktree: &StaleWorktree) -> Result<(), String> {
+    // Without --force git refuses if changes were made since the plan.
+    let result = git(
+        Path::new(&worktree.project),
+        &["worktree", "remove", &worktree.path],
+    );
+    audit::record(
+        app,
+        AuditEvent {
+            kind: AuditKind::GitMutation,
+            agent: None,
+            project: Some(&worktree.project),
+            target: &worktree.path,
+            outcome: if result.is_ok() {
+                Outcome::Ok
+            } else {
+                Outcome::Error
+            },
+            detail: Some(format!(
+                "removed merged worktree of {} by retention policy",
+                worktree.branch
+            )),
+        },
+    );
+    result.map(|_| ())
+}
+
+fn trim_transcript(app: &AppHandle, transcript: &OversizedTranscript) -> Result<(), String> {
+    crate::db::with(app, |conn| {
+        conn.execute(
+            "DELETE FROM transcript_entries WHERE run_id = ?1 AND seq > ?2 AND seq <= ?3",
+            params![transcript.run_id, transcript.first, transcript.cutoff],
+        )
+    })
+    .map(|_| ())
+}
+
+/// Remove what `plan` finds. Each item is removed on its own, so one
+/// failure doesn't stop the rest.
+fn run(app: &AppHandle, settings: &RetentionSettings) -> Result<CleanupReport, String> {
+    let planned = plan(app, settings)?;
+    let mut removed = CleanupPlan::default();
+    let mut errors = Vec::new();
+    for worktree in planned.worktrees {
+        match remove_worktree(app, &worktree) {
+            Ok(()) => removed.worktrees.push(worktree),
+            Err(e) => errors.push(format!("{}: {e}", worktree.path)),
+        }
+    }
+    for transcript in planned.transcripts {
+        match trim_transcript(app, &transcript) {
+            Ok(()) => removed.transcripts.push(transcript),
+            Err(e) => errors.push(format!("transcript {}: {e}", transcript.run_id)),
+        }
+    }
+    for id in planned.crash_reports {
+        match crate::crash::delete(app, &id) {
+            Ok(()) => removed.crash_reports.push(id),
+            Err(e) => errors.push(e),
+        }
+    }
+    Ok(CleanupReport { removed, errors })
+}
+
+fn log_report(report: &CleanupReport) {
+    let removed = &report.removed;
+    let freed: u64 = removed.transcripts.iter().map(|t| t.freed_bytes).sum();
+    let any = !removed.worktrees.is_empty()
+        || !removed.transcripts.is_empty()
+        || !removed.crash_reports.is_empty();
+    if any {
+        tracing::info!(
+            "cleanup removed {} worktrees, {} crash reports and {freed} bytes of transcripts",
+            removed.worktrees.len(),
+            removed.crash_reports.len(),
+        );
+    }
+    for error in &report.errors {
+        tracing::warn!("cleanup: {error}");
+    }
+}
+
+/// Enforce the retention settings every few hours. Called once from `setup`.
+pub fn init(app: &AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(FIRST_DELAY);
+        loop {
+            let settings = crate::settings::current(&app).retention;
+            match run(&app, &settings) {
+                Ok(report) => log_report(&report),
+                Err(e) => tracing::warn!("cleanup failed: {e}"),
+            }
+            std::thread::sleep(INTERVAL);
+        }
+    });
+}
+
+/// What the next cleanup would remove, without removing anything.
+#[tauri::command]
+pub async fn cleanup_preview(window: WebviewWindow, app: AppHandle) -> Result<CleanupPlan, String> {
+    crate::require_app_window(&window)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        plan(&app, &crate::settings::current(&app).retention)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Clean up now rather than at the next scheduled run.
+#[tauri::command]
+pub async fn cleanup_run(window: WebviewWindow, app: AppHandle) -> Result<CleanupReport, String> {
+    crate::require_window(&window, "main")?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let report = run(&app, &crate::settings::current(&app).retention)?;
+        log_report(&report);
+        Ok(report)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
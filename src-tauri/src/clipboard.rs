@@ -0,0 +1,253 @@
+use base64::Engine;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State, WebviewWindow};
+
+/// Entries kept, newest first; older ones are dropped.
+const MAX_ENTRIES: usize = 50;
+/// Longer copies are cut to this many bytes.
+const MAX_ENTRY_BYTES: usize = 256 * 1024;
+/// Oldest entries are dropped once all of them together are larger.
+const MAX_TOTAL_BYTES: usize = 4 * 1024 * 1024;
+/// OSC 52 payloads (base64) longer than this are ignored.
+const MAX_OSC52_BYTES: usize = MAX_ENTRY_BYTES * 4 / 3 + 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ClipboardSource {
+    /// Copied from a terminal's selection.
+    Copy,
+    /// Set by a program in the terminal with OSC 52.
+    Osc52,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClipboardEntry {
+    pub id: u64,
+    pub text: String,
+    pub source: ClipboardSource,
+    pub terminal: Option<String>,
+    /// RFC 3339.
+    pub copied_at: String,
+    /// Looks like it holds a stored secret or an API token.
+    pub sensitive: bool,
+    /// Cut to `MAX_ENTRY_BYTES`.
+    pub truncated: bool,
+}
+
+/// Recent copies from terminals. Kept in memory only: the history is gone
+/// when the app quits.
+pub struct ClipboardHistory {
+    entries: Mutex<VecDeque<ClipboardEntry>>,
+    next_id: AtomicU64,
+}
+
+impl ClipboardHistory {
+    pub fn new() -> Self {
+        Self {
+            entries: Mutex::new(VecDeque::new()),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Entries kept and the bytes of their text.
+    pub(crate) fn usage(&self) -> (usize, usize) {
+        self.entries
+            .lock()
+            .map(|entries| (entries.len(), entries.iter().map(|e| e.text.len()).sum()))
+            .unwrap_or_default()
+    }
+}
+
+/// Add a copy to the history. Copying the newest entry again moves nothing.
+pub(crate) fn record(
+    app: &AppHandle,
+    terminal: Option<&str>,
+    source: ClipboardSource,
+    mut text: String,
+) {
+    if text.is_empty() {
+        return;
+    }
+    let truncated = text.len() > MAX_ENTRY_BYTES;
+    if truncated {
+        let mut end = MAX_ENTRY_BYTES;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+    }
+    let state = match app.try_state::<ClipboardHistory>() {
+        Some(state) => state,
+        None => return,
+    };
+    let mut entries = match state.entries.lock() {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    if entries.front().is_some_and(|newest| newest.text == text) {
+        return;
+    }
+    let sensitive = crate::redact::redact(&text) != text;
+    entries.push_front(ClipboardEntry {
+        id: state.next_id.fetch_add(1, Ordering::Relaxed),
+        text,
+        source,
+        terminal: terminal.map(str::to_string),
+        copied_at: chrono::Utc::now().to_rfc3339(),
+        sensitive,
+        truncated,
+    });
+    entries.truncate(MAX_ENTRIES);
+    let mut total = 0;
+    let keep = entries
+        .iter()
+        .take_while(|entry| {
+            total += entry.text.len();
+            total <= MAX_TOTAL_BYTES
+        })
+        .count()
+        .max(1);
+    entries.truncate(keep);
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+enum Osc52State {
+    #[default]
+    Text,
+    Escape,
+    /// Inside an OSC, collecting until BEL or ST.
+    Osc,
+    /// `ESC` inside an OSC, possibly the start of ST.
+    OscEscape,
+}
+
+/// Picks OSC 52 clipboard writes (`ESC ] 52 ; c ; <base64> BEL`) out of raw
+/// terminal output, including ones split across reads.
+#[derive(Default)]
+pub(crate) struct Osc52Parser {
+    state: Osc52State,
+    osc: Vec<u8>,
+    overflow: bool,
+}
+
+impl Osc52Parser {
+    /// Texts of the clipboard writes completed by `bytes`.
+    pub(crate) fn feed(&mut self, bytes: &[u8]) -> Vec<String> {
+        let mut texts = Vec::new();
+        for &b in bytes {
+            self.state = match (self.state, b) {
+                (Osc52State::Text, 0x1b) => Osc52State::Escape,
+                (Osc52State::Text, _) => Osc52State::Text,
+                (Osc52State::Escape, b']') => {
+                    self.osc.clear();
+                    self.overflow = false;
+                    Osc52State::Osc
+                }
+                (Osc52State::Escape, 0x1b) => Osc52State::Escape,
+                (Osc52State::Escape, _) => Osc52State::Text,
+                (Osc52State::Osc, 0x07) | (Osc52State::OscEscape, b'\\') => {
+                    texts.extend(self.finish_osc());
+                    Osc52State::Text
+                }
+                (Osc52State::Osc, 0x1b) => Osc52State::OscEscape,
+                (Osc52State::Osc, _) => {
+                    // Only OSC 52 is worth buffering.
+                    if !self.overflow && (self.osc.len() >= 3 || b"52;"[self.osc.len()] == b) {
+                        if self.osc.len() < MAX_OSC52_BYTES {
+                            self.osc.push(b);
+                        } else {
+                            self.overflow = true;
+                        }
+                    } else {
+                        self.overflow = true;
+                    }
+                    Osc52State::Osc
+                }
+                // An ESC that doesn't end the OSC starts a new sequence.
+                (Osc52State::OscEscape, b']') => {
+                    self.osc.clear();
+                    self.overflow = false;
+                    Osc52State::Osc
+                }
+                (Osc52State::OscEscape, _) => Osc52State::Text,
+            };
+        }
+        texts
+    }
+
+    fn finish_osc(&mut self) -> Option<String> {
+        if self.overflow {
+            return None;
+        }
+        let osc = std::mem::take(&mut self.osc);
+        let rest = osc.strip_prefix(b"52;")?;
+        let (_selection, data) = rest.split_at(rest.iter().position(|&b| b == b';')?);
+        let data = &data[1..];
+        // `?` asks to read the clipboard, which isn't a copy.
+        if data == b"?" {
+            return None;
+        }
+        let decoded = base64::engine::general_purpose::STANDARD
+            .decode(data)
+            .ok()?;
+        String::from_utf8(decoded).ok()
+    }
+}
+
+/// Note something the user copied from terminal `terminal`. The frontend
+/// writes the system clipboard itself; this only keeps the history.
+#[tauri::command]
+pub fn clipboard_record(
+    window: WebviewWindow,
+    app: AppHandle,
+    terminal: Option<String>,
+    text: String,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    record(&app, terminal.as_deref(), ClipboardSource::Copy, text);
+    Ok(())
+}
+
+/// Recent copies from terminals and OSC 52 writes, newest first. Entries
+/// flagged `sensitive` have their secrets masked unless `include_sensitive`
+/// is set.
+#[tauri::command]
+pub fn clipboard_history(
+    window: WebviewWindow,
+    state: State<'_, ClipboardHistory>,
+    include_sensitive: Option<bool>,
+) -> Result<Vec<ClipboardEntry>, String> {
+    crate::require_app_window(&window)?;
+    let include_sensitive = include_sensitive.unwrap_or(false);
+    let entries = state.entries.lock().map_err(|e| e.to_string())?;
+    Ok(entries
+        .iter()
+        .map(|entry| {
+            let mut entry = entry.clone();
+            if entry.sensitive && !include_sensitive {
+                entry.text = crate::redact::redact(&entry.text);
+            }
+            entry
+        })
+        .collect())
+}
+
+/// Forget every entry, or just `id`.
+#[tauri::command]
+pub fn clipboard_history_clear(
+    window: WebviewWindow,
+    state: State<'_, ClipboardHistory>,
+    id: Option<u64>,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    let mut entries = state.entries.lock().map_err(|e| e.to_string())?;
+    match id {
+        Some(id) => entries.retain(|entry| entry.id != id),
+        None => entries.clear(),
+    }
+    Ok(())
+}
@@ -0,0 +1,311 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+use crate::sandbox::Sandbox;
+
+/// At most this many candidates are returned; the palette only shows a few.
+const MAX_CANDIDATES: usize = 200;
+
+/// How long the list of executables on PATH is reused before rescanning.
+const EXECUTABLES_TTL: Duration = Duration::from_secs(30);
+
+/// Porcelain commands offered after `git`, besides the user's aliases.
+const GIT_SUBCOMMANDS: &[&str] = &[
+    "add",
+    "bisect",
+    "blame",
+    "branch",
+    "checkout",
+    "cherry-pick",
+    "clean",
+    "clone",
+    "commit",
+    "config",
+    "diff",
+    "fetch",
+    "grep",
+    "init",
+    "log",
+    "merge",
+    "mv",
+    "pull",
+    "push",
+    "rebase",
+    "reflog",
+    "remote",
+    "reset",
+    "restore",
+    "revert",
+    "rm",
+    "show",
+    "stash",
+    "status",
+    "switch",
+    "tag",
+    "worktree",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CandidateKind {
+    Executable,
+    Directory,
+    File,
+    GitSubcommand,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Candidate {
+    /// Replaces the word being completed. Directories end in `/`.
+    pub value: String,
+    pub kind: CandidateKind,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Completions {
+    /// Where the word being completed starts in `line`, in UTF-16 code units;
+    /// a candidate replaces everything from there to the end.
+    pub start: u32,
+    pub candidates: Vec<Candidate>,
+}
+
+/// Caches the executables found on PATH, which is slow to scan on every
+/// keystroke.
+pub struct Completer {
+    executables: Mutex<Option<(Instant, Arc<Vec<String>>)>>,
+}
+
+impl Completer {
+    pub fn new() -> Self {
+        Self {
+            executables: Mutex::new(None),
+        }
+    }
+
+    fn executables(&self) -> Arc<Vec<String>> {
+        if let Ok(cached) = self.executables.lock() {
+            if let Some((scanned, names)) = cached.as_ref() {
+                if scanned.elapsed() < EXECUTABLES_TTL {
+                    return names.clone();
+                }
+            }
+        }
+        let names = Arc::new(scan_path());
+        if let Ok(mut cached) = self.executables.lock() {
+            *cached = Some((Instant::now(), names.clone()));
+        }
+        names
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path)
+        .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+    path.is_file() && matches!(extension.as_deref(), Some("exe" | "cmd" | "bat"))
+}
+
+/// Names of the executables on PATH, sorted and deduplicated. Windows names
+/// drop their extension, as they're typed.
+fn scan_path() -> Vec<String> {
+    let mut names = Vec::new();
+    for dir in crate::diagnostics::executable_dirs() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if !is_executable(&path) {
+                continue;
+            }
+            let name = if cfg!(windows) {
+                path.file_stem()
+            } else {
+                path.file_name()
+            };
+            if let Some(name) = name {
+                names.push(name.to_string_lossy().to_string());
+            }
+        }
+    }
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Byte offset where the command containing the end of `line` starts.
+fn segment_start(line: &str) -> usize {
+    line.rfind(['|', '&', ';', '(', '`'])
+        .map(|i| i + 1)
+        .unwrap_or(0)
+}
+
+/// Backslash-escape characters the shell would split or expand.
+fn escape(name: &str) -> String {
+    let mut escaped = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c.is_whitespace() || "'\"\\$`&|;()<>*?![]{}#~".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Entries of the directory `word` points into whose names start with the
+/// rest of `word`. Directories outside the registered projects are not
+/// listed.
+fn complete_paths(
+    sandbox: &Sandbox,
+    cwd: &Path,
+    word: &str,
+    executables_only: bool,
+) -> Vec<Candidate> {
+    let (dir_part, prefix) = match word.rfind('/') {
+        Some(i) => word.split_at(i + 1),
+        None => ("", word),
+    };
+    let dir = match dir_part.strip_prefix("~/") {
+        Some(rest) => match std::env::var_os("HOME") {
+            Some(home) => PathBuf::from(home).join(rest),
+            None => return Vec::new(),
+        },
+        None if dir_part.is_empty() => cwd.to_path_buf(),
+        None => cwd.join(dir_part),
+    };
+    let dir = match sandbox.check_dir(&dir.to_string_lossy()) {
+        Ok(dir) => dir,
+        Err(_) => return Vec::new(),
+    };
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut candidates = Vec::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+            continue;
+        }
+        let path = entry.path();
+        let (kind, suffix) = if path.is_dir() {
+            (CandidateKind::Directory, "/")
+        } else if executables_only && !is_executable(&path) {
+            continue;
+        } else {
+            (CandidateKind::File, "")
+        };
+        candidates.push(Candidate {
+            value: format!("{dir_part}{}{suffix}", escape(&name)),
+            kind,
+        });
+    }
+    candidates
+}
+
+/// The user's git aliases, as configured for `cwd`.
+fn git_aliases(cwd: &Path) -> Vec<String> {
+    crate::forge::run_capture(
+        Command::new("git")
+            .args(["config", "--get-regexp", r"^alias\."])
+            .current_dir(cwd),
+        None,
+    )
+    .map(|output| {
+        output
+            .lines()
+            .filter_map(|line| line.split_whitespace().next())
+            .filter_map(|key| key.strip_prefix("alias."))
+            .map(str::to_string)
+            .collect()
+    })
+    .unwrap_or_default()
+}
+
+fn complete(app: &AppHandle, cwd: &Path, line: &str) -> Completions {
+    let segment = &line[segment_start(line)..];
+    let word_start = segment
+        .rfind(char::is_whitespace)
+        .map(|i| i + segment[i..].chars().next().map_or(1, char::len_utf8))
+        .unwrap_or(0);
+    let word = &segment[word_start..];
+    // Leading `FOO=bar` assignments aren't the command.
+    let before: Vec<&str> = segment[..word_start]
+        .split_whitespace()
+        .skip_while(|w| w.contains('=') && !w.starts_with('='))
+        .collect();
+
+    let sandbox = app.state::<Sandbox>();
+    let mut candidates = if before.is_empty() {
+        if word.contains('/') {
+            complete_paths(&sandbox, cwd, word, true)
+        } else {
+            app.state::<Completer>()
+                .executables()
+                .iter()
+                .filter(|name| name.starts_with(word))
+                .map(|name| Candidate {
+                    value: name.clone(),
+                    kind: CandidateKind::Executable,
+                })
+                .collect()
+        }
+    } else if before[0] == "git"
+        && before[1..].iter().all(|w| w.starts_with('-'))
+        && !word.starts_with('-')
+    {
+        GIT_SUBCOMMANDS
+            .iter()
+            .map(|name| name.to_string())
+            .chain(git_aliases(cwd))
+            .filter(|name| name.starts_with(word))
+            .map(|value| Candidate {
+                value,
+                kind: CandidateKind::GitSubcommand,
+            })
+            .collect()
+    } else {
+        complete_paths(&sandbox, cwd, word, false)
+    };
+    candidates.sort_by(|a, b| a.value.cmp(&b.value));
+    candidates.dedup_by(|a, b| a.value == b.value);
+    candidates.truncate(MAX_CANDIDATES);
+
+    let start = line.len() - word.len();
+    Completions {
+        start: line[..start].encode_utf16().count() as u32,
+        candidates,
+    }
+}
+
+/// Completions for the last word of `line`, a partial command line run in
+/// `cwd`: executables on PATH for the command, git subcommands and aliases
+/// after `git`, and file paths otherwise.
+#[tauri::command]
+pub async fn shell_complete(
+    window: WebviewWindow,
+    app: AppHandle,
+    line: String,
+    cwd: String,
+) -> Result<Completions, String> {
+    crate::require_app_window(&window)?;
+    let cwd = app.state::<Sandbox>().check_dir(&cwd)?;
+    tauri::async_runtime::spawn_blocking(move || complete(&app, &cwd, &line))
+        .await
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,121 @@
+use portable_pty::CommandBuilder;
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::path::Path;
+use tauri::WebviewWindow;
+
+/// How tightly an agent's shell is confined. Set on the agent defaults, per
+/// project or globally, and passed to `pty_spawn`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ShellSandbox {
+    #[default]
+    Off,
+    /// Reads anywhere; writes only in the working directory and temp.
+    Project,
+    /// `Project`, and no network beyond localhost.
+    ProjectOffline,
+}
+
+/// Seatbelt profile for `sandbox-exec`. `PROJECT` is passed with `-D`.
+#[cfg(target_os = "macos")]
+const SEATBELT_PROFILE: &str = r#"(version 1)
+(allow default)
+(deny file-write*)
+(allow file-write*
+  (subpath (param "PROJECT"))
+  (subpath "/private/tmp")
+  (subpath "/private/var/folders")
+  (literal "/dev/null")
+  (regex #"^/dev/tty"))
+"#;
+
+#[cfg(target_os = "macos")]
+const SEATBELT_OFFLINE: &str = r#"(deny network*)
+(allow network* (remote unix-socket))
+(allow network* (remote ip "localhost:*"))
+"#;
+
+/// The tool that enforces `ShellSandbox` here, if there is one.
+fn launcher() -> Option<&'static str> {
+    if cfg!(target_os = "macos") {
+        Some("sandbox-exec")
+    } else if cfg!(target_os = "linux") {
+        crate::diagnostics::find_executable("bwrap").map(|_| "bwrap")
+    } else {
+        None
+    }
+}
+
+/// Prefix `command` so it runs confined to `project`. Fails rather than
+/// running unconfined when the platform has no way to enforce `mode`.
+pub(crate) fn wrap(
+    mode: ShellSandbox,
+    project: &Path,
+    command: &mut CommandBuilder,
+) -> Result<(), String> {
+    if mode == ShellSandbox::Off {
+        return Ok(());
+    }
+    let launcher = launcher().ok_or_else(|| {
+        if cfg!(target_os = "linux") {
+            "shell sandbox needs bubblewrap (bwrap) installed".to_string()
+        } else {
+            "shell sandbox is not supported on this platform".to_string()
+        }
+    })?;
+    let project = project.as_os_str().to_os_string();
+    let mut prefix: Vec<OsString> = vec![launcher.into()];
+
+    #[cfg(target_os = "macos")]
+    {
+        let mut profile = SEATBELT_PROFILE.to_string();
+        if mode == ShellSandbox::ProjectOffline {
+            profile.push_str(SEATBELT_OFFLINE);
+        }
+        let mut define = OsString::from("PROJECT=");
+        define.push(&project);
+        prefix.extend(["-D".into(), define, "-p".into(), profile.into()]);
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        for arg in ["--die-with-parent", "--ro-bind", "/", "/"] {
+            prefix.push(arg.into());
+        }
+        for arg in ["--dev-bind", "/dev", "/dev", "--proc", "/proc"] {
+            prefix.push(arg.into());
+        }
+        prefix.extend(["--tmpfs".into(), "/tmp".into()]);
+        prefix.extend(["--bind".into(), project.clone(), project.clone()]);
+        if mode == ShellSandbox::ProjectOffline {
+            prefix.push("--unshare-net".into());
+        }
+        prefix.extend(["--chdir".into(), project, "--".into()]);
+    }
+
+    let argv = command.get_argv_mut();
+    prefix.append(argv);
+    *argv = prefix;
+    tracing::info!(target: "agents", "confining shell with {launcher} ({mode:?})");
+    Ok(())
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShellSandboxSupport {
+    pub supported: bool,
+    /// `sandbox-exec` or `bwrap`.
+    pub launcher: Option<String>,
+}
+
+/// Whether agent shells can be confined here, for greying out the option.
+#[tauri::command]
+pub fn shell_sandbox_support(window: WebviewWindow) -> Result<ShellSandboxSupport, String> {
+    crate::require_app_window(&window)?;
+    let launcher = launcher();
+    Ok(ShellSandboxSupport {
+        supported: launcher.is_some(),
+        launcher: launcher.map(str::to_string),
+    })
+}
@@ -0,0 +1,235 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::menu::{Menu, MenuItem, PredefinedMenuItem};
+use tauri::{AppHandle, Emitter, Manager, State, WebviewWindow, Wry};
+
+use crate::pty::PtyManager;
+use crate::sandbox::Sandbox;
+
+const MENU_ID_PREFIX: &str = "context:";
+
+#[cfg(target_os = "macos")]
+const REVEAL_LABEL: &str = "Reveal in Finder";
+#[cfg(windows)]
+const REVEAL_LABEL: &str = "Show in Explorer";
+#[cfg(not(any(target_os = "macos", windows)))]
+const REVEAL_LABEL: &str = "Open Containing Folder";
+
+/// What a context menu was opened on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum MenuTarget {
+    /// A terminal tab.
+    #[serde(rename_all = "camelCase")]
+    Terminal { id: String },
+    /// A task card: the agent run and its worktree.
+    #[serde(rename_all = "camelCase")]
+    Task {
+        run_id: String,
+        path: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MenuAction {
+    Kill,
+    Restart,
+    Reveal,
+    CopyPath,
+}
+
+impl MenuAction {
+    const ALL: [MenuAction; 4] = [
+        MenuAction::Kill,
+        MenuAction::Restart,
+        MenuAction::Reveal,
+        MenuAction::CopyPath,
+    ];
+
+    fn id(self) -> &'static str {
+        match self {
+            MenuAction::Kill => "context:kill",
+            MenuAction::Restart => "context:restart",
+            MenuAction::Reveal => "context:reveal",
+            MenuAction::CopyPath => "context:copy-path",
+        }
+    }
+}
+
+/// A picked action the window carries out itself: the server owns agents
+/// and the frontend owns terminal views and the clipboard.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextMenuAction {
+    pub action: MenuAction,
+    pub target: MenuTarget,
+    /// The terminal's directory or the task's worktree, for `copyPath`.
+    pub path: Option<String>,
+}
+
+struct Shown {
+    window: String,
+    target: MenuTarget,
+    path: Option<PathBuf>,
+}
+
+/// The context menu last shown, which menu clicks act on.
+pub struct ContextMenus {
+    shown: Mutex<Option<Shown>>,
+}
+
+impl ContextMenus {
+    pub fn new() -> Self {
+        Self {
+            shown: Mutex::new(None),
+        }
+    }
+}
+
+/// Action, label and whether it applies.
+type MenuItems = Vec<(MenuAction, &'static str, bool)>;
+
+/// The menu's items above the path ones, and the path they act on.
+fn entries(
+    app: &AppHandle,
+    window: &WebviewWindow,
+    target: &MenuTarget,
+) -> Result<(MenuItems, Option<PathBuf>), String> {
+    match target {
+        MenuTarget::Terminal { id } => {
+            let terminal = crate::pty::terminal_state(&app.state::<PtyManager>(), window, id);
+            let path = terminal.as_ref().map(|t| t.cwd.clone());
+            let kill = if terminal.as_ref().is_some_and(|t| t.busy) {
+                "Kill Running Command"
+            } else {
+                "Kill Terminal"
+            };
+            Ok((
+                vec![
+                    (MenuAction::Kill, kill, terminal.is_some()),
+                    (
+                        MenuAction::Restart,
+                        "Restart Shell",
+                        terminal.as_ref().is_some_and(|t| t.shell),
+                    ),
+                ],
+                path,
+            ))
+        }
+        MenuTarget::Task { run_id, path } => {
+            let run = crate::history::run(app, run_id)?;
+            let running = run.as_ref().is_some_and(|r| r.finished_at.is_none());
+            let path = path
+                .as_deref()
+                .and_then(|p| app.state::<Sandbox>().check_dir(p).ok());
+            Ok((
+                vec![
+                    (MenuAction::Kill, "Stop Agent", running),
+                    (
+                        MenuAction::Restart,
+                        "Restart Agent",
+                        run.is_some() && !running,
+                    ),
+                ],
+                path,
+            ))
+        }
+    }
+}
+
+fn build_menu(app: &AppHandle, items: MenuItems, path: Option<&Path>) -> tauri::Result<Menu<Wry>> {
+    let menu = Menu::new(app)?;
+    let item = |action: MenuAction, label: &str, enabled: bool| {
+        MenuItem::with_id(app, action.id(), label, enabled, None::<&str>)
+    };
+    for (action, label, enabled) in items {
+        menu.append(&item(action, label, enabled)?)?;
+    }
+    menu.append(&PredefinedMenuItem::separator(app)?)?;
+    menu.append(&item(
+        MenuAction::Reveal,
+        REVEAL_LABEL,
+        path.is_some_and(Path::exists),
+    )?)?;
+    menu.append(&item(MenuAction::CopyPath, "Copy Path", path.is_some())?)?;
+    Ok(menu)
+}
+
+/// Show the native context menu for a terminal tab or task card at the
+/// cursor. Items are enabled by what the backend knows of the terminal or
+/// run right now. Picks arrive as `context-menu:action`, except killing a
+/// terminal and revealing a path, which are done here.
+#[tauri::command]
+pub fn show_context_menu(
+    window: WebviewWindow,
+    app: AppHandle,
+    state: State<'_, ContextMenus>,
+    target: MenuTarget,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    let (items, path) = entries(&app, &window, &target)?;
+    let menu = build_menu(&app, items, path.as_deref()).map_err(|e| e.to_string())?;
+    *state.shown.lock().map_err(|e| e.to_string())? = Some(Shown {
+        window: window.label().to_string(),
+        target,
+        path,
+    });
+    window.popup_menu(&menu).map_err(|e| e.to_string())
+}
+
+fn on_menu_event(app: &AppHandle, id: &str) {
+    let Some(action) = MenuAction::ALL.into_iter().find(|a| a.id() == id) else {
+        return;
+    };
+    let shown = match app.state::<ContextMenus>().shown.lock() {
+        Ok(mut shown) => shown.take(),
+        Err(_) => return,
+    };
+    let Some(shown) = shown else {
+        return;
+    };
+    let Some(window) = app.get_webview_window(&shown.window) else {
+        return;
+    };
+    let result = match (&shown.target, action) {
+        (MenuTarget::Terminal { id }, MenuAction::Kill) => {
+            crate::pty::close(&app.state::<PtyManager>(), &window, id)
+        }
+        (_, MenuAction::Reveal) => {
+            // This is the main thread; the file manager may take a while.
+            if let Some(path) = shown.path {
+                std::thread::spawn(move || {
+                    if let Err(e) = crate::open::reveal(&path) {
+                        tracing::warn!("failed to reveal {}: {e}", path.display());
+                    }
+                });
+            }
+            Ok(())
+        }
+        (target, action) => {
+            let payload = ContextMenuAction {
+                action,
+                target: target.clone(),
+                path: shown.path.map(|p| p.to_string_lossy().to_string()),
+            };
+            window
+                .emit_to(window.label(), "context-menu:action", payload)
+                .map_err(|e| e.to_string())
+        }
+    };
+    if let Err(e) = result {
+        tracing::warn!("context menu {action:?} failed: {e}");
+    }
+}
+
+/// Route clicks on context menu items. Called once from `setup`.
+pub fn init(app: &AppHandle) {
+    app.on_menu_event(|app, event| {
+        let id = event.id().as_ref();
+        if id.starts_with(MENU_ID_PREFIX) {
+            on_menu_event(app, id);
+        }
+    });
+}
@@ -0,0 +1,356 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use tauri::async_runtime::Receiver;
+use tauri::{AppHandle, Manager, Url, WebviewWindow};
+use tauri_plugin_shell::process::CommandEvent;
+
+use crate::pty::{PtyManager, TerminalSnapshot};
+
+/// Lines of server output kept for crash reports.
+const MAX_SERVER_LOG_LINES: usize = 500;
+
+/// Server lines included when a report is shared as a GitHub issue; the
+/// full log stays in the local report.
+const SHARED_LOG_LINES: usize = 40;
+
+/// Longest backtrace put into a shared issue, to stay under URL limits.
+const SHARED_BACKTRACE_CHARS: usize = 4000;
+
+const CRASH_DIR: &str = "crashes";
+
+const ISSUES_URL: &str = "https://github.com/ironmussa/funny/issues/new";
+
+/// Set once `install` runs; the panic hook can't be handed state any other way.
+static APP: OnceLock<AppHandle> = OnceLock::new();
+
+/// Recent server output, so a crash report shows what the sidecar was doing.
+pub struct ServerLog {
+    lines: Mutex<VecDeque<String>>,
+}
+
+impl ServerLog {
+    pub fn new() -> Self {
+        Self {
+            lines: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    fn push(&self, stream: &str, bytes: &[u8]) {
+        if let Ok(mut lines) = self.lines.lock() {
+            for line in String::from_utf8_lossy(bytes).lines() {
+                if lines.len() == MAX_SERVER_LOG_LINES {
+                    lines.pop_front();
+                }
+                lines.push_back(format!("[{stream}] {}", crate::redact::redact(line)));
+            }
+        }
+    }
+
+    /// Uses `try_lock` so a panic while the log is locked doesn't deadlock
+    /// the hook.
+    fn recent(&self) -> Vec<String> {
+        self.lines
+            .try_lock()
+            .map(|lines| lines.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CrashKind {
+    /// A panic in the Rust backend.
+    Panic,
+    /// The server sidecar exited while the app still needed it.
+    ServerExit,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashReport {
+    pub id: String,
+    pub kind: CrashKind,
+    pub timestamp: String,
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    pub message: String,
+    pub location: Option<String>,
+    pub thread: Option<String>,
+    pub backtrace: Option<String>,
+    pub server_log: Vec<String>,
+    /// `None` when the terminal state couldn't be read at crash time.
+    pub terminals: Option<Vec<TerminalSnapshot>>,
+    /// Set once the user has shared the report.
+    #[serde(default)]
+    pub shared: bool,
+}
+
+fn crash_dir(app: &AppHandle) -> Option<PathBuf> {
+    app.path()
+        .app_data_dir()
+        .ok()
+        .map(|dir| dir.join(CRASH_DIR))
+}
+
+fn report_path(app: &AppHandle, id: &str) -> Result<PathBuf, String> {
+    // Ids are generated here; anything else could escape the crash folder.
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(format!("invalid crash report id {id}"));
+    }
+    Ok(crash_dir(app)
+        .ok_or("no app data directory")?
+        .join(format!("{id}.json")))
+}
+
+fn build_report(
+    app: &AppHandle,
+    kind: CrashKind,
+    message: String,
+    location: Option<String>,
+    backtrace: Option<String>,
+) -> CrashReport {
+    let now = chrono::Utc::now();
+    let kind_slug = match kind {
+        CrashKind::Panic => "panic",
+        CrashKind::ServerExit => "server-exit",
+    };
+    CrashReport {
+        id: format!("{}-{kind_slug}", now.format("%Y%m%d-%H%M%S-%3f")),
+        kind,
+        timestamp: now.to_rfc3339(),
+        app_version: app.package_info().version.to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        message,
+        location,
+        thread: std::thread::current().name().map(str::to_string),
+        backtrace,
+        server_log: app
+            .try_state::<ServerLog>()
+            .map(|log| log.recent())
+            .unwrap_or_default(),
+        terminals: app.try_state::<PtyManager>().and_then(|pty| pty.snapshot()),
+        shared: false,
+    }
+}
+
+fn write_report(app: &AppHandle, report: &CrashReport) -> Result<PathBuf, String> {
+    let path = report_path(app, &report.id)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_vec_pretty(report).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+/// Write a crash report for every panic, then defer to the default hook so
+/// the panic is still printed. Called once from `setup`.
+pub fn install(app: &AppHandle) {
+    if APP.set(app.clone()).is_err() {
+        return;
+    }
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if let Some(app) = APP.get() {
+            let payload = info.payload();
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "panic with a non-string payload".to_string());
+            let location = info
+                .location()
+                .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()));
+            let backtrace = std::backtrace::Backtrace::force_capture().to_string();
+            let report = build_report(app, CrashKind::Panic, message, location, Some(backtrace));
+            crate::analytics::record_error(app, "panic");
+            match write_report(app, &report) {
+                Ok(path) => tracing::error!("crash report written to {}", path.display()),
+                Err(e) => tracing::error!("failed to write crash report: {e}"),
+            }
+        }
+        default_hook(info);
+    }));
+}
+
+/// Collect the output of the server process `pid` and record a crash report
+/// if it exits while it is still the app's server — not when it was stopped
+/// for a restart or on app exit.
+pub(crate) fn watch_server(app: &AppHandle, pid: u32, mut rx: Receiver<CommandEvent>) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        while let Some(event) = rx.recv().await {
+            let log = app.state::<ServerLog>();
+            match event {
+                CommandEvent::Stdout(bytes) => {
+                    for line in String::from_utf8_lossy(&bytes).lines() {
+                        tracing::info!(target: "server", "{line}");
+                    }
+                    log.push("stdout", &bytes);
+                }
+                CommandEvent::Stderr(bytes) => {
+                    for line in String::from_utf8_lossy(&bytes).lines() {
+                        tracing::warn!(target: "server", "{line}");
+                    }
+                    log.push("stderr", &bytes);
+                }
+                CommandEvent::Error(error) => {
+                    tracing::error!(target: "server", "{error}");
+                    log.push("error", error.as_bytes());
+                }
+                CommandEvent::Terminated(status) => {
+                    let current = app.try_state::<crate::ServerProcess>().and_then(|state| {
+                        state
+                            .0
+                            .lock()
+                            .ok()
+                            .and_then(|guard| guard.as_ref().map(|c| c.pid()))
+                    });
+                    if current != Some(pid) {
+                        break;
+                    }
+                    let message = match (status.code, status.signal) {
+                        (Some(code), _) => format!("server exited with code {code}"),
+                        (None, Some(signal)) => format!("server killed by signal {signal}"),
+                        (None, None) => "server exited".to_string(),
+                    };
+                    tracing::error!(target: "server", "{message}");
+                    crate::analytics::record_error(&app, "server_exit");
+                    let report = build_report(&app, CrashKind::ServerExit, message, None, None);
+                    if let Err(e) = write_report(&app, &report) {
+                        tracing::error!("failed to write crash report: {e}");
+                    }
+                    break;
+                }
+                _ => {}
+            }
+        }
+    });
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashSummary {
+    pub id: String,
+    pub kind: CrashKind,
+    pub timestamp: String,
+    pub message: String,
+    pub shared: bool,
+}
+
+/// Crash reports in the local crash folder, newest first.
+pub(crate) fn summaries(app: &AppHandle) -> Result<Vec<CrashSummary>, String> {
+    let dir = match crash_dir(app) {
+        Some(dir) if dir.is_dir() => dir,
+        _ => return Ok(Vec::new()),
+    };
+    let mut reports: Vec<CrashSummary> = std::fs::read_dir(dir)
+        .map_err(|e| e.to_string())?
+        .filter_map(|entry| {
+            let content = std::fs::read_to_string(entry.ok()?.path()).ok()?;
+            let report: CrashReport = serde_json::from_str(&content).ok()?;
+            Some(CrashSummary {
+                id: report.id,
+                kind: report.kind,
+                timestamp: report.timestamp,
+                message: report.message,
+                shared: report.shared,
+            })
+        })
+        .collect();
+    reports.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    Ok(reports)
+}
+
+#[tauri::command]
+pub fn list_crash_reports(
+    window: WebviewWindow,
+    app: AppHandle,
+) -> Result<Vec<CrashSummary>, String> {
+    crate::require_window(&window, "main")?;
+    summaries(&app)
+}
+
+/// Delete crash report `id`.
+pub(crate) fn delete(app: &AppHandle, id: &str) -> Result<(), String> {
+    std::fs::remove_file(report_path(app, id)?).map_err(|e| format!("{id}: {e}"))
+}
+
+#[tauri::command]
+pub fn get_crash_report(
+    window: WebviewWindow,
+    app: AppHandle,
+    id: String,
+) -> Result<CrashReport, String> {
+    crate::require_window(&window, "main")?;
+    let content =
+        std::fs::read_to_string(report_path(&app, &id)?).map_err(|e| format!("{id}: {e}"))?;
+    serde_json::from_str(&content).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_crash_report(
+    window: WebviewWindow,
+    app: AppHandle,
+    id: String,
+) -> Result<(), String> {
+    crate::require_window(&window, "main")?;
+    delete(&app, &id)
+}
+
+fn issue_body(report: &CrashReport) -> String {
+    let mut backtrace = report.backtrace.clone().unwrap_or_default();
+    if backtrace.len() > SHARED_BACKTRACE_CHARS {
+        let mut end = SHARED_BACKTRACE_CHARS;
+        while !backtrace.is_char_boundary(end) {
+            end -= 1;
+        }
+        backtrace.truncate(end);
+        backtrace.push_str("\n…");
+    }
+    let log_start = report.server_log.len().saturating_sub(SHARED_LOG_LINES);
+    format!(
+        "**Version:** {} ({} {})\n**Kind:** {:?}\n**When:** {}\n**Where:** {}\n\n{}\n\n\
+         <details><summary>Backtrace</summary>\n\n```\n{}\n```\n</details>\n\n\
+         <details><summary>Server log (last {} lines)</summary>\n\n```\n{}\n```\n</details>\n",
+        report.app_version,
+        report.os,
+        report.arch,
+        report.kind,
+        report.timestamp,
+        report.location.as_deref().unwrap_or("-"),
+        report.message,
+        backtrace,
+        SHARED_LOG_LINES,
+        report.server_log[log_start..].join("\n"),
+    )
+}
+
+/// Share a report by opening a prefilled GitHub issue in the browser, where
+/// the user can review and edit it before submitting. Nothing leaves the
+/// machine unless this is called.
+#[tauri::command]
+pub fn share_crash_report(window: WebviewWindow, app: AppHandle, id: String) -> Result<(), String> {
+    crate::require_window(&window, "main")?;
+    let path = report_path(&app, &id)?;
+    let content = std::fs::read_to_string(&path).map_err(|e| format!("{id}: {e}"))?;
+    let mut report: CrashReport = serde_json::from_str(&content).map_err(|e| e.to_string())?;
+
+    let title = format!(
+        "Crash: {}",
+        report.message.lines().next().unwrap_or_default()
+    );
+    let url = Url::parse_with_params(
+        ISSUES_URL,
+        &[("title", title), ("body", issue_body(&report))],
+    )
+    .map_err(|e| e.to_string())?;
+    crate::dev_servers::launch_browser(url.as_str())?;
+
+    report.shared = true;
+    write_report(&app, &report).map(|_| ())
+}
@@ -0,0 +1,360 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, WebviewWindow};
+
+use crate::onboarding::home_dir;
+
+/// Each ping gets this long before the provider counts as unreachable.
+const PING_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A login that can't refresh itself and ends sooner than this is flagged,
+/// so an overnight batch doesn't start on it.
+const EXPIRY_WARNING: chrono::Duration = chrono::Duration::hours(24);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Health {
+    Ok,
+    /// Valid now, but ends within a day and can't refresh itself.
+    Expiring,
+    Expired,
+    /// Rejected by the provider (401/403), or the CLI reports it logged out.
+    Invalid,
+    /// The provider couldn't be reached to check.
+    Unreachable,
+    /// Found, but its state can't be told.
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CredentialCheck {
+    /// `anthropic`, `openai`, `gemini`, `github`, `gitlab` for API keys;
+    /// `claude`, `codex`, `gemini-cli`, `gh` for CLI logins.
+    pub provider: String,
+    /// Where it was found: a keychain secret, an environment variable or a
+    /// CLI's credentials file.
+    pub source: String,
+    pub health: Health,
+    pub detail: Option<String>,
+    pub expires_at: Option<String>,
+}
+
+impl CredentialCheck {
+    fn new(provider: &str, source: String, health: Health, detail: Option<String>) -> Self {
+        Self {
+            provider: provider.to_string(),
+            source,
+            health,
+            detail,
+            expires_at: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ApiProvider {
+    Anthropic,
+    Openai,
+    Gemini,
+    Github,
+    Gitlab,
+}
+
+impl ApiProvider {
+    const ALL: [ApiProvider; 5] = [
+        ApiProvider::Anthropic,
+        ApiProvider::Openai,
+        ApiProvider::Gemini,
+        ApiProvider::Github,
+        ApiProvider::Gitlab,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            ApiProvider::Anthropic => "anthropic",
+            ApiProvider::Openai => "openai",
+            ApiProvider::Gemini => "gemini",
+            ApiProvider::Github => "github",
+            ApiProvider::Gitlab => "gitlab",
+        }
+    }
+
+    /// The keychain secret, then the environment variables, in the order
+    /// the agents look at them.
+    fn sources(self) -> (&'static str, &'static [&'static str]) {
+        match self {
+            ApiProvider::Anthropic => ("anthropic-api-key", &["ANTHROPIC_API_KEY"]),
+            ApiProvider::Openai => ("openai-api-key", &["OPENAI_API_KEY"]),
+            ApiProvider::Gemini => ("gemini-api-key", &["GEMINI_API_KEY"]),
+            ApiProvider::Github => ("github-token", &["GH_TOKEN", "GITHUB_TOKEN"]),
+            ApiProvider::Gitlab => ("gitlab-token", &["GITLAB_TOKEN"]),
+        }
+    }
+
+    /// A free, read-only request that only succeeds with a valid key.
+    fn ping(self, http: &ureq::Agent, key: &str) -> ureq::Request {
+        match self {
+            ApiProvider::Anthropic => http
+                .get("https://api.anthropic.com/v1/models")
+                .set("x-api-key", key)
+                .set("anthropic-version", "2023-06-01"),
+            ApiProvider::Openai => http
+                .get("https://api.openai.com/v1/models")
+                .set("Authorization", &format!("Bearer {key}")),
+            ApiProvider::Gemini => http
+                .get("https://generativelanguage.googleapis.com/v1beta/models")
+                .set("x-goog-api-key", key),
+            ApiProvider::Github => http
+                .get("https://api.github.com/user")
+                .set("Authorization", &format!("Bearer {key}")),
+            ApiProvider::Gitlab => {
+                // glab's variable for a self-managed instance.
+                let host = std::env::var("GITLAB_HOST")
+                    .ok()
+                    .filter(|h| !h.is_empty())
+                    .unwrap_or_else(|| "gitlab.com".to_string());
+                let host = host.trim_start_matches("https://").trim_end_matches('/');
+                http.get(&format!("https://{host}/api/v4/user"))
+                    .set("PRIVATE-TOKEN", key)
+            }
+        }
+    }
+}
+
+/// The key for `provider` and where it came from, if there is one.
+fn find_key(provider: ApiProvider) -> Option<(String, String)> {
+    let (secret, vars) = provider.sources();
+    if let Some(value) = crate::secrets::get(secret) {
+        return Some((value, format!("keychain {secret}")));
+    }
+    vars.iter().find_map(|var| {
+        std::env::var(var)
+            .ok()
+            .filter(|v| !v.is_empty())
+            .map(|value| (value, var.to_string()))
+    })
+}
+
+fn check_key(
+    http: &ureq::Agent,
+    provider: ApiProvider,
+    key: &str,
+    source: String,
+) -> CredentialCheck {
+    let (health, detail) = match provider.ping(http, key).call() {
+        Ok(_) => (Health::Ok, None),
+        Err(ureq::Error::Status(401 | 403, _)) => (
+            Health::Invalid,
+            Some("rejected by the provider".to_string()),
+        ),
+        // Throttled means the key itself was accepted.
+        Err(ureq::Error::Status(429, _)) => (Health::Ok, Some("rate limited".to_string())),
+        Err(ureq::Error::Status(code, _)) => {
+            (Health::Unknown, Some(format!("provider returned {code}")))
+        }
+        Err(e) => (Health::Unreachable, Some(e.to_string())),
+    };
+    CredentialCheck::new(provider.name(), source, health, detail)
+}
+
+fn read_json(path: &Path) -> Option<Value> {
+    serde_json::from_str(&std::fs::read_to_string(path).ok()?).ok()
+}
+
+/// A login's health from when its access token expires. One with a
+/// refresh token renews itself when the CLI next runs.
+fn login_check(
+    provider: &str,
+    path: &Path,
+    expires_ms: Option<i64>,
+    refreshable: bool,
+) -> CredentialCheck {
+    let source = path.to_string_lossy().to_string();
+    let expires = expires_ms.and_then(chrono::DateTime::from_timestamp_millis);
+    let (health, detail) = match expires {
+        _ if refreshable => (Health::Ok, None),
+        None => (Health::Unknown, Some("no expiry recorded".to_string())),
+        Some(at) if at <= chrono::Utc::now() => {
+            (Health::Expired, Some(format!("log in to {provider} again")))
+        }
+        Some(at) if at - chrono::Utc::now() < EXPIRY_WARNING => (
+            Health::Expiring,
+            Some(format!("log in to {provider} again before long runs")),
+        ),
+        Some(_) => (Health::Ok, None),
+    };
+    CredentialCheck {
+        expires_at: expires.map(|at| at.to_rfc3339()),
+        ..CredentialCheck::new(provider, source, health, detail)
+    }
+}
+
+/// `exp` of a JWT, in milliseconds; the signature isn't checked.
+fn jwt_expiry_ms(token: &str) -> Option<i64> {
+    use base64::Engine;
+    let payload = token.split('.').nth(1)?;
+    let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(payload.trim_end_matches('='))
+        .ok()?;
+    let claims: Value = serde_json::from_slice(&bytes).ok()?;
+    claims["exp"].as_i64().map(|exp| exp * 1000)
+}
+
+fn claude_login(home: &Path) -> Option<CredentialCheck> {
+    let dir = std::env::var_os("CLAUDE_CONFIG_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home.join(".claude"));
+    let path = dir.join(".credentials.json");
+    if let Some(credentials) = read_json(&path) {
+        let oauth = &credentials["claudeAiOauth"];
+        return Some(login_check(
+            "claude",
+            &path,
+            oauth["expiresAt"].as_i64(),
+            oauth["refreshToken"]
+                .as_str()
+                .is_some_and(|t| !t.is_empty()),
+        ));
+    }
+    // On macOS the token is in the login keychain, which can't be read
+    // without a prompt: only the account is known.
+    let config = home.join(".claude.json");
+    read_json(&config)
+        .filter(|config| config.get("oauthAccount").is_some())
+        .map(|_| {
+            CredentialCheck::new(
+                "claude",
+                config.to_string_lossy().to_string(),
+                Health::Unknown,
+                Some("the token is kept in the system keychain".to_string()),
+            )
+        })
+}
+
+fn codex_login(home: &Path) -> Option<CredentialCheck> {
+    let dir = std::env::var_os("CODEX_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home.join(".codex"));
+    let path = dir.join("auth.json");
+    let auth = read_json(&path)?;
+    let tokens = &auth["tokens"];
+    if tokens.is_null() {
+        // Logged in with an API key: there is no token to expire.
+        return None;
+    }
+    Some(login_check(
+        "codex",
+        &path,
+        tokens["access_token"].as_str().and_then(jwt_expiry_ms),
+        tokens["refresh_token"]
+            .as_str()
+            .is_some_and(|t| !t.is_empty()),
+    ))
+}
+
+fn gemini_login(home: &Path) -> Option<CredentialCheck> {
+    let path = home.join(".gemini").join("oauth_creds.json");
+    let credentials = read_json(&path)?;
+    Some(login_check(
+        "gemini-cli",
+        &path,
+        credentials["expiry_date"].as_i64(),
+        credentials["refresh_token"]
+            .as_str()
+            .is_some_and(|t| !t.is_empty()),
+    ))
+}
+
+/// `gh auth status`, for a gh that has logged in at all.
+fn gh_login(home: &Path) -> Option<CredentialCheck> {
+    let config = std::env::var_os("GH_CONFIG_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home.join(".config").join("gh"));
+    if !config.join("hosts.yml").is_file() {
+        return None;
+    }
+    let gh = crate::diagnostics::find_executable("gh")?;
+    let (health, detail) =
+        match crate::forge::run_capture(Command::new(gh).args(["auth", "status"]), None) {
+            Ok(_) => (Health::Ok, None),
+            Err(e) => (
+                Health::Invalid,
+                e.lines()
+                    .map(str::trim)
+                    .find(|line| line.contains("token") || line.contains("log"))
+                    .or_else(|| e.lines().next())
+                    .map(str::to_string),
+            ),
+        };
+    Some(CredentialCheck::new(
+        "gh",
+        "gh auth".to_string(),
+        health,
+        detail,
+    ))
+}
+
+fn check_all() -> Vec<CredentialCheck> {
+    let http = ureq::AgentBuilder::new().timeout(PING_TIMEOUT).build();
+    let home = home_dir();
+    std::thread::scope(|scope| {
+        let http = &http;
+        let mut running: Vec<_> = ApiProvider::ALL
+            .into_iter()
+            .filter_map(|provider| find_key(provider).map(|(key, source)| (provider, key, source)))
+            .map(|(provider, key, source)| {
+                scope.spawn(move || vec![check_key(http, provider, &key, source)])
+            })
+            .collect();
+        if let Some(home) = home.as_deref() {
+            running.push(scope.spawn(move || {
+                [claude_login, codex_login, gemini_login]
+                    .into_iter()
+                    .filter_map(|login| login(home))
+                    .collect()
+            }));
+            running.push(scope.spawn(move || gh_login(home).into_iter().collect()));
+        }
+        running
+            .into_iter()
+            .flat_map(|check| check.join().unwrap_or_default())
+            .collect()
+    })
+}
+
+/// Check every stored API key and CLI login agents use: a cheap
+/// authenticated request per API key, and the token expiry of Claude Code,
+/// Codex and Gemini CLI logins and `gh auth status`. Credentials that aren't
+/// set up are left out. Anything not `ok` is also sent to the main window
+/// as `credentials:warning`, so a batch of runs can be held back before it
+/// fails on 401s.
+#[tauri::command]
+pub async fn credentials_check(
+    window: WebviewWindow,
+    app: AppHandle,
+) -> Result<Vec<CredentialCheck>, String> {
+    crate::require_app_window(&window)?;
+    let checks = tauri::async_runtime::spawn_blocking(check_all)
+        .await
+        .map_err(|e| e.to_string())?;
+    let failing: Vec<&CredentialCheck> = checks.iter().filter(|c| c.health != Health::Ok).collect();
+    for check in &failing {
+        tracing::warn!(
+            "{} credential from {} is {:?}: {}",
+            check.provider,
+            check.source,
+            check.health,
+            check.detail.as_deref().unwrap_or("")
+        );
+    }
+    if !failing.is_empty() {
+        if let Err(e) = app.emit_to("main", "credentials:warning", &failing) {
+            tracing::debug!("credentials:warning not delivered: {e}");
+        }
+    }
+    Ok(checks)
+}
@@ -0,0 +1,248 @@
+use rusqlite::Connection;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+const DB_FILE: &str = "funny.db";
+
+/// Schema migrations, applied in order. `PRAGMA user_version` records how
+/// many have run; append new ones, never edit old ones.
+const MIGRATIONS: &[&str] = &[
+    // 1: terminals, agent runs and transcripts, usage counts, audit events.
+    r#"
+    CREATE TABLE terminals (
+        id TEXT PRIMARY KEY,
+        window TEXT NOT NULL,
+        cwd TEXT NOT NULL,
+        program TEXT NOT NULL,
+        started_at TEXT NOT NULL,
+        exited_at TEXT
+    );
+    CREATE TABLE agent_runs (
+        id TEXT PRIMARY KEY,
+        project TEXT,
+        provider TEXT,
+        model TEXT,
+        title TEXT,
+        status TEXT NOT NULL,
+        started_at TEXT NOT NULL,
+        finished_at TEXT
+    );
+    CREATE INDEX agent_runs_project ON agent_runs (project, started_at);
+    CREATE TABLE transcript_entries (
+        run_id TEXT NOT NULL REFERENCES agent_runs (id) ON DELETE CASCADE,
+        seq INTEGER NOT NULL,
+        role TEXT NOT NULL,
+        content TEXT NOT NULL,
+        timestamp TEXT NOT NULL,
+        PRIMARY KEY (run_id, seq)
+    );
+    CREATE TABLE usage_counts (
+        day TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        name TEXT NOT NULL,
+        count INTEGER NOT NULL,
+        PRIMARY KEY (day, kind, name)
+    );
+    CREATE TABLE audit_events (
+        id INTEGER PRIMARY KEY,
+        timestamp TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        agent TEXT,
+        project TEXT,
+        target TEXT NOT NULL,
+        outcome TEXT NOT NULL,
+        detail TEXT
+    );
+    CREATE INDEX audit_events_timestamp ON audit_events (timestamp);
+    "#,
+    // 2: verification results of agent runs.
+    r#"
+    CREATE TABLE verifications (
+        run_id TEXT NOT NULL REFERENCES agent_runs (id) ON DELETE CASCADE,
+        attempt INTEGER NOT NULL,
+        passed INTEGER NOT NULL,
+        started_at TEXT NOT NULL,
+        result TEXT NOT NULL,
+        PRIMARY KEY (run_id, attempt)
+    );
+    "#,
+    // 3: saved terminal and agent layouts per project.
+    r#"
+    CREATE TABLE layouts (
+        project TEXT NOT NULL,
+        name TEXT NOT NULL,
+        layout TEXT NOT NULL,
+        updated_at TEXT NOT NULL,
+        PRIMARY KEY (project, name)
+    );
+    "#,
+    // 4: prompt templates for recurring tasks.
+    r#"
+    CREATE TABLE templates (
+        id TEXT PRIMARY KEY,
+        template TEXT NOT NULL,
+        updated_at TEXT NOT NULL
+    );
+    "#,
+    // 5: tasks posted to the intake endpoint.
+    r#"
+    CREATE TABLE intake_tasks (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        project TEXT NOT NULL,
+        request TEXT NOT NULL,
+        source TEXT NOT NULL,
+        status TEXT NOT NULL,
+        detail TEXT,
+        received_at TEXT NOT NULL,
+        updated_at TEXT NOT NULL
+    );
+    CREATE INDEX intake_tasks_status ON intake_tasks (status);
+    "#,
+    // 6: what agent runs changed and cost, and the digests sent of them.
+    r#"
+    ALTER TABLE agent_runs ADD COLUMN cost_usd REAL;
+    ALTER TABLE agent_runs ADD COLUMN files_changed INTEGER;
+    ALTER TABLE agent_runs ADD COLUMN additions INTEGER;
+    ALTER TABLE agent_runs ADD COLUMN deletions INTEGER;
+    CREATE TABLE digests (
+        until TEXT PRIMARY KEY,
+        since TEXT NOT NULL,
+        runs INTEGER NOT NULL
+    );
+    "#,
+    // 7: shells suspended to free their processes, with their scrollback.
+    r#"
+    CREATE TABLE suspended_terminals (
+        id TEXT PRIMARY KEY,
+        cwd TEXT NOT NULL,
+        sandbox TEXT NOT NULL,
+        scrollback TEXT NOT NULL,
+        suspended_at TEXT NOT NULL
+    );
+    "#,
+    // 8: the activity timeline across terminals, agents and git.
+    r#"
+    CREATE TABLE timeline_events (
+        id INTEGER PRIMARY KEY,
+        timestamp TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        project TEXT,
+        subject TEXT NOT NULL,
+        title TEXT NOT NULL,
+        detail TEXT
+    );
+    CREATE INDEX timeline_events_timestamp ON timeline_events (timestamp);
+    CREATE INDEX timeline_events_project ON timeline_events (project, timestamp);
+    "#,
+    // 9: tokens used by agent runs.
+    r#"
+    ALTER TABLE agent_runs ADD COLUMN input_tokens INTEGER;
+    ALTER TABLE agent_runs ADD COLUMN output_tokens INTEGER;
+    "#,
+    // 10: registered SSH hosts.
+    r#"
+    CREATE TABLE remote_hosts (
+        alias TEXT PRIMARY KEY,
+        host TEXT NOT NULL,
+        updated_at TEXT NOT NULL
+    );
+    "#,
+    // 11: the environment agent runs started in.
+    r#"
+    CREATE TABLE run_environments (
+        run_id TEXT PRIMARY KEY REFERENCES agent_runs (id) ON DELETE CASCADE,
+        captured_at TEXT NOT NULL,
+        snapshot TEXT NOT NULL
+    );
+    "#,
+];
+
+/// The app's embedded database in app data, opened the first time it is
+/// needed. One connection, shared behind a lock: writes are small and
+/// infrequent enough that a pool would buy nothing.
+pub struct Database {
+    conn: Mutex<Option<Connection>>,
+}
+
+impl Database {
+    pub fn new() -> Self {
+        Self {
+            conn: Mutex::new(None),
+        }
+    }
+}
+
+pub(crate) fn db_path(app: &AppHandle) -> Option<PathBuf> {
+    app.path().app_data_dir().ok().map(|dir| dir.join(DB_FILE))
+}
+
+fn open(app: &AppHandle) -> Result<Connection, String> {
+    let path = db_path(app).ok_or("no app data directory")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let mut conn = Connection::open(&path).map_err(|e| e.to_string())?;
+    conn.busy_timeout(Duration::from_secs(5))
+        .map_err(|e| e.to_string())?;
+    conn.execute_batch("PRAGMA journal_mode = WAL; PRAGMA foreign_keys = ON;")
+        .map_err(|e| e.to_string())?;
+    migrate(app, &mut conn)?;
+    Ok(conn)
+}
+
+fn migrate(app: &AppHandle, conn: &mut Connection) -> Result<(), String> {
+    let version: usize = conn
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    for (i, sql) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        tx.execute_batch(sql)
+            .map_err(|e| format!("migration {} failed: {e}", i + 1))?;
+        if i == 0 {
+            crate::audit::import_legacy(app, &tx);
+            crate::analytics::import_legacy(app, &tx);
+        }
+        tx.pragma_update(None, "user_version", i + 1)
+            .map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())?;
+        tracing::info!("database migrated to version {}", i + 1);
+    }
+    Ok(())
+}
+
+/// Run `f` on the database, opening it first if needed.
+pub(crate) fn with<T>(
+    app: &AppHandle,
+    f: impl FnOnce(&mut Connection) -> rusqlite::Result<T>,
+) -> Result<T, String> {
+    let db = app.try_state::<Database>().ok_or("database not ready")?;
+    let mut conn = db.conn.lock().map_err(|e| e.to_string())?;
+    run(app, &mut conn, f)
+}
+
+/// `with`, but gives up instead of waiting if the database is busy. For
+/// callers that must never block, like the panic hook.
+pub(crate) fn try_with<T>(
+    app: &AppHandle,
+    f: impl FnOnce(&mut Connection) -> rusqlite::Result<T>,
+) -> Result<T, String> {
+    let db = app.try_state::<Database>().ok_or("database not ready")?;
+    let mut conn = db.conn.try_lock().map_err(|e| e.to_string())?;
+    run(app, &mut conn, f)
+}
+
+fn run<T>(
+    app: &AppHandle,
+    conn: &mut Option<Connection>,
+    f: impl FnOnce(&mut Connection) -> rusqlite::Result<T>,
+) -> Result<T, String> {
+    if conn.is_none() {
+        *conn = Some(open(app)?);
+    }
+    match conn.as_mut() {
+        Some(conn) => f(conn).map_err(|e| e.to_string()),
+        None => Err("database not open".to_string()),
+    }
+}
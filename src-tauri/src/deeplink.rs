@@ -0,0 +1,69 @@
+use tauri::{AppHandle, Url};
+use tauri_plugin_deep_link::DeepLinkExt;
+
+use crate::instance::{self, OpenRequest};
+
+const SCHEME: &str = "funny";
+
+/// Parse `funny://open?project=/path&task=123`. The project must be an
+/// existing directory; `task` is passed through for the frontend to look up.
+pub(crate) fn parse(url: &Url) -> Result<OpenRequest, String> {
+    if url.scheme() != SCHEME {
+        return Err(format!("unsupported scheme {}", url.scheme()));
+    }
+    // `funny://open?…` puts the action in the host; accept `funny:open?…` too.
+    let action = url
+        .host_str()
+        .unwrap_or_else(|| url.path().trim_matches('/'));
+    if action != "open" {
+        return Err(format!("unsupported deep link action {action}"));
+    }
+
+    let mut request = OpenRequest::default();
+    for (key, value) in url.query_pairs() {
+        match key.as_ref() {
+            "project" => {
+                let path = std::fs::canonicalize(value.as_ref())
+                    .map_err(|e| format!("invalid project {value}: {e}"))?;
+                if !path.is_dir() {
+                    return Err(format!("{value} is not a directory"));
+                }
+                request.project = Some(path.to_string_lossy().to_string());
+            }
+            "task" => request.task = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    if request.project.is_none() && request.task.is_none() {
+        return Err("deep link names neither a project nor a task".to_string());
+    }
+    Ok(request)
+}
+
+fn handle(app: &AppHandle, urls: Vec<Url>) {
+    for url in urls {
+        match parse(&url) {
+            Ok(request) => instance::dispatch(app, Some(request)),
+            Err(e) => tracing::warn!("ignoring deep link {url}: {e}"),
+        }
+    }
+}
+
+/// Route `funny://` URLs to the open queue: ones the app was launched with,
+/// and ones opened while it runs (forwarded by the single-instance plugin on
+/// Windows and Linux). Called once from `setup`.
+pub fn init(app: &AppHandle) {
+    let deep_link = app.deep_link();
+    // Installed builds register the scheme through the bundle; dev builds on
+    // Windows and Linux have to do it at runtime.
+    #[cfg(any(windows, target_os = "linux"))]
+    if cfg!(debug_assertions) {
+        let _ = deep_link.register_all();
+    }
+
+    if let Ok(Some(urls)) = deep_link.get_current() {
+        handle(app, urls);
+    }
+    let handle_app = app.clone();
+    deep_link.on_open_url(move |event| handle(&handle_app, event.urls()));
+}
@@ -0,0 +1,178 @@
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream};
+use std::sync::OnceLock;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, EventTarget, WebviewWindow};
+
+/// Servers often announce their address a moment before they accept
+/// connections, so the port is probed a few times.
+const PROBE_ATTEMPTS: u32 = 20;
+const PROBE_INTERVAL: Duration = Duration::from_millis(250);
+const PROBE_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Lines longer than this are cut before matching.
+const MAX_LINE: usize = 4096;
+
+/// `pty:server-detected`: a terminal printed a local server address and the
+/// port accepts connections.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ServerDetected {
+    /// The terminal.
+    id: String,
+    url: String,
+    port: u16,
+}
+
+struct Patterns {
+    url: Regex,
+    port: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        let pattern = |p: &str| Regex::new(p).expect("valid pattern");
+        Patterns {
+            // `http://localhost:5173/`, `127.0.0.1:8000`, `[::]:3000`.
+            url: pattern(
+                r"(?i)(?:(https?)://)?(localhost|127\.0\.0\.1|0\.0\.0\.0|\[::1?\]):(\d{2,5})\b(/[^\s'`]*)?",
+            ),
+            // `Listening on port 3000`, `server started at port 8080`.
+            port: pattern(
+                r"(?i)\b(?:listening|running|started|serving|available|ready)\b[^\n]{0,40}?\bport\s*:?\s*(\d{2,5})\b",
+            ),
+        }
+    })
+}
+
+/// The local URL announced on `line`, if any.
+fn parse(line: &str) -> Option<(String, u16)> {
+    let patterns = patterns();
+    if let Some(c) = patterns.url.captures(line) {
+        let port: u16 = c[3].parse().ok().filter(|p| *p != 0)?;
+        let scheme = c.get(1).map_or("http", |m| m.as_str()).to_ascii_lowercase();
+        let host = match &c[2] {
+            "0.0.0.0" | "[::]" => "localhost",
+            host => host,
+        };
+        let path = c.get(4).map_or("/", |m| m.as_str());
+        let path = path.trim_end_matches(['.', ',', ')', ']', ';']);
+        let path = if path.is_empty() { "/" } else { path };
+        return Some((format!("{scheme}://{host}:{port}{path}"), port));
+    }
+    let c = patterns.port.captures(line)?;
+    let port: u16 = c[1].parse().ok().filter(|p| *p != 0)?;
+    Some((format!("http://localhost:{port}/"), port))
+}
+
+/// Whether something on this machine accepts connections on `port`.
+fn listening(port: u16) -> bool {
+    [
+        SocketAddr::from((Ipv4Addr::LOCALHOST, port)),
+        SocketAddr::from((Ipv6Addr::LOCALHOST, port)),
+    ]
+    .iter()
+    .any(|addr| TcpStream::connect_timeout(addr, PROBE_TIMEOUT).is_ok())
+}
+
+/// Watches one terminal's output for dev servers. Each port is reported
+/// once per terminal.
+pub(crate) struct ServerDetector {
+    app: AppHandle,
+    window: String,
+    terminal: String,
+    partial: String,
+    seen: HashSet<u16>,
+}
+
+impl ServerDetector {
+    pub(crate) fn new(app: &AppHandle, window: &str, terminal: &str) -> Self {
+        Self {
+            app: app.clone(),
+            window: window.to_string(),
+            terminal: terminal.to_string(),
+            partial: String::new(),
+            seen: HashSet::new(),
+        }
+    }
+
+    pub(crate) fn feed(&mut self, chunk: &str) {
+        self.partial.push_str(chunk);
+        let complete = match self.partial.rfind('\n') {
+            Some(i) => self.partial.drain(..=i).collect::<String>(),
+            None => {
+                if self.partial.len() > MAX_LINE {
+                    self.partial.clear();
+                }
+                return;
+            }
+        };
+        for line in complete.lines() {
+            let line = crate::problems::strip_ansi(line);
+            let line = match line.char_indices().nth(MAX_LINE) {
+                Some((i, _)) => &line[..i],
+                None => &line[..],
+            };
+            if let Some((url, port)) = parse(line) {
+                if self.seen.insert(port) {
+                    self.probe(url, port);
+                }
+            }
+        }
+    }
+
+    /// Confirm `port` off the reader thread, then tell the terminal's
+    /// window.
+    fn probe(&self, url: String, port: u16) {
+        let app = self.app.clone();
+        let target = EventTarget::webview_window(&self.window);
+        let id = self.terminal.clone();
+        std::thread::spawn(move || {
+            for attempt in 0..PROBE_ATTEMPTS {
+                if attempt > 0 {
+                    std::thread::sleep(PROBE_INTERVAL);
+                }
+                if listening(port) {
+                    tracing::info!("terminal {id}: server on {url}");
+                    let payload = ServerDetected { id, url, port };
+                    let _ = app.emit_to(target, "pty:server-detected", payload);
+                    return;
+                }
+            }
+            tracing::debug!("terminal {id}: nothing listening on port {port}");
+        });
+    }
+}
+
+/// Start the browser on `url`, without checking it.
+pub(crate) fn launch_browser(url: &str) -> Result<(), String> {
+    let mut command = if cfg!(target_os = "macos") {
+        std::process::Command::new("open")
+    } else if cfg!(windows) {
+        let mut command = std::process::Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        std::process::Command::new("xdg-open")
+    };
+    command
+        .arg(url)
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("failed to open browser: {e}"))
+}
+
+/// Open `url`, e.g. from a `pty:server-detected` event, in the default
+/// browser. Only http and https URLs are opened.
+#[tauri::command]
+pub fn open_in_browser(window: WebviewWindow, url: String) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    let parsed = url::Url::parse(&url).map_err(|e| format!("invalid URL: {e}"))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(format!("not opening {} URLs", parsed.scheme()));
+    }
+    launch_browser(parsed.as_str())
+}
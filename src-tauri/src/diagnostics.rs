@@ -0,0 +1,345 @@
+use serde::Serialize;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, WebviewWindow};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::logging::Logging;
+
+/// Agent CLIs funny can drive, by executable name.
+pub(crate) const AGENT_COMMANDS: &[&str] = &[
+    "claude",
+    "codex",
+    "codex-acp",
+    "gemini",
+    "cursor-agent",
+    "opencode",
+    "pi",
+];
+
+/// `--version` probes that take longer than this are killed.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Only the tail of each log file goes into the bundle.
+const MAX_LOG_BYTES: u64 = 512 * 1024;
+
+/// Most recent crash reports included.
+const MAX_CRASH_REPORTS: usize = 5;
+
+const DIAGNOSTICS_DIR: &str = "diagnostics";
+
+/// Mask secrets and the user's home directory.
+fn redact(text: &str, home: Option<&str>) -> String {
+    let text = crate::redact::redact(text);
+    match home {
+        Some(home) if home.len() > 1 => text.replace(home, "~"),
+        _ => text,
+    }
+}
+
+/// Run `program args…` and return its first line of output, or `None` if it
+/// fails or doesn't answer within `PROBE_TIMEOUT`.
+pub(crate) fn probe(program: &Path, args: &[&str]) -> Option<String> {
+    probe_in(program, args, None)
+}
+
+/// `probe`, run in `cwd`: version managers pick the toolchain by directory.
+pub(crate) fn probe_in(program: &Path, args: &[&str], cwd: Option<&Path>) -> Option<String> {
+    let mut command = Command::new(program);
+    if let Some(cwd) = cwd {
+        command.current_dir(cwd);
+    }
+    let mut child = command
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .ok()?;
+    let started = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break,
+            Ok(None) if started.elapsed() < PROBE_TIMEOUT => {
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            _ => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+        }
+    }
+    let mut output = String::new();
+    child.stdout.take()?.read_to_string(&mut output).ok()?;
+    if output.trim().is_empty() {
+        child.stderr.take()?.read_to_string(&mut output).ok()?;
+    }
+    output
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .map(str::to_string)
+}
+
+/// PATH, plus the usual install locations a GUI app's PATH tends to miss.
+pub(crate) fn executable_dirs() -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = std::env::var_os("PATH")
+        .map(|path| std::env::split_paths(&path).collect())
+        .unwrap_or_default();
+    if let Some(home) = std::env::var_os("HOME") {
+        let home = PathBuf::from(home);
+        dirs.push(home.join(".local/bin"));
+        dirs.push(home.join(".bun/bin"));
+        dirs.push(home.join(".npm-global/bin"));
+    }
+    dirs.push(PathBuf::from("/opt/homebrew/bin"));
+    dirs.push(PathBuf::from("/usr/local/bin"));
+    dirs
+}
+
+/// Look `name` up in `executable_dirs`.
+pub(crate) fn find_executable(name: &str) -> Option<PathBuf> {
+    let dirs = executable_dirs();
+    let extensions: &[&str] = if cfg!(windows) {
+        &[".exe", ".cmd", ".bat", ""]
+    } else {
+        &[""]
+    };
+    dirs.iter().find_map(|dir| {
+        extensions
+            .iter()
+            .map(|ext| dir.join(format!("{name}{ext}")))
+            .find(|candidate| candidate.is_file())
+    })
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DetectedTool {
+    name: String,
+    path: Option<String>,
+    version: Option<String>,
+}
+
+fn detect(name: &str, version_args: &[&str]) -> DetectedTool {
+    let path = find_executable(name);
+    let version = path.as_deref().and_then(|p| probe(p, version_args));
+    DetectedTool {
+        name: name.to_string(),
+        path: path.map(|p| p.to_string_lossy().to_string()),
+        version,
+    }
+}
+
+fn detect_shells() -> Vec<DetectedTool> {
+    let mut names: Vec<String> = Vec::new();
+    if cfg!(windows) {
+        names.extend(["pwsh", "powershell", "cmd", "bash"].map(String::from));
+    } else {
+        // Login shells listed in /etc/shells, by name.
+        if let Ok(content) = std::fs::read_to_string("/etc/shells") {
+            for line in content.lines().map(str::trim) {
+                if !line.starts_with('/') {
+                    continue;
+                }
+                let name = line.rsplit('/').next().unwrap_or_default();
+                if !names.iter().any(|n| n == name) {
+                    names.push(name.to_string());
+                }
+            }
+        }
+        for name in ["bash", "zsh", "fish"] {
+            if !names.iter().any(|n| n == name) {
+                names.push(name.to_string());
+            }
+        }
+    }
+    names
+        .iter()
+        .filter(|name| name.as_str() != "nologin" && name.as_str() != "false")
+        .map(|name| {
+            // cmd.exe has no --version; its presence is enough.
+            let args: &[&str] = if name == "cmd" { &[] } else { &["--version"] };
+            detect(name, args)
+        })
+        .filter(|tool| tool.path.is_some())
+        .collect()
+}
+
+fn os_version() -> Option<String> {
+    if cfg!(target_os = "macos") {
+        probe(Path::new("sw_vers"), &["-productVersion"]).map(|v| format!("macOS {v}"))
+    } else if cfg!(windows) {
+        probe(Path::new("cmd"), &["/C", "ver"])
+    } else {
+        std::fs::read_to_string("/etc/os-release")
+            .ok()
+            .and_then(|content| {
+                content.lines().find_map(|l| {
+                    l.strip_prefix("PRETTY_NAME=")
+                        .map(|v| v.trim_matches('"').to_string())
+                })
+            })
+            .or_else(|| probe(Path::new("uname"), &["-sr"]))
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SystemInfo {
+    app_version: String,
+    sidecar_version: String,
+    release_channel: crate::updater::ReleaseChannel,
+    log_level: Option<String>,
+    os: String,
+    os_version: Option<String>,
+    arch: String,
+    default_shell: Option<String>,
+    generated_at: String,
+}
+
+/// The newest files in `dir` whose names end in `suffix`, newest first.
+fn newest_files(dir: &Path, suffix: &str, limit: usize) -> Vec<PathBuf> {
+    let mut files: Vec<(std::time::SystemTime, PathBuf)> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.is_file() && p.to_string_lossy().ends_with(suffix))
+                .filter_map(|p| Some((p.metadata().ok()?.modified().ok()?, p)))
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    files.into_iter().take(limit).map(|(_, p)| p).collect()
+}
+
+/// The last `MAX_LOG_BYTES` of a file, starting at a line boundary.
+fn read_tail(path: &Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let len = file.metadata()?.len();
+    let truncated = len > MAX_LOG_BYTES;
+    if truncated {
+        file.seek(SeekFrom::Start(len - MAX_LOG_BYTES))?;
+    }
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    let text = String::from_utf8_lossy(&bytes).into_owned();
+    Ok(match (truncated, text.find('\n')) {
+        (true, Some(i)) => text[i + 1..].to_string(),
+        _ => text,
+    })
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn to_json<T: Serialize>(value: &T) -> String {
+    serde_json::to_string_pretty(value).unwrap_or_default()
+}
+
+fn build_bundle(app: &AppHandle, destination: &Path) -> Result<(), String> {
+    let home = std::env::var("HOME")
+        .or_else(|_| std::env::var("USERPROFILE"))
+        .ok();
+    let home = home.as_deref();
+    let mut zip = ZipWriter::new(std::fs::File::create(destination).map_err(|e| e.to_string())?);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut add = |name: &str, content: &str| -> Result<(), String> {
+        zip.start_file(name, options).map_err(|e| e.to_string())?;
+        zip.write_all(redact(content, home).as_bytes())
+            .map_err(|e| e.to_string())
+    };
+
+    let system = SystemInfo {
+        app_version: app.package_info().version.to_string(),
+        sidecar_version: crate::updater::sidecar_version(app),
+        release_channel: crate::updater::release_channel(app),
+        log_level: app.try_state::<Logging>().map(|l| l.level()),
+        os: std::env::consts::OS.to_string(),
+        os_version: os_version(),
+        arch: std::env::consts::ARCH.to_string(),
+        default_shell: std::env::var("SHELL")
+            .or_else(|_| std::env::var("COMSPEC"))
+            .ok(),
+        generated_at: chrono::Utc::now().to_rfc3339(),
+    };
+    add("system.json", &to_json(&system))?;
+    add("shells.json", &to_json(&detect_shells()))?;
+    let agents: Vec<DetectedTool> = AGENT_COMMANDS
+        .iter()
+        .map(|name| detect(name, &["--version"]))
+        .collect();
+    add("agents.json", &to_json(&agents))?;
+
+    if let Ok(dir) = app.path().app_log_dir() {
+        for path in newest_files(&dir, ".log", 20) {
+            if let Ok(content) = read_tail(&path) {
+                add(&format!("logs/{}", file_name(&path)), &content)?;
+            }
+        }
+    }
+    if let Ok(dir) = app.path().app_data_dir() {
+        for path in newest_files(&dir.join("crashes"), ".json", MAX_CRASH_REPORTS) {
+            if let Ok(content) = std::fs::read_to_string(&path) {
+                add(&format!("crashes/{}", file_name(&path)), &content)?;
+            }
+        }
+    }
+
+    zip.finish().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticsBundle {
+    pub path: String,
+    pub size: u64,
+}
+
+/// Write a zip with system info, app and server versions, detected shells
+/// and agent CLIs, recent logs and crash reports, for attaching to bug
+/// reports. Secrets (API keys, tokens, passwords) and the home directory are
+/// masked. Saved to `destination`, or the app's diagnostics folder.
+#[tauri::command]
+pub async fn generate_diagnostics(
+    window: WebviewWindow,
+    app: AppHandle,
+    destination: Option<String>,
+) -> Result<DiagnosticsBundle, String> {
+    crate::require_window(&window, "main")?;
+    let path = match destination {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let dir = app
+                .path()
+                .app_data_dir()
+                .map_err(|e| e.to_string())?
+                .join(DIAGNOSTICS_DIR);
+            std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+            let stamp = chrono::Utc::now().format("%Y%m%d-%H%M%S");
+            dir.join(format!("funny-diagnostics-{stamp}.zip"))
+        }
+    };
+
+    tauri::async_runtime::spawn_blocking(move || {
+        build_bundle(&app, &path)?;
+        let size = std::fs::metadata(&path).map_err(|e| e.to_string())?.len();
+        tracing::info!("diagnostics bundle written to {}", path.display());
+        crate::analytics::record_feature(&app, "diagnostics.generate");
+        Ok(DiagnosticsBundle {
+            path: path.to_string_lossy().to_string(),
+            size,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
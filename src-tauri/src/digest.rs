@@ -0,0 +1,336 @@
+use chrono::{Local, Timelike};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, WebviewWindow};
+
+use crate::history::AgentRun;
+use crate::webhooks::WebhookKind;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// The first digest covers this many hours; later ones start where the
+/// last one ended.
+const FIRST_PERIOD_HOURS: i64 = 24;
+/// Runs listed in a webhook message; the Markdown file lists them all.
+const MAX_LISTED_RUNS: usize = 30;
+
+/// Where the digest goes: a Slack or Discord webhook whose URL is in a
+/// secret, like the notification webhooks.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DigestWebhook {
+    pub kind: WebhookKind,
+    pub secret: String,
+}
+
+/// A daily summary of the agent runs that finished since the last one, for
+/// work queued to run unattended.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DigestSettings {
+    pub enabled: bool,
+    /// Local time to send it, `HH:MM`.
+    pub at: String,
+    /// Directory the Markdown digest is written to, as `digest-<date>.md`.
+    pub directory: Option<String>,
+    pub webhook: Option<DigestWebhook>,
+}
+
+impl Default for DigestSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            at: "08:00".to_string(),
+            directory: None,
+            webhook: None,
+        }
+    }
+}
+
+pub(crate) fn validate_settings(settings: &DigestSettings) -> Result<(), String> {
+    if crate::notifications::parse_hhmm(&settings.at).is_none() {
+        return Err("the digest time must be HH:MM".to_string());
+    }
+    if let Some(directory) = &settings.directory {
+        if !Path::new(directory).is_absolute() {
+            return Err("the digest directory must be an absolute path".to_string());
+        }
+    }
+    if let Some(webhook) = &settings.webhook {
+        if webhook.secret.trim().is_empty() {
+            return Err(
+                "the digest webhook needs the name of the secret holding its URL".to_string(),
+            );
+        }
+    }
+    if settings.enabled && settings.directory.is_none() && settings.webhook.is_none() {
+        return Err("the digest needs a directory or a webhook to go to".to_string());
+    }
+    Ok(())
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Digest {
+    /// RFC 3339; runs that finished after `since` and by `until`.
+    pub since: String,
+    pub until: String,
+    pub runs: Vec<AgentRun>,
+    pub markdown: String,
+}
+
+fn local_time(rfc3339: &str) -> String {
+    chrono::DateTime::parse_from_rfc3339(rfc3339)
+        .map(|t| t.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|_| rfc3339.to_string())
+}
+
+/// `12 runs: 9 completed, 3 failed. 34 files changed, +1200 −300. $4.21.`
+fn headline(runs: &[AgentRun]) -> String {
+    let mut statuses: BTreeMap<&str, usize> = BTreeMap::new();
+    for run in runs {
+        *statuses.entry(run.status.as_str()).or_default() += 1;
+    }
+    let statuses: Vec<String> = statuses
+        .iter()
+        .map(|(status, count)| format!("{count} {status}"))
+        .collect();
+    let noun = if runs.len() == 1 { "run" } else { "runs" };
+    let mut line = format!("{} {noun}: {}.", runs.len(), statuses.join(", "));
+    let diffs: Vec<_> = runs.iter().filter_map(|r| r.diff).collect();
+    if !diffs.is_empty() {
+        let files: u32 = diffs.iter().map(|d| d.files_changed).sum();
+        let additions: u32 = diffs.iter().map(|d| d.additions).sum();
+        let deletions: u32 = diffs.iter().map(|d| d.deletions).sum();
+        line.push_str(&format!(
+            " {files} files changed, +{additions} −{deletions}."
+        ));
+    }
+    let costs: Vec<f64> = runs.iter().filter_map(|r| r.cost_usd).collect();
+    if !costs.is_empty() {
+        line.push_str(&format!(" ${:.2}.", costs.iter().sum::<f64>()));
+    }
+    line
+}
+
+fn changes(run: &AgentRun) -> String {
+    match run.diff {
+        Some(d) => format!(
+            "{} files, +{} −{}",
+            d.files_changed, d.additions, d.deletions
+        ),
+        None => "—".to_string(),
+    }
+}
+
+fn cost(run: &AgentRun) -> String {
+    run.cost_usd
+        .map(|c| format!("${c:.2}"))
+        .unwrap_or_else(|| "—".to_string())
+}
+
+fn run_title(run: &AgentRun) -> String {
+    run.title.clone().unwrap_or_else(|| run.id.clone())
+}
+
+fn project_name(run: &AgentRun) -> String {
+    run.project
+        .as_deref()
+        .map(|p| {
+            Path::new(p)
+                .file_name()
+                .map_or(p.to_string(), |n| n.to_string_lossy().to_string())
+        })
+        .unwrap_or_else(|| "—".to_string())
+}
+
+fn title(since: &str, until: &str) -> String {
+    format!("Agent runs, {} – {}", local_time(since), local_time(until))
+}
+
+fn markdown(since: &str, until: &str, runs: &[AgentRun]) -> String {
+    let cell = |text: String| text.replace('|', "\\|").replace('\n', " ");
+    let mut out = format!("# {}\n\n{}\n", title(since, until), headline(runs));
+    if runs.is_empty() {
+        return out;
+    }
+    out.push_str("\n| Run | Project | Status | Changes | Cost | Finished |\n");
+    out.push_str("| --- | --- | --- | --- | --- | --- |\n");
+    for run in runs {
+        out.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} |\n",
+            cell(run_title(run)),
+            cell(project_name(run)),
+            cell(run.status.clone()),
+            changes(run),
+            cost(run),
+            run.finished_at
+                .as_deref()
+                .map(local_time)
+                .unwrap_or_default(),
+        ));
+    }
+    out
+}
+
+/// The runs as a list short enough for a chat message.
+fn message(runs: &[AgentRun]) -> String {
+    let mut lines = vec![headline(runs)];
+    for run in runs.iter().take(MAX_LISTED_RUNS) {
+        lines.push(format!(
+            "• {} ({}): {}, {}, {}",
+            run_title(run),
+            project_name(run),
+            run.status,
+            changes(run),
+            cost(run)
+        ));
+    }
+    if runs.len() > MAX_LISTED_RUNS {
+        lines.push(format!("…and {} more", runs.len() - MAX_LISTED_RUNS));
+    }
+    lines.join("\n")
+}
+
+/// Where the last digest ended, if one was sent.
+fn last_until(app: &AppHandle) -> Result<Option<String>, String> {
+    crate::db::with(app, |conn| {
+        conn.query_row(
+            "SELECT until FROM digests ORDER BY julianday(until) DESC LIMIT 1",
+            [],
+            |row| row.get(0),
+        )
+        .optional()
+    })
+}
+
+fn build(app: &AppHandle, since: String, until: String) -> Result<Digest, String> {
+    let runs = crate::history::finished_between(app, &since, &until)?;
+    let markdown = markdown(&since, &until, &runs);
+    Ok(Digest {
+        since,
+        until,
+        runs,
+        markdown,
+    })
+}
+
+/// The digest of runs since the last one, or of the last day before the
+/// first.
+fn next(app: &AppHandle) -> Result<Digest, String> {
+    let now = chrono::Utc::now();
+    let since = match last_until(app)? {
+        Some(until) => until,
+        None => (now - chrono::Duration::hours(FIRST_PERIOD_HOURS)).to_rfc3339(),
+    };
+    build(app, since, now.to_rfc3339())
+}
+
+fn write_file(directory: &str, digest: &Digest) -> Result<PathBuf, String> {
+    std::fs::create_dir_all(directory).map_err(|e| format!("{directory}: {e}"))?;
+    let date = Local::now().format("%Y-%m-%d");
+    let path = Path::new(directory).join(format!("digest-{date}.md"));
+    std::fs::write(&path, &digest.markdown).map_err(|e| format!("{}: {e}", path.display()))?;
+    Ok(path)
+}
+
+/// Deliver the next digest to the configured targets and record its
+/// period. An empty period is recorded but not sent.
+fn send(app: &AppHandle, settings: &DigestSettings) -> Result<Digest, String> {
+    let digest = next(app)?;
+    if !digest.runs.is_empty() {
+        if let Some(directory) = &settings.directory {
+            let path = write_file(directory, &digest)?;
+            tracing::info!("digest written to {}", path.display());
+        }
+        if let Some(webhook) = &settings.webhook {
+            crate::webhooks::post_message(
+                webhook.kind,
+                &webhook.secret,
+                &title(&digest.since, &digest.until),
+                &message(&digest.runs),
+            )?;
+        }
+    }
+    crate::db::with(app, |conn| {
+        conn.execute(
+            "INSERT OR REPLACE INTO digests (until, since, runs) VALUES (?1, ?2, ?3)",
+            params![digest.until, digest.since, digest.runs.len()],
+        )
+    })?;
+    Ok(digest)
+}
+
+/// Whether today's digest is due: past `at`, and none sent yet today.
+fn due(app: &AppHandle, settings: &DigestSettings) -> Result<bool, String> {
+    let at = match crate::notifications::parse_hhmm(&settings.at) {
+        Some(at) => at,
+        None => return Ok(false),
+    };
+    let now = Local::now();
+    if now.hour() * 60 + now.minute() < at {
+        return Ok(false);
+    }
+    let today = now.format("%Y-%m-%d").to_string();
+    let sent_today = last_until(app)?.is_some_and(|until| local_time(&until).starts_with(&today));
+    Ok(!sent_today)
+}
+
+/// Check once a minute whether the digest is due. Called once from `setup`.
+pub fn init(app: &AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || loop {
+        std::thread::sleep(CHECK_INTERVAL);
+        let settings = crate::settings::current(&app).digest;
+        if !settings.enabled {
+            continue;
+        }
+        let result = due(&app, &settings).and_then(|due| match due {
+            true => send(&app, &settings).map(|d| {
+                tracing::info!("digest of {} runs sent", d.runs.len());
+            }),
+            false => Ok(()),
+        });
+        if let Err(e) = result {
+            tracing::warn!("digest failed: {e}");
+        }
+    });
+}
+
+/// The digest that would go out next, without sending it. `hours` looks
+/// back that far instead of to the last digest.
+#[tauri::command]
+pub async fn digest_preview(
+    window: WebviewWindow,
+    app: AppHandle,
+    hours: Option<u32>,
+) -> Result<Digest, String> {
+    crate::require_app_window(&window)?;
+    tauri::async_runtime::spawn_blocking(move || match hours {
+        Some(hours) => {
+            let now = chrono::Utc::now();
+            let since = now - chrono::Duration::hours(hours.into());
+            build(&app, since.to_rfc3339(), now.to_rfc3339())
+        }
+        None => next(&app),
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Send the next digest now rather than at the set time.
+#[tauri::command]
+pub async fn digest_send(window: WebviewWindow, app: AppHandle) -> Result<Digest, String> {
+    crate::require_window(&window, "main")?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let settings = crate::settings::current(&app).digest;
+        if settings.directory.is_none() && settings.webhook.is_none() {
+            return Err("set a directory or a webhook for the digest first".to_string());
+        }
+        send(&app, &settings)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,232 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State, WebviewWindow};
+
+use crate::forge::run_capture;
+use crate::sandbox::Sandbox;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DiskSettings {
+    /// Free space to leave on a project's disk. Worktrees and scratch copies
+    /// that would go below it are refused, and `disk:low` is sent when a
+    /// project's disk drops below it. 0 turns the guard off.
+    pub min_free_mb: u32,
+}
+
+impl Default for DiskSettings {
+    fn default() -> Self {
+        Self { min_free_mb: 2048 }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskSpace {
+    pub path: String,
+    pub free_bytes: u64,
+    pub min_free_bytes: u64,
+    /// What the refused operation needed, for `disk:low` sent on a refusal.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub needed_bytes: Option<u64>,
+}
+
+/// `path`, or its closest ancestor that exists, as a worktree about to be
+/// created doesn't.
+fn existing(path: &Path) -> Option<&Path> {
+    path.ancestors().find(|p| p.exists())
+}
+
+/// Free space available to this user on the disk holding `path`.
+#[cfg(unix)]
+pub(crate) fn free_bytes(path: &Path) -> Result<u64, String> {
+    let dir = existing(path).ok_or_else(|| format!("{} does not exist", path.display()))?;
+    // POSIX output: the available 1024-byte blocks come third from the
+    // right, as the filesystem name may hold spaces.
+    let out = run_capture(Command::new("df").arg("-Pk").arg(dir), None)?;
+    let fields: Vec<&str> = out
+        .lines()
+        .nth(1)
+        .map(|line| line.split_whitespace().collect())
+        .unwrap_or_default();
+    fields
+        .len()
+        .checked_sub(3)
+        .and_then(|i| fields[i].parse::<u64>().ok())
+        .map(|kib| kib * 1024)
+        .ok_or_else(|| format!("unexpected df output: {}", out.trim()))
+}
+
+#[cfg(windows)]
+pub(crate) fn free_bytes(path: &Path) -> Result<u64, String> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let dir = existing(path).ok_or_else(|| format!("{} does not exist", path.display()))?;
+    let name: Vec<u16> = dir.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut available: u64 = 0;
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            name.as_ptr(),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    if ok == 0 {
+        return Err(std::io::Error::last_os_error().to_string());
+    }
+    Ok(available)
+}
+
+/// The size of the files checked in at `root`'s HEAD: what a new worktree
+/// of it takes, give or take build output.
+pub(crate) fn checkout_bytes(root: &Path) -> u64 {
+    let out = match run_capture(
+        Command::new("git")
+            .args(["ls-tree", "-r", "-l", "HEAD"])
+            .current_dir(root),
+        None,
+    ) {
+        Ok(out) => out,
+        Err(_) => return 0,
+    };
+    // `<mode> <type> <object> <size>\t<path>`; submodules have `-`.
+    out.lines()
+        .filter_map(|line| line.split('\t').next()?.split_whitespace().nth(3))
+        .filter_map(|size| size.parse::<u64>().ok())
+        .sum()
+}
+
+/// The size of everything under `root`, symlinks not followed.
+pub(crate) fn tree_bytes(root: &Path) -> u64 {
+    let meta = match std::fs::symlink_metadata(root) {
+        Ok(meta) => meta,
+        Err(_) => return 0,
+    };
+    if !meta.is_dir() {
+        return meta.len();
+    }
+    std::fs::read_dir(root)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| tree_bytes(&entry.path()))
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+fn min_free_bytes(app: &AppHandle) -> u64 {
+    u64::from(crate::settings::current(app).disk.min_free_mb) * 1024 * 1024
+}
+
+fn warn(app: &AppHandle, space: &DiskSpace) {
+    if let Err(e) = app.emit_to("main", "disk:low", space.clone()) {
+        tracing::warn!("failed to emit disk:low: {e}");
+    }
+}
+
+/// Refuse, and send `disk:low`, if writing `needed` bytes at `path` would
+/// leave less than the configured free space. Goes ahead when the free
+/// space can't be read.
+pub(crate) fn ensure_space(app: &AppHandle, path: &Path, needed: u64) -> Result<(), String> {
+    let min_free = min_free_bytes(app);
+    if min_free == 0 {
+        return Ok(());
+    }
+    let free = match free_bytes(path) {
+        Ok(free) => free,
+        Err(e) => {
+            tracing::debug!("free space of {} unknown: {e}", path.display());
+            return Ok(());
+        }
+    };
+    if free >= needed.saturating_add(min_free) {
+        return Ok(());
+    }
+    warn(
+        app,
+        &DiskSpace {
+            path: path.to_string_lossy().to_string(),
+            free_bytes: free,
+            min_free_bytes: min_free,
+            needed_bytes: Some(needed),
+        },
+    );
+    Err(format!(
+        "not enough disk space at {}: {} MB free, {} MB needed plus {} MB kept free",
+        path.display(),
+        free / (1024 * 1024),
+        needed.div_ceil(1024 * 1024),
+        min_free / (1024 * 1024),
+    ))
+}
+
+/// Check the registered projects' disks every few minutes and send
+/// `disk:low` when one drops below the configured free space, once until it
+/// recovers. Called once from `setup`.
+pub fn init(app: &AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let mut low: HashSet<PathBuf> = HashSet::new();
+        loop {
+            std::thread::sleep(CHECK_INTERVAL);
+            let min_free = min_free_bytes(&app);
+            let projects = app.state::<Sandbox>().projects();
+            low.retain(|p| projects.contains(p));
+            for project in projects {
+                let free = match free_bytes(&project) {
+                    Ok(free) => free,
+                    Err(_) => continue,
+                };
+                if free >= min_free {
+                    low.remove(&project);
+                } else if low.insert(project.clone()) {
+                    tracing::warn!(
+                        "{} MB free on the disk of {}",
+                        free / (1024 * 1024),
+                        project.display()
+                    );
+                    warn(
+                        &app,
+                        &DiskSpace {
+                            path: project.to_string_lossy().to_string(),
+                            free_bytes: free,
+                            min_free_bytes: min_free,
+                            needed_bytes: None,
+                        },
+                    );
+                }
+            }
+        }
+    });
+}
+
+/// Free space on the disk of `path` inside a project, for checking before
+/// work the server does, like starting an agent or a container.
+#[tauri::command]
+pub async fn disk_space(
+    window: WebviewWindow,
+    app: AppHandle,
+    sandbox: State<'_, Sandbox>,
+    path: String,
+) -> Result<DiskSpace, String> {
+    crate::require_app_window(&window)?;
+    let dir = sandbox.check_dir(&path)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        Ok(DiskSpace {
+            path: dir.to_string_lossy().to_string(),
+            free_bytes: free_bytes(&dir)?,
+            min_free_bytes: min_free_bytes(&app),
+            needed_bytes: None,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,326 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State, WebviewWindow};
+
+use crate::audit::{self, AuditEvent, AuditKind, Outcome};
+use crate::cancel::CancelToken;
+use crate::fs::{FileStat, FsOpFailure, FsOpResult, WriteFileOptions};
+use crate::policy::WriteSummary;
+use crate::sandbox::Sandbox;
+
+/// A write an agent asked for in dry-run mode, held back from disk.
+struct Captured {
+    /// What the write was asked with, to replay it on apply.
+    root: String,
+    path: String,
+    /// Shown to the user: the file in the project, or an `ssh://` URI.
+    target: String,
+    /// The file before the first captured write; `None` if it didn't exist.
+    original: Option<String>,
+    /// Its mtime then, so apply refuses files changed on disk since.
+    modified_ms: Option<i64>,
+    content: String,
+    encoding: Option<String>,
+    bom: Option<bool>,
+}
+
+/// One agent session's overlay: project, then path in it → the file as the
+/// agent would have left it.
+type Overlay = BTreeMap<(String, String), Captured>;
+
+/// Agent runs in dry-run mode, and the writes they made there. Their reads
+/// through the backend see the overlay, so the run goes on as if the writes
+/// had landed.
+pub struct DryRun {
+    sessions: Mutex<HashSet<String>>,
+    changes: Mutex<HashMap<String, Overlay>>,
+}
+
+impl DryRun {
+    pub fn new() -> Self {
+        Self {
+            sessions: Mutex::new(HashSet::new()),
+            changes: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+/// A write for `capture` to hold back.
+pub(crate) struct Capture {
+    pub root: String,
+    pub path: String,
+    /// The canonical project root or its URI, and the path relative to it.
+    pub project: String,
+    pub relative: String,
+    pub target: String,
+    pub original: Option<String>,
+    pub modified_ms: Option<i64>,
+    pub content: String,
+    pub encoding: Option<String>,
+    pub bom: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingChange {
+    pub project: String,
+    /// Relative to the project.
+    pub path: String,
+    pub target: String,
+    pub summary: WriteSummary,
+    /// Both sides of the diff; `original` is `None` for a new file.
+    pub original: Option<String>,
+    pub content: String,
+}
+
+/// Whether `agent`'s writes are being captured rather than made.
+pub(crate) fn active(app: &AppHandle, agent: Option<&str>) -> bool {
+    agent.is_some_and(|agent| {
+        app.state::<DryRun>()
+            .sessions
+            .lock()
+            .is_ok_and(|sessions| sessions.contains(agent))
+    })
+}
+
+fn changed(app: &AppHandle, session: &str, pending: usize) {
+    if let Err(e) = app.emit_to(
+        "main",
+        "dry-run:changed",
+        serde_json::json!({ "session": session, "pending": pending }),
+    ) {
+        tracing::debug!("dry-run:changed not delivered: {e}");
+    }
+}
+
+/// Hold `write` back for `agent`, on top of what it already wrote to the
+/// same file, and answer as the write would have.
+pub(crate) fn capture(app: &AppHandle, agent: &str, write: Capture) -> Result<FileStat, String> {
+    let stat = FileStat {
+        path: write.target.clone(),
+        kind: "file".to_string(),
+        size: write.content.len() as u64,
+        modified_ms: None,
+        readonly: false,
+    };
+    let state = app.state::<DryRun>();
+    let mut changes = state.changes.lock().map_err(|e| e.to_string())?;
+    let overlay = changes.entry(agent.to_string()).or_default();
+    let key = (write.project, write.relative);
+    match overlay.get_mut(&key) {
+        Some(captured) => {
+            captured.content = write.content;
+            captured.encoding = write.encoding;
+            captured.bom = write.bom;
+        }
+        None => {
+            overlay.insert(
+                key,
+                Captured {
+                    root: write.root,
+                    path: write.path,
+                    target: write.target.clone(),
+                    original: write.original,
+                    modified_ms: write.modified_ms,
+                    content: write.content,
+                    encoding: write.encoding,
+                    bom: write.bom,
+                },
+            );
+        }
+    }
+    let pending = overlay.len();
+    drop(changes);
+    tracing::info!(target: "agents", "dry run captured a write from {agent}: {}", write.target);
+    changed(app, agent, pending);
+    Ok(stat)
+}
+
+/// What `agent` wrote to `relative` in `project` during its dry run, for
+/// its reads to see.
+pub(crate) fn overlay(
+    app: &AppHandle,
+    agent: &str,
+    project: &str,
+    relative: &str,
+) -> Option<String> {
+    let state = app.state::<DryRun>();
+    let changes = state.changes.lock().ok()?;
+    let key = (project.to_string(), relative.replace('\\', "/"));
+    changes.get(agent)?.get(&key).map(|c| c.content.clone())
+}
+
+/// Put an agent run in or out of dry-run mode. Leaving it keeps what was
+/// captured, for `apply_pending_changes` or `discard_pending_changes`.
+#[tauri::command]
+pub fn dry_run_set(
+    window: WebviewWindow,
+    state: State<'_, DryRun>,
+    session: String,
+    enabled: bool,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    let mut sessions = state.sessions.lock().map_err(|e| e.to_string())?;
+    let changed = if enabled {
+        sessions.insert(session.clone())
+    } else {
+        sessions.remove(&session)
+    };
+    if changed {
+        tracing::info!(target: "agents", "dry run {} for {session}", if enabled { "on" } else { "off" });
+    }
+    Ok(())
+}
+
+/// The writes `session` made in dry-run mode, as diffs to review.
+#[tauri::command]
+pub fn pending_changes(
+    window: WebviewWindow,
+    state: State<'_, DryRun>,
+    session: String,
+) -> Result<Vec<PendingChange>, String> {
+    crate::require_app_window(&window)?;
+    let changes = state.changes.lock().map_err(|e| e.to_string())?;
+    let Some(overlay) = changes.get(&session) else {
+        return Ok(Vec::new());
+    };
+    Ok(overlay
+        .iter()
+        .map(|((project, path), captured)| PendingChange {
+            project: project.clone(),
+            path: path.clone(),
+            target: captured.target.clone(),
+            summary: crate::policy::summarize_write(
+                captured.original.as_deref(),
+                &captured.content,
+            ),
+            original: captured.original.clone(),
+            content: captured.content.clone(),
+        })
+        .collect())
+}
+
+/// Write what `session` captured in dry-run mode to disk, as the user's own
+/// edits once they have reviewed it. A file changed on disk since the agent
+/// wrote it fails and stays pending, as do other failures.
+#[tauri::command]
+pub async fn apply_pending_changes(
+    window: WebviewWindow,
+    app: AppHandle,
+    session: String,
+) -> Result<FsOpResult, String> {
+    crate::require_window(&window, "main")?;
+    let overlay = app
+        .state::<DryRun>()
+        .changes
+        .lock()
+        .map_err(|e| e.to_string())?
+        .remove(&session)
+        .unwrap_or_default();
+    tauri::async_runtime::spawn_blocking(move || {
+        let sandbox = app.state::<Sandbox>();
+        let cancel = CancelToken::default();
+        let mut result = FsOpResult {
+            completed: Vec::new(),
+            failed: Vec::new(),
+        };
+        let mut kept = Overlay::new();
+        for (key, captured) in overlay {
+            let options = WriteFileOptions {
+                encoding: captured.encoding.clone(),
+                bom: captured.bom,
+                create_dirs: Some(true),
+                expected_modified_ms: captured.modified_ms,
+                ..Default::default()
+            };
+            let written = match sandbox.remote(&captured.root) {
+                Ok(Some(remote)) => crate::fs::write_remote_blocking(
+                    &app,
+                    &remote,
+                    &captured.path,
+                    &captured.content,
+                    &options,
+                    &cancel,
+                ),
+                Ok(None) => crate::fs::write_local(
+                    &app,
+                    &sandbox,
+                    &captured.root,
+                    &captured.path,
+                    &captured.content,
+                    &options,
+                    &cancel,
+                ),
+                Err(e) => Err(e),
+            };
+            match written {
+                Ok(_) => result.completed.push(captured.target),
+                Err(error) => {
+                    result.failed.push(FsOpFailure {
+                        path: captured.target.clone(),
+                        error,
+                    });
+                    kept.insert(key, captured);
+                }
+            }
+        }
+        audit::record(
+            &app,
+            AuditEvent {
+                kind: AuditKind::FileWrite,
+                agent: Some(&session),
+                project: None,
+                target: &session,
+                outcome: if result.failed.is_empty() {
+                    Outcome::Ok
+                } else {
+                    Outcome::Error
+                },
+                detail: Some(format!(
+                    "applied {} dry-run change(s), {} failed",
+                    result.completed.len(),
+                    result.failed.len()
+                )),
+            },
+        );
+        let state = app.state::<DryRun>();
+        let mut changes = state.changes.lock().map_err(|e| e.to_string())?;
+        // Writes captured while this ran are newer; keep them over ours.
+        let overlay = changes.entry(session.clone()).or_default();
+        for (key, captured) in kept {
+            overlay.entry(key).or_insert(captured);
+        }
+        let pending = overlay.len();
+        if pending == 0 {
+            changes.remove(&session);
+        }
+        drop(changes);
+        changed(&app, &session, pending);
+        Ok(result)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Drop what `session` captured in dry-run mode without writing it.
+#[tauri::command]
+pub fn discard_pending_changes(
+    window: WebviewWindow,
+    app: AppHandle,
+    state: State<'_, DryRun>,
+    session: String,
+) -> Result<usize, String> {
+    crate::require_app_window(&window)?;
+    let dropped = state
+        .changes
+        .lock()
+        .map_err(|e| e.to_string())?
+        .remove(&session)
+        .map_or(0, |overlay| overlay.len());
+    if dropped > 0 {
+        tracing::info!(target: "agents", "discarded {dropped} dry-run change(s) from {session}");
+        changed(&app, &session, 0);
+    }
+    Ok(dropped)
+}
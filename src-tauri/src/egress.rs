@@ -0,0 +1,413 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io::{Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State, WebviewWindow};
+
+/// Largest request head the proxy reads before giving up on a client.
+const MAX_HEAD_BYTES: usize = 16 * 1024;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// How long a client has to send its request head. Tunnels, once set up,
+/// have no timeout: a download can keep the client quiet for long.
+const HEAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Where the proxy variables point when the proxy couldn't start: nothing
+/// listens on port 0, so every proxied connection fails.
+const CLOSED_PROXY: &str = "http://127.0.0.1:0";
+
+/// Blocked requests kept for `egress_violations`.
+const MAX_VIOLATIONS: usize = 200;
+
+/// Which hosts agents may reach when egress is restricted. Kept in the
+/// settings file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NetworkSettings {
+    /// Route the server and its agents through the filtering proxy. Takes
+    /// effect when the server next starts.
+    pub restrict_egress: bool,
+    /// `example.com` also allows its subdomains; `*.example.com` allows only
+    /// the subdomains; `*` allows everything.
+    pub allowed_domains: Vec<String>,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        Self {
+            restrict_egress: false,
+            allowed_domains: [
+                "api.anthropic.com",
+                "api.openai.com",
+                "generativelanguage.googleapis.com",
+                "github.com",
+                "githubusercontent.com",
+                "registry.npmjs.org",
+                "pypi.org",
+                "files.pythonhosted.org",
+            ]
+            .map(String::from)
+            .to_vec(),
+        }
+    }
+}
+
+pub(crate) fn validate_settings(settings: &NetworkSettings) -> Result<(), String> {
+    for domain in &settings.allowed_domains {
+        let bare = domain.strip_prefix("*.").unwrap_or(domain);
+        let valid = domain == "*"
+            || (!bare.is_empty()
+                && bare
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.'));
+        if !valid {
+            return Err(format!(
+                "allowed domain {domain:?} must be a host name like example.com"
+            ));
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn host_allowed(host: &str, allowed: &[String]) -> bool {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    // The server and agents talking to each other never leave the machine.
+    if host == "localhost" || host == "127.0.0.1" || host == "::1" {
+        return true;
+    }
+    allowed.iter().any(|entry| {
+        let entry = entry.to_ascii_lowercase();
+        match entry.strip_prefix("*.") {
+            _ if entry == "*" => true,
+            Some(parent) => host.ends_with(&format!(".{parent}")),
+            None => host == entry || host.ends_with(&format!(".{entry}")),
+        }
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Violation {
+    pub timestamp: String,
+    pub host: String,
+    pub port: u16,
+}
+
+/// The local filtering proxy, started the first time restricted egress is
+/// needed, and the requests it refused.
+pub struct Egress {
+    port: Mutex<Option<u16>>,
+    /// Why the proxy failed to start, the last time it did.
+    error: Mutex<Option<String>>,
+    violations: Mutex<VecDeque<Violation>>,
+}
+
+impl Egress {
+    pub fn new() -> Self {
+        Self {
+            port: Mutex::new(None),
+            error: Mutex::new(None),
+            violations: Mutex::new(VecDeque::new()),
+        }
+    }
+}
+
+/// Read up to the end of the request head. Returns the head and whatever
+/// body bytes came along with it.
+pub(crate) fn read_head(stream: &mut TcpStream) -> Option<(String, Vec<u8>)> {
+    let mut data = Vec::new();
+    let mut buf = [0u8; 4096];
+    loop {
+        let n = stream.read(&mut buf).ok()?;
+        if n == 0 {
+            return None;
+        }
+        data.extend_from_slice(&buf[..n]);
+        if let Some(end) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+            let rest = data.split_off(end + 4);
+            return Some((String::from_utf8_lossy(&data).into_owned(), rest));
+        }
+        if data.len() > MAX_HEAD_BYTES {
+            return None;
+        }
+    }
+}
+
+/// Split `host[:port]`, with IPv6 literals in brackets.
+fn split_host_port(authority: &str, default_port: u16) -> Option<(String, u16)> {
+    if let Some(rest) = authority.strip_prefix('[') {
+        let (host, after) = rest.split_once(']')?;
+        let port = match after.strip_prefix(':') {
+            Some(port) => port.parse().ok()?,
+            None => default_port,
+        };
+        return Some((host.to_string(), port));
+    }
+    match authority.rsplit_once(':') {
+        Some((host, port)) => Some((host.to_string(), port.parse().ok()?)),
+        None => Some((authority.to_string(), default_port)),
+    }
+}
+
+/// Copy bytes both ways until either side closes.
+fn pipe(client: TcpStream, upstream: TcpStream) {
+    let (mut client_read, mut upstream_write) = match (client.try_clone(), upstream.try_clone()) {
+        (Ok(c), Ok(u)) => (c, u),
+        _ => return,
+    };
+    let forward = std::thread::spawn(move || {
+        let _ = std::io::copy(&mut client_read, &mut upstream_write);
+        let _ = upstream_write.shutdown(Shutdown::Write);
+    });
+    let (mut upstream_read, mut client_write) = (upstream, client);
+    let _ = std::io::copy(&mut upstream_read, &mut client_write);
+    let _ = client_write.shutdown(Shutdown::Write);
+    let _ = forward.join();
+}
+
+fn refuse(app: &AppHandle, mut client: TcpStream, host: &str, port: u16) {
+    tracing::warn!(target: "agents", "blocked network access to {host}:{port}");
+    let target = format!("{host}:{port}");
+    crate::audit::record(
+        app,
+        crate::audit::AuditEvent {
+            kind: crate::audit::AuditKind::Network,
+            agent: None,
+            project: None,
+            target: &target,
+            outcome: crate::audit::Outcome::Denied,
+            detail: None,
+        },
+    );
+    let violation = Violation {
+        timestamp: chrono::Utc::now().to_rfc3339(),
+        host: host.to_string(),
+        port,
+    };
+    if let Ok(mut violations) = app.state::<Egress>().violations.lock() {
+        if violations.len() == MAX_VIOLATIONS {
+            violations.pop_front();
+        }
+        violations.push_back(violation.clone());
+    }
+    if let Err(e) = app.emit_to("main", "egress:blocked", violation) {
+        tracing::debug!("egress:blocked not delivered: {e}");
+    }
+    let body = format!("funny: network access to {host} is not allowed\n");
+    let response = format!(
+        "HTTP/1.1 403 Forbidden\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    let _ = client.write_all(response.as_bytes());
+}
+
+fn connect(host: &str, port: u16) -> Option<TcpStream> {
+    use std::net::ToSocketAddrs;
+    let addr = (host, port).to_socket_addrs().ok()?.next()?;
+    TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT).ok()
+}
+
+/// Serve one proxy client: `CONNECT` tunnels for HTTPS, absolute-URI
+/// requests for plain HTTP.
+fn handle(app: &AppHandle, mut client: TcpStream) {
+    if client.set_read_timeout(Some(HEAD_TIMEOUT)).is_err() {
+        return;
+    }
+    let (head, rest) = match read_head(&mut client) {
+        Some(head) => head,
+        None => return,
+    };
+    if client.set_read_timeout(None).is_err() {
+        return;
+    }
+    let mut request_line = head.lines().next().unwrap_or_default().split_whitespace();
+    let (method, target) = match (request_line.next(), request_line.next()) {
+        (Some(method), Some(target)) => (method.to_string(), target.to_string()),
+        _ => return,
+    };
+
+    let (authority, default_port, origin_form) = if method.eq_ignore_ascii_case("CONNECT") {
+        (target.clone(), 443, None)
+    } else {
+        let without_scheme = match target.strip_prefix("http://") {
+            Some(rest) => rest,
+            // Only plain HTTP arrives in the clear; HTTPS comes as CONNECT.
+            None => return,
+        };
+        let (authority, path) = match without_scheme.find('/') {
+            Some(i) => without_scheme.split_at(i),
+            None => (without_scheme, "/"),
+        };
+        (authority.to_string(), 80, Some(path.to_string()))
+    };
+    let (host, port) = match split_host_port(&authority, default_port) {
+        Some(parts) => parts,
+        None => return,
+    };
+
+    let allowed = crate::settings::current(app).network.allowed_domains;
+    if !host_allowed(&host, &allowed) {
+        refuse(app, client, &host, port);
+        return;
+    }
+    let mut upstream = match connect(&host, port) {
+        Some(upstream) => upstream,
+        None => {
+            let _ = client.write_all(b"HTTP/1.1 502 Bad Gateway\r\nConnection: close\r\n\r\n");
+            return;
+        }
+    };
+
+    let forwarded = match origin_form {
+        None => client.write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n"),
+        Some(path) => {
+            // Re-send the request to the origin in origin-form.
+            let head = head.replacen(&target, &path, 1);
+            upstream
+                .write_all(head.as_bytes())
+                .and_then(|_| upstream.write_all(&rest))
+        }
+    };
+    if forwarded.is_ok() {
+        pipe(client, upstream);
+    }
+}
+
+/// Start the proxy if it isn't running and return its port.
+fn ensure_started(app: &AppHandle) -> Result<u16, String> {
+    let egress = app.state::<Egress>();
+    let mut port = egress.port.lock().map_err(|e| e.to_string())?;
+    if let Some(port) = *port {
+        return Ok(port);
+    }
+    let listener = TcpListener::bind("127.0.0.1:0").map_err(|e| e.to_string())?;
+    let bound = listener.local_addr().map_err(|e| e.to_string())?.port();
+    let app = app.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let app = app.clone();
+            std::thread::spawn(move || handle(&app, stream));
+        }
+    });
+    tracing::info!("egress proxy listening on 127.0.0.1:{bound}");
+    *port = Some(bound);
+    Ok(bound)
+}
+
+/// Proxy variables for the server sidecar, and through it every agent, when
+/// egress is restricted. Tools that ignore proxy variables aren't covered.
+/// If the proxy can't start, they point at a closed port instead, so
+/// restricted egress fails closed; the error goes to `egress_status` and an
+/// `egress:error` event.
+pub(crate) fn proxy_env(app: &AppHandle) -> Vec<(String, String)> {
+    if !crate::settings::current(app).network.restrict_egress {
+        return Vec::new();
+    }
+    let started = ensure_started(app);
+    if let Ok(mut error) = app.state::<Egress>().error.lock() {
+        *error = started.as_ref().err().cloned();
+    }
+    let proxy = match started {
+        Ok(port) => format!("http://127.0.0.1:{port}"),
+        Err(e) => {
+            tracing::error!("failed to start egress proxy, blocking all proxied egress: {e}");
+            if let Err(e) = app.emit_to("main", "egress:error", &e) {
+                tracing::debug!("egress:error not delivered: {e}");
+            }
+            CLOSED_PROXY.to_string()
+        }
+    };
+    let mut env = Vec::new();
+    for name in ["HTTP_PROXY", "HTTPS_PROXY", "ALL_PROXY"] {
+        env.push((name.to_string(), proxy.clone()));
+        env.push((name.to_lowercase(), proxy.clone()));
+    }
+    for name in ["NO_PROXY", "no_proxy"] {
+        env.push((name.to_string(), "localhost,127.0.0.1,::1".to_string()));
+    }
+    env
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EgressStatus {
+    /// Whether restriction is switched on in the settings.
+    pub restricted: bool,
+    /// Port of the running proxy; `None` until the server starts with
+    /// restriction on.
+    pub proxy_port: Option<u16>,
+    /// Why the proxy failed to start; egress is then blocked altogether.
+    pub error: Option<String>,
+    pub allowed_domains: Vec<String>,
+}
+
+#[tauri::command]
+pub fn egress_status(
+    window: WebviewWindow,
+    app: AppHandle,
+    state: State<'_, Egress>,
+) -> Result<EgressStatus, String> {
+    crate::require_app_window(&window)?;
+    let network = crate::settings::current(&app).network;
+    Ok(EgressStatus {
+        restricted: network.restrict_egress,
+        proxy_port: *state.port.lock().map_err(|e| e.to_string())?,
+        error: state.error.lock().map_err(|e| e.to_string())?.clone(),
+        allowed_domains: network.allowed_domains,
+    })
+}
+
+/// Requests the proxy refused, newest last. Each one is also emitted as
+/// `egress:blocked` when it happens.
+#[tauri::command]
+pub fn egress_violations(
+    window: WebviewWindow,
+    state: State<'_, Egress>,
+) -> Result<Vec<Violation>, String> {
+    crate::require_app_window(&window)?;
+    let violations = state.violations.lock().map_err(|e| e.to_string())?;
+    Ok(violations.iter().cloned().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allowed(entries: &[&str]) -> Vec<String> {
+        entries.iter().map(|entry| entry.to_string()).collect()
+    }
+
+    #[test]
+    fn loopback_is_always_allowed() {
+        for host in ["localhost", "127.0.0.1", "::1", "LOCALHOST."] {
+            assert!(host_allowed(host, &[]), "{host}");
+        }
+    }
+
+    #[test]
+    fn plain_entries_allow_the_host_and_its_subdomains() {
+        let list = allowed(&["github.com"]);
+        assert!(host_allowed("github.com", &list));
+        assert!(host_allowed("api.GitHub.com.", &list));
+        assert!(!host_allowed("evilgithub.com", &list));
+        assert!(!host_allowed("github.com.evil.net", &list));
+    }
+
+    #[test]
+    fn wildcard_entries_allow_only_subdomains() {
+        let list = allowed(&["*.example.org"]);
+        assert!(host_allowed("a.example.org", &list));
+        assert!(host_allowed("a.b.example.org", &list));
+        assert!(!host_allowed("example.org", &list));
+        assert!(!host_allowed("badexample.org", &list));
+    }
+
+    #[test]
+    fn star_allows_everything_and_nothing_allows_nothing() {
+        assert!(host_allowed("anything.net", &allowed(&["*"])));
+        assert!(!host_allowed("anything.net", &[]));
+    }
+}
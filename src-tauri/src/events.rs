@@ -0,0 +1,472 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, EventTarget, Manager, State, WebviewWindow};
+
+/// Which windows listen to which backend resource. A topic names one
+/// resource (`watch:<id>`, `forge:<cwd>#<number>`); its events are sent only
+/// to the windows subscribed to it instead of being broadcast to every
+/// webview.
+pub struct EventRouter {
+    topics: Mutex<HashMap<String, HashSet<String>>>,
+}
+
+impl EventRouter {
+    pub fn new() -> Self {
+        Self {
+            topics: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub(crate) fn subscribe(&self, topic: &str, label: &str) {
+        if let Ok(mut topics) = self.topics.lock() {
+            topics
+                .entry(topic.to_string())
+                .or_default()
+                .insert(label.to_string());
+        }
+    }
+
+    /// Remove `label` from `topic`. Returns true when nobody is left
+    /// listening, so the caller can stop the resource.
+    pub(crate) fn unsubscribe(&self, topic: &str, label: &str) -> bool {
+        let mut topics = match self.topics.lock() {
+            Ok(topics) => topics,
+            Err(_) => return false,
+        };
+        let empty = match topics.get_mut(topic) {
+            Some(labels) => {
+                labels.remove(label);
+                labels.is_empty()
+            }
+            None => true,
+        };
+        if empty {
+            topics.remove(topic);
+        }
+        empty
+    }
+
+    /// Forget a topic whose resource went away on its own.
+    pub(crate) fn remove_topic(&self, topic: &str) {
+        if let Ok(mut topics) = self.topics.lock() {
+            topics.remove(topic);
+        }
+    }
+
+    /// Drop every subscription of a closed window. Returns the topics nobody
+    /// listens to anymore.
+    pub(crate) fn window_closed(&self, label: &str) -> Vec<String> {
+        let mut topics = match self.topics.lock() {
+            Ok(topics) => topics,
+            Err(_) => return Vec::new(),
+        };
+        let mut orphaned = Vec::new();
+        topics.retain(|topic, labels| {
+            if labels.remove(label) && labels.is_empty() {
+                orphaned.push(topic.clone());
+                return false;
+            }
+            true
+        });
+        orphaned
+    }
+
+    fn subscribers(&self, topic: &str) -> Vec<String> {
+        self.topics
+            .lock()
+            .ok()
+            .and_then(|topics| topics.get(topic).map(|l| l.iter().cloned().collect()))
+            .unwrap_or_default()
+    }
+}
+
+/// Send `event` to the windows subscribed to `topic`. Nothing is sent when
+/// nobody is subscribed.
+pub(crate) fn emit_subscribed<S: Serialize + Clone>(
+    app: &AppHandle,
+    topic: &str,
+    event: &str,
+    payload: S,
+) {
+    let labels = match app.try_state::<EventRouter>() {
+        Some(router) => router.subscribers(topic),
+        None => return,
+    };
+    for label in labels {
+        let _ = app.emit_to(EventTarget::webview_window(label), event, payload.clone());
+    }
+}
+
+/// `emit_subscribed`, throttled per window with `emit_throttled`.
+pub(crate) fn emit_subscribed_throttled<S: Serialize>(
+    app: &AppHandle,
+    topic: &str,
+    class: &str,
+    event: &str,
+    coalesce: Coalesce,
+    payload: S,
+) {
+    let labels = match app.try_state::<EventRouter>() {
+        Some(router) => router.subscribers(topic),
+        None => return,
+    };
+    let payload = match serde_json::to_value(payload) {
+        Ok(payload) => payload,
+        Err(_) => return,
+    };
+    for label in labels {
+        emit_throttled(app, &label, topic, class, event, coalesce, payload.clone());
+    }
+}
+
+/// How long a burst of an event class is held back before the latest state
+/// goes out, by class. Kept in the settings file; 0 sends every event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct EventSettings {
+    pub throttle_ms: BTreeMap<String, u64>,
+}
+
+impl Default for EventSettings {
+    fn default() -> Self {
+        Self {
+            throttle_ms: [
+                (PTY_DATA, 8),
+                (FS_CHANGED, 100),
+                (FORGE_PR_STATUS, 1000),
+                (PROBLEMS, 250),
+            ]
+            .into_iter()
+            .map(|(class, ms)| (class.to_string(), ms))
+            .collect(),
+        }
+    }
+}
+
+pub(crate) const PTY_DATA: &str = "pty:data";
+pub(crate) const FS_CHANGED: &str = "fs:changed";
+pub(crate) const FORGE_PR_STATUS: &str = "forge:pr-status";
+pub(crate) const JOB_PROGRESS: &str = "job:progress";
+pub(crate) const PROBLEMS: &str = "problems";
+
+pub(crate) fn validate_settings(settings: &EventSettings) -> Result<(), String> {
+    match settings
+        .throttle_ms
+        .iter()
+        .find(|(_, ms)| **ms > MAX_THROTTLE_MS)
+    {
+        Some((class, _)) => Err(format!(
+            "throttle for {class} must be at most {MAX_THROTTLE_MS} ms"
+        )),
+        None => Ok(()),
+    }
+}
+
+const MAX_THROTTLE_MS: u64 = 10_000;
+
+/// How often held-back events are checked for being due.
+const FLUSH_TICK: Duration = Duration::from_millis(4);
+
+/// Appended output held back past this many bytes is sent straight away.
+const MAX_PENDING_BYTES: usize = 256 * 1024;
+
+/// How a burst of one event to one window is squashed into a single send.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Coalesce {
+    /// Only the newest payload is sent.
+    Latest,
+    /// String fields are concatenated: terminal output.
+    Append,
+    /// Array fields are merged and booleans or-ed; the rest is the newest.
+    Merge,
+}
+
+struct Pending {
+    event: String,
+    class: String,
+    payload: Value,
+    due: Instant,
+}
+
+/// Window label, then topic: each resource a window listens to is throttled
+/// on its own.
+type Key = (String, String);
+
+/// Per-class counters, for debugging a flooded bridge.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventStats {
+    /// Events actually sent to a webview.
+    pub sent: u64,
+    /// Events folded into a later send instead of going out on their own.
+    pub coalesced: u64,
+    /// Sends that failed, e.g. because the window was gone.
+    pub failed: u64,
+}
+
+#[derive(Default)]
+struct BusState {
+    throttle: BTreeMap<String, Duration>,
+    pending: HashMap<Key, Pending>,
+    last_sent: HashMap<Key, Instant>,
+    stats: BTreeMap<String, EventStats>,
+}
+
+/// Throttles bursty per-window events (terminal output, fs changes, PR
+/// status) so they can't swamp the IPC bridge: the first event of a burst
+/// goes out at once, the rest are coalesced and sent when the class's
+/// interval is up.
+pub struct EventBus {
+    state: Mutex<BusState>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(BusState::default()),
+        }
+    }
+}
+
+fn merge(coalesce: Coalesce, pending: &mut Value, next: Value) {
+    let (old, new) = match (coalesce, pending.as_object_mut(), next) {
+        (Coalesce::Latest, _, next) | (_, None, next) => {
+            *pending = next;
+            return;
+        }
+        (_, Some(old), Value::Object(new)) => (old, new),
+        (_, Some(_), next) => {
+            *pending = next;
+            return;
+        }
+    };
+    for (key, value) in new {
+        match (old.get_mut(&key), value) {
+            (Some(Value::String(a)), Value::String(b)) if coalesce == Coalesce::Append => {
+                a.push_str(&b)
+            }
+            (Some(Value::Array(a)), Value::Array(b)) if coalesce == Coalesce::Merge => {
+                for item in b {
+                    if !a.contains(&item) {
+                        a.push(item);
+                    }
+                }
+            }
+            (Some(Value::Bool(a)), Value::Bool(b)) if coalesce == Coalesce::Merge => *a |= b,
+            (_, value) => {
+                old.insert(key, value);
+            }
+        }
+    }
+}
+
+fn pending_bytes(payload: &Value) -> usize {
+    match payload {
+        Value::Object(fields) => fields
+            .values()
+            .filter_map(Value::as_str)
+            .map(str::len)
+            .sum(),
+        Value::String(s) => s.len(),
+        _ => 0,
+    }
+}
+
+fn send(app: &AppHandle, label: &str, event: &str, class: &str, payload: Value) {
+    let result = app.emit_to(EventTarget::webview_window(label), event, payload);
+    if let Some(bus) = app.try_state::<EventBus>() {
+        if let Ok(mut state) = bus.state.lock() {
+            let stats = state.stats.entry(class.to_string()).or_default();
+            match result {
+                Ok(()) => stats.sent += 1,
+                Err(_) => stats.failed += 1,
+            }
+        }
+    }
+}
+
+/// Send `event` about `topic` to window `label`, throttled by `class`'s
+/// interval and squashed with `coalesce` during a burst.
+pub(crate) fn emit_throttled<S: Serialize>(
+    app: &AppHandle,
+    label: &str,
+    topic: &str,
+    class: &str,
+    event: &str,
+    coalesce: Coalesce,
+    payload: S,
+) {
+    let payload = match serde_json::to_value(payload) {
+        Ok(payload) => payload,
+        Err(_) => return,
+    };
+    let bus = match app.try_state::<EventBus>() {
+        Some(bus) => bus,
+        None => return send(app, label, event, class, payload),
+    };
+    let mut state = match bus.state.lock() {
+        Ok(state) => state,
+        Err(_) => return,
+    };
+    let interval = state.throttle.get(class).copied().unwrap_or_default();
+    let key = (label.to_string(), topic.to_string());
+    let now = Instant::now();
+
+    let ready = match state.pending.get_mut(&key) {
+        Some(pending) => {
+            merge(coalesce, &mut pending.payload, payload);
+            let full =
+                coalesce == Coalesce::Append && pending_bytes(&pending.payload) > MAX_PENDING_BYTES;
+            state.stats.entry(class.to_string()).or_default().coalesced += 1;
+            if full {
+                state.pending.remove(&key).map(|p| p.payload)
+            } else {
+                None
+            }
+        }
+        None => {
+            let since = state.last_sent.get(&key).map(|t| now.duration_since(*t));
+            if since.is_none_or(|since| since >= interval) {
+                Some(payload)
+            } else {
+                let due = now + interval - since.unwrap_or_default();
+                state.pending.insert(
+                    key.clone(),
+                    Pending {
+                        event: event.to_string(),
+                        class: class.to_string(),
+                        payload,
+                        due,
+                    },
+                );
+                None
+            }
+        }
+    };
+    if let Some(payload) = ready {
+        state.last_sent.insert(key, now);
+        drop(state);
+        send(app, label, event, class, payload);
+    }
+}
+
+/// Send whatever is held back for `topic` to `label` now, e.g. the last
+/// terminal output before its exit event.
+pub(crate) fn flush_topic(app: &AppHandle, label: &str, topic: &str) {
+    let bus = match app.try_state::<EventBus>() {
+        Some(bus) => bus,
+        None => return,
+    };
+    let pending = match bus.state.lock() {
+        Ok(mut state) => state
+            .pending
+            .remove(&(label.to_string(), topic.to_string())),
+        Err(_) => return,
+    };
+    if let Some(pending) = pending {
+        send(app, label, &pending.event, &pending.class, pending.payload);
+    }
+}
+
+fn flush_due(app: &AppHandle) {
+    let bus = app.state::<EventBus>();
+    let due: Vec<(Key, Pending)> = {
+        let mut state = match bus.state.lock() {
+            Ok(state) => state,
+            Err(_) => return,
+        };
+        let now = Instant::now();
+        let keys: Vec<Key> = state
+            .pending
+            .iter()
+            .filter(|(_, p)| p.due <= now)
+            .map(|(key, _)| key.clone())
+            .collect();
+        let mut due = Vec::with_capacity(keys.len());
+        for key in keys {
+            if let Some(pending) = state.pending.remove(&key) {
+                state.last_sent.insert(key.clone(), now);
+                due.push((key, pending));
+            }
+        }
+        // Nothing throttles longer than MAX_THROTTLE_MS, so older entries
+        // can't hold anything back.
+        let horizon = Duration::from_millis(MAX_THROTTLE_MS);
+        state
+            .last_sent
+            .retain(|_, sent| now.duration_since(*sent) < horizon);
+        due
+    };
+    for ((label, _), pending) in due {
+        send(app, &label, &pending.event, &pending.class, pending.payload);
+    }
+}
+
+/// Apply the throttle settings. Called at startup and whenever settings
+/// change.
+pub(crate) fn configure(app: &AppHandle, settings: &EventSettings) {
+    if let Some(bus) = app.try_state::<EventBus>() {
+        if let Ok(mut state) = bus.state.lock() {
+            state.throttle = settings
+                .throttle_ms
+                .iter()
+                .map(|(class, ms)| (class.clone(), Duration::from_millis(*ms)))
+                .collect();
+        }
+    }
+}
+
+/// Start sending held-back events once they're due. Called once from
+/// `setup`.
+pub fn init(app: &AppHandle) {
+    configure(app, &crate::settings::current(app).events);
+    let app = app.clone();
+    std::thread::spawn(move || loop {
+        std::thread::sleep(FLUSH_TICK);
+        flush_due(&app);
+    });
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventBusStats {
+    pub throttle_ms: BTreeMap<String, u64>,
+    pub classes: BTreeMap<String, EventStats>,
+    /// Events held back right now.
+    pub pending: usize,
+}
+
+/// Events held back right now and the bytes of text they carry, by class.
+pub(crate) fn queue_depths(bus: &EventBus) -> BTreeMap<String, (usize, usize)> {
+    let mut depths: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+    if let Ok(state) = bus.state.lock() {
+        for pending in state.pending.values() {
+            let depth = depths.entry(pending.class.clone()).or_default();
+            depth.0 += 1;
+            depth.1 += pending_bytes(&pending.payload);
+        }
+    }
+    depths
+}
+
+/// Throttle counters since startup, for debugging.
+#[tauri::command]
+pub fn event_stats(
+    window: WebviewWindow,
+    bus: State<'_, EventBus>,
+) -> Result<EventBusStats, String> {
+    crate::require_app_window(&window)?;
+    let state = bus.state.lock().map_err(|e| e.to_string())?;
+    Ok(EventBusStats {
+        throttle_ms: state
+            .throttle
+            .iter()
+            .map(|(class, interval)| (class.clone(), interval.as_millis() as u64))
+            .collect(),
+        classes: state.stats.clone(),
+        pending: state.pending.len(),
+    })
+}
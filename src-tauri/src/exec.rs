@@ -0,0 +1,419 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+use crate::audit::{self, AuditEvent, AuditKind, Outcome};
+use crate::problems::{Diagnostic, ProblemScanner};
+use crate::redact::StreamRedactor;
+use crate::remote_fs::RemoteRoot;
+use crate::sandbox::Sandbox;
+use crate::test_report::{TestCase, TestParser, TestSummary};
+
+/// Commands run at most this long unless the caller says otherwise.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+const MAX_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+/// How often the waiting loop checks for timeout and cancellation.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// How long output is still collected once the process has exited.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecOptions {
+    /// Added to the app's environment.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Kill the process after this long. 10 minutes by default, at most an
+    /// hour.
+    pub timeout_ms: Option<u64>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "event",
+    content = "data"
+)]
+pub enum ExecEvent {
+    Stdout(String),
+    Stderr(String),
+    /// A test result recognised in the output.
+    Test(TestCase),
+    /// Test totals, sent once the command has finished.
+    TestSummary(TestSummary),
+    /// A compiler or linter diagnostic recognised in the output.
+    Problem(Diagnostic),
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecResult {
+    /// `None` if the process was killed by a signal or the timeout.
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+    pub elapsed_ms: u64,
+    /// Test totals, if the output looked like a test run.
+    pub tests: Option<TestSummary>,
+}
+
+#[derive(Clone, Copy)]
+enum Stream {
+    Stdout = 0,
+    Stderr = 1,
+}
+
+impl Stream {
+    fn event(self, text: String) -> ExecEvent {
+        match self {
+            Stream::Stdout => ExecEvent::Stdout(text),
+            Stream::Stderr => ExecEvent::Stderr(text),
+        }
+    }
+}
+
+/// Decodes a byte stream as UTF-8 without splitting characters that straddle
+/// two reads.
+#[derive(Default)]
+struct Utf8Decoder {
+    pending: Vec<u8>,
+}
+
+impl Utf8Decoder {
+    fn feed(&mut self, bytes: &[u8]) -> String {
+        self.pending.extend_from_slice(bytes);
+        let complete = match std::str::from_utf8(&self.pending) {
+            Ok(_) => self.pending.len(),
+            // An incomplete sequence at the end waits for the next read.
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(_) => self.pending.len(),
+        };
+        let rest = self.pending.split_off(complete);
+        let text = String::from_utf8_lossy(&self.pending).into_owned();
+        self.pending = rest;
+        text
+    }
+
+    fn finish(&mut self) -> String {
+        String::from_utf8_lossy(&std::mem::take(&mut self.pending)).into_owned()
+    }
+}
+
+/// Read `pipe` to the end, passing decoded chunks to `sender`.
+fn forward(
+    mut pipe: impl Read + Send + 'static,
+    stream: Stream,
+    sender: mpsc::Sender<(Stream, String)>,
+) {
+    std::thread::spawn(move || {
+        let mut buf = [0u8; 8192];
+        let mut decoder = Utf8Decoder::default();
+        loop {
+            match pipe.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    let text = decoder.feed(&buf[..n]);
+                    if !text.is_empty() && sender.send((stream, text)).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+        let rest = decoder.finish();
+        if !rest.is_empty() {
+            let _ = sender.send((stream, rest));
+        }
+    });
+}
+
+fn run(
+    app: &AppHandle,
+    mut command: Command,
+    cwd: &Path,
+    timeout: Duration,
+    on_event: &mut dyn FnMut(ExecEvent),
+    cancel: &crate::cancel::CancelToken,
+) -> Result<ExecResult, String> {
+    let started = Instant::now();
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    let (sender, receiver) = mpsc::channel();
+    if let Some(stdout) = child.stdout.take() {
+        forward(stdout, Stream::Stdout, sender.clone());
+    }
+    if let Some(stderr) = child.stderr.take() {
+        forward(stderr, Stream::Stderr, sender);
+    }
+
+    let redact = crate::settings::current(app).terminal.redact_secrets;
+    let mut redactors = [StreamRedactor::default(), StreamRedactor::default()];
+    let mut tests = TestParser::default();
+    let mut problems = [
+        ProblemScanner::new(app, cwd, false),
+        ProblemScanner::new(app, cwd, false),
+    ];
+    let mut send = |stream: Stream, text: String, end: bool| {
+        let text = match (redact, end) {
+            (false, _) => text,
+            (true, false) => redactors[stream as usize].feed(&text),
+            (true, true) => redactors[stream as usize].finish(),
+        };
+        if text.is_empty() {
+            return;
+        }
+        for case in tests.feed(stream as usize, &text) {
+            on_event(ExecEvent::Test(case));
+        }
+        if let Some(scanner) = &mut problems[stream as usize] {
+            for diagnostic in scanner.feed(&text) {
+                on_event(ExecEvent::Problem(diagnostic));
+            }
+        }
+        on_event(stream.event(text));
+    };
+
+    let mut timed_out = false;
+    let status = loop {
+        while let Ok((stream, text)) = receiver.try_recv() {
+            send(stream, text, false);
+        }
+        if cancel.is_cancelled() || started.elapsed() >= timeout {
+            timed_out = !cancel.is_cancelled();
+            let _ = child.kill();
+            break child.wait().map_err(|e| e.to_string())?;
+        }
+        match child.try_wait().map_err(|e| e.to_string())? {
+            Some(status) => break status,
+            None => match receiver.recv_timeout(POLL_INTERVAL) {
+                Ok((stream, text)) => send(stream, text, false),
+                Err(_) => continue,
+            },
+        }
+    };
+    // Output still in the pipes after exit. A background process that
+    // inherited them could keep them open forever, so don't wait long.
+    let drain_until = Instant::now() + DRAIN_TIMEOUT;
+    while let Ok((stream, text)) =
+        receiver.recv_timeout(drain_until.saturating_duration_since(Instant::now()))
+    {
+        send(stream, text, false);
+    }
+    // Whatever the redactors held back.
+    send(Stream::Stdout, String::new(), true);
+    send(Stream::Stderr, String::new(), true);
+    for case in tests.finish() {
+        on_event(ExecEvent::Test(case));
+    }
+    for scanner in problems.iter_mut().flatten() {
+        for diagnostic in scanner.finish() {
+            on_event(ExecEvent::Problem(diagnostic));
+        }
+    }
+    cancel.check()?;
+    let summary = tests.summary();
+    if let Some(summary) = &summary {
+        on_event(ExecEvent::TestSummary(summary.clone()));
+    }
+    Ok(ExecResult {
+        exit_code: status.code(),
+        timed_out,
+        elapsed_ms: started.elapsed().as_millis() as u64,
+        tests: summary,
+    })
+}
+
+/// Resolve `program` the way `exec_command` runs it: relative to `cwd` if
+/// it has a path separator, else on PATH.
+pub(crate) fn resolve_program(cwd: &Path, program: &str) -> Result<PathBuf, String> {
+    if program.contains(['/', '\\']) {
+        Ok(cwd.join(program))
+    } else {
+        crate::diagnostics::find_executable(program)
+            .ok_or_else(|| format!("{program} not found on PATH"))
+    }
+}
+
+/// `exec_command` for a `cwd` that has passed the sandbox: run, pass the
+/// events to `on_event`, audit.
+pub(crate) async fn execute(
+    app: &AppHandle,
+    cwd: PathBuf,
+    argv: Vec<String>,
+    options: ExecOptions,
+    request_id: Option<String>,
+    on_event: impl FnMut(ExecEvent) + Send + 'static,
+) -> Result<ExecResult, String> {
+    let (program, args) = argv.split_first().ok_or("argv is empty")?;
+    let mut command = Command::new(resolve_program(&cwd, program)?);
+    command.args(args).current_dir(&cwd).envs(&options.env);
+    supervise(
+        app,
+        command,
+        cwd,
+        argv.join(" "),
+        options.timeout_ms,
+        request_id,
+        on_event,
+    )
+    .await
+}
+
+/// `execute` in a directory on an SSH host, which has passed the sandbox.
+/// Diagnostics point at `ssh://` paths. Killing it on timeout or `cancel`
+/// closes the connection; a command that ignores that keeps running there.
+pub(crate) async fn execute_remote(
+    app: &AppHandle,
+    dir: RemoteRoot,
+    argv: Vec<String>,
+    options: ExecOptions,
+    request_id: Option<String>,
+    on_event: impl FnMut(ExecEvent) + Send + 'static,
+) -> Result<ExecResult, String> {
+    if argv.is_empty() {
+        return Err("argv is empty".to_string());
+    }
+    let command = crate::remote_fs::command_in(app, &dir, &argv, &options.env);
+    supervise(
+        app,
+        command,
+        PathBuf::from(dir.uri(&dir.path)),
+        argv.join(" "),
+        options.timeout_ms,
+        request_id,
+        on_event,
+    )
+    .await
+}
+
+/// Run `command` off the async runtime, then audit it as `line` run in
+/// `cwd`.
+async fn supervise(
+    app: &AppHandle,
+    command: Command,
+    cwd: PathBuf,
+    line: String,
+    timeout_ms: Option<u64>,
+    request_id: Option<String>,
+    mut on_event: impl FnMut(ExecEvent) + Send + 'static,
+) -> Result<ExecResult, String> {
+    let timeout = timeout_of(timeout_ms);
+    let project = cwd.to_string_lossy().to_string();
+    tracing::info!("exec {line} in {project}");
+    let guard = crate::cancel::register(app, request_id.as_deref());
+    let runner = app.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        run(
+            &runner,
+            command,
+            &cwd,
+            timeout,
+            &mut on_event,
+            &guard.token(),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    record(app, None, &project, &line, &result);
+    result
+}
+
+/// `execute` for a caller that is already off the async runtime and has
+/// its own cancellation, such as an agent loop. Audited as run by `agent`.
+pub(crate) fn execute_blocking(
+    app: &AppHandle,
+    cwd: &Path,
+    argv: &[String],
+    options: &ExecOptions,
+    agent: Option<&str>,
+    cancel: &crate::cancel::CancelToken,
+    on_event: &mut dyn FnMut(ExecEvent),
+) -> Result<ExecResult, String> {
+    let (program, args) = argv.split_first().ok_or("argv is empty")?;
+    let mut command = Command::new(resolve_program(cwd, program)?);
+    command.args(args).current_dir(cwd).envs(&options.env);
+    let timeout = timeout_of(options.timeout_ms);
+    let project = cwd.to_string_lossy().to_string();
+    let line = argv.join(" ");
+    tracing::info!("exec {line} in {project}");
+    let result = run(app, command, cwd, timeout, on_event, cancel);
+    record(app, agent, &project, &line, &result);
+    result
+}
+
+fn timeout_of(timeout_ms: Option<u64>) -> Duration {
+    timeout_ms
+        .map(Duration::from_millis)
+        .unwrap_or(DEFAULT_TIMEOUT)
+        .min(MAX_TIMEOUT)
+}
+
+fn record(
+    app: &AppHandle,
+    agent: Option<&str>,
+    project: &str,
+    line: &str,
+    result: &Result<ExecResult, String>,
+) {
+    audit::record(
+        app,
+        AuditEvent {
+            kind: AuditKind::Command,
+            agent,
+            project: Some(project),
+            target: line,
+            outcome: match result {
+                Ok(r) if r.exit_code == Some(0) => Outcome::Ok,
+                _ => Outcome::Error,
+            },
+            detail: Some(match result {
+                Ok(r) if r.timed_out => "timed out".to_string(),
+                Ok(r) => match r.exit_code {
+                    Some(code) => format!("exit code {code}"),
+                    None => "killed".to_string(),
+                },
+                Err(e) => e.clone(),
+            }),
+        },
+    );
+}
+
+/// Run `argv` in `cwd` to completion without a terminal, for lint, test and
+/// format actions. Output streams over `on_event` as it arrives, redacted
+/// like terminal output, along with any test results recognised in it
+/// (`cargo test`, pytest, Jest, `go test`) and diagnostics found by the
+/// problem matchers; the exit code is returned. `argv[0]` is looked up on
+/// PATH and nothing goes through a shell. Stop it with `cancel(request_id)`.
+#[tauri::command]
+pub async fn exec_command(
+    window: WebviewWindow,
+    app: AppHandle,
+    cwd: String,
+    argv: Vec<String>,
+    options: Option<ExecOptions>,
+    request_id: Option<String>,
+    on_event: Channel<ExecEvent>,
+) -> Result<ExecResult, String> {
+    crate::require_app_window(&window)?;
+    let cwd = app.state::<Sandbox>().check_dir(&cwd)?;
+    execute(
+        &app,
+        cwd,
+        argv,
+        options.unwrap_or_default(),
+        request_id,
+        move |event| {
+            let _ = on_event.send(event);
+        },
+    )
+    .await
+}
@@ -0,0 +1,134 @@
+use serde::Serialize;
+use std::path::{Component, Path, PathBuf};
+use tauri::{AppHandle, DragDropEvent, Emitter, EventTarget, Manager, WebviewWindow, Window};
+
+use crate::pty::PtyManager;
+use crate::sandbox::Sandbox;
+
+/// `files:dropped`: files dropped onto a window, for the frontend to pass to
+/// `pty_quote_paths` for the terminal under `position`.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FilesDropped {
+    paths: Vec<String>,
+    /// Physical pixels from the top left of the window.
+    x: f64,
+    y: f64,
+}
+
+/// Forward native file drops to the window they landed on.
+pub(crate) fn on_drag_drop(window: &Window, event: &DragDropEvent) {
+    if let DragDropEvent::Drop { paths, position } = event {
+        let payload = FilesDropped {
+            paths: paths
+                .iter()
+                .map(|p| p.to_string_lossy().to_string())
+                .collect(),
+            x: position.x,
+            y: position.y,
+        };
+        let target = EventTarget::webview_window(window.label());
+        let _ = window
+            .app_handle()
+            .emit_to(target, "files:dropped", payload);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Quoting {
+    Posix,
+    PowerShell,
+    Cmd,
+}
+
+impl Quoting {
+    fn for_program(program: &str) -> Self {
+        let name = Path::new(program)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        match name.as_str() {
+            "powershell" | "pwsh" => Quoting::PowerShell,
+            "cmd" => Quoting::Cmd,
+            _ => Quoting::Posix,
+        }
+    }
+
+    fn quote(self, word: &str) -> String {
+        let safe = match self {
+            Quoting::Posix => "_-./:@%+=,",
+            Quoting::PowerShell | Quoting::Cmd => "_-./:\\",
+        };
+        let plain = !word.is_empty()
+            && word
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || safe.contains(c));
+        if plain {
+            return word.to_string();
+        }
+        match self {
+            Quoting::Posix => format!("'{}'", word.replace('\'', r"'\''")),
+            Quoting::PowerShell => format!("'{}'", word.replace('\'', "''")),
+            // Double quotes can't appear in Windows file names.
+            Quoting::Cmd => format!("\"{word}\""),
+        }
+    }
+}
+
+/// `path` relative to `base`, climbing with `..` as needed. Both absolute.
+fn relative_to(path: &Path, base: &Path) -> PathBuf {
+    let path: Vec<Component> = path.components().collect();
+    let base: Vec<Component> = base.components().collect();
+    let common = path.iter().zip(&base).take_while(|(a, b)| a == b).count();
+    let mut relative = PathBuf::new();
+    for _ in common..base.len() {
+        relative.push("..");
+    }
+    for part in &path[common..] {
+        relative.push(part);
+    }
+    if relative.as_os_str().is_empty() {
+        relative.push(".");
+    }
+    relative
+}
+
+/// How `path` is best written in a terminal whose shell is in `cwd`:
+/// relative when both are in the same project, absolute otherwise.
+fn display_path(sandbox: &Sandbox, cwd: &Path, path: &str) -> String {
+    let path = std::fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path));
+    let same_project = match (sandbox.root_of(&path), sandbox.root_of(cwd)) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    };
+    if same_project {
+        relative_to(&path, cwd).to_string_lossy().to_string()
+    } else {
+        path.to_string_lossy().to_string()
+    }
+}
+
+/// Dropped `paths` as text to type into terminal `id`: quoted for its shell,
+/// relative to its current directory when inside the same project, joined
+/// with spaces and followed by one.
+#[tauri::command]
+pub fn pty_quote_paths(
+    window: WebviewWindow,
+    app: AppHandle,
+    id: String,
+    paths: Vec<String>,
+) -> Result<String, String> {
+    crate::require_app_window(&window)?;
+    let (cwd, program) = app
+        .state::<PtyManager>()
+        .cwd_and_program(&id, window.label())
+        .ok_or_else(|| format!("no terminal {id} in this window"))?;
+    let quoting = Quoting::for_program(&program);
+    let sandbox = app.state::<Sandbox>();
+    let mut text = String::new();
+    for path in &paths {
+        text.push_str(&quoting.quote(&display_path(&sandbox, &cwd, path)));
+        text.push(' ');
+    }
+    Ok(text)
+}
@@ -0,0 +1,467 @@
+use ignore::WalkBuilder;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Instant;
+use tauri::{AppHandle, Manager, State, WebviewWindow};
+
+use crate::sandbox::Sandbox;
+use crate::watcher::{self, FileWatcher, WatchOptions};
+
+/// Stop indexing beyond this many entries; `truncated` is reported instead.
+const MAX_INDEX_ENTRIES: usize = 1_000_000;
+
+/// Default number of hits returned by `tree_search`.
+const DEFAULT_SEARCH_LIMIT: usize = 100;
+
+/// Index for one project root. Paths are root-relative with `/` separators;
+/// the root itself is `""`.
+pub(crate) struct ProjectTree {
+    root: PathBuf,
+    /// Directory path → child name → whether the child is a directory.
+    children: HashMap<String, BTreeMap<String, bool>>,
+    entries: usize,
+    truncated: bool,
+}
+
+impl ProjectTree {
+    fn new(root: PathBuf) -> Self {
+        let mut children = HashMap::new();
+        children.insert(String::new(), BTreeMap::new());
+        Self {
+            root,
+            children,
+            entries: 0,
+            truncated: false,
+        }
+    }
+
+    fn insert(&mut self, rel: &str, is_dir: bool) {
+        if rel.is_empty() {
+            return;
+        }
+        let (parent, name) = split_parent(rel);
+        if !self.children.contains_key(parent) {
+            self.insert(parent, true);
+        }
+        let siblings = self.children.entry(parent.to_string()).or_default();
+        if siblings.insert(name.to_string(), is_dir).is_none() {
+            self.entries += 1;
+        }
+        if is_dir {
+            self.children.entry(rel.to_string()).or_default();
+        }
+    }
+
+    fn remove(&mut self, rel: &str) {
+        let (parent, name) = split_parent(rel);
+        let was_dir = match self.children.get_mut(parent).and_then(|s| s.remove(name)) {
+            Some(is_dir) => is_dir,
+            None => return,
+        };
+        self.entries -= 1;
+        if was_dir {
+            let nested = format!("{rel}/");
+            let mut removed = 0;
+            self.children.retain(|dir, kids| {
+                if dir == rel || dir.starts_with(&nested) {
+                    removed += kids.len();
+                    false
+                } else {
+                    true
+                }
+            });
+            self.entries -= removed;
+        }
+    }
+
+    /// Walk `start` (the root or a directory inside it) honoring .gitignore,
+    /// .git/info/exclude and parent ignore files.
+    fn walk_from(&mut self, start: &Path) {
+        let walker = WalkBuilder::new(start)
+            .hidden(false)
+            .require_git(false)
+            .filter_entry(|e| e.file_name() != ".git")
+            .build();
+        for entry in walker.flatten() {
+            if self.entries >= MAX_INDEX_ENTRIES {
+                self.truncated = true;
+                break;
+            }
+            let rel = relative(&self.root, entry.path());
+            let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+            self.insert(&rel, is_dir);
+        }
+    }
+
+    /// Add the entries of `other`, a walk of part of the same root.
+    fn merge(&mut self, other: &ProjectTree) {
+        self.truncated |= other.truncated;
+        for (dir, kids) in &other.children {
+            for (name, is_dir) in kids {
+                if self.entries >= MAX_INDEX_ENTRIES {
+                    self.truncated = true;
+                    return;
+                }
+                self.insert(&join(dir, name), *is_dir);
+            }
+        }
+    }
+
+    /// Every indexed file (not directory), as root-relative paths.
+    pub(crate) fn files(&self) -> impl Iterator<Item = String> + '_ {
+        self.children.iter().flat_map(|(dir, kids)| {
+            kids.iter()
+                .filter(|(_, is_dir)| !**is_dir)
+                .map(move |(name, _)| join(dir, name))
+        })
+    }
+}
+
+fn split_parent(rel: &str) -> (&str, &str) {
+    match rel.rfind('/') {
+        Some(i) => (&rel[..i], &rel[i + 1..]),
+        None => ("", rel),
+    }
+}
+
+fn join(dir: &str, name: &str) -> String {
+    if dir.is_empty() {
+        name.to_string()
+    } else {
+        format!("{dir}/{name}")
+    }
+}
+
+fn relative(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+pub struct FileIndex {
+    trees: Mutex<HashMap<String, ProjectTree>>,
+}
+
+impl FileIndex {
+    pub fn new() -> Self {
+        Self {
+            trees: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Root, entries and roughly the bytes of names held, for each index.
+    pub(crate) fn usage(&self) -> Vec<(String, usize, usize)> {
+        let trees = match self.trees.lock() {
+            Ok(trees) => trees,
+            Err(_) => return Vec::new(),
+        };
+        trees
+            .values()
+            .map(|tree| {
+                let bytes = tree
+                    .children
+                    .iter()
+                    .map(|(dir, names)| dir.len() + names.keys().map(String::len).sum::<usize>())
+                    .sum();
+                (tree.root.to_string_lossy().to_string(), tree.entries, bytes)
+            })
+            .collect()
+    }
+
+    /// Run `f` against the index for `root`, if one has been built.
+    pub(crate) fn with_tree<T>(
+        &self,
+        root: &Path,
+        f: impl FnOnce(&ProjectTree) -> T,
+    ) -> Result<Option<T>, String> {
+        let trees = self.trees.lock().map_err(|e| e.to_string())?;
+        Ok(trees.get(root.to_string_lossy().as_ref()).map(f))
+    }
+}
+
+fn watch_id(root: &Path) -> String {
+    format!("file-index:{}", root.display())
+}
+
+fn canonical_root(root: &str) -> Result<PathBuf, String> {
+    std::fs::canonicalize(root).map_err(|e| format!("invalid root {root}: {e}"))
+}
+
+fn build_tree(root: PathBuf) -> ProjectTree {
+    let mut tree = ProjectTree::new(root.clone());
+    tree.walk_from(&root);
+    tree
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TreeIndexInfo {
+    pub root: String,
+    pub entries: u32,
+    pub truncated: bool,
+    pub build_ms: u64,
+}
+
+/// Build the index for `root` off the main thread and start the watch that
+/// keeps it current. Also used by `tree_query`/`tree_search` on first access.
+pub(crate) async fn ensure_index(
+    app: &AppHandle,
+    root: &str,
+    rebuild: bool,
+) -> Result<TreeIndexInfo, String> {
+    let root = app.state::<Sandbox>().check_dir(root)?;
+    let index = app.state::<FileIndex>();
+    if !rebuild {
+        let existing = index.with_tree(&root, |tree| (tree.entries, tree.truncated))?;
+        if let Some((entries, truncated)) = existing {
+            return Ok(TreeIndexInfo {
+                root: root.to_string_lossy().to_string(),
+                entries: entries as u32,
+                truncated,
+                build_ms: 0,
+            });
+        }
+    }
+
+    let started = Instant::now();
+    let build_root = root.clone();
+    let tree = tauri::async_runtime::spawn_blocking(move || build_tree(build_root))
+        .await
+        .map_err(|e| e.to_string())?;
+    let info = TreeIndexInfo {
+        root: root.to_string_lossy().to_string(),
+        entries: tree.entries as u32,
+        truncated: tree.truncated,
+        build_ms: started.elapsed().as_millis() as u64,
+    };
+    index
+        .trees
+        .lock()
+        .map_err(|e| e.to_string())?
+        .insert(info.root.clone(), tree);
+
+    let watcher = app.state::<FileWatcher>();
+    watcher::start_watch(
+        app,
+        &watcher,
+        watch_id(&root),
+        &info.root,
+        WatchOptions::default(),
+    )?;
+    Ok(info)
+}
+
+enum Change {
+    Removed,
+    File,
+    /// A directory, with what a walk of it found.
+    Dir(ProjectTree),
+}
+
+/// Apply a batch of watcher changes to the index for `root`, if there is one.
+/// `overflow` batches carry no paths, so the whole tree is rebuilt. Walks
+/// happen before taking the lock, which every index shares.
+pub(crate) fn apply_changes(app: &AppHandle, root: &Path, paths: &[String], overflow: bool) {
+    let index = match app.try_state::<FileIndex>() {
+        Some(index) => index,
+        None => return,
+    };
+    let key = root.to_string_lossy().to_string();
+    if !index
+        .trees
+        .lock()
+        .is_ok_and(|trees| trees.contains_key(&key))
+    {
+        return;
+    }
+
+    if overflow {
+        let rebuilt = build_tree(root.to_path_buf());
+        if let Ok(mut trees) = index.trees.lock() {
+            if let Some(tree) = trees.get_mut(&key) {
+                *tree = rebuilt;
+            }
+        }
+        return;
+    }
+    let changes: Vec<(&str, Change)> = paths
+        .iter()
+        .map(|rel| {
+            let abs = root.join(rel);
+            let change = match std::fs::symlink_metadata(&abs) {
+                Ok(meta) if meta.is_dir() => {
+                    // A directory that appears at once (mv, git checkout)
+                    // only produces one event, so pick up its contents here.
+                    let mut contents = ProjectTree::new(root.to_path_buf());
+                    contents.walk_from(&abs);
+                    Change::Dir(contents)
+                }
+                Ok(_) => Change::File,
+                Err(_) => Change::Removed,
+            };
+            (rel.as_str(), change)
+        })
+        .collect();
+
+    let mut trees = match index.trees.lock() {
+        Ok(trees) => trees,
+        Err(_) => return,
+    };
+    let tree = match trees.get_mut(&key) {
+        Some(tree) => tree,
+        None => return,
+    };
+    for (rel, change) in changes {
+        match change {
+            Change::Dir(contents) => {
+                tree.insert(rel, true);
+                tree.merge(&contents);
+            }
+            Change::File => tree.insert(rel, false),
+            Change::Removed => tree.remove(rel),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TreeNode {
+    pub name: String,
+    pub path: String,
+    /// `file` or `directory`.
+    pub kind: String,
+    /// Loaded children, or `None` for files and directories beyond `depth`.
+    pub children: Option<Vec<TreeNode>>,
+}
+
+fn collect_children(tree: &ProjectTree, dir: &str, depth: u32) -> Vec<TreeNode> {
+    let kids = match tree.children.get(dir) {
+        Some(kids) => kids,
+        None => return Vec::new(),
+    };
+    let mut nodes: Vec<TreeNode> = kids
+        .iter()
+        .map(|(name, is_dir)| {
+            let path = join(dir, name);
+            let children = if *is_dir && depth > 1 {
+                Some(collect_children(tree, &path, depth - 1))
+            } else {
+                None
+            };
+            TreeNode {
+                name: name.clone(),
+                kind: if *is_dir { "directory" } else { "file" }.to_string(),
+                path,
+                children,
+            }
+        })
+        .collect();
+    nodes.sort_by(|a, b| {
+        (b.kind == "directory")
+            .cmp(&(a.kind == "directory"))
+            .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+    });
+    nodes
+}
+
+/// Build (or rebuild) the file-tree index for a project and keep it updated
+/// from the file watcher.
+#[tauri::command]
+pub async fn tree_index(
+    window: WebviewWindow,
+    app: AppHandle,
+    root: String,
+    rebuild: Option<bool>,
+) -> Result<TreeIndexInfo, String> {
+    crate::require_app_window(&window)?;
+    ensure_index(&app, &root, rebuild.unwrap_or(false)).await
+}
+
+/// Children of `path` (root-relative, `""` for the root), `depth` levels deep.
+#[tauri::command]
+pub async fn tree_query(
+    window: WebviewWindow,
+    app: AppHandle,
+    root: String,
+    path: Option<String>,
+    depth: Option<u32>,
+) -> Result<Vec<TreeNode>, String> {
+    crate::require_app_window(&window)?;
+    let info = ensure_index(&app, &root, false).await?;
+    let dir = path.unwrap_or_default().trim_matches('/').to_string();
+    let depth = depth.unwrap_or(1).max(1);
+    let index = app.state::<FileIndex>();
+    let nodes = index.with_tree(Path::new(&info.root), |tree| {
+        collect_children(tree, &dir, depth)
+    })?;
+    Ok(nodes.unwrap_or_default())
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TreeSearchHit {
+    pub name: String,
+    pub path: String,
+    pub kind: String,
+}
+
+/// Entries whose root-relative path or file name starts with `prefix`
+/// (case-insensitive), shortest paths first.
+#[tauri::command]
+pub async fn tree_search(
+    window: WebviewWindow,
+    app: AppHandle,
+    root: String,
+    prefix: String,
+    limit: Option<u32>,
+) -> Result<Vec<TreeSearchHit>, String> {
+    crate::require_app_window(&window)?;
+    let info = ensure_index(&app, &root, false).await?;
+    let needle = prefix.trim_start_matches('/').to_lowercase();
+    let limit = limit.map(|l| l as usize).unwrap_or(DEFAULT_SEARCH_LIMIT);
+    let index = app.state::<FileIndex>();
+    let hits = index.with_tree(Path::new(&info.root), |tree| {
+        let mut hits: Vec<TreeSearchHit> = Vec::new();
+        for (dir, kids) in &tree.children {
+            for (name, is_dir) in kids {
+                let path = join(dir, name);
+                if !name.to_lowercase().starts_with(&needle)
+                    && !path.to_lowercase().starts_with(&needle)
+                {
+                    continue;
+                }
+                hits.push(TreeSearchHit {
+                    name: name.clone(),
+                    path,
+                    kind: if *is_dir { "directory" } else { "file" }.to_string(),
+                });
+            }
+        }
+        hits.sort_by(|a, b| a.path.len().cmp(&b.path.len()).then(a.path.cmp(&b.path)));
+        hits.truncate(limit);
+        hits
+    })?;
+    Ok(hits.unwrap_or_default())
+}
+
+/// Drop a project's index and stop its watch.
+#[tauri::command]
+pub async fn tree_drop(
+    window: WebviewWindow,
+    app: AppHandle,
+    state: State<'_, FileIndex>,
+    root: String,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    let root = canonical_root(&root)?;
+    state
+        .trees
+        .lock()
+        .map_err(|e| e.to_string())?
+        .remove(root.to_string_lossy().as_ref());
+    let watcher = app.state::<FileWatcher>();
+    watcher::stop_watch(&watcher, &watch_id(&root))
+}
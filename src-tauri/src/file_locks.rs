@@ -0,0 +1,251 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State, WebviewWindow};
+
+use crate::sandbox::Sandbox;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LockMode {
+    Off,
+    /// Send `files:conflict` and let the write through.
+    #[default]
+    Warn,
+    /// Also refuse an agent's write to a file someone else holds. The user's
+    /// own writes are never refused.
+    Block,
+}
+
+/// Advisory locks on files agents and the user are modifying, so parallel
+/// writers in one worktree notice each other. Kept in the settings file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FileLockSettings {
+    pub mode: LockMode,
+    /// How long a write holds its file, renewed by each write after it.
+    pub hold_secs: u64,
+}
+
+impl Default for FileLockSettings {
+    fn default() -> Self {
+        Self {
+            mode: LockMode::default(),
+            hold_secs: 120,
+        }
+    }
+}
+
+pub(crate) fn validate_settings(settings: &FileLockSettings) -> Result<(), String> {
+    if settings.hold_secs == 0 {
+        return Err("locks.holdSecs must be at least 1".to_string());
+    }
+    Ok(())
+}
+
+struct Lock {
+    project: String,
+    /// `None` for the user.
+    agent: Option<String>,
+    since: String,
+    expires: Instant,
+}
+
+/// File (canonical path or `ssh://` URI) → who is modifying it.
+pub struct FileLocks {
+    locks: Mutex<HashMap<String, Lock>>,
+}
+
+impl FileLocks {
+    pub fn new() -> Self {
+        Self {
+            locks: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileLock {
+    pub path: String,
+    pub project: String,
+    pub agent: Option<String>,
+    pub since: String,
+    pub expires_in_ms: u64,
+}
+
+impl FileLock {
+    fn of(path: &str, lock: &Lock) -> Self {
+        Self {
+            path: path.to_string(),
+            project: lock.project.clone(),
+            agent: lock.agent.clone(),
+            since: lock.since.clone(),
+            expires_in_ms: lock
+                .expires
+                .saturating_duration_since(Instant::now())
+                .as_millis() as u64,
+        }
+    }
+}
+
+/// A second writer to a file someone holds, sent as `files:conflict`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileConflict {
+    pub lock: FileLock,
+    /// Who wrote; `None` for the user.
+    pub writer: Option<String>,
+    /// The write was refused.
+    pub blocked: bool,
+}
+
+fn who(agent: Option<&str>) -> &str {
+    agent.unwrap_or("the user")
+}
+
+/// Take or renew the lock on `path` for `agent` (`None`: the user), for
+/// `hold`. A lock someone else holds is a conflict: reported, and unless
+/// `block` taken over.
+fn acquire(
+    app: &AppHandle,
+    project: &str,
+    path: &str,
+    agent: Option<&str>,
+    hold: Duration,
+    block: bool,
+) -> Result<FileLock, String> {
+    let state = app.state::<FileLocks>();
+    let mut locks = state.locks.lock().map_err(|e| e.to_string())?;
+    let now = Instant::now();
+    locks.retain(|_, lock| lock.expires > now);
+    let conflict = locks
+        .get(path)
+        .filter(|lock| lock.agent.as_deref() != agent)
+        .map(|lock| FileLock::of(path, lock));
+    if let Some(held) = conflict {
+        let holder = who(held.agent.as_deref()).to_string();
+        tracing::warn!(
+            target: "agents",
+            "{} wrote {path} while {holder} holds it{}",
+            who(agent),
+            if block { "; refused" } else { "" }
+        );
+        let payload = FileConflict {
+            lock: held,
+            writer: agent.map(str::to_string),
+            blocked: block,
+        };
+        if let Err(e) = app.emit_to("main", "files:conflict", payload) {
+            tracing::debug!("files:conflict not delivered: {e}");
+        }
+        if block {
+            return Err(format!(
+                "{path} is being modified by {holder}; try again once they are done"
+            ));
+        }
+        locks.remove(path);
+    }
+    let lock = locks.entry(path.to_string()).or_insert_with(|| Lock {
+        project: project.to_string(),
+        agent: agent.map(str::to_string),
+        since: chrono::Utc::now().to_rfc3339(),
+        expires: now,
+    });
+    lock.expires = now + hold;
+    Ok(FileLock::of(path, lock))
+}
+
+/// Check and record a write to `path` in `project` by `agent` (`None`: the
+/// user), as the lock settings say. `Err` when it is refused.
+pub(crate) fn claim(
+    app: &AppHandle,
+    project: &str,
+    path: &str,
+    agent: Option<&str>,
+) -> Result<(), String> {
+    let settings = crate::settings::current(app).locks;
+    if settings.mode == LockMode::Off {
+        return Ok(());
+    }
+    let block = settings.mode == LockMode::Block && agent.is_some();
+    let hold = Duration::from_secs(settings.hold_secs);
+    acquire(app, project, path, agent, hold, block).map(|_| ())
+}
+
+/// Lock `path` in `root` for `agent` (unset: the user) before modifying it,
+/// for `hold_secs` or the configured hold. Fails while someone else holds
+/// it, whatever the lock mode.
+#[tauri::command]
+pub fn file_lock(
+    window: WebviewWindow,
+    app: AppHandle,
+    sandbox: State<'_, Sandbox>,
+    root: String,
+    path: String,
+    agent: Option<String>,
+    hold_secs: Option<u64>,
+) -> Result<FileLock, String> {
+    crate::require_app_window(&window)?;
+    let (project, target) = match sandbox.remote(&root)? {
+        Some(remote) => (
+            remote.uri(&remote.path),
+            remote.uri(&remote.resolve(&path)?),
+        ),
+        None => (
+            sandbox.check_dir(&root)?.to_string_lossy().to_string(),
+            sandbox.resolve(&root, &path)?.to_string_lossy().to_string(),
+        ),
+    };
+    let hold = hold_secs
+        .filter(|&secs| secs > 0)
+        .unwrap_or_else(|| crate::settings::current(&app).locks.hold_secs);
+    acquire(
+        &app,
+        &project,
+        &target,
+        agent.as_deref(),
+        Duration::from_secs(hold),
+        true,
+    )
+}
+
+/// Release what `agent` (unset: the user) holds: the file `path` (a path
+/// from `file_locks`), or with no `path` everything, e.g. when its run
+/// ends. Returns how many locks were released.
+#[tauri::command]
+pub fn file_unlock(
+    window: WebviewWindow,
+    state: State<'_, FileLocks>,
+    agent: Option<String>,
+    path: Option<String>,
+) -> Result<usize, String> {
+    crate::require_app_window(&window)?;
+    let mut locks = state.locks.lock().map_err(|e| e.to_string())?;
+    let before = locks.len();
+    locks.retain(|locked, lock| {
+        lock.agent != agent || path.as_ref().is_some_and(|path| path != locked)
+    });
+    Ok(before - locks.len())
+}
+
+/// The locks held now, in `project` if given.
+#[tauri::command]
+pub fn file_locks(
+    window: WebviewWindow,
+    state: State<'_, FileLocks>,
+    project: Option<String>,
+) -> Result<Vec<FileLock>, String> {
+    crate::require_app_window(&window)?;
+    let locks = state.locks.lock().map_err(|e| e.to_string())?;
+    let now = Instant::now();
+    let mut list: Vec<FileLock> = locks
+        .iter()
+        .filter(|(_, lock)| lock.expires > now)
+        .filter(|(_, lock)| project.as_ref().is_none_or(|p| *p == lock.project))
+        .map(|(path, lock)| FileLock::of(path, lock))
+        .collect();
+    list.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(list)
+}
@@ -0,0 +1,1045 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, EventTarget, Manager, State, WebviewWindow};
+
+use crate::audit::{self, AuditEvent, AuditKind, Outcome};
+use crate::cancel::CancelToken;
+use crate::events::{
+    emit_subscribed, emit_subscribed_throttled, Coalesce, EventRouter, FORGE_PR_STATUS,
+};
+use crate::redact::redact_tokens;
+use crate::sandbox::Sandbox;
+use crate::timeline::{TimelineEvent, TimelineKind};
+
+/// Maximum length of a title derived from the transcript.
+const MAX_TITLE_LEN: usize = 72;
+
+/// How often `run_capture_cancellable` looks at its token.
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Default PR body. `{{summary}}` is the agent's final message, `{{transcript}}`
+/// the full conversation rendered as markdown.
+const DEFAULT_BODY_TEMPLATE: &str = "## Summary\n\n{{summary}}\n\n<details>\n<summary>Agent transcript</summary>\n\n{{transcript}}\n\n</details>\n";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ForgeProvider {
+    Github,
+    Gitlab,
+}
+
+/// A parsed `remote.<name>.url`, reduced to what the forge APIs need.
+#[derive(Clone, Debug)]
+pub(crate) struct ForgeRemote {
+    pub provider: ForgeProvider,
+    pub host: String,
+    /// `owner/repo` on GitHub, `group/subgroup/project` on GitLab.
+    pub path: String,
+}
+
+/// Where the stored `github-token` / `gitlab-token` may be sent. Kept in the
+/// settings file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ForgeSettings {
+    /// Self-hosted GitHub or GitLab hosts, e.g. `git.example.com`, that get
+    /// the stored tokens besides github.com and gitlab.com.
+    pub token_hosts: Vec<String>,
+}
+
+pub(crate) fn validate_settings(settings: &ForgeSettings) -> Result<(), String> {
+    for host in &settings.token_hosts {
+        let valid = !host.is_empty()
+            && host
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-');
+        if !valid {
+            return Err(format!("forge.tokenHosts: {host:?} is not a host name"));
+        }
+    }
+    Ok(())
+}
+
+/// The stored token for `remote`'s forge, if it may go to `remote`'s host:
+/// github.com, gitlab.com, or a host in `forge.tokenHosts`. Anywhere else,
+/// e.g. a remote an agent re-pointed, needs a token passed with the request.
+pub(crate) fn stored_token(app: &AppHandle, remote: &ForgeRemote) -> Option<String> {
+    let public = match remote.provider {
+        ForgeProvider::Github => "github.com",
+        ForgeProvider::Gitlab => "gitlab.com",
+    };
+    let bound = remote.host.eq_ignore_ascii_case(public)
+        || crate::settings::current(app)
+            .forge
+            .token_hosts
+            .iter()
+            .any(|host| host.eq_ignore_ascii_case(&remote.host));
+    if !bound {
+        tracing::info!(
+            "not sending the stored forge token to {}: not in forge.tokenHosts",
+            remote.host
+        );
+        return None;
+    }
+    crate::secrets::forge_token(remote.provider)
+}
+
+#[derive(Deserialize, Clone)]
+pub struct TranscriptEntry {
+    role: String,
+    content: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatePrRequest {
+    cwd: String,
+    /// Task branch to open the PR from.
+    head: String,
+    /// Branch to merge into.
+    base: String,
+    #[serde(default)]
+    remote: Option<String>,
+    /// Title template. Defaults to the first line of the first user message.
+    #[serde(default)]
+    title: Option<String>,
+    /// Body template. Defaults to `DEFAULT_BODY_TEMPLATE`.
+    #[serde(default)]
+    body: Option<String>,
+    #[serde(default)]
+    transcript: Vec<TranscriptEntry>,
+    #[serde(default)]
+    draft: bool,
+    /// The agent run the PR is opened for, recorded in the audit log.
+    #[serde(default)]
+    agent: Option<String>,
+    /// API token. When present, or stored as the `github-token` /
+    /// `gitlab-token` secret, the REST API is used instead of `gh`/`glab`.
+    /// The stored ones are only sent to the hosts `stored_token` allows.
+    #[serde(default)]
+    token: Option<String>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PullRequestInfo {
+    cwd: String,
+    branch: String,
+    provider: ForgeProvider,
+    url: String,
+    number: Option<u64>,
+}
+
+/// Run a command to completion and return trimmed stdout, or stderr as the error.
+pub(crate) fn run_capture(cmd: &mut Command, stdin: Option<&str>) -> Result<String, String> {
+    cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+    if stdin.is_some() {
+        cmd.stdin(Stdio::piped());
+    }
+    let mut child = cmd.spawn().map_err(|e| e.to_string())?;
+    if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        pipe.write_all(input.as_bytes())
+            .map_err(|e| e.to_string())?;
+    }
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    capture_result(output)
+}
+
+/// `run_capture`, killing the process and failing with `cancelled` as soon
+/// as `cancel` is set.
+pub(crate) fn run_capture_cancellable(
+    cmd: &mut Command,
+    stdin: Option<&str>,
+    cancel: &CancelToken,
+) -> Result<String, String> {
+    cancel.check()?;
+    cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+    if stdin.is_some() {
+        cmd.stdin(Stdio::piped());
+    }
+    let mut child = cmd.spawn().map_err(|e| e.to_string())?;
+    let stdout = child.stdout.take().map(read_to_end_thread);
+    let stderr = child.stderr.take().map(read_to_end_thread);
+    if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        if let Err(e) = pipe.write_all(input.as_bytes()) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(e.to_string());
+        }
+    }
+    let status = loop {
+        if cancel.is_cancelled() {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err("cancelled".to_string());
+        }
+        match child.try_wait().map_err(|e| e.to_string())? {
+            Some(status) => break status,
+            None => std::thread::sleep(CANCEL_POLL_INTERVAL),
+        }
+    };
+    let collect = |reader: Option<std::thread::JoinHandle<Vec<u8>>>| {
+        reader.and_then(|r| r.join().ok()).unwrap_or_default()
+    };
+    capture_result(std::process::Output {
+        status,
+        stdout: collect(stdout),
+        stderr: collect(stderr),
+    })
+}
+
+fn read_to_end_thread(
+    mut pipe: impl std::io::Read + Send + 'static,
+) -> std::thread::JoinHandle<Vec<u8>> {
+    std::thread::spawn(move || {
+        let mut buffer = Vec::new();
+        let _ = pipe.read_to_end(&mut buffer);
+        buffer
+    })
+}
+
+fn capture_result(output: std::process::Output) -> Result<String, String> {
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        Err(if stderr.is_empty() {
+            format!("command exited with {}", output.status)
+        } else {
+            stderr
+        })
+    }
+}
+
+/// Parse `git@host:owner/repo.git`, `ssh://git@host/owner/repo`, or
+/// `https://host/owner/repo.git` into a forge remote. Self-hosted instances
+/// are recognised by `github` / `gitlab` appearing in the host name.
+pub(crate) fn parse_remote_url(raw: &str) -> Option<ForgeRemote> {
+    let raw = raw.trim();
+    let (host, path) = if let Ok(parsed) = url::Url::parse(raw) {
+        (parsed.host_str()?.to_string(), parsed.path().to_string())
+    } else {
+        // scp-like syntax: [user@]host:path
+        let (left, path) = raw.split_once(':')?;
+        let host = left.rsplit('@').next()?;
+        (host.to_string(), path.to_string())
+    };
+    let path = path.trim_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path).to_string();
+    if path.is_empty() {
+        return None;
+    }
+    let lower = host.to_lowercase();
+    let provider = if lower.contains("github") {
+        ForgeProvider::Github
+    } else if lower.contains("gitlab") {
+        ForgeProvider::Gitlab
+    } else {
+        return None;
+    };
+    Some(ForgeRemote {
+        provider,
+        host,
+        path,
+    })
+}
+
+pub(crate) fn resolve_remote(cwd: &str, remote: &str) -> Result<ForgeRemote, String> {
+    let url = run_capture(
+        Command::new("git")
+            .args(["remote", "get-url", remote])
+            .current_dir(cwd),
+        None,
+    )?;
+    parse_remote_url(&url)
+        .ok_or_else(|| format!("remote {remote} ({url}) is not a GitHub or GitLab repository"))
+}
+
+fn render_template(template: &str, vars: &[(&str, &str)]) -> String {
+    let mut out = template.to_string();
+    for (key, value) in vars {
+        out = out.replace(&format!("{{{{{key}}}}}"), value);
+    }
+    out
+}
+
+fn default_title(transcript: &[TranscriptEntry], head: &str) -> String {
+    let first_prompt = transcript
+        .iter()
+        .find(|e| e.role == "user")
+        .and_then(|e| e.content.lines().map(str::trim).find(|l| !l.is_empty()));
+    let title = first_prompt.unwrap_or(head);
+    if title.chars().count() > MAX_TITLE_LEN {
+        let truncated: String = title.chars().take(MAX_TITLE_LEN - 1).collect();
+        format!("{}…", truncated.trim_end())
+    } else {
+        title.to_string()
+    }
+}
+
+fn render_transcript(transcript: &[TranscriptEntry]) -> String {
+    transcript
+        .iter()
+        .map(|e| format!("**{}:** {}", e.role, e.content.trim()))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Extract the PR/MR number from a URL like `…/pull/42` or `…/merge_requests/7`.
+fn number_from_url(url: &str) -> Option<u64> {
+    url.trim_end_matches('/').rsplit('/').next()?.parse().ok()
+}
+
+fn last_url_line(output: &str) -> Option<String> {
+    output
+        .lines()
+        .rev()
+        .map(str::trim)
+        .find(|l| l.starts_with("http://") || l.starts_with("https://"))
+        .map(str::to_string)
+}
+
+fn create_with_cli(
+    remote: &ForgeRemote,
+    cwd: &str,
+    head: &str,
+    base: &str,
+    title: &str,
+    body: &str,
+    draft: bool,
+) -> Result<String, String> {
+    match remote.provider {
+        ForgeProvider::Github => {
+            let mut cmd = Command::new("gh");
+            cmd.args([
+                "pr", "create", "--head", head, "--base", base, "--title", title,
+            ])
+            .args(["--body-file", "-"])
+            .current_dir(cwd);
+            if draft {
+                cmd.arg("--draft");
+            }
+            let output = run_capture(&mut cmd, Some(body))?;
+            last_url_line(&output)
+                .ok_or_else(|| format!("could not find PR URL in output: {output}"))
+        }
+        ForgeProvider::Gitlab => {
+            // `glab mr create` only takes the description as an argument,
+            // where other users can read it, so post it through the API.
+            let (path, payload) = gitlab_merge_request(remote, head, base, title, body, draft);
+            let output = run_capture(
+                Command::new("glab")
+                    .args([
+                        "api",
+                        "--hostname",
+                        remote.host.as_str(),
+                        "--method",
+                        "POST",
+                    ])
+                    .args(["--header", "Content-Type: application/json"])
+                    .args([path.as_str(), "--input", "-"])
+                    .current_dir(cwd),
+                Some(&payload.to_string()),
+            )?;
+            let response: serde_json::Value =
+                serde_json::from_str(&output).map_err(|e| e.to_string())?;
+            response["web_url"]
+                .as_str()
+                .map(str::to_string)
+                .ok_or_else(|| format!("could not find MR URL in output: {output}"))
+        }
+    }
+}
+
+/// API path (relative to `api_base`) and payload that open a merge request.
+fn gitlab_merge_request(
+    remote: &ForgeRemote,
+    head: &str,
+    base: &str,
+    title: &str,
+    body: &str,
+    draft: bool,
+) -> (String, serde_json::Value) {
+    let title = if draft {
+        format!("Draft: {title}")
+    } else {
+        title.to_string()
+    };
+    (
+        format!(
+            "projects/{}/merge_requests",
+            url::form_urlencoded::byte_serialize(remote.path.as_bytes()).collect::<String>()
+        ),
+        serde_json::json!({
+            "title": title,
+            "source_branch": head,
+            "target_branch": base,
+            "description": body,
+        }),
+    )
+}
+
+/// Base URL of the forge REST API for `remote`.
+pub(crate) fn api_base(remote: &ForgeRemote) -> String {
+    match remote.provider {
+        ForgeProvider::Github if remote.host == "github.com" => {
+            "https://api.github.com".to_string()
+        }
+        ForgeProvider::Github => format!("https://{}/api/v3", remote.host),
+        ForgeProvider::Gitlab => format!("https://{}/api/v4", remote.host),
+    }
+}
+
+/// Attach the provider-specific auth header to a request.
+pub(crate) fn authorize(
+    request: ureq::Request,
+    provider: ForgeProvider,
+    token: &str,
+) -> ureq::Request {
+    match provider {
+        ForgeProvider::Github => request
+            .set("Authorization", &format!("Bearer {token}"))
+            .set("Accept", "application/vnd.github+json"),
+        ForgeProvider::Gitlab => request.set("PRIVATE-TOKEN", token),
+    }
+}
+
+fn api_error(e: ureq::Error) -> String {
+    match e {
+        ureq::Error::Status(code, resp) => format!(
+            "forge API returned {code}: {}",
+            resp.into_string().unwrap_or_default()
+        ),
+        other => other.to_string(),
+    }
+}
+
+/// GET a forge REST endpoint (relative to `api_base`). With a token the API is
+/// called directly; otherwise `gh api` / `glab api` are used so the CLI's own
+/// login is reused.
+pub(crate) fn api_get(
+    remote: &ForgeRemote,
+    token: Option<&str>,
+    cwd: &str,
+    path: &str,
+) -> Result<serde_json::Value, String> {
+    if let Some(token) = token {
+        let url = format!("{}/{}", api_base(remote), path);
+        return authorize(ureq::get(&url), remote.provider, token)
+            .call()
+            .map_err(api_error)?
+            .into_json()
+            .map_err(|e| e.to_string());
+    }
+    let program = match remote.provider {
+        ForgeProvider::Github => "gh",
+        ForgeProvider::Gitlab => "glab",
+    };
+    let output = run_capture(
+        Command::new(program)
+            .args(["api", "--hostname", remote.host.as_str(), path])
+            .current_dir(cwd),
+        None,
+    )?;
+    serde_json::from_str(&output).map_err(|e| e.to_string())
+}
+
+fn create_with_api(
+    remote: &ForgeRemote,
+    token: &str,
+    head: &str,
+    base: &str,
+    title: &str,
+    body: &str,
+    draft: bool,
+) -> Result<(String, Option<u64>), String> {
+    let (url, payload, url_key, number_key) = match remote.provider {
+        ForgeProvider::Github => (
+            format!("{}/repos/{}/pulls", api_base(remote), remote.path),
+            serde_json::json!({ "title": title, "head": head, "base": base, "body": body, "draft": draft }),
+            "html_url",
+            "number",
+        ),
+        ForgeProvider::Gitlab => {
+            let (path, payload) = gitlab_merge_request(remote, head, base, title, body, draft);
+            (
+                format!("{}/{path}", api_base(remote)),
+                payload,
+                "web_url",
+                "iid",
+            )
+        }
+    };
+
+    let response: serde_json::Value = authorize(ureq::post(&url), remote.provider, token)
+        .send_json(payload)
+        .map_err(api_error)?
+        .into_json()
+        .map_err(|e| e.to_string())?;
+
+    let pr_url = response[url_key]
+        .as_str()
+        .ok_or("forge API response did not include a URL")?
+        .to_string();
+    Ok((pr_url, response[number_key].as_u64()))
+}
+
+/// Create a pull request (GitHub) or merge request (GitLab) for a task branch.
+///
+/// Title and body are templates rendered with `{{branch}}`, `{{base}}`,
+/// `{{summary}}` (last assistant message) and `{{transcript}}`. Without a token
+/// the authenticated `gh` / `glab` CLI is used. The result is returned and
+/// also emitted as `forge:pr-created` so task cards can pick up the URL.
+#[tauri::command]
+pub async fn forge_create_pr(
+    window: WebviewWindow,
+    app: AppHandle,
+    sandbox: State<'_, Sandbox>,
+    request: CreatePrRequest,
+) -> Result<PullRequestInfo, String> {
+    crate::require_app_window(&window)?;
+    sandbox.check_dir(&request.cwd)?;
+    crate::plan_mode::check(
+        &app,
+        request.agent.as_deref(),
+        Some(&request.cwd),
+        "pullRequest",
+        &format!("{} → {}", request.head, request.base),
+    )?;
+    let audited = (
+        request.cwd.clone(),
+        format!("pull request {} → {}", request.head, request.base),
+        request.agent.clone(),
+    );
+
+    let worker = app.clone();
+    let info = tauri::async_runtime::spawn_blocking(move || {
+        let remote_name = request.remote.as_deref().unwrap_or("origin");
+        let remote = resolve_remote(&request.cwd, remote_name)?;
+
+        let summary = request
+            .transcript
+            .iter()
+            .rev()
+            .find(|e| e.role == "assistant")
+            .map(|e| redact_tokens(e.content.trim()))
+            .unwrap_or_default();
+        let transcript = redact_tokens(&render_transcript(&request.transcript));
+        let vars = [
+            ("branch", request.head.as_str()),
+            ("base", request.base.as_str()),
+            ("summary", summary.as_str()),
+            ("transcript", transcript.as_str()),
+        ];
+        let title = match &request.title {
+            Some(t) => render_template(t, &vars),
+            None => default_title(&request.transcript, &request.head),
+        };
+        let body = render_template(
+            request.body.as_deref().unwrap_or(DEFAULT_BODY_TEMPLATE),
+            &vars,
+        );
+
+        let token = request
+            .token
+            .clone()
+            .or_else(|| stored_token(&worker, &remote));
+        let (url, number) = match &token {
+            Some(token) => create_with_api(
+                &remote,
+                token,
+                &request.head,
+                &request.base,
+                &title,
+                &body,
+                request.draft,
+            )?,
+            None => {
+                let url = create_with_cli(
+                    &remote,
+                    &request.cwd,
+                    &request.head,
+                    &request.base,
+                    &title,
+                    &body,
+                    request.draft,
+                )?;
+                let number = number_from_url(&url);
+                (url, number)
+            }
+        };
+
+        Ok::<_, String>(PullRequestInfo {
+            cwd: request.cwd,
+            branch: request.head,
+            provider: remote.provider,
+            url,
+            number,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let (cwd, target, agent) = audited;
+    audit::record(
+        &app,
+        AuditEvent {
+            kind: AuditKind::GitMutation,
+            agent: agent.as_deref(),
+            project: Some(&cwd),
+            target: &target,
+            outcome: if info.is_ok() {
+                Outcome::Ok
+            } else {
+                Outcome::Error
+            },
+            detail: match &info {
+                Ok(info) => Some(info.url.clone()),
+                Err(e) => Some(e.clone()),
+            },
+        },
+    );
+    let info = info?;
+
+    crate::timeline::record(
+        &app,
+        TimelineEvent {
+            kind: TimelineKind::PrOpened,
+            project: crate::timeline::project_of(&app, &cwd).as_deref(),
+            subject: &info.url,
+            title: &target,
+            detail: None,
+        },
+    );
+    crate::analytics::record_feature(&app, "forge.create_pr");
+    app.emit_to(
+        EventTarget::webview_window(window.label()),
+        "forge:pr-created",
+        info.clone(),
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(info)
+}
+
+/// How often a watched PR is polled unless the caller asks otherwise.
+const DEFAULT_POLL_INTERVAL_SECS: u64 = 60;
+
+/// Floor for the poll interval so a misconfigured client can't hammer the API.
+const MIN_POLL_INTERVAL_SECS: u64 = 15;
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PrStatus {
+    cwd: String,
+    number: u64,
+    /// `open`, `draft`, `merged` or `closed`.
+    state: String,
+    /// `approved`, `changes_requested` or `pending`.
+    review: String,
+    /// `passing`, `failing`, `pending` or `none` when no checks are configured.
+    checks: String,
+    checks_passed: u32,
+    checks_failed: u32,
+    checks_pending: u32,
+}
+
+impl PrStatus {
+    fn is_final(&self) -> bool {
+        self.state == "merged" || self.state == "closed"
+    }
+}
+
+#[derive(Default)]
+struct CheckCounts {
+    passed: u32,
+    failed: u32,
+    pending: u32,
+}
+
+impl CheckCounts {
+    fn summary(&self) -> &'static str {
+        if self.failed > 0 {
+            "failing"
+        } else if self.pending > 0 {
+            "pending"
+        } else if self.passed > 0 {
+            "passing"
+        } else {
+            "none"
+        }
+    }
+}
+
+fn github_status(
+    remote: &ForgeRemote,
+    token: Option<&str>,
+    cwd: &str,
+    number: u64,
+) -> Result<PrStatus, String> {
+    let base = format!("repos/{}", remote.path);
+    let pr = api_get(remote, token, cwd, &format!("{base}/pulls/{number}"))?;
+    let state = if pr["merged"].as_bool().unwrap_or(false) {
+        "merged"
+    } else if pr["state"].as_str() == Some("closed") {
+        "closed"
+    } else if pr["draft"].as_bool().unwrap_or(false) {
+        "draft"
+    } else {
+        "open"
+    };
+
+    // Latest non-comment review per reviewer decides the overall verdict.
+    let reviews = api_get(
+        remote,
+        token,
+        cwd,
+        &format!("{base}/pulls/{number}/reviews"),
+    )?;
+    let mut latest: Vec<(String, String)> = Vec::new();
+    for review in reviews.as_array().into_iter().flatten() {
+        let user = review["user"]["login"].as_str().unwrap_or("").to_string();
+        let verdict = review["state"].as_str().unwrap_or("").to_string();
+        if verdict == "COMMENTED" || verdict == "PENDING" {
+            continue;
+        }
+        latest.retain(|(u, _)| u != &user);
+        latest.push((user, verdict));
+    }
+    let review = if latest.iter().any(|(_, v)| v == "CHANGES_REQUESTED") {
+        "changes_requested"
+    } else if latest.iter().any(|(_, v)| v == "APPROVED") {
+        "approved"
+    } else {
+        "pending"
+    };
+
+    let mut counts = CheckCounts::default();
+    if let Some(sha) = pr["head"]["sha"].as_str() {
+        let runs = api_get(
+            remote,
+            token,
+            cwd,
+            &format!("{base}/commits/{sha}/check-runs"),
+        )?;
+        for run in runs["check_runs"].as_array().into_iter().flatten() {
+            match (run["status"].as_str(), run["conclusion"].as_str()) {
+                (Some("completed"), Some("success" | "neutral" | "skipped")) => counts.passed += 1,
+                (Some("completed"), _) => counts.failed += 1,
+                _ => counts.pending += 1,
+            }
+        }
+        // Legacy commit statuses (e.g. external CI posting via the Status API).
+        let combined = api_get(remote, token, cwd, &format!("{base}/commits/{sha}/status"))?;
+        for status in combined["statuses"].as_array().into_iter().flatten() {
+            match status["state"].as_str() {
+                Some("success") => counts.passed += 1,
+                Some("pending") => counts.pending += 1,
+                _ => counts.failed += 1,
+            }
+        }
+    }
+
+    Ok(PrStatus {
+        cwd: cwd.to_string(),
+        number,
+        state: state.to_string(),
+        review: review.to_string(),
+        checks: counts.summary().to_string(),
+        checks_passed: counts.passed,
+        checks_failed: counts.failed,
+        checks_pending: counts.pending,
+    })
+}
+
+fn gitlab_status(
+    remote: &ForgeRemote,
+    token: Option<&str>,
+    cwd: &str,
+    number: u64,
+) -> Result<PrStatus, String> {
+    let project = url::form_urlencoded::byte_serialize(remote.path.as_bytes()).collect::<String>();
+    let base = format!("projects/{project}/merge_requests/{number}");
+    let mr = api_get(remote, token, cwd, &base)?;
+    let state = match mr["state"].as_str() {
+        Some("merged") => "merged",
+        Some("closed") | Some("locked") => "closed",
+        _ if mr["draft"].as_bool().unwrap_or(false) => "draft",
+        _ => "open",
+    };
+
+    let approvals = api_get(remote, token, cwd, &format!("{base}/approvals"))?;
+    let review = if approvals["approved"].as_bool().unwrap_or(false) {
+        "approved"
+    } else {
+        "pending"
+    };
+
+    // GitLab reports one pipeline per MR head rather than individual checks.
+    let mut counts = CheckCounts::default();
+    match mr["head_pipeline"]["status"].as_str() {
+        Some("success") | Some("skipped") => counts.passed = 1,
+        Some("failed") | Some("canceled") => counts.failed = 1,
+        Some(_) => counts.pending = 1,
+        None => {}
+    }
+
+    Ok(PrStatus {
+        cwd: cwd.to_string(),
+        number,
+        state: state.to_string(),
+        review: review.to_string(),
+        checks: counts.summary().to_string(),
+        checks_passed: counts.passed,
+        checks_failed: counts.failed,
+        checks_pending: counts.pending,
+    })
+}
+
+fn fetch_pr_status(
+    app: &AppHandle,
+    cwd: &str,
+    remote: Option<&str>,
+    number: u64,
+    token: Option<&str>,
+) -> Result<PrStatus, String> {
+    let remote = resolve_remote(cwd, remote.unwrap_or("origin"))?;
+    let stored = match token {
+        Some(_) => None,
+        None => stored_token(app, &remote),
+    };
+    let token = token.or(stored.as_deref());
+    match remote.provider {
+        ForgeProvider::Github => github_status(&remote, token, cwd, number),
+        ForgeProvider::Gitlab => gitlab_status(&remote, token, cwd, number),
+    }
+}
+
+/// Active PR polls, keyed by `<cwd>#<number>`. Each poll runs on its own
+/// thread and exits when its stop flag is set or the PR is merged/closed.
+pub struct ForgeWatcher {
+    polls: Mutex<HashMap<String, Poll>>,
+}
+
+struct Poll {
+    stop: Arc<AtomicBool>,
+    /// The status last emitted, handed to windows that join the poll later.
+    last: Arc<Mutex<Option<PrStatus>>>,
+}
+
+impl ForgeWatcher {
+    pub fn new() -> Self {
+        Self {
+            polls: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchPrRequest {
+    cwd: String,
+    number: u64,
+    #[serde(default)]
+    remote: Option<String>,
+    #[serde(default)]
+    token: Option<String>,
+    #[serde(default)]
+    interval_secs: Option<u64>,
+}
+
+fn poll_key(cwd: &str, number: u64) -> String {
+    format!("{cwd}#{number}")
+}
+
+/// Event-router topic for a PR poll. Windows watching the same PR share one
+/// poll; it stops when the last of them unwatches or closes.
+fn poll_topic(key: &str) -> String {
+    format!("{POLL_TOPIC_PREFIX}{key}")
+}
+
+const POLL_TOPIC_PREFIX: &str = "forge:";
+
+/// Fetch the current review/CI status of a PR once.
+#[tauri::command]
+pub async fn forge_pr_status(
+    window: WebviewWindow,
+    app: AppHandle,
+    sandbox: State<'_, Sandbox>,
+    cwd: String,
+    number: u64,
+    remote: Option<String>,
+    token: Option<String>,
+) -> Result<PrStatus, String> {
+    crate::require_app_window(&window)?;
+    sandbox.check_dir(&cwd)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        fetch_pr_status(&app, &cwd, remote.as_deref(), number, token.as_deref())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Start polling a PR in the background. A `forge:pr-status` event is emitted
+/// on the first fetch and whenever the status changes; polling stops by itself
+/// once the PR is merged or closed. Watching an already-watched PR joins its
+/// poll, and the window gets the last fetched status right away.
+#[tauri::command]
+pub fn forge_watch_pr(
+    window: WebviewWindow,
+    app: AppHandle,
+    state: State<'_, ForgeWatcher>,
+    sandbox: State<'_, Sandbox>,
+    request: WatchPrRequest,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    sandbox.check_dir(&request.cwd)?;
+
+    let WatchPrRequest {
+        cwd,
+        number,
+        remote,
+        token,
+        interval_secs,
+    } = request;
+    let key = poll_key(&cwd, number);
+    let topic = poll_topic(&key);
+    let stop = Arc::new(AtomicBool::new(false));
+    let last = Arc::new(Mutex::new(None));
+    {
+        let mut polls = state.polls.lock().map_err(|e| e.to_string())?;
+        app.state::<EventRouter>().subscribe(&topic, window.label());
+        if let Some(poll) = polls.get(&key) {
+            let status = poll.last.lock().map_err(|e| e.to_string())?.clone();
+            if let Some(status) = status {
+                app.emit_to(
+                    EventTarget::webview_window(window.label()),
+                    "forge:pr-status",
+                    status,
+                )
+                .map_err(|e| e.to_string())?;
+            }
+            return Ok(());
+        }
+        polls.insert(
+            key.clone(),
+            Poll {
+                stop: stop.clone(),
+                last: last.clone(),
+            },
+        );
+    }
+
+    let interval = Duration::from_secs(
+        interval_secs
+            .unwrap_or(DEFAULT_POLL_INTERVAL_SECS)
+            .max(MIN_POLL_INTERVAL_SECS),
+    );
+
+    std::thread::spawn(move || {
+        while !stop.load(Ordering::Relaxed) {
+            match fetch_pr_status(&app, &cwd, remote.as_deref(), number, token.as_deref()) {
+                Ok(status) => {
+                    let done = status.is_final();
+                    let changed = last.lock().map_or(true, |mut last| {
+                        let changed = last.as_ref() != Some(&status);
+                        if changed {
+                            *last = Some(status.clone());
+                        }
+                        changed
+                    });
+                    if changed {
+                        emit_subscribed_throttled(
+                            &app,
+                            &topic,
+                            FORGE_PR_STATUS,
+                            "forge:pr-status",
+                            Coalesce::Latest,
+                            status,
+                        );
+                    }
+                    if done {
+                        break;
+                    }
+                }
+                Err(error) => {
+                    emit_subscribed(
+                        &app,
+                        &topic,
+                        "forge:pr-status-error",
+                        serde_json::json!({ "cwd": cwd, "number": number, "error": error }),
+                    );
+                }
+            }
+            // Sleep in short slices so unwatching takes effect promptly.
+            let deadline = Instant::now() + interval;
+            while Instant::now() < deadline && !stop.load(Ordering::Relaxed) {
+                std::thread::sleep(Duration::from_millis(500));
+            }
+        }
+        if let Some(watcher) = app.try_state::<ForgeWatcher>() {
+            if let Ok(mut polls) = watcher.polls.lock() {
+                // Only remove our own entry; a re-watch may have replaced it.
+                if polls
+                    .get(&key)
+                    .is_some_and(|poll| Arc::ptr_eq(&poll.stop, &stop))
+                {
+                    polls.remove(&key);
+                    app.state::<EventRouter>().remove_topic(&topic);
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Stop polling a PR started with `forge_watch_pr`.
+#[tauri::command]
+pub fn forge_unwatch_pr(
+    window: WebviewWindow,
+    state: State<'_, ForgeWatcher>,
+    router: State<'_, EventRouter>,
+    cwd: String,
+    number: u64,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    let key = poll_key(&cwd, number);
+    if router.unsubscribe(&poll_topic(&key), window.label()) {
+        stop_poll(&state, &key);
+    }
+    Ok(())
+}
+
+fn stop_poll(state: &ForgeWatcher, key: &str) {
+    if let Ok(mut polls) = state.polls.lock() {
+        if let Some(poll) = polls.remove(key) {
+            poll.stop.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Stop the poll behind an event-router topic nobody listens to anymore.
+/// Returns false for topics that aren't PR polls.
+pub(crate) fn stop_orphaned(state: &ForgeWatcher, topic: &str) -> bool {
+    match topic.strip_prefix(POLL_TOPIC_PREFIX) {
+        Some(key) => {
+            stop_poll(state, key);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Stop every PR poll — called on app exit.
+pub fn unwatch_all(state: &ForgeWatcher) {
+    if let Ok(mut polls) = state.polls.lock() {
+        for (_, poll) in polls.drain() {
+            poll.stop.store(true, Ordering::Relaxed);
+        }
+    }
+}
@@ -0,0 +1,345 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Ipv4Addr, Shutdown, TcpListener, TcpStream};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, EventTarget, Manager, State, WebviewWindow};
+
+use crate::pty::PtyManager;
+
+/// How often a forward's listener checks whether it was removed.
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ContainerEngine {
+    Docker,
+    Podman,
+}
+
+/// Where the terminal's session runs, and so where `remote_port` is.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "kind"
+)]
+pub enum ForwardTarget {
+    /// `destination` as passed to `ssh`, e.g. `user@host` or a `Host` from
+    /// `~/.ssh/config`. Keys or an agent must be set up: there is no
+    /// password prompt.
+    Ssh { destination: String },
+    /// A running container. Connections go through `nc` or `socat` inside
+    /// it, so one of them must be installed there.
+    Container {
+        engine: ContainerEngine,
+        container: String,
+    },
+}
+
+/// An ssh destination or container name, passed as an argument: it must
+/// not be empty, hold spaces or look like an option.
+pub(crate) fn check_destination(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() || name.starts_with('-') || name.contains(char::is_whitespace) {
+        return Err(format!("invalid destination {name:?}"));
+    }
+    Ok(())
+}
+
+impl ForwardTarget {
+    fn validate(&self) -> Result<(), String> {
+        check_destination(match self {
+            ForwardTarget::Ssh { destination } => destination,
+            ForwardTarget::Container { container, .. } => container,
+        })
+    }
+
+    /// The process that carries one connection to `port` over its stdio.
+    fn connect_command(&self, port: u16) -> Command {
+        match self {
+            ForwardTarget::Ssh { destination } => {
+                let mut command = Command::new("ssh");
+                command
+                    .args(["-o", "BatchMode=yes", "-W"])
+                    .arg(format!("localhost:{port}"))
+                    .arg("--")
+                    .arg(destination);
+                command
+            }
+            ForwardTarget::Container { engine, container } => {
+                let mut command = Command::new(match engine {
+                    ContainerEngine::Docker => "docker",
+                    ContainerEngine::Podman => "podman",
+                });
+                command
+                    .args(["exec", "-i", container, "sh", "-c"])
+                    .arg(format!(
+                        "command -v nc >/dev/null && exec nc localhost {port}; \
+                     exec socat - TCP:localhost:{port}"
+                    ));
+                command
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Forward {
+    pub id: String,
+    /// The terminal whose session the forward belongs to; it is removed when
+    /// the terminal exits.
+    pub terminal: String,
+    pub target: ForwardTarget,
+    pub remote_port: u16,
+    pub local_port: u16,
+    pub url: String,
+}
+
+struct ActiveForward {
+    forward: Forward,
+    window: String,
+    stop: Arc<AtomicBool>,
+    /// Helper processes of open connections, by connection number.
+    connections: Arc<Mutex<HashMap<u64, Child>>>,
+}
+
+impl ActiveForward {
+    fn close(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Ok(mut connections) = self.connections.lock() {
+            for (_, mut child) in connections.drain() {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+        }
+    }
+}
+
+/// Local ports forwarded into SSH and container sessions.
+pub struct PortForwards {
+    forwards: Mutex<HashMap<String, ActiveForward>>,
+    next_id: AtomicU64,
+}
+
+impl PortForwards {
+    pub fn new() -> Self {
+        Self {
+            forwards: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+        }
+    }
+}
+
+/// Copy `from` into `to` until either side closes.
+fn pump(mut from: impl Read, mut to: impl Write) {
+    let _ = std::io::copy(&mut from, &mut to);
+    let _ = to.flush();
+}
+
+/// Carry `stream` to the session's `port` through a helper process.
+fn connect(
+    stream: TcpStream,
+    target: &ForwardTarget,
+    port: u16,
+    connection: u64,
+    connections: Arc<Mutex<HashMap<u64, Child>>>,
+) -> Result<(), String> {
+    let mut child = target
+        .connect_command(port)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    let stdin = child.stdin.take().ok_or("no stdin")?;
+    let stdout = child.stdout.take().ok_or("no stdout")?;
+    let reader = stream.try_clone().map_err(|e| e.to_string())?;
+    connections
+        .lock()
+        .map_err(|e| e.to_string())?
+        .insert(connection, child);
+
+    std::thread::spawn(move || pump(reader, stdin));
+    std::thread::spawn(move || {
+        pump(stdout, &stream);
+        let _ = stream.shutdown(Shutdown::Both);
+        let child = connections
+            .lock()
+            .ok()
+            .and_then(|mut connections| connections.remove(&connection));
+        if let Some(mut child) = child {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    });
+    Ok(())
+}
+
+fn serve(
+    listener: TcpListener,
+    id: String,
+    target: ForwardTarget,
+    port: u16,
+    stop: Arc<AtomicBool>,
+    connections: Arc<Mutex<HashMap<u64, Child>>>,
+) {
+    let mut next_connection = 0u64;
+    while !stop.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                let _ = stream.set_nonblocking(false);
+                next_connection += 1;
+                if let Err(e) = connect(stream, &target, port, next_connection, connections.clone())
+                {
+                    tracing::warn!("forward {id}: connection failed: {e}");
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                std::thread::sleep(ACCEPT_POLL_INTERVAL);
+            }
+            Err(e) => {
+                tracing::warn!("forward {id}: accept failed: {e}");
+                std::thread::sleep(ACCEPT_POLL_INTERVAL);
+            }
+        }
+    }
+    tracing::info!("forward {id} closed");
+}
+
+/// Make `remote_port` of terminal `terminal`'s SSH or container session
+/// reachable on `localhost:local_port`. Without a `local_port`, the same
+/// port number is tried first, then any free one. Each connection runs its
+/// own `ssh -W` or container `exec`, so nothing keeps running on the other
+/// side between connections.
+#[tauri::command]
+pub fn forward_add(
+    window: WebviewWindow,
+    app: AppHandle,
+    state: State<'_, PortForwards>,
+    terminal: String,
+    target: ForwardTarget,
+    remote_port: u16,
+    local_port: Option<u16>,
+) -> Result<Forward, String> {
+    crate::require_app_window(&window)?;
+    if app.state::<PtyManager>().window_of(&terminal).as_deref() != Some(window.label()) {
+        return Err(format!("no terminal {terminal} in this window"));
+    }
+    target.validate()?;
+    if remote_port == 0 {
+        return Err("remote port must not be 0".to_string());
+    }
+    let listener = match local_port {
+        Some(port) => TcpListener::bind((Ipv4Addr::LOCALHOST, port)),
+        None => TcpListener::bind((Ipv4Addr::LOCALHOST, remote_port))
+            .or_else(|_| TcpListener::bind((Ipv4Addr::LOCALHOST, 0))),
+    }
+    .map_err(|e| format!("failed to listen on localhost: {e}"))?;
+    listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+    let local_port = listener.local_addr().map_err(|e| e.to_string())?.port();
+
+    let id = format!("forward-{}", state.next_id.fetch_add(1, Ordering::Relaxed));
+    let forward = Forward {
+        id: id.clone(),
+        terminal,
+        target: target.clone(),
+        remote_port,
+        local_port,
+        url: format!("http://localhost:{local_port}/"),
+    };
+    let stop = Arc::new(AtomicBool::new(false));
+    let connections = Arc::new(Mutex::new(HashMap::new()));
+    state.forwards.lock().map_err(|e| e.to_string())?.insert(
+        id.clone(),
+        ActiveForward {
+            forward: forward.clone(),
+            window: window.label().to_string(),
+            stop: stop.clone(),
+            connections: connections.clone(),
+        },
+    );
+    tracing::info!("forward {id}: localhost:{local_port} -> {target:?} port {remote_port}",);
+    std::thread::spawn(move || serve(listener, id, target, remote_port, stop, connections));
+    Ok(forward)
+}
+
+/// Close a forward and the connections through it.
+#[tauri::command]
+pub fn forward_remove(
+    window: WebviewWindow,
+    state: State<'_, PortForwards>,
+    id: String,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    let mut forwards = state.forwards.lock().map_err(|e| e.to_string())?;
+    match forwards.get(&id) {
+        Some(active) if active.window == window.label() => {}
+        _ => return Err(format!("no forward {id} in this window")),
+    }
+    if let Some(active) = forwards.remove(&id) {
+        active.close();
+    }
+    Ok(())
+}
+
+/// This window's forwards.
+#[tauri::command]
+pub fn forward_list(
+    window: WebviewWindow,
+    state: State<'_, PortForwards>,
+) -> Result<Vec<Forward>, String> {
+    crate::require_app_window(&window)?;
+    let forwards = state.forwards.lock().map_err(|e| e.to_string())?;
+    let mut list: Vec<Forward> = forwards
+        .values()
+        .filter(|active| active.window == window.label())
+        .map(|active| active.forward.clone())
+        .collect();
+    list.sort_by_key(|forward| forward.local_port);
+    Ok(list)
+}
+
+/// `forward:closed`: a forward went away with its terminal.
+#[derive(Clone, Serialize)]
+struct ForwardClosed {
+    id: String,
+}
+
+/// Close the forwards of a terminal that exited.
+pub(crate) fn terminal_exited(app: &AppHandle, terminal: &str) {
+    let state = match app.try_state::<PortForwards>() {
+        Some(state) => state,
+        None => return,
+    };
+    let closed: Vec<ActiveForward> = match state.forwards.lock() {
+        Ok(mut forwards) => {
+            let ids: Vec<String> = forwards
+                .values()
+                .filter(|active| active.forward.terminal == terminal)
+                .map(|active| active.forward.id.clone())
+                .collect();
+            ids.iter().filter_map(|id| forwards.remove(id)).collect()
+        }
+        Err(_) => return,
+    };
+    for active in closed {
+        active.close();
+        let target = EventTarget::webview_window(&active.window);
+        let payload = ForwardClosed {
+            id: active.forward.id,
+        };
+        let _ = app.emit_to(target, "forward:closed", payload);
+    }
+}
+
+pub fn close_all(state: &PortForwards) {
+    if let Ok(mut forwards) = state.forwards.lock() {
+        for (_, active) in forwards.drain() {
+            active.close();
+        }
+    }
+}
@@ -0,0 +1,1447 @@
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, EventTarget, Manager, State, WebviewWindow};
+
+use crate::audit::{self, AuditEvent, AuditKind, Outcome};
+use crate::cancel::{CancelGuard, CancelToken};
+use crate::dry_run::Capture;
+use crate::policy::WriteSummary;
+use crate::recent::{self, TouchSource};
+use crate::remote_fs::RemoteRoot;
+use crate::sandbox::Sandbox;
+
+/// Default cap for `read_file`. Callers may ask for more, up to `MAX_READ_BYTES`.
+const DEFAULT_READ_BYTES: u64 = 2 * 1024 * 1024;
+
+/// Hard cap for `read_file`, regardless of what the caller asks for.
+const MAX_READ_BYTES: u64 = 20 * 1024 * 1024;
+
+/// Hard cap for `write_file` payloads.
+const MAX_WRITE_BYTES: usize = 20 * 1024 * 1024;
+
+/// Bytes inspected for NULs when deciding if a file is binary — the same
+/// heuristic git uses (`buffer_is_binary`).
+const BINARY_SNIFF_BYTES: usize = 8000;
+
+/// `list_dir` stops after this many entries and sets `truncated`.
+const MAX_DIR_ENTRIES: usize = 5000;
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+fn modified_ms(meta: &std::fs::Metadata) -> Option<i64> {
+    meta.modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as i64)
+}
+
+fn kind_of(meta: &std::fs::Metadata) -> &'static str {
+    if meta.is_dir() {
+        "directory"
+    } else if meta.file_type().is_symlink() {
+        "symlink"
+    } else {
+        "file"
+    }
+}
+
+pub(crate) fn looks_binary(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(BINARY_SNIFF_BYTES)].contains(&0)
+}
+
+fn decode_utf16(bytes: &[u8], little_endian: bool) -> String {
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|c| {
+            if little_endian {
+                u16::from_le_bytes([c[0], c[1]])
+            } else {
+                u16::from_be_bytes([c[0], c[1]])
+            }
+        })
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+/// Decode file bytes, returning `(text, encoding, had_bom)`. Falls back to
+/// Latin-1 for bytes that aren't valid UTF-8 so legacy files still open.
+fn decode(bytes: &[u8]) -> (String, &'static str, bool) {
+    if let Some(rest) = bytes.strip_prefix(UTF8_BOM) {
+        return (String::from_utf8_lossy(rest).into_owned(), "utf-8", true);
+    }
+    if let Some(rest) = bytes.strip_prefix(UTF16LE_BOM) {
+        return (decode_utf16(rest, true), "utf-16le", true);
+    }
+    if let Some(rest) = bytes.strip_prefix(UTF16BE_BOM) {
+        return (decode_utf16(rest, false), "utf-16be", true);
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(text) => (text.to_string(), "utf-8", false),
+        // A read cut off mid-character by the size cap is still UTF-8.
+        Err(e) if e.error_len().is_none() => (
+            String::from_utf8_lossy(&bytes[..e.valid_up_to()]).into_owned(),
+            "utf-8",
+            false,
+        ),
+        Err(_) => (bytes.iter().map(|&b| b as char).collect(), "latin1", false),
+    }
+}
+
+fn encode(text: &str, encoding: &str, bom: bool) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(text.len() + 3);
+    match encoding {
+        "utf-8" => {
+            if bom {
+                out.extend_from_slice(UTF8_BOM);
+            }
+            out.extend_from_slice(text.as_bytes());
+        }
+        "utf-16le" | "utf-16be" => {
+            let le = encoding == "utf-16le";
+            if bom {
+                out.extend_from_slice(if le { UTF16LE_BOM } else { UTF16BE_BOM });
+            }
+            for unit in text.encode_utf16() {
+                out.extend_from_slice(&if le {
+                    unit.to_le_bytes()
+                } else {
+                    unit.to_be_bytes()
+                });
+            }
+        }
+        "latin1" => {
+            for c in text.chars() {
+                let code = c as u32;
+                if code > 0xFF {
+                    return Err(format!("character {c:?} cannot be encoded as latin1"));
+                }
+                out.push(code as u8);
+            }
+        }
+        other => return Err(format!("unsupported encoding {other}")),
+    }
+    Ok(out)
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadFileOptions {
+    /// Maximum bytes to read; the rest of the file is skipped and
+    /// `truncated` is set.
+    pub max_bytes: Option<u64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileContent {
+    pub path: String,
+    pub size: u64,
+    pub modified_ms: Option<i64>,
+    /// Binary files are detected but not decoded; `content` is empty.
+    pub binary: bool,
+    pub truncated: bool,
+    /// `utf-8`, `utf-16le`, `utf-16be`, `latin1`, or `binary` when not decoded.
+    pub encoding: String,
+    pub bom: bool,
+    pub content: String,
+}
+
+#[tauri::command]
+pub async fn read_file(
+    window: WebviewWindow,
+    sandbox: State<'_, Sandbox>,
+    root: String,
+    path: String,
+    options: Option<ReadFileOptions>,
+) -> Result<FileContent, String> {
+    crate::require_app_window(&window)?;
+    let options = options.unwrap_or_default();
+    let limit = options
+        .max_bytes
+        .unwrap_or(DEFAULT_READ_BYTES)
+        .min(MAX_READ_BYTES);
+    if let Some(remote) = sandbox.remote(&root)? {
+        let app = window.app_handle().clone();
+        let (stat, bytes) = tauri::async_runtime::spawn_blocking(move || {
+            crate::remote_fs::read(&app, &remote, &path, limit)
+        })
+        .await
+        .map_err(|e| e.to_string())??;
+        return Ok(file_content(stat, bytes));
+    }
+    read_local(&sandbox.resolve(&root, &path)?, limit)
+}
+
+/// `read_file` of a local path that has passed the sandbox: at most `limit`
+/// bytes, decoded.
+pub(crate) fn read_local(resolved: &Path, limit: u64) -> Result<FileContent, String> {
+    let meta = std::fs::metadata(resolved).map_err(|e| e.to_string())?;
+    if !meta.is_file() {
+        return Err(format!("{} is not a file", resolved.display()));
+    }
+
+    let mut bytes = Vec::new();
+    std::fs::File::open(resolved)
+        .and_then(|f| f.take(limit).read_to_end(&mut bytes))
+        .map_err(|e| e.to_string())?;
+    let stat = FileStat {
+        path: resolved.to_string_lossy().to_string(),
+        kind: kind_of(&meta).to_string(),
+        size: meta.len(),
+        modified_ms: modified_ms(&meta),
+        readonly: meta.permissions().readonly(),
+    };
+    Ok(file_content(stat, bytes))
+}
+
+/// Decode what was read of a file, unless it looks binary.
+fn file_content(stat: FileStat, bytes: Vec<u8>) -> FileContent {
+    let truncated = stat.size > bytes.len() as u64;
+    let utf16 = bytes.starts_with(UTF16LE_BOM) || bytes.starts_with(UTF16BE_BOM);
+    let (content, encoding, bom, binary) = if !utf16 && looks_binary(&bytes) {
+        (String::new(), "binary", false, true)
+    } else {
+        let (text, encoding, bom) = decode(&bytes);
+        (text, encoding, bom, false)
+    };
+
+    FileContent {
+        path: stat.path,
+        size: stat.size,
+        modified_ms: stat.modified_ms,
+        binary,
+        truncated,
+        encoding: encoding.to_string(),
+        bom,
+        content,
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WriteFileOptions {
+    /// Encoding to write with; defaults to `utf-8`. Pass back what
+    /// `read_file` returned to round-trip a file unchanged.
+    pub encoding: Option<String>,
+    pub bom: Option<bool>,
+    /// Create missing parent directories.
+    pub create_dirs: Option<bool>,
+    /// Refuse to overwrite if the file changed on disk since this mtime
+    /// (as returned by `read_file` / `stat`), e.g. because an agent edited it.
+    pub expected_modified_ms: Option<i64>,
+    /// The agent run this is done for, recorded in the audit log; unset for
+    /// the user's own edits.
+    pub agent: Option<String>,
+    /// Makes a write waiting for the user's approval abandonable with
+    /// `cancel(request_id)`.
+    pub request_id: Option<String>,
+}
+
+/// Makes the temp file of each write unique, so overlapping writes to one
+/// file never share it.
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Write a file atomically (temp file + rename) so a crash or a concurrent
+/// reader never sees a half-written file. An agent's write to a path the
+/// command policy's `askWrites` lists first waits for the user's approval;
+/// one from an agent in dry-run mode is captured instead (see `dry_run`).
+/// Writes take an advisory lock on the file; see `file_locks`. Agents can't
+/// write the project's `.funny/config.json`.
+#[tauri::command]
+pub async fn write_file(
+    window: WebviewWindow,
+    sandbox: State<'_, Sandbox>,
+    root: String,
+    path: String,
+    content: String,
+    options: Option<WriteFileOptions>,
+) -> Result<FileStat, String> {
+    crate::require_app_window(&window)?;
+    let options = options.unwrap_or_default();
+    if let Some(remote) = sandbox.remote(&root)? {
+        return write_remote(window, remote, path, content, options).await;
+    }
+    let app = window.app_handle().clone();
+    let guard = crate::cancel::register(&app, options.request_id.as_deref());
+    tauri::async_runtime::spawn_blocking(move || {
+        write_local(
+            &app,
+            &app.state::<Sandbox>(),
+            &root,
+            &path,
+            &content,
+            &options,
+            &guard.token(),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// `write_file` for a local `root`, with the same checks: the sandbox, plan
+/// mode and approval for `options.agent`, and the expected mtime. Blocks
+/// while approval is pending, until `cancel`.
+pub(crate) fn write_local(
+    app: &AppHandle,
+    sandbox: &Sandbox,
+    root: &str,
+    path: &str,
+    content: &str,
+    options: &WriteFileOptions,
+    cancel: &CancelToken,
+) -> Result<FileStat, String> {
+    let resolved = sandbox.resolve(root, path)?;
+    let root_dir = sandbox.check_dir(root)?;
+    let project = root_dir.to_string_lossy().to_string();
+    crate::plan_mode::check(
+        app,
+        options.agent.as_deref(),
+        Some(&project),
+        "write",
+        &resolved.to_string_lossy(),
+    )?;
+    let relative = resolved.strip_prefix(&root_dir).unwrap_or(&resolved);
+    let relative = relative.to_string_lossy();
+    let agent = options.agent.as_deref();
+    crate::project_config::check_agent_write(agent, &relative)?;
+    if let Some(session) = agent.filter(|_| crate::dry_run::active(app, agent)) {
+        encode_for_write(content, options)?;
+        let meta = std::fs::metadata(&resolved).ok();
+        return crate::dry_run::capture(
+            app,
+            session,
+            Capture {
+                root: root.to_string(),
+                path: path.to_string(),
+                project: project.clone(),
+                relative: relative.replace('\\', "/"),
+                target: resolved.to_string_lossy().to_string(),
+                original: std::fs::read_to_string(&resolved).ok(),
+                modified_ms: meta.as_ref().and_then(modified_ms),
+                content: content.to_string(),
+                encoding: options.encoding.clone(),
+                bom: options.bom,
+            },
+        );
+    }
+    crate::file_locks::claim(app, &project, &resolved.to_string_lossy(), agent)?;
+    if let Some(reason) = crate::policy::write_needs_approval(app, &project, agent, &relative) {
+        let old = std::fs::read_to_string(&resolved).ok();
+        crate::policy::authorize_write(
+            app,
+            &project,
+            agent,
+            &relative,
+            reason,
+            crate::policy::summarize_write(old.as_deref(), content),
+            cancel,
+        )?;
+    }
+
+    let bytes = encode_for_write(content, options)?;
+
+    if let Some(expected) = options.expected_modified_ms {
+        if let Ok(meta) = std::fs::metadata(&resolved) {
+            if modified_ms(&meta) != Some(expected) {
+                return Err(format!("{path} was modified on disk since it was read"));
+            }
+        }
+    }
+
+    let parent = resolved
+        .parent()
+        .ok_or_else(|| format!("invalid path {path}"))?;
+    if options.create_dirs.unwrap_or(false) {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let file_name = resolved
+        .file_name()
+        .ok_or_else(|| format!("invalid path {path}"))?
+        .to_string_lossy();
+    let temp = parent.join(format!(
+        ".{file_name}.funny-{}-{}.tmp",
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::write(&temp, &bytes).map_err(|e| e.to_string())?;
+    // Keep the original permissions (e.g. executable scripts).
+    if let Ok(meta) = std::fs::metadata(&resolved) {
+        if let Err(e) = std::fs::set_permissions(&temp, meta.permissions()) {
+            remove_temp(&temp);
+            return Err(e.to_string());
+        }
+    }
+    let renamed = std::fs::rename(&temp, &resolved);
+    audit::record(
+        app,
+        AuditEvent {
+            kind: AuditKind::FileWrite,
+            agent: options.agent.as_deref(),
+            project: Some(&project),
+            target: &resolved.to_string_lossy(),
+            outcome: if renamed.is_ok() {
+                Outcome::Ok
+            } else {
+                Outcome::Error
+            },
+            detail: Some(format!("{} bytes", bytes.len())),
+        },
+    );
+    if let Err(e) = renamed {
+        remove_temp(&temp);
+        return Err(e.to_string());
+    }
+
+    if let Ok(root) = sandbox.check_dir(root) {
+        if let Ok(rel) = resolved.strip_prefix(&root) {
+            let rel = rel.to_string_lossy().replace('\\', "/");
+            let source = match options.agent {
+                Some(_) => TouchSource::Agent,
+                None => TouchSource::User,
+            };
+            recent::record(app, &root, &[rel], source);
+        }
+    }
+    stat_path(&resolved)
+}
+
+/// Clean up the temp file of a failed write; the write's own error is what
+/// gets reported.
+fn remove_temp(temp: &Path) {
+    if let Err(e) = std::fs::remove_file(temp) {
+        tracing::warn!("could not remove {}: {e}", temp.display());
+    }
+}
+
+fn encode_for_write(content: &str, options: &WriteFileOptions) -> Result<Vec<u8>, String> {
+    let bytes = encode(
+        content,
+        options.encoding.as_deref().unwrap_or("utf-8"),
+        options.bom.unwrap_or(false),
+    )?;
+    if bytes.len() > MAX_WRITE_BYTES {
+        return Err(format!(
+            "refusing to write {} bytes (limit {MAX_WRITE_BYTES})",
+            bytes.len()
+        ));
+    }
+    Ok(bytes)
+}
+
+/// `write_file` for a project on an SSH host; the host does the temp file
+/// and rename.
+async fn write_remote(
+    window: WebviewWindow,
+    remote: RemoteRoot,
+    path: String,
+    content: String,
+    options: WriteFileOptions,
+) -> Result<FileStat, String> {
+    let app = window.app_handle().clone();
+    let guard = crate::cancel::register(&app, options.request_id.as_deref());
+    tauri::async_runtime::spawn_blocking(move || {
+        write_remote_blocking(&app, &remote, &path, &content, &options, &guard.token())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// `write_remote` off the async runtime, with the checks of `write_local`.
+pub(crate) fn write_remote_blocking(
+    app: &AppHandle,
+    remote: &RemoteRoot,
+    path: &str,
+    content: &str,
+    options: &WriteFileOptions,
+    cancel: &CancelToken,
+) -> Result<FileStat, String> {
+    let project = remote.uri(&remote.path);
+    let resolved = remote.resolve(path)?;
+    let target = remote.uri(&resolved);
+    let agent = options.agent.as_deref();
+    crate::plan_mode::check(app, agent, Some(&project), "write", &target)?;
+    let bytes = encode_for_write(content, options)?;
+    let relative = resolved
+        .strip_prefix(remote.path.as_str())
+        .unwrap_or(&resolved)
+        .trim_start_matches('/');
+    crate::project_config::check_agent_write(agent, relative)?;
+    let dry_run = agent.filter(|_| crate::dry_run::active(app, agent));
+    let reason = match dry_run {
+        Some(_) => None,
+        None => crate::policy::write_needs_approval(app, &project, agent, relative),
+    };
+    let old = if dry_run.is_some() || reason.is_some() {
+        crate::remote_fs::read(app, remote, path, MAX_READ_BYTES).ok()
+    } else {
+        None
+    };
+    let (modified_ms, old) = match old {
+        Some((stat, bytes)) => (
+            stat.modified_ms,
+            Some(String::from_utf8_lossy(&bytes).into_owned()),
+        ),
+        None => (None, None),
+    };
+    if let Some(session) = dry_run {
+        return crate::dry_run::capture(
+            app,
+            session,
+            Capture {
+                root: project.clone(),
+                path: path.to_string(),
+                project: project.clone(),
+                relative: relative.to_string(),
+                target,
+                original: old,
+                modified_ms,
+                content: content.to_string(),
+                encoding: options.encoding.clone(),
+                bom: options.bom,
+            },
+        );
+    }
+    crate::file_locks::claim(app, &project, &target, agent)?;
+    if let Some(reason) = reason {
+        crate::policy::authorize_write(
+            app,
+            &project,
+            agent,
+            relative,
+            reason,
+            crate::policy::summarize_write(old.as_deref(), content),
+            cancel,
+        )?;
+    }
+    let written = crate::remote_fs::write(
+        app,
+        remote,
+        path,
+        &bytes,
+        options.expected_modified_ms,
+        options.create_dirs.unwrap_or(false),
+    );
+    audit::record(
+        app,
+        AuditEvent {
+            kind: AuditKind::FileWrite,
+            agent,
+            project: Some(&project),
+            target: &target,
+            outcome: if written.is_ok() {
+                Outcome::Ok
+            } else {
+                Outcome::Error
+            },
+            detail: Some(format!("{} bytes", bytes.len())),
+        },
+    );
+    written
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileStat {
+    pub path: String,
+    /// `file`, `directory` or `symlink`.
+    pub kind: String,
+    pub size: u64,
+    pub modified_ms: Option<i64>,
+    pub readonly: bool,
+}
+
+fn stat_path(path: &Path) -> Result<FileStat, String> {
+    let meta = std::fs::metadata(path).map_err(|e| e.to_string())?;
+    Ok(FileStat {
+        path: path.to_string_lossy().to_string(),
+        kind: kind_of(&meta).to_string(),
+        size: meta.len(),
+        modified_ms: modified_ms(&meta),
+        readonly: meta.permissions().readonly(),
+    })
+}
+
+#[tauri::command]
+pub async fn stat(
+    window: WebviewWindow,
+    sandbox: State<'_, Sandbox>,
+    root: String,
+    path: String,
+) -> Result<FileStat, String> {
+    crate::require_app_window(&window)?;
+    if let Some(remote) = sandbox.remote(&root)? {
+        let app = window.app_handle().clone();
+        return tauri::async_runtime::spawn_blocking(move || {
+            crate::remote_fs::stat(&app, &remote, &path)
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+    }
+    stat_path(&sandbox.resolve(&root, &path)?)
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DirEntry {
+    pub name: String,
+    pub path: String,
+    pub kind: String,
+    pub size: u64,
+    pub modified_ms: Option<i64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DirListing {
+    pub path: String,
+    /// Directories first, then files, each sorted by name.
+    pub entries: Vec<DirEntry>,
+    pub truncated: bool,
+}
+
+#[tauri::command]
+pub async fn list_dir(
+    window: WebviewWindow,
+    sandbox: State<'_, Sandbox>,
+    root: String,
+    path: String,
+) -> Result<DirListing, String> {
+    crate::require_app_window(&window)?;
+    if let Some(remote) = sandbox.remote(&root)? {
+        let app = window.app_handle().clone();
+        let (path, mut entries, truncated) = tauri::async_runtime::spawn_blocking(move || {
+            crate::remote_fs::list(&app, &remote, &path, MAX_DIR_ENTRIES)
+        })
+        .await
+        .map_err(|e| e.to_string())??;
+        sort_entries(&mut entries);
+        return Ok(DirListing {
+            path,
+            entries,
+            truncated,
+        });
+    }
+    list_local(&sandbox.resolve(&root, &path)?)
+}
+
+/// `list_dir` of a local directory that has passed the sandbox.
+pub(crate) fn list_local(resolved: &Path) -> Result<DirListing, String> {
+    let reader = std::fs::read_dir(resolved).map_err(|e| e.to_string())?;
+
+    let mut entries = Vec::new();
+    let mut truncated = false;
+    for entry in reader.flatten() {
+        if entries.len() >= MAX_DIR_ENTRIES {
+            truncated = true;
+            break;
+        }
+        // `DirEntry::metadata` doesn't follow symlinks, so links show as such.
+        let meta = match entry.metadata() {
+            Ok(m) => m,
+            Err(_) => continue,
+        };
+        entries.push(DirEntry {
+            name: entry.file_name().to_string_lossy().to_string(),
+            path: entry.path().to_string_lossy().to_string(),
+            kind: kind_of(&meta).to_string(),
+            size: meta.len(),
+            modified_ms: modified_ms(&meta),
+        });
+    }
+    sort_entries(&mut entries);
+
+    Ok(DirListing {
+        path: resolved.to_string_lossy().to_string(),
+        entries,
+        truncated,
+    })
+}
+
+fn sort_entries(entries: &mut [DirEntry]) {
+    entries.sort_by(|a, b| {
+        (b.kind == "directory")
+            .cmp(&(a.kind == "directory"))
+            .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+    });
+}
+
+/// Minimum time between two `fs:progress` events for the same operation.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FsProgressPayload {
+    pub op_id: String,
+    /// `delete`, `move` or `copy`.
+    pub operation: String,
+    pub done: u64,
+    pub total: u64,
+    pub current: Option<String>,
+}
+
+/// Throttled emitter for `fs:progress`, sent only to the window that started
+/// the operation. Operations without an `op_id` run silently. The `op_id` is
+/// also the operation's request id for `cancel`.
+struct Progress {
+    window: WebviewWindow,
+    op_id: Option<String>,
+    cancel: CancelGuard,
+    operation: &'static str,
+    done: u64,
+    total: u64,
+    last_emit: Option<Instant>,
+}
+
+impl Progress {
+    fn new(
+        window: WebviewWindow,
+        op_id: Option<String>,
+        operation: &'static str,
+        total: u64,
+    ) -> Self {
+        let cancel = crate::cancel::register(window.app_handle(), op_id.as_deref());
+        Self {
+            window,
+            op_id,
+            cancel,
+            operation,
+            done: 0,
+            total,
+            last_emit: None,
+        }
+    }
+
+    fn advance(&mut self, current: &Path) {
+        self.done += 1;
+        let due = self
+            .last_emit
+            .map(|t| t.elapsed() >= PROGRESS_INTERVAL)
+            .unwrap_or(true);
+        if due || self.done == self.total {
+            self.emit(Some(current));
+        }
+    }
+
+    fn emit(&mut self, current: Option<&Path>) {
+        let op_id = match &self.op_id {
+            Some(id) => id.clone(),
+            None => return,
+        };
+        self.last_emit = Some(Instant::now());
+        let _ = self.window.emit_to(
+            EventTarget::webview_window(self.window.label()),
+            "fs:progress",
+            FsProgressPayload {
+                op_id,
+                operation: self.operation.to_string(),
+                done: self.done,
+                total: self.total,
+                current: current.map(|p| p.to_string_lossy().to_string()),
+            },
+        );
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FsOpFailure {
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FsOpResult {
+    /// Paths that were processed successfully (destinations for move/copy).
+    pub completed: Vec<String>,
+    pub failed: Vec<FsOpFailure>,
+}
+
+impl FsOpResult {
+    fn new() -> Self {
+        Self {
+            completed: Vec::new(),
+            failed: Vec::new(),
+        }
+    }
+
+    fn record(&mut self, path: &Path, result: Result<(), String>) {
+        let path = path.to_string_lossy().to_string();
+        match result {
+            Ok(()) => self.completed.push(path),
+            Err(error) => self.failed.push(FsOpFailure { path, error }),
+        }
+    }
+}
+
+/// Number of files (not directories) under `path`, for progress totals.
+fn count_files(path: &Path) -> u64 {
+    match std::fs::symlink_metadata(path) {
+        Ok(meta) if meta.is_dir() => std::fs::read_dir(path)
+            .map(|entries| entries.flatten().map(|e| count_files(&e.path())).sum())
+            .unwrap_or(0),
+        Ok(_) => 1,
+        Err(_) => 0,
+    }
+}
+
+fn copy_recursive(
+    from: &Path,
+    to: &Path,
+    progress: &mut Progress,
+    cancel: &CancelToken,
+) -> Result<(), String> {
+    cancel.check()?;
+    let meta = std::fs::symlink_metadata(from).map_err(|e| e.to_string())?;
+    if meta.is_dir() {
+        std::fs::create_dir_all(to).map_err(|e| e.to_string())?;
+        for entry in std::fs::read_dir(from).map_err(|e| e.to_string())? {
+            let entry = entry.map_err(|e| e.to_string())?;
+            copy_recursive(&entry.path(), &to.join(entry.file_name()), progress, cancel)?;
+        }
+        return Ok(());
+    }
+    #[cfg(unix)]
+    if meta.file_type().is_symlink() {
+        // Recreate links rather than copying what they point at, which may be
+        // outside the project.
+        let target = std::fs::read_link(from).map_err(|e| e.to_string())?;
+        std::os::unix::fs::symlink(target, to).map_err(|e| e.to_string())?;
+        progress.advance(from);
+        return Ok(());
+    }
+    std::fs::copy(from, to).map_err(|e| e.to_string())?;
+    progress.advance(from);
+    Ok(())
+}
+
+fn remove_path(path: &Path) -> std::io::Result<()> {
+    let meta = std::fs::symlink_metadata(path)?;
+    if meta.is_dir() {
+        std::fs::remove_dir_all(path)
+    } else {
+        std::fs::remove_file(path)
+    }
+}
+
+/// The checks `write_local` makes of a write, for a delete, move or copy.
+trait ChangeGate {
+    /// Who makes the change; `None` for the user.
+    fn agent(&self) -> Option<&str>;
+    /// The agent's writes are being captured (see `dry_run`).
+    fn dry_run(&self) -> bool;
+    fn claim(&self, target: &Path) -> Result<(), String>;
+    fn needs_approval(&self, relative: &str) -> Option<String>;
+    fn authorize(
+        &self,
+        relative: &str,
+        reason: String,
+        summary: WriteSummary,
+    ) -> Result<(), String>;
+}
+
+struct AppGate<'a> {
+    app: &'a AppHandle,
+    project: &'a str,
+    agent: Option<&'a str>,
+    cancel: &'a CancelToken,
+}
+
+impl ChangeGate for AppGate<'_> {
+    fn agent(&self) -> Option<&str> {
+        self.agent
+    }
+
+    fn dry_run(&self) -> bool {
+        crate::dry_run::active(self.app, self.agent)
+    }
+
+    fn claim(&self, target: &Path) -> Result<(), String> {
+        crate::file_locks::claim(
+            self.app,
+            self.project,
+            &target.to_string_lossy(),
+            self.agent,
+        )
+    }
+
+    fn needs_approval(&self, relative: &str) -> Option<String> {
+        crate::policy::write_needs_approval(self.app, self.project, self.agent, relative)
+    }
+
+    fn authorize(
+        &self,
+        relative: &str,
+        reason: String,
+        summary: WriteSummary,
+    ) -> Result<(), String> {
+        crate::policy::authorize_write(
+            self.app,
+            self.project,
+            self.agent,
+            relative,
+            reason,
+            summary,
+            self.cancel,
+        )
+    }
+}
+
+/// A path a delete, move or copy changes: removed, or replaced by `from`.
+struct Change<'a> {
+    target: &'a Path,
+    from: Option<&'a Path>,
+}
+
+impl Change<'_> {
+    fn summary(&self) -> WriteSummary {
+        let old = std::fs::read_to_string(self.target).ok();
+        match self.from {
+            Some(from) => crate::policy::summarize_write(
+                old.as_deref(),
+                &std::fs::read_to_string(from).unwrap_or_default(),
+            ),
+            None => crate::policy::summarize_write(Some(old.as_deref().unwrap_or_default()), ""),
+        }
+    }
+}
+
+fn delete_changes(target: &Path) -> Vec<Change<'_>> {
+    vec![Change { target, from: None }]
+}
+
+/// A move changes its source as well as its destination; a copy only the
+/// destination.
+fn transfer_changes<'a>(kind: &str, from: &'a Path, to: &'a Path) -> Vec<Change<'a>> {
+    let replaced = Change {
+        target: to,
+        from: Some(from),
+    };
+    if kind == "copy" {
+        return vec![replaced];
+    }
+    vec![
+        Change {
+            target: from,
+            from: None,
+        },
+        replaced,
+    ]
+}
+
+/// Hold `changes` to what a write to each path would need. A dry run only
+/// captures writes, so it refuses them outright; otherwise the project's
+/// config is off limits to agents, every path's lock is claimed, then
+/// approval asked where it is needed. Blocks while approval
+/// is pending.
+fn check_changes(
+    gate: &impl ChangeGate,
+    operation: &str,
+    root_dir: &Path,
+    changes: &[Change],
+) -> Result<(), String> {
+    if gate.dry_run() {
+        return Err(format!(
+            "cannot {operation} in dry-run mode; only file writes are captured"
+        ));
+    }
+    let relative = |change: &Change| {
+        change
+            .target
+            .strip_prefix(root_dir)
+            .unwrap_or(change.target)
+            .to_string_lossy()
+            .to_string()
+    };
+    for change in changes {
+        crate::project_config::check_agent_write(gate.agent(), &relative(change))?;
+    }
+    for change in changes {
+        gate.claim(change.target)?;
+    }
+    for change in changes {
+        let relative = relative(change);
+        if let Some(reason) = gate.needs_approval(&relative) {
+            gate.authorize(
+                &relative,
+                format!("{operation}: {reason}"),
+                change.summary(),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteOptions {
+    /// Move to the OS trash / recycle bin instead of deleting. Defaults to true.
+    pub to_trash: Option<bool>,
+    /// Emit `fs:progress` events tagged with this id; also the request id
+    /// for `cancel`.
+    pub op_id: Option<String>,
+    /// The agent run this is done for, recorded in the audit log; unset for
+    /// the user's own edits.
+    pub agent: Option<String>,
+}
+
+/// Delete files or directories under `root`, by default into the OS trash so
+/// the operation can be undone from Finder / Explorer. Each path succeeds or
+/// fails independently; once cancelled, the remaining ones fail as
+/// `cancelled`. Each path is checked like a write to it (locks, approval);
+/// an agent in dry-run mode cannot delete.
+#[tauri::command]
+pub async fn fs_delete(
+    window: WebviewWindow,
+    sandbox: State<'_, Sandbox>,
+    root: String,
+    paths: Vec<String>,
+    options: Option<DeleteOptions>,
+) -> Result<FsOpResult, String> {
+    crate::require_app_window(&window)?;
+    let options = options.unwrap_or_default();
+    let targets = paths
+        .iter()
+        .map(|p| sandbox.resolve_entry(&root, p))
+        .collect::<Result<Vec<_>, _>>()?;
+    let project = sandbox.check_dir(&root)?.to_string_lossy().to_string();
+    for target in &targets {
+        crate::plan_mode::check(
+            window.app_handle(),
+            options.agent.as_deref(),
+            Some(&project),
+            "delete",
+            &target.to_string_lossy(),
+        )?;
+    }
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let to_trash = options.to_trash.unwrap_or(true);
+        let mut progress = Progress::new(window, options.op_id, "delete", targets.len() as u64);
+        let cancel = progress.cancel.token();
+        let app = progress.window.app_handle().clone();
+        let gate = AppGate {
+            app: &app,
+            project: &project,
+            agent: options.agent.as_deref(),
+            cancel: &cancel,
+        };
+        let mut result = FsOpResult::new();
+        for target in &targets {
+            if let Err(e) = cancel.check() {
+                result.record(target, Err(e));
+                continue;
+            }
+            let outcome = check_changes(
+                &gate,
+                "delete",
+                Path::new(&project),
+                &delete_changes(target),
+            )
+            .and_then(|_| {
+                if to_trash {
+                    trash::delete(target).map_err(|e| e.to_string())
+                } else {
+                    remove_path(target).map_err(|e| e.to_string())
+                }
+            });
+            audit::record(
+                &app,
+                AuditEvent {
+                    kind: AuditKind::FileDelete,
+                    agent: options.agent.as_deref(),
+                    project: Some(&project),
+                    target: &target.to_string_lossy(),
+                    outcome: if outcome.is_ok() {
+                        Outcome::Ok
+                    } else {
+                        Outcome::Error
+                    },
+                    detail: Some(if to_trash { "to trash" } else { "permanently" }.to_string()),
+                },
+            );
+            result.record(target, outcome);
+            progress.advance(target);
+        }
+        result
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FsTransfer {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferOptions {
+    /// Replace existing destinations (they are moved to the trash first).
+    pub overwrite: Option<bool>,
+    /// Emit `fs:progress` events tagged with this id; also the request id
+    /// for `cancel`.
+    pub op_id: Option<String>,
+    /// The agent run this is done for, recorded in the audit log; unset for
+    /// the user's own edits.
+    pub agent: Option<String>,
+}
+
+fn resolve_transfers(
+    sandbox: &Sandbox,
+    root: &str,
+    transfers: &[FsTransfer],
+) -> Result<Vec<(PathBuf, PathBuf)>, String> {
+    transfers
+        .iter()
+        .map(|t| {
+            let from = sandbox.resolve_entry(root, &t.from)?;
+            let to = sandbox.resolve_entry(root, &t.to)?;
+            if to.starts_with(&from) {
+                return Err(format!("cannot move or copy {} into itself", t.from));
+            }
+            Ok((from, to))
+        })
+        .collect()
+}
+
+/// Make room at `to`: fail if it exists, unless overwriting is allowed, in
+/// which case the old destination goes to the trash.
+fn clear_destination(to: &Path, overwrite: bool) -> Result<(), String> {
+    if std::fs::symlink_metadata(to).is_err() {
+        return Ok(());
+    }
+    if !overwrite {
+        return Err(format!("{} already exists", to.display()));
+    }
+    trash::delete(to).map_err(|e| e.to_string())
+}
+
+fn transfer(
+    kind: &'static str,
+    window: WebviewWindow,
+    project: &str,
+    pairs: Vec<(PathBuf, PathBuf)>,
+    options: TransferOptions,
+) -> FsOpResult {
+    let overwrite = options.overwrite.unwrap_or(false);
+    let total = if kind == "copy" {
+        pairs.iter().map(|(from, _)| count_files(from)).sum()
+    } else {
+        pairs.len() as u64
+    };
+    let mut progress = Progress::new(window, options.op_id, kind, total);
+    let cancel = progress.cancel.token();
+    let app = progress.window.app_handle().clone();
+    let gate = AppGate {
+        app: &app,
+        project,
+        agent: options.agent.as_deref(),
+        cancel: &cancel,
+    };
+    let mut result = FsOpResult::new();
+    for (from, to) in &pairs {
+        if let Err(e) = cancel.check() {
+            result.record(to, Err(e));
+            continue;
+        }
+        let changes = transfer_changes(kind, from, to);
+        let outcome = check_changes(&gate, kind, Path::new(project), &changes)
+            .and_then(|_| clear_destination(to, overwrite))
+            .and_then(|_| {
+                if kind == "copy" {
+                    return copy_recursive(from, to, &mut progress, &cancel);
+                }
+                if std::fs::rename(from, to).is_ok() {
+                    progress.advance(from);
+                    return Ok(());
+                }
+                // Most likely a cross-device move: copy, then remove the source.
+                let mut quiet = Progress::new(progress.window.clone(), None, kind, 0);
+                copy_recursive(from, to, &mut quiet, &cancel)?;
+                remove_path(from).map_err(|e| e.to_string())?;
+                progress.advance(from);
+                Ok(())
+            });
+        audit::record(
+            &app,
+            AuditEvent {
+                kind: if kind == "copy" {
+                    AuditKind::FileCopy
+                } else {
+                    AuditKind::FileMove
+                },
+                agent: options.agent.as_deref(),
+                project: Some(project),
+                target: &to.to_string_lossy(),
+                outcome: if outcome.is_ok() {
+                    Outcome::Ok
+                } else {
+                    Outcome::Error
+                },
+                detail: Some(format!("from {}", from.display())),
+            },
+        );
+        result.record(to, outcome);
+    }
+    result
+}
+
+/// Move or rename entries within `root`. Each pair fails independently, and
+/// is checked like writes to both its paths; an agent in dry-run mode cannot
+/// move.
+#[tauri::command]
+pub async fn fs_move(
+    window: WebviewWindow,
+    sandbox: State<'_, Sandbox>,
+    root: String,
+    transfers: Vec<FsTransfer>,
+    options: Option<TransferOptions>,
+) -> Result<FsOpResult, String> {
+    crate::require_app_window(&window)?;
+    let pairs = resolve_transfers(&sandbox, &root, &transfers)?;
+    let project = sandbox.check_dir(&root)?.to_string_lossy().to_string();
+    let options = options.unwrap_or_default();
+    for (_, to) in &pairs {
+        crate::plan_mode::check(
+            window.app_handle(),
+            options.agent.as_deref(),
+            Some(&project),
+            "move",
+            &to.to_string_lossy(),
+        )?;
+    }
+    tauri::async_runtime::spawn_blocking(move || transfer("move", window, &project, pairs, options))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Copy files or directory trees within `root`. Symlinks are copied as links.
+/// A copy stopped with `cancel` leaves behind what it had copied so far.
+/// Destinations are checked like writes; an agent in dry-run mode cannot
+/// copy.
+#[tauri::command]
+pub async fn fs_copy(
+    window: WebviewWindow,
+    sandbox: State<'_, Sandbox>,
+    root: String,
+    transfers: Vec<FsTransfer>,
+    options: Option<TransferOptions>,
+) -> Result<FsOpResult, String> {
+    crate::require_app_window(&window)?;
+    let pairs = resolve_transfers(&sandbox, &root, &transfers)?;
+    let project = sandbox.check_dir(&root)?.to_string_lossy().to_string();
+    let options = options.unwrap_or_default();
+    for (_, to) in &pairs {
+        crate::plan_mode::check(
+            window.app_handle(),
+            options.agent.as_deref(),
+            Some(&project),
+            "copy",
+            &to.to_string_lossy(),
+        )?;
+    }
+    tauri::async_runtime::spawn_blocking(move || transfer("copy", window, &project, pairs, options))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// Answers as configured and records what it was asked.
+    #[derive(Default)]
+    struct FakeGate {
+        dry_run: bool,
+        locked: Vec<&'static str>,
+        ask: Vec<&'static str>,
+        deny: bool,
+        claimed: RefCell<Vec<String>>,
+        asked: RefCell<Vec<String>>,
+    }
+
+    impl ChangeGate for FakeGate {
+        fn agent(&self) -> Option<&str> {
+            Some("run-1")
+        }
+
+        fn dry_run(&self) -> bool {
+            self.dry_run
+        }
+
+        fn claim(&self, target: &Path) -> Result<(), String> {
+            let target = target.to_string_lossy().to_string();
+            self.claimed.borrow_mut().push(target.clone());
+            match self.locked.iter().any(|locked| target.ends_with(locked)) {
+                true => Err(format!("{target} is being modified")),
+                false => Ok(()),
+            }
+        }
+
+        fn needs_approval(&self, relative: &str) -> Option<String> {
+            self.ask.contains(&relative).then(|| "asked".to_string())
+        }
+
+        fn authorize(
+            &self,
+            relative: &str,
+            reason: String,
+            _summary: WriteSummary,
+        ) -> Result<(), String> {
+            self.asked
+                .borrow_mut()
+                .push(format!("{relative} ({reason})"));
+            match self.deny {
+                true => Err("write not approved".to_string()),
+                false => Ok(()),
+            }
+        }
+    }
+
+    fn root() -> PathBuf {
+        PathBuf::from("/project")
+    }
+
+    #[test]
+    fn delete_is_refused_in_dry_run() {
+        let gate = FakeGate {
+            dry_run: true,
+            ..Default::default()
+        };
+        let target = root().join("a.txt");
+        let result = check_changes(&gate, "delete", &root(), &delete_changes(&target));
+        assert_eq!(
+            result,
+            Err("cannot delete in dry-run mode; only file writes are captured".to_string())
+        );
+        assert!(gate.claimed.borrow().is_empty());
+    }
+
+    #[test]
+    fn delete_claims_the_lock_and_asks_for_approval() {
+        let gate = FakeGate {
+            ask: vec!["a.txt"],
+            ..Default::default()
+        };
+        let target = root().join("a.txt");
+        check_changes(&gate, "delete", &root(), &delete_changes(&target)).unwrap();
+        assert_eq!(*gate.claimed.borrow(), ["/project/a.txt"]);
+        assert_eq!(*gate.asked.borrow(), ["a.txt (delete: asked)"]);
+    }
+
+    #[test]
+    fn delete_of_a_locked_file_is_refused() {
+        let gate = FakeGate {
+            locked: vec!["a.txt"],
+            ask: vec!["a.txt"],
+            ..Default::default()
+        };
+        let target = root().join("a.txt");
+        assert!(check_changes(&gate, "delete", &root(), &delete_changes(&target)).is_err());
+        assert!(gate.asked.borrow().is_empty());
+    }
+
+    #[test]
+    fn delete_of_the_project_config_is_refused() {
+        let gate = FakeGate::default();
+        let target = root().join(".funny");
+        assert!(check_changes(&gate, "delete", &root(), &delete_changes(&target)).is_err());
+        assert!(gate.claimed.borrow().is_empty());
+    }
+
+    #[test]
+    fn move_is_refused_in_dry_run() {
+        let gate = FakeGate {
+            dry_run: true,
+            ..Default::default()
+        };
+        let (from, to) = (root().join("a.txt"), root().join("b.txt"));
+        let changes = transfer_changes("move", &from, &to);
+        assert!(check_changes(&gate, "move", &root(), &changes).is_err());
+        assert!(gate.claimed.borrow().is_empty());
+    }
+
+    #[test]
+    fn move_checks_source_and_destination() {
+        let gate = FakeGate {
+            ask: vec!["a.txt", "b.txt"],
+            ..Default::default()
+        };
+        let (from, to) = (root().join("a.txt"), root().join("b.txt"));
+        let changes = transfer_changes("move", &from, &to);
+        check_changes(&gate, "move", &root(), &changes).unwrap();
+        assert_eq!(*gate.claimed.borrow(), ["/project/a.txt", "/project/b.txt"]);
+        assert_eq!(
+            *gate.asked.borrow(),
+            ["a.txt (move: asked)", "b.txt (move: asked)"]
+        );
+    }
+
+    #[test]
+    fn move_onto_a_locked_destination_is_refused() {
+        let gate = FakeGate {
+            locked: vec!["b.txt"],
+            ..Default::default()
+        };
+        let (from, to) = (root().join("a.txt"), root().join("b.txt"));
+        let changes = transfer_changes("move", &from, &to);
+        assert!(check_changes(&gate, "move", &root(), &changes).is_err());
+    }
+
+    #[test]
+    fn move_over_the_project_config_is_refused() {
+        let gate = FakeGate::default();
+        let (from, to) = (root().join("a.json"), root().join(".funny/config.json"));
+        let changes = transfer_changes("move", &from, &to);
+        assert!(check_changes(&gate, "move", &root(), &changes).is_err());
+    }
+
+    #[test]
+    fn copy_is_refused_in_dry_run() {
+        let gate = FakeGate {
+            dry_run: true,
+            ..Default::default()
+        };
+        let (from, to) = (root().join("a.txt"), root().join("b.txt"));
+        let changes = transfer_changes("copy", &from, &to);
+        assert!(check_changes(&gate, "copy", &root(), &changes).is_err());
+        assert!(gate.claimed.borrow().is_empty());
+    }
+
+    #[test]
+    fn copy_checks_only_the_destination() {
+        let gate = FakeGate {
+            ask: vec!["a.txt", "b.txt"],
+            ..Default::default()
+        };
+        let (from, to) = (root().join("a.txt"), root().join("b.txt"));
+        let changes = transfer_changes("copy", &from, &to);
+        check_changes(&gate, "copy", &root(), &changes).unwrap();
+        assert_eq!(*gate.claimed.borrow(), ["/project/b.txt"]);
+        assert_eq!(*gate.asked.borrow(), ["b.txt (copy: asked)"]);
+    }
+
+    #[test]
+    fn copy_that_is_not_approved_is_refused() {
+        let gate = FakeGate {
+            ask: vec!["b.txt"],
+            deny: true,
+            ..Default::default()
+        };
+        let (from, to) = (root().join("a.txt"), root().join("b.txt"));
+        let changes = transfer_changes("copy", &from, &to);
+        assert_eq!(
+            check_changes(&gate, "copy", &root(), &changes),
+            Err("write not approved".to_string())
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use nucleo_matcher::pattern::{CaseMatching, Normalization, Pattern};
+use nucleo_matcher::{Config, Matcher, Utf32Str};
+use serde::Serialize;
+use std::path::Path;
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+use crate::file_index::{ensure_index, FileIndex};
+
+/// Default number of matches returned by `fuzzy_find_files`.
+const DEFAULT_LIMIT: usize = 50;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FuzzyMatch {
+    pub path: String,
+    pub score: u32,
+    /// Matched character positions in `path`, in UTF-16 code units, for
+    /// highlighting.
+    pub indices: Vec<u32>,
+}
+
+/// Map char positions from the matcher to UTF-16 offsets into `path`.
+fn utf16_indices(path: &str, char_indices: &mut [u32]) -> Vec<u32> {
+    char_indices.sort_unstable();
+    let mut out = Vec::with_capacity(char_indices.len());
+    let mut wanted = char_indices.iter().peekable();
+    let mut offset = 0u32;
+    for (i, c) in path.chars().enumerate() {
+        if wanted.peek().map(|&&w| w as usize == i).unwrap_or(false) {
+            out.push(offset);
+            wanted.next();
+        }
+        offset += c.len_utf16() as u32;
+    }
+    out
+}
+
+fn rank(paths: impl Iterator<Item = String>, query: &str, limit: usize) -> Vec<FuzzyMatch> {
+    if query.trim().is_empty() {
+        // No query yet: shallow, short paths first, like the quick-open
+        // dropdown before the user types.
+        let mut all: Vec<String> = paths.collect();
+        all.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+        return all
+            .into_iter()
+            .take(limit)
+            .map(|path| FuzzyMatch {
+                path,
+                score: 0,
+                indices: Vec::new(),
+            })
+            .collect();
+    }
+
+    let mut matcher = Matcher::new(Config::DEFAULT.match_paths());
+    let pattern = Pattern::parse(query, CaseMatching::Smart, Normalization::Smart);
+    let mut buf = Vec::new();
+    let mut scored: Vec<(u32, String)> = paths
+        .filter_map(|path| {
+            let score = pattern.score(Utf32Str::new(&path, &mut buf), &mut matcher)?;
+            Some((score, path))
+        })
+        .collect();
+    scored.sort_by(|a, b| {
+        b.0.cmp(&a.0)
+            .then_with(|| a.1.len().cmp(&b.1.len()))
+            .then_with(|| a.1.cmp(&b.1))
+    });
+    scored.truncate(limit);
+
+    // Indices are only computed for the few paths actually returned.
+    scored
+        .into_iter()
+        .map(|(score, path)| {
+            let mut indices = Vec::new();
+            pattern.indices(Utf32Str::new(&path, &mut buf), &mut matcher, &mut indices);
+            let indices = utf16_indices(&path, &mut indices);
+            FuzzyMatch {
+                path,
+                score,
+                indices,
+            }
+        })
+        .collect()
+}
+
+/// fzf-style fuzzy match of `query` against every indexed file under `root`,
+/// best matches first. Builds the file index on first use.
+#[tauri::command]
+pub async fn fuzzy_find_files(
+    window: WebviewWindow,
+    app: AppHandle,
+    root: String,
+    query: String,
+    limit: Option<u32>,
+) -> Result<Vec<FuzzyMatch>, String> {
+    crate::require_app_window(&window)?;
+    let info = ensure_index(&app, &root, false).await?;
+    let limit = limit.map(|l| l as usize).unwrap_or(DEFAULT_LIMIT);
+    let index = app.state::<FileIndex>();
+    let matches = index.with_tree(Path::new(&info.root), |tree| {
+        rank(tree.files(), &query, limit)
+    })?;
+    Ok(matches.unwrap_or_default())
+}
@@ -0,0 +1,450 @@
+use rusqlite::{params, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+use crate::sandbox::Sandbox;
+use crate::timeline::{self, TimelineEvent, TimelineKind};
+
+const DEFAULT_LIMIT: u32 = 100;
+const MAX_LIMIT: u32 = 1000;
+
+/// An agent run as the frontend reports it. Runs are driven by the server;
+/// the app keeps their history so it survives restarts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentRun {
+    pub id: String,
+    pub project: Option<String>,
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub title: Option<String>,
+    /// `running`, `completed`, `failed`, … as the server names them.
+    pub status: String,
+    /// RFC 3339.
+    pub started_at: String,
+    pub finished_at: Option<String>,
+    /// What the provider charged, if it says. Kept when an update leaves
+    /// it out, like `diff`.
+    #[serde(default)]
+    pub cost_usd: Option<f64>,
+    /// What the run changed, once it is known.
+    #[serde(default)]
+    pub diff: Option<DiffStats>,
+    /// Tokens the run used, if the provider says. Kept like `cost_usd`.
+    #[serde(default)]
+    pub tokens: Option<TokenUsage>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenUsage {
+    pub input: u64,
+    pub output: u64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffStats {
+    pub files_changed: u32,
+    pub additions: u32,
+    pub deletions: u32,
+}
+
+/// `agent_runs` columns in the order `AgentRun::from_row` reads them.
+const RUN_COLUMNS: &str = "id, project, provider, model, title, status, started_at, finished_at,
+     cost_usd, files_changed, additions, deletions, input_tokens, output_tokens";
+
+impl AgentRun {
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        let diff = match (row.get(9)?, row.get(10)?, row.get(11)?) {
+            (Some(files_changed), Some(additions), Some(deletions)) => Some(DiffStats {
+                files_changed,
+                additions,
+                deletions,
+            }),
+            _ => None,
+        };
+        let tokens = match (row.get(12)?, row.get(13)?) {
+            (Some(input), Some(output)) => Some(TokenUsage { input, output }),
+            _ => None,
+        };
+        Ok(Self {
+            id: row.get(0)?,
+            project: row.get(1)?,
+            provider: row.get(2)?,
+            model: row.get(3)?,
+            title: row.get(4)?,
+            status: row.get(5)?,
+            started_at: row.get(6)?,
+            finished_at: row.get(7)?,
+            cost_usd: row.get(8)?,
+            diff,
+            tokens,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranscriptMessage {
+    pub role: String,
+    pub content: String,
+    /// Set when stored; ignored on append.
+    #[serde(default)]
+    pub timestamp: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TerminalRecord {
+    pub id: String,
+    pub window: String,
+    pub cwd: String,
+    pub program: String,
+    pub started_at: String,
+    /// `None` while open, or if the app quit with it open.
+    pub exited_at: Option<String>,
+}
+
+fn now() -> String {
+    chrono::Utc::now().to_rfc3339()
+}
+
+/// Note a terminal opening. Terminal ids can be reused, so an earlier row
+/// with the same id is replaced.
+pub(crate) fn terminal_started(app: &AppHandle, id: &str, window: &str, cwd: &Path, program: &str) {
+    let result = crate::db::with(app, |conn| {
+        conn.execute(
+            "INSERT OR REPLACE INTO terminals (id, window, cwd, program, started_at, exited_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, NULL)",
+            params![id, window, cwd.to_string_lossy(), program, now()],
+        )
+    });
+    if let Err(e) = result {
+        tracing::debug!("terminal {id} not recorded: {e}");
+    }
+    let project = app
+        .state::<Sandbox>()
+        .root_of(cwd)
+        .map(|root| root.to_string_lossy().to_string());
+    timeline::record(
+        app,
+        TimelineEvent {
+            kind: TimelineKind::TerminalSpawned,
+            project: project.as_deref(),
+            subject: id,
+            title: program,
+            detail: Some(cwd.to_string_lossy().to_string()),
+        },
+    );
+}
+
+pub(crate) fn terminal_exited(app: &AppHandle, id: &str) {
+    let result = crate::db::with(app, |conn| {
+        conn.execute(
+            "UPDATE terminals SET exited_at = ?2 WHERE id = ?1 AND exited_at IS NULL",
+            params![id, now()],
+        )
+    });
+    if let Err(e) = result {
+        tracing::debug!("terminal {id} exit not recorded: {e}");
+    }
+}
+
+/// Recently opened terminals, newest first, e.g. to reopen them after a
+/// restart.
+#[tauri::command]
+pub async fn history_terminals(
+    window: WebviewWindow,
+    app: AppHandle,
+    limit: Option<u32>,
+) -> Result<Vec<TerminalRecord>, String> {
+    crate::require_app_window(&window)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+    tauri::async_runtime::spawn_blocking(move || {
+        crate::db::with(&app, |conn| {
+            let mut statement = conn.prepare(
+                "SELECT id, window, cwd, program, started_at, exited_at FROM terminals
+                 ORDER BY started_at DESC LIMIT ?1",
+            )?;
+            let rows = statement.query_map(params![limit], |row| {
+                Ok(TerminalRecord {
+                    id: row.get(0)?,
+                    window: row.get(1)?,
+                    cwd: row.get(2)?,
+                    program: row.get(3)?,
+                    started_at: row.get(4)?,
+                    exited_at: row.get(5)?,
+                })
+            })?;
+            rows.collect()
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Create or update a run, e.g. when it starts and when its status changes.
+#[tauri::command]
+pub async fn history_run_save(
+    window: WebviewWindow,
+    app: AppHandle,
+    run: AgentRun,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let (started, finished) = crate::db::with(&app, |conn| {
+            let was_finished: Option<Option<String>> = conn
+                .query_row(
+                    "SELECT finished_at FROM agent_runs WHERE id = ?1",
+                    [&run.id],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            conn.execute(
+                "INSERT INTO agent_runs
+                     (id, project, provider, model, title, status, started_at, finished_at,
+                      cost_usd, files_changed, additions, deletions, input_tokens, output_tokens)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
+                 ON CONFLICT (id) DO UPDATE SET
+                     project = excluded.project, provider = excluded.provider,
+                     model = excluded.model, title = excluded.title,
+                     status = excluded.status, finished_at = excluded.finished_at,
+                     cost_usd = COALESCE(excluded.cost_usd, cost_usd),
+                     files_changed = COALESCE(excluded.files_changed, files_changed),
+                     additions = COALESCE(excluded.additions, additions),
+                     deletions = COALESCE(excluded.deletions, deletions),
+                     input_tokens = COALESCE(excluded.input_tokens, input_tokens),
+                     output_tokens = COALESCE(excluded.output_tokens, output_tokens)",
+                params![
+                    run.id,
+                    run.project,
+                    run.provider,
+                    run.model,
+                    run.title,
+                    run.status,
+                    run.started_at,
+                    run.finished_at,
+                    run.cost_usd,
+                    run.diff.map(|d| d.files_changed),
+                    run.diff.map(|d| d.additions),
+                    run.diff.map(|d| d.deletions),
+                    run.tokens.map(|t| t.input),
+                    run.tokens.map(|t| t.output),
+                ],
+            )?;
+            let started = was_finished.is_none();
+            Ok((
+                started,
+                run.finished_at.is_some() && was_finished.flatten().is_none(),
+            ))
+        })?;
+        let title = run.title.as_deref().unwrap_or("Agent run");
+        let event = |kind| {
+            timeline::record(
+                &app,
+                TimelineEvent {
+                    kind,
+                    project: run.project.as_deref(),
+                    subject: &run.id,
+                    title,
+                    detail: Some(run.status.clone()),
+                },
+            )
+        };
+        if started {
+            event(TimelineKind::AgentStarted);
+            if let Some(project) = &run.project {
+                crate::run_env::capture(&app, &run.id, PathBuf::from(project));
+            }
+        }
+        if finished {
+            event(TimelineKind::AgentFinished);
+            crate::plugins::emit(&app, "agent.finished", serde_json::json!(run));
+        }
+        Ok(())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunFilter {
+    pub project: Option<String>,
+    pub status: Option<String>,
+    pub limit: Option<u32>,
+}
+
+/// Run `id`, if it is in the history.
+pub(crate) fn run(app: &AppHandle, id: &str) -> Result<Option<AgentRun>, String> {
+    crate::db::with(app, |conn| {
+        conn.query_row(
+            &format!("SELECT {RUN_COLUMNS} FROM agent_runs WHERE id = ?1"),
+            params![id],
+            AgentRun::from_row,
+        )
+        .optional()
+    })
+}
+
+/// Runs matching `filter`, newest first.
+pub(crate) fn runs(app: &AppHandle, filter: &RunFilter) -> Result<Vec<AgentRun>, String> {
+    let limit = filter.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+    crate::db::with(app, |conn| {
+        let mut statement = conn.prepare(&format!(
+            "SELECT {RUN_COLUMNS} FROM agent_runs
+             WHERE (?1 IS NULL OR project = ?1) AND (?2 IS NULL OR status = ?2)
+             ORDER BY started_at DESC LIMIT ?3"
+        ))?;
+        let rows = statement.query_map(
+            params![filter.project, filter.status, limit],
+            AgentRun::from_row,
+        )?;
+        rows.collect()
+    })
+}
+
+/// Runs that finished after `since` and no later than `until` (RFC 3339),
+/// in the order they finished.
+pub(crate) fn finished_between(
+    app: &AppHandle,
+    since: &str,
+    until: &str,
+) -> Result<Vec<AgentRun>, String> {
+    crate::db::with(app, |conn| {
+        // Compared as dates: the frontend's timestamps may not use our
+        // offset or precision.
+        let mut statement = conn.prepare(&format!(
+            "SELECT {RUN_COLUMNS} FROM agent_runs
+             WHERE julianday(finished_at) > julianday(?1)
+               AND julianday(finished_at) <= julianday(?2)
+             ORDER BY julianday(finished_at)"
+        ))?;
+        let rows = statement.query_map(params![since, until], AgentRun::from_row)?;
+        rows.collect()
+    })
+}
+
+/// Runs of `project` (any when `None`) that started after `since` and no
+/// later than `until`, in the order they started.
+pub(crate) fn started_between(
+    app: &AppHandle,
+    project: Option<&str>,
+    since: &str,
+    until: &str,
+) -> Result<Vec<AgentRun>, String> {
+    crate::db::with(app, |conn| {
+        let mut statement = conn.prepare(&format!(
+            "SELECT {RUN_COLUMNS} FROM agent_runs
+             WHERE (?1 IS NULL OR project = ?1)
+               AND julianday(started_at) > julianday(?2)
+               AND julianday(started_at) <= julianday(?3)
+             ORDER BY julianday(started_at)"
+        ))?;
+        let rows = statement.query_map(params![project, since, until], AgentRun::from_row)?;
+        rows.collect()
+    })
+}
+
+/// Past and current runs, newest first.
+#[tauri::command]
+pub async fn history_runs(
+    window: WebviewWindow,
+    app: AppHandle,
+    filter: Option<RunFilter>,
+) -> Result<Vec<AgentRun>, String> {
+    crate::require_app_window(&window)?;
+    let filter = filter.unwrap_or_default();
+    tauri::async_runtime::spawn_blocking(move || runs(&app, &filter))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Add messages to the end of a run's transcript. Stored secrets and tokens
+/// are masked before anything is written.
+#[tauri::command]
+pub async fn history_transcript_append(
+    window: WebviewWindow,
+    app: AppHandle,
+    run_id: String,
+    messages: Vec<TranscriptMessage>,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        crate::db::with(&app, |conn| {
+            let tx = conn.transaction()?;
+            let last: Option<i64> = tx
+                .query_row(
+                    "SELECT MAX(seq) FROM transcript_entries WHERE run_id = ?1",
+                    params![run_id],
+                    |row| row.get(0),
+                )
+                .optional()?
+                .flatten();
+            let timestamp = now();
+            for (i, message) in messages.iter().enumerate() {
+                tx.execute(
+                    "INSERT INTO transcript_entries (run_id, seq, role, content, timestamp)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        run_id,
+                        last.unwrap_or(0) + 1 + i as i64,
+                        message.role,
+                        crate::redact::redact_tokens(&message.content),
+                        timestamp,
+                    ],
+                )?;
+            }
+            tx.commit()
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn history_transcript(
+    window: WebviewWindow,
+    app: AppHandle,
+    run_id: String,
+) -> Result<Vec<TranscriptMessage>, String> {
+    crate::require_app_window(&window)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        crate::db::with(&app, |conn| {
+            let mut statement = conn.prepare(
+                "SELECT role, content, timestamp FROM transcript_entries
+                 WHERE run_id = ?1 ORDER BY seq",
+            )?;
+            let rows = statement.query_map(params![run_id], |row| {
+                Ok(TranscriptMessage {
+                    role: row.get(0)?,
+                    content: row.get(1)?,
+                    timestamp: row.get(2)?,
+                })
+            })?;
+            rows.collect()
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Forget a run and its transcript.
+#[tauri::command]
+pub async fn history_run_delete(
+    window: WebviewWindow,
+    app: AppHandle,
+    run_id: String,
+) -> Result<(), String> {
+    crate::require_window(&window, "main")?;
+    tauri::async_runtime::spawn_blocking(move || {
+        crate::db::with(&app, |conn| {
+            conn.execute("DELETE FROM agent_runs WHERE id = ?1", params![run_id])
+        })
+        .map(|_| ())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,92 @@
+use serde::Serialize;
+use std::path::Path;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State, WebviewWindow};
+
+/// Something the app was asked to open from outside: a project path passed on
+/// the command line (`funny /path/to/project`) or a `funny://open` deep link.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenRequest {
+    /// Absolute project directory.
+    pub project: Option<String>,
+    pub task: Option<String>,
+}
+
+/// Open requests the frontend hasn't picked up yet. The one from the first
+/// launch arrives before the frontend listens for events, so requests are
+/// queued and the `app:open-requested` event only says there is something to
+/// take.
+pub struct OpenQueue {
+    pending: Mutex<Vec<OpenRequest>>,
+}
+
+impl OpenQueue {
+    pub fn new() -> Self {
+        Self {
+            pending: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+/// The first positional argument, resolved against the launching shell's
+/// `cwd`, if it names a directory. Flags (`--foo`) are ignored.
+pub(crate) fn parse_args(argv: &[String], cwd: &Path) -> Option<OpenRequest> {
+    let arg = argv.iter().skip(1).find(|a| !a.starts_with('-'))?;
+    let path = std::fs::canonicalize(cwd.join(arg)).ok()?;
+    if !path.is_dir() {
+        return None;
+    }
+    Some(OpenRequest {
+        project: Some(path.to_string_lossy().to_string()),
+        task: None,
+    })
+}
+
+/// Queue `request` (if any), bring the main window to front and tell the
+/// frontend to take pending requests.
+pub(crate) fn dispatch(app: &AppHandle, request: Option<OpenRequest>) {
+    if let Some(request) = request {
+        if let Some(project) = &request.project {
+            crate::app_menu::project_opened(app, project);
+        }
+        if let Ok(mut pending) = app.state::<OpenQueue>().pending.lock() {
+            pending.push(request);
+        }
+    }
+    if let Some(main) = app.get_webview_window("main") {
+        let _ = main.unminimize();
+        let _ = main.show();
+        let _ = main.set_focus();
+    }
+    let _ = app.emit_to("main", "app:open-requested", ());
+}
+
+/// Called by the single-instance plugin in the running instance when the app
+/// is launched again; the second process exits without starting a sidecar.
+pub fn on_second_instance(app: &AppHandle, argv: Vec<String>, cwd: String) {
+    dispatch(app, parse_args(&argv, Path::new(&cwd)));
+}
+
+/// Queue the first launch's own arguments. Called once from `setup`.
+pub fn queue_launch_args(app: &AppHandle) {
+    let argv: Vec<String> = std::env::args().collect();
+    let cwd = std::env::current_dir().unwrap_or_default();
+    if let Some(request) = parse_args(&argv, &cwd) {
+        if let Ok(mut pending) = app.state::<OpenQueue>().pending.lock() {
+            pending.push(request);
+        }
+    }
+}
+
+/// Drain pending open requests. The frontend calls this on startup and on
+/// every `app:open-requested` event.
+#[tauri::command]
+pub fn take_open_requests(
+    window: WebviewWindow,
+    state: State<'_, OpenQueue>,
+) -> Result<Vec<OpenRequest>, String> {
+    crate::require_window(&window, "main")?;
+    let mut pending = state.pending.lock().map_err(|e| e.to_string())?;
+    Ok(std::mem::take(&mut *pending))
+}
@@ -0,0 +1,576 @@
+use base64::Engine;
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State, WebviewWindow};
+
+use crate::issues::IssueProvider;
+use crate::sandbox::Sandbox;
+
+const TOKEN_SECRET: &str = "intake-token";
+const MAX_BODY_BYTES: usize = 64 * 1024;
+/// New tasks are refused while this many are still queued.
+const MAX_QUEUED: i64 = 100;
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(200);
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The local endpoint external tools post tasks to. Off unless switched on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct IntakeSettings {
+    pub enabled: bool,
+    /// Listened on at 127.0.0.1 only.
+    pub port: u16,
+}
+
+impl Default for IntakeSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 7431,
+        }
+    }
+}
+
+pub(crate) fn validate_settings(settings: &IntakeSettings) -> Result<(), String> {
+    if settings.port < 1024 {
+        return Err("the intake port must be 1024 or above".to_string());
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IssueRef {
+    pub provider: IssueProvider,
+    pub id: String,
+}
+
+/// What a client posts to `/tasks`: a project and exactly one of a prompt,
+/// a template to fill with `vars`, or an issue.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntakeRequest {
+    /// An open project's root.
+    pub project: String,
+    pub title: Option<String>,
+    pub prompt: Option<String>,
+    pub template: Option<String>,
+    #[serde(default)]
+    pub vars: BTreeMap<String, Value>,
+    pub issue: Option<IssueRef>,
+    /// Overrides the project's agent defaults.
+    #[serde(default)]
+    pub agent: IntakeAgent,
+}
+
+/// The agent defaults a posted task may override. Anything touching what
+/// the agent is allowed to do (policy, sandbox, permission mode, verify
+/// commands) stays with the app and the project, and is rejected.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct IntakeAgent {
+    pub provider: Option<String>,
+    pub model: Option<String>,
+}
+
+impl IntakeRequest {
+    fn validate(&self) -> Result<(), String> {
+        let sources = [
+            self.prompt.is_some(),
+            self.template.is_some(),
+            self.issue.is_some(),
+        ];
+        if sources.iter().filter(|&&set| set).count() != 1 {
+            return Err("give exactly one of prompt, template and issue".to_string());
+        }
+        if self.prompt.as_ref().is_some_and(|p| p.trim().is_empty()) {
+            return Err("the prompt must not be empty".to_string());
+        }
+        if !self.vars.is_empty() && self.template.is_none() {
+            return Err("vars are only used with a template".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IntakeStatus {
+    /// Waiting for the frontend to start it.
+    Queued,
+    Started,
+    Done,
+    Failed,
+    /// Dismissed without running.
+    Rejected,
+}
+
+impl IntakeStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            IntakeStatus::Queued => "queued",
+            IntakeStatus::Started => "started",
+            IntakeStatus::Done => "done",
+            IntakeStatus::Failed => "failed",
+            IntakeStatus::Rejected => "rejected",
+        }
+    }
+
+    fn parse(text: &str) -> Option<Self> {
+        serde_json::from_value(Value::from(text)).ok()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntakeTask {
+    pub id: i64,
+    pub request: IntakeRequest,
+    /// The client's `User-Agent`, or `unknown`.
+    pub source: String,
+    pub status: IntakeStatus,
+    /// Why it failed or was rejected, or the run it started.
+    pub detail: Option<String>,
+    /// RFC 3339.
+    pub received_at: String,
+    pub updated_at: String,
+}
+
+struct Running {
+    port: u16,
+    stop: Arc<AtomicBool>,
+}
+
+/// The running endpoint, if any, and why it last failed to start.
+pub struct Intake {
+    running: Mutex<Option<Running>>,
+    error: Mutex<Option<String>>,
+}
+
+impl Intake {
+    pub fn new() -> Self {
+        Self {
+            running: Mutex::new(None),
+            error: Mutex::new(None),
+        }
+    }
+}
+
+pub(crate) fn load(app: &AppHandle, id: i64) -> Result<Option<IntakeTask>, String> {
+    let row = crate::db::with(app, |conn| {
+        conn.query_row(
+            "SELECT request, source, status, detail, received_at, updated_at
+             FROM intake_tasks WHERE id = ?1",
+            params![id],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                ))
+            },
+        )
+        .optional()
+    })?;
+    Ok(row.and_then(
+        |(request, source, status, detail, received_at, updated_at)| {
+            Some(IntakeTask {
+                id,
+                request: serde_json::from_str(&request).ok()?,
+                source,
+                status: IntakeStatus::parse(&status)?,
+                detail,
+                received_at,
+                updated_at,
+            })
+        },
+    ))
+}
+
+/// How many tasks wait to be started.
+pub(crate) fn queued(app: &AppHandle) -> Result<i64, String> {
+    crate::db::with(app, |conn| {
+        conn.query_row(
+            "SELECT COUNT(*) FROM intake_tasks WHERE status = 'queued'",
+            [],
+            |row| row.get(0),
+        )
+    })
+}
+
+/// Tasks, newest first, optionally only those with `status`.
+pub(crate) fn list(
+    app: &AppHandle,
+    status: Option<IntakeStatus>,
+) -> Result<Vec<IntakeTask>, String> {
+    let ids: Vec<i64> = crate::db::with(app, |conn| {
+        let mut statement = conn.prepare(
+            "SELECT id FROM intake_tasks WHERE ?1 IS NULL OR status = ?1
+             ORDER BY id DESC LIMIT 500",
+        )?;
+        let rows =
+            statement.query_map(params![status.map(IntakeStatus::as_str)], |row| row.get(0))?;
+        rows.collect()
+    })?;
+    let mut tasks = Vec::new();
+    for id in ids {
+        tasks.extend(load(app, id)?);
+    }
+    Ok(tasks)
+}
+
+/// Check and store a posted task, and tell the main window.
+pub(crate) fn enqueue(
+    app: &AppHandle,
+    mut request: IntakeRequest,
+    source: &str,
+) -> Result<IntakeTask, String> {
+    request.validate()?;
+    request.project = app
+        .state::<Sandbox>()
+        .check_dir(&request.project)?
+        .to_string_lossy()
+        .to_string();
+    let json = serde_json::to_string(&request).map_err(|e| e.to_string())?;
+    let now = chrono::Utc::now().to_rfc3339();
+    if queued(app)? >= MAX_QUEUED {
+        return Err("too many queued tasks".to_string());
+    }
+    let id = crate::db::with(app, |conn| {
+        conn.execute(
+            "INSERT INTO intake_tasks (project, request, source, status, received_at, updated_at)
+             VALUES (?1, ?2, ?3, 'queued', ?4, ?4)",
+            params![request.project, json, source, now],
+        )?;
+        Ok(conn.last_insert_rowid())
+    })?;
+    let task = IntakeTask {
+        id,
+        request,
+        source: source.to_string(),
+        status: IntakeStatus::Queued,
+        detail: None,
+        received_at: now.clone(),
+        updated_at: now,
+    };
+    tracing::info!(
+        "intake task {id} for {} from {source}",
+        task.request.project
+    );
+    if let Err(e) = app.emit_to("main", "intake:received", task.clone()) {
+        tracing::debug!("intake:received not delivered: {e}");
+    }
+    crate::plugins::emit(app, "task.received", serde_json::json!(task));
+    Ok(task)
+}
+
+fn respond(stream: &mut TcpStream, status: &str, body: &Value) {
+    let body = body.to_string();
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    let _ = stream.write_all(response.as_bytes());
+}
+
+fn error(stream: &mut TcpStream, status: &str, message: &str) {
+    respond(stream, status, &serde_json::json!({ "error": message }));
+}
+
+/// Compare without stopping at the first difference.
+fn same_token(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Serve one client: `POST /tasks` queues a task, `GET /tasks/<id>` reports
+/// on one. Both need `Authorization: Bearer <token>`.
+fn handle(app: &AppHandle, mut stream: TcpStream) {
+    let _ = stream.set_read_timeout(Some(CLIENT_TIMEOUT));
+    let (head, mut body) = match crate::egress::read_head(&mut stream) {
+        Some(head) => head,
+        None => return,
+    };
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let (method, path) = match (request_line.next(), request_line.next()) {
+        (Some(method), Some(path)) => (method.to_string(), path.to_string()),
+        _ => return error(&mut stream, "400 Bad Request", "malformed request"),
+    };
+    let headers: BTreeMap<String, String> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+        .collect();
+
+    let token = match crate::secrets::get(TOKEN_SECRET) {
+        Some(token) => token,
+        None => {
+            return error(
+                &mut stream,
+                "503 Service Unavailable",
+                "no intake token is set",
+            )
+        }
+    };
+    let given = headers
+        .get("authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or_default();
+    if !same_token(given.trim().as_bytes(), token.as_bytes()) {
+        tracing::warn!("intake request with a wrong token refused");
+        return error(&mut stream, "401 Unauthorized", "bad token");
+    }
+
+    let path = path.split('?').next().unwrap_or_default();
+    match (method.as_str(), path.strip_prefix("/tasks")) {
+        ("POST", Some("")) => {
+            let length: usize = match headers.get("content-length").map(|v| v.parse()) {
+                Some(Ok(length)) => length,
+                _ => return error(&mut stream, "411 Length Required", "no content length"),
+            };
+            if length > MAX_BODY_BYTES {
+                return error(&mut stream, "413 Payload Too Large", "request too large");
+            }
+            if body.len() < length {
+                let mut rest = vec![0; length - body.len()];
+                if stream.read_exact(&mut rest).is_err() {
+                    return error(&mut stream, "400 Bad Request", "incomplete body");
+                }
+                body.extend(rest);
+            }
+            body.truncate(length);
+            let request: IntakeRequest = match serde_json::from_slice(&body) {
+                Ok(request) => request,
+                Err(e) => return error(&mut stream, "400 Bad Request", &e.to_string()),
+            };
+            let source = headers
+                .get("user-agent")
+                .map(|agent| agent.chars().take(200).collect::<String>())
+                .unwrap_or_else(|| "unknown".to_string());
+            match enqueue(app, request, &source) {
+                Ok(task) => respond(
+                    &mut stream,
+                    "202 Accepted",
+                    &serde_json::to_value(task).unwrap_or_default(),
+                ),
+                Err(e) => error(&mut stream, "422 Unprocessable Entity", &e),
+            }
+        }
+        ("GET", Some(rest)) if rest.starts_with('/') => {
+            let task = rest[1..]
+                .parse()
+                .ok()
+                .and_then(|id| load(app, id).ok().flatten());
+            match task {
+                Some(task) => respond(
+                    &mut stream,
+                    "200 OK",
+                    &serde_json::to_value(task).unwrap_or_default(),
+                ),
+                None => error(&mut stream, "404 Not Found", "no such task"),
+            }
+        }
+        (_, Some(_)) => error(&mut stream, "405 Method Not Allowed", "method not allowed"),
+        _ => error(&mut stream, "404 Not Found", "not found"),
+    }
+}
+
+fn serve(app: AppHandle, listener: TcpListener, stop: Arc<AtomicBool>) {
+    while !stop.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                let _ = stream.set_nonblocking(false);
+                let app = app.clone();
+                std::thread::spawn(move || handle(&app, stream));
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                std::thread::sleep(ACCEPT_POLL_INTERVAL);
+            }
+            Err(e) => {
+                tracing::warn!("intake: accept failed: {e}");
+                std::thread::sleep(ACCEPT_POLL_INTERVAL);
+            }
+        }
+    }
+}
+
+/// Start, stop or move the endpoint to match `settings`.
+pub(crate) fn configure(app: &AppHandle, settings: &IntakeSettings) {
+    let intake = match app.try_state::<Intake>() {
+        Some(intake) => intake,
+        None => return,
+    };
+    let mut running = match intake.running.lock() {
+        Ok(running) => running,
+        Err(_) => return,
+    };
+    if settings.enabled && running.as_ref().is_some_and(|r| r.port == settings.port) {
+        return;
+    }
+    if let Some(old) = running.take() {
+        old.stop.store(true, Ordering::Relaxed);
+        tracing::info!("intake endpoint on port {} stopped", old.port);
+    }
+    let mut last_error = intake.error.lock().ok();
+    if let Some(last_error) = last_error.as_deref_mut() {
+        *last_error = None;
+    }
+    if !settings.enabled {
+        return;
+    }
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, settings.port))
+        .and_then(|l| l.set_nonblocking(true).map(|_| l));
+    match listener {
+        Ok(listener) => {
+            let stop = Arc::new(AtomicBool::new(false));
+            *running = Some(Running {
+                port: settings.port,
+                stop: stop.clone(),
+            });
+            let app = app.clone();
+            std::thread::spawn(move || serve(app, listener, stop));
+            tracing::info!("intake endpoint listening on 127.0.0.1:{}", settings.port);
+        }
+        Err(e) => {
+            tracing::error!("intake endpoint failed to start: {e}");
+            if let Some(last_error) = last_error.as_deref_mut() {
+                *last_error = Some(format!("port {}: {e}", settings.port));
+            }
+        }
+    }
+}
+
+/// Start the endpoint if the settings ask for it. Called once from `setup`.
+pub fn init(app: &AppHandle) {
+    configure(app, &crate::settings::current(app).intake);
+}
+
+pub(crate) fn stop(state: &Intake) {
+    if let Ok(mut running) = state.running.lock() {
+        if let Some(running) = running.take() {
+            running.stop.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntakeInfo {
+    pub enabled: bool,
+    /// `http://127.0.0.1:<port>/tasks` while listening.
+    pub url: Option<String>,
+    pub has_token: bool,
+    /// Why the endpoint isn't listening although enabled.
+    pub error: Option<String>,
+}
+
+#[tauri::command]
+pub fn intake_status(
+    window: WebviewWindow,
+    app: AppHandle,
+    state: State<'_, Intake>,
+) -> Result<IntakeInfo, String> {
+    crate::require_window(&window, "main")?;
+    let port = state
+        .running
+        .lock()
+        .map_err(|e| e.to_string())?
+        .as_ref()
+        .map(|r| r.port);
+    Ok(IntakeInfo {
+        enabled: crate::settings::current(&app).intake.enabled,
+        url: port.map(|port| format!("http://127.0.0.1:{port}/tasks")),
+        has_token: crate::secrets::get(TOKEN_SECRET).is_some(),
+        error: state.error.lock().map_err(|e| e.to_string())?.clone(),
+    })
+}
+
+/// Make a new token for the endpoint, replacing the old one, and return it.
+/// It is shown only this once; the keychain keeps it.
+#[tauri::command]
+pub fn intake_rotate_token(window: WebviewWindow) -> Result<String, String> {
+    crate::require_window(&window, "main")?;
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes).map_err(|e| e.to_string())?;
+    let token = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes);
+    crate::secrets::set_unlisted(TOKEN_SECRET, &token)?;
+    tracing::info!("intake token rotated");
+    Ok(token)
+}
+
+/// Posted tasks, newest first, optionally only those with `status`.
+#[tauri::command]
+pub async fn intake_list(
+    window: WebviewWindow,
+    app: AppHandle,
+    status: Option<IntakeStatus>,
+) -> Result<Vec<IntakeTask>, String> {
+    crate::require_app_window(&window)?;
+    tauri::async_runtime::spawn_blocking(move || list(&app, status))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Record what became of task `id` once the frontend has started it (or
+/// decided not to), so clients polling `GET /tasks/<id>` see it.
+#[tauri::command]
+pub async fn intake_update(
+    window: WebviewWindow,
+    app: AppHandle,
+    id: i64,
+    status: IntakeStatus,
+    detail: Option<String>,
+) -> Result<IntakeTask, String> {
+    crate::require_app_window(&window)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let changed = crate::db::with(&app, |conn| {
+            conn.execute(
+                "UPDATE intake_tasks SET status = ?2, detail = ?3, updated_at = ?4 WHERE id = ?1",
+                params![id, status.as_str(), detail, chrono::Utc::now().to_rfc3339()],
+            )
+        })?;
+        if changed == 0 {
+            return Err(format!("no intake task {id}"));
+        }
+        load(&app, id)?.ok_or_else(|| format!("intake task {id} is corrupt"))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(agent: Value) -> Result<IntakeRequest, serde_json::Error> {
+        serde_json::from_value(serde_json::json!({
+            "project": "/project",
+            "prompt": "fix the build",
+            "agent": agent,
+        }))
+    }
+
+    #[test]
+    fn posted_tasks_may_pick_the_provider_and_model() {
+        let request = parse(serde_json::json!({ "provider": "codex", "model": "o3" })).unwrap();
+        assert_eq!(request.agent.provider.as_deref(), Some("codex"));
+        assert_eq!(request.agent.model.as_deref(), Some("o3"));
+    }
+
+    #[test]
+    fn posted_tasks_may_not_loosen_the_agent() {
+        for field in ["policy", "shellSandbox", "permissionMode", "verify"] {
+            let agent = serde_json::json!({ field: "x" });
+            assert!(parse(agent).is_err(), "{field} was accepted");
+        }
+    }
+}
@@ -0,0 +1,350 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::Path;
+use std::sync::OnceLock;
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+use crate::forge::{ForgeProvider, ForgeRemote};
+use crate::injection::Finding;
+use crate::jobs::JobInfo;
+use crate::sandbox::Sandbox;
+use crate::settings::AgentDefaults;
+
+const LINEAR_API: &str = "https://api.linear.app/graphql";
+const LINEAR_SECRET: &str = "linear-api-key";
+const JIRA_SECRET: &str = "jira-token";
+/// Issue bodies are cut to this many bytes before going into a prompt.
+const MAX_BODY_LEN: usize = 32 * 1024;
+/// Words of the title kept in a task's branch name.
+const BRANCH_TITLE_WORDS: usize = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IssueProvider {
+    Github,
+    Linear,
+    Jira,
+}
+
+impl IssueProvider {
+    fn label(self) -> &'static str {
+        match self {
+            IssueProvider::Github => "GitHub",
+            IssueProvider::Linear => "Linear",
+            IssueProvider::Jira => "Jira",
+        }
+    }
+}
+
+/// Where the Jira site is and who to sign in as; the API token is the
+/// `jira-token` secret.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct IssueSettings {
+    /// e.g. `https://example.atlassian.net`.
+    pub jira_url: Option<String>,
+    pub jira_email: Option<String>,
+}
+
+pub(crate) fn validate_settings(settings: &IssueSettings) -> Result<(), String> {
+    if let Some(url) = &settings.jira_url {
+        let parsed = url::Url::parse(url).map_err(|e| format!("jira URL {url:?}: {e}"))?;
+        if parsed.scheme() != "https" {
+            return Err("the Jira URL must use https".to_string());
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Issue {
+    pub provider: IssueProvider,
+    /// `#42`, `ENG-123`, `PROJ-7`.
+    pub key: String,
+    pub title: String,
+    pub body: String,
+    pub url: Option<String>,
+    pub state: Option<String>,
+    pub labels: Vec<String>,
+}
+
+fn api_error(e: ureq::Error) -> String {
+    match e {
+        ureq::Error::Status(code, resp) => format!(
+            "issue tracker returned {code}: {}",
+            resp.into_string().unwrap_or_default()
+        ),
+        other => other.to_string(),
+    }
+}
+
+fn text(value: &Value) -> String {
+    value.as_str().unwrap_or_default().to_string()
+}
+
+/// `42`, `#42` or `owner/repo#42`; the repository defaults to the project's
+/// `origin`.
+fn github_issue(app: &AppHandle, project: &Path, id: &str) -> Result<Issue, String> {
+    let (repo, number) = match id.rsplit_once('#') {
+        Some((repo, number)) if !repo.is_empty() => (Some(repo), number),
+        Some((_, number)) => (None, number),
+        None => (None, id),
+    };
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("{id:?} is not a GitHub issue number"))?;
+    let cwd = project.to_string_lossy();
+    let remote = match repo {
+        Some(repo) => ForgeRemote {
+            provider: ForgeProvider::Github,
+            host: "github.com".to_string(),
+            path: repo.to_string(),
+        },
+        None => crate::forge::resolve_remote(&cwd, "origin")?,
+    };
+    if remote.provider != ForgeProvider::Github {
+        return Err(format!("{} is not a GitHub repository", remote.path));
+    }
+    let token = crate::forge::stored_token(app, &remote);
+    let issue = crate::forge::api_get(
+        &remote,
+        token.as_deref(),
+        &cwd,
+        &format!("repos/{}/issues/{number}", remote.path),
+    )?;
+    Ok(Issue {
+        provider: IssueProvider::Github,
+        key: format!("#{number}"),
+        title: text(&issue["title"]),
+        body: text(&issue["body"]),
+        url: issue["html_url"].as_str().map(str::to_string),
+        state: issue["state"].as_str().map(str::to_string),
+        labels: issue["labels"]
+            .as_array()
+            .map(|labels| labels.iter().map(|l| text(&l["name"])).collect())
+            .unwrap_or_default(),
+    })
+}
+
+fn linear_issue(id: &str) -> Result<Issue, String> {
+    let key = crate::secrets::get(LINEAR_SECRET)
+        .ok_or_else(|| format!("store a Linear API key as the {LINEAR_SECRET} secret"))?;
+    let query = "query($id: String!) { issue(id: $id) {
+        identifier title description url state { name } labels { nodes { name } } } }";
+    let response: Value = ureq::post(LINEAR_API)
+        .set("Authorization", &key)
+        .send_json(json!({ "query": query, "variables": { "id": id } }))
+        .map_err(api_error)?
+        .into_json()
+        .map_err(|e| e.to_string())?;
+    if let Some(message) = response["errors"][0]["message"].as_str() {
+        return Err(format!("Linear: {message}"));
+    }
+    let issue = &response["data"]["issue"];
+    if issue.is_null() {
+        return Err(format!("no Linear issue {id}"));
+    }
+    Ok(Issue {
+        provider: IssueProvider::Linear,
+        key: text(&issue["identifier"]),
+        title: text(&issue["title"]),
+        body: text(&issue["description"]),
+        url: issue["url"].as_str().map(str::to_string),
+        state: issue["state"]["name"].as_str().map(str::to_string),
+        labels: issue["labels"]["nodes"]
+            .as_array()
+            .map(|labels| labels.iter().map(|l| text(&l["name"])).collect())
+            .unwrap_or_default(),
+    })
+}
+
+fn jira_issue(app: &AppHandle, id: &str) -> Result<Issue, String> {
+    let settings = crate::settings::current(app).issues;
+    let (base, email) = match (settings.jira_url, settings.jira_email) {
+        (Some(base), Some(email)) => (base, email),
+        _ => return Err("set the Jira URL and email in the settings".to_string()),
+    };
+    let token = crate::secrets::get(JIRA_SECRET)
+        .ok_or_else(|| format!("store a Jira API token as the {JIRA_SECRET} secret"))?;
+    let credentials = base64::Engine::encode(
+        &base64::engine::general_purpose::STANDARD,
+        format!("{email}:{token}"),
+    );
+    let base = base.trim_end_matches('/');
+    // v2 returns the description as text rather than a document tree.
+    let issue: Value = ureq::get(&format!("{base}/rest/api/2/issue/{id}"))
+        .query("fields", "summary,description,status,labels")
+        .set("Authorization", &format!("Basic {credentials}"))
+        .set("Accept", "application/json")
+        .call()
+        .map_err(api_error)?
+        .into_json()
+        .map_err(|e| e.to_string())?;
+    let key = text(&issue["key"]);
+    let fields = &issue["fields"];
+    Ok(Issue {
+        provider: IssueProvider::Jira,
+        url: Some(format!("{base}/browse/{key}")),
+        key,
+        title: text(&fields["summary"]),
+        body: text(&fields["description"]),
+        state: fields["status"]["name"].as_str().map(str::to_string),
+        labels: fields["labels"]
+            .as_array()
+            .map(|labels| labels.iter().map(text).collect())
+            .unwrap_or_default(),
+    })
+}
+
+fn fetch(
+    app: &AppHandle,
+    project: &Path,
+    provider: IssueProvider,
+    id: &str,
+) -> Result<Issue, String> {
+    static ID: OnceLock<Regex> = OnceLock::new();
+    let valid = ID.get_or_init(|| {
+        Regex::new(r"^([A-Za-z0-9_.-]+/[A-Za-z0-9_.-]+)?#?[A-Za-z0-9][A-Za-z0-9_-]*$")
+            .expect("valid pattern")
+    });
+    let id = id.trim();
+    if !valid.is_match(id) {
+        return Err(format!("{id:?} is not an issue id"));
+    }
+    match provider {
+        IssueProvider::Github => github_issue(app, project, id),
+        IssueProvider::Linear => linear_issue(id),
+        IssueProvider::Jira => jira_issue(app, id),
+    }
+}
+
+/// `eng-123-fix-login-on-safari`: the key and the first words of the title.
+fn branch_name(issue: &Issue) -> String {
+    let key = issue.key.trim_start_matches('#').to_ascii_lowercase();
+    let words: Vec<String> = issue
+        .title
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .take(BRANCH_TITLE_WORDS)
+        .map(str::to_ascii_lowercase)
+        .collect();
+    match issue.provider {
+        IssueProvider::Github => format!("issue-{key}-{}", words.join("-")),
+        _ => format!("{key}-{}", words.join("-")),
+    }
+    .trim_end_matches('-')
+    .to_string()
+}
+
+/// The agent prompt for `issue`, with its title and body as they came out of
+/// the injection scan.
+fn prompt(issue: &Issue, title: &str, body: &str) -> String {
+    let mut body = crate::redact::redact(body.trim());
+    if body.len() > MAX_BODY_LEN {
+        let mut end = MAX_BODY_LEN;
+        while !body.is_char_boundary(end) {
+            end -= 1;
+        }
+        body.truncate(end);
+        body.push_str("\n[…]");
+    }
+    let mut prompt = format!(
+        "Resolve {} issue {}: {}\n",
+        issue.provider.label(),
+        issue.key,
+        title
+    );
+    if let Some(url) = &issue.url {
+        prompt.push_str(&format!("{url}\n"));
+    }
+    if !body.is_empty() {
+        prompt.push_str(&format!("\n{body}\n"));
+    }
+    prompt
+}
+
+/// The task created from an issue: the agent is for the frontend to start
+/// in `worktree` through the server.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IssueTask {
+    pub issue: Issue,
+    pub title: String,
+    pub branch: String,
+    pub worktree: String,
+    pub prompt: String,
+    pub agent: AgentDefaults,
+    /// Likely prompt injection found in the issue; see `injection`.
+    pub injection: Vec<Finding>,
+    /// The project's bootstrap of the new worktree, if it has one: start
+    /// the agent once this job finishes.
+    pub bootstrap: Option<JobInfo>,
+}
+
+/// Fetch issue `id` from `provider`. GitHub issues are looked up in
+/// `project`'s `origin` unless `id` names the repository.
+#[tauri::command]
+pub async fn issue_get(
+    window: WebviewWindow,
+    app: AppHandle,
+    project: String,
+    provider: IssueProvider,
+    id: String,
+) -> Result<Issue, String> {
+    crate::require_app_window(&window)?;
+    let root = app.state::<Sandbox>().check_dir(&project)?;
+    tauri::async_runtime::spawn_blocking(move || fetch(&app, &root, provider, &id))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Start a task for issue `id`: create a branch named after it and a
+/// worktree off HEAD, and seed the agent prompt with the issue. The prompt
+/// and agent settings come back for the frontend to start the agent with.
+#[tauri::command]
+pub async fn task_create_from_issue(
+    window: WebviewWindow,
+    app: AppHandle,
+    project: String,
+    provider: IssueProvider,
+    id: String,
+) -> Result<IssueTask, String> {
+    crate::require_app_window(&window)?;
+    let root = app.state::<Sandbox>().check_dir(&project)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let issue = fetch(&app, &root, provider, &id)?;
+        let project = root.to_string_lossy();
+        let source = format!("{} issue {}", provider.label(), issue.key);
+        let title = crate::injection::scan(&app, Some(&project), &source, &issue.title);
+        let body = crate::injection::scan(&app, Some(&project), &source, &issue.body);
+        let (prefix, directory, agent) = crate::templates::task_config(&app, &root);
+        let branch = format!("{prefix}{}", branch_name(&issue));
+        crate::templates::check_branch(&branch)?;
+        let worktree = crate::templates::add_worktree(&app, &root, &branch, directory.as_deref())?;
+        tracing::info!(
+            "created worktree {} for {} issue {}",
+            worktree.display(),
+            provider.label(),
+            issue.key
+        );
+        let bootstrap =
+            crate::bootstrap::start(&app, &window, &root, &worktree).unwrap_or_else(|e| {
+                tracing::warn!("bootstrap of {} not started: {e}", worktree.display());
+                None
+            });
+        Ok(IssueTask {
+            title: format!("{} {}", issue.key, issue.title),
+            branch,
+            worktree: worktree.to_string_lossy().to_string(),
+            prompt: prompt(&issue, &title.text, &body.text),
+            agent,
+            injection: title.findings.into_iter().chain(body.findings).collect(),
+            bootstrap,
+            issue,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
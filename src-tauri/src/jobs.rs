@@ -0,0 +1,271 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, EventTarget, Manager, State, WebviewWindow};
+
+use crate::cancel::CancelToken;
+use crate::events::{Coalesce, JOB_PROGRESS};
+
+/// Finished jobs kept for `job_list`; older ones are forgotten.
+const MAX_FINISHED: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum JobState {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobInfo {
+    pub id: u64,
+    /// What the job does, e.g. `scratch.create`.
+    pub kind: String,
+    /// Human-readable subject, e.g. the project name.
+    pub label: String,
+    pub state: JobState,
+    pub done: u64,
+    /// `None` while the amount of work isn't known yet.
+    pub total: Option<u64>,
+    pub message: Option<String>,
+    pub error: Option<String>,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JobFinishedPayload {
+    job: JobInfo,
+    /// What the job produced, when it completed.
+    result: Option<Value>,
+}
+
+struct JobEntry {
+    info: JobInfo,
+    window: String,
+    cancelled: CancelToken,
+}
+
+/// Long-running backend work, each job on its own thread, with uniform
+/// `job:progress` and `job:finished` events to the window that started it.
+pub struct Jobs {
+    jobs: Mutex<BTreeMap<u64, JobEntry>>,
+    next_id: AtomicU64,
+}
+
+impl Jobs {
+    pub fn new() -> Self {
+        Self {
+            jobs: Mutex::new(BTreeMap::new()),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    pub(crate) fn running_count(&self) -> usize {
+        self.jobs
+            .lock()
+            .map(|jobs| {
+                jobs.values()
+                    .filter(|e| e.info.state == JobState::Running)
+                    .count()
+            })
+            .unwrap_or(0)
+    }
+}
+
+/// What a job's work function gets to report progress and notice
+/// cancellation.
+pub(crate) struct JobHandle {
+    app: AppHandle,
+    id: u64,
+    window: String,
+    cancelled: CancelToken,
+}
+
+impl JobHandle {
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancelled.is_cancelled()
+    }
+
+    /// `Err` once the job is cancelled, for `?` at convenient checkpoints.
+    pub(crate) fn check(&self) -> Result<(), String> {
+        self.cancelled.check()
+    }
+
+    /// The job's token, for helpers that take one, like
+    /// `forge::run_capture_cancellable`.
+    pub(crate) fn token(&self) -> &CancelToken {
+        &self.cancelled
+    }
+
+    pub(crate) fn progress(&self, done: u64, total: Option<u64>, message: Option<&str>) {
+        let info = {
+            let jobs = self.app.state::<Jobs>();
+            let mut jobs = match jobs.jobs.lock() {
+                Ok(jobs) => jobs,
+                Err(_) => return,
+            };
+            let entry = match jobs.get_mut(&self.id) {
+                Some(entry) => entry,
+                None => return,
+            };
+            entry.info.done = done;
+            entry.info.total = total;
+            entry.info.message = message.map(str::to_string);
+            entry.info.clone()
+        };
+        crate::events::emit_throttled(
+            &self.app,
+            &self.window,
+            &topic(self.id),
+            JOB_PROGRESS,
+            "job:progress",
+            Coalesce::Latest,
+            info,
+        );
+    }
+}
+
+fn topic(id: u64) -> String {
+    format!("job:{id}")
+}
+
+fn finish(app: &AppHandle, id: u64, outcome: Result<Value, String>, cancelled: bool) {
+    let (info, window) = {
+        let jobs = app.state::<Jobs>();
+        let mut jobs = match jobs.jobs.lock() {
+            Ok(jobs) => jobs,
+            Err(_) => return,
+        };
+        let entry = match jobs.get_mut(&id) {
+            Some(entry) => entry,
+            None => return,
+        };
+        // A job that finished its work despite a late cancel still completed.
+        entry.info.state = match (&outcome, cancelled) {
+            (Ok(_), _) => JobState::Completed,
+            (Err(_), true) => JobState::Cancelled,
+            (Err(_), false) => JobState::Failed,
+        };
+        entry.info.error = outcome.as_ref().err().filter(|_| !cancelled).cloned();
+        entry.info.finished_at = Some(chrono::Utc::now().to_rfc3339());
+        let finished = (entry.info.clone(), entry.window.clone());
+
+        let done: Vec<u64> = jobs
+            .iter()
+            .filter(|(_, e)| e.info.state != JobState::Running)
+            .map(|(id, _)| *id)
+            .collect();
+        for old in done.iter().take(done.len().saturating_sub(MAX_FINISHED)) {
+            jobs.remove(old);
+        }
+        finished
+    };
+    match &info.error {
+        Some(error) => tracing::warn!("job {id} ({}) failed: {error}", info.kind),
+        None => tracing::info!("job {id} ({}) {:?}", info.kind, info.state),
+    }
+    // Progress still held back must not arrive after the result.
+    crate::events::flush_topic(app, &window, &topic(id));
+    let payload = JobFinishedPayload {
+        result: outcome.ok(),
+        job: info,
+    };
+    if let Err(e) = app.emit_to(
+        EventTarget::webview_window(&window),
+        "job:finished",
+        payload,
+    ) {
+        tracing::debug!("job:finished not delivered: {e}");
+    }
+}
+
+/// Run `work` as a job on a new thread and return at once. Its `Ok` value is
+/// sent as the `result` of `job:finished`. The job can be stopped with
+/// `job_cancel` or with `cancel("job:<id>")`.
+pub(crate) fn spawn<T, F>(
+    app: &AppHandle,
+    window: &WebviewWindow,
+    kind: &str,
+    label: String,
+    work: F,
+) -> Result<JobInfo, String>
+where
+    T: Serialize,
+    F: FnOnce(&JobHandle) -> Result<T, String> + Send + 'static,
+{
+    let jobs = app.state::<Jobs>();
+    let id = jobs.next_id.fetch_add(1, Ordering::Relaxed);
+    let guard = crate::cancel::register(app, Some(&topic(id)));
+    let cancelled = guard.token();
+    let info = JobInfo {
+        id,
+        kind: kind.to_string(),
+        label,
+        state: JobState::Running,
+        done: 0,
+        total: None,
+        message: None,
+        error: None,
+        started_at: chrono::Utc::now().to_rfc3339(),
+        finished_at: None,
+    };
+    jobs.jobs.lock().map_err(|e| e.to_string())?.insert(
+        id,
+        JobEntry {
+            info: info.clone(),
+            window: window.label().to_string(),
+            cancelled: cancelled.clone(),
+        },
+    );
+    let handle = JobHandle {
+        app: app.clone(),
+        id,
+        window: window.label().to_string(),
+        cancelled,
+    };
+    std::thread::Builder::new()
+        .name(format!("job-{id}"))
+        .spawn(move || {
+            let outcome = work(&handle)
+                .and_then(|value| serde_json::to_value(value).map_err(|e| e.to_string()));
+            finish(&handle.app, id, outcome, handle.is_cancelled());
+            drop(guard);
+        })
+        .map_err(|e| e.to_string())?;
+    tracing::info!("job {id} ({kind}) started");
+    Ok(info)
+}
+
+/// Ask every running job to stop — called on app exit.
+pub fn cancel_all(state: &Jobs) {
+    if let Ok(jobs) = state.jobs.lock() {
+        for entry in jobs.values() {
+            entry.cancelled.cancel();
+        }
+    }
+}
+
+/// Running jobs and recently finished ones, oldest first.
+#[tauri::command]
+pub fn job_list(window: WebviewWindow, state: State<'_, Jobs>) -> Result<Vec<JobInfo>, String> {
+    crate::require_app_window(&window)?;
+    let jobs = state.jobs.lock().map_err(|e| e.to_string())?;
+    Ok(jobs.values().map(|entry| entry.info.clone()).collect())
+}
+
+/// Ask a job to stop. It finishes as `cancelled` at its next checkpoint.
+#[tauri::command]
+pub fn job_cancel(window: WebviewWindow, state: State<'_, Jobs>, id: u64) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    let jobs = state.jobs.lock().map_err(|e| e.to_string())?;
+    let entry = jobs.get(&id).ok_or_else(|| format!("job {id} not found"))?;
+    entry.cancelled.cancel();
+    Ok(())
+}
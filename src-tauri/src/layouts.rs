@@ -0,0 +1,281 @@
+use portable_pty::PtySize;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+use crate::confine::ShellSandbox;
+use crate::pty::{Launch, PtyManager};
+use crate::sandbox::Sandbox;
+
+/// Terminals start at this size; the frontend resizes them when it attaches.
+const INITIAL_ROWS: u16 = 24;
+const INITIAL_COLS: u16 = 80;
+
+const MAX_TERMINALS: usize = 32;
+const MAX_AGENTS: usize = 32;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LayoutTerminal {
+    pub title: Option<String>,
+    /// Relative to the project; its root by default.
+    pub cwd: Option<String>,
+    /// Typed into the shell once it starts, e.g. `pnpm dev`.
+    pub command: Option<String>,
+    pub sandbox: Option<ShellSandbox>,
+}
+
+/// An agent to start with the layout. The server starts agents, so these are
+/// handed back to the frontend as they are.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LayoutAgent {
+    pub title: Option<String>,
+    pub prompt: Option<String>,
+    /// A prompt template to fill instead of `prompt`.
+    pub template: Option<String>,
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub permission_mode: Option<String>,
+}
+
+/// A named set of terminals and agents to open a project with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Layout {
+    pub name: String,
+    #[serde(default)]
+    pub terminals: Vec<LayoutTerminal>,
+    #[serde(default)]
+    pub agents: Vec<LayoutAgent>,
+}
+
+impl Layout {
+    fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("layout name must not be empty".to_string());
+        }
+        if self.terminals.len() > MAX_TERMINALS || self.agents.len() > MAX_AGENTS {
+            return Err(format!(
+                "layouts hold at most {MAX_TERMINALS} terminals and {MAX_AGENTS} agents"
+            ));
+        }
+        if let Some(i) = self
+            .agents
+            .iter()
+            .position(|a| a.prompt.is_none() && a.template.is_none())
+        {
+            return Err(format!("agent {} needs a prompt or a template", i + 1));
+        }
+        Ok(())
+    }
+}
+
+/// A terminal `layout_apply` opened.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenedTerminal {
+    pub id: String,
+    pub title: Option<String>,
+    pub cwd: String,
+    pub command: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppliedLayout {
+    pub terminals: Vec<OpenedTerminal>,
+    /// For the frontend to start through the server.
+    pub agents: Vec<LayoutAgent>,
+    /// Terminals that could not be opened, with the reason.
+    pub errors: Vec<String>,
+}
+
+pub(crate) fn load_all(
+    app: &AppHandle,
+    project: Option<&str>,
+) -> Result<Vec<(String, Layout)>, String> {
+    let rows: Vec<(String, String)> = crate::db::with(app, |conn| {
+        let mut statement = conn.prepare(
+            "SELECT project, layout FROM layouts
+             WHERE ?1 IS NULL OR project = ?1 ORDER BY project, name",
+        )?;
+        let rows = statement.query_map(params![project], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    })?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|(project, json)| Some((project, serde_json::from_str(&json).ok()?)))
+        .collect())
+}
+
+pub(crate) fn store(app: &AppHandle, project: &str, layout: &Layout) -> Result<(), String> {
+    layout.validate()?;
+    let json = serde_json::to_string(layout).map_err(|e| e.to_string())?;
+    crate::db::with(app, |conn| {
+        conn.execute(
+            "INSERT OR REPLACE INTO layouts (project, name, layout, updated_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![project, layout.name, json, chrono::Utc::now().to_rfc3339()],
+        )
+    })
+    .map(|_| ())
+}
+
+/// Spawn `terminals` in `window`, each in its `cwd` under `base` (which must
+/// pass the sandbox), and type in their commands. Returns the terminals
+/// opened and why the others failed; ids start with `prefix`.
+pub(crate) fn open_terminals(
+    app: &AppHandle,
+    window: &WebviewWindow,
+    base: &str,
+    terminals: &[LayoutTerminal],
+    prefix: &str,
+) -> (Vec<OpenedTerminal>, Vec<String>) {
+    let stamp = chrono::Utc::now().timestamp_millis();
+    let size = PtySize {
+        rows: INITIAL_ROWS,
+        cols: INITIAL_COLS,
+        pixel_width: 0,
+        pixel_height: 0,
+    };
+    let mut opened_terminals = Vec::new();
+    let mut errors = Vec::new();
+    for (i, terminal) in terminals.iter().enumerate() {
+        let id = format!("{prefix}-{stamp}-{i}");
+        let opened = app
+            .state::<Sandbox>()
+            .resolve(base, terminal.cwd.as_deref().unwrap_or("."))
+            .and_then(|cwd| {
+                let launch = Launch::Shell(terminal.sandbox.unwrap_or_default());
+                crate::pty::spawn(app, window, id.clone(), &cwd, size, launch)?;
+                if let Some(line) = terminal.command.as_deref().filter(|c| !c.trim().is_empty()) {
+                    crate::pty::write(
+                        &app.state::<PtyManager>(),
+                        window,
+                        &id,
+                        &format!("{line}\r"),
+                    )?;
+                }
+                Ok(cwd)
+            });
+        match opened {
+            Ok(cwd) => opened_terminals.push(OpenedTerminal {
+                id,
+                title: terminal.title.clone(),
+                cwd: cwd.to_string_lossy().to_string(),
+                command: terminal.command.clone(),
+            }),
+            Err(e) => errors.push(format!(
+                "terminal {}: {e}",
+                terminal
+                    .title
+                    .clone()
+                    .unwrap_or_else(|| (i + 1).to_string())
+            )),
+        }
+    }
+    (opened_terminals, errors)
+}
+
+/// The canonical project root layouts are stored under.
+fn project_key(app: &AppHandle, project: &str) -> Result<String, String> {
+    Ok(app
+        .state::<Sandbox>()
+        .check_dir(project)?
+        .to_string_lossy()
+        .to_string())
+}
+
+/// The project's saved layouts, by name.
+#[tauri::command]
+pub async fn layout_list(
+    window: WebviewWindow,
+    app: AppHandle,
+    project: String,
+) -> Result<Vec<Layout>, String> {
+    crate::require_app_window(&window)?;
+    let project = project_key(&app, &project)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        Ok(load_all(&app, Some(&project))?
+            .into_iter()
+            .map(|(_, layout)| layout)
+            .collect())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Save `layout` for `project`, replacing one with the same name.
+#[tauri::command]
+pub async fn layout_save(
+    window: WebviewWindow,
+    app: AppHandle,
+    project: String,
+    layout: Layout,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    let project = project_key(&app, &project)?;
+    tauri::async_runtime::spawn_blocking(move || store(&app, &project, &layout))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn layout_delete(
+    window: WebviewWindow,
+    app: AppHandle,
+    project: String,
+    name: String,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    let project = project_key(&app, &project)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        crate::db::with(&app, |conn| {
+            conn.execute(
+                "DELETE FROM layouts WHERE project = ?1 AND name = ?2",
+                params![project, name],
+            )
+        })
+        .map(|_| ())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Open `project` with the saved layout `layout`: every terminal is spawned
+/// in this window and its command typed in, and the agents are returned for
+/// the frontend to start. A terminal that fails to open doesn't stop the
+/// rest.
+#[tauri::command]
+pub async fn layout_apply(
+    window: WebviewWindow,
+    app: AppHandle,
+    project: String,
+    layout: String,
+) -> Result<AppliedLayout, String> {
+    crate::require_app_window(&window)?;
+    let root = project_key(&app, &project)?;
+    let loader = app.clone();
+    let key = root.clone();
+    let layout = tauri::async_runtime::spawn_blocking(move || load_all(&loader, Some(&key)))
+        .await
+        .map_err(|e| e.to_string())??
+        .into_iter()
+        .map(|(_, l)| l)
+        .find(|l| l.name == layout)
+        .ok_or_else(|| format!("no layout {layout} for {root}"))?;
+
+    let (terminals, errors) = open_terminals(&app, &window, &root, &layout.terminals, "layout");
+    tracing::info!(
+        "applied layout {} to {root}: {} terminals, {} agents",
+        layout.name,
+        terminals.len(),
+        layout.agents.len()
+    );
+    Ok(AppliedLayout {
+        terminals,
+        agents: layout.agents,
+        errors,
+    })
+}
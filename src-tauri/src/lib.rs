@@ -1,4 +1,87 @@
+mod analytics;
+mod app_menu;
+mod audit;
+mod automation;
+mod badge;
+mod bootstrap;
+mod cancel;
+mod capabilities;
+mod cleanup;
+mod clipboard;
+mod completion;
+mod confine;
+mod context_menu;
+mod crash;
+mod credentials;
+mod db;
+mod deeplink;
+mod dev_servers;
+mod diagnostics;
+mod digest;
+mod disk;
+mod dry_run;
+mod egress;
+mod events;
+mod exec;
+mod file_drop;
+mod file_index;
+mod file_locks;
+mod forge;
+mod forward;
+mod fs;
+mod fuzzy;
+mod history;
+mod injection;
+mod instance;
+mod intake;
+mod issues;
+mod jobs;
+mod layouts;
+mod local_agent;
+mod logging;
+mod notifications;
+mod onboarding;
+mod open;
+mod patch;
+mod plan_mode;
+mod plugins;
+mod policy;
+mod power;
+mod problems;
+mod profiles;
+mod project_config;
 mod pty;
+mod recent;
+mod redact;
+mod remote;
+mod remote_fs;
+mod remote_runner;
+mod remotes;
+mod report;
+mod run_env;
+mod sandbox;
+mod scratch;
+mod search;
+mod secrets;
+mod settings;
+mod shell_pool;
+mod shortcut;
+mod stats;
+mod suspend;
+mod tasks;
+mod templates;
+mod test_report;
+mod timeline;
+mod tmux;
+mod tray;
+mod updater;
+mod verify;
+mod watcher;
+mod webhooks;
+mod window_state;
+mod windows;
+mod workspace;
+mod worktree_pool;
 
 use tauri::Emitter;
 use tauri::Manager;
@@ -6,7 +89,6 @@ use tauri::WebviewUrl;
 use tauri::WebviewWindow;
 use tauri::WebviewWindowBuilder;
 use tauri_plugin_shell::process::CommandChild;
-use tauri_plugin_shell::ShellExt;
 
 struct ServerProcess(std::sync::Mutex<Option<CommandChild>>);
 
@@ -18,6 +100,16 @@ fn require_window(window: &WebviewWindow, allowed_label: &str) -> Result<(), Str
     }
 }
 
+/// Accept commands from the main window and from per-project windows, which
+/// get the same workspace features.
+fn require_app_window(window: &WebviewWindow) -> Result<(), String> {
+    if window.label() == "main" || windows::is_project_window(window.label()) {
+        Ok(())
+    } else {
+        Err("command is only available from app windows".to_string())
+    }
+}
+
 /// Opens the single preview browser window. If it already exists, focuses it.
 /// The window loads index.html with a flag so the frontend renders the preview UI.
 #[tauri::command]
@@ -95,7 +187,7 @@ async fn open_annotator(
         // initialization_script on the new page load.
         window.set_focus().map_err(|e| format!("{e}"))?;
         window
-            .eval(&format!("window.location.href = {:?};", parsed.as_str()))
+            .eval(format!("window.location.href = {:?};", parsed.as_str()))
             .map_err(|e| format!("{e}"))?;
         return Ok(());
     }
@@ -140,16 +232,24 @@ async fn annotator_send(
     url: String,
 ) -> Result<(), String> {
     require_window(&window, "annotator")?;
-    app.emit("annotator:capture", AnnotatorCapture { markdown, url })
-        .map_err(|e| format!("{e}"))?;
+    app.emit_to(
+        "main",
+        "annotator:capture",
+        AnnotatorCapture { markdown, url },
+    )
+    .map_err(|e| format!("{e}"))?;
 
     // Bring funny back to the foreground so the prefilled compose UI is
     // immediately visible.
     if let Some(main) = app.get_webview_window("main") {
-        let _ = main.set_focus();
+        if let Err(e) = main.set_focus() {
+            tracing::warn!("failed to focus main window: {e}");
+        }
     }
     if let Some(annotator) = app.get_webview_window("annotator") {
-        let _ = annotator.close();
+        if let Err(e) = annotator.close() {
+            tracing::warn!("failed to close annotator window: {e}");
+        }
     }
     Ok(())
 }
@@ -157,30 +257,341 @@ async fn annotator_send(
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     let app = tauri::Builder::default()
+        // Must be the first plugin: a second launch forwards its arguments
+        // here and exits before spawning another sidecar.
+        .plugin(tauri_plugin_single_instance::init(|app, argv, cwd| {
+            instance::on_second_instance(app, argv, cwd);
+        }))
+        .plugin(tauri_plugin_deep_link::init())
         .plugin(tauri_plugin_shell::init())
+        .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_updater::Builder::new().build())
+        .manage(sandbox::Sandbox::new())
+        .manage(app_menu::AppMenu::new())
+        .manage(context_menu::ContextMenus::new())
+        .manage(tmux::TmuxManager::new())
+        .manage(remotes::RemoteManager::new())
+        .manage(remote_runner::RunnerManager::new())
+        .manage(window_state::WindowStates::new())
         .manage(pty::PtyManager::new())
+        .manage(forge::ForgeWatcher::new())
+        .manage(watcher::FileWatcher::new())
+        .manage(file_index::FileIndex::new())
+        .manage(cancel::Cancellation::new())
+        .manage(completion::Completer::new())
+        .manage(problems::ProblemMatchers::new())
+        .manage(recent::RecentFiles::new())
+        .manage(notifications::Notifications::new())
+        .manage(tray::TrayState::new())
+        .manage(shortcut::SummonShortcut::new())
+        .manage(instance::OpenQueue::new())
+        .manage(events::EventRouter::new())
+        .manage(events::EventBus::new())
+        .manage(power::PowerManager::new())
+        .manage(updater::Updates::new())
+        .manage(crash::ServerLog::new())
+        .manage(settings::SettingsStore::new())
+        .manage(project_config::ProjectConfigs::new())
+        .manage(secrets::SecretStore::new())
+        .manage(onboarding::Onboarding::new())
+        .manage(policy::CommandPolicy::new())
+        .manage(db::Database::new())
+        .manage(egress::Egress::new())
+        .manage(plan_mode::PlanMode::new())
+        .manage(dry_run::DryRun::new())
+        .manage(file_locks::FileLocks::new())
+        .manage(worktree_pool::WorktreePool::new())
+        .manage(scratch::Scratches::new())
+        .manage(jobs::Jobs::new())
+        .manage(forward::PortForwards::new())
+        .manage(clipboard::ClipboardHistory::new())
+        .manage(intake::Intake::new())
+        .manage(automation::Automation::new())
+        .manage(plugins::Plugins::new())
+        .manage(shell_pool::ShellPool::new())
+        .menu(app_menu::build)
         .invoke_handler(tauri::generate_handler![
+            sandbox::set_project_roots,
             pty::pty_spawn,
             pty::pty_write,
             pty::pty_resize,
             pty::pty_kill,
+            suspend::pty_suspend,
+            suspend::pty_suspended,
+            suspend::pty_suspended_list,
+            suspend::pty_resume,
+            suspend::pty_suspended_delete,
+            pty::pty_benchmark,
+            file_drop::pty_quote_paths,
+            completion::shell_complete,
+            clipboard::clipboard_record,
+            clipboard::clipboard_history,
+            clipboard::clipboard_history_clear,
+            exec::exec_command,
+            local_agent::local_agent_run,
+            dev_servers::open_in_browser,
+            forward::forward_add,
+            forward::forward_remove,
+            forward::forward_list,
+            problems::problem_matchers,
+            tasks::tasks_list,
+            tasks::task_run,
+            tasks::task_run_terminal,
+            profiles::terminal_profiles_import,
+            profiles::profile_spawn,
+            tmux::tmux_sessions,
+            tmux::tmux_attach,
+            tmux::tmux_state,
+            tmux::tmux_send,
+            tmux::tmux_resize,
+            tmux::tmux_capture,
+            tmux::tmux_action,
+            tmux::tmux_detach,
+            remote::remote_transports,
+            remote::remote_spawn,
+            remotes::remote_hosts,
+            remotes::remote_host_save,
+            remotes::remote_host_delete,
+            remotes::remote_connect,
+            remotes::remote_disconnect,
+            remote_runner::remote_runner_start,
+            remote_runner::remote_runner_stop,
+            remote_runner::remote_runners,
+            verify::agent_verify,
+            verify::agent_verifications,
             open_preview,
             close_preview,
             open_annotator,
             close_annotator,
             annotator_send,
+            forge::forge_create_pr,
+            forge::forge_pr_status,
+            forge::forge_watch_pr,
+            forge::forge_unwatch_pr,
+            watcher::watch_path,
+            watcher::unwatch_path,
+            fs::read_file,
+            fs::write_file,
+            fs::stat,
+            fs::list_dir,
+            fs::fs_delete,
+            fs::fs_move,
+            fs::fs_copy,
+            file_index::tree_index,
+            file_index::tree_query,
+            file_index::tree_search,
+            file_index::tree_drop,
+            fuzzy::fuzzy_find_files,
+            search::search_project,
+            search::cancel_search,
+            cancel::cancel,
+            recent::recent_files,
+            recent::record_file_touches,
+            notifications::get_notification_rules,
+            notifications::set_notification_rules,
+            webhooks::notification_webhook_test,
+            cleanup::cleanup_preview,
+            cleanup::cleanup_run,
+            digest::digest_preview,
+            digest::digest_send,
+            disk::disk_space,
+            notifications::notify_milestone,
+            notifications::set_active_view,
+            notifications::notifications_test,
+            tray::update_tray_status,
+            badge::set_dock_badge,
+            badge::set_taskbar_progress,
+            shortcut::get_summon_shortcut,
+            shortcut::set_summon_shortcut,
+            instance::take_open_requests,
+            windows::open_project_window,
+            power::power_status,
+            updater::check_for_updates,
+            updater::install_update,
+            updater::get_update_channel,
+            updater::set_update_channel,
+            crash::list_crash_reports,
+            crash::get_crash_report,
+            crash::delete_crash_report,
+            crash::share_crash_report,
+            logging::set_log_level,
+            logging::get_log_level,
+            logging::get_recent_logs,
+            diagnostics::generate_diagnostics,
+            capabilities::system_capabilities,
+            credentials::credentials_check,
+            injection::injection_scan,
+            settings::settings_get,
+            settings::settings_set,
+            settings::settings_subscribe,
+            settings::settings_unsubscribe,
+            project_config::project_config_load,
+            project_config::project_config_get,
+            project_config::project_config_unload,
+            project_config::project_trust,
+            secrets::secret_set,
+            secrets::secret_get,
+            secrets::secret_list,
+            secrets::secret_delete,
+            onboarding::onboarding_state,
+            onboarding::onboarding_fix,
+            onboarding::onboarding_skip,
+            onboarding::onboarding_finish,
+            onboarding::onboarding_reset,
+            analytics::telemetry_record,
+            analytics::telemetry_preview,
+            analytics::telemetry_clear,
+            policy::policy_check,
+            policy::policy_set,
+            policy::policy_respond,
+            policy::policy_respond_bundle,
+            policy::policy_pending,
+            policy::pty_run_agent_command,
+            audit::audit_query,
+            audit::audit_log_dir,
+            egress::egress_status,
+            egress::egress_violations,
+            plan_mode::plan_mode_set,
+            plan_mode::plan_mode_list,
+            dry_run::dry_run_set,
+            dry_run::pending_changes,
+            dry_run::apply_pending_changes,
+            dry_run::discard_pending_changes,
+            file_locks::file_lock,
+            file_locks::file_unlock,
+            file_locks::file_locks,
+            worktree_pool::worktree_pool_warm,
+            worktree_pool::worktree_pool_status,
+            worktree_pool::worktree_pool_drain,
+            bootstrap::worktree_bootstrap,
+            scratch::scratch_create,
+            scratch::scratch_list,
+            scratch::scratch_diff,
+            scratch::scratch_apply,
+            patch::apply_patch,
+            patch::revert_hunk,
+            scratch::scratch_discard,
+            confine::shell_sandbox_support,
+            events::event_stats,
+            stats::backend_stats,
+            layouts::layout_list,
+            layouts::layout_save,
+            layouts::layout_delete,
+            layouts::layout_apply,
+            templates::template_list,
+            templates::template_save,
+            templates::template_delete,
+            templates::template_render,
+            templates::task_create_from_template,
+            issues::issue_get,
+            issues::task_create_from_issue,
+            intake::intake_status,
+            intake::intake_rotate_token,
+            intake::intake_list,
+            intake::intake_update,
+            automation::automation_status,
+            plugins::plugin_list,
+            plugins::plugin_invoke,
+            plugins::plugin_restart,
+            workspace::workspace_export,
+            workspace::workspace_import,
+            history::history_terminals,
+            history::history_run_save,
+            history::history_runs,
+            history::history_transcript_append,
+            history::history_transcript,
+            history::history_run_delete,
+            run_env::run_env,
+            run_env::run_env_diff,
+            jobs::job_list,
+            jobs::job_cancel,
+            context_menu::show_context_menu,
+            open::reveal_path,
+            open::open_in_editor,
+            open::preview_file,
+            app_menu::recent_project_opened,
+            timeline::timeline_query,
+            timeline::timeline_record,
+            report::stats_report,
         ])
+        .on_window_event(|window, event| match event {
+            tauri::WindowEvent::DragDrop(drop) => file_drop::on_drag_drop(window, drop),
+            tauri::WindowEvent::Focused(focused) => {
+                notifications::on_window_focus(window.app_handle(), window.label(), Some(*focused));
+                if *focused && window.label() == "main" {
+                    notifications::on_main_focused(window.app_handle());
+                }
+            }
+            tauri::WindowEvent::Moved(_) | tauri::WindowEvent::Resized(_) => {
+                window_state::track(window);
+            }
+            tauri::WindowEvent::CloseRequested { .. } => {
+                window_state::save(window.app_handle());
+            }
+            tauri::WindowEvent::Destroyed => {
+                notifications::on_window_focus(window.app_handle(), window.label(), None);
+                if let Some(pty_state) = window.try_state::<pty::PtyManager>() {
+                    pty::close_window(&pty_state, window.label());
+                }
+                if let Some(tmux_state) = window.try_state::<tmux::TmuxManager>() {
+                    tmux::close_window(&tmux_state, window.label());
+                }
+                // Stop watches, PR polls and project configs only the closed window
+                // listened to.
+                let router = window.state::<events::EventRouter>();
+                for topic in router.window_closed(window.label()) {
+                    if forge::stop_orphaned(&window.state::<forge::ForgeWatcher>(), &topic) {
+                        continue;
+                    }
+                    if project_config::stop_orphaned(window.app_handle(), &topic) {
+                        continue;
+                    }
+                    if let Some(id) = topic.strip_prefix(watcher::WATCH_TOPIC_PREFIX) {
+                        if let Err(e) =
+                            watcher::stop_watch(&window.state::<watcher::FileWatcher>(), id)
+                        {
+                            tracing::warn!("failed to stop watch {id}: {e}");
+                        }
+                    }
+                }
+            }
+            _ => {}
+        })
         .setup(|app| {
-            // Spawn the server sidecar on startup
-            let shell = app.shell();
-            let sidecar = shell
-                .sidecar("funny-server")
-                .expect("failed to create sidecar command");
+            logging::init(app.handle());
+            crash::install(app.handle());
+
+            // Spawn the server sidecar on startup, or the newer server staged
+            // by a sidecar update
+            let sidecar =
+                updater::server_command(app.handle()).expect("failed to create sidecar command");
 
-            let (_rx, child) = sidecar.spawn().expect("failed to spawn server sidecar");
+            let (rx, child) = sidecar.spawn().expect("failed to spawn server sidecar");
+            let pid = child.pid();
 
             // Store the child process so we can kill it on exit
             app.manage(ServerProcess(std::sync::Mutex::new(Some(child))));
+            crash::watch_server(app.handle(), pid, rx);
+
+            if let Some(main) = app.get_webview_window("main") {
+                window_state::restore(&main);
+            }
+            tray::init(app.handle())?;
+            context_menu::init(app.handle());
+            app_menu::init(app.handle());
+            shortcut::init(app.handle())?;
+            power::init(app.handle());
+            analytics::init(app.handle());
+            digest::init(app.handle());
+            cleanup::init(app.handle());
+            disk::init(app.handle());
+            events::init(app.handle());
+            instance::queue_launch_args(app.handle());
+            deeplink::init(app.handle());
+            intake::init(app.handle());
+            automation::init(app.handle());
+            remotes::init(app.handle());
+            plugins::init(app.handle());
 
             Ok(())
         })
@@ -189,16 +600,92 @@ pub fn run() {
 
     app.run(|app_handle, event| {
         if let tauri::RunEvent::Exit = event {
+            // Remember where the windows were
+            window_state::save(app_handle);
+
             // Kill all PTY instances
             if let Some(pty_state) = app_handle.try_state::<pty::PtyManager>() {
                 pty::kill_all(&pty_state);
             }
+            if let Some(pool_state) = app_handle.try_state::<shell_pool::ShellPool>() {
+                shell_pool::drain(&pool_state);
+            }
+
+            // Detach from tmux sessions, which keep running
+            if let Some(tmux_state) = app_handle.try_state::<tmux::TmuxManager>() {
+                tmux::detach_all(&tmux_state);
+            }
+
+            // Stop runners on remote hosts
+            if let Some(runner_state) = app_handle.try_state::<remote_runner::RunnerManager>() {
+                remote_runner::stop_all(&runner_state);
+            }
+
+            // Close pooled ssh connections
+            if let Some(remotes_state) = app_handle.try_state::<remotes::RemoteManager>() {
+                remotes::disconnect_all(&remotes_state);
+            }
+
+            // Close port forwards
+            if let Some(forward_state) = app_handle.try_state::<forward::PortForwards>() {
+                forward::close_all(&forward_state);
+            }
+
+            // Stop accepting posted tasks
+            if let Some(intake_state) = app_handle.try_state::<intake::Intake>() {
+                intake::stop(&intake_state);
+            }
+
+            // Close the automation socket
+            if let Some(automation_state) = app_handle.try_state::<automation::Automation>() {
+                automation::stop(app_handle, &automation_state);
+            }
+
+            // Shut plugins down
+            if let Some(plugin_state) = app_handle.try_state::<plugins::Plugins>() {
+                plugins::stop_all(&plugin_state);
+            }
+
+            // Stop background PR status polls
+            if let Some(forge_state) = app_handle.try_state::<forge::ForgeWatcher>() {
+                forge::unwatch_all(&forge_state);
+            }
+
+            // Stop file watchers
+            if let Some(watch_state) = app_handle.try_state::<watcher::FileWatcher>() {
+                watcher::unwatch_all(&watch_state);
+            }
+
+            // Cancel running content searches
+            if let Some(cancel_state) = app_handle.try_state::<cancel::Cancellation>() {
+                cancel::cancel_all(&cancel_state);
+            }
+
+            // Stop background jobs
+            if let Some(jobs_state) = app_handle.try_state::<jobs::Jobs>() {
+                jobs::cancel_all(&jobs_state);
+            }
+
+            // Unmount and remove scratch copies
+            if let Some(scratch_state) = app_handle.try_state::<scratch::Scratches>() {
+                scratch::discard_all(&scratch_state);
+            }
+
+            // Let the system sleep again
+            if let Some(power_state) = app_handle.try_state::<power::PowerManager>() {
+                power::release(&power_state);
+            }
+
+            // Persist file activity not yet written to disk
+            recent::flush(app_handle);
 
             // Kill the server process on app exit
             if let Some(state) = app_handle.try_state::<ServerProcess>() {
                 if let Ok(mut guard) = state.0.lock() {
                     if let Some(child) = guard.take() {
-                        let _ = child.kill();
+                        if let Err(e) = child.kill() {
+                            tracing::warn!("failed to kill server: {e}");
+                        }
                     }
                 }
             }
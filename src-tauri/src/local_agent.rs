@@ -0,0 +1,543 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+use crate::cancel::CancelToken;
+use crate::exec::{ExecEvent, ExecOptions};
+use crate::fs::WriteFileOptions;
+use crate::sandbox::Sandbox;
+
+/// Ollama's OpenAI-compatible API.
+const DEFAULT_ENDPOINT: &str = "http://127.0.0.1:11434/v1";
+
+const DEFAULT_MAX_STEPS: u32 = 40;
+const MAX_STEPS: u32 = 200;
+
+/// A local model on modest hardware can take minutes for one reply.
+const COMPLETION_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// Most of a file the model is shown at once.
+const READ_LIMIT: u64 = 256 * 1024;
+
+/// Tool output beyond this is cut from the front before the model sees it,
+/// keeping the end, where errors usually are.
+const MAX_TOOL_OUTPUT: usize = 16 * 1024;
+
+/// Commands the model runs are killed after this long.
+const COMMAND_TIMEOUT_MS: u64 = 5 * 60 * 1000;
+
+/// Where stored API keys may be sent. Kept in the settings file, so a
+/// request can't point a key at a server of its choosing.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LocalAgentSettings {
+    /// Secret name → endpoint hosts that get it, e.g.
+    /// `{"openrouter-api-key": ["openrouter.ai"]}`.
+    pub key_hosts: BTreeMap<String, Vec<String>>,
+}
+
+pub(crate) fn validate_settings(settings: &LocalAgentSettings) -> Result<(), String> {
+    for (name, hosts) in &settings.key_hosts {
+        let invalid = hosts.iter().find(|host| {
+            host.is_empty()
+                || !host
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-')
+        });
+        if let Some(host) = invalid {
+            return Err(format!(
+                "localAgent.keyHosts.{name}: {host:?} is not a host name"
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalAgentRequest {
+    /// The agent run, for plan mode and the audit log.
+    pub agent: String,
+    /// The project it works in; tool paths are relative to it.
+    pub root: String,
+    pub model: String,
+    /// Base URL of an OpenAI-compatible API, up to `/v1`. Ollama's by
+    /// default; llama.cpp's `llama-server` serves one too.
+    pub endpoint: Option<String>,
+    /// Name of a stored API key to send as the bearer token, for servers that
+    /// want one. Only sent to the hosts `localAgent.keyHosts` binds it to.
+    pub api_key_secret: Option<String>,
+    /// Replaces the built-in instructions.
+    pub system: Option<String>,
+    pub prompt: String,
+    /// Model replies before the run stops; 40 by default, at most 200.
+    pub max_steps: Option<u32>,
+    pub temperature: Option<f32>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "event",
+    content = "data"
+)]
+pub enum LocalAgentEvent {
+    /// Text the model replied with.
+    Message { content: String },
+    ToolCall {
+        id: String,
+        name: String,
+        arguments: Value,
+    },
+    /// What the model is given back for a tool call; `is_error` when the
+    /// tool failed or was refused.
+    ToolResult {
+        id: String,
+        output: String,
+        is_error: bool,
+    },
+    /// Output of a `run_command` call as it arrives, redacted.
+    Output(String),
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalAgentResult {
+    pub steps: u32,
+    /// The model ended the run itself, rather than hitting `max_steps`.
+    pub finished: bool,
+    /// Its last reply.
+    pub message: Option<String>,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+fn system_prompt(root: &Path) -> String {
+    format!(
+        "You are a coding agent working in the project at {}. Use the tools to \
+         look at and change files and to run commands; paths are relative to \
+         the project. Make the change asked for, check it where you can, and \
+         when you are done reply with a short summary and no tool call.",
+        root.display()
+    )
+}
+
+fn tools() -> Value {
+    let function = |name: &str, description: &str, properties: Value, required: &[&str]| {
+        json!({
+            "type": "function",
+            "function": {
+                "name": name,
+                "description": description,
+                "parameters": {
+                    "type": "object",
+                    "properties": properties,
+                    "required": required,
+                },
+            },
+        })
+    };
+    let path = json!({ "type": "string", "description": "Relative to the project" });
+    json!([
+        function(
+            "list_dir",
+            "List a directory: subdirectories end with /.",
+            json!({ "path": path }),
+            &["path"],
+        ),
+        function(
+            "read_file",
+            "Read a text file.",
+            json!({ "path": path }),
+            &["path"],
+        ),
+        function(
+            "write_file",
+            "Create or replace a file with the full new content.",
+            json!({ "path": path, "content": { "type": "string" } }),
+            &["path", "content"],
+        ),
+        function(
+            "run_command",
+            "Run a shell command in the project directory and get its exit code and output.",
+            json!({ "command": { "type": "string" } }),
+            &["command"],
+        ),
+    ])
+}
+
+/// Check the endpoint is an http(s) URL the network settings let the app
+/// reach, and return its host.
+fn check_endpoint(app: &AppHandle, endpoint: &str) -> Result<String, String> {
+    let parsed = url::Url::parse(endpoint).map_err(|e| format!("invalid endpoint: {e}"))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(format!("endpoint must be http or https: {endpoint}"));
+    }
+    let host = parsed.host_str().ok_or("endpoint has no host")?;
+    let network = crate::settings::current(app).network;
+    if network.restrict_egress && !crate::egress::host_allowed(host, &network.allowed_domains) {
+        return Err(format!("{host} is not in the allowed domains"));
+    }
+    Ok(host.to_string())
+}
+
+/// The stored API key `name`, if `localAgent.keyHosts` lets it go to `host`.
+fn api_key(app: &AppHandle, name: &str, host: &str) -> Result<String, String> {
+    let bound = crate::settings::current(app)
+        .local_agent
+        .key_hosts
+        .get(name)
+        .is_some_and(|hosts| hosts.iter().any(|h| h.eq_ignore_ascii_case(host)));
+    if !bound {
+        return Err(format!(
+            "{name} may not be sent to {host}; add the host to localAgent.keyHosts.{name}"
+        ));
+    }
+    crate::secrets::api_key(name)
+}
+
+fn api_error(e: ureq::Error) -> String {
+    match e {
+        ureq::Error::Status(code, resp) => format!(
+            "model server returned {code}: {}",
+            resp.into_string().unwrap_or_default()
+        ),
+        other => other.to_string(),
+    }
+}
+
+fn clip(mut text: String) -> String {
+    if text.len() <= MAX_TOOL_OUTPUT {
+        return text;
+    }
+    let mut start = text.len() - MAX_TOOL_OUTPUT;
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    text.replace_range(..start, "[earlier output omitted]\n");
+    text
+}
+
+/// A string argument of a tool call.
+fn argument<'a>(arguments: &'a Value, name: &str) -> Result<&'a str, String> {
+    arguments[name]
+        .as_str()
+        .ok_or_else(|| format!("missing string argument {name}"))
+}
+
+/// The platform shell running `command`.
+fn shell(command: &str) -> Vec<String> {
+    if cfg!(windows) {
+        vec!["cmd".to_string(), "/C".to_string(), command.to_string()]
+    } else {
+        vec!["sh".to_string(), "-c".to_string(), command.to_string()]
+    }
+}
+
+/// Everything a tool call needs from the run.
+struct Tools<'a> {
+    app: &'a AppHandle,
+    agent: &'a str,
+    root: &'a Path,
+    cancel: &'a CancelToken,
+}
+
+impl Tools<'_> {
+    fn call(
+        &self,
+        name: &str,
+        arguments: &Value,
+        send: &mut dyn FnMut(LocalAgentEvent),
+    ) -> Result<String, String> {
+        match name {
+            "list_dir" => self.list_dir(argument(arguments, "path")?),
+            "read_file" => self.read_file(argument(arguments, "path")?),
+            "write_file" => self.write_file(
+                argument(arguments, "path")?,
+                argument(arguments, "content")?,
+            ),
+            "run_command" => self.run_command(argument(arguments, "command")?, send),
+            other => Err(format!("no tool named {other}")),
+        }
+    }
+
+    fn list_dir(&self, path: &str) -> Result<String, String> {
+        let resolved = crate::sandbox::resolve_in_root(self.root, path)?;
+        let listing = crate::fs::list_local(&resolved)?;
+        let mut lines: Vec<String> = listing
+            .entries
+            .iter()
+            .map(|entry| match entry.kind.as_str() {
+                "directory" => format!("{}/", entry.name),
+                _ => entry.name.clone(),
+            })
+            .collect();
+        if listing.truncated {
+            lines.push("[more entries omitted]".to_string());
+        }
+        Ok(lines.join("\n"))
+    }
+
+    fn read_file(&self, path: &str) -> Result<String, String> {
+        let resolved = crate::sandbox::resolve_in_root(self.root, path)?;
+        let project = self.root.to_string_lossy();
+        let relative = resolved.strip_prefix(self.root).unwrap_or(&resolved);
+        // In dry-run mode the run sees its own writes.
+        if let Some(content) =
+            crate::dry_run::overlay(self.app, self.agent, &project, &relative.to_string_lossy())
+        {
+            return Ok(content);
+        }
+        let file = crate::fs::read_local(&resolved, READ_LIMIT)?;
+        if file.binary {
+            return Err(format!("{path} is a binary file"));
+        }
+        let findings = crate::injection::flag(self.app, Some(&project), path, &file.content);
+        // Flagged, not stripped: the model may write the file back whole.
+        let mut content = match crate::injection::warning(&findings) {
+            Some(warning) => format!("{warning}\n{}", file.content),
+            None => file.content,
+        };
+        if file.truncated {
+            content.push_str(&format!("\n[file truncated at {READ_LIMIT} bytes]"));
+        }
+        Ok(content)
+    }
+
+    fn write_file(&self, path: &str, content: &str) -> Result<String, String> {
+        let options = WriteFileOptions {
+            create_dirs: Some(true),
+            agent: Some(self.agent.to_string()),
+            ..Default::default()
+        };
+        let stat = crate::fs::write_local(
+            self.app,
+            &self.app.state::<Sandbox>(),
+            &self.root.to_string_lossy(),
+            path,
+            content,
+            &options,
+            self.cancel,
+        )?;
+        Ok(format!("wrote {} bytes to {path}", stat.size))
+    }
+
+    fn run_command(
+        &self,
+        command: &str,
+        send: &mut dyn FnMut(LocalAgentEvent),
+    ) -> Result<String, String> {
+        let project = self.root.to_string_lossy();
+        crate::plan_mode::check_command(self.app, Some(self.agent), Some(&project), command)?;
+        crate::policy::authorize(
+            self.app,
+            Some(&project),
+            Some(self.agent),
+            command,
+            self.cancel,
+        )?;
+        let options = ExecOptions {
+            timeout_ms: Some(COMMAND_TIMEOUT_MS),
+            ..Default::default()
+        };
+        let mut output = String::new();
+        let result = crate::exec::execute_blocking(
+            self.app,
+            self.root,
+            &shell(command),
+            &options,
+            Some(self.agent),
+            self.cancel,
+            &mut |event| {
+                if let ExecEvent::Stdout(text) | ExecEvent::Stderr(text) = event {
+                    output.push_str(&text);
+                    send(LocalAgentEvent::Output(text));
+                }
+            },
+        )?;
+        let status = match (result.timed_out, result.exit_code) {
+            (true, _) => "timed out".to_string(),
+            (false, Some(code)) => format!("exit code {code}"),
+            (false, None) => "killed".to_string(),
+        };
+        Ok(format!("{status}\n{}", clip(output)))
+    }
+}
+
+/// The chat completion loop: ask the model, run the tools it calls, hand
+/// back their results, until it replies without a tool call.
+fn run(
+    app: &AppHandle,
+    request: LocalAgentRequest,
+    root: &Path,
+    cancel: &CancelToken,
+    send: &mut dyn FnMut(LocalAgentEvent),
+) -> Result<LocalAgentResult, String> {
+    let endpoint = request
+        .endpoint
+        .as_deref()
+        .unwrap_or(DEFAULT_ENDPOINT)
+        .trim_end_matches('/');
+    let host = check_endpoint(app, endpoint)?;
+    let api_key = match &request.api_key_secret {
+        Some(name) => Some(api_key(app, name, &host)?),
+        None => None,
+    };
+    let http = ureq::AgentBuilder::new()
+        .timeout(COMPLETION_TIMEOUT)
+        .build();
+    let url = format!("{endpoint}/chat/completions");
+    let tools_spec = tools();
+    let tools = Tools {
+        app,
+        agent: &request.agent,
+        root,
+        cancel,
+    };
+    let max_steps = request
+        .max_steps
+        .unwrap_or(DEFAULT_MAX_STEPS)
+        .clamp(1, MAX_STEPS);
+    let mut messages = vec![
+        json!({
+            "role": "system",
+            "content": request.system.clone().unwrap_or_else(|| system_prompt(root)),
+        }),
+        json!({ "role": "user", "content": request.prompt }),
+    ];
+    let mut result = LocalAgentResult {
+        steps: 0,
+        finished: false,
+        message: None,
+        prompt_tokens: 0,
+        completion_tokens: 0,
+    };
+    tracing::info!(target: "agents", "local agent {} started with {}", request.agent, request.model);
+
+    while result.steps < max_steps {
+        cancel.check()?;
+        let mut body = json!({
+            "model": request.model,
+            "messages": messages,
+            "tools": tools_spec,
+            "stream": false,
+        });
+        if let Some(temperature) = request.temperature {
+            body["temperature"] = json!(temperature);
+        }
+        let mut post = http.post(&url);
+        if let Some(key) = &api_key {
+            post = post.set("Authorization", &format!("Bearer {key}"));
+        }
+        let response: Value = post
+            .send_json(body)
+            .map_err(api_error)?
+            .into_json()
+            .map_err(|e| e.to_string())?;
+        cancel.check()?;
+        result.steps += 1;
+        result.prompt_tokens += response["usage"]["prompt_tokens"].as_u64().unwrap_or(0);
+        result.completion_tokens += response["usage"]["completion_tokens"].as_u64().unwrap_or(0);
+
+        let message = &response["choices"][0]["message"];
+        if message.is_null() {
+            return Err(format!("model server sent no message: {response}"));
+        }
+        let content = message["content"].as_str().unwrap_or_default().to_string();
+        if !content.trim().is_empty() {
+            send(LocalAgentEvent::Message {
+                content: content.clone(),
+            });
+            result.message = Some(content.clone());
+        }
+
+        // Some servers leave out call ids or send the arguments as an
+        // object instead of a JSON string.
+        let calls: Vec<(String, String, Value)> = message["tool_calls"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .enumerate()
+            .map(|(i, call)| {
+                let id = call["id"]
+                    .as_str()
+                    .map_or_else(|| format!("call_{}_{i}", result.steps), str::to_string);
+                let name = call["function"]["name"].as_str().unwrap_or_default();
+                let arguments = match &call["function"]["arguments"] {
+                    Value::String(text) => serde_json::from_str(text).unwrap_or(Value::Null),
+                    other => other.clone(),
+                };
+                (id, name.to_string(), arguments)
+            })
+            .collect();
+        if calls.is_empty() {
+            result.finished = true;
+            break;
+        }
+        messages.push(json!({
+            "role": "assistant",
+            "content": content,
+            "tool_calls": calls.iter().map(|(id, name, arguments)| json!({
+                "id": id,
+                "type": "function",
+                "function": { "name": name, "arguments": arguments.to_string() },
+            })).collect::<Vec<_>>(),
+        }));
+        for (id, name, arguments) in calls {
+            send(LocalAgentEvent::ToolCall {
+                id: id.clone(),
+                name: name.clone(),
+                arguments: arguments.clone(),
+            });
+            let outcome = tools.call(&name, &arguments, send);
+            cancel.check()?;
+            let is_error = outcome.is_err();
+            let output = outcome.unwrap_or_else(|e| e);
+            send(LocalAgentEvent::ToolResult {
+                id: id.clone(),
+                output: output.clone(),
+                is_error,
+            });
+            messages.push(json!({ "role": "tool", "tool_call_id": id, "content": output }));
+        }
+    }
+    tracing::info!(
+        target: "agents",
+        "local agent {} stopped after {} steps",
+        request.agent,
+        result.steps
+    );
+    Ok(result)
+}
+
+/// Run an agent on a local model, with no external CLI: the model at an
+/// OpenAI-compatible endpoint (Ollama's by default) is given tools to list,
+/// read and write files in `root` and to run commands there, and called
+/// until it answers without using one. Writes and commands go through plan
+/// mode, the command policy and the audit log like any agent's. Progress
+/// streams over `on_event`. `cancel(request_id)` stops it once the model's
+/// current reply is in, and kills a running command at once.
+#[tauri::command]
+pub async fn local_agent_run(
+    window: WebviewWindow,
+    app: AppHandle,
+    request: LocalAgentRequest,
+    request_id: Option<String>,
+    on_event: Channel<LocalAgentEvent>,
+) -> Result<LocalAgentResult, String> {
+    crate::require_app_window(&window)?;
+    let root: PathBuf = app.state::<Sandbox>().check_dir(&request.root)?;
+    let guard = crate::cancel::register(&app, request_id.as_deref());
+    tauri::async_runtime::spawn_blocking(move || {
+        run(&app, request, &root, &guard.token(), &mut |event| {
+            let _ = on_event.send(event);
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,259 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Manager, State, WebviewWindow};
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::{Event, Level, Subscriber};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{filter, fmt, reload, Layer, Registry};
+
+use crate::redact::Redacting;
+
+/// Log lines kept in memory for `get_recent_logs`.
+const MAX_RECENT: usize = 2000;
+
+/// Default number of lines returned by `get_recent_logs`.
+const DEFAULT_LIMIT: usize = 500;
+
+/// Daily log files kept per component.
+const MAX_LOG_FILES: usize = 7;
+
+/// Overridable at startup with `FUNNY_LOG=debug` and so on.
+const DEFAULT_LEVEL: LevelFilter = LevelFilter::INFO;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Component {
+    App,
+    Pty,
+    /// Output of the server sidecar.
+    Server,
+    /// Agent status as seen by the desktop shell.
+    Agents,
+}
+
+impl Component {
+    const ALL: [Component; 4] = [
+        Component::App,
+        Component::Pty,
+        Component::Server,
+        Component::Agents,
+    ];
+
+    fn file_prefix(self) -> &'static str {
+        match self {
+            Component::App => "app",
+            Component::Pty => "pty",
+            Component::Server => "server",
+            Component::Agents => "agents",
+        }
+    }
+
+    /// The log an event goes to. `server` and `agents` are explicit
+    /// `target:`s; the pty module logs to `pty`; everything else, dependencies
+    /// included, goes to `app`.
+    fn of(target: &str) -> Self {
+        match target {
+            "server" => Component::Server,
+            "agents" => Component::Agents,
+            t if t.ends_with("::pty") => Component::Pty,
+            _ => Component::App,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub level: String,
+    pub component: Component,
+    pub target: String,
+    pub message: String,
+}
+
+/// Collects an event's message and fields into one line.
+#[derive(Default)]
+struct LineVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for LineVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{value:?}");
+        } else {
+            let _ = write!(self.fields, " {}={value:?}", field.name());
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.fields, " {}={value}", field.name());
+        }
+    }
+}
+
+/// Keeps the latest events in memory for the debug panel.
+struct RecentLayer {
+    recent: Arc<Mutex<VecDeque<LogEntry>>>,
+}
+
+impl<S: Subscriber> Layer<S> for RecentLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let meta = event.metadata();
+        let mut visitor = LineVisitor::default();
+        event.record(&mut visitor);
+        let entry = LogEntry {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            level: meta.level().to_string().to_lowercase(),
+            component: Component::of(meta.target()),
+            target: meta.target().to_string(),
+            message: crate::redact::redact(&(visitor.message + &visitor.fields)),
+        };
+        // try_lock: an event logged while the buffer is being read (or from
+        // a panicking reader) is dropped rather than deadlocking.
+        if let Ok(mut recent) = self.recent.try_lock() {
+            if recent.len() == MAX_RECENT {
+                recent.pop_front();
+            }
+            recent.push_back(entry);
+        }
+    }
+}
+
+/// Handle on the installed subscriber: the reloadable level and the
+/// in-memory buffer.
+pub struct Logging {
+    level: reload::Handle<LevelFilter, Registry>,
+    recent: Arc<Mutex<VecDeque<LogEntry>>>,
+}
+
+impl Logging {
+    pub(crate) fn level(&self) -> String {
+        self.level
+            .with_current(|current| current.to_string().to_lowercase())
+            .unwrap_or_default()
+    }
+}
+
+/// Install the global subscriber: stderr, one daily-rotated file per
+/// component in the app log directory, and the in-memory buffer, all
+/// redacted. Called first
+/// thing in `setup`; events from before that are lost.
+pub fn init(app: &AppHandle) {
+    let initial = std::env::var("FUNNY_LOG")
+        .ok()
+        .and_then(|level| LevelFilter::from_str(&level).ok())
+        .unwrap_or(DEFAULT_LEVEL);
+    let (level_layer, level) = reload::Layer::new(initial);
+    let recent = Arc::new(Mutex::new(VecDeque::new()));
+
+    let mut layers = vec![
+        fmt::layer().with_writer(Redacting(std::io::stderr)).boxed(),
+        RecentLayer {
+            recent: recent.clone(),
+        }
+        .boxed(),
+    ];
+    match app.path().app_log_dir() {
+        Ok(dir) => {
+            for component in Component::ALL {
+                let appender = match RollingFileAppender::builder()
+                    .rotation(Rotation::DAILY)
+                    .filename_prefix(component.file_prefix())
+                    .filename_suffix("log")
+                    .max_log_files(MAX_LOG_FILES)
+                    .build(&dir)
+                {
+                    Ok(appender) => appender,
+                    Err(e) => {
+                        eprintln!("failed to open {} log: {e}", component.file_prefix());
+                        continue;
+                    }
+                };
+                layers.push(
+                    fmt::layer()
+                        .with_writer(Redacting(appender))
+                        .with_ansi(false)
+                        .with_filter(filter::filter_fn(move |meta| {
+                            Component::of(meta.target()) == component
+                        }))
+                        .boxed(),
+                );
+            }
+        }
+        Err(e) => eprintln!("no log directory, logging to stderr only: {e}"),
+    }
+
+    if tracing_subscriber::registry()
+        .with(level_layer)
+        .with(layers)
+        .try_init()
+        .is_ok()
+    {
+        app.manage(Logging { level, recent });
+    }
+}
+
+/// Change the log level at runtime: `error`, `warn`, `info`, `debug`,
+/// `trace` or `off`.
+#[tauri::command]
+pub fn set_log_level(
+    window: WebviewWindow,
+    state: State<'_, Logging>,
+    level: String,
+) -> Result<(), String> {
+    crate::require_window(&window, "main")?;
+    let filter = LevelFilter::from_str(&level).map_err(|_| format!("unknown log level {level}"))?;
+    state
+        .level
+        .modify(|current| *current = filter)
+        .map_err(|e| e.to_string())?;
+    tracing::info!("log level set to {filter}");
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_log_level(window: WebviewWindow, state: State<'_, Logging>) -> Result<String, String> {
+    crate::require_window(&window, "main")?;
+    Ok(state.level())
+}
+
+/// The most recent log lines, oldest first, optionally only one component's
+/// and only at `level` or more severe.
+#[tauri::command]
+pub fn get_recent_logs(
+    window: WebviewWindow,
+    state: State<'_, Logging>,
+    component: Option<Component>,
+    level: Option<String>,
+    limit: Option<u32>,
+) -> Result<Vec<LogEntry>, String> {
+    crate::require_app_window(&window)?;
+    let min = level
+        .map(|l| Level::from_str(&l).map_err(|_| format!("unknown log level {l}")))
+        .transpose()?;
+    let recent = state.recent.lock().map_err(|e| e.to_string())?;
+    let mut entries: Vec<LogEntry> = recent
+        .iter()
+        .rev()
+        .filter(|e| component.is_none_or(|c| e.component == c))
+        .filter(|e| {
+            // Level ordering is by verbosity: ERROR < WARN < ... < TRACE.
+            min.is_none_or(|min| Level::from_str(&e.level).is_ok_and(|l| l <= min))
+        })
+        .take(limit.map(|l| l as usize).unwrap_or(DEFAULT_LIMIT))
+        .cloned()
+        .collect();
+    entries.reverse();
+    Ok(entries)
+}
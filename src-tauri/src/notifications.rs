@@ -0,0 +1,652 @@
+use chrono::{DateTime, Datelike, Local, Timelike};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State, WebviewWindow};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::webhooks::Webhook;
+
+/// The notification plugin can't report clicks on every desktop platform, so
+/// the main window gaining focus this soon after a notification is treated as
+/// clicking it.
+const ACTIVATION_WINDOW: Duration = Duration::from_secs(20);
+
+/// At most one bell notification per terminal in this interval; some tools
+/// ring on every keystroke they reject.
+const BELL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How often the screen is checked for being unlocked while notifications
+/// are held.
+const LOCK_POLL: Duration = Duration::from_secs(10);
+
+/// Titles listed in the notification summing up ones held while locked.
+const MAX_BATCH_TITLES: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Milestone {
+    AgentFinished,
+    AwaitingApproval,
+    TestsFailed,
+    TerminalBell,
+}
+
+/// Do-not-disturb window in local time, as `HH:MM`. `start` after `end`
+/// wraps past midnight (`22:00`–`08:00`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuietHours {
+    pub start: String,
+    pub end: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Day {
+    Mon,
+    Tue,
+    Wed,
+    Thu,
+    Fri,
+    Sat,
+    Sun,
+}
+
+impl Day {
+    const ALL: [Day; 7] = [
+        Day::Mon,
+        Day::Tue,
+        Day::Wed,
+        Day::Thu,
+        Day::Fri,
+        Day::Sat,
+        Day::Sun,
+    ];
+
+    fn of(date: &DateTime<Local>) -> Day {
+        Day::ALL[date.weekday().num_days_from_monday() as usize]
+    }
+
+    fn before(self) -> Day {
+        Day::ALL[(self as usize + 6) % 7]
+    }
+}
+
+/// A recurring do-not-disturb period: "no popups 21:00–08:00", "only
+/// approvals on weekends".
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Schedule {
+    /// Shown by `notifications_test`.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Days it starts on; every day when empty. A period past midnight
+    /// belongs to the day it starts: `fri` 22:00–08:00 covers Saturday
+    /// morning.
+    #[serde(default)]
+    pub days: Vec<Day>,
+    /// Both `HH:MM` in local time, or both unset for the whole day.
+    #[serde(default)]
+    pub start: Option<String>,
+    #[serde(default)]
+    pub end: Option<String>,
+    /// Milestones still shown during it; none when empty.
+    #[serde(default)]
+    pub allow: Vec<Milestone>,
+}
+
+impl Schedule {
+    fn on(&self, day: Day) -> bool {
+        self.days.is_empty() || self.days.contains(&day)
+    }
+
+    /// Whether the schedule is in effect at `minute_of_day` on `day`.
+    fn covers(&self, day: Day, minute_of_day: u32) -> bool {
+        let (start, end) = match (&self.start, &self.end) {
+            (Some(start), Some(end)) => match (parse_hhmm(start), parse_hhmm(end)) {
+                (Some(start), Some(end)) => (start, end),
+                _ => return false,
+            },
+            _ => return self.on(day),
+        };
+        if start <= end {
+            self.on(day) && (start..end).contains(&minute_of_day)
+        } else {
+            (self.on(day) && minute_of_day >= start)
+                || (self.on(day.before()) && minute_of_day < end)
+        }
+    }
+
+    fn label(&self, index: usize) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| format!("schedule {}", index + 1))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NotificationRules {
+    pub enabled: bool,
+    pub agent_finished: bool,
+    pub awaiting_approval: bool,
+    pub tests_failed: bool,
+    pub terminal_bell: bool,
+    pub quiet_hours: Option<QuietHours>,
+    /// Do-not-disturb periods besides `quiet_hours`.
+    pub schedules: Vec<Schedule>,
+    /// Skip notifications about the terminal or agent thread open in a
+    /// focused window.
+    pub suppress_focused: bool,
+    /// Hold notifications while the screen is locked and show them as one
+    /// once it is unlocked.
+    pub batch_while_locked: bool,
+    /// Slack and Discord channels milestones are also posted to. Quiet
+    /// hours and `enabled` only govern desktop notifications.
+    pub webhooks: Vec<Webhook>,
+}
+
+impl Default for NotificationRules {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            agent_finished: true,
+            awaiting_approval: true,
+            tests_failed: true,
+            terminal_bell: false,
+            quiet_hours: None,
+            schedules: Vec::new(),
+            suppress_focused: true,
+            batch_while_locked: true,
+            webhooks: Vec::new(),
+        }
+    }
+}
+
+impl NotificationRules {
+    fn allows(&self, milestone: Milestone) -> bool {
+        self.enabled
+            && match milestone {
+                Milestone::AgentFinished => self.agent_finished,
+                Milestone::AwaitingApproval => self.awaiting_approval,
+                Milestone::TestsFailed => self.tests_failed,
+                Milestone::TerminalBell => self.terminal_bell,
+            }
+    }
+
+    /// Why a `milestone` notification wouldn't be shown at `at`, if it
+    /// wouldn't: it is turned off, or quiet hours or a schedule hold it
+    /// back.
+    fn blocked(&self, milestone: Milestone, at: &DateTime<Local>) -> Option<String> {
+        if !self.enabled {
+            return Some("notifications are off".to_string());
+        }
+        if !self.allows(milestone) {
+            return Some("turned off for this milestone".to_string());
+        }
+        let minute_of_day = at.hour() * 60 + at.minute();
+        if let Some(quiet) = &self.quiet_hours {
+            if in_quiet_hours(quiet, minute_of_day) {
+                return Some("quiet hours".to_string());
+            }
+        }
+        let day = Day::of(at);
+        self.schedules
+            .iter()
+            .enumerate()
+            .find(|(_, schedule)| {
+                schedule.covers(day, minute_of_day) && !schedule.allow.contains(&milestone)
+            })
+            .map(|(i, schedule)| schedule.label(i))
+    }
+}
+
+pub(crate) fn parse_hhmm(value: &str) -> Option<u32> {
+    let (h, m) = value.trim().split_once(':')?;
+    let (h, m) = (h.parse::<u32>().ok()?, m.parse::<u32>().ok()?);
+    (h < 24 && m < 60).then_some(h * 60 + m)
+}
+
+fn in_quiet_hours(quiet: &QuietHours, minute_of_day: u32) -> bool {
+    let (start, end) = match (parse_hhmm(&quiet.start), parse_hhmm(&quiet.end)) {
+        (Some(start), Some(end)) => (start, end),
+        _ => return false,
+    };
+    if start <= end {
+        (start..end).contains(&minute_of_day)
+    } else {
+        minute_of_day >= start || minute_of_day < end
+    }
+}
+
+/// Where a click on the notification should take the user.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationTarget {
+    /// Client route, e.g. `/projects/<id>/threads/<id>`.
+    pub route: Option<String>,
+    pub thread_id: Option<String>,
+    pub terminal_id: Option<String>,
+    /// Project directory, for the deep link in webhook messages.
+    pub project: Option<String>,
+}
+
+/// What a window shows, as reported by its frontend.
+#[derive(Debug, Clone, Default)]
+struct WindowView {
+    focused: bool,
+    thread_id: Option<String>,
+    terminal_id: Option<String>,
+}
+
+impl WindowView {
+    fn shows(&self, target: &NotificationTarget) -> bool {
+        let same = |a: &Option<String>, b: &Option<String>| a.is_some() && a == b;
+        self.focused
+            && (same(&self.thread_id, &target.thread_id)
+                || same(&self.terminal_id, &target.terminal_id))
+    }
+}
+
+/// A notification held while the screen is locked.
+struct Held {
+    title: String,
+    body: String,
+    target: NotificationTarget,
+}
+
+pub struct Notifications {
+    /// Target of the last notification shown, until it is activated or
+    /// `ACTIVATION_WINDOW` passes.
+    pending: Mutex<Option<(Instant, NotificationTarget)>>,
+    last_bell: Mutex<HashMap<String, Instant>>,
+    /// By window label.
+    views: Mutex<HashMap<String, WindowView>>,
+    /// Held while the screen is locked, oldest first. Non-empty while a
+    /// thread waits for the unlock.
+    held: Mutex<Vec<Held>>,
+}
+
+impl Notifications {
+    pub fn new() -> Self {
+        Self {
+            pending: Mutex::new(None),
+            last_bell: Mutex::new(HashMap::new()),
+            views: Mutex::new(HashMap::new()),
+            held: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// The terminal that rang its bell most recently, if it is still open.
+    pub(crate) fn last_bell_terminal(&self, open: impl Fn(&str) -> bool) -> Option<String> {
+        let last_bell = self.last_bell.lock().ok()?;
+        last_bell
+            .iter()
+            .filter(|(id, _)| open(id))
+            .max_by_key(|(_, at)| **at)
+            .map(|(id, _)| id.clone())
+    }
+}
+
+pub(crate) fn validate_rules(rules: &NotificationRules) -> Result<(), String> {
+    if let Some(quiet) = &rules.quiet_hours {
+        if parse_hhmm(&quiet.start).is_none() || parse_hhmm(&quiet.end).is_none() {
+            return Err("quiet hours must be HH:MM".to_string());
+        }
+    }
+    for (i, schedule) in rules.schedules.iter().enumerate() {
+        let label = schedule.label(i);
+        match (&schedule.start, &schedule.end) {
+            (Some(start), Some(end)) => {
+                if parse_hhmm(start).is_none() || parse_hhmm(end).is_none() {
+                    return Err(format!("{label}: start and end must be HH:MM"));
+                }
+            }
+            (None, None) => {}
+            _ => return Err(format!("{label}: set both start and end, or neither")),
+        }
+    }
+    crate::webhooks::validate_webhooks(&rules.webhooks)
+}
+
+#[cfg(target_os = "macos")]
+fn screen_locked() -> bool {
+    std::process::Command::new("ioreg")
+        .args(["-n", "Root", "-d1"])
+        .output()
+        .map(|out| {
+            String::from_utf8_lossy(&out.stdout).contains("\"CGSSessionScreenIsLocked\"=Yes")
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn screen_locked() -> bool {
+    let session = std::env::var("XDG_SESSION_ID").unwrap_or_else(|_| "auto".to_string());
+    std::process::Command::new("loginctl")
+        .args(["show-session", &session, "--property=LockedHint", "--value"])
+        .output()
+        .map(|out| String::from_utf8_lossy(&out.stdout).trim() == "yes")
+        .unwrap_or(false)
+}
+
+#[cfg(windows)]
+fn screen_locked() -> bool {
+    use windows_sys::Win32::System::StationsAndDesktops::{
+        CloseDesktop, OpenInputDesktop, DESKTOP_SWITCHDESKTOP,
+    };
+
+    // The input desktop is the secure one, which can't be opened, while the
+    // session is locked.
+    let desktop = unsafe { OpenInputDesktop(0, 0, DESKTOP_SWITCHDESKTOP) };
+    if desktop.is_null() {
+        return true;
+    }
+    unsafe { CloseDesktop(desktop) };
+    false
+}
+
+fn show(
+    app: &AppHandle,
+    state: &Notifications,
+    title: &str,
+    body: &str,
+    target: NotificationTarget,
+) -> Result<(), String> {
+    app.notification()
+        .builder()
+        .title(title)
+        .body(body)
+        .show()
+        .map_err(|e| e.to_string())?;
+    *state.pending.lock().map_err(|e| e.to_string())? = Some((Instant::now(), target));
+    Ok(())
+}
+
+/// Wait for the screen to be unlocked, then show what was held as one
+/// notification.
+fn flush_when_unlocked(app: AppHandle) {
+    std::thread::spawn(move || {
+        while screen_locked() {
+            std::thread::sleep(LOCK_POLL);
+        }
+        let state = app.state::<Notifications>();
+        let mut held = match state.held.lock() {
+            Ok(mut held) => std::mem::take(&mut *held),
+            Err(_) => return,
+        };
+        let result = match held.len() {
+            0 => Ok(()),
+            1 => {
+                let only = held.remove(0);
+                show(&app, &state, &only.title, &only.body, only.target)
+            }
+            n => {
+                let mut titles: Vec<&str> = held
+                    .iter()
+                    .take(MAX_BATCH_TITLES)
+                    .map(|h| h.title.as_str())
+                    .collect();
+                if n > MAX_BATCH_TITLES {
+                    titles.push("…");
+                }
+                show(
+                    &app,
+                    &state,
+                    &format!("{n} notifications while you were away"),
+                    &titles.join("\n"),
+                    NotificationTarget::default(),
+                )
+            }
+        };
+        if let Err(e) = result {
+            tracing::warn!("failed to show held notifications: {e}");
+        }
+    });
+}
+
+/// Show an OS notification for `milestone` if the rules allow it right now.
+/// Returns whether it was shown; held ones count as shown.
+pub(crate) fn notify(
+    app: &AppHandle,
+    milestone: Milestone,
+    title: &str,
+    body: &str,
+    target: NotificationTarget,
+) -> Result<bool, String> {
+    let state = match app.try_state::<Notifications>() {
+        Some(state) => state,
+        None => return Ok(false),
+    };
+    let rules = crate::settings::current(app).notifications;
+    if rules.blocked(milestone, &Local::now()).is_some() {
+        return Ok(false);
+    }
+    if rules.suppress_focused {
+        let views = state.views.lock().map_err(|e| e.to_string())?;
+        if views.values().any(|view| view.shows(&target)) {
+            return Ok(false);
+        }
+    }
+    if rules.batch_while_locked && screen_locked() {
+        let mut held = state.held.lock().map_err(|e| e.to_string())?;
+        if held.is_empty() {
+            flush_when_unlocked(app.clone());
+        }
+        held.push(Held {
+            title: title.to_string(),
+            body: body.to_string(),
+            target,
+        });
+        return Ok(true);
+    }
+    show(app, &state, title, body, target)?;
+    Ok(true)
+}
+
+/// Notify about a bell (`\x07`) rung in terminal `id`, throttled per terminal.
+pub(crate) fn terminal_bell(app: &AppHandle, id: &str) {
+    let state = match app.try_state::<Notifications>() {
+        Some(state) => state,
+        None => return,
+    };
+    if let Ok(mut last_bell) = state.last_bell.lock() {
+        if last_bell
+            .get(id)
+            .map(|t| t.elapsed() < BELL_INTERVAL)
+            .unwrap_or(false)
+        {
+            return;
+        }
+        last_bell.insert(id.to_string(), Instant::now());
+    }
+    let target = NotificationTarget {
+        terminal_id: Some(id.to_string()),
+        ..Default::default()
+    };
+    let _ = notify(
+        app,
+        Milestone::TerminalBell,
+        "Terminal",
+        "A terminal needs your attention",
+        target,
+    );
+}
+
+/// Emit `notification:activated` with the pending target when the main window
+/// is focused shortly after a notification was shown.
+pub(crate) fn on_main_focused(app: &AppHandle) {
+    let state = match app.try_state::<Notifications>() {
+        Some(state) => state,
+        None => return,
+    };
+    let pending = match state.pending.lock() {
+        Ok(mut pending) => pending.take(),
+        Err(_) => return,
+    };
+    if let Some((shown_at, target)) = pending {
+        if shown_at.elapsed() <= ACTIVATION_WINDOW {
+            let _ = app.emit_to("main", "notification:activated", target);
+        }
+    }
+}
+
+/// Note that a window gained or lost focus, or closed.
+pub(crate) fn on_window_focus(app: &AppHandle, label: &str, focused: Option<bool>) {
+    let state = match app.try_state::<Notifications>() {
+        Some(state) => state,
+        None => return,
+    };
+    let mut views = match state.views.lock() {
+        Ok(views) => views,
+        Err(_) => return,
+    };
+    match focused {
+        Some(focused) => views.entry(label.to_string()).or_default().focused = focused,
+        None => {
+            views.remove(label);
+        }
+    }
+}
+
+/// Report the thread and terminal the calling window shows, whenever the
+/// active tab changes, so notifications about them are skipped while the
+/// window is focused.
+#[tauri::command]
+pub fn set_active_view(
+    window: WebviewWindow,
+    state: State<'_, Notifications>,
+    thread_id: Option<String>,
+    terminal_id: Option<String>,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    let mut views = state.views.lock().map_err(|e| e.to_string())?;
+    let view = views
+        .entry(window.label().to_string())
+        .or_insert_with(|| WindowView {
+            focused: window.is_focused().unwrap_or(false),
+            ..Default::default()
+        });
+    view.thread_id = thread_id;
+    view.terminal_id = terminal_id;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_notification_rules(
+    window: WebviewWindow,
+    app: AppHandle,
+) -> Result<NotificationRules, String> {
+    crate::require_window(&window, "main")?;
+    Ok(crate::settings::current(&app).notifications)
+}
+
+/// Replace the notification rules; shorthand for the `notifications` section
+/// of `settings_set`.
+#[tauri::command]
+pub fn set_notification_rules(
+    window: WebviewWindow,
+    app: AppHandle,
+    rules: NotificationRules,
+) -> Result<(), String> {
+    crate::require_window(&window, "main")?;
+    crate::settings::update(&app, |settings| {
+        settings.notifications = rules;
+        Ok(())
+    })
+    .map(|_| ())
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MilestoneCheck {
+    pub milestone: Milestone,
+    pub shown: bool,
+    /// What holds it back: "quiet hours", a schedule's name, ….
+    pub blocked_by: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationTest {
+    /// The time checked, RFC 3339.
+    pub at: String,
+    pub checks: Vec<MilestoneCheck>,
+    /// Whether a test notification was shown; only when checking now.
+    pub sent: bool,
+}
+
+/// Check the notification rules: which milestones would be shown at `at`
+/// (RFC 3339, default now) and what holds the others back. Checking now
+/// also shows a test notification if any milestone would be.
+#[tauri::command]
+pub fn notifications_test(
+    window: WebviewWindow,
+    app: AppHandle,
+    state: State<'_, Notifications>,
+    at: Option<String>,
+) -> Result<NotificationTest, String> {
+    crate::require_window(&window, "main")?;
+    let rules = crate::settings::current(&app).notifications;
+    let (time, now) = match &at {
+        Some(at) => (
+            DateTime::parse_from_rfc3339(at)
+                .map_err(|e| format!("invalid time {at}: {e}"))?
+                .with_timezone(&Local),
+            false,
+        ),
+        None => (Local::now(), true),
+    };
+    let checks: Vec<MilestoneCheck> = [
+        Milestone::AgentFinished,
+        Milestone::AwaitingApproval,
+        Milestone::TestsFailed,
+        Milestone::TerminalBell,
+    ]
+    .into_iter()
+    .map(|milestone| {
+        let blocked_by = rules.blocked(milestone, &time);
+        MilestoneCheck {
+            milestone,
+            shown: blocked_by.is_none(),
+            blocked_by,
+        }
+    })
+    .collect();
+    let sent = now && checks.iter().any(|c| c.shown);
+    if sent {
+        show(
+            &app,
+            &state,
+            "Test notification",
+            "Notifications from funny are working",
+            NotificationTarget::default(),
+        )?;
+    }
+    Ok(NotificationTest {
+        at: time.to_rfc3339(),
+        checks,
+        sent,
+    })
+}
+
+/// Report an agent milestone (finished, awaiting approval, tests failed).
+/// It is posted to the webhooks that want it; returns whether a desktop
+/// notification was shown.
+#[tauri::command]
+pub fn notify_milestone(
+    window: WebviewWindow,
+    app: AppHandle,
+    milestone: Milestone,
+    title: String,
+    body: String,
+    target: Option<NotificationTarget>,
+) -> Result<bool, String> {
+    crate::require_app_window(&window)?;
+    tracing::info!(target: "agents", "{milestone:?}: {title}");
+    let target = target.unwrap_or_default();
+    crate::webhooks::post(&app, milestone, &title, &body, &target);
+    notify(&app, milestone, &title, &body, target)
+}
@@ -0,0 +1,578 @@
+use portable_pty::{CommandBuilder, PtySize};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+use crate::diagnostics::{find_executable, probe, AGENT_COMMANDS};
+use crate::pty::Launch;
+
+const STATE_FILE: &str = "onboarding.json";
+
+/// Directory in app data the shell integration scripts are installed to.
+const SHELL_INTEGRATION_DIR: &str = "shell-integration";
+
+/// Marks the line `install_shell_integration` adds to a shell's rc file.
+const RC_MARKER: &str = "# funny shell integration";
+
+/// Prompt marks (OSC 133) and working-directory reports (OSC 7), only inside
+/// funny's terminals, which set `TERM_PROGRAM=funny`.
+const ZSH_INTEGRATION: &str = r#"[[ "$TERM_PROGRAM" == "funny" ]] || return
+__funny_precmd() { printf '\e]133;D;%s\a\e]7;file://%s%s\a\e]133;A\a' "$?" "$HOST" "$PWD"; }
+__funny_preexec() { printf '\e]133;C\a'; }
+autoload -Uz add-zsh-hook
+add-zsh-hook precmd __funny_precmd
+add-zsh-hook preexec __funny_preexec
+"#;
+
+const BASH_INTEGRATION: &str = r#"[[ "$TERM_PROGRAM" == "funny" ]] || return
+__funny_prompt() { printf '\e]133;D;%s\a\e]7;file://%s%s\a\e]133;A\a' "$?" "$HOSTNAME" "$PWD"; }
+PROMPT_COMMAND="__funny_prompt${PROMPT_COMMAND:+;$PROMPT_COMMAND}"
+"#;
+
+const FISH_INTEGRATION: &str = r#"test "$TERM_PROGRAM" = funny; or exit
+function __funny_prompt --on-event fish_prompt
+    printf '\e]133;D;%s\a\e]7;file://%s%s\a\e]133;A\a' $status (hostname) $PWD
+end
+function __funny_preexec --on-event fish_preexec
+    printf '\e]133;C\a'
+end
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Step {
+    Git,
+    AgentCli,
+    AgentLogin,
+    ShellIntegration,
+}
+
+impl Step {
+    const ALL: [Step; 4] = [
+        Step::Git,
+        Step::AgentCli,
+        Step::AgentLogin,
+        Step::ShellIntegration,
+    ];
+
+    /// Git is the one thing nothing works without.
+    fn skippable(self) -> bool {
+        self != Step::Git
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StepStatus {
+    Done,
+    Pending,
+    Skipped,
+    /// Not applicable on this platform or setup.
+    Unsupported,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StepState {
+    pub step: Step,
+    pub status: StepStatus,
+    pub detail: String,
+    /// Whether `onboarding_fix` can resolve this step.
+    pub fixable: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentCli {
+    pub name: String,
+    pub path: String,
+    /// `None` when the CLI keeps its login somewhere funny can't check.
+    pub logged_in: Option<bool>,
+    /// Whether `onboarding_fix` can open this CLI's login flow.
+    pub can_login: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OnboardingState {
+    pub steps: Vec<StepState>,
+    /// The first step that is neither done nor skipped; `None` once the
+    /// frontend can offer `onboarding_finish`.
+    pub current: Option<Step>,
+    pub agents: Vec<AgentCli>,
+    pub completed: bool,
+}
+
+/// What has been decided so far, persisted across launches.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct Progress {
+    completed: bool,
+    skipped: Vec<Step>,
+}
+
+pub struct Onboarding {
+    progress: Mutex<Option<Progress>>,
+}
+
+impl Onboarding {
+    pub fn new() -> Self {
+        Self {
+            progress: Mutex::new(None),
+        }
+    }
+}
+
+fn state_path(app: &AppHandle) -> Option<PathBuf> {
+    app.path()
+        .app_data_dir()
+        .ok()
+        .map(|dir| dir.join(STATE_FILE))
+}
+
+fn load_progress(app: &AppHandle) -> Progress {
+    state_path(app)
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_progress(app: &AppHandle, progress: &Progress) -> Result<(), String> {
+    let path = state_path(app).ok_or("no app data directory")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_vec_pretty(progress).map_err(|e| e.to_string())?;
+    std::fs::write(path, json).map_err(|e| e.to_string())
+}
+
+/// Change the persisted progress with `edit`.
+fn update_progress(
+    app: &AppHandle,
+    edit: impl FnOnce(&mut Progress) -> Result<(), String>,
+) -> Result<(), String> {
+    let state = app.state::<Onboarding>();
+    let mut guard = state.progress.lock().map_err(|e| e.to_string())?;
+    let mut progress = guard.get_or_insert_with(|| load_progress(app)).clone();
+    edit(&mut progress)?;
+    save_progress(app, &progress)?;
+    *guard = Some(progress);
+    Ok(())
+}
+
+pub(crate) fn home_dir() -> Option<PathBuf> {
+    std::env::var_os(if cfg!(windows) { "USERPROFILE" } else { "HOME" }).map(PathBuf::from)
+}
+
+/// Whether an API key for an agent is available, from the environment or the
+/// keychain.
+fn has_key(env: &str, secret: &str) -> bool {
+    std::env::var_os(env).is_some_and(|v| !v.is_empty()) || crate::secrets::get(secret).is_some()
+}
+
+/// Best-effort, offline check of where each CLI keeps its login.
+fn logged_in(name: &str, home: &Path) -> Option<bool> {
+    match name {
+        "claude" => {
+            let account = std::fs::read_to_string(home.join(".claude.json"))
+                .ok()
+                .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+                .is_some_and(|config| config.get("oauthAccount").is_some());
+            Some(account || has_key("ANTHROPIC_API_KEY", "anthropic-api-key"))
+        }
+        "codex" | "codex-acp" => {
+            let codex_home = std::env::var_os("CODEX_HOME")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| home.join(".codex"));
+            Some(
+                codex_home.join("auth.json").is_file()
+                    || has_key("OPENAI_API_KEY", "openai-api-key"),
+            )
+        }
+        "gemini" => Some(
+            home.join(".gemini/oauth_creds.json").is_file()
+                || has_key("GEMINI_API_KEY", "gemini-api-key"),
+        ),
+        "opencode" => Some(home.join(".local/share/opencode/auth.json").is_file()),
+        _ => None,
+    }
+}
+
+/// Arguments that start a CLI's interactive login, for those that have one.
+fn login_args(name: &str) -> Option<&'static [&'static str]> {
+    match name {
+        // Claude Code and Gemini walk through login on first start.
+        "claude" | "gemini" => Some(&[]),
+        "codex" => Some(&["login"]),
+        "cursor-agent" => Some(&["login"]),
+        "opencode" => Some(&["auth", "login"]),
+        _ => None,
+    }
+}
+
+fn detect_agents(home: Option<&Path>) -> Vec<AgentCli> {
+    AGENT_COMMANDS
+        .iter()
+        .filter_map(|name| {
+            let path = find_executable(name)?;
+            Some(AgentCli {
+                name: name.to_string(),
+                path: path.to_string_lossy().to_string(),
+                logged_in: home.and_then(|home| logged_in(name, home)),
+                can_login: login_args(name).is_some(),
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Shell {
+    Zsh,
+    Bash,
+    Fish,
+}
+
+impl Shell {
+    fn name(self) -> &'static str {
+        match self {
+            Shell::Zsh => "zsh",
+            Shell::Bash => "bash",
+            Shell::Fish => "fish",
+        }
+    }
+
+    fn script(self) -> &'static str {
+        match self {
+            Shell::Zsh => ZSH_INTEGRATION,
+            Shell::Bash => BASH_INTEGRATION,
+            Shell::Fish => FISH_INTEGRATION,
+        }
+    }
+
+    /// The file that loads the integration: an rc file a `source` line is
+    /// added to, or for fish a `conf.d` snippet that is the script itself.
+    fn rc_file(self, home: &Path) -> PathBuf {
+        match self {
+            Shell::Zsh => home.join(".zshrc"),
+            Shell::Bash => home.join(".bashrc"),
+            Shell::Fish => home.join(".config/fish/conf.d/funny.fish"),
+        }
+    }
+}
+
+/// The shell funny's terminals run, if it's one with an integration script.
+fn terminal_shell(app: &AppHandle) -> Option<Shell> {
+    let shell = crate::pty::default_shell(app);
+    let name = Path::new(&shell).file_stem()?.to_string_lossy().to_string();
+    match name.as_str() {
+        "zsh" => Some(Shell::Zsh),
+        "bash" => Some(Shell::Bash),
+        "fish" => Some(Shell::Fish),
+        _ => None,
+    }
+}
+
+fn shell_integration_installed(shell: Shell, home: &Path) -> bool {
+    let content = std::fs::read_to_string(shell.rc_file(home)).unwrap_or_default();
+    match shell {
+        Shell::Fish => content.contains("__funny_prompt"),
+        _ => content.contains(RC_MARKER),
+    }
+}
+
+/// Install the integration script for the terminal shell and hook it into
+/// the shell's startup. Installing twice leaves one hook.
+fn install_shell_integration(app: &AppHandle) -> Result<(), String> {
+    let shell = terminal_shell(app).ok_or("no shell integration for this shell")?;
+    let home = home_dir().ok_or("no home directory")?;
+    let rc = shell.rc_file(&home);
+    if let Some(dir) = rc.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+
+    if shell == Shell::Fish {
+        std::fs::write(&rc, shell.script()).map_err(|e| e.to_string())?;
+    } else {
+        let dir = app
+            .path()
+            .app_data_dir()
+            .map_err(|e| e.to_string())?
+            .join(SHELL_INTEGRATION_DIR);
+        std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        let script = dir.join(format!("funny.{}", shell.name()));
+        std::fs::write(&script, shell.script()).map_err(|e| e.to_string())?;
+
+        if !shell_integration_installed(shell, &home) {
+            let script = script.to_string_lossy();
+            let hook = format!("\n{RC_MARKER}\n[ -f \"{script}\" ] && . \"{script}\"\n");
+            let mut file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&rc)
+                .map_err(|e| e.to_string())?;
+            std::io::Write::write_all(&mut file, hook.as_bytes()).map_err(|e| e.to_string())?;
+        }
+    }
+    tracing::info!(
+        "installed {} shell integration in {}",
+        shell.name(),
+        rc.display()
+    );
+    Ok(())
+}
+
+fn check(app: &AppHandle) -> OnboardingState {
+    let progress = app
+        .state::<Onboarding>()
+        .progress
+        .lock()
+        .map(|mut p| p.get_or_insert_with(|| load_progress(app)).clone())
+        .unwrap_or_default();
+    let home = home_dir();
+    let agents = detect_agents(home.as_deref());
+
+    let mut steps: Vec<StepState> = Step::ALL
+        .iter()
+        .map(|&step| {
+            let (status, detail, fixable) = match step {
+                Step::Git => match find_executable("git") {
+                    Some(git) => {
+                        let version = probe(&git, &["--version"]);
+                        (
+                            StepStatus::Done,
+                            version.unwrap_or_else(|| "git".to_string()),
+                            false,
+                        )
+                    }
+                    None => {
+                        let hint = if cfg!(target_os = "macos") {
+                            "Install git with `xcode-select --install` or Homebrew."
+                        } else if cfg!(windows) {
+                            "Install Git for Windows from git-scm.com."
+                        } else {
+                            "Install git with your package manager."
+                        };
+                        (StepStatus::Pending, hint.to_string(), false)
+                    }
+                },
+                Step::AgentCli => match agents.len() {
+                    0 => (
+                        StepStatus::Pending,
+                        format!("Install one of: {}", AGENT_COMMANDS.join(", ")),
+                        false,
+                    ),
+                    _ => {
+                        let names: Vec<&str> = agents.iter().map(|a| a.name.as_str()).collect();
+                        (
+                            StepStatus::Done,
+                            format!("Found {}", names.join(", ")),
+                            false,
+                        )
+                    }
+                },
+                Step::AgentLogin => {
+                    let unknown: Vec<&str> = agents
+                        .iter()
+                        .filter(|a| a.logged_in.is_none())
+                        .map(|a| a.name.as_str())
+                        .collect();
+                    if let Some(agent) = agents.iter().find(|a| a.logged_in == Some(true)) {
+                        (
+                            StepStatus::Done,
+                            format!("Logged in to {}", agent.name),
+                            false,
+                        )
+                    } else if !unknown.is_empty() {
+                        // Nothing to go on, so don't hold the user up.
+                        let detail = format!("Can't check the login of {}", unknown.join(", "));
+                        (StepStatus::Done, detail, false)
+                    } else if agents.is_empty() {
+                        (
+                            StepStatus::Pending,
+                            "No agent CLI installed".to_string(),
+                            false,
+                        )
+                    } else {
+                        let fixable = agents.iter().any(|a| a.can_login);
+                        (
+                            StepStatus::Pending,
+                            "Not logged in to any agent".to_string(),
+                            fixable,
+                        )
+                    }
+                }
+                Step::ShellIntegration => match (terminal_shell(app), home.as_deref()) {
+                    (Some(shell), Some(home)) if shell_integration_installed(shell, home) => (
+                        StepStatus::Done,
+                        format!("Installed for {}", shell.name()),
+                        false,
+                    ),
+                    (Some(shell), Some(_)) => (
+                        StepStatus::Pending,
+                        format!("Not installed for {}", shell.name()),
+                        true,
+                    ),
+                    _ => (
+                        StepStatus::Unsupported,
+                        "No integration for this shell".to_string(),
+                        false,
+                    ),
+                },
+            };
+            StepState {
+                step,
+                status,
+                detail,
+                fixable,
+            }
+        })
+        .collect();
+
+    for state in &mut steps {
+        if state.status == StepStatus::Pending && progress.skipped.contains(&state.step) {
+            state.status = StepStatus::Skipped;
+        }
+    }
+    let current = steps
+        .iter()
+        .find(|s| s.status == StepStatus::Pending)
+        .map(|s| s.step);
+    OnboardingState {
+        steps,
+        current,
+        agents,
+        completed: progress.completed,
+    }
+}
+
+/// What `onboarding_fix` should do.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "action")]
+pub enum FixAction {
+    InstallShellIntegration,
+    /// Start `agent`'s login flow in terminal `terminal_id`, which the
+    /// frontend then attaches to like any other terminal.
+    #[serde(rename_all = "camelCase")]
+    Login {
+        agent: String,
+        terminal_id: String,
+        rows: u16,
+        cols: u16,
+    },
+}
+
+/// Check every prerequisite and report where onboarding stands. Checks run
+/// each call, so the frontend re-polls after the user fixes something by
+/// hand.
+#[tauri::command]
+pub async fn onboarding_state(
+    window: WebviewWindow,
+    app: AppHandle,
+) -> Result<OnboardingState, String> {
+    crate::require_window(&window, "main")?;
+    tauri::async_runtime::spawn_blocking(move || check(&app))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Run a fix-it action and return the updated state. A login finishes in its
+/// terminal; re-check with `onboarding_state` once it exits.
+#[tauri::command]
+pub async fn onboarding_fix(
+    window: WebviewWindow,
+    app: AppHandle,
+    action: FixAction,
+) -> Result<OnboardingState, String> {
+    crate::require_window(&window, "main")?;
+    tauri::async_runtime::spawn_blocking(move || {
+        match action {
+            FixAction::InstallShellIntegration => install_shell_integration(&app)?,
+            FixAction::Login {
+                agent,
+                terminal_id,
+                rows,
+                cols,
+            } => {
+                if !AGENT_COMMANDS.contains(&agent.as_str()) {
+                    return Err(format!("unknown agent {agent}"));
+                }
+                let args =
+                    login_args(&agent).ok_or_else(|| format!("{agent} has no login flow"))?;
+                let program =
+                    find_executable(&agent).ok_or_else(|| format!("{agent} is not installed"))?;
+                let mut command = CommandBuilder::new(program);
+                command.args(args);
+                // A fixed command in the home directory, not a frontend path,
+                // so this doesn't go through the sandbox.
+                let cwd = home_dir().ok_or("no home directory")?;
+                let size = PtySize {
+                    rows,
+                    cols,
+                    pixel_width: 0,
+                    pixel_height: 0,
+                };
+                crate::pty::spawn(
+                    &app,
+                    &window,
+                    terminal_id,
+                    &cwd,
+                    size,
+                    Launch::Command(command),
+                )?;
+                tracing::info!("started {agent} login");
+            }
+        }
+        Ok(check(&app))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Skip an optional step; it stays skipped until onboarding is reset.
+#[tauri::command]
+pub fn onboarding_skip(window: WebviewWindow, app: AppHandle, step: Step) -> Result<(), String> {
+    crate::require_window(&window, "main")?;
+    if !step.skippable() {
+        return Err(format!("{step:?} can't be skipped"));
+    }
+    update_progress(&app, |progress| {
+        if !progress.skipped.contains(&step) {
+            progress.skipped.push(step);
+        }
+        Ok(())
+    })
+}
+
+/// Mark onboarding done so it isn't shown on later launches. Fails while a
+/// required step is still pending.
+#[tauri::command]
+pub async fn onboarding_finish(window: WebviewWindow, app: AppHandle) -> Result<(), String> {
+    crate::require_window(&window, "main")?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = check(&app);
+        if let Some(step) = state
+            .steps
+            .iter()
+            .find(|s| s.status == StepStatus::Pending && !s.step.skippable())
+        {
+            return Err(format!("{:?} is still pending: {}", step.step, step.detail));
+        }
+        update_progress(&app, |progress| {
+            progress.completed = true;
+            Ok(())
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Start over: forget skipped steps and show onboarding again.
+#[tauri::command]
+pub fn onboarding_reset(window: WebviewWindow, app: AppHandle) -> Result<(), String> {
+    crate::require_window(&window, "main")?;
+    update_progress(&app, |progress| {
+        *progress = Progress::default();
+        Ok(())
+    })
+}
@@ -0,0 +1,230 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use tauri::{AppHandle, State, WebviewWindow};
+
+use crate::sandbox::Sandbox;
+
+// `code` is a batch file on Windows, which `Command` only finds by its full
+// name.
+#[cfg(windows)]
+const DEFAULT_EDITOR: &str = "code.cmd -g {file}:{line}:{column}";
+#[cfg(not(windows))]
+const DEFAULT_EDITOR: &str = "code -g {file}:{line}:{column}";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct EditorSettings {
+    /// How to open a file in the external editor. `{file}`, `{line}` and
+    /// `{column}` are replaced in each word; lines and columns count from 1.
+    /// Words with spaces can be put in double quotes. Run directly, not
+    /// through a shell.
+    pub command: String,
+}
+
+impl Default for EditorSettings {
+    fn default() -> Self {
+        Self {
+            command: DEFAULT_EDITOR.to_string(),
+        }
+    }
+}
+
+/// Split a command line into words, keeping double-quoted spans together.
+pub(crate) fn split_command(command: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quoted = false;
+    for c in command.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                word.get_or_insert_with(String::new);
+            }
+            c if c.is_whitespace() && !quoted => words.extend(word.take()),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quoted {
+        return Err("unterminated quote in editor command".to_string());
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+pub(crate) fn validate_settings(settings: &EditorSettings) -> Result<(), String> {
+    let words = split_command(&settings.command)?;
+    if words.is_empty() {
+        return Err("editor command must not be empty".to_string());
+    }
+    if !words.iter().any(|w| w.contains("{file}")) {
+        return Err("editor command must contain {file}".to_string());
+    }
+    Ok(())
+}
+
+/// Show `path` in the platform's file manager, selected.
+pub(crate) fn reveal(path: &Path) -> Result<(), String> {
+    let spawned = if cfg!(target_os = "macos") {
+        Command::new("open").arg("-R").arg(path).spawn()
+    } else if cfg!(windows) {
+        let mut select = std::ffi::OsString::from("/select,");
+        select.push(path);
+        Command::new("explorer").arg(select).spawn()
+    } else {
+        // File managers implementing the freedesktop interface can select
+        // the item; anything else just opens the folder it is in.
+        let selected = url::Url::from_file_path(path).ok().and_then(|uri| {
+            Command::new("dbus-send")
+                .args([
+                    "--session",
+                    "--print-reply",
+                    "--dest=org.freedesktop.FileManager1",
+                    "/org/freedesktop/FileManager1",
+                    "org.freedesktop.FileManager1.ShowItems",
+                ])
+                .arg(format!("array:string:{uri}"))
+                .arg("string:")
+                .stdout(Stdio::null())
+                .status()
+                .ok()
+                .filter(|status| status.success())
+        });
+        match selected {
+            Some(_) => return Ok(()),
+            None => Command::new("xdg-open")
+                .arg(path.parent().unwrap_or(path))
+                .spawn(),
+        }
+    };
+    spawned
+        .map(|_| ())
+        .map_err(|e| format!("failed to open the file manager: {e}"))
+}
+
+/// Show `path` in a preview: Quick Look on macOS, the GNOME previewer where
+/// it runs, and otherwise the file's default app.
+pub(crate) fn preview(path: &Path) -> Result<(), String> {
+    let spawned = if cfg!(target_os = "macos") {
+        // `qlmanage -p` stays up until the panel is closed and logs to
+        // stdout the whole time.
+        Command::new("qlmanage")
+            .arg("-p")
+            .arg(path)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+    } else if cfg!(windows) {
+        Command::new("explorer").arg(path).spawn()
+    } else {
+        let previewed = url::Url::from_file_path(path).ok().and_then(|uri| {
+            Command::new("dbus-send")
+                .args([
+                    "--session",
+                    "--print-reply",
+                    "--dest=org.gnome.NautilusPreviewer",
+                    "/org/gnome/NautilusPreviewer",
+                    "org.gnome.NautilusPreviewer.ShowFile",
+                ])
+                .arg(format!("string:{uri}"))
+                .args(["int32:0", "boolean:false"])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .ok()
+                .filter(|status| status.success())
+        });
+        match previewed {
+            Some(_) => return Ok(()),
+            None => Command::new("xdg-open").arg(path).spawn(),
+        }
+    };
+    spawned
+        .map(|_| ())
+        .map_err(|e| format!("failed to preview {}: {e}", path.display()))
+}
+
+/// Open `path` at `line` and `column` with the configured editor command.
+pub(crate) fn launch_editor(
+    app: &AppHandle,
+    path: &Path,
+    line: u32,
+    column: u32,
+) -> Result<(), String> {
+    let template = crate::settings::current(app).editor.command;
+    let file = path.to_string_lossy();
+    let mut words = split_command(&template)?.into_iter().map(|word| {
+        word.replace("{file}", &file)
+            .replace("{line}", &line.to_string())
+            .replace("{column}", &column.to_string())
+    });
+    let program = words.next().ok_or("editor command is empty")?;
+    Command::new(&program)
+        .args(words)
+        .current_dir(path.parent().unwrap_or(path))
+        .spawn()
+        .map(|_| ())
+        .map_err(|e| format!("failed to start editor {program}: {e}"))
+}
+
+/// Show `path` (absolute, or relative to `root`) selected in Finder,
+/// Explorer or the desktop's file manager.
+#[tauri::command]
+pub async fn reveal_path(
+    window: WebviewWindow,
+    sandbox: State<'_, Sandbox>,
+    root: String,
+    path: String,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    let resolved = sandbox.resolve(&root, &path)?;
+    if !resolved.exists() {
+        return Err(format!("{path} does not exist"));
+    }
+    tauri::async_runtime::spawn_blocking(move || reveal(&resolved))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Open `path` (absolute, or relative to `root`) in the external editor,
+/// at `line` and `column` when given — from a diff hunk or a problem
+/// matcher hit.
+#[tauri::command]
+pub async fn open_in_editor(
+    window: WebviewWindow,
+    app: AppHandle,
+    sandbox: State<'_, Sandbox>,
+    root: String,
+    path: String,
+    line: Option<u32>,
+    column: Option<u32>,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    let resolved = sandbox.resolve(&root, &path)?;
+    if !resolved.is_file() {
+        return Err(format!("{path} is not a file"));
+    }
+    let (line, column) = (line.unwrap_or(1).max(1), column.unwrap_or(1).max(1));
+    tauri::async_runtime::spawn_blocking(move || launch_editor(&app, &resolved, line, column))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Peek at `path` (absolute, or relative to `root`), like an image or PDF an
+/// agent wrote. See `preview` for what shows it.
+#[tauri::command]
+pub async fn preview_file(
+    window: WebviewWindow,
+    sandbox: State<'_, Sandbox>,
+    root: String,
+    path: String,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    let resolved = sandbox.resolve(&root, &path)?;
+    if !resolved.is_file() {
+        return Err(format!("{path} is not a file"));
+    }
+    tauri::async_runtime::spawn_blocking(move || preview(&resolved))
+        .await
+        .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,204 @@
+use serde::Serialize;
+use std::path::Path;
+use std::process::Command;
+use tauri::{AppHandle, State, WebviewWindow};
+
+use crate::audit::{self, AuditEvent, AuditKind, Outcome};
+use crate::forge::run_capture;
+use crate::recent::{self, TouchSource};
+use crate::sandbox::Sandbox;
+
+/// Tried in turn until one applies: exact, then ignoring whitespace with a
+/// single line of context required to match, for patches made against a
+/// file that has moved on a little since.
+const ATTEMPTS: &[&[&str]] = &[&[], &["--ignore-whitespace", "-C1"]];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchResult {
+    /// Files the patch touched, relative to the worktree.
+    pub files: Vec<String>,
+    /// Only applied with reduced context or whitespace ignored: worth a look.
+    pub fuzzy: bool,
+}
+
+/// `git` output as is; `run_capture` trims, which can eat a patch's last
+/// context line.
+fn git_output(root: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(root)
+        .output()
+        .map_err(|e| format!("failed to run git: {e}"))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Apply `patch` to the working tree of `root` with `git apply`, retrying
+/// with fuzz if it doesn't apply as is. Nothing is changed on failure.
+fn apply(root: &Path, patch: &str, reverse: bool) -> Result<PatchResult, String> {
+    let patch = if patch.ends_with('\n') {
+        patch.to_string()
+    } else {
+        format!("{patch}\n")
+    };
+    let files = run_capture(
+        Command::new("git")
+            .args(["apply", "--numstat", "--recount", "-"])
+            .current_dir(root),
+        Some(&patch),
+    )?
+    .lines()
+    .filter_map(|line| line.splitn(3, '\t').nth(2).map(str::to_string))
+    .collect();
+    let mut first_error = None;
+    for (i, extra) in ATTEMPTS.iter().enumerate() {
+        let mut command = Command::new("git");
+        command
+            .args(["apply", "--whitespace=nowarn", "--recount"])
+            .args(*extra)
+            .current_dir(root);
+        if reverse {
+            command.arg("-R");
+        }
+        match run_capture(command.arg("-"), Some(&patch)) {
+            Ok(_) => {
+                return Ok(PatchResult {
+                    files,
+                    fuzzy: i > 0,
+                })
+            }
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    Err(first_error.unwrap_or_else(|| "patch does not apply".to_string()))
+}
+
+/// The file header of a one-file `diff` and its `index`th hunk, on their
+/// own: a patch of just that hunk, as `--recount` takes it.
+fn hunk_patch(diff: &str, index: usize) -> Result<String, String> {
+    let mut header = Vec::new();
+    let mut hunks: Vec<Vec<&str>> = Vec::new();
+    // Kept with their line endings, `\r` included.
+    for line in diff.split_inclusive('\n') {
+        if line.starts_with("@@") {
+            hunks.push(vec![line]);
+        } else if let Some(hunk) = hunks.last_mut() {
+            hunk.push(line);
+        } else {
+            header.push(line);
+        }
+    }
+    let count = hunks.len();
+    let hunk = hunks
+        .get(index)
+        .ok_or_else(|| format!("no hunk {index}: the diff has {count}"))?;
+    Ok(header.concat() + &hunk.concat())
+}
+
+fn record(app: &AppHandle, root: &Path, result: &Result<PatchResult, String>, detail: String) {
+    audit::record(
+        app,
+        AuditEvent {
+            kind: AuditKind::FileWrite,
+            agent: None,
+            project: Some(&root.to_string_lossy()),
+            target: &root.to_string_lossy(),
+            outcome: if result.is_ok() {
+                Outcome::Ok
+            } else {
+                Outcome::Error
+            },
+            detail: Some(detail),
+        },
+    );
+    if let Ok(result) = result {
+        recent::record(app, root, &result.files, TouchSource::User);
+    }
+}
+
+/// Apply `patch`, a unified diff as git makes them, to the working tree of
+/// `worktree`: e.g. the hunks of an agent's changes the user picked. With
+/// `reverse`, take it back out instead.
+#[tauri::command]
+pub async fn apply_patch(
+    window: WebviewWindow,
+    app: AppHandle,
+    sandbox: State<'_, Sandbox>,
+    worktree: String,
+    patch: String,
+    reverse: Option<bool>,
+) -> Result<PatchResult, String> {
+    crate::require_window(&window, "main")?;
+    let root = sandbox.check_dir(&worktree)?;
+    let reverse = reverse.unwrap_or(false);
+    tauri::async_runtime::spawn_blocking(move || {
+        let result = apply(&root, &patch, reverse);
+        let verb = if reverse { "reverted" } else { "applied" };
+        record(&app, &root, &result, format!("{verb} a patch"));
+        result
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Revert hunk `hunk` (0-based, in diff order) of `file` in `worktree`:
+/// of its uncommitted changes against `HEAD`, or, given `commit`, of what
+/// that commit changed. The working tree is patched; nothing is committed.
+#[tauri::command]
+pub async fn revert_hunk(
+    window: WebviewWindow,
+    app: AppHandle,
+    sandbox: State<'_, Sandbox>,
+    worktree: String,
+    file: String,
+    hunk: usize,
+    commit: Option<String>,
+) -> Result<PatchResult, String> {
+    crate::require_window(&window, "main")?;
+    let root = sandbox.check_dir(&worktree)?;
+    let resolved = sandbox.resolve(&worktree, &file)?;
+    let relative = resolved
+        .strip_prefix(&root)
+        .map_err(|_| format!("{file} is not in {worktree}"))?
+        .to_string_lossy()
+        .replace('\\', "/");
+    if commit.as_deref().is_some_and(|c| c.starts_with('-')) {
+        return Err("invalid commit".to_string());
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        let flags = ["--no-color", "--no-ext-diff", "--no-renames"];
+        let diff = match &commit {
+            Some(commit) => {
+                let mut args = vec!["show", "--format="];
+                args.extend(flags);
+                args.extend([commit.as_str(), "--", &relative]);
+                git_output(&root, &args)?
+            }
+            None => {
+                let mut args = vec!["diff"];
+                args.extend(flags);
+                args.extend(["HEAD", "--", &relative]);
+                git_output(&root, &args)?
+            }
+        };
+        if diff.trim().is_empty() {
+            return Err(format!("{relative} has no changes to revert"));
+        }
+        let result = hunk_patch(&diff, hunk).and_then(|patch| apply(&root, &patch, true));
+        let from = commit.as_deref().unwrap_or("the working tree");
+        record(
+            &app,
+            &root,
+            &result,
+            format!("reverted hunk {hunk} of {relative} from {from}"),
+        );
+        result
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,264 @@
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State, WebviewWindow};
+
+use crate::audit::{self, AuditEvent, AuditKind, Outcome};
+
+/// Programs that only read. Anything else is refused in plan mode.
+const READ_ONLY_PROGRAMS: &[&str] = &[
+    "cat", "cd", "diff", "du", "echo", "file", "find", "grep", "head", "less", "ls", "pwd", "rg",
+    "stat", "tail", "tree", "wc", "which",
+];
+
+const READ_ONLY_GIT: &[&str] = &[
+    "blame",
+    "describe",
+    "diff",
+    "grep",
+    "log",
+    "ls-files",
+    "rev-parse",
+    "shortlog",
+    "show",
+    "status",
+];
+
+/// Options that make an otherwise read-only program write files or run
+/// commands, matched as prefixes: `--output` also covers `--output=x`,
+/// `-exec` covers `-execdir`, `-O` covers `-Ovim`.
+const WRITING_FLAGS: &[(&str, &[&str])] = &[
+    ("find", &["-delete", "-exec", "-ok", "-fprint", "-fls"]),
+    ("rg", &["--pre"]),
+    ("tree", &["-o"]),
+    (
+        "git",
+        &["--output", "-O", "--open-files-in-pager", "--ext-diff"],
+    ),
+];
+
+fn writing_flag(program: &str, word: &str) -> bool {
+    WRITING_FLAGS
+        .iter()
+        .filter(|(name, _)| *name == program)
+        .any(|(_, flags)| flags.iter().any(|flag| word.starts_with(flag)))
+}
+
+/// What a refused agent gets back, serialized as the command's error so it
+/// can be relayed verbatim.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Refusal {
+    /// Always `planMode`, to tell this apart from other errors.
+    pub code: &'static str,
+    pub agent: String,
+    /// `write`, `delete`, `move`, `copy`, `command` or `pullRequest`.
+    pub operation: String,
+    pub target: String,
+    pub message: String,
+}
+
+/// Agent runs in plan mode: the backend refuses every file write and
+/// mutating command they ask for, whatever the agent itself decides.
+pub struct PlanMode {
+    sessions: Mutex<HashSet<String>>,
+}
+
+impl PlanMode {
+    pub fn new() -> Self {
+        Self {
+            sessions: Mutex::new(HashSet::new()),
+        }
+    }
+
+    fn contains(&self, agent: &str) -> bool {
+        // Fail closed: a poisoned lock refuses rather than lets writes through.
+        self.sessions
+            .lock()
+            .map(|sessions| sessions.contains(agent))
+            .unwrap_or(true)
+    }
+}
+
+fn refuse(
+    app: &AppHandle,
+    agent: &str,
+    project: Option<&str>,
+    operation: &str,
+    target: &str,
+) -> String {
+    tracing::info!(target: "agents", "plan mode refused {operation} from {agent}: {target}");
+    audit::record(
+        app,
+        AuditEvent {
+            kind: AuditKind::Permission,
+            agent: Some(agent),
+            project,
+            target,
+            outcome: Outcome::Denied,
+            detail: Some(format!("plan mode: {operation}")),
+        },
+    );
+    let refusal = Refusal {
+        code: "planMode",
+        agent: agent.to_string(),
+        operation: operation.to_string(),
+        target: target.to_string(),
+        message: format!(
+            "{target}: this run is in plan mode and may not modify the repository; \
+             describe the change instead"
+        ),
+    };
+    serde_json::to_string(&refusal).unwrap_or(refusal.message)
+}
+
+/// Refuse a mutating `operation` on `target` if `agent` is in plan mode.
+/// User actions (no agent) always pass.
+pub(crate) fn check(
+    app: &AppHandle,
+    agent: Option<&str>,
+    project: Option<&str>,
+    operation: &str,
+    target: &str,
+) -> Result<(), String> {
+    match agent {
+        Some(agent) if app.state::<PlanMode>().contains(agent) => {
+            Err(refuse(app, agent, project, operation, target))
+        }
+        _ => Ok(()),
+    }
+}
+
+pub(crate) fn is_read_only(command: &str) -> bool {
+    if command.contains('>') {
+        return false;
+    }
+    crate::policy::segments(command).iter().all(|segment| {
+        let words: Vec<&str> = segment.split_whitespace().collect();
+        if let Some((program, args)) = words.split_first() {
+            if args.iter().any(|word| writing_flag(program, word)) {
+                return false;
+            }
+        }
+        match words.as_slice() {
+            ["git", sub, ..] => READ_ONLY_GIT.contains(sub),
+            [program, ..] => READ_ONLY_PROGRAMS.contains(program),
+            [] => true,
+        }
+    })
+}
+
+/// Refuse a shell command from an agent in plan mode unless every part of it
+/// only reads.
+pub(crate) fn check_command(
+    app: &AppHandle,
+    agent: Option<&str>,
+    project: Option<&str>,
+    command: &str,
+) -> Result<(), String> {
+    if is_read_only(command) {
+        return Ok(());
+    }
+    check(app, agent, project, "command", command)
+}
+
+/// Put an agent run in or out of plan mode.
+#[tauri::command]
+pub fn plan_mode_set(
+    window: WebviewWindow,
+    app: AppHandle,
+    state: State<'_, PlanMode>,
+    agent: String,
+    enabled: bool,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    let mut sessions = state.sessions.lock().map_err(|e| e.to_string())?;
+    let changed = if enabled {
+        sessions.insert(agent.clone())
+    } else {
+        sessions.remove(&agent)
+    };
+    drop(sessions);
+    if changed {
+        tracing::info!(target: "agents", "plan mode {} for {agent}", if enabled { "on" } else { "off" });
+        if let Err(e) = app.emit_to(
+            "main",
+            "plan-mode:changed",
+            serde_json::json!({ "agent": agent, "enabled": enabled }),
+        ) {
+            tracing::debug!("plan-mode:changed not delivered: {e}");
+        }
+    }
+    Ok(())
+}
+
+/// Agent runs currently in plan mode.
+#[tauri::command]
+pub fn plan_mode_list(
+    window: WebviewWindow,
+    state: State<'_, PlanMode>,
+) -> Result<Vec<String>, String> {
+    crate::require_app_window(&window)?;
+    let sessions = state.sessions.lock().map_err(|e| e.to_string())?;
+    let mut agents: Vec<String> = sessions.iter().cloned().collect();
+    agents.sort();
+    Ok(agents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reading_commands_are_read_only() {
+        for command in [
+            "ls -la",
+            "cat src/main.rs | grep fn",
+            "git status && git diff --stat",
+            "git log -p -- src",
+            "find . -name '*.rs' -o -name '*.toml'",
+            "rg -n TODO src",
+            "tree -L 2",
+        ] {
+            assert!(is_read_only(command), "{command}");
+        }
+    }
+
+    #[test]
+    fn redirects_and_other_programs_are_not() {
+        for command in [
+            "echo hi > x",
+            "cat a >> b",
+            "ls && touch x",
+            "cargo build",
+            "git commit -m x",
+            "git -c core.pager=sh log",
+            "echo $(rm -rf x)",
+        ] {
+            assert!(!is_read_only(command), "{command}");
+        }
+    }
+
+    #[test]
+    fn flags_that_write_or_run_commands_are_not() {
+        for command in [
+            "find . -delete",
+            "find . -exec rm {} ;",
+            "find . -execdir rm {} +",
+            "find . -okdir rm {} ;",
+            "find . -fprint out",
+            "find . -fprintf out %p",
+            "find . -fls out",
+            "git diff --output=x",
+            "git log --output x",
+            "git grep -Ovim TODO",
+            "git grep --open-files-in-pager=sh TODO",
+            "git diff --ext-diff",
+            "rg --pre ./run.sh TODO",
+            "rg --pre=./run.sh TODO",
+            "tree -o out",
+        ] {
+            assert!(!is_read_only(command), "{command}");
+        }
+    }
+}
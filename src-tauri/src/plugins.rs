@@ -0,0 +1,685 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State, WebviewWindow};
+
+/// Bumped when the messages between the app and plugins change.
+const PROTOCOL_VERSION: u32 = 1;
+const MANIFEST_FILE: &str = "plugin.json";
+const INIT_TIMEOUT: Duration = Duration::from_secs(10);
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long plugins get to exit after `shutdown` before they are killed.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(1);
+/// Messages waiting for a plugin to read them; events past this are dropped.
+const OUTBOX_SIZE: usize = 256;
+/// Lines from a plugin longer than this end it.
+const MAX_MESSAGE_BYTES: u64 = 1024 * 1024;
+/// Terminal lines are matched against plugin patterns up to this many
+/// characters.
+const MAX_LINE: usize = 4096;
+
+/// Which of the installed plugins run. Plugins are programs with the
+/// user's permissions, so none runs until it is listed here.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PluginSettings {
+    /// Plugin directory names under `<app data>/plugins`.
+    pub enabled: Vec<String>,
+}
+
+fn check_name(name: &str) -> Result<(), String> {
+    static NAME: OnceLock<Regex> = OnceLock::new();
+    let valid =
+        NAME.get_or_init(|| Regex::new(r"^[A-Za-z0-9][A-Za-z0-9_.-]*$").expect("valid pattern"));
+    if valid.is_match(name) {
+        Ok(())
+    } else {
+        Err(format!("{name:?} is not a plugin name"))
+    }
+}
+
+pub(crate) fn validate_settings(settings: &PluginSettings) -> Result<(), String> {
+    let mut seen = HashSet::new();
+    for name in &settings.enabled {
+        check_name(name)?;
+        if !seen.insert(name) {
+            return Err(format!("plugin {name} is enabled twice"));
+        }
+    }
+    Ok(())
+}
+
+/// `plugin.json` in the plugin's directory. `command[0]` is looked up on
+/// `PATH` unless it is a path, which is relative to the directory.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    version: Option<String>,
+    description: Option<String>,
+    command: Vec<String>,
+}
+
+/// A command a plugin offers, e.g. for the command palette.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginCommand {
+    pub name: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PtyPattern {
+    name: String,
+    pattern: String,
+}
+
+/// What a plugin answers `initialize` with.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct Registration {
+    commands: Vec<PluginCommand>,
+    /// `agent.finished`, `terminal.exited`, `task.received`.
+    events: Vec<String>,
+    /// Terminal lines matching one of these are sent as `pty.match`.
+    pty_patterns: Vec<PtyPattern>,
+}
+
+type Pending = Mutex<HashMap<u64, Sender<Result<Value, String>>>>;
+
+/// A running plugin.
+struct Process {
+    child: Mutex<Child>,
+    outbox: SyncSender<Value>,
+    pending: Arc<Pending>,
+    next_id: AtomicU64,
+    commands: Vec<PluginCommand>,
+    events: Vec<String>,
+    patterns: Vec<(String, Regex)>,
+}
+
+impl Process {
+    fn send(&self, message: Value) -> Result<(), String> {
+        self.outbox.try_send(message).map_err(|e| match e {
+            TrySendError::Full(_) => "the plugin is not keeping up".to_string(),
+            TrySendError::Disconnected(_) => "the plugin has exited".to_string(),
+        })
+    }
+
+    fn notify(&self, method: &str, params: Value) -> Result<(), String> {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }))
+    }
+
+    fn request(&self, method: &str, params: Value, timeout: Duration) -> Result<Value, String> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = mpsc::channel();
+        self.pending
+            .lock()
+            .map_err(|e| e.to_string())?
+            .insert(id, sender);
+        let result = self
+            .send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))
+            .and_then(|_| {
+                receiver.recv_timeout(timeout).map_err(|e| match e {
+                    mpsc::RecvTimeoutError::Timeout => format!("no answer to {method} in time"),
+                    mpsc::RecvTimeoutError::Disconnected => "the plugin has exited".to_string(),
+                })?
+            });
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.remove(&id);
+        }
+        result
+    }
+
+    fn kill(&self) {
+        if let Ok(mut child) = self.child.lock() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+
+    fn exited(&self) -> bool {
+        self.child
+            .lock()
+            .map(|mut child| !matches!(child.try_wait(), Ok(None)))
+            .unwrap_or(true)
+    }
+}
+
+/// Running plugins, and why the others aren't.
+pub struct Plugins {
+    running: Mutex<HashMap<String, Arc<Process>>>,
+    errors: Mutex<HashMap<String, String>>,
+    /// Whether any running plugin matches terminal output, so terminals
+    /// skip the work when none does.
+    watching: AtomicBool,
+    /// Held while plugins are started or stopped.
+    changing: Mutex<()>,
+}
+
+impl Plugins {
+    pub fn new() -> Self {
+        Self {
+            running: Mutex::new(HashMap::new()),
+            errors: Mutex::new(HashMap::new()),
+            watching: AtomicBool::new(false),
+            changing: Mutex::new(()),
+        }
+    }
+
+    pub(crate) fn running_count(&self) -> usize {
+        self.running.lock().map(|r| r.len()).unwrap_or(0)
+    }
+
+    fn process(&self, name: &str) -> Option<Arc<Process>> {
+        self.running.lock().ok()?.get(name).cloned()
+    }
+
+    fn set_error(&self, name: &str, error: Option<String>) {
+        if let Ok(mut errors) = self.errors.lock() {
+            match error {
+                Some(error) => errors.insert(name.to_string(), error),
+                None => errors.remove(name),
+            };
+        }
+    }
+
+    fn update_watching(&self, running: &HashMap<String, Arc<Process>>) {
+        let watching = running.values().any(|p| !p.patterns.is_empty());
+        self.watching.store(watching, Ordering::Relaxed);
+    }
+}
+
+fn plugins_dir(app: &AppHandle) -> Option<PathBuf> {
+    app.path()
+        .app_data_dir()
+        .ok()
+        .map(|dir| dir.join("plugins"))
+}
+
+fn read_manifest(dir: &Path) -> Result<Manifest, String> {
+    let path = dir.join(MANIFEST_FILE);
+    let content = std::fs::read_to_string(&path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let manifest: Manifest =
+        serde_json::from_str(&content).map_err(|e| format!("{}: {e}", path.display()))?;
+    if manifest.command.is_empty() {
+        return Err(format!("{}: command is empty", path.display()));
+    }
+    Ok(manifest)
+}
+
+/// Handle what a plugin writes: answers to our requests, and its `log` and
+/// `message` notifications.
+fn read_messages(app: AppHandle, name: String, pending: Arc<Pending>, stdout: impl Read) {
+    let mut reader = BufReader::new(stdout);
+    loop {
+        let mut line = String::new();
+        match (&mut reader).take(MAX_MESSAGE_BYTES).read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) if !line.ends_with('\n') && line.len() as u64 >= MAX_MESSAGE_BYTES => {
+                tracing::warn!(target: "plugins", "{name}: message too long");
+                break;
+            }
+            Ok(_) => {}
+        }
+        let message: Value = match serde_json::from_str(&line) {
+            Ok(message) => message,
+            Err(_) if line.trim().is_empty() => continue,
+            Err(e) => {
+                tracing::debug!(target: "plugins", "{name}: not JSON: {e}");
+                continue;
+            }
+        };
+        if let Some(id) = message.get("id").and_then(Value::as_u64) {
+            let result = match message.get("error") {
+                Some(error) => Err(error["message"]
+                    .as_str()
+                    .unwrap_or("the plugin reported an error")
+                    .to_string()),
+                None => Ok(message.get("result").cloned().unwrap_or(Value::Null)),
+            };
+            let sender = pending.lock().ok().and_then(|mut p| p.remove(&id));
+            if let Some(sender) = sender {
+                let _ = sender.send(result);
+            }
+            continue;
+        }
+        let params = &message["params"];
+        match message["method"].as_str() {
+            Some("log") => {
+                let text = params["message"].as_str().unwrap_or_default();
+                match params["level"].as_str() {
+                    Some("error") => tracing::error!(target: "plugins", "{name}: {text}"),
+                    Some("warn") => tracing::warn!(target: "plugins", "{name}: {text}"),
+                    Some("debug") => tracing::debug!(target: "plugins", "{name}: {text}"),
+                    _ => tracing::info!(target: "plugins", "{name}: {text}"),
+                }
+            }
+            Some("message") => {
+                let payload = json!({
+                    "plugin": name,
+                    "text": params["text"].as_str().unwrap_or_default(),
+                    "level": params["level"].as_str().unwrap_or("info"),
+                });
+                if let Err(e) = app.emit_to("main", "plugin:message", payload) {
+                    tracing::debug!("plugin:message not delivered: {e}");
+                }
+            }
+            other => tracing::debug!(target: "plugins", "{name}: unknown notification {other:?}"),
+        }
+    }
+    // Dropping the senders fails the requests still waiting.
+    if let Ok(mut pending) = pending.lock() {
+        pending.clear();
+    }
+    exited(&app, &name, &pending);
+}
+
+fn write_messages(mut stdin: ChildStdin, outbox: Receiver<Value>) {
+    for message in outbox {
+        let sent = writeln!(stdin, "{message}").and_then(|_| stdin.flush());
+        if sent.is_err() {
+            return;
+        }
+    }
+}
+
+/// Forget a plugin whose process ended on its own. `pending` tells it from a
+/// restarted one of the same name.
+fn exited(app: &AppHandle, name: &str, pending: &Arc<Pending>) {
+    let state = match app.try_state::<Plugins>() {
+        Some(state) => state,
+        None => return,
+    };
+    let mut running = match state.running.lock() {
+        Ok(running) => running,
+        Err(_) => return,
+    };
+    let ended = running
+        .get(name)
+        .is_some_and(|p| Arc::ptr_eq(&p.pending, pending));
+    if ended {
+        if let Some(process) = running.remove(name) {
+            process.kill();
+        }
+        state.update_watching(&running);
+        drop(running);
+        tracing::warn!(target: "plugins", "{name} exited");
+        state.set_error(name, Some("exited".to_string()));
+    }
+}
+
+/// Start plugin `name` and wait for it to register.
+fn spawn(app: &AppHandle, name: &str) -> Result<Process, String> {
+    check_name(name)?;
+    let dir = plugins_dir(app).ok_or("no app data directory")?.join(name);
+    let manifest = read_manifest(&dir)?;
+    let (program, args) = manifest.command.split_first().ok_or("command is empty")?;
+    let program = if program.contains('/') || program.contains('\\') {
+        dir.join(program)
+    } else {
+        PathBuf::from(program)
+    };
+    let mut child = Command::new(&program)
+        .args(args)
+        .current_dir(&dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("{}: {e}", program.display()))?;
+    let stdin = child.stdin.take().ok_or("no stdin")?;
+    let stdout = child.stdout.take().ok_or("no stdout")?;
+    let stderr = child.stderr.take().ok_or("no stderr")?;
+
+    let pending: Arc<Pending> = Arc::new(Mutex::new(HashMap::new()));
+    let (outbox, queue) = mpsc::sync_channel(OUTBOX_SIZE);
+    std::thread::spawn(move || write_messages(stdin, queue));
+    let (reader_app, reader_name, reader_pending) =
+        (app.clone(), name.to_string(), pending.clone());
+    std::thread::spawn(move || read_messages(reader_app, reader_name, reader_pending, stdout));
+    let stderr_name = name.to_string();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+            tracing::info!(target: "plugins", "{stderr_name}: {line}");
+        }
+    });
+
+    let mut process = Process {
+        child: Mutex::new(child),
+        outbox,
+        pending,
+        next_id: AtomicU64::new(1),
+        commands: Vec::new(),
+        events: Vec::new(),
+        patterns: Vec::new(),
+    };
+    let registered = process
+        .request(
+            "initialize",
+            json!({
+                "appVersion": app.package_info().version.to_string(),
+                "protocolVersion": PROTOCOL_VERSION,
+            }),
+            INIT_TIMEOUT,
+        )
+        .and_then(|result| {
+            serde_json::from_value::<Registration>(result).map_err(|e| e.to_string())
+        })
+        .and_then(|registration| {
+            let patterns = registration
+                .pty_patterns
+                .into_iter()
+                .map(|p| {
+                    Regex::new(&p.pattern)
+                        .map(|regex| (p.name, regex))
+                        .map_err(|e| format!("pattern {:?}: {e}", p.pattern))
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            Ok((registration.commands, registration.events, patterns))
+        });
+    match registered {
+        Ok((commands, events, patterns)) => {
+            process.commands = commands;
+            process.events = events;
+            process.patterns = patterns;
+            Ok(process)
+        }
+        Err(e) => {
+            process.kill();
+            Err(format!("initialize: {e}"))
+        }
+    }
+}
+
+fn start(app: &AppHandle, state: &Plugins, name: &str) -> Result<(), String> {
+    match spawn(app, name) {
+        Ok(process) => {
+            tracing::info!(target: "plugins", "{name} started");
+            state.set_error(name, None);
+            let mut running = state.running.lock().map_err(|e| e.to_string())?;
+            running.insert(name.to_string(), Arc::new(process));
+            state.update_watching(&running);
+            Ok(())
+        }
+        Err(e) => {
+            tracing::warn!(target: "plugins", "{name} failed to start: {e}");
+            state.set_error(name, Some(e.clone()));
+            Err(e)
+        }
+    }
+}
+
+/// Ask `processes` to exit, and kill the ones that don't in time.
+fn shut_down(processes: Vec<Arc<Process>>) {
+    for process in &processes {
+        let _ = process.notify("shutdown", Value::Null);
+    }
+    let deadline = Instant::now() + SHUTDOWN_GRACE;
+    while Instant::now() < deadline && processes.iter().any(|p| !p.exited()) {
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    for process in processes {
+        process.kill();
+    }
+}
+
+fn take(state: &Plugins, names: &[String]) -> Vec<Arc<Process>> {
+    let mut running = match state.running.lock() {
+        Ok(running) => running,
+        Err(_) => return Vec::new(),
+    };
+    let taken = names.iter().filter_map(|n| running.remove(n)).collect();
+    state.update_watching(&running);
+    taken
+}
+
+/// Start and stop plugins to match `settings`, off the calling thread.
+pub(crate) fn configure(app: &AppHandle, settings: &PluginSettings) {
+    let enabled = settings.enabled.clone();
+    let app = app.clone();
+    std::thread::spawn(move || {
+        let state = match app.try_state::<Plugins>() {
+            Some(state) => state,
+            None => return,
+        };
+        let _changing = match state.changing.lock() {
+            Ok(guard) => guard,
+            Err(_) => return,
+        };
+        let running: Vec<String> = match state.running.lock() {
+            Ok(running) => running.keys().cloned().collect(),
+            Err(_) => return,
+        };
+        let disabled: Vec<String> = running
+            .iter()
+            .filter(|n| !enabled.contains(n))
+            .cloned()
+            .collect();
+        shut_down(take(&state, &disabled));
+        if let Ok(mut errors) = state.errors.lock() {
+            errors.retain(|name, _| enabled.contains(name));
+        }
+        for name in enabled.iter().filter(|n| !running.contains(n)) {
+            let _ = start(&app, &state, name);
+        }
+    });
+}
+
+/// Start the enabled plugins. Called once from `setup`.
+pub fn init(app: &AppHandle) {
+    configure(app, &crate::settings::current(app).plugins);
+}
+
+/// Stop every plugin, e.g. on exit.
+pub(crate) fn stop_all(state: &Plugins) {
+    let names: Vec<String> = match state.running.lock() {
+        Ok(running) => running.keys().cloned().collect(),
+        Err(_) => return,
+    };
+    shut_down(take(state, &names));
+}
+
+/// Tell the plugins subscribed to `event` about it.
+pub(crate) fn emit(app: &AppHandle, event: &str, data: Value) {
+    let state = match app.try_state::<Plugins>() {
+        Some(state) => state,
+        None => return,
+    };
+    let running = match state.running.lock() {
+        Ok(running) => running,
+        Err(_) => return,
+    };
+    for (name, process) in running.iter() {
+        if !process.events.iter().any(|e| e == event) {
+            continue;
+        }
+        let params = json!({ "name": event, "data": data });
+        if let Err(e) = process.notify("event", params) {
+            tracing::debug!(target: "plugins", "{name}: {event} dropped: {e}");
+        }
+    }
+}
+
+/// Matches one terminal's output against the patterns plugins registered,
+/// and sends them `pty.match` for each matching line.
+pub(crate) struct OutputMatcher {
+    app: AppHandle,
+    terminal: String,
+    partial: String,
+}
+
+impl OutputMatcher {
+    pub(crate) fn new(app: &AppHandle, terminal: &str) -> Self {
+        Self {
+            app: app.clone(),
+            terminal: terminal.to_string(),
+            partial: String::new(),
+        }
+    }
+
+    pub(crate) fn feed(&mut self, chunk: &str) {
+        let state = match self.app.try_state::<Plugins>() {
+            Some(state) => state,
+            None => return,
+        };
+        if !state.watching.load(Ordering::Relaxed) {
+            self.partial.clear();
+            return;
+        }
+        self.partial.push_str(chunk);
+        let complete = match self.partial.rfind('\n') {
+            Some(i) => self.partial.drain(..=i).collect::<String>(),
+            None => {
+                if self.partial.len() > MAX_LINE {
+                    self.partial.clear();
+                }
+                return;
+            }
+        };
+        let running = match state.running.lock() {
+            Ok(running) => running,
+            Err(_) => return,
+        };
+        for line in complete.lines() {
+            let line = crate::problems::strip_ansi(line);
+            let line = match line.char_indices().nth(MAX_LINE) {
+                Some((i, _)) => &line[..i],
+                None => &line[..],
+            };
+            for (name, process) in running.iter() {
+                for (pattern, regex) in &process.patterns {
+                    if !regex.is_match(line) {
+                        continue;
+                    }
+                    let params = json!({
+                        "name": "pty.match",
+                        "data": { "terminal": self.terminal, "pattern": pattern, "line": line },
+                    });
+                    if let Err(e) = process.notify("event", params) {
+                        tracing::debug!(target: "plugins", "{name}: pty.match dropped: {e}");
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginInfo {
+    pub name: String,
+    pub version: Option<String>,
+    pub description: Option<String>,
+    pub dir: String,
+    pub enabled: bool,
+    pub running: bool,
+    /// Why it isn't running: a bad manifest, a failed start, or an exit.
+    pub error: Option<String>,
+    /// What it registered; empty unless running.
+    pub commands: Vec<PluginCommand>,
+}
+
+/// The installed plugins: each directory under `<app data>/plugins`.
+#[tauri::command]
+pub fn plugin_list(
+    window: WebviewWindow,
+    app: AppHandle,
+    state: State<'_, Plugins>,
+) -> Result<Vec<PluginInfo>, String> {
+    crate::require_app_window(&window)?;
+    let root = match plugins_dir(&app) {
+        Some(root) => root,
+        None => return Ok(Vec::new()),
+    };
+    let enabled = crate::settings::current(&app).plugins.enabled;
+    let errors = state.errors.lock().map_err(|e| e.to_string())?.clone();
+    let mut plugins = Vec::new();
+    for entry in std::fs::read_dir(&root).into_iter().flatten().flatten() {
+        let dir = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !dir.is_dir() || check_name(&name).is_err() {
+            continue;
+        }
+        let (version, description, manifest_error) = match read_manifest(&dir) {
+            Ok(m) => (m.version, m.description, None),
+            Err(e) => (None, None, Some(e)),
+        };
+        let process = state.process(&name);
+        plugins.push(PluginInfo {
+            enabled: enabled.contains(&name),
+            running: process.is_some(),
+            error: manifest_error.or_else(|| errors.get(&name).cloned()),
+            commands: process.map(|p| p.commands.clone()).unwrap_or_default(),
+            dir: dir.to_string_lossy().to_string(),
+            name,
+            version,
+            description,
+        });
+    }
+    plugins.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(plugins)
+}
+
+/// Run `command` of `plugin` and return its result.
+#[tauri::command]
+pub async fn plugin_invoke(
+    window: WebviewWindow,
+    app: AppHandle,
+    plugin: String,
+    command: String,
+    args: Option<Value>,
+) -> Result<Value, String> {
+    crate::require_app_window(&window)?;
+    let process = app
+        .state::<Plugins>()
+        .process(&plugin)
+        .ok_or_else(|| format!("plugin {plugin} is not running"))?;
+    if !process.commands.iter().any(|c| c.name == command) {
+        return Err(format!("plugin {plugin} has no command {command}"));
+    }
+    tracing::info!(target: "plugins", "{plugin}: {command}");
+    tauri::async_runtime::spawn_blocking(move || {
+        process.request(
+            "command",
+            json!({ "name": command, "args": args.unwrap_or(Value::Null) }),
+            COMMAND_TIMEOUT,
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Restart an enabled plugin, e.g. after editing it.
+#[tauri::command]
+pub async fn plugin_restart(
+    window: WebviewWindow,
+    app: AppHandle,
+    name: String,
+) -> Result<(), String> {
+    crate::require_window(&window, "main")?;
+    if !crate::settings::current(&app)
+        .plugins
+        .enabled
+        .contains(&name)
+    {
+        return Err(format!("plugin {name} is not enabled"));
+    }
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<Plugins>();
+        let _changing = state.changing.lock().map_err(|e| e.to_string())?;
+        shut_down(take(&state, std::slice::from_ref(&name)));
+        start(&app, &state, &name)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,1011 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State, WebviewWindow};
+
+use crate::audit::{self, AuditEvent, AuditKind, Outcome};
+use crate::cancel::CancelToken;
+use crate::pty::PtyManager;
+use crate::timeline::{TimelineEvent, TimelineKind};
+
+/// Unanswered approval requests are denied after this long.
+const APPROVAL_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// How often a waiting approval looks at its cancellation token.
+const APPROVAL_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Changed lines shown with a write waiting for approval.
+const WRITE_PREVIEW_LINES: usize = 20;
+
+/// Allow, deny and ask patterns on top of the built-in rules, kept in the
+/// settings file. Patterns are matched against each command of a pipeline or
+/// `&&`/`;` chain, with `*` matching anything.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CommandRules {
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+    /// Commands that need approval each time, unless allowed.
+    pub ask: Vec<String>,
+    /// Paths, relative to the project, that agents need approval to write.
+    pub ask_writes: Vec<String>,
+    /// Profile per project root, over `agents.policy`. Kept here rather than
+    /// in `.funny/config.json`, which the repo (or an agent) could change.
+    pub profiles: BTreeMap<String, PolicyProfile>,
+}
+
+pub(crate) fn validate_rules(rules: &CommandRules) -> Result<(), String> {
+    let all = rules.allow.iter().chain(&rules.deny).chain(&rules.ask);
+    if all.into_iter().any(|p| p.trim().is_empty()) {
+        return Err("command patterns must not be empty".to_string());
+    }
+    if rules.ask_writes.iter().any(|p| p.trim().is_empty()) {
+        return Err("path patterns must not be empty".to_string());
+    }
+    Ok(())
+}
+
+/// Blocked outright, whatever the allow lists say.
+fn builtin_deny() -> &'static [(Regex, &'static str)] {
+    static RULES: OnceLock<Vec<(Regex, &'static str)>> = OnceLock::new();
+    RULES.get_or_init(|| {
+        [
+            (
+                r"^(sudo )?rm (-[a-zA-Z]*[rR][a-zA-Z]* |-[a-zA-Z]*f[a-zA-Z]* |--recursive |--force |--no-preserve-root )+(/|/\*|~|~/|\$HOME/?)$",
+                "deletes the root or home directory",
+            ),
+            (
+                r"^git push .*(--force|-f\b|\+).*\b(main|master)\b",
+                "force-pushes to main",
+            ),
+            (r"^(sudo )?mkfs(\.\w+)? ", "formats a disk"),
+            (r"^(sudo )?dd .*\bof=/dev/", "writes to a raw device"),
+            (r":\(\)\s*\{.*:\|:.*\}", "is a fork bomb"),
+        ]
+        .into_iter()
+        .map(|(pattern, reason)| (Regex::new(pattern).expect("valid pattern"), reason))
+        .collect()
+    })
+}
+
+/// Flagged for approval unless explicitly allowed.
+fn builtin_ask() -> &'static [(Regex, &'static str)] {
+    static RULES: OnceLock<Vec<(Regex, &'static str)>> = OnceLock::new();
+    RULES.get_or_init(|| {
+        [
+            (r"^git push .*(--force|-f\b|\+)", "force-pushes"),
+            (r"^git reset --hard", "discards uncommitted changes"),
+            (r"^git clean -[a-zA-Z]*f", "deletes untracked files"),
+            (
+                r"^rm (-[a-zA-Z]*[rR][a-zA-Z]*|--recursive)\b",
+                "deletes recursively",
+            ),
+            (r"^sudo ", "runs as root"),
+            (
+                r"^(curl|wget) .*\| *(ba|z)?sh\b",
+                "pipes a download into a shell",
+            ),
+        ]
+        .into_iter()
+        .map(|(pattern, reason)| (Regex::new(pattern).expect("valid pattern"), reason))
+        .collect()
+    })
+}
+
+/// Split a command line into the commands it runs, whitespace collapsed.
+/// Quoting is not parsed: a separator inside quotes splits too, which can
+/// only make matching stricter.
+pub(crate) fn segments(command: &str) -> Vec<String> {
+    static SEPARATORS: OnceLock<Regex> = OnceLock::new();
+    let separators =
+        SEPARATORS.get_or_init(|| Regex::new(r"&&|\|\||;|\n|\|&?|`|\$\(").expect("valid pattern"));
+    let mut parts: Vec<String> = separators
+        .split(command)
+        .map(|part| part.split_whitespace().collect::<Vec<_>>().join(" "))
+        .map(|part| part.trim_end_matches(')').trim().to_string())
+        .filter(|part| !part.is_empty())
+        .collect();
+    // Piping into a shell spans two segments; check the whole line for it.
+    parts.push(command.split_whitespace().collect::<Vec<_>>().join(" "));
+    parts
+}
+
+/// `*` matches anything; the rest is literal. Whole-command match.
+fn glob_matches(pattern: &str, command: &str) -> bool {
+    let pattern = pattern.split_whitespace().collect::<Vec<_>>().join(" ");
+    let regex = format!(
+        "^{}$",
+        pattern
+            .split('*')
+            .map(regex::escape)
+            .collect::<Vec<_>>()
+            .join(".*")
+    );
+    Regex::new(&regex).is_ok_and(|r| r.is_match(command))
+}
+
+/// Presets for how much agents may do unasked. The built-in and user deny
+/// rules hold under every one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PolicyProfile {
+    /// Ask before every command that isn't read-only or allowed, and before
+    /// every write: for production code.
+    Safe,
+    /// The command rules and ask-before-writing paths as configured.
+    #[default]
+    Standard,
+    /// Run and write everything not denied without asking: for throwaway
+    /// repos.
+    Yolo,
+}
+
+impl PolicyProfile {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Safe => "safe",
+            Self::Standard => "standard",
+            Self::Yolo => "yolo",
+        }
+    }
+}
+
+/// The profile `agent` runs under: the one `policy_set` chose for its
+/// session, else the one the settings give its project, else the global
+/// one. Never the project's own config.
+pub(crate) fn profile(
+    app: &AppHandle,
+    project: Option<&str>,
+    agent: Option<&str>,
+) -> PolicyProfile {
+    let chosen = agent.and_then(|agent| {
+        let policy = app.state::<CommandPolicy>();
+        let sessions = policy.sessions.lock().ok()?;
+        sessions.get(agent).copied()
+    });
+    chosen
+        .or_else(|| {
+            let settings = crate::settings::current(app);
+            project
+                .and_then(|root| settings.commands.profiles.get(root).copied())
+                .or(settings.agents.policy)
+        })
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase", tag = "decision")]
+pub enum Verdict {
+    Allow,
+    Deny { reason: String },
+    Ask { reason: String },
+}
+
+/// Decide on `command` from `agent` with the global rules and, with a loaded
+/// project, its `.funny/config.json` allowed commands. See `decide`.
+pub(crate) fn evaluate(
+    app: &AppHandle,
+    project: Option<&str>,
+    agent: Option<&str>,
+    command: &str,
+) -> Verdict {
+    let rules = crate::settings::current(app).commands;
+    let project_allowed = project
+        .and_then(|root| crate::project_config::get(app, root))
+        .and_then(|config| config.allowed_commands);
+    decide(
+        &rules,
+        project_allowed.as_deref(),
+        profile(app, project, agent),
+        command,
+    )
+}
+
+/// Deny rules win, then the user's allow rules, then the ask rules; under
+/// yolo only deny rules apply. A project's allowed commands, checked into
+/// the repo, only narrow: other commands need approval, and the ones listed
+/// still go through every ask rule. Anything left is allowed, or under the
+/// safe profile only if it is read-only.
+fn decide(
+    rules: &CommandRules,
+    project_allowed: Option<&[String]>,
+    profile: PolicyProfile,
+    command: &str,
+) -> Verdict {
+    let parts = segments(command);
+
+    for part in &parts {
+        if let Some((_, reason)) = builtin_deny().iter().find(|(r, _)| r.is_match(part)) {
+            return Verdict::Deny {
+                reason: format!("`{part}` {reason}"),
+            };
+        }
+        if let Some(pattern) = rules.deny.iter().find(|p| glob_matches(p, part)) {
+            return Verdict::Deny {
+                reason: format!("`{part}` matches the deny rule `{pattern}`"),
+            };
+        }
+    }
+
+    if profile == PolicyProfile::Yolo {
+        return Verdict::Allow;
+    }
+
+    // The whole line, pushed last by `segments`; its pieces are what the
+    // project's list and the safe profile look at.
+    let whole = parts.len() - 1;
+    for (i, part) in parts.iter().enumerate() {
+        if rules.allow.iter().any(|p| glob_matches(p, part)) {
+            continue;
+        }
+        if let Some((_, reason)) = builtin_ask().iter().find(|(r, _)| r.is_match(part)) {
+            return Verdict::Ask {
+                reason: format!("`{part}` {reason}"),
+            };
+        }
+        if let Some(pattern) = rules.ask.iter().find(|p| glob_matches(p, part)) {
+            return Verdict::Ask {
+                reason: format!("`{part}` matches the ask rule `{pattern}`"),
+            };
+        }
+        let piece = i < whole || whole == 0;
+        if let Some(allowed) = project_allowed.filter(|_| piece) {
+            if !allowed.iter().any(|p| glob_matches(p, part)) {
+                return Verdict::Ask {
+                    reason: format!("`{part}` is not in the project's allowed commands"),
+                };
+            }
+        }
+        if profile == PolicyProfile::Safe && piece && !crate::plan_mode::is_read_only(part) {
+            return Verdict::Ask {
+                reason: format!("`{part}` is not read-only, and this run uses the safe profile"),
+            };
+        }
+    }
+    Verdict::Allow
+}
+
+/// Approval requests waiting on the user, grouped into bundles, and what
+/// was approved for the rest of the session.
+pub struct CommandPolicy {
+    /// Request id → where the answer goes, and the key `remember` stores.
+    pending: Mutex<HashMap<u64, (Sender<bool>, String)>>,
+    /// Bundles with requests still unanswered.
+    bundles: Mutex<BTreeMap<u64, ApprovalBundle>>,
+    next_id: AtomicU64,
+    approved: Mutex<HashSet<String>>,
+    /// Agent session → the profile `policy_set` switched it to.
+    sessions: Mutex<HashMap<String, PolicyProfile>>,
+}
+
+impl CommandPolicy {
+    pub fn new() -> Self {
+        Self {
+            pending: Mutex::new(HashMap::new()),
+            bundles: Mutex::new(BTreeMap::new()),
+            next_id: AtomicU64::new(1),
+            approved: Mutex::new(HashSet::new()),
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ApprovalKind {
+    Command,
+    FileWrite,
+}
+
+/// How a write changes a file, for approving it without opening it.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WriteSummary {
+    pub created: bool,
+    pub added: usize,
+    pub removed: usize,
+    /// The first changed lines, `+` or `-` prefixed.
+    pub preview: Vec<String>,
+}
+
+/// Lines of `new` not in `old` and the other way round, counted as
+/// multisets: cheap, and close to a diff's stat for the edits agents make.
+pub(crate) fn summarize_write(old: Option<&str>, new: &str) -> WriteSummary {
+    let Some(old) = old else {
+        return WriteSummary {
+            created: true,
+            added: new.lines().count(),
+            removed: 0,
+            preview: new
+                .lines()
+                .take(WRITE_PREVIEW_LINES)
+                .map(|line| format!("+{line}"))
+                .collect(),
+        };
+    };
+    let mut counts: HashMap<&str, i64> = HashMap::new();
+    for line in old.lines() {
+        *counts.entry(line).or_default() += 1;
+    }
+    let mut added = Vec::new();
+    for line in new.lines() {
+        match counts.get_mut(line) {
+            Some(count) if *count > 0 => *count -= 1,
+            _ => added.push(line),
+        }
+    }
+    let removed: Vec<&str> = old
+        .lines()
+        .filter(|line| {
+            counts.get_mut(line).is_some_and(|count| {
+                let left = *count > 0;
+                *count -= 1;
+                left
+            })
+        })
+        .collect();
+    let preview = removed
+        .iter()
+        .map(|line| format!("-{line}"))
+        .chain(added.iter().map(|line| format!("+{line}")))
+        .take(WRITE_PREVIEW_LINES)
+        .collect();
+    WriteSummary {
+        created: false,
+        added: added.len(),
+        removed: removed.len(),
+        preview,
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApprovalItem {
+    /// What `policy_respond` answers.
+    pub id: u64,
+    /// The command, or the file's path in the project.
+    pub target: String,
+    pub reason: String,
+    pub write: Option<WriteSummary>,
+}
+
+/// Similar requests from one agent, waiting together: its commands running
+/// the same program, or its writes under the same top-level directory.
+/// Each item is still decided, and audited, on its own.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApprovalBundle {
+    pub id: u64,
+    pub kind: ApprovalKind,
+    pub agent: Option<String>,
+    pub project: Option<String>,
+    /// What the items share: a program, or a directory like `src/`.
+    pub scope: String,
+    pub items: Vec<ApprovalItem>,
+    /// Line totals over the writes.
+    pub added: usize,
+    pub removed: usize,
+}
+
+impl ApprovalBundle {
+    fn recount(&mut self) {
+        let writes = self.items.iter().filter_map(|item| item.write.as_ref());
+        (self.added, self.removed) = writes.fold((0, 0), |(a, r), w| (a + w.added, r + w.removed));
+    }
+}
+
+/// A request for `ask` to put before the user.
+struct Pending<'a> {
+    kind: ApprovalKind,
+    agent: Option<&'a str>,
+    project: Option<&'a str>,
+    target: &'a str,
+    reason: &'a str,
+    write: Option<WriteSummary>,
+}
+
+impl Pending<'_> {
+    fn scope(&self) -> String {
+        match self.kind {
+            ApprovalKind::Command => self
+                .target
+                .split_whitespace()
+                .next()
+                .unwrap_or("")
+                .to_string(),
+            ApprovalKind::FileWrite => match self.target.split_once(['/', '\\']) {
+                Some((dir, _)) => format!("{dir}/"),
+                None => "./".to_string(),
+            },
+        }
+    }
+
+    /// What `remember` keeps approved.
+    fn key(&self) -> String {
+        match self.kind {
+            ApprovalKind::Command => self.target.to_string(),
+            ApprovalKind::FileWrite => format!("write {}", self.target),
+        }
+    }
+}
+
+fn publish(app: &AppHandle, bundle: &ApprovalBundle) {
+    if let Err(e) = app.emit_to("main", "policy:approval-requested", bundle) {
+        tracing::warn!("failed to request approval: {e}");
+    }
+}
+
+/// Add `item` to the open bundle it belongs with, or start one, and show
+/// the bundle in the main window.
+fn enqueue(app: &AppHandle, request: &Pending, item: ApprovalItem) {
+    let policy = app.state::<CommandPolicy>();
+    let Ok(mut bundles) = policy.bundles.lock() else {
+        return;
+    };
+    let scope = request.scope();
+    let agent = request.agent.map(str::to_string);
+    let project = request.project.map(str::to_string);
+    let open = bundles.values_mut().find(|b| {
+        b.kind == request.kind && b.agent == agent && b.project == project && b.scope == scope
+    });
+    let bundle = match open {
+        Some(bundle) => bundle,
+        None => {
+            let id = policy.next_id.fetch_add(1, Ordering::Relaxed);
+            bundles.entry(id).or_insert(ApprovalBundle {
+                id,
+                kind: request.kind,
+                agent,
+                project,
+                scope,
+                items: Vec::new(),
+                added: 0,
+                removed: 0,
+            })
+        }
+    };
+    bundle.items.push(item);
+    bundle.recount();
+    publish(app, bundle);
+}
+
+/// Take item `id` out of its bundle once it is decided, dropping bundles
+/// left empty. The main window learns through `policy:approval-cancelled`
+/// when it was withdrawn rather than answered.
+fn dequeue(app: &AppHandle, id: u64, withdrawn: bool) {
+    let policy = app.state::<CommandPolicy>();
+    if let Ok(mut bundles) = policy.bundles.lock() {
+        bundles.retain(|_, bundle| {
+            bundle.items.retain(|item| item.id != id);
+            bundle.recount();
+            !bundle.items.is_empty()
+        });
+    }
+    if withdrawn {
+        let _ = app.emit_to("main", "policy:approval-cancelled", id);
+    }
+}
+
+/// Ask the user in the main window about a flagged request and block until
+/// they answer or `APPROVAL_TIMEOUT` passes. `Err` if `cancel` is set first;
+/// the prompt is then withdrawn with `policy:approval-cancelled`.
+fn ask(app: &AppHandle, request: Pending, cancel: &CancelToken) -> Result<bool, String> {
+    let policy = app.state::<CommandPolicy>();
+    let key = request.key();
+    if policy
+        .approved
+        .lock()
+        .is_ok_and(|approved| approved.contains(&key))
+    {
+        return Ok(true);
+    }
+
+    let id = policy.next_id.fetch_add(1, Ordering::Relaxed);
+    let (tx, rx) = mpsc::channel();
+    match policy.pending.lock() {
+        Ok(mut pending) => pending.insert(id, (tx, key)),
+        Err(_) => return Ok(false),
+    };
+    let item = ApprovalItem {
+        id,
+        target: request.target.to_string(),
+        reason: request.reason.to_string(),
+        write: request.write.clone(),
+    };
+    enqueue(app, &request, item);
+
+    let deadline = Instant::now() + APPROVAL_TIMEOUT;
+    let answer = loop {
+        if cancel.is_cancelled() {
+            break Err("cancelled".to_string());
+        }
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            break Ok(false);
+        }
+        match rx.recv_timeout(left.min(APPROVAL_POLL_INTERVAL)) {
+            Ok(approved) => break Ok(approved),
+            Err(mpsc::RecvTimeoutError::Timeout) => continue,
+            Err(mpsc::RecvTimeoutError::Disconnected) => break Ok(false),
+        }
+    };
+    let unanswered = match policy.pending.lock() {
+        Ok(mut pending) => pending.remove(&id).is_some(),
+        Err(_) => false,
+    };
+    dequeue(app, id, unanswered);
+    answer
+}
+
+/// Audit a decision that needed the user, and log it.
+fn record_decision(
+    app: &AppHandle,
+    target: &str,
+    project: Option<&str>,
+    agent: Option<&str>,
+    reason: String,
+    answer: Result<bool, String>,
+    refused: &str,
+) -> Result<(), String> {
+    let (outcome, detail, result) = match answer {
+        Ok(true) => {
+            tracing::info!(target: "agents", "approved: {reason}");
+            crate::timeline::record(
+                app,
+                TimelineEvent {
+                    kind: TimelineKind::ApprovalGranted,
+                    project,
+                    subject: target,
+                    title: &reason,
+                    detail: agent.map(str::to_string),
+                },
+            );
+            (Outcome::Approved, reason, Ok(()))
+        }
+        Ok(false) => {
+            tracing::info!(target: "agents", "rejected: {reason}");
+            let error = format!("{refused}: {reason}");
+            (Outcome::Rejected, reason, Err(error))
+        }
+        Err(e) => {
+            tracing::info!(target: "agents", "approval cancelled: {reason}");
+            (Outcome::Rejected, format!("{reason} (cancelled)"), Err(e))
+        }
+    };
+    audit::record(
+        app,
+        AuditEvent {
+            kind: AuditKind::Permission,
+            agent,
+            project,
+            target,
+            outcome,
+            detail: Some(detail),
+        },
+    );
+    result
+}
+
+/// Why `agent` writing `path` (relative to `project`) needs the user's
+/// approval, if it does. The user's own writes never do.
+pub(crate) fn write_needs_approval(
+    app: &AppHandle,
+    project: &str,
+    agent: Option<&str>,
+    path: &str,
+) -> Option<String> {
+    agent?;
+    let path = path.replace('\\', "/");
+    match profile(app, Some(project), agent) {
+        PolicyProfile::Yolo => None,
+        PolicyProfile::Safe => Some(format!(
+            "`{path}` is written by a run that uses the safe profile"
+        )),
+        PolicyProfile::Standard => crate::settings::current(app)
+            .commands
+            .ask_writes
+            .iter()
+            .any(|pattern| glob_matches(pattern, &path))
+            .then(|| format!("`{path}` matches an ask-before-writing rule")),
+    }
+}
+
+/// Ask the user about `agent` writing `path` in `project`, which
+/// `write_needs_approval` flagged for `reason`, and wait for the answer.
+/// Call off the main thread.
+pub(crate) fn authorize_write(
+    app: &AppHandle,
+    project: &str,
+    agent: Option<&str>,
+    path: &str,
+    reason: String,
+    summary: WriteSummary,
+    cancel: &CancelToken,
+) -> Result<(), String> {
+    let path = path.replace('\\', "/");
+    let answer = ask(
+        app,
+        Pending {
+            kind: ApprovalKind::FileWrite,
+            agent,
+            project: Some(project),
+            target: &path,
+            reason: &reason,
+            write: Some(summary),
+        },
+        cancel,
+    );
+    record_decision(
+        app,
+        &path,
+        Some(project),
+        agent,
+        reason,
+        answer,
+        "write not approved",
+    )
+}
+
+/// Evaluate `command` run by `agent` and, if it is flagged, wait for the
+/// user's answer. `Ok` means the command may run. The decision goes to the
+/// audit log. Call off the main thread; a wait for approval ends early once
+/// `cancel` is set.
+pub(crate) fn authorize(
+    app: &AppHandle,
+    project: Option<&str>,
+    agent: Option<&str>,
+    command: &str,
+    cancel: &CancelToken,
+) -> Result<(), String> {
+    let (outcome, reason, result) = match evaluate(app, project, agent, command) {
+        Verdict::Allow => (Outcome::Allowed, None, Ok(())),
+        Verdict::Deny { reason } => {
+            tracing::warn!(target: "agents", "blocked command: {reason}");
+            let error = format!("command blocked: {reason}");
+            (Outcome::Denied, Some(reason), Err(error))
+        }
+        Verdict::Ask { reason } => {
+            let answer = ask(
+                app,
+                Pending {
+                    kind: ApprovalKind::Command,
+                    agent,
+                    project,
+                    target: command,
+                    reason: &reason,
+                    write: None,
+                },
+                cancel,
+            );
+            return record_decision(
+                app,
+                command,
+                project,
+                agent,
+                reason,
+                answer,
+                "command not approved",
+            );
+        }
+    };
+    audit::record(
+        app,
+        AuditEvent {
+            kind: AuditKind::Permission,
+            agent,
+            project,
+            target: command,
+            outcome,
+            detail: reason,
+        },
+    );
+    result
+}
+
+/// What the policy would decide for `command` from `agent`, without running
+/// or asking.
+#[tauri::command]
+pub fn policy_check(
+    window: WebviewWindow,
+    app: AppHandle,
+    command: String,
+    project: Option<String>,
+    agent: Option<String>,
+) -> Result<Verdict, String> {
+    crate::require_app_window(&window)?;
+    Ok(evaluate(
+        &app,
+        project.as_deref(),
+        agent.as_deref(),
+        &command,
+    ))
+}
+
+/// Switch agent session `session` to `profile` until it ends or the app
+/// restarts; `None` goes back to the configured one. Audited, since it can
+/// loosen what the agent may do.
+#[tauri::command]
+pub fn policy_set(
+    window: WebviewWindow,
+    app: AppHandle,
+    state: State<'_, CommandPolicy>,
+    session: String,
+    profile: Option<PolicyProfile>,
+) -> Result<(), String> {
+    crate::require_window(&window, "main")?;
+    let mut sessions = state.sessions.lock().map_err(|e| e.to_string())?;
+    let changed = match profile {
+        Some(profile) => sessions.insert(session.clone(), profile) != Some(profile),
+        None => sessions.remove(&session).is_some(),
+    };
+    drop(sessions);
+    if !changed {
+        return Ok(());
+    }
+    let name = profile.map_or("configured", PolicyProfile::name);
+    tracing::info!(target: "agents", "policy profile for {session}: {name}");
+    audit::record(
+        &app,
+        AuditEvent {
+            kind: AuditKind::Permission,
+            agent: Some(&session),
+            project: None,
+            target: &session,
+            outcome: Outcome::Ok,
+            detail: Some(format!("policy profile {name}")),
+        },
+    );
+    if let Err(e) = app.emit_to(
+        "main",
+        "policy:profile-changed",
+        serde_json::json!({ "session": session, "profile": profile }),
+    ) {
+        tracing::debug!("policy:profile-changed not delivered: {e}");
+    }
+    Ok(())
+}
+
+/// Answer a `policy:approval-requested` event. With `remember`, the same
+/// command runs without asking until the app restarts.
+#[tauri::command]
+pub fn policy_respond(
+    window: WebviewWindow,
+    state: State<'_, CommandPolicy>,
+    id: u64,
+    approved: bool,
+    remember: Option<bool>,
+) -> Result<(), String> {
+    crate::require_window(&window, "main")?;
+    let (tx, command) = state
+        .pending
+        .lock()
+        .map_err(|e| e.to_string())?
+        .remove(&id)
+        .ok_or_else(|| format!("no pending approval {id}"))?;
+    if approved && remember.unwrap_or(false) {
+        state
+            .approved
+            .lock()
+            .map_err(|e| e.to_string())?
+            .insert(command);
+    }
+    if tx.send(approved).is_err() {
+        tracing::debug!("approval {id} answered after it timed out");
+    }
+    Ok(())
+}
+
+/// Answer every request of bundle `bundle` at once, except those in
+/// `except`, which stay pending. Each is audited on its own. Returns how
+/// many were answered.
+#[tauri::command]
+pub fn policy_respond_bundle(
+    window: WebviewWindow,
+    state: State<'_, CommandPolicy>,
+    bundle: u64,
+    approved: bool,
+    remember: Option<bool>,
+    except: Option<Vec<u64>>,
+) -> Result<usize, String> {
+    crate::require_window(&window, "main")?;
+    let except = except.unwrap_or_default();
+    let ids: Vec<u64> = state
+        .bundles
+        .lock()
+        .map_err(|e| e.to_string())?
+        .get(&bundle)
+        .ok_or_else(|| format!("no pending approval bundle {bundle}"))?
+        .items
+        .iter()
+        .map(|item| item.id)
+        .filter(|id| !except.contains(id))
+        .collect();
+    let mut answered = 0;
+    for id in ids {
+        let Some((tx, key)) = state.pending.lock().map_err(|e| e.to_string())?.remove(&id) else {
+            continue;
+        };
+        if approved && remember.unwrap_or(false) {
+            state
+                .approved
+                .lock()
+                .map_err(|e| e.to_string())?
+                .insert(key);
+        }
+        if tx.send(approved).is_ok() {
+            answered += 1;
+        }
+    }
+    Ok(answered)
+}
+
+/// The approval bundles still waiting, e.g. for a reloaded main window.
+#[tauri::command]
+pub fn policy_pending(
+    window: WebviewWindow,
+    state: State<'_, CommandPolicy>,
+) -> Result<Vec<ApprovalBundle>, String> {
+    crate::require_window(&window, "main")?;
+    let bundles = state.bundles.lock().map_err(|e| e.to_string())?;
+    Ok(bundles.values().cloned().collect())
+}
+
+/// Run `command` for an agent in terminal `id`: checked against the policy
+/// (waiting for approval if flagged), then typed into the terminal followed
+/// by Enter. `cancel(request_id)` abandons a pending approval.
+///
+/// Only commands sent this way (and the local agent's) are checked.
+/// `pty_write` and automation's `terminals.write` pass keystrokes through
+/// unchecked, as they can't tell an agent's input from the user's, and the
+/// server's ACP terminals run outside the app. Who counts as an agent is
+/// whatever `agent` the caller sends: the policy catches agents' mistakes,
+/// it is not a boundary against a hostile window.
+#[tauri::command]
+pub async fn pty_run_agent_command(
+    window: WebviewWindow,
+    app: AppHandle,
+    id: String,
+    command: String,
+    project: Option<String>,
+    agent: Option<String>,
+    request_id: Option<String>,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    if !app.state::<PtyManager>().is_open(&id) {
+        return Err(format!("terminal {id} not found"));
+    }
+    let project = project
+        .map(|p| app.state::<crate::sandbox::Sandbox>().check_dir(&p))
+        .transpose()?
+        .map(|p| p.to_string_lossy().to_string());
+    crate::plan_mode::check_command(&app, agent.as_deref(), project.as_deref(), &command)?;
+    let checked = app.clone();
+    let (line, scope, actor) = (command.clone(), project.clone(), agent.clone());
+    let guard = crate::cancel::register(&app, request_id.as_deref());
+    tauri::async_runtime::spawn_blocking(move || {
+        authorize(
+            &checked,
+            scope.as_deref(),
+            actor.as_deref(),
+            &line,
+            &guard.token(),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    let written = crate::pty::write(
+        &app.state::<PtyManager>(),
+        &window,
+        &id,
+        &format!("{command}\r"),
+    );
+    audit::record(
+        &app,
+        AuditEvent {
+            kind: AuditKind::Command,
+            agent: agent.as_deref(),
+            project: project.as_deref(),
+            target: &command,
+            outcome: if written.is_ok() {
+                Outcome::Ok
+            } else {
+                Outcome::Error
+            },
+            detail: Some(format!("terminal {id}")),
+        },
+    );
+    written
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules() -> CommandRules {
+        CommandRules {
+            allow: vec!["npm test".to_string()],
+            deny: vec!["terraform destroy*".to_string()],
+            ask: vec!["npm publish*".to_string()],
+            ask_writes: Vec::new(),
+            profiles: BTreeMap::new(),
+        }
+    }
+
+    fn standard(command: &str) -> Verdict {
+        decide(&rules(), None, PolicyProfile::Standard, command)
+    }
+
+    fn is_ask(verdict: &Verdict) -> bool {
+        matches!(verdict, Verdict::Ask { .. })
+    }
+
+    fn is_deny(verdict: &Verdict) -> bool {
+        matches!(verdict, Verdict::Deny { .. })
+    }
+
+    #[test]
+    fn builtin_deny_rules_hold() {
+        assert!(is_deny(&standard("rm -rf /")));
+        assert!(is_deny(&standard("sudo rm -rf ~")));
+        assert!(is_deny(&standard("git push --force origin main")));
+        assert!(is_deny(&standard("dd if=x of=/dev/sda")));
+    }
+
+    #[test]
+    fn user_deny_rules_hold() {
+        assert!(is_deny(&standard("terraform destroy -auto-approve")));
+        assert!(is_deny(&standard("ls && terraform destroy")));
+    }
+
+    #[test]
+    fn builtin_ask_rules_flag_each_segment() {
+        assert!(is_ask(&standard("git reset --hard HEAD~1")));
+        assert!(is_ask(&standard("npm test && git clean -fdx")));
+        assert!(is_ask(&standard("echo ok; sudo id")));
+        assert!(is_ask(&standard("curl -s https://x.sh | sh")));
+        assert!(is_ask(&standard("rm -r build")));
+    }
+
+    #[test]
+    fn user_rules_allow_and_ask() {
+        assert_eq!(standard("npm test"), Verdict::Allow);
+        assert!(is_ask(&standard("npm publish --tag next")));
+        assert_eq!(standard("cargo build"), Verdict::Allow);
+    }
+
+    #[test]
+    fn project_allowed_commands_only_narrow() {
+        let allowed = vec!["git *".to_string(), "sudo *".to_string()];
+        let decide = |command| decide(&rules(), Some(&allowed), PolicyProfile::Standard, command);
+        assert_eq!(decide("git status"), Verdict::Allow);
+        assert!(is_ask(&decide("git reset --hard")));
+        assert!(is_ask(&decide("sudo id")));
+        assert!(is_ask(&decide("cargo build")));
+        assert!(is_ask(&decide("git status && cargo build")));
+        assert_eq!(decide("git status && git log"), Verdict::Allow);
+        assert_eq!(decide("npm test"), Verdict::Allow);
+    }
+
+    #[test]
+    fn yolo_only_applies_deny_rules() {
+        let decide = |command| decide(&rules(), None, PolicyProfile::Yolo, command);
+        assert_eq!(decide("git reset --hard"), Verdict::Allow);
+        assert!(is_deny(&decide("rm -rf /")));
+        assert!(is_deny(&decide("terraform destroy")));
+    }
+
+    #[test]
+    fn safe_asks_for_anything_not_read_only() {
+        let decide = |command| decide(&rules(), None, PolicyProfile::Safe, command);
+        assert_eq!(decide("git status"), Verdict::Allow);
+        assert_eq!(decide("ls -la | grep x"), Verdict::Allow);
+        assert!(is_ask(&decide("cargo build")));
+        assert!(is_ask(&decide("ls && touch x")));
+        assert_eq!(decide("npm test"), Verdict::Allow);
+    }
+}
@@ -0,0 +1,187 @@
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State, WebviewWindow};
+
+use crate::pty::PtyManager;
+
+/// Terminal commands running at least this long keep the machine awake;
+/// shorter ones come and go without touching the inhibitor.
+const LONG_COMMAND: Duration = Duration::from_secs(60);
+
+/// How often terminals are sampled for running commands.
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+const REASON: &str = "Agents or terminal commands are running";
+
+/// A held sleep inhibitor; dropping it lets the system sleep again.
+enum Inhibitor {
+    /// `caffeinate` / `systemd-inhibit`, tied to our pid so a crash can't
+    /// leave it behind.
+    #[cfg(unix)]
+    Process(std::process::Child),
+    /// A thread holding `SetThreadExecutionState`; it resets the state and
+    /// exits when the sender is dropped.
+    #[cfg(windows)]
+    Thread(std::sync::mpsc::Sender<()>),
+}
+
+impl Drop for Inhibitor {
+    fn drop(&mut self) {
+        match self {
+            #[cfg(unix)]
+            Inhibitor::Process(child) => {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+            #[cfg(windows)]
+            Inhibitor::Thread(_) => {}
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn acquire() -> Result<Inhibitor, String> {
+    // -i: prevent idle sleep; -w: exit together with this process.
+    std::process::Command::new("caffeinate")
+        .args(["-i", "-w", &std::process::id().to_string()])
+        .spawn()
+        .map(Inhibitor::Process)
+        .map_err(|e| format!("failed to start caffeinate: {e}"))
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn acquire() -> Result<Inhibitor, String> {
+    // `tail --pid` returns once this process is gone, releasing the lock.
+    std::process::Command::new("systemd-inhibit")
+        .args([
+            "--what=sleep:idle",
+            "--who=funny",
+            &format!("--why={REASON}"),
+            "--mode=block",
+            "tail",
+            &format!("--pid={}", std::process::id()),
+            "-f",
+            "/dev/null",
+        ])
+        .stdin(std::process::Stdio::null())
+        .spawn()
+        .map(Inhibitor::Process)
+        .map_err(|e| format!("failed to start systemd-inhibit: {e}"))
+}
+
+#[cfg(windows)]
+fn acquire() -> Result<Inhibitor, String> {
+    use windows_sys::Win32::System::Power::{
+        SetThreadExecutionState, ES_CONTINUOUS, ES_SYSTEM_REQUIRED,
+    };
+
+    let (tx, rx) = std::sync::mpsc::channel::<()>();
+    std::thread::spawn(move || {
+        // The execution state belongs to the calling thread, so this thread
+        // lives exactly as long as the inhibitor.
+        unsafe { SetThreadExecutionState(ES_CONTINUOUS | ES_SYSTEM_REQUIRED) };
+        let _ = rx.recv();
+        unsafe { SetThreadExecutionState(ES_CONTINUOUS) };
+    });
+    Ok(Inhibitor::Thread(tx))
+}
+
+#[derive(Default)]
+struct PowerState {
+    agents_running: u32,
+    busy_terminals: usize,
+    inhibitor: Option<Inhibitor>,
+}
+
+/// Keeps the system awake while agents run or terminals have long commands
+/// going, so overnight batch runs aren't suspended. Display sleep and screen
+/// locking are left alone.
+pub struct PowerManager {
+    state: Mutex<PowerState>,
+}
+
+impl PowerManager {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(PowerState::default()),
+        }
+    }
+}
+
+/// Take or release the inhibitor to match the current activity.
+fn update(state: &mut PowerState) {
+    let active = state.agents_running > 0 || state.busy_terminals > 0;
+    if active && state.inhibitor.is_none() {
+        match acquire() {
+            Ok(inhibitor) => {
+                tracing::info!("preventing sleep: {REASON}");
+                state.inhibitor = Some(inhibitor);
+            }
+            Err(e) => tracing::warn!("{e}"),
+        }
+    } else if !active && state.inhibitor.take().is_some() {
+        tracing::info!("idle, allowing sleep");
+    }
+}
+
+/// Record how many agent runs are active — fed from the tray status updates.
+pub(crate) fn set_agents_running(app: &AppHandle, running: u32) {
+    if let Some(power) = app.try_state::<PowerManager>() {
+        if let Ok(mut state) = power.state.lock() {
+            if state.agents_running != running {
+                tracing::debug!(target: "agents", "{running} agent runs active");
+            }
+            state.agents_running = running;
+            update(&mut state);
+        }
+    }
+}
+
+/// Start sampling terminals for long-running commands. Called once from
+/// `setup`.
+pub fn init(app: &AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || loop {
+        std::thread::sleep(POLL_INTERVAL);
+        let busy = app.state::<PtyManager>().busy_terminals(LONG_COMMAND);
+        if let Ok(mut state) = app.state::<PowerManager>().state.lock() {
+            if state.busy_terminals != busy {
+                state.busy_terminals = busy;
+                update(&mut state);
+            }
+        }
+    });
+}
+
+/// Let the system sleep again — called on app exit.
+pub fn release(state: &PowerManager) {
+    if let Ok(mut state) = state.state.lock() {
+        state.inhibitor = None;
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PowerStatus {
+    /// Whether a sleep inhibitor is currently held.
+    pub inhibited: bool,
+    pub agents_running: u32,
+    /// Terminals running a command for over a minute.
+    pub busy_terminals: usize,
+}
+
+/// Whether the app is currently keeping the system awake, and why.
+#[tauri::command]
+pub fn power_status(
+    window: WebviewWindow,
+    state: State<'_, PowerManager>,
+) -> Result<PowerStatus, String> {
+    crate::require_app_window(&window)?;
+    let state = state.state.lock().map_err(|e| e.to_string())?;
+    Ok(PowerStatus {
+        inhibited: state.inhibitor.is_some(),
+        agents_running: state.agents_running,
+        busy_terminals: state.busy_terminals,
+    })
+}
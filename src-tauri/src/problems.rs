@@ -0,0 +1,381 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+/// A diagnostic whose location is on a later line (rustc) gives up after
+/// this many lines without one.
+const LOCATION_LOOKAHEAD: usize = 5;
+
+/// Lines longer than this aren't matched; they're minified output, not
+/// compiler messages.
+const MAX_LINE_LEN: usize = 2000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+/// How to recognise diagnostics in build and lint output. `pattern` uses
+/// the named groups `file`, `line`, `column`, `severity`, `message` and
+/// `code`; only `message` is required, as long as `location` or `file`
+/// supplies the file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProblemMatcher {
+    pub name: String,
+    pub pattern: String,
+    /// Matched against the next few lines when the location follows the
+    /// message, as with rustc's `--> file:line:col`. Groups `file`, `line`
+    /// and `column`.
+    #[serde(default)]
+    pub location: Option<String>,
+    /// For formats that print each file once above its diagnostics, like
+    /// eslint's: a line matching this (group `file`) applies to the
+    /// diagnostics after it.
+    #[serde(default)]
+    pub file: Option<String>,
+    /// Used when `pattern` has no `severity` group.
+    #[serde(default)]
+    pub severity: Option<Severity>,
+}
+
+/// Problem matchers in the settings file. The built-in matchers for rustc
+/// (and clippy), tsc and eslint come first unless switched off.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ProblemSettings {
+    pub builtin: bool,
+    pub matchers: Vec<ProblemMatcher>,
+    /// Also scan terminal output, not just commands run with `exec_command`.
+    pub terminals: bool,
+}
+
+impl Default for ProblemSettings {
+    fn default() -> Self {
+        Self {
+            builtin: true,
+            matchers: Vec::new(),
+            terminals: true,
+        }
+    }
+}
+
+fn builtin_matchers() -> Vec<ProblemMatcher> {
+    let matcher =
+        |name: &str, pattern: &str, location: Option<&str>, file: Option<&str>| ProblemMatcher {
+            name: name.to_string(),
+            pattern: pattern.to_string(),
+            location: location.map(str::to_string),
+            file: file.map(str::to_string),
+            severity: None,
+        };
+    vec![
+        matcher(
+            "rustc",
+            r"^(?P<severity>error|warning)(?:\[(?P<code>[^\]]+)\])?: (?P<message>.+)$",
+            Some(r"^\s*--> (?P<file>.+?):(?P<line>\d+):(?P<column>\d+)"),
+            None,
+        ),
+        matcher(
+            "tsc",
+            r"^(?P<file>[^\s(][^(]*)\((?P<line>\d+),(?P<column>\d+)\): (?P<severity>error|warning) (?P<code>TS\d+): (?P<message>.+)$",
+            None,
+            None,
+        ),
+        matcher(
+            "tsc-pretty",
+            r"^(?P<file>\S+):(?P<line>\d+):(?P<column>\d+) - (?P<severity>error|warning) (?P<code>TS\d+): (?P<message>.+)$",
+            None,
+            None,
+        ),
+        matcher(
+            "eslint",
+            r"^\s+(?P<line>\d+):(?P<column>\d+)\s+(?P<severity>error|warning)\s+(?P<message>.+?)(?:\s{2,}(?P<code>[\w@/-]+))?$",
+            None,
+            Some(r"^(?P<file>(?:/|[A-Za-z]:\\|\./)?\S.*\.(?:[cm]?[jt]sx?|vue|svelte))$"),
+        ),
+    ]
+}
+
+struct Compiled {
+    name: String,
+    pattern: Regex,
+    location: Option<Regex>,
+    file: Option<Regex>,
+    severity: Severity,
+}
+
+impl Compiled {
+    fn new(matcher: &ProblemMatcher) -> Result<Self, String> {
+        let regex = |p: &str| Regex::new(p).map_err(|e| format!("matcher {}: {e}", matcher.name));
+        let compiled = Self {
+            name: matcher.name.clone(),
+            pattern: regex(&matcher.pattern)?,
+            location: matcher.location.as_deref().map(regex).transpose()?,
+            file: matcher.file.as_deref().map(regex).transpose()?,
+            severity: matcher.severity.unwrap_or(Severity::Error),
+        };
+        let has = |group: &str| {
+            compiled
+                .pattern
+                .capture_names()
+                .flatten()
+                .any(|n| n == group)
+        };
+        if !has("message") {
+            return Err(format!(
+                "matcher {}: pattern needs a message group",
+                matcher.name
+            ));
+        }
+        if !has("file") && compiled.location.is_none() && compiled.file.is_none() {
+            return Err(format!(
+                "matcher {}: needs a file group, a location or a file pattern",
+                matcher.name
+            ));
+        }
+        Ok(compiled)
+    }
+}
+
+pub(crate) fn validate_settings(settings: &ProblemSettings) -> Result<(), String> {
+    for matcher in &settings.matchers {
+        if matcher.name.trim().is_empty() {
+            return Err("problem matchers need a name".to_string());
+        }
+        Compiled::new(matcher)?;
+    }
+    Ok(())
+}
+
+/// The compiled matchers for the current settings, rebuilt when they change.
+pub struct ProblemMatchers {
+    compiled: Mutex<Option<(ProblemSettings, Arc<Vec<Compiled>>)>>,
+}
+
+impl ProblemMatchers {
+    pub fn new() -> Self {
+        Self {
+            compiled: Mutex::new(None),
+        }
+    }
+}
+
+fn effective(settings: &ProblemSettings) -> Vec<ProblemMatcher> {
+    let mut matchers = if settings.builtin {
+        builtin_matchers()
+    } else {
+        Vec::new()
+    };
+    matchers.extend(settings.matchers.iter().cloned());
+    matchers
+}
+
+fn compiled(app: &AppHandle, settings: &ProblemSettings) -> Arc<Vec<Compiled>> {
+    let state = app.state::<ProblemMatchers>();
+    let mut cache = match state.compiled.lock() {
+        Ok(cache) => cache,
+        Err(_) => return Arc::new(Vec::new()),
+    };
+    if let Some((cached, compiled)) = cache.as_ref() {
+        if cached.builtin == settings.builtin && cached.matchers == settings.matchers {
+            return compiled.clone();
+        }
+    }
+    let compiled: Arc<Vec<Compiled>> = Arc::new(
+        effective(settings)
+            .iter()
+            .filter_map(|matcher| {
+                Compiled::new(matcher)
+                    .inspect_err(|e| tracing::warn!("ignoring problem matcher: {e}"))
+                    .ok()
+            })
+            .collect(),
+    );
+    *cache = Some((settings.clone(), compiled.clone()));
+    compiled
+}
+
+/// Drop colour and cursor escape sequences so patterns see plain text.
+pub(crate) fn strip_ansi(text: &str) -> Cow<'_, str> {
+    static ANSI: OnceLock<Regex> = OnceLock::new();
+    ANSI.get_or_init(|| Regex::new(r"\x1b\[[0-9;?]*[A-Za-z]").expect("valid pattern"))
+        .replace_all(text, "")
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Diagnostic {
+    /// The matcher that found it.
+    pub matcher: String,
+    /// Absolute, for opening; relative paths are taken from the command's
+    /// working directory.
+    pub file: String,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    pub severity: Severity,
+    pub message: String,
+    pub code: Option<String>,
+}
+
+/// A diagnostic still waiting for its `location` line.
+struct Awaiting {
+    diagnostic: Diagnostic,
+    matcher: usize,
+    lines_left: usize,
+}
+
+/// Applies the problem matchers to one output stream, line by line.
+pub(crate) struct ProblemScanner {
+    matchers: Arc<Vec<Compiled>>,
+    cwd: PathBuf,
+    partial: String,
+    /// Last file line seen, per matcher with a `file` pattern.
+    files: Vec<Option<String>>,
+    awaiting: Option<Awaiting>,
+}
+
+impl ProblemScanner {
+    /// A scanner for output of a command run in `cwd`; `None` when there are
+    /// no matchers to apply, or terminals are excluded and `terminal` is
+    /// set.
+    pub(crate) fn new(app: &AppHandle, cwd: &Path, terminal: bool) -> Option<Self> {
+        let settings = crate::settings::current(app).problems;
+        if terminal && !settings.terminals {
+            return None;
+        }
+        let matchers = compiled(app, &settings);
+        if matchers.is_empty() {
+            return None;
+        }
+        Some(Self {
+            files: vec![None; matchers.len()],
+            matchers,
+            cwd: cwd.to_path_buf(),
+            partial: String::new(),
+            awaiting: None,
+        })
+    }
+
+    /// Feed a chunk of output and get the diagnostics it completed.
+    pub(crate) fn feed(&mut self, chunk: &str) -> Vec<Diagnostic> {
+        self.partial.push_str(chunk);
+        let complete = match self.partial.rfind('\n') {
+            Some(i) => self.partial.drain(..=i).collect::<String>(),
+            None => return Vec::new(),
+        };
+        let mut found = Vec::new();
+        for line in complete.lines() {
+            self.line(line, &mut found);
+        }
+        found
+    }
+
+    /// Diagnostics in output that ended without a newline.
+    pub(crate) fn finish(&mut self) -> Vec<Diagnostic> {
+        let rest = std::mem::take(&mut self.partial);
+        let mut found = Vec::new();
+        self.line(&rest, &mut found);
+        found
+    }
+
+    fn resolve(&self, file: &str) -> String {
+        self.cwd.join(file.trim()).to_string_lossy().to_string()
+    }
+
+    fn line(&mut self, raw: &str, found: &mut Vec<Diagnostic>) {
+        if raw.len() > MAX_LINE_LEN {
+            return;
+        }
+        let line = strip_ansi(raw.trim_end_matches('\r'));
+        let line = line.as_ref();
+
+        if let Some(mut awaiting) = self.awaiting.take() {
+            let location = self.matchers[awaiting.matcher]
+                .location
+                .as_ref()
+                .and_then(|l| l.captures(line));
+            if let Some(c) = location {
+                if let Some(file) = c.name("file") {
+                    awaiting.diagnostic.file = self.resolve(file.as_str());
+                    awaiting.diagnostic.line = number(&c, "line");
+                    awaiting.diagnostic.column = number(&c, "column");
+                    found.push(awaiting.diagnostic);
+                }
+                return;
+            }
+            awaiting.lines_left -= 1;
+            if awaiting.lines_left > 0 {
+                self.awaiting = Some(awaiting);
+            }
+        }
+
+        for (index, matcher) in self.matchers.iter().enumerate() {
+            if let Some(c) = matcher.file.as_ref().and_then(|f| f.captures(line)) {
+                self.files[index] = c.name("file").map(|f| f.as_str().to_string());
+                continue;
+            }
+            let c = match matcher.pattern.captures(line) {
+                Some(c) => c,
+                None => continue,
+            };
+            let file = c
+                .name("file")
+                .map(|f| f.as_str().to_string())
+                .or_else(|| self.files[index].clone());
+            let diagnostic = Diagnostic {
+                matcher: matcher.name.clone(),
+                file: file.as_deref().map(|f| self.resolve(f)).unwrap_or_default(),
+                line: number(&c, "line"),
+                column: number(&c, "column"),
+                severity: c
+                    .name("severity")
+                    .map(|s| severity(s.as_str()))
+                    .unwrap_or(matcher.severity),
+                message: c
+                    .name("message")
+                    .map(|m| m.as_str().trim().to_string())
+                    .unwrap_or_default(),
+                code: c.name("code").map(|m| m.as_str().to_string()),
+            };
+            if file.is_some() {
+                found.push(diagnostic);
+            } else if matcher.location.is_some() {
+                self.awaiting = Some(Awaiting {
+                    diagnostic,
+                    matcher: index,
+                    lines_left: LOCATION_LOOKAHEAD,
+                });
+            }
+            return;
+        }
+    }
+}
+
+fn number(captures: &regex::Captures<'_>, group: &str) -> Option<u32> {
+    captures.name(group).and_then(|m| m.as_str().parse().ok())
+}
+
+fn severity(text: &str) -> Severity {
+    match text.to_ascii_lowercase().as_str() {
+        "error" | "fatal" | "err" => Severity::Error,
+        "warning" | "warn" => Severity::Warning,
+        _ => Severity::Info,
+    }
+}
+
+/// The matchers in effect, built-in ones first, for the settings UI.
+#[tauri::command]
+pub fn problem_matchers(
+    window: WebviewWindow,
+    app: AppHandle,
+) -> Result<Vec<ProblemMatcher>, String> {
+    crate::require_app_window(&window)?;
+    Ok(effective(&crate::settings::current(&app).problems))
+}
@@ -0,0 +1,414 @@
+use portable_pty::{CommandBuilder, PtySize};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+use crate::pty::Launch;
+use crate::sandbox::Sandbox;
+
+/// iTerm2's preferences, which hold its profiles under `New Bookmarks`.
+const ITERM2_PREFERENCES: &str = "Library/Preferences/com.googlecode.iterm2.plist";
+
+/// Where Windows Terminal keeps `settings.json` under `%LOCALAPPDATA%`: the
+/// Store build, the preview build, then an unpackaged install.
+const WINDOWS_TERMINAL_SETTINGS: [&str; 3] = [
+    r"Packages\Microsoft.WindowsTerminal_8wekyb3d8bbwe\LocalState\settings.json",
+    r"Packages\Microsoft.WindowsTerminalPreview_8wekyb3d8bbwe\LocalState\settings.json",
+    r"Microsoft\Windows Terminal\settings.json",
+];
+
+/// A named way to open a terminal: what to run, where, and how it looks.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TerminalProfile {
+    pub name: String,
+    /// Starting directory; the project the terminal opens in when unset or
+    /// outside every project.
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Program and arguments; the default shell when empty.
+    #[serde(default)]
+    pub command: Vec<String>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Font hints for the terminal view.
+    #[serde(default)]
+    pub font_family: Option<String>,
+    #[serde(default)]
+    pub font_size: Option<f32>,
+    /// The terminal app it was imported from.
+    #[serde(default)]
+    pub imported_from: Option<String>,
+}
+
+pub(crate) fn validate_profiles(profiles: &[TerminalProfile]) -> Result<(), String> {
+    let mut names = HashSet::new();
+    for profile in profiles {
+        if profile.name.trim().is_empty() {
+            return Err("terminal profile names must not be empty".to_string());
+        }
+        if !names.insert(profile.name.as_str()) {
+            return Err(format!("duplicate terminal profile {}", profile.name));
+        }
+        if profile.font_size.is_some_and(|size| size <= 0.0) {
+            return Err(format!("{}: font size must be positive", profile.name));
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ProfileSource {
+    Iterm2,
+    WindowsTerminal,
+}
+
+impl ProfileSource {
+    fn label(self) -> &'static str {
+        match self {
+            ProfileSource::Iterm2 => "iTerm2",
+            ProfileSource::WindowsTerminal => "Windows Terminal",
+        }
+    }
+
+    /// The settings file the app keeps on this machine, if there is one.
+    fn default_path(self) -> Option<PathBuf> {
+        match self {
+            ProfileSource::Iterm2 => {
+                crate::onboarding::home_dir().map(|home| home.join(ITERM2_PREFERENCES))
+            }
+            ProfileSource::WindowsTerminal => {
+                let local = PathBuf::from(std::env::var_os("LOCALAPPDATA")?);
+                WINDOWS_TERMINAL_SETTINGS
+                    .iter()
+                    .map(|p| local.join(p))
+                    .find(|p| p.is_file())
+            }
+        }
+    }
+}
+
+/// Replace `%NAME%` (Windows) and a leading `~` with their values.
+fn expand(path: &str) -> String {
+    let mut out = String::new();
+    let mut rest = path;
+    while let Some(start) = rest.find('%') {
+        let Some(len) = rest[start + 1..].find('%') else {
+            break;
+        };
+        let name = &rest[start + 1..start + 1 + len];
+        out.push_str(&rest[..start]);
+        match std::env::var(name) {
+            Ok(value) if !name.is_empty() => out.push_str(&value),
+            _ => out.push_str(&rest[start..start + len + 2]),
+        }
+        rest = &rest[start + len + 2..];
+    }
+    out.push_str(rest);
+    match (out.strip_prefix('~'), crate::onboarding::home_dir()) {
+        (Some(tail), Some(home)) if tail.is_empty() || tail.starts_with(['/', '\\']) => {
+            format!("{}{tail}", home.display())
+        }
+        _ => out,
+    }
+}
+
+/// iTerm2 stores a font as `PostScriptName size`.
+fn iterm2_font(font: &str) -> (Option<String>, Option<f32>) {
+    match font.rsplit_once(' ') {
+        Some((family, size)) => match size.parse::<f32>() {
+            Ok(size) => (Some(family.to_string()), Some(size)),
+            Err(_) => (Some(font.to_string()), None),
+        },
+        None => (Some(font.to_string()), None),
+    }
+}
+
+fn iterm2_profile(profile: &Value) -> Result<TerminalProfile, String> {
+    let text = |key: &str| profile.get(key).and_then(Value::as_str);
+    let name = text("Name").ok_or("a profile without a name")?;
+    let cwd = match (text("Custom Directory"), text("Working Directory")) {
+        (Some("Yes"), Some(dir)) if !dir.is_empty() => Some(expand(dir)),
+        _ => None,
+    };
+    let command = match (text("Custom Command"), text("Command")) {
+        (Some("Yes"), Some(command)) => {
+            crate::open::split_command(command).map_err(|e| format!("{name}: {e}"))?
+        }
+        _ => Vec::new(),
+    };
+    let (font_family, font_size) = text("Normal Font").map(iterm2_font).unwrap_or_default();
+    Ok(TerminalProfile {
+        name: name.to_string(),
+        cwd,
+        command,
+        env: BTreeMap::new(),
+        font_family,
+        font_size,
+        imported_from: Some(ProfileSource::Iterm2.label().to_string()),
+    })
+}
+
+/// iTerm2's preferences plist, through `plutil`, or a profile export: a
+/// `Profiles` list (also the dynamic profile format) or a single profile.
+fn read_iterm2(path: &Path) -> Result<Value, String> {
+    if path.extension().is_some_and(|ext| ext == "plist") {
+        let out = crate::forge::run_capture(
+            Command::new("plutil")
+                .args(["-convert", "json", "-o", "-"])
+                .arg(path),
+            None,
+        )?;
+        return serde_json::from_str(&out).map_err(|e| e.to_string());
+    }
+    let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&content).map_err(|e| format!("invalid JSON in {}: {e}", path.display()))
+}
+
+fn iterm2_profiles(value: &Value) -> Vec<&Value> {
+    ["New Bookmarks", "Profiles"]
+        .iter()
+        .find_map(|key| value.get(key).and_then(Value::as_array))
+        .map(|profiles| profiles.iter().collect())
+        .unwrap_or_else(|| vec![value])
+}
+
+/// Drop `//` and `/* */` comments and trailing commas, which Windows
+/// Terminal accepts in its settings.
+fn strip_jsonc(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                '\\' => out.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                out.push(c);
+            }
+            ('/', Some('/')) => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        out.push('\n');
+                        break;
+                    }
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut last = ' ';
+                for c in chars.by_ref() {
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+            }
+            (',', _) => {
+                let next = chars.clone().find(|c| !c.is_whitespace());
+                if !matches!(next, Some('}' | ']')) {
+                    out.push(c);
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn windows_terminal_profile(
+    profile: &Value,
+    defaults: Option<&Value>,
+) -> Result<Option<TerminalProfile>, String> {
+    let get = |key: &str| {
+        profile
+            .get(key)
+            .or_else(|| defaults.and_then(|d| d.get(key)))
+    };
+    let text = |key: &str| get(key).and_then(Value::as_str);
+    let name = text("name").ok_or("a profile without a name")?;
+    if get("hidden").and_then(Value::as_bool) == Some(true) {
+        return Ok(None);
+    }
+    let command = match profile.get("commandline").and_then(Value::as_str) {
+        Some(line) => {
+            crate::open::split_command(&expand(line)).map_err(|e| format!("{name}: {e}"))?
+        }
+        // Generated ones (WSL distributions, Azure Cloud Shell) say only
+        // where they come from; there is nothing to run outside Windows
+        // Terminal.
+        None if profile.get("source").is_some() => return Ok(None),
+        None => Vec::new(),
+    };
+    let font = get("font");
+    let font_family = font
+        .and_then(|f| f.get("face"))
+        .or_else(|| get("fontFace"))
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let font_size = font
+        .and_then(|f| f.get("size"))
+        .or_else(|| get("fontSize"))
+        .and_then(Value::as_f64)
+        .map(|size| size as f32);
+    let env = get("environment")
+        .and_then(Value::as_object)
+        .map(|vars| {
+            vars.iter()
+                .filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string())))
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(Some(TerminalProfile {
+        name: name.to_string(),
+        cwd: text("startingDirectory").map(expand),
+        command,
+        env,
+        font_family,
+        font_size,
+        imported_from: Some(ProfileSource::WindowsTerminal.label().to_string()),
+    }))
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileImport {
+    pub path: String,
+    pub profiles: Vec<TerminalProfile>,
+    /// Profiles left out, and why.
+    pub skipped: Vec<String>,
+}
+
+fn import(source: ProfileSource, path: &Path) -> Result<ProfileImport, String> {
+    let mut result = ProfileImport {
+        path: path.to_string_lossy().to_string(),
+        ..Default::default()
+    };
+    match source {
+        ProfileSource::Iterm2 => {
+            let value = read_iterm2(path)?;
+            for profile in iterm2_profiles(&value) {
+                match iterm2_profile(profile) {
+                    Ok(profile) => result.profiles.push(profile),
+                    Err(e) => result.skipped.push(e),
+                }
+            }
+        }
+        ProfileSource::WindowsTerminal => {
+            let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+            let value: Value = serde_json::from_str(&strip_jsonc(&content))
+                .map_err(|e| format!("invalid settings in {}: {e}", path.display()))?;
+            // `profiles` is a list, or `defaults` plus a `list`.
+            let profiles = value.get("profiles");
+            let defaults = profiles.and_then(|p| p.get("defaults"));
+            let list = profiles
+                .and_then(|p| p.as_array().or_else(|| p.get("list")?.as_array()))
+                .ok_or("no profiles in the settings")?;
+            for profile in list {
+                let name = profile.get("name").and_then(Value::as_str).unwrap_or("?");
+                match windows_terminal_profile(profile, defaults) {
+                    Ok(Some(profile)) => result.profiles.push(profile),
+                    Ok(None) => result
+                        .skipped
+                        .push(format!("{name}: hidden or generated by Windows Terminal")),
+                    Err(e) => result.skipped.push(e),
+                }
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Read the profiles of iTerm2 or Windows Terminal, from `path` or where the
+/// app keeps them on this machine, and add them to the terminal profiles,
+/// replacing ones of the same name. With `preview` nothing is saved.
+#[tauri::command]
+pub async fn terminal_profiles_import(
+    window: WebviewWindow,
+    app: AppHandle,
+    source: ProfileSource,
+    path: Option<String>,
+    preview: Option<bool>,
+) -> Result<ProfileImport, String> {
+    crate::require_window(&window, "main")?;
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => source
+            .default_path()
+            .ok_or_else(|| format!("no {} settings found", source.label()))?,
+    };
+    let result = tauri::async_runtime::spawn_blocking(move || import(source, &path))
+        .await
+        .map_err(|e| e.to_string())??;
+    if preview != Some(true) && !result.profiles.is_empty() {
+        let imported = result.profiles.clone();
+        crate::settings::update(&app, move |settings| {
+            let profiles = &mut settings.terminal.profiles;
+            profiles.retain(|p| !imported.iter().any(|i| i.name == p.name));
+            profiles.extend(imported);
+            Ok(())
+        })?;
+        tracing::info!(
+            "imported {} terminal profiles from {}",
+            result.profiles.len(),
+            source.label()
+        );
+    }
+    Ok(result)
+}
+
+/// Open terminal `id` with terminal profile `profile`, in its directory if
+/// that is inside a project and in `cwd` otherwise.
+#[tauri::command]
+pub fn profile_spawn(
+    window: WebviewWindow,
+    app: AppHandle,
+    profile: String,
+    id: String,
+    cwd: String,
+    rows: u16,
+    cols: u16,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    let profile = crate::settings::current(&app)
+        .terminal
+        .profiles
+        .into_iter()
+        .find(|p| p.name == profile)
+        .ok_or_else(|| format!("no terminal profile {profile}"))?;
+    let sandbox = app.state::<Sandbox>();
+    let cwd = match profile.cwd.as_deref().map(|dir| sandbox.check_dir(dir)) {
+        Some(Ok(dir)) => dir,
+        _ => sandbox.check_dir(&cwd)?,
+    };
+    let mut command = match profile.command.split_first() {
+        Some((program, args)) => {
+            let mut command = CommandBuilder::new(crate::exec::resolve_program(&cwd, program)?);
+            command.args(args);
+            command
+        }
+        None => CommandBuilder::new(crate::pty::default_shell(&app)),
+    };
+    for (key, value) in &profile.env {
+        command.env(key, value);
+    }
+    let size = PtySize {
+        rows,
+        cols,
+        pixel_width: 0,
+        pixel_height: 0,
+    };
+    crate::pty::spawn(&app, &window, id, &cwd, size, Launch::Command(command))
+}
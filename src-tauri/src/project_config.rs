@@ -0,0 +1,429 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State, WebviewWindow};
+
+use crate::events::{emit_subscribed, EventRouter};
+use crate::sandbox::Sandbox;
+use crate::settings::AgentDefaults;
+use crate::watcher::{self, FileWatcher, WatchOptions};
+
+const CONFIG_DIR: &str = ".funny";
+const CONFIG_FILE: &str = "config.json";
+
+/// Watch ids and event-router topics for loaded projects are this plus the
+/// canonical project root.
+const TOPIC_PREFIX: &str = "project-config:";
+
+/// `.funny/config.json`, checked into the project. Every field is optional
+/// and overrides the global settings for this project only, except
+/// `agent.policy`: a repo doesn't get to pick its own policy profile.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct ProjectConfig {
+    pub agent: AgentDefaults,
+    /// Extra environment for agents and terminals in this project.
+    pub env: BTreeMap<String, String>,
+    pub worktree: WorktreeNaming,
+    /// Narrows what agents may run without asking: other commands need
+    /// approval. Never skips an ask rule; `None` keeps the global policy.
+    pub allowed_commands: Option<Vec<String>>,
+    /// Commands run in each new worktree before agents start in it, e.g.
+    /// `["npm", "ci"]`, in order.
+    pub bootstrap: Vec<Vec<String>>,
+}
+
+/// Projects the user trusts to run commands from their config. Kept in the
+/// settings file: a repo can't vouch for itself.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TrustSettings {
+    /// Canonical project roots whose `bootstrap` and `agent.verify` run.
+    pub projects: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct WorktreeNaming {
+    /// Prefix for branches funny creates, e.g. `agent/`.
+    pub branch_prefix: Option<String>,
+    /// Worktree directory name; `{branch}` is replaced by the branch name
+    /// with `/` turned into `-`.
+    pub directory: Option<String>,
+}
+
+fn is_env_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+impl ProjectConfig {
+    fn validate(&self) -> Result<(), String> {
+        if let Some(name) = self.env.keys().find(|k| !is_env_name(k)) {
+            return Err(format!("env: invalid variable name {name:?}"));
+        }
+        if let Some(i) = self.bootstrap.iter().position(|argv| argv.is_empty()) {
+            return Err(format!("bootstrap[{i}]: command is empty"));
+        }
+        if let Some(prefix) = &self.worktree.branch_prefix {
+            let bad = prefix.contains("..")
+                || prefix.starts_with('/')
+                || prefix
+                    .chars()
+                    .any(|c| c.is_whitespace() || "~^:?*[\\".contains(c));
+            if bad {
+                return Err(format!(
+                    "worktree.branchPrefix: {prefix:?} is not a valid branch prefix"
+                ));
+            }
+        }
+        if let Some(directory) = &self.worktree.directory {
+            if directory.trim().is_empty()
+                || directory.contains(['/', '\\'])
+                || directory.starts_with('.')
+            {
+                return Err(format!(
+                    "worktree.directory: {directory:?} must be a plain directory name"
+                ));
+            }
+        }
+        if let Some(verify) = &self.agent.verify {
+            crate::verify::validate_config(verify).map_err(|e| format!("agent.{e}"))?;
+        }
+        if let Some(commands) = &self.allowed_commands {
+            if commands.iter().any(|c| c.trim().is_empty()) {
+                return Err("allowedCommands: entries must not be empty".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Global settings with the project's overrides applied.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectiveProjectConfig {
+    pub project: String,
+    /// The config file, if the project has one.
+    pub source: Option<String>,
+    pub agent: AgentDefaults,
+    pub env: BTreeMap<String, String>,
+    pub worktree: WorktreeNaming,
+    pub allowed_commands: Option<Vec<String>>,
+    /// Run only when `trusted`.
+    pub bootstrap: Vec<Vec<String>>,
+    /// Whether the user trusts the project to run its `bootstrap` and
+    /// `agent.verify` commands; see `project_trust`. Without that, the global
+    /// verify applies and new worktrees aren't bootstrapped.
+    pub trusted: bool,
+    /// Why the config file was ignored, if it is invalid. The global settings
+    /// apply until it is fixed.
+    pub error: Option<String>,
+}
+
+struct LoadedProject {
+    config: EffectiveProjectConfig,
+    /// Whether the watch is on `.funny/` itself, or on the project root
+    /// waiting for `.funny/` to appear.
+    watching_config_dir: bool,
+}
+
+/// Configs of open projects, kept current by a watch on each one's `.funny/`.
+pub struct ProjectConfigs {
+    loaded: Mutex<HashMap<String, LoadedProject>>,
+}
+
+impl ProjectConfigs {
+    pub fn new() -> Self {
+        Self {
+            loaded: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+fn read_config(app: &AppHandle, root: &Path) -> EffectiveProjectConfig {
+    let path = root.join(CONFIG_DIR).join(CONFIG_FILE);
+    let (project, source, error) = match std::fs::read_to_string(&path) {
+        Ok(content) => {
+            let parsed = serde_json::from_str::<ProjectConfig>(&content)
+                .map_err(|e| e.to_string())
+                .and_then(|config| config.validate().map(|_| config));
+            match parsed {
+                Ok(config) => (config, Some(path), None),
+                Err(e) => {
+                    tracing::warn!("ignoring {}: {e}", path.display());
+                    (ProjectConfig::default(), Some(path), Some(e))
+                }
+            }
+        }
+        Err(_) => (ProjectConfig::default(), None, None),
+    };
+
+    if project.agent.policy.is_some() {
+        tracing::warn!(
+            "{}/{CONFIG_DIR}/{CONFIG_FILE}: agent.policy is ignored; set the profile in the app",
+            root.display()
+        );
+    }
+    let settings = crate::settings::current(app);
+    let key = root.to_string_lossy();
+    let trusted = settings.trust.projects.iter().any(|p| *p == key);
+    let global = settings.agents;
+    EffectiveProjectConfig {
+        project: root.to_string_lossy().to_string(),
+        source: source.map(|p| p.to_string_lossy().to_string()),
+        agent: AgentDefaults {
+            provider: project.agent.provider.or(global.provider),
+            model: project.agent.model.or(global.model),
+            permission_mode: project.agent.permission_mode.or(global.permission_mode),
+            shell_sandbox: project.agent.shell_sandbox.or(global.shell_sandbox),
+            verify: match trusted {
+                true => project.agent.verify.or(global.verify),
+                false => global.verify,
+            },
+            policy: global.policy,
+        },
+        env: project.env,
+        worktree: project.worktree,
+        allowed_commands: project.allowed_commands,
+        bootstrap: project.bootstrap,
+        trusted,
+        error,
+    }
+}
+
+/// Watch `.funny/` if it exists, else the project root (non-recursively) so
+/// its creation is noticed. Ignore rules are off: `.funny/` is often
+/// gitignored.
+fn watch(app: &AppHandle, root: &Path) -> Result<bool, String> {
+    let config_dir = root.join(CONFIG_DIR);
+    let watching_config_dir = config_dir.is_dir();
+    let target: PathBuf = if watching_config_dir {
+        config_dir
+    } else {
+        root.to_path_buf()
+    };
+    watcher::start_watch(
+        app,
+        &app.state::<FileWatcher>(),
+        format!("{TOPIC_PREFIX}{}", root.to_string_lossy()),
+        &target.to_string_lossy(),
+        WatchOptions {
+            recursive: Some(false),
+            gitignore: Some(false),
+            ignore_globs: Vec::new(),
+        },
+    )?;
+    Ok(watching_config_dir)
+}
+
+/// Reload the config of the project behind the watch `id`. Returns false for
+/// watches that aren't project-config watches.
+pub(crate) fn on_watch_event(app: &AppHandle, id: &str) -> bool {
+    let key = match id.strip_prefix(TOPIC_PREFIX) {
+        Some(key) => key,
+        None => return false,
+    };
+    let root = PathBuf::from(key);
+    let config = read_config(app, &root);
+    let state = app.state::<ProjectConfigs>();
+    let mut loaded = match state.loaded.lock() {
+        Ok(loaded) => loaded,
+        Err(_) => return true,
+    };
+    let entry = match loaded.get_mut(key) {
+        Some(entry) => entry,
+        None => return true,
+    };
+    // `.funny/` was created or removed: move the watch. This replaces the
+    // watch whose thread is running this callback, which then ends.
+    if root.join(CONFIG_DIR).is_dir() != entry.watching_config_dir {
+        match watch(app, &root) {
+            Ok(watching) => entry.watching_config_dir = watching,
+            Err(e) => tracing::warn!("failed to re-watch {}: {e}", root.display()),
+        }
+    }
+    let changed = serde_json::to_value(&entry.config).ok() != serde_json::to_value(&config).ok();
+    entry.config = config.clone();
+    drop(loaded);
+
+    if changed {
+        tracing::info!("reloaded project config for {}", root.display());
+        emit_subscribed(app, id, "project-config:changed", config);
+    }
+    true
+}
+
+/// Refuse `agent` writing, moving or deleting its project's config, which
+/// would let it loosen its own rules; `relative` is the path in the project.
+pub(crate) fn check_agent_write(agent: Option<&str>, relative: &str) -> Result<(), String> {
+    let relative = relative.replace('\\', "/").to_ascii_lowercase();
+    let config = format!("{CONFIG_DIR}/{CONFIG_FILE}");
+    if agent.is_some() && (relative == CONFIG_DIR || relative == config) {
+        return Err(format!("agents may not modify {config}"));
+    }
+    Ok(())
+}
+
+/// The effective config of an open project, if it has been loaded.
+pub(crate) fn get(app: &AppHandle, root: &str) -> Option<EffectiveProjectConfig> {
+    let state = app.try_state::<ProjectConfigs>()?;
+    let loaded = state.loaded.lock().ok()?;
+    loaded.get(root).map(|entry| entry.config.clone())
+}
+
+/// Forget a project nobody has open anymore. Returns false for topics that
+/// aren't project configs.
+pub(crate) fn stop_orphaned(app: &AppHandle, topic: &str) -> bool {
+    let root = match topic.strip_prefix(TOPIC_PREFIX) {
+        Some(root) => root,
+        None => return false,
+    };
+    if let Ok(mut loaded) = app.state::<ProjectConfigs>().loaded.lock() {
+        loaded.remove(root);
+    }
+    if let Err(e) = watcher::stop_watch(&app.state::<FileWatcher>(), topic) {
+        tracing::warn!("failed to stop config watch for {root}: {e}");
+    }
+    true
+}
+
+/// Load and validate `project`'s `.funny/config.json` over the global
+/// settings, and keep it current: the calling window gets a
+/// `project-config:changed` event with the new effective config whenever the
+/// file changes, until `project_config_unload` or the window closes.
+#[tauri::command]
+pub fn project_config_load(
+    window: WebviewWindow,
+    app: AppHandle,
+    sandbox: State<'_, Sandbox>,
+    state: State<'_, ProjectConfigs>,
+    project: String,
+) -> Result<EffectiveProjectConfig, String> {
+    crate::require_app_window(&window)?;
+    let root = sandbox.check_dir(&project)?;
+    let key = root.to_string_lossy().to_string();
+    let config = read_config(&app, &root);
+
+    let mut loaded = state.loaded.lock().map_err(|e| e.to_string())?;
+    if !loaded.contains_key(&key) {
+        let watching_config_dir = watch(&app, &root)?;
+        loaded.insert(
+            key.clone(),
+            LoadedProject {
+                config: config.clone(),
+                watching_config_dir,
+            },
+        );
+    } else if let Some(entry) = loaded.get_mut(&key) {
+        entry.config = config.clone();
+    }
+    drop(loaded);
+
+    app.state::<EventRouter>()
+        .subscribe(&format!("{TOPIC_PREFIX}{key}"), window.label());
+    Ok(config)
+}
+
+/// The effective config of a project loaded with `project_config_load`.
+#[tauri::command]
+pub fn project_config_get(
+    window: WebviewWindow,
+    app: AppHandle,
+    sandbox: State<'_, Sandbox>,
+    project: String,
+) -> Result<EffectiveProjectConfig, String> {
+    crate::require_app_window(&window)?;
+    let root = sandbox.check_dir(&project)?;
+    get(&app, &root.to_string_lossy()).ok_or_else(|| format!("project {project} is not loaded"))
+}
+
+/// Trust `project` to run the commands its `.funny/config.json` supplies,
+/// `bootstrap` in new worktrees and `agent.verify` after runs, or stop
+/// trusting it. Returns the new effective config, which windows with the
+/// project open also get as `project-config:changed`.
+#[tauri::command]
+pub fn project_trust(
+    window: WebviewWindow,
+    app: AppHandle,
+    sandbox: State<'_, Sandbox>,
+    state: State<'_, ProjectConfigs>,
+    project: String,
+    trusted: bool,
+) -> Result<EffectiveProjectConfig, String> {
+    crate::require_window(&window, "main")?;
+    let root = sandbox.check_dir(&project)?;
+    let key = root.to_string_lossy().to_string();
+    crate::settings::update(&app, |settings| {
+        let projects = &mut settings.trust.projects;
+        projects.retain(|p| *p != key);
+        if trusted {
+            projects.push(key.clone());
+        }
+        Ok(())
+    })?;
+    let config = read_config(&app, &root);
+    if let Some(entry) = state
+        .loaded
+        .lock()
+        .map_err(|e| e.to_string())?
+        .get_mut(&key)
+    {
+        entry.config = config.clone();
+    }
+    tracing::info!(
+        "{} {}",
+        if trusted {
+            "trusted"
+        } else {
+            "stopped trusting"
+        },
+        root.display()
+    );
+    emit_subscribed(
+        &app,
+        &format!("{TOPIC_PREFIX}{key}"),
+        "project-config:changed",
+        config.clone(),
+    );
+    Ok(config)
+}
+
+/// Stop receiving config changes for `project`; the watch stops once no
+/// window has the project open.
+#[tauri::command]
+pub fn project_config_unload(
+    window: WebviewWindow,
+    app: AppHandle,
+    sandbox: State<'_, Sandbox>,
+    router: State<'_, EventRouter>,
+    project: String,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    let root = sandbox.check_dir(&project)?;
+    let topic = format!("{TOPIC_PREFIX}{}", root.to_string_lossy());
+    if router.unsubscribe(&topic, window.label()) {
+        stop_orphaned(&app, &topic);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn agents_may_not_modify_the_config() {
+        let agent = Some("run-1");
+        assert!(check_agent_write(agent, ".funny/config.json").is_err());
+        assert!(check_agent_write(agent, ".funny\\CONFIG.json").is_err());
+        assert!(check_agent_write(agent, ".funny").is_err());
+        assert!(check_agent_write(agent, ".funny/notes.md").is_ok());
+        assert!(check_agent_write(agent, "src/config.json").is_ok());
+        assert!(check_agent_write(None, ".funny/config.json").is_ok());
+    }
+}
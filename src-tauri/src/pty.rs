@@ -1,17 +1,40 @@
 use portable_pty::{native_pty_system, CommandBuilder, PtySize};
-use serde::Serialize;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
 use std::io::{Read, Write};
-use std::sync::Mutex;
-use tauri::{AppHandle, Emitter, State, WebviewWindow};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, EventTarget, Listener, Manager, State, WebviewWindow};
+
+use crate::cancel::CancelToken;
+use crate::clipboard::{ClipboardSource, Osc52Parser};
+use crate::confine::ShellSandbox;
+use crate::dev_servers::ServerDetector;
+use crate::events::{Coalesce, PROBLEMS, PTY_DATA};
+use crate::problems::{Diagnostic, ProblemScanner};
+use crate::redact::StreamRedactor;
+use crate::sandbox::Sandbox;
 
 type TerminalId = String;
 
 struct PtyInstance {
-    #[allow(dead_code)]
     child: Box<dyn portable_pty::Child + Send + Sync>,
     writer: Box<dyn Write + Send>,
     master: Box<dyn portable_pty::MasterPty + Send>,
+    /// Label of the window that spawned the terminal. Output goes only there,
+    /// and only that window may write to, resize or kill it.
+    window: String,
+    /// Where the terminal was opened, canonical.
+    cwd: PathBuf,
+    /// What it runs, e.g. the shell's path.
+    program: String,
+    /// How a plain shell is confined; `None` for a terminal running a
+    /// command, which can't be suspended and resumed.
+    shell: Option<ShellSandbox>,
+    /// When the shell handed the foreground to a command, as last sampled by
+    /// `busy_terminals`.
+    busy_since: Option<Instant>,
 }
 
 pub struct PtyManager {
@@ -24,65 +47,357 @@ impl PtyManager {
             instances: Mutex::new(HashMap::new()),
         }
     }
+
+    pub(crate) fn is_open(&self, id: &str) -> bool {
+        self.instances
+            .lock()
+            .map(|instances| instances.contains_key(id))
+            .unwrap_or(false)
+    }
+
+    /// Label of the window terminal `id` belongs to, if it is open.
+    pub(crate) fn window_of(&self, id: &str) -> Option<String> {
+        let instances = self.instances.lock().ok()?;
+        instances.get(id).map(|instance| instance.window.clone())
+    }
+
+    /// The working directory and program of terminal `id`, if `window` owns
+    /// it. The directory is the shell's current one where the OS tells,
+    /// else the one it was opened in.
+    pub(crate) fn cwd_and_program(&self, id: &str, window: &str) -> Option<(PathBuf, String)> {
+        let instances = self.instances.lock().ok()?;
+        let instance = instances.get(id).filter(|i| i.window == window)?;
+        let cwd = instance
+            .child
+            .process_id()
+            .and_then(current_dir)
+            .unwrap_or_else(|| instance.cwd.clone());
+        Some((cwd, instance.program.clone()))
+    }
+
+    /// Number of terminals that have been running a foreground command (not
+    /// sitting at the shell prompt) for at least `min`. Meant to be sampled
+    /// periodically; how long a command has run is measured from the first
+    /// sample that saw it. Always 0 on Windows, where there is no foreground
+    /// process group to look at.
+    pub(crate) fn busy_terminals(&self, min: Duration) -> usize {
+        let mut instances = match self.instances.lock() {
+            Ok(instances) => instances,
+            Err(_) => return 0,
+        };
+        let mut busy = 0;
+        for instance in instances.values_mut() {
+            if !running_command(instance) {
+                instance.busy_since = None;
+                continue;
+            }
+            let since = *instance.busy_since.get_or_insert_with(Instant::now);
+            if since.elapsed() >= min {
+                busy += 1;
+            }
+        }
+        busy
+    }
+
+    /// Open terminals, and how many of them are running a command.
+    pub(crate) fn counts(&self) -> (usize, usize) {
+        self.instances
+            .lock()
+            .map(|instances| {
+                let busy = instances
+                    .values()
+                    .filter(|i| i.busy_since.is_some())
+                    .count();
+                (instances.len(), busy)
+            })
+            .unwrap_or_default()
+    }
+
+    /// The open terminals, for crash reports. Uses `try_lock` and returns
+    /// `None` if the state is locked — possibly by the panicking thread.
+    pub(crate) fn snapshot(&self) -> Option<Vec<TerminalSnapshot>> {
+        let instances = self.instances.try_lock().ok()?;
+        Some(
+            instances
+                .iter()
+                .map(|(id, instance)| TerminalSnapshot {
+                    id: id.clone(),
+                    window: instance.window.clone(),
+                    pid: instance.child.process_id(),
+                    running_command: instance.busy_since.is_some(),
+                })
+                .collect(),
+        )
+    }
 }
 
-#[derive(Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TerminalSnapshot {
+    pub id: String,
+    pub window: String,
+    pub pid: Option<u32>,
+    /// A foreground command was running as of the last power-management sample.
+    pub running_command: bool,
+}
+
+#[cfg(target_os = "linux")]
+fn current_dir(pid: u32) -> Option<PathBuf> {
+    std::fs::read_link(format!("/proc/{pid}/cwd")).ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn current_dir(_pid: u32) -> Option<PathBuf> {
+    None
+}
+
+/// Whether the terminal's foreground process group is something other than
+/// the shell itself.
+#[cfg(unix)]
+fn running_command(instance: &PtyInstance) -> bool {
+    match (
+        instance.master.process_group_leader(),
+        instance.child.process_id(),
+    ) {
+        (Some(leader), Some(shell)) => leader as u32 != shell,
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn running_command(_instance: &PtyInstance) -> bool {
+    false
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 struct PtyDataPayload {
     data: String,
 }
 
-fn require_main_window(window: &WebviewWindow) -> Result<(), String> {
-    if window.label() == "main" {
-        Ok(())
+/// `problems:<id>`: diagnostics the problem matchers found in a terminal's
+/// output. Relative paths are resolved against the directory the terminal
+/// was opened in.
+#[derive(Clone, Serialize)]
+struct ProblemsPayload {
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// Spots bells (`\x07`) in PTY output. BEL also terminates OSC sequences
+/// (window titles, hyperlinks, shell integration marks), so those are skipped,
+/// including when a sequence is split across reads.
+#[derive(Default)]
+struct BellDetector {
+    escape: bool,
+    in_string: bool,
+}
+
+impl BellDetector {
+    fn feed(&mut self, bytes: &[u8]) -> bool {
+        let mut rang = false;
+        for &b in bytes {
+            if self.escape {
+                self.escape = false;
+                match b {
+                    // OSC, DCS, APC and PM strings run until BEL or ST (`ESC \`).
+                    b']' | b'P' | b'_' | b'^' => self.in_string = true,
+                    b'\\' => self.in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+            match b {
+                0x1b => self.escape = true,
+                0x07 if self.in_string => self.in_string = false,
+                0x07 => rang = true,
+                _ => {}
+            }
+        }
+        rang
+    }
+}
+
+/// The terminal `id`, if `window` owns it.
+fn owned<'a>(
+    instances: &'a mut HashMap<TerminalId, PtyInstance>,
+    id: &str,
+    window: &WebviewWindow,
+) -> Result<&'a mut PtyInstance, String> {
+    match instances.get_mut(id) {
+        Some(instance) if instance.window == window.label() => Ok(instance),
+        _ => Err("Terminal not found".to_string()),
+    }
+}
+
+/// The configured shell, else the user's.
+pub(crate) fn default_shell(app: &AppHandle) -> String {
+    if let Some(shell) = crate::settings::current(app).terminal.default_shell {
+        shell
+    } else if cfg!(windows) {
+        std::env::var("COMSPEC").unwrap_or_else(|_| "powershell.exe".to_string())
     } else {
-        Err("pty commands are only available from main".to_string())
+        std::env::var("SHELL").unwrap_or_else(|_| "/bin/bash".to_string())
     }
 }
 
+/// Open a shell in `cwd`. Agent shells pass the agent's `sandbox` profile to
+/// be confined to the project.
 #[tauri::command]
 pub fn pty_spawn(
     window: WebviewWindow,
     app: AppHandle,
-    state: State<'_, PtyManager>,
     id: String,
     cwd: String,
     rows: u16,
     cols: u16,
+    sandbox: Option<ShellSandbox>,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    let cwd = app.state::<Sandbox>().check_dir(&cwd)?;
+    let size = PtySize {
+        rows,
+        cols,
+        pixel_width: 0,
+        pixel_height: 0,
+    };
+    spawn(
+        &app,
+        &window,
+        id,
+        &cwd,
+        size,
+        Launch::Shell(sandbox.unwrap_or_default()),
+    )
+}
+
+/// What a terminal runs.
+pub(crate) enum Launch {
+    /// The configured shell, confined to the given mode.
+    Shell(ShellSandbox),
+    Command(CommandBuilder),
+}
+
+/// The shell `spawn` should run in `cwd`, confined to `sandbox`; `None` for
+/// the plain default shell.
+fn shell_command(
+    app: &AppHandle,
+    cwd: &Path,
+    sandbox: ShellSandbox,
+) -> Result<Option<CommandBuilder>, String> {
+    match sandbox {
+        ShellSandbox::Off => Ok(None),
+        mode => {
+            let mut command = CommandBuilder::new(default_shell(app));
+            crate::confine::wrap(mode, cwd, &mut command)?;
+            Ok(Some(command))
+        }
+    }
+}
+
+/// A process started in a new PTY, not yet attached to a window.
+pub(crate) struct Opened {
+    child: Box<dyn portable_pty::Child + Send + Sync>,
+    writer: Box<dyn Write + Send>,
+    master: Box<dyn portable_pty::MasterPty + Send>,
+    /// Output waits in the PTY until a terminal reads it.
+    reader: Box<dyn Read + Send>,
+    pub(crate) program: String,
+}
+
+impl Opened {
+    pub(crate) fn is_running(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    pub(crate) fn kill(&mut self) {
+        let _ = self.child.kill();
+    }
+}
+
+/// Start `command`, or by default the configured shell, in a new PTY in
+/// `cwd`.
+pub(crate) fn open(
+    app: &AppHandle,
+    cwd: &Path,
+    size: PtySize,
+    command: Option<CommandBuilder>,
+) -> Result<Opened, String> {
+    let pty_system = native_pty_system();
+
+    let pair = pty_system.openpty(size).map_err(|e| e.to_string())?;
+
+    let mut cmd = command.unwrap_or_else(|| CommandBuilder::new(default_shell(app)));
+    cmd.cwd(cwd);
+    // Lets shell integration scripts know they're running inside funny.
+    cmd.env("TERM_PROGRAM", "funny");
+    let program = cmd.get_argv().first().cloned().unwrap_or_default();
+
+    let child = pair.slave.spawn_command(cmd).map_err(|e| e.to_string())?;
+    let writer = pair.master.take_writer().map_err(|e| e.to_string())?;
+    let reader = pair.master.try_clone_reader().map_err(|e| e.to_string())?;
+    Ok(Opened {
+        child,
+        writer,
+        master: pair.master,
+        reader,
+        program: program.to_string_lossy().to_string(),
+    })
+}
+
+/// Open terminal `id` for `window`, running what `launch` says. `cwd` must
+/// already have passed the sandbox.
+pub(crate) fn spawn(
+    app: &AppHandle,
+    window: &WebviewWindow,
+    id: String,
+    cwd: &Path,
+    size: PtySize,
+    launch: Launch,
 ) -> Result<(), String> {
-    require_main_window(&window)?;
+    let state = app.state::<PtyManager>();
 
     // Check if terminal already exists (idempotency)
     {
         let instances = state.instances.lock().map_err(|e| e.to_string())?;
-        if instances.contains_key(&id) {
+        if let Some(existing) = instances.get(&id) {
+            if existing.window != window.label() {
+                return Err(format!("terminal {id} belongs to another window"));
+            }
             return Ok(());
         }
     }
 
-    let pty_system = native_pty_system();
-
-    let pair = pty_system
-        .openpty(PtySize {
-            rows,
-            cols,
-            pixel_width: 0,
-            pixel_height: 0,
-        })
-        .map_err(|e| e.to_string())?;
-
-    // Determine default shell
-    let shell_path = if cfg!(windows) {
-        std::env::var("COMSPEC").unwrap_or_else(|_| "powershell.exe".to_string())
+    let (command, shell) = match launch {
+        Launch::Shell(mode) => (shell_command(app, cwd, mode)?, Some(mode)),
+        Launch::Command(command) => (Some(command), None),
+    };
+    // The plain shell can come from the pool; the pool then makes another.
+    let plain = command.is_none();
+    let pooled = if plain {
+        crate::shell_pool::take(app, cwd)
     } else {
-        std::env::var("SHELL").unwrap_or_else(|_| "/bin/bash".to_string())
+        None
     };
+    let opened = match pooled {
+        Some(opened) => {
+            opened.master.resize(size).map_err(|e| e.to_string())?;
+            tracing::debug!("terminal {id}: using a pre-started shell");
+            opened
+        }
+        None => open(app, cwd, size, command)?,
+    };
+    if plain {
+        crate::shell_pool::replenish(app, cwd);
+    }
+    let Opened {
+        child,
+        writer,
+        master,
+        mut reader,
+        program,
+    } = opened;
 
-    let mut cmd = CommandBuilder::new(&shell_path);
-    cmd.cwd(&cwd);
-
-    let child = pair.slave.spawn_command(cmd).map_err(|e| e.to_string())?;
-    let writer = pair.master.take_writer().map_err(|e| e.to_string())?;
-    let mut reader = pair.master.try_clone_reader().map_err(|e| e.to_string())?;
+    tracing::info!("spawned terminal {id} ({program}) in {}", cwd.display());
+    crate::history::terminal_started(app, &id, window.label(), cwd, &program);
 
     // Store instance
     {
@@ -92,7 +407,12 @@ pub fn pty_spawn(
             PtyInstance {
                 child,
                 writer,
-                master: pair.master,
+                master,
+                window: window.label().to_string(),
+                cwd: cwd.to_path_buf(),
+                program,
+                shell,
+                busy_since: None,
             },
         );
     }
@@ -100,20 +420,102 @@ pub fn pty_spawn(
     // Spawn reader thread: reads PTY output and emits events to frontend
     let data_event = format!("pty:data:{}", id);
     let exit_event = format!("pty:exit:{}", id);
+    let problems_event = format!("problems:{}", id);
+    let mut problems = ProblemScanner::new(app, cwd, true);
+    let mut servers = ServerDetector::new(app, window.label(), &id);
+    let mut matcher = crate::plugins::OutputMatcher::new(app, &id);
+    let label = window.label().to_string();
+    let target = EventTarget::webview_window(&label);
+    let app = app.clone();
+
+    let mut redactor = crate::settings::current(&app)
+        .terminal
+        .redact_secrets
+        .then(StreamRedactor::default);
 
     std::thread::spawn(move || {
         let mut buf = [0u8; 4096];
+        let mut bell = BellDetector::default();
+        let mut osc52 = Osc52Parser::default();
+        let emit = |data: String| {
+            if data.is_empty() {
+                return;
+            }
+            crate::events::emit_throttled(
+                &app,
+                &label,
+                &data_event,
+                PTY_DATA,
+                &data_event,
+                Coalesce::Append,
+                PtyDataPayload { data },
+            );
+        };
+        let report = |diagnostics: Vec<Diagnostic>| {
+            if diagnostics.is_empty() {
+                return;
+            }
+            crate::events::emit_throttled(
+                &app,
+                &label,
+                &problems_event,
+                PROBLEMS,
+                &problems_event,
+                Coalesce::Merge,
+                ProblemsPayload { diagnostics },
+            );
+        };
         loop {
             match reader.read(&mut buf) {
                 Ok(0) => break,
                 Ok(n) => {
-                    let text = String::from_utf8_lossy(&buf[..n]).to_string();
-                    let _ = app.emit(&data_event, PtyDataPayload { data: text });
+                    if bell.feed(&buf[..n]) {
+                        crate::notifications::terminal_bell(&app, &id);
+                    }
+                    for copied in osc52.feed(&buf[..n]) {
+                        crate::clipboard::record(&app, Some(&id), ClipboardSource::Osc52, copied);
+                    }
+                    let text = String::from_utf8_lossy(&buf[..n]);
+                    let text = match &mut redactor {
+                        Some(redactor) => redactor.feed(&text),
+                        None => text.into_owned(),
+                    };
+                    if let Some(problems) = &mut problems {
+                        report(problems.feed(&text));
+                    }
+                    servers.feed(&text);
+                    matcher.feed(&text);
+                    crate::automation::terminal_output(&app, &id, &text);
+                    emit(text);
+                }
+                Err(e) => {
+                    tracing::debug!("terminal {id}: read failed: {e}");
+                    break;
                 }
-                Err(_) => break,
             }
         }
-        let _ = app.emit(&exit_event, ());
+        if let Some(redactor) = &mut redactor {
+            let text = redactor.finish();
+            if let Some(problems) = &mut problems {
+                report(problems.feed(&text));
+            }
+            matcher.feed(&text);
+            crate::automation::terminal_output(&app, &id, &text);
+            emit(text);
+        }
+        if let Some(problems) = &mut problems {
+            report(problems.finish());
+        }
+        tracing::info!("terminal {id} exited");
+        crate::history::terminal_exited(&app, &id);
+        crate::forward::terminal_exited(&app, &id);
+        crate::automation::terminal_exited(&app, &id);
+        crate::plugins::emit(&app, "terminal.exited", serde_json::json!({ "id": id }));
+        crate::events::flush_topic(&app, &label, &data_event);
+        crate::events::flush_topic(&app, &label, &problems_event);
+        if let Err(e) = app.emit_to(target, &exit_event, ()) {
+            tracing::debug!("terminal {id}: exit event not delivered: {e}");
+        }
     });
 
     Ok(())
@@ -126,10 +528,19 @@ pub fn pty_write(
     id: String,
     data: String,
 ) -> Result<(), String> {
-    require_main_window(&window)?;
+    crate::require_app_window(&window)?;
+    write(&state, &window, &id, &data)
+}
 
+/// Send `data` to terminal `id`, which must belong to `window`.
+pub(crate) fn write(
+    state: &PtyManager,
+    window: &WebviewWindow,
+    id: &str,
+    data: &str,
+) -> Result<(), String> {
     let mut instances = state.instances.lock().map_err(|e| e.to_string())?;
-    let instance = instances.get_mut(&id).ok_or("Terminal not found")?;
+    let instance = owned(&mut instances, id, window)?;
     instance
         .writer
         .write_all(data.as_bytes())
@@ -146,10 +557,10 @@ pub fn pty_resize(
     rows: u16,
     cols: u16,
 ) -> Result<(), String> {
-    require_main_window(&window)?;
+    crate::require_app_window(&window)?;
 
-    let instances = state.instances.lock().map_err(|e| e.to_string())?;
-    let instance = instances.get(&id).ok_or("Terminal not found")?;
+    let mut instances = state.instances.lock().map_err(|e| e.to_string())?;
+    let instance = owned(&mut instances, &id, &window)?;
     instance
         .master
         .resize(PtySize {
@@ -168,20 +579,332 @@ pub fn pty_kill(
     state: State<'_, PtyManager>,
     id: String,
 ) -> Result<(), String> {
-    require_main_window(&window)?;
+    crate::require_app_window(&window)?;
+    close(&state, &window, &id)
+}
 
+/// Kill terminal `id` if `window` owns it.
+pub(crate) fn close(state: &PtyManager, window: &WebviewWindow, id: &str) -> Result<(), String> {
     let mut instances = state.instances.lock().map_err(|e| e.to_string())?;
-    if let Some(mut instance) = instances.remove(&id) {
-        let _ = instance.child.kill();
+    if owned(&mut instances, id, window).is_ok() {
+        if let Some(mut instance) = instances.remove(id) {
+            kill(id, &mut instance);
+        }
     }
     Ok(())
 }
 
+/// What a terminal is doing, to decide which actions apply to it.
+pub(crate) struct TerminalState {
+    /// Its directory now, or where it was opened.
+    pub cwd: PathBuf,
+    /// A plain shell rather than a command.
+    pub shell: bool,
+    pub busy: bool,
+}
+
+/// The state of terminal `id`, if `window` owns it.
+pub(crate) fn terminal_state(
+    state: &PtyManager,
+    window: &WebviewWindow,
+    id: &str,
+) -> Option<TerminalState> {
+    let mut instances = state.instances.lock().ok()?;
+    let instance = owned(&mut instances, id, window).ok()?;
+    Some(TerminalState {
+        cwd: instance
+            .child
+            .process_id()
+            .and_then(current_dir)
+            .unwrap_or_else(|| instance.cwd.clone()),
+        shell: instance.shell.is_some(),
+        busy: running_command(instance),
+    })
+}
+
+/// Where shell `id` of `window` is and how it is confined, for suspending
+/// it. Shells busy with a command are refused, as suspending would kill
+/// the command.
+pub(crate) fn resumable(
+    state: &PtyManager,
+    window: &WebviewWindow,
+    id: &str,
+) -> Result<(PathBuf, ShellSandbox), String> {
+    let mut instances = state.instances.lock().map_err(|e| e.to_string())?;
+    let instance = owned(&mut instances, id, window)?;
+    let sandbox = instance
+        .shell
+        .ok_or_else(|| format!("terminal {id} runs a command, not a shell"))?;
+    if running_command(instance) {
+        return Err(format!("terminal {id} is running a command"));
+    }
+    let cwd = instance
+        .child
+        .process_id()
+        .and_then(current_dir)
+        .unwrap_or_else(|| instance.cwd.clone());
+    Ok((cwd, sandbox))
+}
+
+fn kill(id: &str, instance: &mut PtyInstance) {
+    match instance.child.kill() {
+        Ok(()) => tracing::info!("killed terminal {id}"),
+        Err(e) => tracing::warn!("failed to kill terminal {id}: {e}"),
+    }
+}
+
+/// Kill the terminals a window spawned — called when that window is destroyed
+pub fn close_window(state: &PtyManager, label: &str) {
+    if let Ok(mut instances) = state.instances.lock() {
+        instances.retain(|id, inst| {
+            if inst.window == label {
+                kill(id, inst);
+                false
+            } else {
+                true
+            }
+        });
+    }
+}
+
 /// Kill all PTY instances — called on app exit
 pub fn kill_all(state: &PtyManager) {
     if let Ok(mut instances) = state.instances.lock() {
-        for (_, mut inst) in instances.drain() {
-            let _ = inst.child.kill();
+        for (id, mut inst) in instances.drain() {
+            kill(&id, &mut inst);
+        }
+    }
+}
+
+/// Output `pty_benchmark` asks for when no size is given.
+const BENCHMARK_DEFAULT_BYTES: u64 = 64 * 1024 * 1024;
+const BENCHMARK_MAX_BYTES: u64 = 1024 * 1024 * 1024;
+/// A benchmark still running after this long is killed and fails.
+const BENCHMARK_TIMEOUT: Duration = Duration::from_secs(120);
+/// How long to wait for the last events to be delivered after output ends.
+const BENCHMARK_DRAIN_TIMEOUT: Duration = Duration::from_secs(2);
+/// Printed over and over by the synthetic process: printable text, like a
+/// chatty build log.
+const BENCHMARK_LINE: &str = "funny pty benchmark 0123456789 abcdefghijklmnopqrstuvwxyz";
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencySummary {
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PtyBenchmarkResult {
+    /// Output bypassed the event bus and was sent read by read.
+    pub bypass: bool,
+    /// Bytes read from the PTY (line endings are expanded by the PTY).
+    pub bytes: u64,
+    pub reads: u64,
+    /// `pty:data` events actually emitted.
+    pub events: u64,
+    /// From spawning the process until the last byte was emitted.
+    pub elapsed_ms: f64,
+    pub throughput_mb_per_sec: f64,
+    /// Time from a chunk being read from the PTY to the event carrying it
+    /// being emitted.
+    pub latency: LatencySummary,
+}
+
+/// Bytes read but not yet emitted, by the total they bring the stream to,
+/// and how long the emitted ones waited.
+#[derive(Default)]
+struct Delivery {
+    unsent: VecDeque<(u64, Instant)>,
+    emitted: u64,
+    events: u64,
+    latencies: Vec<Duration>,
+    last: Option<Instant>,
+}
+
+fn percentile(sorted: &[Duration], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let index = ((sorted.len() - 1) as f64 * p).round() as usize;
+    sorted[index].as_secs_f64() * 1000.0
+}
+
+fn run_benchmark(
+    app: &AppHandle,
+    label: &str,
+    bytes: u64,
+    bypass: bool,
+    cancel: &CancelToken,
+) -> Result<PtyBenchmarkResult, String> {
+    if cfg!(windows) {
+        return Err("the PTY benchmark needs a Unix shell".to_string());
+    }
+    let pair = native_pty_system()
+        .openpty(PtySize {
+            rows: 24,
+            cols: 80,
+            pixel_width: 0,
+            pixel_height: 0,
+        })
+        .map_err(|e| e.to_string())?;
+    let mut cmd = CommandBuilder::new("/bin/sh");
+    cmd.args(["-c", &format!("yes '{BENCHMARK_LINE}' | head -c {bytes}")]);
+    let started = Instant::now();
+    let mut child = pair.slave.spawn_command(cmd).map_err(|e| e.to_string())?;
+    // Our copy of the slave side would keep the reader from ever seeing EOF.
+    drop(pair.slave);
+    let mut reader = pair.master.try_clone_reader().map_err(|e| e.to_string())?;
+
+    let event = format!(
+        "pty:benchmark:{}",
+        chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default()
+    );
+    let delivery = Arc::new(Mutex::new(Delivery::default()));
+    let observed = delivery.clone();
+    let listener = app.listen_any(event.clone(), move |emitted| {
+        let len = serde_json::from_str::<PtyDataPayload>(emitted.payload())
+            .map(|payload| payload.data.len() as u64)
+            .unwrap_or(0);
+        let now = Instant::now();
+        if let Ok(mut delivery) = observed.lock() {
+            delivery.emitted += len;
+            delivery.events += 1;
+            delivery.last = Some(now);
+            while let Some(&(end, read_at)) = delivery.unsent.front() {
+                if end > delivery.emitted {
+                    break;
+                }
+                delivery.unsent.pop_front();
+                delivery.latencies.push(now.duration_since(read_at));
+            }
+        }
+    });
+
+    let mut buf = [0u8; 4096];
+    let mut bell = BellDetector::default();
+    let (mut total, mut reads) = (0u64, 0u64);
+    let outcome = loop {
+        if let Err(e) = cancel.check() {
+            break Err(e);
+        }
+        if started.elapsed() > BENCHMARK_TIMEOUT {
+            break Err("benchmark timed out".to_string());
         }
+        let n = match reader.read(&mut buf) {
+            Ok(0) | Err(_) => break Ok(()),
+            Ok(n) => n,
+        };
+        // The same per-read work as a real terminal, minus redaction.
+        bell.feed(&buf[..n]);
+        let data = String::from_utf8_lossy(&buf[..n]).into_owned();
+        total += data.len() as u64;
+        reads += 1;
+        if let Ok(mut delivery) = delivery.lock() {
+            delivery.unsent.push_back((total, Instant::now()));
+        }
+        let payload = PtyDataPayload { data };
+        if bypass {
+            let _ = app.emit_to(EventTarget::webview_window(label), &event, payload);
+        } else {
+            crate::events::emit_throttled(
+                app,
+                label,
+                &event,
+                PTY_DATA,
+                &event,
+                Coalesce::Append,
+                payload,
+            );
+        }
+    };
+    crate::events::flush_topic(app, label, &event);
+    if outcome.is_err() {
+        let _ = child.kill();
+    }
+    let _ = child.wait();
+
+    let drain_until = Instant::now() + BENCHMARK_DRAIN_TIMEOUT;
+    while outcome.is_ok()
+        && Instant::now() < drain_until
+        && delivery.lock().is_ok_and(|d| d.emitted < total)
+    {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    app.unlisten(listener);
+    outcome?;
+
+    let mut delivery = delivery.lock().map_err(|e| e.to_string())?;
+    let elapsed = delivery.last.unwrap_or_else(Instant::now) - started;
+    delivery.latencies.sort();
+    let latencies = &delivery.latencies;
+    Ok(PtyBenchmarkResult {
+        bypass,
+        bytes: total,
+        reads,
+        events: delivery.events,
+        elapsed_ms: elapsed.as_secs_f64() * 1000.0,
+        throughput_mb_per_sec: total as f64 / (1024.0 * 1024.0) / elapsed.as_secs_f64().max(1e-9),
+        latency: LatencySummary {
+            p50_ms: percentile(latencies, 0.50),
+            p95_ms: percentile(latencies, 0.95),
+            p99_ms: percentile(latencies, 0.99),
+            max_ms: latencies
+                .last()
+                .map(|d| d.as_secs_f64() * 1000.0)
+                .unwrap_or(0.0),
+        },
+    })
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PtyBenchmarkOptions {
+    /// How much output the synthetic process writes. 64 MiB by default.
+    pub bytes: Option<u64>,
+    /// Emit every read directly instead of through the event bus, to compare
+    /// against the throttled path.
+    pub bypass: Option<bool>,
+}
+
+/// Internal: measure terminal output throughput and event latency by running
+/// a process that floods a PTY and pushing its output to the calling window
+/// the way a terminal's would be, on an event nothing listens to. For
+/// validating changes to the IPC and flow control; stop it with
+/// `cancel(request_id)`.
+#[tauri::command]
+pub async fn pty_benchmark(
+    window: WebviewWindow,
+    app: AppHandle,
+    options: Option<PtyBenchmarkOptions>,
+    request_id: Option<String>,
+) -> Result<PtyBenchmarkResult, String> {
+    crate::require_app_window(&window)?;
+    let options = options.unwrap_or_default();
+    let bytes = options.bytes.unwrap_or(BENCHMARK_DEFAULT_BYTES);
+    if bytes == 0 || bytes > BENCHMARK_MAX_BYTES {
+        return Err(format!(
+            "benchmark size must be between 1 and {BENCHMARK_MAX_BYTES} bytes"
+        ));
     }
+    let bypass = options.bypass.unwrap_or(false);
+    let label = window.label().to_string();
+    let guard = crate::cancel::register(&app, request_id.as_deref());
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        run_benchmark(&app, &label, bytes, bypass, &guard.token())
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    tracing::info!(
+        "pty benchmark: {} bytes in {:.0} ms ({:.1} MB/s, {} events, p99 {:.1} ms)",
+        result.bytes,
+        result.elapsed_ms,
+        result.throughput_mb_per_sec,
+        result.events,
+        result.latency.p99_ms
+    );
+    Ok(result)
 }
@@ -0,0 +1,281 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager, State, WebviewWindow};
+
+use crate::sandbox::Sandbox;
+
+/// Scores halve after this long without a touch, so files worked on this week
+/// outrank files that were hot a month ago.
+const HALF_LIFE_MS: f64 = 3.0 * 24.0 * 60.0 * 60.0 * 1000.0;
+
+/// Repeated changes to one file within this window count as a single touch:
+/// one editor save or agent write usually produces several fs events, and a
+/// project can be watched more than once.
+const COALESCE_MS: i64 = 2000;
+
+/// Least-recently-relevant files are dropped beyond this many per project.
+const MAX_FILES_PER_PROJECT: usize = 5000;
+
+/// The store is written at most this often; the rest is flushed on exit.
+const SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Default number of files returned by `recent_files`.
+const DEFAULT_LIMIT: usize = 20;
+
+const STORE_FILE: &str = "recent-files.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TouchSource {
+    /// A change seen by the file watcher; could be anyone.
+    Fs,
+    /// Reported from an agent run manifest.
+    Agent,
+    /// Saved from the app's editor.
+    User,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FileActivity {
+    touches: u32,
+    agent_touches: u32,
+    user_touches: u32,
+    last_touched_ms: i64,
+    /// Decayed touch count as of `last_touched_ms`.
+    score: f64,
+}
+
+impl FileActivity {
+    fn score_at(&self, now: i64) -> f64 {
+        let age = (now - self.last_touched_ms).max(0) as f64;
+        self.score * 0.5f64.powf(age / HALF_LIFE_MS)
+    }
+}
+
+#[derive(Default)]
+struct RecentState {
+    loaded: bool,
+    /// Canonical project root → root-relative path → activity.
+    projects: HashMap<String, HashMap<String, FileActivity>>,
+    dirty: bool,
+    last_saved: Option<Instant>,
+}
+
+pub struct RecentFiles {
+    state: Mutex<RecentState>,
+}
+
+impl RecentFiles {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(RecentState::default()),
+        }
+    }
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+fn store_path(app: &AppHandle) -> Option<PathBuf> {
+    app.path()
+        .app_data_dir()
+        .ok()
+        .map(|dir| dir.join(STORE_FILE))
+}
+
+/// Load the store from disk the first time it is needed.
+fn ensure_loaded(app: &AppHandle, state: &mut RecentState) {
+    if state.loaded {
+        return;
+    }
+    state.loaded = true;
+    if let Some(content) = store_path(app).and_then(|p| std::fs::read_to_string(p).ok()) {
+        state.projects = serde_json::from_str(&content).unwrap_or_default();
+    }
+}
+
+fn save(app: &AppHandle, state: &mut RecentState) {
+    let path = match store_path(app) {
+        Some(path) => path,
+        None => return,
+    };
+    let json = match serde_json::to_vec(&state.projects) {
+        Ok(json) => json,
+        Err(_) => return,
+    };
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    let tmp = path.with_extension("json.tmp");
+    if std::fs::write(&tmp, json).is_ok() && std::fs::rename(&tmp, &path).is_ok() {
+        state.dirty = false;
+        state.last_saved = Some(Instant::now());
+    }
+}
+
+fn prune(files: &mut HashMap<String, FileActivity>, now: i64) {
+    if files.len() <= MAX_FILES_PER_PROJECT {
+        return;
+    }
+    let mut scores: Vec<f64> = files.values().map(|a| a.score_at(now)).collect();
+    scores.sort_by(|a, b| b.total_cmp(a));
+    let cutoff = scores[MAX_FILES_PER_PROJECT - 1];
+    files.retain(|_, a| a.score_at(now) >= cutoff);
+}
+
+/// Count a touch of each root-relative path under the canonical `root`.
+/// Watcher changes for paths that no longer exist forget the file instead;
+/// directories are ignored.
+pub(crate) fn record(app: &AppHandle, root: &Path, paths: &[String], source: TouchSource) {
+    let recent = match app.try_state::<RecentFiles>() {
+        Some(recent) => recent,
+        None => return,
+    };
+    let mut state = match recent.state.lock() {
+        Ok(state) => state,
+        Err(_) => return,
+    };
+    ensure_loaded(app, &mut state);
+
+    let now = now_ms();
+    let files = state
+        .projects
+        .entry(root.to_string_lossy().to_string())
+        .or_default();
+    for rel in paths {
+        match std::fs::metadata(root.join(rel)) {
+            Ok(meta) if meta.is_file() => {}
+            Ok(_) => continue,
+            Err(_) => {
+                if source == TouchSource::Fs {
+                    files.remove(rel);
+                }
+                continue;
+            }
+        }
+        let activity = files.entry(rel.clone()).or_default();
+        match source {
+            TouchSource::Agent => activity.agent_touches += 1,
+            TouchSource::User => activity.user_touches += 1,
+            TouchSource::Fs => {}
+        }
+        if activity.touches > 0 && now - activity.last_touched_ms < COALESCE_MS {
+            continue;
+        }
+        activity.score = activity.score_at(now) + 1.0;
+        activity.touches += 1;
+        activity.last_touched_ms = now;
+    }
+    prune(files, now);
+
+    state.dirty = true;
+    let due = state
+        .last_saved
+        .map(|t| t.elapsed() >= SAVE_INTERVAL)
+        .unwrap_or(true);
+    if due {
+        save(app, &mut state);
+    }
+}
+
+/// Write pending changes to disk — called on app exit.
+pub fn flush(app: &AppHandle) {
+    if let Some(recent) = app.try_state::<RecentFiles>() {
+        if let Ok(mut state) = recent.state.lock() {
+            if state.dirty {
+                save(app, &mut state);
+            }
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentFile {
+    pub path: String,
+    pub touches: u32,
+    pub agent_touches: u32,
+    pub user_touches: u32,
+    pub last_touched_ms: i64,
+    /// Touch count with exponential decay (3-day half-life); the sort key.
+    pub score: f64,
+}
+
+/// Files in `project` ranked by how often and how recently they were touched,
+/// by agents, the editor or anything else the file watcher saw.
+#[tauri::command]
+pub fn recent_files(
+    window: WebviewWindow,
+    app: AppHandle,
+    sandbox: State<'_, Sandbox>,
+    state: State<'_, RecentFiles>,
+    project: String,
+    limit: Option<u32>,
+) -> Result<Vec<RecentFile>, String> {
+    crate::require_app_window(&window)?;
+    let root = sandbox.check_dir(&project)?;
+    let mut state = state.state.lock().map_err(|e| e.to_string())?;
+    ensure_loaded(&app, &mut state);
+
+    let now = now_ms();
+    let mut files: Vec<RecentFile> = state
+        .projects
+        .get(root.to_string_lossy().as_ref())
+        .map(|files| {
+            files
+                .iter()
+                .map(|(path, a)| RecentFile {
+                    path: path.clone(),
+                    touches: a.touches,
+                    agent_touches: a.agent_touches,
+                    user_touches: a.user_touches,
+                    last_touched_ms: a.last_touched_ms,
+                    score: a.score_at(now),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| b.last_touched_ms.cmp(&a.last_touched_ms))
+    });
+    files.truncate(limit.map(|l| l as usize).unwrap_or(DEFAULT_LIMIT));
+    Ok(files)
+}
+
+/// Report files an agent run or the user touched, e.g. from a run manifest's
+/// entries. `paths` may be absolute or relative to `project`.
+#[tauri::command]
+pub fn record_file_touches(
+    window: WebviewWindow,
+    app: AppHandle,
+    sandbox: State<'_, Sandbox>,
+    project: String,
+    paths: Vec<String>,
+    source: TouchSource,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    let root = sandbox.check_dir(&project)?;
+    let rels = paths
+        .iter()
+        .map(|p| {
+            let resolved = sandbox.resolve(&project, p)?;
+            Ok(resolved
+                .strip_prefix(&root)
+                .unwrap_or(&resolved)
+                .to_string_lossy()
+                .replace('\\', "/"))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    record(&app, &root, &rels, source);
+    Ok(())
+}
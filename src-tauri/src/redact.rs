@@ -0,0 +1,253 @@
+use regex::Regex;
+use std::io::Write;
+use std::sync::{OnceLock, RwLock};
+use tracing_subscriber::fmt::MakeWriter;
+
+/// Stored secrets shorter than this aren't masked by value: they'd match
+/// ordinary text.
+const MIN_SECRET_LEN: usize = 8;
+
+/// A trailing run of token characters at least this long is held back from
+/// PTY output until the next read, in case it's the start of a secret.
+const MIN_HOLD: usize = 4;
+
+/// Longer runs are let through rather than buffered indefinitely.
+const MAX_HOLD: usize = 512;
+
+const MASK: &str = "[redacted]";
+
+/// Known token formats: provider API keys, forge tokens, AWS access keys,
+/// bearer tokens.
+fn token_patterns() -> &'static [(Regex, &'static str)] {
+    static PATTERNS: OnceLock<Vec<(Regex, &'static str)>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        [
+            (r"sk-ant-[A-Za-z0-9_\-]{16,}", "[redacted-anthropic-key]"),
+            (r"sk-[A-Za-z0-9_\-]{20,}", "[redacted-api-key]"),
+            (r"gh[pousr]_[A-Za-z0-9]{20,}", "[redacted-github-token]"),
+            (r"github_pat_[A-Za-z0-9_]{20,}", "[redacted-github-token]"),
+            (r"glpat-[A-Za-z0-9_\-]{20,}", "[redacted-gitlab-token]"),
+            (r"AKIA[0-9A-Z]{16}", "[redacted-aws-key]"),
+            (r"(?i)bearer\s+[A-Za-z0-9._\-]{8,}", "Bearer [redacted]"),
+        ]
+        .into_iter()
+        .map(|(pattern, replacement)| (Regex::new(pattern).expect("valid pattern"), replacement))
+        .collect()
+    })
+}
+
+/// Secrets spelled out as assignments, which also catches formats with no
+/// recognizable shape. Too eager for terminal output (it would mask
+/// `token = getToken()` in source listings), so only logs get these.
+fn assignment_patterns() -> &'static [(Regex, &'static str)] {
+    static PATTERNS: OnceLock<Vec<(Regex, &'static str)>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        [
+            // `token=…`, `"password": "…"` and the like.
+            (
+                r#"(?i)((?:api[_-]?key|token|secret|password|passwd)["']?\s*[:=]\s*["']?)[^\s"',;&]+"#,
+                "${1}[redacted]",
+            ),
+            // Credentials embedded in URLs.
+            (r"([A-Za-z][A-Za-z0-9+.\-]*://)[^/\s:@]*:[^/\s@]+@", "${1}[redacted]@"),
+        ]
+        .into_iter()
+        .map(|(pattern, replacement)| (Regex::new(pattern).expect("valid pattern"), replacement))
+        .collect()
+    })
+}
+
+/// Prefixes of `token_patterns`, for holding back partial tokens.
+const TOKEN_PREFIXES: &[&str] = &[
+    "sk-",
+    "ghp_",
+    "gho_",
+    "ghu_",
+    "ghs_",
+    "ghr_",
+    "github_pat_",
+    "glpat-",
+    "AKIA",
+];
+
+/// Values of the secrets in the keychain, longest first so a secret that
+/// contains another is masked whole. Global because the log writers have no
+/// app handle.
+fn secret_values() -> &'static RwLock<Vec<String>> {
+    static VALUES: OnceLock<RwLock<Vec<String>>> = OnceLock::new();
+    VALUES.get_or_init(|| RwLock::new(Vec::new()))
+}
+
+/// Replace the set of stored secret values to mask.
+pub(crate) fn set_secret_values(values: impl IntoIterator<Item = String>) {
+    let mut values: Vec<String> = values
+        .into_iter()
+        .filter(|v| v.len() >= MIN_SECRET_LEN)
+        .collect();
+    values.sort_by_key(|v| std::cmp::Reverse(v.len()));
+    values.dedup();
+    if let Ok(mut current) = secret_values().write() {
+        *current = values;
+    }
+}
+
+fn apply(text: &str, patterns: &[(Regex, &'static str)]) -> String {
+    let mut text = text.to_string();
+    if let Ok(values) = secret_values().read() {
+        for value in values.iter() {
+            if text.contains(value.as_str()) {
+                text = text.replace(value.as_str(), MASK);
+            }
+        }
+    }
+    for (pattern, replacement) in patterns {
+        if pattern.is_match(&text) {
+            text = pattern.replace_all(&text, *replacement).into_owned();
+        }
+    }
+    text
+}
+
+/// Mask stored secrets and known token formats. For terminal output and
+/// transcripts.
+pub(crate) fn redact_tokens(text: &str) -> String {
+    apply(text, token_patterns())
+}
+
+/// `redact_tokens` plus secret-looking assignments and URL credentials. For
+/// logs and diagnostics.
+pub(crate) fn redact(text: &str) -> String {
+    apply(&apply(text, token_patterns()), assignment_patterns())
+}
+
+fn is_token_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.')
+}
+
+/// Redacts a stream of terminal output. A secret split across two reads
+/// would slip through a per-chunk filter, so a trailing run that could be the
+/// start of one is held until the next chunk completes (or rules it out).
+#[derive(Default)]
+pub(crate) struct StreamRedactor {
+    carry: String,
+}
+
+impl StreamRedactor {
+    pub(crate) fn feed(&mut self, chunk: &str) -> String {
+        let mut text = std::mem::take(&mut self.carry);
+        text.push_str(chunk);
+        let run_start = text
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| is_token_char(*c))
+            .last()
+            .map(|(i, _)| i)
+            .unwrap_or(text.len());
+        let run = &text[run_start..];
+        if (MIN_HOLD..=MAX_HOLD).contains(&run.len()) && could_be_secret(run) {
+            self.carry = run.to_string();
+            text.truncate(run_start);
+        }
+        redact_tokens(&text)
+    }
+
+    /// Whatever is still held back, once the stream ends.
+    pub(crate) fn finish(&mut self) -> String {
+        redact_tokens(&std::mem::take(&mut self.carry))
+    }
+}
+
+fn could_be_secret(run: &str) -> bool {
+    TOKEN_PREFIXES
+        .iter()
+        .any(|p| run.starts_with(p) || p.starts_with(run))
+        || secret_values()
+            .read()
+            .is_ok_and(|values| values.iter().any(|v| v.starts_with(run)))
+}
+
+/// Wraps a log writer so every formatted event is redacted before it is
+/// written. The fmt layer writes each event in one call, so patterns don't
+/// straddle writes.
+pub(crate) struct Redacting<M>(pub M);
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for Redacting<M> {
+    type Writer = RedactingWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter(self.0.make_writer())
+    }
+}
+
+pub(crate) struct RedactingWriter<W>(W);
+
+impl<W: Write> Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0
+            .write_all(redact(&String::from_utf8_lossy(buf)).as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream(chunks: &[&str]) -> String {
+        let mut redactor = StreamRedactor::default();
+        let mut out: String = chunks.iter().map(|chunk| redactor.feed(chunk)).collect();
+        out.push_str(&redactor.finish());
+        out
+    }
+
+    #[test]
+    fn plain_output_passes_through() {
+        let mut redactor = StreamRedactor::default();
+        assert_eq!(redactor.feed("cargo build\r\n"), "cargo build\r\n");
+        assert_eq!(redactor.finish(), "");
+    }
+
+    #[test]
+    fn tokens_split_across_reads_are_masked() {
+        let mut redactor = StreamRedactor::default();
+        assert_eq!(redactor.feed("key: sk-ant-abc"), "key: ");
+        assert_eq!(
+            redactor.feed("def0123456789xyz done"),
+            "[redacted-anthropic-key] done"
+        );
+        assert_eq!(
+            stream(&["export GH=ghp_", "abcdefghij", "klmnopqrstuvwx\n"]),
+            "export GH=[redacted-github-token]\n"
+        );
+    }
+
+    #[test]
+    fn held_back_text_is_released_at_the_end() {
+        let mut redactor = StreamRedactor::default();
+        assert_eq!(redactor.feed("prefix sk-a"), "prefix ");
+        assert_eq!(redactor.finish(), "sk-a");
+    }
+
+    #[test]
+    fn short_and_long_runs_are_not_held_back() {
+        let mut redactor = StreamRedactor::default();
+        // Typed words would echo late otherwise.
+        assert_eq!(redactor.feed("$ sk"), "$ sk");
+        let run = format!("sk-{}", "a".repeat(MAX_HOLD));
+        assert_eq!(redactor.feed(&run), redact_tokens(&run));
+    }
+
+    #[test]
+    fn stored_secrets_split_across_reads_are_masked() {
+        set_secret_values(["correct-horse-battery".to_string()]);
+        assert_eq!(
+            stream(&["pass correct-hor", "se-battery ok"]),
+            "pass [redacted] ok"
+        );
+        set_secret_values([]);
+    }
+}
@@ -0,0 +1,208 @@
+use portable_pty::{CommandBuilder, PtySize};
+use serde::{Deserialize, Serialize};
+use std::process::Stdio;
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+use crate::pty::Launch;
+use crate::sandbox::Sandbox;
+
+/// Keepalives for plain ssh, so a dead connection ends the terminal within
+/// a minute instead of hanging it.
+const SSH_KEEPALIVE: [&str; 4] = [
+    "-o",
+    "ServerAliveInterval=15",
+    "-o",
+    "ServerAliveCountMax=4",
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Transport {
+    /// mosh when it is installed here and on the host, else ssh.
+    #[default]
+    Auto,
+    /// Survives roaming and sleep, but needs `mosh-server` on the host and
+    /// its UDP ports open.
+    Mosh,
+    Ssh,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransportCheck {
+    /// `mosh` is on PATH here.
+    pub mosh_local: bool,
+    /// `mosh-server` is on the host's PATH; `None` if the host couldn't be
+    /// asked without a password prompt.
+    pub mosh_remote: Option<bool>,
+}
+
+/// Ask `destination` over a non-interactive ssh whether `mosh-server` is
+/// installed.
+fn mosh_on_host(app: &AppHandle, destination: &str) -> Option<bool> {
+    let status = crate::remote_fs::ssh(app, destination)
+        .arg("command -v mosh-server")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .ok()?;
+    // 255 is ssh's own failure: unreachable or asking for a password.
+    match status.code() {
+        Some(0) => Some(true),
+        Some(255) | None => None,
+        Some(_) => Some(false),
+    }
+}
+
+fn check(app: &AppHandle, destination: &str) -> TransportCheck {
+    let mosh_local = crate::diagnostics::find_executable("mosh").is_some();
+    TransportCheck {
+        mosh_local,
+        mosh_remote: if mosh_local {
+            mosh_on_host(app, destination)
+        } else {
+            None
+        },
+    }
+}
+
+/// Whether mosh can be used for `destination`.
+#[tauri::command]
+pub async fn remote_transports(
+    window: WebviewWindow,
+    app: AppHandle,
+    destination: String,
+) -> Result<TransportCheck, String> {
+    crate::require_app_window(&window)?;
+    crate::forward::check_destination(&destination)?;
+    tauri::async_runtime::spawn_blocking(move || Ok(check(&app, &destination)))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteTerminalRequest {
+    /// As passed to `ssh`: `user@host` or a `Host` from `~/.ssh/config`.
+    pub destination: String,
+    #[serde(default)]
+    pub transport: Transport,
+    /// Run on the host instead of the login shell, e.g. `tmux new -A -s
+    /// agents` to come back to the same session.
+    pub command: Option<String>,
+    pub rows: u16,
+    pub cols: u16,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteTerminal {
+    /// `mosh` or `ssh`, never `auto`.
+    pub transport: Transport,
+    /// Why `auto` or `mosh` ended up on ssh.
+    pub fallback: Option<String>,
+}
+
+/// Pick the transport: `Auto` becomes mosh only when it is known to work
+/// on both ends, and an unusable `Mosh` falls back to ssh.
+fn choose(app: &AppHandle, requested: Transport, destination: &str) -> (Transport, Option<String>) {
+    if requested == Transport::Ssh {
+        return (Transport::Ssh, None);
+    }
+    let found = check(app, destination);
+    let fallback = match (found.mosh_local, found.mosh_remote) {
+        (false, _) => "mosh is not installed here",
+        (true, Some(false)) => "mosh-server is not installed on the host",
+        (true, None) if requested == Transport::Auto => {
+            "could not check the host for mosh-server without a password"
+        }
+        _ => return (Transport::Mosh, None),
+    };
+    (Transport::Ssh, Some(fallback.to_string()))
+}
+
+fn remote_command(
+    app: &AppHandle,
+    transport: Transport,
+    name: &str,
+    command: Option<&str>,
+) -> Result<CommandBuilder, String> {
+    let (options, destination) = crate::remotes::ssh_target(app, name);
+    let destination = destination.as_str();
+    let program = if transport == Transport::Mosh {
+        "mosh"
+    } else {
+        "ssh"
+    };
+    let path = crate::diagnostics::find_executable(program)
+        .ok_or_else(|| format!("{program} not found on PATH"))?;
+    let mut builder = CommandBuilder::new(path);
+    if transport == Transport::Mosh {
+        if !options.is_empty() {
+            // mosh logs in with this ssh command line.
+            let ssh: Vec<String> = options.iter().map(|o| crate::tmux::quote(o)).collect();
+            builder.arg(format!("--ssh=ssh {}", ssh.join(" ")));
+        }
+        builder.args(["--", destination]);
+        if let Some(command) = command {
+            // mosh runs the words after the host as the remote command.
+            builder.args(crate::open::split_command(command)?);
+        }
+    } else {
+        builder.args(SSH_KEEPALIVE);
+        builder.args(&options);
+        if command.is_some() {
+            builder.arg("-t");
+        }
+        builder.args(["--", destination]);
+        if let Some(command) = command {
+            builder.arg(command);
+        }
+    }
+    Ok(builder)
+}
+
+/// Open terminal `id` on a remote host over mosh or ssh, for a project at
+/// `cwd`. Password and host key prompts appear in the terminal. Port
+/// forwards into the session still go over ssh.
+#[tauri::command]
+pub async fn remote_spawn(
+    window: WebviewWindow,
+    app: AppHandle,
+    id: String,
+    cwd: String,
+    request: RemoteTerminalRequest,
+) -> Result<RemoteTerminal, String> {
+    crate::require_app_window(&window)?;
+    crate::forward::check_destination(&request.destination)?;
+    let cwd = app.state::<Sandbox>().check_dir(&cwd)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let (transport, fallback) = choose(&app, request.transport, &request.destination);
+        if let Some(reason) = &fallback {
+            tracing::info!(
+                "terminal {id}: using ssh to {}: {reason}",
+                request.destination
+            );
+        }
+        let command = remote_command(
+            &app,
+            transport,
+            &request.destination,
+            request.command.as_deref(),
+        )?;
+        let size = PtySize {
+            rows: request.rows,
+            cols: request.cols,
+            pixel_width: 0,
+            pixel_height: 0,
+        };
+        crate::pty::spawn(&app, &window, id, &cwd, size, Launch::Command(command))?;
+        Ok(RemoteTerminal {
+            transport,
+            fallback,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,389 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use tauri::AppHandle;
+
+use crate::fs::{DirEntry, FileStat};
+use crate::tmux::quote;
+
+/// Prefix of a project that lives on an SSH host: `ssh://<destination>/<path>`,
+/// with `destination` as passed to `ssh` and `path` absolute on the host.
+pub(crate) const SCHEME: &str = "ssh://";
+
+/// Shell functions shared by the scripts below, run with `sh` on the host.
+/// `real` resolves a path like `canonicalize` does, but also for a file that
+/// doesn't exist yet; `inside` fails unless its argument lies in `$root`;
+/// `meta` prints `kind size mtime writable`, following links with `-L`,
+/// from either GNU or BSD `stat`.
+const PRELUDE: &str = r#"
+root=$(cd -P -- "$1" 2>/dev/null && pwd -P) || { echo "$1: no such directory" >&2; exit 2; }
+p=$2
+shift 2
+real() {
+    if [ -d "$1" ]; then (cd -P -- "$1" && pwd -P); return; fi
+    d=$(cd -P -- "$(dirname -- "$1")" 2>/dev/null && pwd -P) || return 1
+    t=$d/$(basename -- "$1")
+    if [ -L "$t" ]; then readlink -f -- "$t"; else printf '%s\n' "$t"; fi
+}
+inside() {
+    case $1 in "$root"|"$root"/*) return 0 ;; esac
+    echo "$p is outside of $root" >&2
+    exit 3
+}
+meta() {
+    if [ "$1" = -L ]; then
+        shift
+        s=$(stat -L -c '%s %Y' -- "$1" 2>/dev/null || stat -L -f '%z %m' -- "$1") || return 1
+        k=file
+    else
+        s=$(stat -c '%s %Y' -- "$1" 2>/dev/null || stat -f '%z %m' -- "$1") || return 1
+        k=file
+        if [ -L "$1" ]; then k=symlink; fi
+    fi
+    if [ $k = file ] && [ -d "$1" ]; then k=directory; fi
+    w=1
+    [ -w "$1" ] || w=0
+    printf '%s %s %s' "$k" "$s" "$w"
+}
+"#;
+
+/// Prints the file's `meta` line, then up to `$1` of its bytes.
+const READ: &str = r#"
+r=$(real "$p") || { echo "$p: no such file" >&2; exit 2; }
+inside "$r"
+[ -f "$r" ] || { echo "$p is not a file" >&2; exit 2; }
+meta -L "$r" && echo && head -c "$1" -- "$r"
+"#;
+
+const STAT: &str = r#"
+r=$(real "$p") || { echo "$p: no such file or directory" >&2; exit 2; }
+inside "$r"
+[ -e "$r" ] || { echo "$p: no such file or directory" >&2; exit 2; }
+meta -L "$r"
+"#;
+
+/// NUL-terminated `meta<TAB>name` records of at most `$1` entries, then
+/// `truncated` if there were more.
+const LIST: &str = r#"
+r=$(real "$p") || { echo "$p: no such directory" >&2; exit 2; }
+inside "$r"
+[ -d "$r" ] || { echo "$p is not a directory" >&2; exit 2; }
+n=0
+for f in "$r"/* "$r"/.[!.]* "$r"/..?*; do
+    [ -e "$f" ] || [ -L "$f" ] || continue
+    n=$((n + 1))
+    if [ $n -gt "$1" ]; then printf 'truncated\0'; break; fi
+    printf '%s\t%s\0' "$(meta "$f")" "${f##*/}"
+done
+"#;
+
+/// Writes stdin to a temp file next to the target and renames it over, after
+/// checking the mtime against `$1` (seconds, empty to skip). `$2` = 1 creates
+/// missing parents, once their closest existing ancestor is known to be
+/// inside the project. The temp file starts as a copy so permissions stay.
+const WRITE: &str = r#"
+if [ "$2" = 1 ]; then
+    a=$(dirname -- "$p")
+    while [ ! -d "$a" ]; do a=$(dirname -- "$a"); done
+    inside "$(cd -P -- "$a" && pwd -P)"
+    mkdir -p -- "$(dirname -- "$p")" || exit 2
+fi
+r=$(real "$p") || { echo "$p: no such directory" >&2; exit 2; }
+inside "$r"
+if [ -n "$1" ] && [ -e "$r" ]; then
+    set -- "$1" $(meta -L "$r")
+    [ "$4" = "$1" ] || { echo "$p was modified on disk since it was read" >&2; exit 4; }
+fi
+t=$(dirname -- "$r")/.$(basename -- "$r").funny-$$.tmp
+if [ -e "$r" ]; then cp -p -- "$r" "$t" || exit 2; fi
+if cat > "$t" && mv -f -- "$t" "$r"; then meta -L "$r"; else rm -f -- "$t"; exit 2; fi
+"#;
+
+/// A project directory on an SSH host.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct RemoteRoot {
+    pub destination: String,
+    /// Absolute on the host, without a trailing slash.
+    pub path: String,
+}
+
+impl RemoteRoot {
+    /// Parse an `ssh://` URI; `None` for a local path.
+    pub(crate) fn parse(uri: &str) -> Option<Result<Self, String>> {
+        let rest = uri.strip_prefix(SCHEME)?;
+        Some((|| {
+            let (destination, path) = rest
+                .split_once('/')
+                .ok_or_else(|| format!("{uri} has no path"))?;
+            crate::forward::check_destination(destination)?;
+            Ok(Self {
+                destination: destination.to_string(),
+                path: normalize("/", &format!("/{path}"))?,
+            })
+        })())
+    }
+
+    pub(crate) fn uri(&self, path: &str) -> String {
+        format!("{SCHEME}{}{path}", self.destination)
+    }
+
+    /// Whether `other` is this directory or lies inside it.
+    pub(crate) fn contains(&self, other: &RemoteRoot) -> bool {
+        self.destination == other.destination
+            && (other.path == self.path
+                || self.path == "/"
+                || other.path.starts_with(&format!("{}/", self.path)))
+    }
+
+    /// `<parent>/.funny-worktrees/<name>`, where the server puts the
+    /// project's thread worktrees.
+    pub(crate) fn worktrees(&self) -> Option<RemoteRoot> {
+        let (parent, name) = self.path.rsplit_once('/')?;
+        (!name.is_empty()).then(|| RemoteRoot {
+            destination: self.destination.clone(),
+            path: format!("{parent}/{}/{name}", crate::sandbox::WORKTREE_DIR_NAME),
+        })
+    }
+
+    /// `path` (absolute, relative to the root, or an `ssh://` URI on the same
+    /// host) as an absolute host path inside the root. Only checked by name
+    /// here; the scripts check again once symlinks are resolved.
+    pub(crate) fn resolve(&self, path: &str) -> Result<String, String> {
+        let path = path
+            .strip_prefix(SCHEME)
+            .and_then(|rest| rest.strip_prefix(self.destination.as_str()))
+            .filter(|rest| rest.starts_with('/'))
+            .unwrap_or(path);
+        let joined = normalize(&self.path, path)?;
+        let inside = RemoteRoot {
+            destination: self.destination.clone(),
+            path: joined.clone(),
+        };
+        if !self.contains(&inside) {
+            return Err(format!("{path} is outside of {}", self.path));
+        }
+        Ok(joined)
+    }
+
+    /// Run `script` (after `PRELUDE`) on the host with `$1` = the root,
+    /// `$2` = `path` and the rest of `args`, feeding it `input`.
+    fn run(
+        &self,
+        app: &AppHandle,
+        script: &str,
+        path: &str,
+        args: &[&str],
+        input: Option<&[u8]>,
+    ) -> Result<Vec<u8>, String> {
+        let mut words = vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            quote(&format!("{PRELUDE}{script}")),
+            "sh".to_string(),
+            quote(&self.path),
+            quote(path),
+        ];
+        words.extend(args.iter().map(|a| quote(a)));
+        let mut child = ssh(app, &self.destination)
+            .arg(words.join(" "))
+            .stdin(if input.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("failed to run ssh: {e}"))?;
+        if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+            stdin.write_all(input).map_err(|e| e.to_string())?;
+        }
+        let output = child.wait_with_output().map_err(|e| e.to_string())?;
+        if output.status.success() {
+            return Ok(output.stdout);
+        }
+        let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        Err(if message.is_empty() {
+            format!("ssh to {} failed ({})", self.destination, output.status)
+        } else {
+            message
+        })
+    }
+}
+
+/// `path` joined to `base` with `.` and `..` applied, refusing to climb above
+/// `/`.
+fn normalize(base: &str, path: &str) -> Result<String, String> {
+    let joined = if path.starts_with('/') {
+        path.to_string()
+    } else {
+        format!("{base}/{path}")
+    };
+    let mut parts: Vec<&str> = Vec::new();
+    for part in joined.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop().ok_or_else(|| format!("invalid path {path}"))?;
+            }
+            part => parts.push(part),
+        }
+    }
+    Ok(format!("/{}", parts.join("/")))
+}
+
+/// `ssh` to `name` (a registered remote's alias or a plain destination)
+/// without prompts. Outside Windows, connections to a host are shared: with
+/// the remote's pooled connection when it is up, otherwise for a minute so a
+/// burst of file operations pays for one handshake.
+pub(crate) fn ssh(app: &AppHandle, name: &str) -> Command {
+    ssh_with(app, name, &[])
+}
+
+/// `ssh` with `extra` options, which win over the defaults: ssh keeps the
+/// first value it is given for an option.
+pub(crate) fn ssh_with(app: &AppHandle, name: &str, extra: &[&str]) -> Command {
+    let (options, destination) = crate::remotes::ssh_target(app, name);
+    let mut command = Command::new("ssh");
+    command
+        .args(extra)
+        .args(["-T", "-o", "BatchMode=yes", "-o", "ConnectTimeout=10"]);
+    #[cfg(not(windows))]
+    {
+        let socket = std::env::temp_dir().join("funny-ssh-%C");
+        command
+            .args(["-o", "ControlMaster=auto", "-o", "ControlPersist=60"])
+            .arg("-o")
+            .arg(format!("ControlPath={}", socket.display()));
+    }
+    command.args(options).arg("--").arg(destination);
+    command
+}
+
+fn parse_meta(root: &RemoteRoot, path: &str, line: &str) -> Result<FileStat, String> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let [kind, size, mtime, writable] = fields[..] else {
+        return Err(format!("unexpected stat output for {path}: {line}"));
+    };
+    Ok(FileStat {
+        path: root.uri(path),
+        kind: kind.to_string(),
+        size: size.parse().unwrap_or(0),
+        modified_ms: mtime.parse::<i64>().ok().map(|s| s * 1000),
+        readonly: writable != "1",
+    })
+}
+
+/// The file's stat and up to `limit` of its bytes.
+pub(crate) fn read(
+    app: &AppHandle,
+    root: &RemoteRoot,
+    path: &str,
+    limit: u64,
+) -> Result<(FileStat, Vec<u8>), String> {
+    let path = root.resolve(path)?;
+    let output = root.run(app, READ, &path, &[&limit.to_string()], None)?;
+    let split = output
+        .iter()
+        .position(|&b| b == b'\n')
+        .ok_or_else(|| format!("unexpected output reading {path}"))?;
+    let stat = parse_meta(root, &path, &String::from_utf8_lossy(&output[..split]))?;
+    Ok((stat, output[split + 1..].to_vec()))
+}
+
+pub(crate) fn stat(app: &AppHandle, root: &RemoteRoot, path: &str) -> Result<FileStat, String> {
+    let path = root.resolve(path)?;
+    let output = root.run(app, STAT, &path, &[], None)?;
+    parse_meta(root, &path, &String::from_utf8_lossy(&output))
+}
+
+/// The directory's entries (unsorted), with links not followed, and whether
+/// there were more than `limit`.
+pub(crate) fn list(
+    app: &AppHandle,
+    root: &RemoteRoot,
+    path: &str,
+    limit: usize,
+) -> Result<(String, Vec<DirEntry>, bool), String> {
+    let path = root.resolve(path)?;
+    let output = root.run(app, LIST, &path, &[&limit.to_string()], None)?;
+    let mut entries = Vec::new();
+    let mut truncated = false;
+    for record in output.split(|&b| b == 0).filter(|r| !r.is_empty()) {
+        let record = String::from_utf8_lossy(record);
+        let Some((meta, name)) = record.split_once('\t') else {
+            truncated |= record == "truncated";
+            continue;
+        };
+        let entry = format!("{}/{name}", path.trim_end_matches('/'));
+        let stat = parse_meta(root, &entry, meta)?;
+        entries.push(DirEntry {
+            name: name.to_string(),
+            path: stat.path,
+            kind: stat.kind,
+            size: stat.size,
+            modified_ms: stat.modified_ms,
+        });
+    }
+    Ok((root.uri(&path), entries, truncated))
+}
+
+/// Replace the file's content with `bytes` atomically on the host.
+pub(crate) fn write(
+    app: &AppHandle,
+    root: &RemoteRoot,
+    path: &str,
+    bytes: &[u8],
+    expected_modified_ms: Option<i64>,
+    create_dirs: bool,
+) -> Result<FileStat, String> {
+    let path = root.resolve(path)?;
+    let expected = expected_modified_ms
+        .map(|ms| (ms / 1000).to_string())
+        .unwrap_or_default();
+    let create = if create_dirs { "1" } else { "0" };
+    let output = root.run(app, WRITE, &path, &[&expected, create], Some(bytes))?;
+    parse_meta(root, &path, &String::from_utf8_lossy(&output))
+}
+
+/// `ssh` running `argv` in `dir` on the host, with `env` added, without a
+/// shell interpreting it.
+pub(crate) fn command_in(
+    app: &AppHandle,
+    dir: &RemoteRoot,
+    argv: &[String],
+    env: &BTreeMap<String, String>,
+) -> Command {
+    let mut words = vec![
+        "sh".to_string(),
+        "-c".to_string(),
+        quote(r#"cd -- "$1" || exit 2; shift; exec "$@""#),
+        "sh".to_string(),
+        quote(&dir.path),
+    ];
+    if !env.is_empty() {
+        words.push("env".to_string());
+        words.extend(env.iter().map(|(k, v)| quote(&format!("{k}={v}"))));
+    }
+    words.extend(argv.iter().map(|a| quote(a)));
+    let mut command = ssh(app, &dir.destination);
+    command.arg(words.join(" "));
+    command
+}
+
+/// Start `script` in the project directory on the host, for streaming
+/// commands like search.
+pub(crate) fn spawn_in(
+    app: &AppHandle,
+    root: &RemoteRoot,
+    script: &str,
+) -> Result<std::process::Child, String> {
+    let script = format!("cd -- {} || exit 2\n{script}", quote(&root.path));
+    ssh(app, &root.destination)
+        .arg(format!("sh -c {}", quote(&script)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("failed to run ssh: {e}"))
+}
@@ -0,0 +1,240 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Stdio};
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, WebviewWindow};
+
+use crate::tmux::quote;
+
+/// The port the server sidecar listens on: its default `PORT`.
+const SERVER_PORT: u16 = 3001;
+
+/// Port on the host that tunnels back to the server; the runner dials it.
+const TUNNEL_PORT: u16 = 39301;
+
+/// A funny runner started on a registered host. It connects back to the
+/// local server through an ssh reverse tunnel, so the server can hand it
+/// threads: their worktrees are created, agents run, and builds happen on
+/// the host, while this app only drives them.
+struct Runner {
+    child: Child,
+    started_at: String,
+}
+
+pub struct RunnerManager {
+    runners: Mutex<HashMap<String, Runner>>,
+}
+
+impl RunnerManager {
+    pub fn new() -> Self {
+        Self {
+            runners: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunnerOutput {
+    pub alias: String,
+    /// A line the runner printed, redacted; on first start it includes the
+    /// code to approve it with in the server's "Link a runner" dialog.
+    pub line: String,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunnerExited {
+    pub alias: String,
+    pub code: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunnerInfo {
+    pub alias: String,
+    pub started_at: String,
+}
+
+/// Send each line of `pipe` to the main window as `remote:runner-output`.
+fn forward(app: &AppHandle, alias: &str, pipe: impl std::io::Read + Send + 'static) {
+    let (app, alias) = (app.clone(), alias.to_string());
+    std::thread::spawn(move || {
+        for line in BufReader::new(pipe).lines().map_while(Result::ok) {
+            let _ = app.emit_to(
+                "main",
+                "remote:runner-output",
+                RunnerOutput {
+                    alias: alias.clone(),
+                    line: crate::redact::redact(&line),
+                },
+            );
+        }
+    });
+}
+
+/// Wait for the runner of `alias` to end, then forget it and say so with
+/// `remote:runner-exited`.
+fn watch(app: &AppHandle, alias: &str) {
+    let (app, alias) = (app.clone(), alias.to_string());
+    std::thread::spawn(move || loop {
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let manager = app.state::<RunnerManager>();
+        let Ok(mut runners) = manager.runners.lock() else {
+            return;
+        };
+        let Some(runner) = runners.get_mut(&alias) else {
+            return;
+        };
+        let Ok(Some(status)) = runner.child.try_wait() else {
+            continue;
+        };
+        runners.remove(&alias);
+        drop(runners);
+        tracing::info!("runner on {alias} exited with {status}");
+        let _ = app.emit_to(
+            "main",
+            "remote:runner-exited",
+            RunnerExited {
+                alias,
+                code: status.code(),
+            },
+        );
+        return;
+    });
+}
+
+fn start(app: &AppHandle, alias: &str) -> Result<RunnerInfo, String> {
+    let host =
+        crate::remotes::find(app, alias)?.ok_or_else(|| format!("no remote host {alias}"))?;
+    let manager = app.state::<RunnerManager>();
+    let mut runners = manager.runners.lock().map_err(|e| e.to_string())?;
+    if let Some(runner) = runners.get(alias) {
+        return Ok(RunnerInfo {
+            alias: alias.to_string(),
+            started_at: runner.started_at.clone(),
+        });
+    }
+    let url = format!("http://127.0.0.1:{TUNNEL_PORT}");
+    let runner = format!(
+        "{} --team {url}",
+        host.runner_command.as_deref().unwrap_or("funny")
+    );
+    let tunnel = format!("{TUNNEL_PORT}:127.0.0.1:{SERVER_PORT}");
+    // Its own connection, so the tunnel goes away with it, and a login
+    // shell, where the user's PATH finds bun and funny.
+    let mut child = crate::remote_fs::ssh_with(
+        app,
+        alias,
+        &[
+            "-o",
+            "ControlPath=none",
+            "-R",
+            &tunnel,
+            "-o",
+            "ExitOnForwardFailure=yes",
+            "-o",
+            "ServerAliveInterval=15",
+            "-o",
+            "ServerAliveCountMax=4",
+        ],
+    )
+    .arg(format!(
+        "WS_TUNNEL_ONLY=true exec \"${{SHELL:-sh}}\" -lc {}",
+        quote(&runner)
+    ))
+    .stdin(Stdio::null())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .map_err(|e| format!("failed to run ssh: {e}"))?;
+    if let Some(stdout) = child.stdout.take() {
+        forward(app, alias, stdout);
+    }
+    if let Some(stderr) = child.stderr.take() {
+        forward(app, alias, stderr);
+    }
+    let started_at = chrono::Utc::now().to_rfc3339();
+    runners.insert(
+        alias.to_string(),
+        Runner {
+            child,
+            started_at: started_at.clone(),
+        },
+    );
+    drop(runners);
+    tracing::info!("started runner on {alias}");
+    watch(app, alias);
+    Ok(RunnerInfo {
+        alias: alias.to_string(),
+        started_at,
+    })
+}
+
+/// Stop every runner — called on app exit. To the server they are runners
+/// that disconnected.
+pub fn stop_all(state: &RunnerManager) {
+    let Ok(mut runners) = state.runners.lock() else {
+        return;
+    };
+    for (_, mut runner) in runners.drain() {
+        let _ = runner.child.kill();
+    }
+}
+
+/// Start a runner on the registered host `alias` (idempotent), so threads
+/// of its `ssh://` projects can execute there end to end: worktree, agent
+/// and verification. The first time, approve it in the server's "Link a
+/// runner" dialog with the code in its `remote:runner-output`.
+#[tauri::command]
+pub async fn remote_runner_start(
+    window: WebviewWindow,
+    app: AppHandle,
+    alias: String,
+) -> Result<RunnerInfo, String> {
+    crate::require_window(&window, "main")?;
+    tauri::async_runtime::spawn_blocking(move || start(&app, &alias))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub fn remote_runner_stop(
+    window: WebviewWindow,
+    app: AppHandle,
+    alias: String,
+) -> Result<(), String> {
+    crate::require_window(&window, "main")?;
+    let manager = app.state::<RunnerManager>();
+    let mut runners = manager.runners.lock().map_err(|e| e.to_string())?;
+    let mut runner = runners
+        .remove(&alias)
+        .ok_or_else(|| format!("no runner on {alias}"))?;
+    drop(runners);
+    runner.child.kill().map_err(|e| e.to_string())?;
+    let _ = runner.child.wait();
+    let _ = app.emit_to(
+        "main",
+        "remote:runner-exited",
+        RunnerExited { alias, code: None },
+    );
+    Ok(())
+}
+
+/// The runners this app started that are still up.
+#[tauri::command]
+pub fn remote_runners(window: WebviewWindow, app: AppHandle) -> Result<Vec<RunnerInfo>, String> {
+    crate::require_app_window(&window)?;
+    let manager = app.state::<RunnerManager>();
+    let runners = manager.runners.lock().map_err(|e| e.to_string())?;
+    let mut list: Vec<RunnerInfo> = runners
+        .iter()
+        .map(|(alias, runner)| RunnerInfo {
+            alias: alias.clone(),
+            started_at: runner.started_at.clone(),
+        })
+        .collect();
+    list.sort_by(|a, b| a.alias.cmp(&b.alias));
+    Ok(list)
+}
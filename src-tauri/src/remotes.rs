@@ -0,0 +1,550 @@
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Read;
+use std::process::{Child, Stdio};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, WebviewWindow};
+
+/// How often pooled connections are checked, and dropped ones reopened.
+const HEALTH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How long `remote_connect` waits for the host to let us in.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "method"
+)]
+pub enum AuthMethod {
+    /// Whatever ssh-agent and `~/.ssh/config` provide.
+    #[default]
+    Agent,
+    Key {
+        identity_file: String,
+    },
+    /// Only terminals can log in, since they show the prompt; file access
+    /// and the pooled connection need a key.
+    Password,
+}
+
+/// A registered SSH host. Its alias stands in for the destination in
+/// `ssh://<alias>/<path>` projects and remote terminals.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteHost {
+    pub alias: String,
+    /// `user@host`, or a `Host` from `~/.ssh/config`.
+    pub destination: String,
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub auth: AuthMethod,
+    /// Project directories on the host, absolute, offered when opening it.
+    #[serde(default)]
+    pub projects: Vec<String>,
+    /// Open the pooled connection at startup.
+    #[serde(default)]
+    pub keep_connected: bool,
+    /// How to start funny on the host for `remote_runner_start`, in a login
+    /// shell; `funny` when unset.
+    #[serde(default)]
+    pub runner_command: Option<String>,
+}
+
+impl RemoteHost {
+    fn validate(&self) -> Result<(), String> {
+        let valid_alias = !self.alias.is_empty()
+            && !self.alias.starts_with('-')
+            && self
+                .alias
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'));
+        if !valid_alias {
+            return Err(format!(
+                "invalid alias {:?}: use letters, digits, '.', '-' and '_'",
+                self.alias
+            ));
+        }
+        crate::forward::check_destination(&self.destination)?;
+        if let AuthMethod::Key { identity_file } = &self.auth {
+            if identity_file.trim().is_empty() {
+                return Err("key authentication needs an identity file".to_string());
+            }
+        }
+        if let Some(project) = self.projects.iter().find(|p| !p.starts_with('/')) {
+            return Err(format!("project path {project} must be absolute"));
+        }
+        Ok(())
+    }
+
+    /// The options and destination to hand `ssh`.
+    fn target(&self) -> (Vec<String>, String) {
+        let mut options = Vec::new();
+        if let Some(port) = self.port {
+            options.extend(["-p".to_string(), port.to_string()]);
+        }
+        if let AuthMethod::Key { identity_file } = &self.auth {
+            options.extend([
+                "-i".to_string(),
+                identity_file.clone(),
+                "-o".to_string(),
+                "IdentitiesOnly=yes".to_string(),
+            ]);
+        }
+        (options, self.destination.clone())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "state"
+)]
+pub enum HostStatus {
+    Disconnected,
+    Connecting,
+    Connected {
+        /// Round trip of a no-op command over the pooled connection.
+        latency_ms: u64,
+        checked_at: String,
+    },
+    /// Retried on the next health check.
+    Failed {
+        reason: String,
+        checked_at: String,
+    },
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusEvent {
+    pub alias: String,
+    pub status: HostStatus,
+}
+
+/// A pooled connection: an `ssh -M` master the file operations, searches and
+/// probes to its host go through. Windows' OpenSSH can't share connections,
+/// so there only the status is kept.
+struct Connection {
+    status: HostStatus,
+    master: Option<Child>,
+}
+
+/// The hosts the user asked to be connected to; a host stays here, and is
+/// reconnected when it drops, until `remote_disconnect`.
+pub struct RemoteManager {
+    connections: Mutex<HashMap<String, Connection>>,
+}
+
+impl RemoteManager {
+    pub fn new() -> Self {
+        Self {
+            connections: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+fn load(app: &AppHandle) -> Result<Vec<RemoteHost>, String> {
+    let rows: Vec<String> = crate::db::with(app, |conn| {
+        let mut statement = conn.prepare("SELECT host FROM remote_hosts ORDER BY alias")?;
+        let rows = statement.query_map([], |row| row.get(0))?;
+        rows.collect()
+    })?;
+    Ok(rows
+        .iter()
+        .filter_map(|json| serde_json::from_str(json).ok())
+        .collect())
+}
+
+pub(crate) fn find(app: &AppHandle, alias: &str) -> Result<Option<RemoteHost>, String> {
+    let json: Option<String> = crate::db::with(app, |conn| {
+        let mut statement = conn.prepare("SELECT host FROM remote_hosts WHERE alias = ?1")?;
+        let mut rows = statement.query_map(params![alias], |row| row.get(0))?;
+        rows.next().transpose()
+    })?;
+    Ok(json.and_then(|json| serde_json::from_str(&json).ok()))
+}
+
+/// The ssh options and destination for `name`: a registered alias brings
+/// its destination, port and key; anything else is passed on as it is.
+pub(crate) fn ssh_target(app: &AppHandle, name: &str) -> (Vec<String>, String) {
+    match find(app, name) {
+        Ok(Some(host)) => host.target(),
+        _ => (Vec::new(), name.to_string()),
+    }
+}
+
+fn now() -> String {
+    chrono::Utc::now().to_rfc3339()
+}
+
+/// Record `status` for a host still in the pool, telling the main window
+/// when its state changed.
+fn set_status(app: &AppHandle, alias: &str, status: HostStatus) {
+    let manager = app.state::<RemoteManager>();
+    let Ok(mut connections) = manager.connections.lock() else {
+        return;
+    };
+    let Some(connection) = connections.get_mut(alias) else {
+        return;
+    };
+    let changed = std::mem::discriminant(&connection.status) != std::mem::discriminant(&status);
+    connection.status = status.clone();
+    drop(connections);
+    if changed {
+        let _ = app.emit_to(
+            "main",
+            "remote:status",
+            StatusEvent {
+                alias: alias.to_string(),
+                status,
+            },
+        );
+    }
+}
+
+/// Time a no-op command on the host.
+fn probe(app: &AppHandle, alias: &str) -> Result<u64, String> {
+    let started = Instant::now();
+    let output = crate::remote_fs::ssh(app, alias)
+        .arg("true")
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("failed to run ssh: {e}"))?;
+    if output.status.success() {
+        return Ok(started.elapsed().as_millis() as u64);
+    }
+    let message = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    Err(if message.is_empty() {
+        format!("ssh exited with {}", output.status)
+    } else {
+        message
+    })
+}
+
+/// Start the master connection that later ssh runs to `alias` share.
+#[cfg(not(windows))]
+fn spawn_master(app: &AppHandle, alias: &str) -> Result<Option<Child>, String> {
+    crate::remote_fs::ssh_with(
+        app,
+        alias,
+        &[
+            "-M",
+            "-N",
+            "-o",
+            "ControlPersist=no",
+            "-o",
+            "ServerAliveInterval=15",
+            "-o",
+            "ServerAliveCountMax=4",
+            "-o",
+            "LogLevel=ERROR",
+        ],
+    )
+    .stdin(Stdio::null())
+    .stdout(Stdio::null())
+    .stderr(Stdio::piped())
+    .spawn()
+    .map(Some)
+    .map_err(|e| format!("failed to run ssh: {e}"))
+}
+
+#[cfg(windows)]
+fn spawn_master(_app: &AppHandle, _alias: &str) -> Result<Option<Child>, String> {
+    Ok(None)
+}
+
+/// Why the master of `alias` exited, if it did.
+fn master_exited(app: &AppHandle, alias: &str) -> Option<String> {
+    let manager = app.state::<RemoteManager>();
+    let mut connections = manager.connections.lock().ok()?;
+    let master = connections.get_mut(alias)?.master.as_mut()?;
+    let status = master.try_wait().ok()??;
+    let mut message = String::new();
+    if let Some(mut stderr) = master.stderr.take() {
+        let _ = stderr.read_to_string(&mut message);
+    }
+    connections.get_mut(alias)?.master = None;
+    let message = message.trim();
+    Some(if message.is_empty() {
+        format!("connection closed ({status})")
+    } else {
+        message.to_string()
+    })
+}
+
+/// Open (or reopen) the pooled connection to `alias` and wait until it
+/// carries commands.
+fn connect(app: &AppHandle, alias: &str) -> Result<HostStatus, String> {
+    let host = find(app, alias)?.ok_or_else(|| format!("no remote host {alias}"))?;
+    if host.auth == AuthMethod::Password {
+        return Err(format!(
+            "{alias} uses password authentication; open a terminal to it instead"
+        ));
+    }
+    let manager = app.state::<RemoteManager>();
+    {
+        let mut connections = manager.connections.lock().map_err(|e| e.to_string())?;
+        let connection = connections.entry(alias.to_string()).or_insert(Connection {
+            status: HostStatus::Disconnected,
+            master: None,
+        });
+        if connection.master.is_none() {
+            connection.master = spawn_master(app, alias)?;
+        }
+    }
+    set_status(app, alias, HostStatus::Connecting);
+
+    let deadline = Instant::now() + CONNECT_TIMEOUT;
+    let status = loop {
+        if let Some(reason) = master_exited(app, alias) {
+            break HostStatus::Failed {
+                reason,
+                checked_at: now(),
+            };
+        }
+        // Until the master is up, `-O check` fails without connecting.
+        let ready = cfg!(windows)
+            || crate::remote_fs::ssh_with(app, alias, &["-O", "check"])
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status()
+                .is_ok_and(|s| s.success());
+        if ready {
+            break match probe(app, alias) {
+                Ok(latency_ms) => HostStatus::Connected {
+                    latency_ms,
+                    checked_at: now(),
+                },
+                Err(reason) => HostStatus::Failed {
+                    reason,
+                    checked_at: now(),
+                },
+            };
+        }
+        if Instant::now() >= deadline {
+            break HostStatus::Failed {
+                reason: format!("no connection after {}s", CONNECT_TIMEOUT.as_secs()),
+                checked_at: now(),
+            };
+        }
+        std::thread::sleep(Duration::from_millis(250));
+    };
+    set_status(app, alias, status.clone());
+    Ok(status)
+}
+
+/// Close the pooled connection to `alias` and forget it.
+fn disconnect(app: &AppHandle, alias: &str) {
+    let manager = app.state::<RemoteManager>();
+    let removed = manager
+        .connections
+        .lock()
+        .ok()
+        .and_then(|mut connections| connections.remove(alias));
+    let Some(connection) = removed else {
+        return;
+    };
+    if let Some(mut master) = connection.master {
+        let _ = master.kill();
+        let _ = master.wait();
+    }
+    let _ = app.emit_to(
+        "main",
+        "remote:status",
+        StatusEvent {
+            alias: alias.to_string(),
+            status: HostStatus::Disconnected,
+        },
+    );
+}
+
+/// Check every pooled connection, reopening the ones that dropped.
+fn check_all(app: &AppHandle) {
+    let aliases: Vec<String> = match app.state::<RemoteManager>().connections.lock() {
+        Ok(connections) => connections.keys().cloned().collect(),
+        Err(_) => return,
+    };
+    for alias in aliases {
+        let exited = master_exited(app, &alias).is_some();
+        let missing = !cfg!(windows)
+            && app
+                .state::<RemoteManager>()
+                .connections
+                .lock()
+                .is_ok_and(|c| c.get(&alias).is_some_and(|c| c.master.is_none()));
+        if exited || missing {
+            if let Err(e) = connect(app, &alias) {
+                tracing::debug!("remote {alias} not reconnected: {e}");
+            }
+            continue;
+        }
+        let status = match probe(app, &alias) {
+            Ok(latency_ms) => HostStatus::Connected {
+                latency_ms,
+                checked_at: now(),
+            },
+            Err(reason) => HostStatus::Failed {
+                reason,
+                checked_at: now(),
+            },
+        };
+        set_status(app, &alias, status);
+    }
+}
+
+/// Connect the hosts marked `keep_connected` and start the health checks.
+/// Called once from `setup`.
+pub fn init(app: &AppHandle) {
+    let app = app.clone();
+    std::thread::spawn(move || {
+        for host in load(&app).unwrap_or_default() {
+            if host.keep_connected && host.auth != AuthMethod::Password {
+                if let Err(e) = connect(&app, &host.alias) {
+                    tracing::debug!("remote {} not connected: {e}", host.alias);
+                }
+            }
+        }
+        loop {
+            std::thread::sleep(HEALTH_INTERVAL);
+            check_all(&app);
+        }
+    });
+}
+
+/// Close every pooled connection — called on app exit.
+pub fn disconnect_all(state: &RemoteManager) {
+    let Ok(mut connections) = state.connections.lock() else {
+        return;
+    };
+    for (_, connection) in connections.drain() {
+        if let Some(mut master) = connection.master {
+            let _ = master.kill();
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteHostInfo {
+    #[serde(flatten)]
+    pub host: RemoteHost,
+    pub status: HostStatus,
+}
+
+/// The registered hosts, by alias, with their connection status.
+#[tauri::command]
+pub async fn remote_hosts(
+    window: WebviewWindow,
+    app: AppHandle,
+) -> Result<Vec<RemoteHostInfo>, String> {
+    crate::require_app_window(&window)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let hosts = load(&app)?;
+        let connections = app
+            .state::<RemoteManager>()
+            .connections
+            .lock()
+            .map_err(|e| e.to_string())?
+            .iter()
+            .map(|(alias, c)| (alias.clone(), c.status.clone()))
+            .collect::<HashMap<_, _>>();
+        Ok(hosts
+            .into_iter()
+            .map(|host| RemoteHostInfo {
+                status: connections
+                    .get(&host.alias)
+                    .cloned()
+                    .unwrap_or(HostStatus::Disconnected),
+                host,
+            })
+            .collect())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Register `host`, replacing one with the same alias. An open connection
+/// is reopened with the new settings.
+#[tauri::command]
+pub async fn remote_host_save(
+    window: WebviewWindow,
+    app: AppHandle,
+    host: RemoteHost,
+) -> Result<(), String> {
+    crate::require_window(&window, "main")?;
+    host.validate()?;
+    let json = serde_json::to_string(&host).map_err(|e| e.to_string())?;
+    tauri::async_runtime::spawn_blocking(move || {
+        crate::db::with(&app, |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO remote_hosts (alias, host, updated_at)
+                 VALUES (?1, ?2, ?3)",
+                params![host.alias, json, now()],
+            )
+        })?;
+        let connected = app
+            .state::<RemoteManager>()
+            .connections
+            .lock()
+            .is_ok_and(|c| c.contains_key(&host.alias));
+        if connected {
+            disconnect(&app, &host.alias);
+            connect(&app, &host.alias)?;
+        }
+        Ok(())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn remote_host_delete(
+    window: WebviewWindow,
+    app: AppHandle,
+    alias: String,
+) -> Result<(), String> {
+    crate::require_window(&window, "main")?;
+    tauri::async_runtime::spawn_blocking(move || {
+        disconnect(&app, &alias);
+        crate::db::with(&app, |conn| {
+            conn.execute("DELETE FROM remote_hosts WHERE alias = ?1", params![alias])
+        })
+        .map(|_| ())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Open the pooled connection to `alias`; it is kept up, and reopened when
+/// it drops, until `remote_disconnect`. Status changes arrive as
+/// `remote:status` events.
+#[tauri::command]
+pub async fn remote_connect(
+    window: WebviewWindow,
+    app: AppHandle,
+    alias: String,
+) -> Result<HostStatus, String> {
+    crate::require_app_window(&window)?;
+    tauri::async_runtime::spawn_blocking(move || connect(&app, &alias))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn remote_disconnect(
+    window: WebviewWindow,
+    app: AppHandle,
+    alias: String,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    tauri::async_runtime::spawn_blocking(move || disconnect(&app, &alias))
+        .await
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,242 @@
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tauri::{AppHandle, WebviewWindow};
+
+use crate::timeline::{TimeRange, TimelineKind};
+
+/// Periods covered when no range is given.
+const DEFAULT_DAYS: i64 = 14;
+const DEFAULT_WEEKS: i64 = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Period {
+    Day,
+    /// Weeks start on Monday.
+    Week,
+}
+
+/// How the parallel-agent workflow did over one period.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeriodStats {
+    /// First day of the period, `YYYY-MM-DD` in local time.
+    pub start: String,
+    /// Agent runs started in the period.
+    pub runs: u32,
+    pub failed_runs: u32,
+    /// Time those runs ran, until now for ones still running.
+    pub agent_hours: f64,
+    /// Tokens and cost of the runs whose provider reports them.
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost_usd: f64,
+    pub commits: u32,
+    pub pull_requests: u32,
+    pub verifications: u32,
+    pub verifications_passed: u32,
+    /// `verifications_passed` of `verifications`, if there were any.
+    pub pass_rate: Option<f64>,
+}
+
+impl PeriodStats {
+    fn add(&mut self, other: &PeriodStats) {
+        self.runs += other.runs;
+        self.failed_runs += other.failed_runs;
+        self.agent_hours += other.agent_hours;
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cost_usd += other.cost_usd;
+        self.commits += other.commits;
+        self.pull_requests += other.pull_requests;
+        self.verifications += other.verifications;
+        self.verifications_passed += other.verifications_passed;
+    }
+
+    fn finish(&mut self) {
+        self.pass_rate = (self.verifications > 0)
+            .then(|| f64::from(self.verifications_passed) / f64::from(self.verifications));
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsReport {
+    pub project: Option<String>,
+    pub period: Period,
+    /// RFC 3339.
+    pub since: String,
+    pub until: String,
+    /// Oldest first, including periods with nothing in them.
+    pub periods: Vec<PeriodStats>,
+    pub total: PeriodStats,
+}
+
+fn period_start(date: NaiveDate, period: Period) -> NaiveDate {
+    match period {
+        Period::Day => date,
+        Period::Week => date - Duration::days(i64::from(date.weekday().num_days_from_monday())),
+    }
+}
+
+fn next_start(start: NaiveDate, period: Period) -> NaiveDate {
+    start
+        + match period {
+            Period::Day => Duration::days(1),
+            Period::Week => Duration::weeks(1),
+        }
+}
+
+fn parse_time(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+/// The stats of the local period `value` falls in, if it is in the report.
+fn bucket<'a>(
+    buckets: &'a mut BTreeMap<NaiveDate, PeriodStats>,
+    value: &str,
+    period: Period,
+) -> Option<&'a mut PeriodStats> {
+    let start = period_start(
+        parse_time(value)?.with_timezone(&Local).date_naive(),
+        period,
+    );
+    buckets.get_mut(&start)
+}
+
+fn build(
+    app: &AppHandle,
+    project: Option<String>,
+    period: Period,
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+) -> Result<StatsReport, String> {
+    let (since_text, until_text) = (since.to_rfc3339(), until.to_rfc3339());
+    let mut buckets: BTreeMap<NaiveDate, PeriodStats> = BTreeMap::new();
+    let first = period_start(since.with_timezone(&Local).date_naive(), period);
+    let last = until.with_timezone(&Local).date_naive();
+    let mut start = first;
+    while start <= last {
+        buckets.insert(start, PeriodStats::default());
+        start = next_start(start, period);
+    }
+
+    let now = Utc::now();
+    for run in crate::history::started_between(app, project.as_deref(), &since_text, &until_text)? {
+        let Some(stats) = bucket(&mut buckets, &run.started_at, period) else {
+            continue;
+        };
+        stats.runs += 1;
+        if run.status == "failed" {
+            stats.failed_runs += 1;
+        }
+        if let Some(started) = parse_time(&run.started_at) {
+            let ended = run
+                .finished_at
+                .as_deref()
+                .and_then(parse_time)
+                .unwrap_or(now);
+            stats.agent_hours += (ended - started).num_seconds().max(0) as f64 / 3600.0;
+        }
+        if let Some(tokens) = run.tokens {
+            stats.input_tokens += tokens.input;
+            stats.output_tokens += tokens.output;
+        }
+        stats.cost_usd += run.cost_usd.unwrap_or(0.0);
+    }
+
+    let events = crate::timeline::query(
+        app,
+        project.clone(),
+        TimeRange {
+            since: Some(since_text.clone()),
+            until: Some(until_text.clone()),
+        },
+        Some(vec![TimelineKind::CommitCreated, TimelineKind::PrOpened]),
+        None,
+    )?;
+    for event in events {
+        if let Some(stats) = bucket(&mut buckets, &event.timestamp, period) {
+            match event.kind {
+                TimelineKind::CommitCreated => stats.commits += 1,
+                _ => stats.pull_requests += 1,
+            }
+        }
+    }
+
+    for (started_at, passed) in
+        crate::verify::results_between(app, project.as_deref(), &since_text, &until_text)?
+    {
+        if let Some(stats) = bucket(&mut buckets, &started_at, period) {
+            stats.verifications += 1;
+            if passed {
+                stats.verifications_passed += 1;
+            }
+        }
+    }
+
+    let mut total = PeriodStats {
+        start: first.to_string(),
+        ..Default::default()
+    };
+    let periods = buckets
+        .into_iter()
+        .map(|(start, mut stats)| {
+            stats.start = start.to_string();
+            stats.finish();
+            total.add(&stats);
+            stats
+        })
+        .collect();
+    total.finish();
+    Ok(StatsReport {
+        project,
+        period,
+        since: since_text,
+        until: until_text,
+        periods,
+        total,
+    })
+}
+
+/// Per-day or per-week statistics of `project` (all projects when unset):
+/// agent runs and hours, tokens and cost, commits and pull requests, and how
+/// often verification passed. Without a `range`, the last 14 days or 12
+/// weeks up to now.
+#[tauri::command]
+pub async fn stats_report(
+    window: WebviewWindow,
+    app: AppHandle,
+    project: Option<String>,
+    period: Period,
+    range: Option<TimeRange>,
+) -> Result<StatsReport, String> {
+    crate::require_app_window(&window)?;
+    let range = range.unwrap_or_default();
+    let bound = |value: Option<String>| {
+        value
+            .map(|v| parse_time(&v).ok_or_else(|| format!("invalid time {v}")))
+            .transpose()
+    };
+    let until = bound(range.until)?.unwrap_or_else(Utc::now);
+    let since = bound(range.since)?.unwrap_or_else(|| {
+        let today = until.with_timezone(&Local).date_naive();
+        let first = match period {
+            Period::Day => today - Duration::days(DEFAULT_DAYS - 1),
+            Period::Week => period_start(today, period) - Duration::weeks(DEFAULT_WEEKS - 1),
+        };
+        first
+            .and_hms_opt(0, 0, 0)
+            .and_then(|t| t.and_local_timezone(Local).earliest())
+            .map_or(until, |t| t.with_timezone(&Utc))
+    });
+    if since > until {
+        return Err("range must not end before it starts".to_string());
+    }
+    tauri::async_runtime::spawn_blocking(move || build(&app, project, period, since, until))
+        .await
+        .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,262 @@
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, WebviewWindow};
+
+/// Toolchains whose versions are recorded, with the arguments that print it.
+const TOOLS: &[(&str, &[&str])] = &[
+    ("node", &["--version"]),
+    ("npm", &["--version"]),
+    ("pnpm", &["--version"]),
+    ("yarn", &["--version"]),
+    ("bun", &["--version"]),
+    ("rustc", &["--version"]),
+    ("cargo", &["--version"]),
+    ("python3", &["--version"]),
+    ("uv", &["--version"]),
+    ("go", &["version"]),
+    ("git", &["--version"]),
+];
+
+/// Lockfiles hashed when the project root has them.
+const LOCKFILES: &[&str] = &[
+    "package-lock.json",
+    "pnpm-lock.yaml",
+    "yarn.lock",
+    "bun.lock",
+    "bun.lockb",
+    "Cargo.lock",
+    "uv.lock",
+    "poetry.lock",
+    "Pipfile.lock",
+    "go.sum",
+    "Gemfile.lock",
+];
+
+/// Parts of variable names whose values are never stored.
+const SECRET_NAMES: &[&str] = &[
+    "TOKEN",
+    "SECRET",
+    "PASSWORD",
+    "PASSWD",
+    "API_KEY",
+    "ACCESS_KEY",
+    "PRIVATE_KEY",
+    "CREDENTIAL",
+];
+
+/// What an agent run started with, for telling apart runs that should have
+/// behaved the same.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvSnapshot {
+    pub run_id: String,
+    pub directory: String,
+    pub captured_at: String,
+    /// Tool → first line of its version output; tools not found are left
+    /// out.
+    pub tools: BTreeMap<String, String>,
+    /// The app's environment with the project's `env` over it, secret
+    /// values and URL credentials masked.
+    pub env: BTreeMap<String, String>,
+    /// Lockfile → SHA-256 of its content.
+    pub lockfiles: BTreeMap<String, String>,
+}
+
+/// One entry that differs between two runs; `None` where a run lacks it.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvChange {
+    pub name: String,
+    pub a: Option<String>,
+    pub b: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvDiff {
+    pub a: EnvSnapshot,
+    pub b: EnvSnapshot,
+    pub tools: Vec<EnvChange>,
+    pub env: Vec<EnvChange>,
+    pub lockfiles: Vec<EnvChange>,
+}
+
+fn is_secret_name(name: &str) -> bool {
+    let name = name.to_ascii_uppercase();
+    SECRET_NAMES.iter().any(|part| name.contains(part))
+}
+
+/// `value` as stored for variable `name`.
+fn masked(name: &str, value: &str) -> String {
+    if is_secret_name(name) {
+        "[redacted]".to_string()
+    } else {
+        crate::redact::redact(value)
+    }
+}
+
+fn sha256(path: &Path) -> Option<String> {
+    let content = std::fs::read(path).ok()?;
+    Some(
+        Sha256::digest(&content)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect(),
+    )
+}
+
+fn snapshot(app: &AppHandle, run_id: &str, directory: &Path) -> EnvSnapshot {
+    let tools = TOOLS
+        .iter()
+        .filter_map(|&(tool, args)| {
+            let path = crate::diagnostics::find_executable(tool)?;
+            let version = crate::diagnostics::probe_in(&path, args, Some(directory))?;
+            Some((tool.to_string(), version))
+        })
+        .collect();
+    let project_env = crate::project_config::get(app, &directory.to_string_lossy())
+        .map(|config| config.env)
+        .unwrap_or_default();
+    let env = std::env::vars()
+        .chain(project_env)
+        .map(|(name, value)| {
+            let value = masked(&name, &value);
+            (name, value)
+        })
+        .collect();
+    let lockfiles = LOCKFILES
+        .iter()
+        .filter_map(|name| Some((name.to_string(), sha256(&directory.join(name))?)))
+        .collect();
+    EnvSnapshot {
+        run_id: run_id.to_string(),
+        directory: directory.to_string_lossy().to_string(),
+        captured_at: chrono::Utc::now().to_rfc3339(),
+        tools,
+        env,
+        lockfiles,
+    }
+}
+
+fn store(app: &AppHandle, snapshot: &EnvSnapshot) -> Result<(), String> {
+    let json = serde_json::to_string(snapshot).map_err(|e| e.to_string())?;
+    crate::db::with(app, |conn| {
+        conn.execute(
+            "INSERT OR REPLACE INTO run_environments (run_id, captured_at, snapshot)
+             VALUES (?1, ?2, ?3)",
+            params![snapshot.run_id, snapshot.captured_at, json],
+        )
+    })
+    .map(|_| ())
+}
+
+/// Record the environment of run `run_id`, started in `directory`, in the
+/// background: probing tools takes a moment.
+pub(crate) fn capture(app: &AppHandle, run_id: &str, directory: PathBuf) {
+    if !directory.is_dir() {
+        return;
+    }
+    let (app, run_id) = (app.clone(), run_id.to_string());
+    std::thread::spawn(move || {
+        let snapshot = snapshot(&app, &run_id, &directory);
+        if let Err(e) = store(&app, &snapshot) {
+            tracing::warn!("environment of run {run_id} not recorded: {e}");
+        }
+    });
+}
+
+fn load(app: &AppHandle, run_id: &str) -> Result<Option<EnvSnapshot>, String> {
+    let json: Option<String> = crate::db::with(app, |conn| {
+        conn.query_row(
+            "SELECT snapshot FROM run_environments WHERE run_id = ?1",
+            params![run_id],
+            |row| row.get(0),
+        )
+        .optional()
+    })?;
+    json.map(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+        .transpose()
+}
+
+fn changes(a: &BTreeMap<String, String>, b: &BTreeMap<String, String>) -> Vec<EnvChange> {
+    let names: BTreeSet<&String> = a.keys().chain(b.keys()).collect();
+    names
+        .into_iter()
+        .filter(|name| a.get(*name) != b.get(*name))
+        .map(|name| EnvChange {
+            name: name.clone(),
+            a: a.get(name).cloned(),
+            b: b.get(name).cloned(),
+        })
+        .collect()
+}
+
+/// The environment run `run_id` started with, if it was recorded.
+#[tauri::command]
+pub async fn run_env(
+    window: WebviewWindow,
+    app: AppHandle,
+    run_id: String,
+) -> Result<Option<EnvSnapshot>, String> {
+    crate::require_app_window(&window)?;
+    tauri::async_runtime::spawn_blocking(move || load(&app, &run_id))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// What differed between the environments two runs started with: tool
+/// versions, variables and lockfiles.
+#[tauri::command]
+pub async fn run_env_diff(
+    window: WebviewWindow,
+    app: AppHandle,
+    run_a: String,
+    run_b: String,
+) -> Result<EnvDiff, String> {
+    crate::require_app_window(&window)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let missing = |run: &str| format!("no environment recorded for run {run}");
+        let a = load(&app, &run_a)?.ok_or_else(|| missing(&run_a))?;
+        let b = load(&app, &run_b)?.ok_or_else(|| missing(&run_b))?;
+        Ok(EnvDiff {
+            tools: changes(&a.tools, &b.tools),
+            env: changes(&a.env, &b.env),
+            lockfiles: changes(&a.lockfiles, &b.lockfiles),
+            a,
+            b,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secret_variables_are_masked_whole() {
+        assert_eq!(masked("GITHUB_TOKEN", "anything"), "[redacted]");
+        assert_eq!(masked("db_password", "hunter22"), "[redacted]");
+        assert_eq!(masked("HOME", "/home/me"), "/home/me");
+    }
+
+    #[test]
+    fn url_credentials_are_masked() {
+        assert_eq!(
+            masked("DATABASE_URL", "postgres://app:s3cret@db:5432/app"),
+            "postgres://[redacted]@db:5432/app"
+        );
+        assert_eq!(
+            masked("REDIS_URL", "redis://:s3cret@cache:6379"),
+            "redis://[redacted]@cache:6379"
+        );
+        assert_eq!(
+            masked("UPSTREAM", "https://user@example.com/path"),
+            "https://user@example.com/path"
+        );
+    }
+}
@@ -0,0 +1,232 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{State, WebviewWindow};
+
+use crate::remote_fs::RemoteRoot;
+
+/// Sibling directory the server creates thread worktrees in
+/// (`<parent>/.funny-worktrees/<project name>/<branch>`), mirroring
+/// `WORKTREE_DIR_NAME` in `@funny/core`.
+pub(crate) const WORKTREE_DIR_NAME: &str = ".funny-worktrees";
+
+/// The project roots the frontend has registered. Every path a command
+/// receives — fs roots and paths, git/forge `cwd`s, PTY working directories,
+/// watch and search roots — must resolve inside one of them. Projects on an
+/// SSH host are kept apart in `remote`: only the fs and search commands
+/// accept them.
+pub struct Sandbox {
+    roots: Mutex<Vec<PathBuf>>,
+    remote: Mutex<Vec<RemoteRoot>>,
+}
+
+impl Sandbox {
+    pub fn new() -> Self {
+        Self {
+            roots: Mutex::new(Vec::new()),
+            remote: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// The remote directory `root` names if it is an `ssh://` URI, after
+    /// making sure it is a registered remote root or lies inside one;
+    /// `None` for a local path.
+    pub(crate) fn remote(&self, root: &str) -> Result<Option<RemoteRoot>, String> {
+        let Some(parsed) = RemoteRoot::parse(root) else {
+            return Ok(None);
+        };
+        let parsed = parsed?;
+        let remote = self.remote.lock().map_err(|e| e.to_string())?;
+        if remote.iter().any(|r| r.contains(&parsed)) {
+            Ok(Some(parsed))
+        } else {
+            Err(format!("{root} is not inside a registered project"))
+        }
+    }
+
+    /// Canonicalize `dir` and make sure it is a registered root or lies
+    /// inside one. Used for roots and working directories.
+    pub(crate) fn check_dir(&self, dir: &str) -> Result<PathBuf, String> {
+        if dir.starts_with(crate::remote_fs::SCHEME) {
+            return Err(format!(
+                "{dir} is on a remote host; only local projects work here"
+            ));
+        }
+        let canonical =
+            std::fs::canonicalize(dir).map_err(|e| format!("invalid path {dir}: {e}"))?;
+        let roots = self.roots.lock().map_err(|e| e.to_string())?;
+        if roots.iter().any(|root| canonical.starts_with(root)) {
+            Ok(canonical)
+        } else {
+            Err(format!("{dir} is not inside a registered project"))
+        }
+    }
+
+    /// The registered root `path` (canonical) lies in, the innermost if
+    /// roots nest.
+    pub(crate) fn root_of(&self, path: &Path) -> Option<PathBuf> {
+        let roots = self.roots.lock().ok()?;
+        roots
+            .iter()
+            .filter(|root| path.starts_with(root))
+            .max_by_key(|root| root.components().count())
+            .cloned()
+    }
+
+    /// The registered project checkouts, without their worktree directories.
+    pub(crate) fn projects(&self) -> Vec<PathBuf> {
+        let roots = match self.roots.lock() {
+            Ok(roots) => roots,
+            Err(_) => return Vec::new(),
+        };
+        roots
+            .iter()
+            .filter(|root| {
+                !root
+                    .components()
+                    .any(|c| c.as_os_str() == WORKTREE_DIR_NAME)
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Check `root` with `check_dir`, then resolve `path` inside it.
+    pub(crate) fn resolve(&self, root: &str, path: &str) -> Result<PathBuf, String> {
+        let root = self.check_dir(root)?;
+        resolve_in_root(&root, path)
+    }
+
+    /// Like `resolve`, but doesn't follow a symlink in the last component:
+    /// deleting or moving a link must act on the link itself, even when it
+    /// points outside the project. The root itself is rejected.
+    pub(crate) fn resolve_entry(&self, root: &str, path: &str) -> Result<PathBuf, String> {
+        let requested = Path::new(path);
+        let name = requested
+            .file_name()
+            .ok_or_else(|| format!("invalid path {path}"))?;
+        let parent = match requested.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p.to_string_lossy().to_string(),
+            _ => ".".to_string(),
+        };
+        Ok(self.resolve(root, &parent)?.join(name))
+    }
+}
+
+/// Resolve `path` (absolute, or relative to `root`) and make sure it stays
+/// inside `root` after following symlinks. `root` must already be canonical.
+/// Paths that don't exist yet are checked through their closest existing
+/// ancestor, so `write_file` can create new files without opening a hole for
+/// `a/../../etc`.
+pub(crate) fn resolve_in_root(root: &Path, path: &str) -> Result<PathBuf, String> {
+    let requested = Path::new(path);
+    let joined = if requested.is_absolute() {
+        requested.to_path_buf()
+    } else {
+        root.join(requested)
+    };
+
+    // Split into the longest existing prefix (canonicalized) and the rest.
+    let mut existing = joined.as_path();
+    let mut rest: Vec<&std::ffi::OsStr> = Vec::new();
+    let mut resolved = loop {
+        match std::fs::canonicalize(existing) {
+            Ok(p) => break p,
+            Err(_) => {
+                rest.push(
+                    existing
+                        .file_name()
+                        .ok_or_else(|| format!("invalid path {path}"))?,
+                );
+                existing = existing
+                    .parent()
+                    .ok_or_else(|| format!("invalid path {path}"))?;
+            }
+        }
+    };
+    // `file_name` never yields `..`, so the remainder can't climb back out.
+    for part in rest.iter().rev() {
+        resolved.push(part);
+    }
+
+    if !resolved.starts_with(root) {
+        return Err(format!("{path} is outside of {}", root.display()));
+    }
+    Ok(resolved)
+}
+
+/// A project path plus its worktree directory, canonicalized. The worktree
+/// directory usually doesn't exist until the first thread needs one, so it is
+/// resolved through its parent.
+fn allowed_dirs(project: &str) -> Result<Vec<PathBuf>, String> {
+    let root =
+        std::fs::canonicalize(project).map_err(|e| format!("invalid project {project}: {e}"))?;
+    let mut dirs = vec![root.clone()];
+    if let (Some(parent), Some(name)) = (root.parent(), root.file_name()) {
+        dirs.push(resolve_in_root(
+            parent,
+            &format!("{WORKTREE_DIR_NAME}/{}", name.to_string_lossy()),
+        )?);
+    }
+    Ok(dirs)
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SandboxRoots {
+    pub roots: Vec<String>,
+    /// Entries of `projects` that could not be registered (missing
+    /// directories), with the reason.
+    pub rejected: Vec<String>,
+}
+
+/// Replace the registered roots with `projects` (and their worktree
+/// directories). Called whenever the project list loads or changes. Remote
+/// projects are given as `ssh://<destination>/<path>` and aren't checked
+/// for existence.
+#[tauri::command]
+pub fn set_project_roots(
+    window: WebviewWindow,
+    state: State<'_, Sandbox>,
+    projects: Vec<String>,
+) -> Result<SandboxRoots, String> {
+    crate::require_window(&window, "main")?;
+    let mut roots: Vec<PathBuf> = Vec::new();
+    let mut remote: Vec<RemoteRoot> = Vec::new();
+    let mut rejected = Vec::new();
+    for project in &projects {
+        if let Some(parsed) = RemoteRoot::parse(project) {
+            match parsed {
+                Ok(root) if root.path != "/" => {
+                    remote.extend(root.worktrees());
+                    remote.push(root);
+                }
+                Ok(_) => rejected.push(format!("invalid project {project}")),
+                Err(e) => rejected.push(e),
+            }
+            continue;
+        }
+        match allowed_dirs(project) {
+            Ok(dirs) => roots.extend(dirs),
+            Err(e) => rejected.push(e),
+        }
+    }
+    // Nobody should be able to widen the sandbox to everything.
+    roots.retain(|r| r.parent().is_some());
+    roots.sort();
+    roots.dedup();
+
+    remote.sort();
+    remote.dedup();
+
+    let listed = roots
+        .iter()
+        .map(|r| r.to_string_lossy().to_string())
+        .chain(remote.iter().map(|r| r.uri(&r.path)))
+        .collect();
+    *state.roots.lock().map_err(|e| e.to_string())? = roots;
+    *state.remote.lock().map_err(|e| e.to_string())? = remote;
+    Ok(SandboxRoots {
+        roots: listed,
+        rejected,
+    })
+}
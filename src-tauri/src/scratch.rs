@@ -0,0 +1,403 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State, WebviewWindow};
+
+use crate::audit::{self, AuditEvent, AuditKind, Outcome};
+use crate::cancel::CancelToken;
+use crate::forge::{run_capture, run_capture_cancellable};
+use crate::jobs::{JobHandle, JobInfo};
+use crate::sandbox::{Sandbox, WORKTREE_DIR_NAME};
+
+/// How a scratch copy was made, best first.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CopyMethod {
+    /// fuse-overlayfs on Linux: nothing is copied, writes land in an upper
+    /// directory.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    Overlay,
+    /// APFS clone on macOS: copied blocks are shared until written.
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+    Clone,
+    /// A full copy, everywhere else.
+    Copy,
+}
+
+/// A throwaway copy of a project an agent can run against. Its changes reach
+/// the real checkout only through `scratch_apply`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScratchCopy {
+    pub id: String,
+    /// The real checkout.
+    pub root: String,
+    /// Where the agent should run.
+    pub path: String,
+    pub method: CopyMethod,
+    pub agent: Option<String>,
+    /// Tree of the copy as it was made, which the diff is taken against.
+    #[serde(skip)]
+    baseline: String,
+    #[serde(skip)]
+    dir: PathBuf,
+}
+
+/// Scratch copies made this session. They are discarded on exit.
+pub struct Scratches {
+    copies: Mutex<HashMap<String, ScratchCopy>>,
+    next_id: AtomicU64,
+}
+
+impl Scratches {
+    pub fn new() -> Self {
+        Self {
+            copies: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+        }
+    }
+}
+
+/// `<parent>/.funny-worktrees/<project name>/.scratch-<id>`, inside the
+/// directory the sandbox already allows for the project's worktrees.
+fn scratch_dir(root: &Path, id: &str) -> Result<PathBuf, String> {
+    match (root.parent(), root.file_name()) {
+        (Some(parent), Some(name)) => Ok(parent
+            .join(WORKTREE_DIR_NAME)
+            .join(name)
+            .join(format!(".scratch-{id}"))),
+        _ => Err(format!("cannot make a scratch copy of {}", root.display())),
+    }
+}
+
+/// Copy `from` to `to`, counting files in `copied` for progress.
+fn copy_tree(from: &Path, to: &Path, job: &JobHandle, copied: &mut u64) -> Result<(), String> {
+    let meta = std::fs::symlink_metadata(from).map_err(|e| e.to_string())?;
+    if meta.is_dir() {
+        job.check()?;
+        std::fs::create_dir_all(to).map_err(|e| e.to_string())?;
+        for entry in std::fs::read_dir(from).map_err(|e| e.to_string())? {
+            let entry = entry.map_err(|e| e.to_string())?;
+            copy_tree(&entry.path(), &to.join(entry.file_name()), job, copied)?;
+        }
+        return Ok(());
+    }
+    *copied += 1;
+    if copied.is_multiple_of(100) {
+        job.progress(*copied, None, Some("copying"));
+    }
+    #[cfg(unix)]
+    if meta.file_type().is_symlink() {
+        let target = std::fs::read_link(from).map_err(|e| e.to_string())?;
+        return std::os::unix::fs::symlink(target, to).map_err(|e| e.to_string());
+    }
+    std::fs::copy(from, to)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(target_os = "linux")]
+fn mount_overlay(root: &Path, dir: &Path) -> Result<PathBuf, String> {
+    let (upper, work, merged) = (dir.join("upper"), dir.join("work"), dir.join("merged"));
+    for d in [&upper, &work, &merged] {
+        std::fs::create_dir_all(d).map_err(|e| e.to_string())?;
+    }
+    run_capture(
+        Command::new("fuse-overlayfs")
+            .arg("-o")
+            .arg(format!(
+                "lowerdir={},upperdir={},workdir={}",
+                root.display(),
+                upper.display(),
+                work.display()
+            ))
+            .arg(&merged),
+        None,
+    )?;
+    Ok(merged)
+}
+
+#[cfg(target_os = "linux")]
+fn unmount_overlay(merged: &Path) {
+    let unmounted = ["fusermount3", "fusermount"]
+        .iter()
+        .any(|program| run_capture(Command::new(program).arg("-u").arg(merged), None).is_ok());
+    if !unmounted {
+        tracing::warn!("failed to unmount scratch overlay {}", merged.display());
+    }
+}
+
+/// Make the copy in `dir`, trying the cheapest method the platform has.
+fn make_copy(
+    app: &AppHandle,
+    root: &Path,
+    dir: &Path,
+    job: &JobHandle,
+) -> Result<(PathBuf, CopyMethod), String> {
+    #[cfg(target_os = "linux")]
+    match mount_overlay(root, dir) {
+        Ok(merged) => return Ok((merged, CopyMethod::Overlay)),
+        Err(e) => {
+            tracing::info!("overlay unavailable, copying instead: {e}");
+            let _ = std::fs::remove_dir_all(dir);
+        }
+    }
+    let tree = dir.join("tree");
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    #[cfg(target_os = "macos")]
+    {
+        // `cp -c` clones with clonefile(2); it fails off APFS.
+        let cloned = run_capture(Command::new("cp").arg("-cR").arg(root).arg(&tree), None);
+        match cloned {
+            Ok(_) => return Ok((tree, CopyMethod::Clone)),
+            Err(e) => {
+                tracing::info!("APFS clone unavailable, copying instead: {e}");
+                let _ = std::fs::remove_dir_all(&tree);
+            }
+        }
+    }
+    crate::disk::ensure_space(app, dir, crate::disk::tree_bytes(root))?;
+    copy_tree(root, &tree, job, &mut 0)?;
+    Ok((tree, CopyMethod::Copy))
+}
+
+/// Write the copy's working tree, untracked files included, as a tree object
+/// using an index of our own, so the agent's staging area is left alone.
+fn snapshot(copy: &ScratchCopy, cancel: &CancelToken) -> Result<String, String> {
+    let index = copy.dir.join("scratch.index");
+    let git = |args: &[&str]| {
+        run_capture_cancellable(
+            Command::new("git")
+                .args(args)
+                .current_dir(&copy.path)
+                .env("GIT_INDEX_FILE", &index),
+            None,
+            cancel,
+        )
+    };
+    git(&["add", "-A"])?;
+    git(&["write-tree"])
+}
+
+fn discard(copy: &ScratchCopy) {
+    #[cfg(target_os = "linux")]
+    if matches!(copy.method, CopyMethod::Overlay) {
+        unmount_overlay(Path::new(&copy.path));
+    }
+    if let Err(e) = std::fs::remove_dir_all(&copy.dir) {
+        tracing::warn!("failed to remove scratch copy {}: {e}", copy.dir.display());
+    }
+}
+
+/// Discard every scratch copy — called on app exit.
+pub fn discard_all(state: &Scratches) {
+    if let Ok(mut copies) = state.copies.lock() {
+        for (_, copy) in copies.drain() {
+            discard(&copy);
+        }
+    }
+}
+
+fn lookup(state: &Scratches, id: &str) -> Result<ScratchCopy, String> {
+    state
+        .copies
+        .lock()
+        .map_err(|e| e.to_string())?
+        .get(id)
+        .cloned()
+        .ok_or_else(|| format!("scratch copy {id} not found"))
+}
+
+/// Make a scratch copy of the git checkout at `root` for a risky agent run.
+/// Experimental. Runs as a `scratch.create` job whose result is the
+/// `ScratchCopy`.
+#[tauri::command]
+pub fn scratch_create(
+    window: WebviewWindow,
+    app: AppHandle,
+    sandbox: State<'_, Sandbox>,
+    root: String,
+    agent: Option<String>,
+) -> Result<JobInfo, String> {
+    crate::require_app_window(&window)?;
+    let root = sandbox.check_dir(&root)?;
+    // Timestamped so leftovers from a crashed session never collide.
+    let id = format!(
+        "{}-{}",
+        chrono::Utc::now().format("%Y%m%d%H%M%S"),
+        app.state::<Scratches>()
+            .next_id
+            .fetch_add(1, Ordering::Relaxed)
+    );
+    let label = root
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let worker = app.clone();
+    crate::jobs::spawn(&app, &window, "scratch.create", label, move |job| {
+        run_capture(
+            Command::new("git")
+                .args(["rev-parse", "--is-inside-work-tree"])
+                .current_dir(&root),
+            None,
+        )
+        .map_err(|_| format!("{} is not a git checkout", root.display()))?;
+        let dir = scratch_dir(&root, &id)?;
+        let (path, method) = match make_copy(&worker, &root, &dir, job) {
+            Ok(made) => made,
+            Err(e) => {
+                let _ = std::fs::remove_dir_all(&dir);
+                return Err(e);
+            }
+        };
+        let mut copy = ScratchCopy {
+            id,
+            root: root.to_string_lossy().to_string(),
+            path: path.to_string_lossy().to_string(),
+            method,
+            agent,
+            baseline: String::new(),
+            dir,
+        };
+        job.progress(0, None, Some("snapshotting"));
+        match snapshot(&copy, job.token()).and_then(|tree| job.check().map(|_| tree)) {
+            Ok(tree) => copy.baseline = tree,
+            Err(e) => {
+                discard(&copy);
+                return Err(e);
+            }
+        }
+        tracing::info!(
+            target: "agents",
+            "scratch copy {} of {} ({:?})",
+            copy.id,
+            copy.root,
+            copy.method
+        );
+        worker
+            .state::<Scratches>()
+            .copies
+            .lock()
+            .map_err(|e| e.to_string())?
+            .insert(copy.id.clone(), copy.clone());
+        Ok(copy)
+    })
+}
+
+#[tauri::command]
+pub fn scratch_list(
+    window: WebviewWindow,
+    state: State<'_, Scratches>,
+) -> Result<Vec<ScratchCopy>, String> {
+    crate::require_app_window(&window)?;
+    let copies = state.copies.lock().map_err(|e| e.to_string())?;
+    Ok(copies.values().cloned().collect())
+}
+
+/// Everything changed in the copy since it was made, as a binary git patch.
+/// Can be stopped with `cancel(request_id)`.
+#[tauri::command]
+pub async fn scratch_diff(
+    window: WebviewWindow,
+    app: AppHandle,
+    state: State<'_, Scratches>,
+    id: String,
+    request_id: Option<String>,
+) -> Result<String, String> {
+    crate::require_app_window(&window)?;
+    let copy = lookup(&state, &id)?;
+    let guard = crate::cancel::register(&app, request_id.as_deref());
+    tauri::async_runtime::spawn_blocking(move || {
+        let cancel = guard.token();
+        let tree = snapshot(&copy, &cancel)?;
+        run_capture_cancellable(
+            Command::new("git")
+                .args(["diff", "--binary", &copy.baseline, &tree])
+                .current_dir(&copy.path),
+            None,
+            &cancel,
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Apply the copy's changes to the real checkout. With `discard`, the copy
+/// is removed once they're in. `cancel(request_id)` stops it while the patch
+/// is being prepared; once applying starts it runs to the end.
+#[tauri::command]
+pub async fn scratch_apply(
+    window: WebviewWindow,
+    app: AppHandle,
+    state: State<'_, Scratches>,
+    id: String,
+    discard: Option<bool>,
+    request_id: Option<String>,
+) -> Result<(), String> {
+    crate::require_window(&window, "main")?;
+    let copy = lookup(&state, &id)?;
+    let applied = copy.clone();
+    let guard = crate::cancel::register(&app, request_id.as_deref());
+    tauri::async_runtime::spawn_blocking(move || {
+        let cancel = guard.token();
+        let tree = snapshot(&applied, &cancel)?;
+        let patch = run_capture_cancellable(
+            Command::new("git")
+                .args(["diff", "--binary", &applied.baseline, &tree])
+                .current_dir(&applied.path),
+            None,
+            &cancel,
+        )?;
+        if patch.is_empty() {
+            return Ok(());
+        }
+        // Killing git apply halfway could leave the checkout half patched.
+        cancel.check()?;
+        // run_capture trims the trailing newline git apply needs.
+        run_capture(
+            Command::new("git")
+                .args(["apply", "--binary", "--whitespace=nowarn", "-"])
+                .current_dir(&applied.root),
+            Some(&format!("{patch}\n")),
+        )
+        .map(|_| ())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .inspect_err(|e| tracing::warn!("failed to apply scratch copy {id}: {e}"))?;
+    audit::record(
+        &app,
+        AuditEvent {
+            kind: AuditKind::FileWrite,
+            agent: copy.agent.as_deref(),
+            project: Some(&copy.root),
+            target: &copy.root,
+            outcome: Outcome::Ok,
+            detail: Some(format!("applied scratch copy {id}")),
+        },
+    );
+    if discard.unwrap_or(false) {
+        scratch_discard(window, state, id)?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn scratch_discard(
+    window: WebviewWindow,
+    state: State<'_, Scratches>,
+    id: String,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    let copy = state
+        .copies
+        .lock()
+        .map_err(|e| e.to_string())?
+        .remove(&id)
+        .ok_or_else(|| format!("scratch copy {id} not found"))?;
+    discard(&copy);
+    Ok(())
+}
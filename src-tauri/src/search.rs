@@ -0,0 +1,470 @@
+use grep_matcher::Matcher;
+use grep_regex::{RegexMatcher, RegexMatcherBuilder};
+use grep_searcher::{BinaryDetection, Searcher, SearcherBuilder, Sink, SinkContext, SinkMatch};
+use ignore::overrides::OverrideBuilder;
+use ignore::{WalkBuilder, WalkState};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Instant;
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+use crate::remote_fs::RemoteRoot;
+use crate::sandbox::Sandbox;
+
+/// Default cap on the number of matching lines reported per search.
+const DEFAULT_MAX_RESULTS: usize = 2000;
+
+/// Files larger than this are skipped (generated bundles, dumps, …).
+const MAX_SEARCH_FILE_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Context lines are capped so a careless request can't flood the channel.
+const MAX_CONTEXT_LINES: usize = 10;
+
+/// Long lines (minified code) are cut to this many characters.
+const MAX_LINE_CHARS: usize = 500;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchOptions {
+    /// Treat `query` as a regular expression instead of a literal string.
+    pub regex: Option<bool>,
+    /// Case sensitivity; defaults to smart case (insensitive unless the query
+    /// contains an uppercase letter).
+    pub case_sensitive: Option<bool>,
+    /// Include globs; prefix with `!` to exclude (ripgrep's `-g` syntax).
+    #[serde(default)]
+    pub globs: Vec<String>,
+    pub max_results: Option<u32>,
+    pub context_lines: Option<u32>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchMatch {
+    pub line_number: u64,
+    pub text: String,
+    /// `[start, end)` of each match in `text`, in UTF-16 code units so the
+    /// frontend can slice the string directly.
+    pub ranges: Vec<[u32; 2]>,
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchSummary {
+    pub files_with_matches: u32,
+    pub matches: u32,
+    /// True when `max_results` was hit or the search was cancelled.
+    pub truncated: bool,
+    pub elapsed_ms: u64,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "event",
+    content = "data"
+)]
+pub enum SearchEvent {
+    /// All matches in one file, sent as soon as that file is searched.
+    File {
+        path: String,
+        matches: Vec<SearchMatch>,
+    },
+    Done(SearchSummary),
+}
+
+fn clip(line: &[u8]) -> String {
+    let text = String::from_utf8_lossy(line);
+    let text = text.trim_end_matches(['\n', '\r']);
+    if text.chars().count() > MAX_LINE_CHARS {
+        text.chars().take(MAX_LINE_CHARS).collect()
+    } else {
+        text.to_string()
+    }
+}
+
+fn utf16_offset(text: &str, byte: usize) -> u32 {
+    let mut end = byte.min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text[..end].encode_utf16().count() as u32
+}
+
+/// Collects one file's matches, attaching context lines to the match they
+/// surround.
+struct FileSink<'a> {
+    matcher: &'a RegexMatcher,
+    matches: Vec<SearchMatch>,
+    pending_before: Vec<String>,
+    budget: &'a AtomicUsize,
+    cancelled: &'a AtomicBool,
+}
+
+impl Sink for FileSink<'_> {
+    type Error = std::io::Error;
+
+    fn matched(&mut self, _searcher: &Searcher, mat: &SinkMatch<'_>) -> Result<bool, Self::Error> {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return Ok(false);
+        }
+        let remaining = self
+            .budget
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1));
+        if remaining.is_err() {
+            return Ok(false);
+        }
+
+        let text = clip(mat.bytes());
+        let mut ranges = Vec::new();
+        let _ = self.matcher.find_iter(text.as_bytes(), |m| {
+            ranges.push([utf16_offset(&text, m.start()), utf16_offset(&text, m.end())]);
+            true
+        });
+        self.matches.push(SearchMatch {
+            line_number: mat.line_number().unwrap_or(0),
+            text,
+            ranges,
+            before: std::mem::take(&mut self.pending_before),
+            after: Vec::new(),
+        });
+        Ok(true)
+    }
+
+    fn context(
+        &mut self,
+        _searcher: &Searcher,
+        ctx: &SinkContext<'_>,
+    ) -> Result<bool, Self::Error> {
+        let line = clip(ctx.bytes());
+        match ctx.kind() {
+            grep_searcher::SinkContextKind::Before => self.pending_before.push(line),
+            grep_searcher::SinkContextKind::After => {
+                if let Some(last) = self.matches.last_mut() {
+                    last.after.push(line);
+                }
+            }
+            grep_searcher::SinkContextKind::Other => {}
+        }
+        Ok(true)
+    }
+}
+
+fn build_matcher(query: &str, options: &SearchOptions) -> Result<RegexMatcher, String> {
+    let mut builder = RegexMatcherBuilder::new();
+    builder.fixed_strings(!options.regex.unwrap_or(false));
+    match options.case_sensitive {
+        Some(sensitive) => builder.case_insensitive(!sensitive),
+        None => builder.case_smart(true),
+    };
+    builder.build(query).map_err(|e| e.to_string())
+}
+
+fn run_search(
+    root: &Path,
+    query: &str,
+    options: &SearchOptions,
+    channel: &Channel<SearchEvent>,
+    cancelled: &AtomicBool,
+) -> Result<SearchSummary, String> {
+    let started = Instant::now();
+    let matcher = build_matcher(query, options)?;
+    let max_results = options
+        .max_results
+        .map(|n| n as usize)
+        .unwrap_or(DEFAULT_MAX_RESULTS);
+    let context = options
+        .context_lines
+        .map(|n| n as usize)
+        .unwrap_or(0)
+        .min(MAX_CONTEXT_LINES);
+
+    let mut overrides = OverrideBuilder::new(root);
+    for glob in &options.globs {
+        overrides.add(glob).map_err(|e| e.to_string())?;
+    }
+    let overrides = overrides.build().map_err(|e| e.to_string())?;
+
+    let budget = AtomicUsize::new(max_results);
+    let files_with_matches = AtomicUsize::new(0);
+
+    WalkBuilder::new(root)
+        .hidden(false)
+        .require_git(false)
+        .filter_entry(|e| e.file_name() != ".git")
+        .overrides(overrides)
+        .max_filesize(Some(MAX_SEARCH_FILE_SIZE))
+        .build_parallel()
+        .run(|| {
+            let matcher = &matcher;
+            let budget = &budget;
+            let files_with_matches = &files_with_matches;
+            let mut searcher = SearcherBuilder::new()
+                .binary_detection(BinaryDetection::quit(b'\x00'))
+                .line_number(true)
+                .before_context(context)
+                .after_context(context)
+                .build();
+            Box::new(move |entry| {
+                if cancelled.load(Ordering::Relaxed) || budget.load(Ordering::Relaxed) == 0 {
+                    return WalkState::Quit;
+                }
+                let entry = match entry {
+                    Ok(e) if e.file_type().map(|t| t.is_file()).unwrap_or(false) => e,
+                    _ => return WalkState::Continue,
+                };
+                let mut sink = FileSink {
+                    matcher,
+                    matches: Vec::new(),
+                    pending_before: Vec::new(),
+                    budget,
+                    cancelled,
+                };
+                if searcher
+                    .search_path(matcher, entry.path(), &mut sink)
+                    .is_err()
+                    || sink.matches.is_empty()
+                {
+                    return WalkState::Continue;
+                }
+                files_with_matches.fetch_add(1, Ordering::Relaxed);
+                let path = entry
+                    .path()
+                    .strip_prefix(root)
+                    .unwrap_or(entry.path())
+                    .to_string_lossy()
+                    .replace('\\', "/");
+                let event = SearchEvent::File {
+                    path,
+                    matches: sink.matches,
+                };
+                // The frontend closed the channel (panel unmounted): stop.
+                if channel.send(event).is_err() {
+                    return WalkState::Quit;
+                }
+                WalkState::Continue
+            })
+        });
+
+    let remaining = budget.load(Ordering::SeqCst);
+    Ok(SearchSummary {
+        files_with_matches: files_with_matches.load(Ordering::SeqCst) as u32,
+        matches: (max_results - remaining) as u32,
+        truncated: remaining == 0 || cancelled.load(Ordering::SeqCst),
+        elapsed_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+/// `rg` and, where it isn't installed, `grep` invocations for a search on an
+/// SSH host. Both print `path\0line:text` per matching line; `grep` can't
+/// read .gitignore, so it only skips `.git` and `node_modules`.
+fn remote_search_script(query: &str, options: &SearchOptions) -> String {
+    let regex = options.regex.unwrap_or(false);
+    let sensitive = options
+        .case_sensitive
+        .unwrap_or_else(|| query.chars().any(char::is_uppercase));
+    let mut rg: Vec<String> = [
+        "rg",
+        "--null",
+        "-nH",
+        "--no-heading",
+        "--color=never",
+        "--hidden",
+        "-g",
+        "!.git",
+    ]
+    .map(String::from)
+    .to_vec();
+    rg.push(format!("--max-filesize={MAX_SEARCH_FILE_SIZE}"));
+    rg.push(if sensitive { "-s" } else { "-i" }.to_string());
+    let mut grep: Vec<String> = [
+        "grep",
+        "-rnIH",
+        "--null",
+        "--exclude-dir=.git",
+        "--exclude-dir=node_modules",
+    ]
+    .map(String::from)
+    .to_vec();
+    grep.push(if regex { "-E" } else { "-F" }.to_string());
+    if !regex {
+        rg.push("-F".to_string());
+    }
+    if !sensitive {
+        grep.push("-i".to_string());
+    }
+    for glob in &options.globs {
+        rg.extend(["-g".to_string(), glob.clone()]);
+        grep.push(match glob.strip_prefix('!') {
+            Some(excluded) => format!("--exclude={excluded}"),
+            None => format!("--include={glob}"),
+        });
+    }
+    for args in [&mut rg, &mut grep] {
+        args.extend(["-e".to_string(), query.to_string()]);
+    }
+    grep.push(".".to_string());
+    let line = |args: &[String]| {
+        args.iter()
+            .map(|a| crate::tmux::quote(a))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    format!(
+        "if command -v rg >/dev/null 2>&1; then exec {}; else exec {}; fi",
+        line(&rg),
+        line(&grep)
+    )
+}
+
+/// `run_search` for a project on an SSH host: `rg` or `grep` runs there and
+/// matches are highlighted here. Context lines aren't collected.
+fn run_remote_search(
+    app: &AppHandle,
+    root: &RemoteRoot,
+    query: &str,
+    options: &SearchOptions,
+    channel: &Channel<SearchEvent>,
+    cancelled: &AtomicBool,
+) -> Result<SearchSummary, String> {
+    let started = Instant::now();
+    let matcher = build_matcher(query, options)?;
+    let max_results = options
+        .max_results
+        .map(|n| n as usize)
+        .unwrap_or(DEFAULT_MAX_RESULTS);
+    let mut child = crate::remote_fs::spawn_in(app, root, &remote_search_script(query, options))?;
+    let stdout = child.stdout.take().ok_or("ssh has no output")?;
+    let mut reader = BufReader::new(stdout);
+
+    let mut files_with_matches = 0;
+    let mut matches = 0;
+    let mut current: Option<(String, Vec<SearchMatch>)> = None;
+    let mut stopped = false;
+    let flush = |current: &mut Option<(String, Vec<SearchMatch>)>| match current.take() {
+        Some((path, matches)) => channel.send(SearchEvent::File { path, matches }).is_ok(),
+        None => true,
+    };
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line).unwrap_or(0) == 0 {
+            break;
+        }
+        if cancelled.load(Ordering::Relaxed) || matches == max_results {
+            stopped = true;
+            break;
+        }
+        let Some(nul) = line.iter().position(|&b| b == 0) else {
+            continue;
+        };
+        let path = String::from_utf8_lossy(&line[..nul]);
+        let path = path.strip_prefix("./").unwrap_or(&path).to_string();
+        let rest = &line[nul + 1..];
+        let Some(colon) = rest.iter().position(|&b| b == b':') else {
+            continue;
+        };
+        let line_number = String::from_utf8_lossy(&rest[..colon]).parse().unwrap_or(0);
+        let text = clip(&rest[colon + 1..]);
+        let mut ranges = Vec::new();
+        let _ = matcher.find_iter(text.as_bytes(), |m| {
+            ranges.push([utf16_offset(&text, m.start()), utf16_offset(&text, m.end())]);
+            true
+        });
+        if current.as_ref().is_some_and(|(p, _)| *p != path) && !flush(&mut current) {
+            stopped = true;
+            break;
+        }
+        let (_, file) = current.get_or_insert_with(|| {
+            files_with_matches += 1;
+            (path, Vec::new())
+        });
+        file.push(SearchMatch {
+            line_number,
+            text,
+            ranges,
+            before: Vec::new(),
+            after: Vec::new(),
+        });
+        matches += 1;
+    }
+    flush(&mut current);
+    if stopped {
+        let _ = child.kill();
+    }
+    let status = child.wait().map_err(|e| e.to_string())?;
+    // 1 is "no matches" and 2 an unreadable file for both tools; 255 is ssh.
+    if !stopped && status.code() == Some(255) {
+        let mut message = String::new();
+        if let Some(mut stderr) = child.stderr.take() {
+            let _ = stderr.read_to_string(&mut message);
+        }
+        return Err(format!(
+            "search on {} failed: {}",
+            root.destination,
+            message.trim()
+        ));
+    }
+    Ok(SearchSummary {
+        files_with_matches,
+        matches: matches as u32,
+        truncated: stopped,
+        elapsed_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+/// Search file contents under `root`, honoring .gitignore. Matches stream
+/// over `on_event` one file at a time; the final `Done` event (also the
+/// return value) carries the totals. `id` doubles as the request id for
+/// `cancel`; starting a search with an `id` that is still running cancels
+/// the previous one.
+#[tauri::command]
+pub async fn search_project(
+    window: WebviewWindow,
+    app: AppHandle,
+    id: String,
+    root: String,
+    query: String,
+    options: Option<SearchOptions>,
+    on_event: Channel<SearchEvent>,
+) -> Result<SearchSummary, String> {
+    crate::require_app_window(&window)?;
+    let options = options.unwrap_or_default();
+    if query.is_empty() {
+        return Err("search query is empty".to_string());
+    }
+    let sandbox = window.state::<Sandbox>();
+    let target = match sandbox.remote(&root)? {
+        Some(remote) => Err(remote),
+        None => Ok(sandbox.check_dir(&root)?),
+    };
+
+    let guard = crate::cancel::register(&app, Some(&id));
+    let token = guard.token();
+    let channel = on_event.clone();
+    let handle = app.clone();
+    let summary = tauri::async_runtime::spawn_blocking(move || match &target {
+        Ok(root_path) => run_search(root_path, &query, &options, &channel, token.flag()),
+        Err(remote) => run_remote_search(&handle, remote, &query, &options, &channel, token.flag()),
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    drop(guard);
+
+    let _ = on_event.send(SearchEvent::Done(summary.clone()));
+    Ok(summary)
+}
+
+/// Same as `cancel` with the search's `id`.
+#[tauri::command]
+pub fn cancel_search(window: WebviewWindow, app: AppHandle, id: String) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    crate::cancel::cancel_request(&app, &id);
+    Ok(())
+}
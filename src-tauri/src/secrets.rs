@@ -0,0 +1,341 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+use crate::forge::ForgeProvider;
+
+/// Keychain service every secret is stored under; the secret name is the
+/// account.
+const SERVICE: &str = "funny";
+
+/// Names of the stored secrets and their environment variables. The keychain
+/// can't be listed portably, so this is what `secret_list` and the spawn-time
+/// injection go by. It never holds values.
+const INDEX_FILE: &str = "secrets.json";
+
+/// Secrets the app knows about, with the variable agents expect them in.
+/// Other names map to their upper-snake-case form (`my-token` → `MY_TOKEN`).
+const KNOWN: &[(&str, &str)] = &[
+    ("anthropic-api-key", "ANTHROPIC_API_KEY"),
+    ("openai-api-key", "OPENAI_API_KEY"),
+    ("gemini-api-key", "GEMINI_API_KEY"),
+    ("github-token", "GH_TOKEN"),
+    ("gitlab-token", "GITLAB_TOKEN"),
+];
+
+/// Secrets the app keeps for itself with `set_unlisted`. They can't be set
+/// through `secret_set` and never reach the server or agents.
+const RESERVED: &[&str] = &["intake-token"];
+
+/// The forge tokens, sent only to their forge (see `forge::stored_token`).
+const FORGE_TOKENS: &[&str] = &["github-token", "gitlab-token"];
+
+const MAX_VALUE_LEN: usize = 16 * 1024;
+
+/// What the frontend sees of a secret: never the value itself.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecretInfo {
+    pub name: String,
+    /// Environment variable agents and the server receive it as.
+    pub env: String,
+    /// The last four characters at most, e.g. `••••3f9a`.
+    pub masked: String,
+}
+
+/// The index of stored secret names, loaded on first use.
+pub struct SecretStore {
+    index: Mutex<Option<BTreeMap<String, String>>>,
+}
+
+impl SecretStore {
+    pub fn new() -> Self {
+        Self {
+            index: Mutex::new(None),
+        }
+    }
+}
+
+fn index_path(app: &AppHandle) -> Option<PathBuf> {
+    app.path()
+        .app_data_dir()
+        .ok()
+        .map(|dir| dir.join(INDEX_FILE))
+}
+
+fn load_index(app: &AppHandle) -> BTreeMap<String, String> {
+    index_path(app)
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_index(app: &AppHandle, index: &BTreeMap<String, String>) -> Result<(), String> {
+    let path = index_path(app).ok_or("no app data directory")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_vec_pretty(index).map_err(|e| e.to_string())?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, json).map_err(|e| e.to_string())?;
+    std::fs::rename(&tmp, &path).map_err(|e| e.to_string())
+}
+
+fn check_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    if !valid {
+        return Err(format!(
+            "invalid secret name {name:?}: use lowercase letters, digits and '-'"
+        ));
+    }
+    if RESERVED.contains(&name) {
+        return Err(format!("{name} is managed by funny"));
+    }
+    Ok(())
+}
+
+fn check_env(env: &str) -> Result<(), String> {
+    let mut chars = env.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_uppercase() || c == '_')
+        && chars.all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("invalid environment variable name {env:?}"))
+    }
+}
+
+fn default_env(name: &str) -> String {
+    KNOWN
+        .iter()
+        .find(|(known, _)| *known == name)
+        .map(|(_, env)| env.to_string())
+        .unwrap_or_else(|| name.replace('-', "_").to_uppercase())
+}
+
+fn mask(value: &str) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    // Short values would be mostly revealed by their last four characters.
+    let shown = if chars.len() >= 12 {
+        chars[chars.len() - 4..].iter().collect()
+    } else {
+        String::new()
+    };
+    format!("••••{shown}")
+}
+
+fn entry(name: &str) -> Result<keyring::Entry, String> {
+    keyring::Entry::new(SERVICE, name).map_err(|e| format!("keychain unavailable: {e}"))
+}
+
+/// Read a secret from the keychain. Missing secrets and keychain errors are
+/// both `None`; the latter are logged.
+pub(crate) fn get(name: &str) -> Option<String> {
+    match keyring::Entry::new(SERVICE, name).and_then(|entry| entry.get_password()) {
+        Ok(value) => Some(value),
+        Err(keyring::Error::NoEntry) => None,
+        Err(e) => {
+            tracing::warn!("failed to read secret {name}: {e}");
+            None
+        }
+    }
+}
+
+/// Store one of the `RESERVED` secrets. It stays out of the index, so it is
+/// neither listed nor injected into the server's environment.
+pub(crate) fn set_unlisted(name: &str, value: &str) -> Result<(), String> {
+    debug_assert!(RESERVED.contains(&name));
+    entry(name)?
+        .set_password(value)
+        .map_err(|e| format!("failed to store secret {name}: {e}"))
+}
+
+/// A stored API key for a model server. The reserved secrets and the forge
+/// tokens are only ever sent by the code that owns them, so they are
+/// refused.
+pub(crate) fn api_key(name: &str) -> Result<String, String> {
+    if RESERVED.contains(&name) || FORGE_TOKENS.contains(&name) {
+        return Err(format!("{name} is not an API key"));
+    }
+    get(name).ok_or_else(|| format!("no secret {name}"))
+}
+
+/// The stored token for a forge, used when a request doesn't bring its own.
+pub(crate) fn forge_token(provider: ForgeProvider) -> Option<String> {
+    get(match provider {
+        ForgeProvider::Github => "github-token",
+        ForgeProvider::Gitlab => "gitlab-token",
+    })
+}
+
+/// Every stored secret as `(variable, value)`, for the environment of the
+/// server sidecar and, through it, the agents it spawns. The values also
+/// become what `redact` masks in terminal output and logs.
+pub(crate) fn env(app: &AppHandle) -> Vec<(String, String)> {
+    let index = match app.try_state::<SecretStore>() {
+        Some(store) => match store.index.lock() {
+            Ok(mut index) => index.get_or_insert_with(|| load_index(app)).clone(),
+            Err(_) => return Vec::new(),
+        },
+        None => return Vec::new(),
+    };
+    let vars: Vec<(String, String)> = index
+        .into_iter()
+        .filter_map(|(name, env)| get(&name).map(|value| (env, value)))
+        .collect();
+    crate::redact::set_secret_values(vars.iter().map(|(_, value)| value.clone()));
+    vars
+}
+
+/// Names of the stored secrets and their environment variables, without
+/// the values.
+pub(crate) fn names(app: &AppHandle) -> BTreeMap<String, String> {
+    match app.try_state::<SecretStore>() {
+        Some(store) => match store.index.lock() {
+            Ok(mut index) => index.get_or_insert_with(|| load_index(app)).clone(),
+            Err(_) => BTreeMap::new(),
+        },
+        None => BTreeMap::new(),
+    }
+}
+
+/// Re-read the stored values for redaction after a change.
+fn refresh_redaction(app: &AppHandle) {
+    env(app);
+}
+
+/// Store `value` in the OS keychain under `name`. It is handed to the server
+/// and agents as `env` (by default the known variable for the name, or its
+/// upper-snake-case form) from the next server start.
+#[tauri::command]
+pub async fn secret_set(
+    window: WebviewWindow,
+    app: AppHandle,
+    name: String,
+    value: String,
+    env: Option<String>,
+) -> Result<SecretInfo, String> {
+    crate::require_window(&window, "main")?;
+    check_name(&name)?;
+    let env = env.unwrap_or_else(|| default_env(&name));
+    check_env(&env)?;
+    if value.is_empty() || value.len() > MAX_VALUE_LEN {
+        return Err(format!(
+            "secret value must be between 1 and {MAX_VALUE_LEN} bytes"
+        ));
+    }
+
+    tauri::async_runtime::spawn_blocking(move || {
+        entry(&name)?
+            .set_password(&value)
+            .map_err(|e| format!("failed to store secret {name}: {e}"))?;
+        let store = app.state::<SecretStore>();
+        let mut guard = store.index.lock().map_err(|e| e.to_string())?;
+        let index = guard.get_or_insert_with(|| load_index(&app));
+        index.insert(name.clone(), env.clone());
+        save_index(&app, index)?;
+        drop(guard);
+        refresh_redaction(&app);
+        tracing::info!("stored secret {name}");
+        Ok(SecretInfo {
+            masked: mask(&value),
+            name,
+            env,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// The masked form of a stored secret, or `None` if it isn't set.
+#[tauri::command]
+pub async fn secret_get(
+    window: WebviewWindow,
+    app: AppHandle,
+    name: String,
+) -> Result<Option<SecretInfo>, String> {
+    crate::require_window(&window, "main")?;
+    check_name(&name)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let env = app
+            .state::<SecretStore>()
+            .index
+            .lock()
+            .map_err(|e| e.to_string())?
+            .get_or_insert_with(|| load_index(&app))
+            .get(&name)
+            .cloned()
+            .unwrap_or_else(|| default_env(&name));
+        Ok(get(&name).map(|value| SecretInfo {
+            masked: mask(&value),
+            name,
+            env,
+        }))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Masked forms of every stored secret.
+#[tauri::command]
+pub async fn secret_list(window: WebviewWindow, app: AppHandle) -> Result<Vec<SecretInfo>, String> {
+    crate::require_window(&window, "main")?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let index = app
+            .state::<SecretStore>()
+            .index
+            .lock()
+            .map_err(|e| e.to_string())?
+            .get_or_insert_with(|| load_index(&app))
+            .clone();
+        Ok(index
+            .into_iter()
+            .filter_map(|(name, env)| {
+                get(&name).map(|value| SecretInfo {
+                    masked: mask(&value),
+                    name,
+                    env,
+                })
+            })
+            .collect())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Remove a secret from the keychain. Deleting a missing secret is a no-op.
+#[tauri::command]
+pub async fn secret_delete(
+    window: WebviewWindow,
+    app: AppHandle,
+    name: String,
+) -> Result<(), String> {
+    crate::require_window(&window, "main")?;
+    check_name(&name)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        match entry(&name)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => {}
+            Err(e) => return Err(format!("failed to delete secret {name}: {e}")),
+        }
+        let store = app.state::<SecretStore>();
+        let mut guard = store.index.lock().map_err(|e| e.to_string())?;
+        let index = guard.get_or_insert_with(|| load_index(&app));
+        if index.remove(&name).is_some() {
+            save_index(&app, index)?;
+        }
+        drop(guard);
+        refresh_redaction(&app);
+        tracing::info!("deleted secret {name}");
+        Ok(())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
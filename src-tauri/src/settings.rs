@@ -0,0 +1,402 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State, WebviewWindow};
+
+use crate::automation::AutomationSettings;
+use crate::cleanup::RetentionSettings;
+use crate::confine::ShellSandbox;
+use crate::digest::DigestSettings;
+use crate::disk::DiskSettings;
+use crate::egress::NetworkSettings;
+use crate::events::{emit_subscribed, EventRouter, EventSettings};
+use crate::file_locks::FileLockSettings;
+use crate::forge::ForgeSettings;
+use crate::injection::InjectionSettings;
+use crate::intake::IntakeSettings;
+use crate::issues::IssueSettings;
+use crate::local_agent::LocalAgentSettings;
+use crate::notifications::NotificationRules;
+use crate::open::EditorSettings;
+use crate::plugins::PluginSettings;
+use crate::policy::{CommandRules, PolicyProfile};
+use crate::problems::ProblemSettings;
+use crate::profiles::TerminalProfile;
+use crate::project_config::TrustSettings;
+use crate::verify::VerifyConfig;
+use crate::worktree_pool::WorktreePoolSettings;
+
+/// Bumped whenever a migration is added to `migrate`.
+const SCHEMA_VERSION: u32 = 1;
+
+const SETTINGS_FILE: &str = "settings.json";
+
+/// Written by earlier versions before notification rules moved into the
+/// settings file; imported once by the v0 → v1 migration.
+const LEGACY_NOTIFICATION_RULES_FILE: &str = "notification-rules.json";
+
+/// Event-router topic windows join with `settings_subscribe`.
+const SETTINGS_TOPIC: &str = "settings";
+
+const MIN_SCROLLBACK: u32 = 100;
+const MAX_SCROLLBACK: u32 = 1_000_000;
+const MAX_PREWARM: u32 = 4;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TerminalSettings {
+    /// Lines of scrollback kept per terminal.
+    pub scrollback: u32,
+    /// Shell for new terminals; `$SHELL` (or `%COMSPEC%`) when unset.
+    pub default_shell: Option<String>,
+    /// Mask stored secrets and API tokens in terminal output.
+    pub redact_secrets: bool,
+    /// Shells kept started and idle per project, so new terminals open
+    /// without waiting for shell startup. 0 turns the pool off.
+    pub prewarm: u32,
+    /// Named terminal setups, e.g. imported from another terminal app.
+    pub profiles: Vec<TerminalProfile>,
+}
+
+impl Default for TerminalSettings {
+    fn default() -> Self {
+        Self {
+            scrollback: 10_000,
+            default_shell: None,
+            redact_secrets: true,
+            prewarm: 1,
+            profiles: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TelemetrySettings {
+    /// Usage counts are only ever uploaded when this is set.
+    pub opt_in: bool,
+}
+
+/// Defaults for new agent runs; unset fields fall back to the server's.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AgentDefaults {
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub permission_mode: Option<String>,
+    /// Confinement for agent shells; see `confine`.
+    pub shell_sandbox: Option<ShellSandbox>,
+    /// Checks the agent's work once it finishes; see `verify`.
+    pub verify: Option<VerifyConfig>,
+    /// Preset for what agents may run and write unasked; see `policy`. Not
+    /// read from project configs.
+    pub policy: Option<PolicyProfile>,
+}
+
+impl AgentDefaults {
+    /// These settings, with the unset ones taken from `defaults`.
+    pub(crate) fn or(self, defaults: AgentDefaults) -> Self {
+        Self {
+            provider: self.provider.or(defaults.provider),
+            model: self.model.or(defaults.model),
+            permission_mode: self.permission_mode.or(defaults.permission_mode),
+            shell_sandbox: self.shell_sandbox.or(defaults.shell_sandbox),
+            verify: self.verify.or(defaults.verify),
+            policy: self.policy.or(defaults.policy),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    pub version: u32,
+    pub terminal: TerminalSettings,
+    pub notifications: NotificationRules,
+    pub agents: AgentDefaults,
+    pub telemetry: TelemetrySettings,
+    /// Policy for shell commands agents run through the app.
+    pub commands: CommandRules,
+    /// Which hosts the server and its agents may reach.
+    pub network: NetworkSettings,
+    /// Throttling of bursty events to the webviews.
+    pub events: EventSettings,
+    /// Recognising compiler and linter diagnostics in command output.
+    pub problems: ProblemSettings,
+    /// Issue trackers tasks can be created from.
+    pub issues: IssueSettings,
+    /// The local endpoint other tools can post tasks to.
+    pub intake: IntakeSettings,
+    /// The local socket `funnyctl` scripts the app through.
+    pub automation: AutomationSettings,
+    /// External programs that extend the app; see `plugins`.
+    pub plugins: PluginSettings,
+    /// The daily summary of finished agent runs.
+    pub digest: DigestSettings,
+    /// How long old worktrees, transcripts and crash reports are kept.
+    pub retention: RetentionSettings,
+    /// Free space kept on project disks.
+    pub disk: DiskSettings,
+    /// The external editor files are opened in.
+    pub editor: EditorSettings,
+    /// Scanning of issue bodies and files for prompt injection.
+    pub injection: InjectionSettings,
+    /// Advisory locks on files being written by agents and the user.
+    pub locks: FileLockSettings,
+    /// Worktrees made ahead of time for new tasks.
+    pub worktree_pool: WorktreePoolSettings,
+    /// Self-hosted forges the stored API tokens may be sent to.
+    pub forge: ForgeSettings,
+    /// Model servers the stored API keys may be sent to.
+    pub local_agent: LocalAgentSettings,
+    /// Projects allowed to run the commands their config supplies.
+    pub trust: TrustSettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SCHEMA_VERSION,
+            terminal: TerminalSettings::default(),
+            notifications: NotificationRules::default(),
+            agents: AgentDefaults::default(),
+            telemetry: TelemetrySettings::default(),
+            commands: CommandRules::default(),
+            network: NetworkSettings::default(),
+            events: EventSettings::default(),
+            problems: ProblemSettings::default(),
+            issues: IssueSettings::default(),
+            intake: IntakeSettings::default(),
+            automation: AutomationSettings::default(),
+            plugins: PluginSettings::default(),
+            digest: DigestSettings::default(),
+            retention: RetentionSettings::default(),
+            disk: DiskSettings::default(),
+            editor: EditorSettings::default(),
+            injection: InjectionSettings::default(),
+            locks: FileLockSettings::default(),
+            worktree_pool: WorktreePoolSettings::default(),
+            forge: ForgeSettings::default(),
+            local_agent: LocalAgentSettings::default(),
+            trust: TrustSettings::default(),
+        }
+    }
+}
+
+impl Settings {
+    fn validate(&self) -> Result<(), String> {
+        if !(MIN_SCROLLBACK..=MAX_SCROLLBACK).contains(&self.terminal.scrollback) {
+            return Err(format!(
+                "scrollback must be between {MIN_SCROLLBACK} and {MAX_SCROLLBACK} lines"
+            ));
+        }
+        if self.terminal.prewarm > MAX_PREWARM {
+            return Err(format!(
+                "at most {MAX_PREWARM} shells can be pre-started per project"
+            ));
+        }
+        if let Some(shell) = &self.terminal.default_shell {
+            if shell.trim().is_empty() {
+                return Err("default shell must not be empty".to_string());
+            }
+        }
+        crate::profiles::validate_profiles(&self.terminal.profiles)?;
+        crate::policy::validate_rules(&self.commands)?;
+        crate::egress::validate_settings(&self.network)?;
+        crate::events::validate_settings(&self.events)?;
+        crate::problems::validate_settings(&self.problems)?;
+        crate::issues::validate_settings(&self.issues)?;
+        crate::intake::validate_settings(&self.intake)?;
+        crate::plugins::validate_settings(&self.plugins)?;
+        crate::digest::validate_settings(&self.digest)?;
+        crate::cleanup::validate_settings(&self.retention)?;
+        crate::open::validate_settings(&self.editor)?;
+        crate::injection::validate_settings(&self.injection)?;
+        crate::file_locks::validate_settings(&self.locks)?;
+        crate::worktree_pool::validate_settings(&self.worktree_pool)?;
+        if let Some(verify) = &self.agents.verify {
+            crate::verify::validate_config(verify)?;
+        }
+        crate::forge::validate_settings(&self.forge)?;
+        crate::local_agent::validate_settings(&self.local_agent)?;
+        crate::notifications::validate_rules(&self.notifications)
+    }
+}
+
+/// The settings in memory, loaded (and migrated) from disk on first use.
+pub struct SettingsStore {
+    settings: Mutex<Option<Settings>>,
+}
+
+impl SettingsStore {
+    pub fn new() -> Self {
+        Self {
+            settings: Mutex::new(None),
+        }
+    }
+}
+
+fn data_file(app: &AppHandle, name: &str) -> Option<PathBuf> {
+    app.path().app_data_dir().ok().map(|dir| dir.join(name))
+}
+
+/// Bring a settings document from `version` up to `SCHEMA_VERSION`, one step
+/// at a time.
+fn migrate(app: &AppHandle, value: &mut Value) {
+    let mut version = value.get("version").and_then(Value::as_u64).unwrap_or(0) as u32;
+    if version == 0 {
+        let legacy = data_file(app, LEGACY_NOTIFICATION_RULES_FILE)
+            .and_then(|p| std::fs::read_to_string(p).ok())
+            .and_then(|content| serde_json::from_str::<Value>(&content).ok());
+        if let (Some(rules), Some(object)) = (legacy, value.as_object_mut()) {
+            object.entry("notifications").or_insert(rules);
+        }
+        version = 1;
+    }
+    if let Some(object) = value.as_object_mut() {
+        object.insert("version".to_string(), Value::from(version));
+    }
+}
+
+fn load(app: &AppHandle) -> Settings {
+    let mut value = data_file(app, SETTINGS_FILE)
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+        .filter(Value::is_object)
+        .unwrap_or_else(|| Value::Object(Default::default()));
+    let from = value.get("version").and_then(Value::as_u64).unwrap_or(0);
+    migrate(app, &mut value);
+    let settings: Settings = serde_json::from_value(value).unwrap_or_default();
+    if from < SCHEMA_VERSION as u64 {
+        if let Err(e) = save(app, &settings) {
+            tracing::warn!("failed to save migrated settings: {e}");
+        }
+    }
+    settings
+}
+
+fn save(app: &AppHandle, settings: &Settings) -> Result<(), String> {
+    let path = data_file(app, SETTINGS_FILE).ok_or("no app data directory")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_vec_pretty(settings).map_err(|e| e.to_string())?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, json).map_err(|e| e.to_string())?;
+    std::fs::rename(&tmp, &path).map_err(|e| e.to_string())
+}
+
+/// The current settings.
+pub(crate) fn current(app: &AppHandle) -> Settings {
+    let store = match app.try_state::<SettingsStore>() {
+        Some(store) => store,
+        None => return Settings::default(),
+    };
+    let mut settings = match store.settings.lock() {
+        Ok(settings) => settings,
+        Err(_) => return Settings::default(),
+    };
+    settings.get_or_insert_with(|| load(app)).clone()
+}
+
+/// Change settings with `edit`, validate, persist and tell subscribed windows.
+pub(crate) fn update(
+    app: &AppHandle,
+    edit: impl FnOnce(&mut Settings) -> Result<(), String>,
+) -> Result<Settings, String> {
+    let store = app.state::<SettingsStore>();
+    let mut guard = store.settings.lock().map_err(|e| e.to_string())?;
+    let mut settings = guard.get_or_insert_with(|| load(app)).clone();
+    edit(&mut settings)?;
+    settings.version = SCHEMA_VERSION;
+    settings.validate()?;
+    save(app, &settings)?;
+    *guard = Some(settings.clone());
+    drop(guard);
+
+    crate::events::configure(app, &settings.events);
+    crate::intake::configure(app, &settings.intake);
+    crate::automation::configure(app, &settings.automation);
+    crate::plugins::configure(app, &settings.plugins);
+    emit_subscribed(app, SETTINGS_TOPIC, "settings:changed", settings.clone());
+    Ok(settings)
+}
+
+/// Replace the settings with `value`, a whole settings document of this or
+/// an earlier schema version, e.g. from a workspace file.
+pub(crate) fn replace(app: &AppHandle, mut value: Value) -> Result<Settings, String> {
+    if !value.is_object() {
+        return Err("settings must be an object".to_string());
+    }
+    if value.get("version").and_then(Value::as_u64).unwrap_or(0) > SCHEMA_VERSION as u64 {
+        return Err("settings are from a newer version of funny".to_string());
+    }
+    migrate(app, &mut value);
+    let imported: Settings =
+        serde_json::from_value(value).map_err(|e| format!("invalid settings: {e}"))?;
+    update(app, |settings| {
+        *settings = imported;
+        Ok(())
+    })
+}
+
+/// Merge `patch` into `target`: objects key by key, anything else replaced.
+fn merge(target: &mut Value, patch: Value) {
+    match (target, patch) {
+        (Value::Object(target), Value::Object(patch)) => {
+            for (key, value) in patch {
+                merge(target.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (target, patch) => *target = patch,
+    }
+}
+
+#[tauri::command]
+pub fn settings_get(window: WebviewWindow, app: AppHandle) -> Result<Settings, String> {
+    crate::require_app_window(&window)?;
+    Ok(current(&app))
+}
+
+/// Apply a partial update, e.g. `{ "terminal": { "scrollback": 5000 } }`,
+/// and return the resulting settings. Invalid values reject the whole patch.
+#[tauri::command]
+pub fn settings_set(
+    window: WebviewWindow,
+    app: AppHandle,
+    patch: Value,
+) -> Result<Settings, String> {
+    crate::require_window(&window, "main")?;
+    if !patch.is_object() {
+        return Err("settings patch must be an object".to_string());
+    }
+    update(&app, |settings| {
+        let mut value = serde_json::to_value(&*settings).map_err(|e| e.to_string())?;
+        merge(&mut value, patch);
+        *settings = serde_json::from_value(value).map_err(|e| format!("invalid settings: {e}"))?;
+        Ok(())
+    })
+}
+
+/// Receive `settings:changed` events with the full settings whenever they
+/// change, until `settings_unsubscribe` or the window closes.
+#[tauri::command]
+pub fn settings_subscribe(
+    window: WebviewWindow,
+    router: State<'_, EventRouter>,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    router.subscribe(SETTINGS_TOPIC, window.label());
+    Ok(())
+}
+
+#[tauri::command]
+pub fn settings_unsubscribe(
+    window: WebviewWindow,
+    router: State<'_, EventRouter>,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    router.unsubscribe(SETTINGS_TOPIC, window.label());
+    Ok(())
+}
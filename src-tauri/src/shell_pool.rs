@@ -0,0 +1,158 @@
+use portable_pty::PtySize;
+use std::collections::{HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+use crate::pty::Opened;
+
+/// Projects shells are kept for at once.
+const MAX_PROJECTS: usize = 4;
+/// Idle shells older than this are replaced, so edits to shell startup
+/// files reach new terminals.
+const MAX_AGE: Duration = Duration::from_secs(10 * 60);
+/// Terminals are resized when handed out; this is only for startup.
+const IDLE_SIZE: PtySize = PtySize {
+    rows: 24,
+    cols: 80,
+    pixel_width: 0,
+    pixel_height: 0,
+};
+
+struct Idle {
+    cwd: PathBuf,
+    shell: Opened,
+    started: Instant,
+}
+
+/// Shells started ahead of time for the projects terminals were recently
+/// opened in, handed to the next plain-shell `pty_spawn` there. Agent
+/// shells are confined per spawn, so they are never pooled.
+pub struct ShellPool {
+    /// Oldest first.
+    idle: Mutex<VecDeque<Idle>>,
+    /// Projects a shell is being started for.
+    filling: Mutex<HashSet<PathBuf>>,
+}
+
+impl ShellPool {
+    pub fn new() -> Self {
+        Self {
+            idle: Mutex::new(VecDeque::new()),
+            filling: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub(crate) fn idle_count(&self) -> usize {
+        self.idle.lock().map(|idle| idle.len()).unwrap_or(0)
+    }
+}
+
+/// An idle default shell started in `cwd`, if one is ready. Ones that are
+/// stale, exited or started with a different shell are dropped.
+pub(crate) fn take(app: &AppHandle, cwd: &Path) -> Option<Opened> {
+    let state = app.try_state::<ShellPool>()?;
+    let shell = crate::pty::default_shell(app);
+    let mut idle = state.idle.lock().ok()?;
+    let mut taken = None;
+    let mut kept = VecDeque::with_capacity(idle.len());
+    for mut entry in idle.drain(..) {
+        let usable = entry.started.elapsed() < MAX_AGE
+            && entry.shell.program == shell
+            && entry.shell.is_running();
+        if !usable {
+            entry.shell.kill();
+        } else if taken.is_none() && entry.cwd == cwd {
+            taken = Some(entry.shell);
+        } else {
+            kept.push_back(entry);
+        }
+    }
+    *idle = kept;
+    taken
+}
+
+/// Top up the idle shells for `cwd`, off the calling thread.
+pub(crate) fn replenish(app: &AppHandle, cwd: &Path) {
+    let wanted = crate::settings::current(app).terminal.prewarm as usize;
+    if wanted == 0 {
+        return;
+    }
+    let state = match app.try_state::<ShellPool>() {
+        Some(state) => state,
+        None => return,
+    };
+    let started = state
+        .filling
+        .lock()
+        .map(|mut filling| filling.insert(cwd.to_path_buf()))
+        .unwrap_or(false);
+    if !started {
+        return;
+    }
+    let app = app.clone();
+    let cwd = cwd.to_path_buf();
+    std::thread::spawn(move || {
+        let state = app.state::<ShellPool>();
+        loop {
+            let have = match state.idle.lock() {
+                Ok(idle) => idle.iter().filter(|e| e.cwd == cwd).count(),
+                Err(_) => break,
+            };
+            if have >= wanted {
+                break;
+            }
+            let shell = match crate::pty::open(&app, &cwd, IDLE_SIZE, None) {
+                Ok(shell) => shell,
+                Err(e) => {
+                    tracing::debug!("failed to pre-start a shell in {}: {e}", cwd.display());
+                    break;
+                }
+            };
+            if let Ok(mut idle) = state.idle.lock() {
+                idle.push_back(Idle {
+                    cwd: cwd.clone(),
+                    shell,
+                    started: Instant::now(),
+                });
+                evict(&mut idle);
+            }
+        }
+        if let Ok(mut filling) = state.filling.lock() {
+            filling.remove(&cwd);
+        };
+    });
+}
+
+/// Drop the shells of the projects past `MAX_PROJECTS` with the oldest
+/// last-started shell.
+fn evict(idle: &mut VecDeque<Idle>) {
+    let mut projects: Vec<PathBuf> = Vec::new();
+    for entry in idle.iter().rev() {
+        if !projects.contains(&entry.cwd) {
+            projects.push(entry.cwd.clone());
+        }
+    }
+    if projects.len() <= MAX_PROJECTS {
+        return;
+    }
+    let evicted = &projects[MAX_PROJECTS..];
+    idle.retain_mut(|entry| {
+        if evicted.contains(&entry.cwd) {
+            entry.shell.kill();
+            false
+        } else {
+            true
+        }
+    });
+}
+
+/// Kill every idle shell, e.g. on exit.
+pub(crate) fn drain(state: &ShellPool) {
+    if let Ok(mut idle) = state.idle.lock() {
+        for mut entry in idle.drain(..) {
+            entry.shell.kill();
+        }
+    }
+}
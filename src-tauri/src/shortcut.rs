@@ -0,0 +1,165 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, State, WebviewWindow};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+
+use crate::notifications::Notifications;
+use crate::pty::PtyManager;
+use crate::tray::{TrayState, TrayTask};
+
+const DEFAULT_SUMMON_SHORTCUT: &str = "CommandOrControl+Shift+Space";
+
+const SHORTCUTS_FILE: &str = "shortcuts.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ShortcutConfig {
+    /// Accelerator string; `None` disables the summon shortcut.
+    summon: Option<String>,
+}
+
+/// The currently registered summon shortcut, if any.
+pub struct SummonShortcut {
+    current: Mutex<Option<Shortcut>>,
+}
+
+impl SummonShortcut {
+    pub fn new() -> Self {
+        Self {
+            current: Mutex::new(None),
+        }
+    }
+}
+
+/// Where the frontend should put focus after the window was summoned.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SummonPayload {
+    /// A task waiting for input, from the last tray status update.
+    pub task: Option<TrayTask>,
+    /// The open terminal that most recently rang its bell.
+    pub terminal_id: Option<String>,
+}
+
+fn config_path(app: &AppHandle) -> Option<PathBuf> {
+    app.path()
+        .app_data_dir()
+        .ok()
+        .map(|dir| dir.join(SHORTCUTS_FILE))
+}
+
+fn saved_accelerator(app: &AppHandle) -> Option<String> {
+    let saved = config_path(app)
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|content| serde_json::from_str::<ShortcutConfig>(&content).ok());
+    match saved {
+        Some(config) => config.summon,
+        None => Some(DEFAULT_SUMMON_SHORTCUT.to_string()),
+    }
+}
+
+/// Bring the main window to front and tell the frontend what needs input.
+fn summon(app: &AppHandle) {
+    let main = match app.get_webview_window("main") {
+        Some(main) => main,
+        None => return,
+    };
+    let _ = main.unminimize();
+    let _ = main.show();
+    let _ = main.set_focus();
+
+    let task = app.state::<TrayState>().waiting_task();
+    let ptys = app.state::<PtyManager>();
+    let terminal_id = app
+        .state::<Notifications>()
+        .last_bell_terminal(|id| ptys.is_open(id));
+    let _ = app.emit_to(
+        "main",
+        "shortcut:summon",
+        SummonPayload { task, terminal_id },
+    );
+}
+
+/// Swap the registered summon shortcut for `accelerator` (or none).
+fn register(app: &AppHandle, accelerator: Option<&str>) -> Result<(), String> {
+    let next = accelerator
+        .map(|a| {
+            a.parse::<Shortcut>()
+                .map_err(|e| format!("invalid shortcut {a}: {e}"))
+        })
+        .transpose()?;
+    let state = app.state::<SummonShortcut>();
+    let mut current = state.current.lock().map_err(|e| e.to_string())?;
+    let shortcuts = app.global_shortcut();
+    if let Some(previous) = current.take() {
+        let _ = shortcuts.unregister(previous);
+    }
+    if let Some(shortcut) = next {
+        shortcuts
+            .register(shortcut)
+            .map_err(|e| format!("could not register shortcut: {e}"))?;
+        *current = Some(shortcut);
+    }
+    Ok(())
+}
+
+/// Install the global-shortcut plugin and register the saved (or default)
+/// summon shortcut. Called once from `setup`; a shortcut another app already
+/// owns is logged, not fatal.
+pub fn init(app: &AppHandle) -> tauri::Result<()> {
+    app.plugin(
+        tauri_plugin_global_shortcut::Builder::new()
+            .with_handler(|app, _shortcut, event| {
+                if event.state() == ShortcutState::Pressed {
+                    summon(app);
+                }
+            })
+            .build(),
+    )?;
+    if let Err(e) = register(app, saved_accelerator(app).as_deref()) {
+        tracing::warn!("{e}");
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_summon_shortcut(
+    window: WebviewWindow,
+    app: AppHandle,
+) -> Result<Option<String>, String> {
+    crate::require_window(&window, "main")?;
+    Ok(saved_accelerator(&app))
+}
+
+/// Change the summon shortcut (e.g. `Alt+Space`), or disable it with `null`.
+/// The new shortcut is registered before it is saved, so a conflict leaves
+/// the previous setting in place on the next launch.
+#[tauri::command]
+pub fn set_summon_shortcut(
+    window: WebviewWindow,
+    app: AppHandle,
+    state: State<'_, SummonShortcut>,
+    accelerator: Option<String>,
+) -> Result<(), String> {
+    crate::require_window(&window, "main")?;
+    let previous = *state.current.lock().map_err(|e| e.to_string())?;
+    if let Err(e) = register(&app, accelerator.as_deref()) {
+        // Put the old shortcut back so a failed change doesn't leave none.
+        if let Some(previous) = previous {
+            let _ = app.global_shortcut().register(previous);
+            *state.current.lock().map_err(|e| e.to_string())? = Some(previous);
+        }
+        return Err(e);
+    }
+
+    let path = config_path(&app).ok_or("no app data directory")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let config = ShortcutConfig {
+        summon: accelerator,
+    };
+    let json = serde_json::to_vec_pretty(&config).map_err(|e| e.to_string())?;
+    std::fs::write(path, json).map_err(|e| e.to_string())
+}
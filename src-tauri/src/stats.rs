@@ -0,0 +1,189 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+use crate::automation::Automation;
+use crate::clipboard::ClipboardHistory;
+use crate::events::EventBus;
+use crate::file_index::FileIndex;
+use crate::jobs::Jobs;
+use crate::plugins::Plugins;
+use crate::pty::PtyManager;
+use crate::shell_pool::ShellPool;
+use crate::watcher::FileWatcher;
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessStats {
+    /// Resident memory of the backend process. `None` where it can't be
+    /// read, as with the other fields.
+    pub rss_bytes: Option<u64>,
+    pub threads: Option<u32>,
+    /// Open file descriptors, including PTY masters and sockets.
+    pub open_files: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TerminalStats {
+    pub open: usize,
+    /// Running a command rather than sitting at a prompt.
+    pub busy: usize,
+    /// Shells started ahead of time, not yet handed out.
+    pub pooled: usize,
+    pub suspended: usize,
+    /// Scrollback kept for suspended terminals. Open terminals' scrollback
+    /// is held by the frontend.
+    pub suspended_scrollback_bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueDepth {
+    pub events: usize,
+    pub bytes: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexStats {
+    pub root: String,
+    pub entries: usize,
+    /// Roughly the bytes of names held; the maps add overhead on top.
+    pub bytes: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoreStats {
+    pub entries: u64,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendStats {
+    pub process: ProcessStats,
+    pub terminals: TerminalStats,
+    /// Events held back by the throttle, by class.
+    pub event_queues: BTreeMap<String, QueueDepth>,
+    pub file_indexes: Vec<IndexStats>,
+    pub clipboard: StoreStats,
+    /// Agent transcripts in the database; only the open run's is in memory,
+    /// on the server.
+    pub transcripts: StoreStats,
+    /// The database file and its write-ahead log.
+    pub database_bytes: Option<u64>,
+    pub watches: usize,
+    pub running_jobs: usize,
+    pub running_plugins: usize,
+    /// Automation clients following terminal output.
+    pub automation_tails: usize,
+}
+
+#[cfg(target_os = "linux")]
+fn process_stats() -> ProcessStats {
+    let status = std::fs::read_to_string("/proc/self/status").unwrap_or_default();
+    let field = |name: &str| {
+        status
+            .lines()
+            .find_map(|line| line.strip_prefix(name))
+            .and_then(|rest| rest.split_whitespace().next())
+            .and_then(|value| value.parse::<u64>().ok())
+    };
+    ProcessStats {
+        rss_bytes: field("VmRSS:").map(|kib| kib * 1024),
+        threads: field("Threads:").map(|n| n as u32),
+        open_files: std::fs::read_dir("/proc/self/fd")
+            .ok()
+            .map(|entries| entries.count() as u32),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn process_stats() -> ProcessStats {
+    ProcessStats::default()
+}
+
+fn terminal_stats(app: &AppHandle) -> Result<TerminalStats, String> {
+    let (open, busy) = app.state::<PtyManager>().counts();
+    let (suspended, suspended_scrollback_bytes) = crate::db::with(app, |conn| {
+        conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(length(CAST(scrollback AS BLOB))), 0)
+             FROM suspended_terminals",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+    })?;
+    Ok(TerminalStats {
+        open,
+        busy,
+        pooled: app.state::<ShellPool>().idle_count(),
+        suspended,
+        suspended_scrollback_bytes,
+    })
+}
+
+fn transcript_stats(app: &AppHandle) -> Result<StoreStats, String> {
+    crate::db::with(app, |conn| {
+        conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(length(CAST(content AS BLOB))), 0)
+             FROM transcript_entries",
+            [],
+            |row| {
+                Ok(StoreStats {
+                    entries: row.get(0)?,
+                    bytes: row.get(1)?,
+                })
+            },
+        )
+    })
+}
+
+fn database_bytes(app: &AppHandle) -> Option<u64> {
+    let path = crate::db::db_path(app)?;
+    let main = std::fs::metadata(&path).ok()?.len();
+    let mut wal = path.into_os_string();
+    wal.push("-wal");
+    Some(main + std::fs::metadata(wal).map(|m| m.len()).unwrap_or(0))
+}
+
+/// Memory and handles held by the backend, by subsystem, for tracking down
+/// leaks and bloat.
+#[tauri::command]
+pub async fn backend_stats(window: WebviewWindow, app: AppHandle) -> Result<BackendStats, String> {
+    crate::require_app_window(&window)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let (clipboard_entries, clipboard_bytes) = app.state::<ClipboardHistory>().usage();
+        Ok(BackendStats {
+            process: process_stats(),
+            terminals: terminal_stats(&app)?,
+            event_queues: crate::events::queue_depths(&app.state::<EventBus>())
+                .into_iter()
+                .map(|(class, (events, bytes))| (class, QueueDepth { events, bytes }))
+                .collect(),
+            file_indexes: app
+                .state::<FileIndex>()
+                .usage()
+                .into_iter()
+                .map(|(root, entries, bytes)| IndexStats {
+                    root,
+                    entries,
+                    bytes,
+                })
+                .collect(),
+            clipboard: StoreStats {
+                entries: clipboard_entries as u64,
+                bytes: clipboard_bytes as u64,
+            },
+            transcripts: transcript_stats(&app)?,
+            database_bytes: database_bytes(&app),
+            watches: app.state::<FileWatcher>().watch_count(),
+            running_jobs: app.state::<Jobs>().running_count(),
+            running_plugins: app.state::<Plugins>().running_count(),
+            automation_tails: app.state::<Automation>().tail_count(),
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
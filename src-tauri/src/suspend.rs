@@ -0,0 +1,201 @@
+use portable_pty::PtySize;
+use rusqlite::{params, OptionalExtension, Row};
+use serde::Serialize;
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+use crate::confine::ShellSandbox;
+use crate::pty::{Launch, PtyManager};
+use crate::sandbox::Sandbox;
+
+/// Scrollback past this is cut from the top when a terminal is suspended.
+const MAX_SCROLLBACK_BYTES: usize = 8 * 1024 * 1024;
+
+/// A shell whose process was ended to save resources, kept so it can be
+/// reopened in the same place with the same confinement.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuspendedTerminal {
+    pub id: String,
+    /// The shell's directory when it was suspended.
+    pub cwd: String,
+    pub sandbox: ShellSandbox,
+    pub suspended_at: String,
+    /// As the frontend handed it over, e.g. serialized by xterm; only sent
+    /// by `pty_suspended`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scrollback: Option<String>,
+}
+
+fn from_row(row: &Row<'_>, with_scrollback: bool) -> rusqlite::Result<SuspendedTerminal> {
+    let sandbox: String = row.get(2)?;
+    Ok(SuspendedTerminal {
+        id: row.get(0)?,
+        cwd: row.get(1)?,
+        sandbox: serde_json::from_str(&sandbox).unwrap_or_default(),
+        suspended_at: row.get(3)?,
+        scrollback: if with_scrollback {
+            Some(row.get(4)?)
+        } else {
+            None
+        },
+    })
+}
+
+fn load(app: &AppHandle, id: &str) -> Result<Option<SuspendedTerminal>, String> {
+    crate::db::with(app, |conn| {
+        conn.query_row(
+            "SELECT id, cwd, sandbox, suspended_at, scrollback FROM suspended_terminals
+             WHERE id = ?1",
+            params![id],
+            |row| from_row(row, true),
+        )
+        .optional()
+    })
+}
+
+/// Keep the end of `scrollback`, where the latest output is.
+fn tail(mut scrollback: String) -> String {
+    if scrollback.len() <= MAX_SCROLLBACK_BYTES {
+        return scrollback;
+    }
+    let mut start = scrollback.len() - MAX_SCROLLBACK_BYTES;
+    while !scrollback.is_char_boundary(start) {
+        start += 1;
+    }
+    scrollback.drain(..start);
+    scrollback
+}
+
+/// End shell `id`'s process but keep `scrollback` and where and how it
+/// ran, for `pty_resume`. The terminal exits as if killed.
+#[tauri::command]
+pub async fn pty_suspend(
+    window: WebviewWindow,
+    app: AppHandle,
+    id: String,
+    scrollback: String,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let state = app.state::<PtyManager>();
+        let (cwd, sandbox) = crate::pty::resumable(&state, &window, &id)?;
+        let scrollback = tail(crate::redact::redact_tokens(&scrollback));
+        let sandbox_json = serde_json::to_string(&sandbox).map_err(|e| e.to_string())?;
+        crate::db::with(&app, |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO suspended_terminals
+                     (id, cwd, sandbox, scrollback, suspended_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    id,
+                    cwd.to_string_lossy(),
+                    sandbox_json,
+                    scrollback,
+                    chrono::Utc::now().to_rfc3339(),
+                ],
+            )
+        })?;
+        tracing::info!("suspending terminal {id}");
+        crate::pty::close(&state, &window, &id)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// A suspended terminal and its scrollback, to restore on screen before
+/// `pty_resume`.
+#[tauri::command]
+pub async fn pty_suspended(
+    window: WebviewWindow,
+    app: AppHandle,
+    id: String,
+) -> Result<SuspendedTerminal, String> {
+    crate::require_app_window(&window)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        load(&app, &id)?.ok_or_else(|| format!("terminal {id} is not suspended"))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Suspended terminals, most recently suspended first, without scrollback.
+#[tauri::command]
+pub async fn pty_suspended_list(
+    window: WebviewWindow,
+    app: AppHandle,
+) -> Result<Vec<SuspendedTerminal>, String> {
+    crate::require_app_window(&window)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        crate::db::with(&app, |conn| {
+            let mut statement = conn.prepare(
+                "SELECT id, cwd, sandbox, suspended_at FROM suspended_terminals
+                 ORDER BY suspended_at DESC",
+            )?;
+            let rows = statement.query_map([], |row| from_row(row, false))?;
+            rows.collect()
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Start a suspended terminal's shell again, for `window`, where it was
+/// suspended. Its output arrives as `pty:data:<id>` like a new terminal's.
+#[tauri::command]
+pub async fn pty_resume(
+    window: WebviewWindow,
+    app: AppHandle,
+    id: String,
+    rows: u16,
+    cols: u16,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        if app.state::<PtyManager>().is_open(&id) {
+            return Err(format!("terminal {id} is already open"));
+        }
+        let suspended =
+            load(&app, &id)?.ok_or_else(|| format!("terminal {id} is not suspended"))?;
+        // The project may have been closed since.
+        let cwd = app.state::<Sandbox>().check_dir(&suspended.cwd)?;
+        let size = PtySize {
+            rows,
+            cols,
+            pixel_width: 0,
+            pixel_height: 0,
+        };
+        crate::pty::spawn(
+            &app,
+            &window,
+            id.clone(),
+            &cwd,
+            size,
+            Launch::Shell(suspended.sandbox),
+        )?;
+        crate::db::with(&app, |conn| {
+            conn.execute("DELETE FROM suspended_terminals WHERE id = ?1", params![id])
+        })?;
+        tracing::info!("resumed terminal {id}");
+        Ok(())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Forget a suspended terminal without resuming it.
+#[tauri::command]
+pub async fn pty_suspended_delete(
+    window: WebviewWindow,
+    app: AppHandle,
+    id: String,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        crate::db::with(&app, |conn| {
+            conn.execute("DELETE FROM suspended_terminals WHERE id = ?1", params![id])
+        })
+        .map(|_| ())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,266 @@
+use portable_pty::{CommandBuilder, PtySize};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+use crate::exec::{ExecEvent, ExecOptions, ExecResult};
+use crate::pty::Launch;
+use crate::sandbox::Sandbox;
+
+const MAKEFILES: &[&str] = &["GNUmakefile", "makefile", "Makefile"];
+const JUSTFILES: &[&str] = &["justfile", "Justfile", ".justfile"];
+
+/// Files larger than this aren't parsed for tasks.
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TaskSource {
+    PackageJson,
+    Make,
+    Just,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Task {
+    pub name: String,
+    pub source: TaskSource,
+    /// What running the task executes, e.g. `pnpm run build`.
+    pub argv: Vec<String>,
+    /// The script itself, or the doc comment of a target or recipe.
+    pub detail: Option<String>,
+}
+
+fn read_small(path: &Path) -> Option<String> {
+    let meta = std::fs::metadata(path).ok()?;
+    if !meta.is_file() || meta.len() > MAX_FILE_SIZE {
+        return None;
+    }
+    std::fs::read_to_string(path).ok()
+}
+
+/// The package manager the lockfile points at, npm by default.
+fn package_manager(root: &Path) -> &'static str {
+    if root.join("bun.lockb").exists() || root.join("bun.lock").exists() {
+        "bun"
+    } else if root.join("pnpm-lock.yaml").exists() {
+        "pnpm"
+    } else if root.join("yarn.lock").exists() {
+        "yarn"
+    } else {
+        "npm"
+    }
+}
+
+fn package_scripts(root: &Path) -> Vec<Task> {
+    #[derive(Deserialize)]
+    struct PackageJson {
+        #[serde(default)]
+        scripts: BTreeMap<String, String>,
+    }
+    let package = match read_small(&root.join("package.json"))
+        .and_then(|content| serde_json::from_str::<PackageJson>(&content).ok())
+    {
+        Some(package) => package,
+        None => return Vec::new(),
+    };
+    let manager = package_manager(root);
+    package
+        .scripts
+        .into_iter()
+        .map(|(name, script)| Task {
+            argv: vec![manager.to_string(), "run".to_string(), name.clone()],
+            name,
+            source: TaskSource::PackageJson,
+            detail: Some(script),
+        })
+        .collect()
+}
+
+/// Explicit targets of the first Makefile found; special (`.PHONY`) and
+/// pattern (`%.o`) targets are left out. A trailing `## text` on the target
+/// line is its description.
+fn make_targets(root: &Path) -> Vec<Task> {
+    static TARGET: OnceLock<Regex> = OnceLock::new();
+    let target = TARGET.get_or_init(|| {
+        Regex::new(r"^([A-Za-z0-9_][A-Za-z0-9_./ -]*?)\s*::?([^=:].*)?$").expect("valid pattern")
+    });
+    let content = match MAKEFILES
+        .iter()
+        .find_map(|name| read_small(&root.join(name)))
+    {
+        Some(content) => content,
+        None => return Vec::new(),
+    };
+    let mut tasks: Vec<Task> = Vec::new();
+    for line in content.lines() {
+        let captures = match target.captures(line) {
+            Some(captures) => captures,
+            None => continue,
+        };
+        let detail = captures
+            .get(2)
+            .and_then(|rest| rest.as_str().split_once("##"))
+            .map(|(_, doc)| doc.trim().to_string())
+            .filter(|doc| !doc.is_empty());
+        for name in captures[1].split_whitespace() {
+            if name.contains('%') || tasks.iter().any(|t| t.name == name) {
+                continue;
+            }
+            tasks.push(Task {
+                name: name.to_string(),
+                source: TaskSource::Make,
+                argv: vec!["make".to_string(), name.to_string()],
+                detail: detail.clone(),
+            });
+        }
+    }
+    tasks
+}
+
+/// Public recipes of the justfile. A `# comment` right above a recipe is its
+/// description; recipes starting with `_` or marked `[private]` are hidden.
+fn just_recipes(root: &Path) -> Vec<Task> {
+    static RECIPE: OnceLock<Regex> = OnceLock::new();
+    let recipe = RECIPE.get_or_init(|| {
+        Regex::new(r"^@?([A-Za-z_][A-Za-z0-9_-]*)(?:\s+[^:]*)?:(?:[^=]|$)").expect("valid pattern")
+    });
+    let content = match JUSTFILES
+        .iter()
+        .find_map(|name| read_small(&root.join(name)))
+    {
+        Some(content) => content,
+        None => return Vec::new(),
+    };
+    let mut tasks = Vec::new();
+    let mut comment: Option<String> = None;
+    let mut private = false;
+    for line in content.lines() {
+        if let Some(text) = line.strip_prefix('#') {
+            comment = Some(text.trim().to_string());
+            continue;
+        }
+        if line.trim() == "[private]" {
+            private = true;
+            continue;
+        }
+        if line.starts_with('[') {
+            continue;
+        }
+        let name = recipe.captures(line).map(|c| c[1].to_string());
+        match name {
+            Some(name) if !private && !name.starts_with('_') && !is_just_keyword(&name) => tasks
+                .push(Task {
+                    argv: vec!["just".to_string(), name.clone()],
+                    name,
+                    source: TaskSource::Just,
+                    detail: comment.take(),
+                }),
+            _ => {}
+        }
+        comment = None;
+        private = false;
+    }
+    tasks
+}
+
+/// Settings lines that look like recipes to the pattern above.
+fn is_just_keyword(name: &str) -> bool {
+    matches!(name, "alias" | "export" | "import" | "mod" | "set")
+}
+
+fn discover(root: &Path) -> Vec<Task> {
+    let mut tasks = package_scripts(root);
+    tasks.extend(make_targets(root));
+    tasks.extend(just_recipes(root));
+    tasks
+}
+
+/// A task to run: `name` from `project`. Without a `source`, the first task
+/// with that name in `tasks_list` order.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskRef {
+    pub project: String,
+    pub name: String,
+    pub source: Option<TaskSource>,
+}
+
+/// The project root, checked against the sandbox, and the task `task` names.
+fn find(app: &AppHandle, task: &TaskRef) -> Result<(PathBuf, Task), String> {
+    let root = app.state::<Sandbox>().check_dir(&task.project)?;
+    let found = discover(&root)
+        .into_iter()
+        .find(|t| t.name == task.name && task.source.is_none_or(|s| s == t.source))
+        .ok_or_else(|| format!("no task {} in {}", task.name, root.display()))?;
+    Ok((root, found))
+}
+
+/// The project's package.json scripts, Makefile targets and justfile
+/// recipes, in that order.
+#[tauri::command]
+pub async fn tasks_list(
+    window: WebviewWindow,
+    app: AppHandle,
+    project: String,
+) -> Result<Vec<Task>, String> {
+    crate::require_app_window(&window)?;
+    let root = app.state::<Sandbox>().check_dir(&project)?;
+    tauri::async_runtime::spawn_blocking(move || discover(&root))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Run `task` in the background like `exec_command`.
+#[tauri::command]
+pub async fn task_run(
+    window: WebviewWindow,
+    app: AppHandle,
+    task: TaskRef,
+    options: Option<ExecOptions>,
+    request_id: Option<String>,
+    on_event: Channel<ExecEvent>,
+) -> Result<ExecResult, String> {
+    crate::require_app_window(&window)?;
+    let (root, task) = find(&app, &task)?;
+    crate::exec::execute(
+        &app,
+        root,
+        task.argv,
+        options.unwrap_or_default(),
+        request_id,
+        move |event| {
+            let _ = on_event.send(event);
+        },
+    )
+    .await
+}
+
+/// Run `task` in a new terminal `id`, which exits with the task.
+#[tauri::command]
+pub fn task_run_terminal(
+    window: WebviewWindow,
+    app: AppHandle,
+    task: TaskRef,
+    id: String,
+    rows: u16,
+    cols: u16,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    let (root, task) = find(&app, &task)?;
+    let (program, args) = task.argv.split_first().ok_or("task has no command")?;
+    let mut command = CommandBuilder::new(crate::exec::resolve_program(&root, program)?);
+    command.args(args);
+    let size = PtySize {
+        rows,
+        cols,
+        pixel_width: 0,
+        pixel_height: 0,
+    };
+    crate::pty::spawn(&app, &window, id, &root, size, Launch::Command(command))
+}
@@ -0,0 +1,501 @@
+use regex::Regex;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+use crate::forge::run_capture;
+use crate::jobs::JobInfo;
+use crate::layouts::{LayoutTerminal, OpenedTerminal};
+use crate::sandbox::{Sandbox, WORKTREE_DIR_NAME};
+use crate::settings::AgentDefaults;
+
+/// Filled in by `task_create_from_template` and not declared in templates.
+const BUILTIN_VARIABLES: &[&str] = &["branch", "project", "date"];
+/// Rendered prompts longer than this are refused.
+const MAX_PROMPT_LEN: usize = 64 * 1024;
+const MAX_TERMINALS: usize = 32;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum VariableKind {
+    #[default]
+    Text,
+    /// An issue key like `ENG-123` or `#42`.
+    Ticket,
+    /// Project-relative paths, rendered one `- path` per line.
+    Files,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateVariable {
+    pub name: String,
+    #[serde(default)]
+    pub kind: VariableKind,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+    pub default: Option<String>,
+}
+
+/// A prompt for a recurring task. `prompt` and `branch` refer to variables
+/// as `{{name}}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Template {
+    /// Lowercase letters, digits and `-`.
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub prompt: String,
+    /// The branch to create, e.g. `fix/{{ticket}}`; the project's branch
+    /// prefix is added in front. The template id and a timestamp by
+    /// default.
+    pub branch: Option<String>,
+    #[serde(default)]
+    pub variables: Vec<TemplateVariable>,
+    /// Opened in the new worktree; `cwd` is relative to it.
+    #[serde(default)]
+    pub terminals: Vec<LayoutTerminal>,
+    /// Overrides the project's agent defaults.
+    #[serde(default)]
+    pub agent: AgentDefaults,
+}
+
+fn placeholder() -> &'static Regex {
+    static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+    PLACEHOLDER.get_or_init(|| Regex::new(r"\{\{\s*([^{}]*?)\s*\}\}").expect("valid pattern"))
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_lowercase() || c == '_')
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// Names `text` refers to. Stray `{{` or `}}` are errors.
+fn referenced(text: &str) -> Result<Vec<String>, String> {
+    let rest = placeholder().replace_all(text, "");
+    if rest.contains("{{") || rest.contains("}}") {
+        return Err("unbalanced {{ }}".to_string());
+    }
+    Ok(placeholder()
+        .captures_iter(text)
+        .map(|c| c[1].to_string())
+        .collect())
+}
+
+impl Template {
+    fn validate(&self) -> Result<(), String> {
+        let id_ok = !self.id.is_empty()
+            && self.id.len() <= 64
+            && self
+                .id
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+        if !id_ok {
+            return Err(format!(
+                "invalid template id {:?}: use lowercase letters, digits and '-'",
+                self.id
+            ));
+        }
+        if self.name.trim().is_empty() || self.prompt.trim().is_empty() {
+            return Err("templates need a name and a prompt".to_string());
+        }
+        if self.terminals.len() > MAX_TERMINALS {
+            return Err(format!("templates open at most {MAX_TERMINALS} terminals"));
+        }
+        let mut declared = HashSet::new();
+        for variable in &self.variables {
+            if !is_identifier(&variable.name) || BUILTIN_VARIABLES.contains(&variable.name.as_str())
+            {
+                return Err(format!("invalid variable name {:?}", variable.name));
+            }
+            if !declared.insert(variable.name.as_str()) {
+                return Err(format!("variable {} is declared twice", variable.name));
+            }
+        }
+        for (field, text) in [
+            ("prompt", Some(&self.prompt)),
+            ("branch", self.branch.as_ref()),
+        ] {
+            let text = match text {
+                Some(text) => text,
+                None => continue,
+            };
+            for name in referenced(text).map_err(|e| format!("{field}: {e}"))? {
+                // The branch can't be named after itself.
+                let builtin = BUILTIN_VARIABLES.contains(&name.as_str())
+                    && !(field == "branch" && name == "branch");
+                if !builtin && !declared.contains(name.as_str()) {
+                    return Err(format!("{field}: unknown variable {{{{{name}}}}}"));
+                }
+            }
+        }
+        if let Some(verify) = &self.agent.verify {
+            crate::verify::validate_config(verify)?;
+        }
+        Ok(())
+    }
+
+    /// Check `vars` against the declared variables and turn them into the
+    /// text they render as. Files must lie inside `root`.
+    fn values(
+        &self,
+        sandbox: &Sandbox,
+        root: &str,
+        vars: &BTreeMap<String, Value>,
+    ) -> Result<BTreeMap<String, String>, String> {
+        static TICKET: OnceLock<Regex> = OnceLock::new();
+        let ticket = TICKET
+            .get_or_init(|| Regex::new(r"^#?[A-Za-z0-9][A-Za-z0-9_.-]*$").expect("valid pattern"));
+        if let Some(name) = vars
+            .keys()
+            .find(|k| !self.variables.iter().any(|v| &v.name == *k))
+        {
+            return Err(format!("{} has no variable {name}", self.id));
+        }
+        let mut values = BTreeMap::new();
+        for variable in &self.variables {
+            let value = match vars.get(&variable.name) {
+                Some(Value::Null) | None => None,
+                Some(value) => Some(value),
+            };
+            let text = match (variable.kind, value) {
+                (_, None) => match (&variable.default, variable.required) {
+                    (Some(default), _) => default.clone(),
+                    (None, true) => return Err(format!("{} is required", variable.name)),
+                    (None, false) => String::new(),
+                },
+                (VariableKind::Files, Some(value)) => {
+                    let files: Vec<String> = match value {
+                        Value::String(file) => vec![file.clone()],
+                        value => serde_json::from_value(value.clone())
+                            .map_err(|_| format!("{} must be a list of paths", variable.name))?,
+                    };
+                    let mut lines = Vec::new();
+                    for file in &files {
+                        let path = sandbox.resolve(root, file)?;
+                        let relative = path.strip_prefix(root).unwrap_or(&path);
+                        lines.push(format!("- {}", relative.display()));
+                    }
+                    lines.join("\n")
+                }
+                (kind, Some(Value::String(text))) => {
+                    if kind == VariableKind::Ticket && !ticket.is_match(text) {
+                        return Err(format!("{} is not a ticket id: {text:?}", variable.name));
+                    }
+                    text.clone()
+                }
+                (_, Some(Value::Number(n))) => n.to_string(),
+                (_, Some(_)) => return Err(format!("{} must be text", variable.name)),
+            };
+            values.insert(variable.name.clone(), text);
+        }
+        Ok(values)
+    }
+}
+
+/// Replace every `{{name}}` in `text` with its value.
+fn render(text: &str, values: &BTreeMap<String, String>) -> String {
+    placeholder()
+        .replace_all(text, |c: &regex::Captures| {
+            values.get(&c[1]).cloned().unwrap_or_default()
+        })
+        .into_owned()
+}
+
+fn load(app: &AppHandle, id: &str) -> Result<Template, String> {
+    let json: String = crate::db::with(app, |conn| {
+        conn.query_row(
+            "SELECT template FROM templates WHERE id = ?1",
+            params![id],
+            |row| row.get(0),
+        )
+    })
+    .map_err(|_| format!("no template {id}"))?;
+    serde_json::from_str(&json).map_err(|e| format!("template {id} is corrupt: {e}"))
+}
+
+/// `<parent>/.funny-worktrees/<project name>/<directory>`, named after the
+/// branch like the server names its worktrees.
+fn worktree_dir(root: &Path, branch: &str, directory: Option<&str>) -> Result<PathBuf, String> {
+    let flat = branch.replace('/', "-");
+    let name = match directory {
+        Some(pattern) => pattern.replace("{branch}", &flat),
+        None => flat,
+    };
+    match (root.parent(), root.file_name()) {
+        (Some(parent), Some(project)) => {
+            Ok(parent.join(WORKTREE_DIR_NAME).join(project).join(name))
+        }
+        _ => Err(format!("cannot make a worktree for {}", root.display())),
+    }
+}
+
+/// Create `branch` off HEAD of `root` in a new worktree, placed by
+/// `worktree_dir`: taken from the project's worktree pool if it has one
+/// ready, else checked out if the disk has room for it.
+pub(crate) fn add_worktree(
+    app: &AppHandle,
+    root: &Path,
+    branch: &str,
+    directory: Option<&str>,
+) -> Result<PathBuf, String> {
+    let worktree = worktree_dir(root, branch, directory)?;
+    if worktree.exists() {
+        return Err(format!("{} already exists", worktree.display()));
+    }
+    if let Some(worktree) = crate::worktree_pool::take(app, root, branch, &worktree) {
+        return Ok(worktree);
+    }
+    crate::disk::ensure_space(app, &worktree, crate::disk::checkout_bytes(root))?;
+    run_capture(
+        Command::new("git")
+            .arg("worktree")
+            .arg("add")
+            .arg("-b")
+            .arg(branch)
+            .arg(&worktree)
+            .current_dir(root),
+        None,
+    )?;
+    if crate::settings::current(app)
+        .worktree_pool
+        .link_dependencies
+    {
+        let linked = crate::worktree_pool::link_dependencies(root, &worktree);
+        tracing::debug!("linked {linked:?} into {}", worktree.display());
+    }
+    Ok(worktree)
+}
+
+/// Whether `branch` is a name git accepts for a new branch.
+pub(crate) fn check_branch(branch: &str) -> Result<(), String> {
+    run_capture(
+        Command::new("git")
+            .args(["check-ref-format", "--branch"])
+            .arg(branch),
+        None,
+    )
+    .map(|_| ())
+    .map_err(|_| format!("{branch:?} is not a valid branch name"))
+}
+
+/// The project's branch prefix and worktree directory pattern, and the agent
+/// defaults for it.
+pub(crate) fn task_config(app: &AppHandle, root: &Path) -> (String, Option<String>, AgentDefaults) {
+    match crate::project_config::get(app, &root.to_string_lossy()) {
+        Some(config) => (
+            config.worktree.branch_prefix.unwrap_or_default(),
+            config.worktree.directory,
+            config.agent,
+        ),
+        None => (String::new(), None, crate::settings::current(app).agents),
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenderedTemplate {
+    pub prompt: String,
+    /// Without the project's branch prefix.
+    pub branch: String,
+}
+
+/// The created task: the agent is for the frontend to start in `worktree`
+/// through the server.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedTask {
+    pub template: String,
+    pub title: String,
+    pub branch: String,
+    pub worktree: String,
+    pub prompt: String,
+    /// The template's agent settings over the project's.
+    pub agent: AgentDefaults,
+    pub terminals: Vec<OpenedTerminal>,
+    /// The project's bootstrap of the new worktree, if it has one: start
+    /// the agent once this job finishes.
+    pub bootstrap: Option<JobInfo>,
+    /// Terminals that could not be opened, with the reason.
+    pub errors: Vec<String>,
+}
+
+fn render_all(
+    app: &AppHandle,
+    template: &Template,
+    root: &Path,
+    vars: &BTreeMap<String, Value>,
+    prefix: &str,
+) -> Result<RenderedTemplate, String> {
+    let root_str = root.to_string_lossy();
+    let mut values = template.values(&app.state::<Sandbox>(), &root_str, vars)?;
+    values.insert(
+        "project".to_string(),
+        root.file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default(),
+    );
+    values.insert(
+        "date".to_string(),
+        chrono::Local::now().format("%Y-%m-%d").to_string(),
+    );
+    let branch = match &template.branch {
+        Some(pattern) => render(pattern, &values).trim().to_string(),
+        None => format!(
+            "{}-{}",
+            template.id,
+            chrono::Local::now().format("%Y%m%d-%H%M%S")
+        ),
+    };
+    check_branch(&format!("{prefix}{branch}"))?;
+    values.insert("branch".to_string(), format!("{prefix}{branch}"));
+    let prompt = render(&template.prompt, &values);
+    if prompt.len() > MAX_PROMPT_LEN {
+        return Err("the rendered prompt is too long".to_string());
+    }
+    Ok(RenderedTemplate { prompt, branch })
+}
+
+#[tauri::command]
+pub async fn template_list(window: WebviewWindow, app: AppHandle) -> Result<Vec<Template>, String> {
+    crate::require_app_window(&window)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let rows: Vec<String> = crate::db::with(&app, |conn| {
+            let mut statement = conn.prepare("SELECT template FROM templates ORDER BY id")?;
+            let rows = statement.query_map([], |row| row.get(0))?;
+            rows.collect()
+        })?;
+        Ok(rows
+            .iter()
+            .filter_map(|json| serde_json::from_str(json).ok())
+            .collect())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Validate and store `template`, replacing one with the same id.
+#[tauri::command]
+pub async fn template_save(
+    window: WebviewWindow,
+    app: AppHandle,
+    template: Template,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    template.validate()?;
+    let json = serde_json::to_string(&template).map_err(|e| e.to_string())?;
+    tauri::async_runtime::spawn_blocking(move || {
+        crate::db::with(&app, |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO templates (id, template, updated_at) VALUES (?1, ?2, ?3)",
+                params![template.id, json, chrono::Utc::now().to_rfc3339()],
+            )
+        })
+        .map(|_| ())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn template_delete(
+    window: WebviewWindow,
+    app: AppHandle,
+    id: String,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        crate::db::with(&app, |conn| {
+            conn.execute("DELETE FROM templates WHERE id = ?1", params![id])
+        })
+        .map(|_| ())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Preview what `task_create_from_template` would use for `vars`, without
+/// creating anything.
+#[tauri::command]
+pub async fn template_render(
+    window: WebviewWindow,
+    app: AppHandle,
+    id: String,
+    project: String,
+    vars: BTreeMap<String, Value>,
+) -> Result<RenderedTemplate, String> {
+    crate::require_app_window(&window)?;
+    let root = app.state::<Sandbox>().check_dir(&project)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let template = load(&app, &id)?;
+        render_all(&app, &template, &root, &vars, "")
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Start a task from template `id` in `project`: render it with `vars`,
+/// create its branch and worktree off HEAD, and open its terminals there in
+/// this window. The rendered prompt and agent settings come back for the
+/// frontend to start the agent with.
+#[tauri::command]
+pub async fn task_create_from_template(
+    window: WebviewWindow,
+    app: AppHandle,
+    id: String,
+    project: String,
+    vars: BTreeMap<String, Value>,
+) -> Result<CreatedTask, String> {
+    crate::require_app_window(&window)?;
+    let root = app.state::<Sandbox>().check_dir(&project)?;
+    let (prefix, directory, defaults) = task_config(&app, &root);
+
+    let worker = app.clone();
+    let git_root = root.clone();
+    let branch_prefix = prefix.clone();
+    let (template, rendered, worktree) = tauri::async_runtime::spawn_blocking(move || {
+        let template = load(&worker, &id)?;
+        let rendered = render_all(&worker, &template, &git_root, &vars, &branch_prefix)?;
+        let branch = format!("{branch_prefix}{}", rendered.branch);
+        let worktree = add_worktree(&worker, &git_root, &branch, directory.as_deref())?;
+        tracing::info!(
+            "created worktree {} for template {}",
+            worktree.display(),
+            template.id
+        );
+        Ok::<_, String>((template, rendered, worktree))
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    let bootstrap = crate::bootstrap::start(&app, &window, &root, &worktree);
+    let worktree = worktree.to_string_lossy().to_string();
+    let (terminals, mut errors) =
+        crate::layouts::open_terminals(&app, &window, &worktree, &template.terminals, "task");
+    let bootstrap = bootstrap.unwrap_or_else(|e| {
+        errors.push(format!("bootstrap: {e}"));
+        None
+    });
+    let agent = template.agent.or(defaults);
+    Ok(CreatedTask {
+        title: format!("{}: {}", template.name, rendered.branch),
+        template: template.id,
+        branch: format!("{prefix}{}", rendered.branch),
+        worktree,
+        prompt: rendered.prompt,
+        agent,
+        terminals,
+        bootstrap,
+        errors,
+    })
+}
@@ -0,0 +1,221 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// Names of failed tests kept in a summary; the counts stay exact.
+const MAX_FAILURES: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TestFramework {
+    Cargo,
+    Pytest,
+    Jest,
+    Go,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TestStatus {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestCase {
+    pub framework: TestFramework,
+    pub name: String,
+    pub status: TestStatus,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestSummary {
+    /// The framework of the first result seen.
+    pub framework: TestFramework,
+    pub passed: u64,
+    pub failed: u64,
+    pub skipped: u64,
+    /// Failed test names, at most `MAX_FAILURES`.
+    pub failures: Vec<String>,
+}
+
+#[derive(Default, Clone, Copy)]
+struct Counts {
+    passed: u64,
+    failed: u64,
+    skipped: u64,
+}
+
+struct Patterns {
+    cargo_case: Regex,
+    cargo_result: Regex,
+    pytest_case: Regex,
+    pytest_result: Regex,
+    jest_case: Regex,
+    jest_result: Regex,
+    go_case: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        let pattern = |p: &str| Regex::new(p).expect("valid pattern");
+        Patterns {
+            cargo_case: pattern(r"^test (\S+) \.\.\. (ok|FAILED|ignored)"),
+            cargo_result: pattern(r"^test result: \w+\. (\d+) passed; (\d+) failed; (\d+) ignored"),
+            pytest_case: pattern(r"^(\S+::\S+) (PASSED|FAILED|ERROR|SKIPPED|XFAIL|XPASS)\b"),
+            pytest_result: pattern(r"^=+ (.*\d+ (?:passed|failed|skipped|error).*) in [\d.]+s"),
+            jest_case: pattern(r"^\s+(✓|√|✕|×|○) (.+?)(?: \(\d+(?:\.\d+)? ?m?s\))?$"),
+            jest_result: pattern(r"^Tests:\s+(.*\d+ total)"),
+            go_case: pattern(r"^\s*--- (PASS|FAIL|SKIP): (\S+)"),
+        }
+    })
+}
+
+/// Count `N word` pairs like `3 passed, 1 failed` in a summary line.
+fn summary_counts(text: &str) -> Counts {
+    let mut counts = Counts::default();
+    let words: Vec<&str> = text
+        .split(|c: char| c == ',' || c == '|' || c.is_whitespace())
+        .filter(|w| !w.is_empty())
+        .collect();
+    for pair in words.windows(2) {
+        let n = match pair[0].parse::<u64>() {
+            Ok(n) => n,
+            Err(_) => continue,
+        };
+        match pair[1] {
+            "passed" | "xpassed" => counts.passed += n,
+            "failed" | "error" | "errors" => counts.failed += n,
+            "skipped" | "xfailed" | "todo" | "deselected" => counts.skipped += n,
+            _ => {}
+        }
+    }
+    counts
+}
+
+/// Picks test results out of command output, line by line. Understands
+/// `cargo test`, pytest (`-v` for individual cases), Jest and `go test -v`;
+/// anything else passes through unnoticed.
+#[derive(Default)]
+pub(crate) struct TestParser {
+    partial: [String; 2],
+    counted: Counts,
+    /// Totals from summary lines, which also cover tests the output didn't
+    /// list one by one.
+    reported: Option<Counts>,
+    framework: Option<TestFramework>,
+    failures: Vec<String>,
+}
+
+impl TestParser {
+    /// Feed a chunk of the output stream `stream` (0 for stdout, 1 for
+    /// stderr) and get the test cases it completed.
+    pub(crate) fn feed(&mut self, stream: usize, chunk: &str) -> Vec<TestCase> {
+        let buffer = &mut self.partial[stream];
+        buffer.push_str(chunk);
+        let complete = match buffer.rfind('\n') {
+            Some(i) => buffer.drain(..=i).collect::<String>(),
+            None => return Vec::new(),
+        };
+        complete
+            .lines()
+            .filter_map(|line| self.line(line))
+            .collect()
+    }
+
+    fn line(&mut self, raw: &str) -> Option<TestCase> {
+        let patterns = patterns();
+        let line = crate::problems::strip_ansi(raw.trim_end_matches('\r'));
+
+        if let Some(c) = patterns.cargo_result.captures(&line) {
+            let reported = self.reported.get_or_insert_with(Counts::default);
+            reported.passed += c[1].parse::<u64>().unwrap_or(0);
+            reported.failed += c[2].parse::<u64>().unwrap_or(0);
+            reported.skipped += c[3].parse::<u64>().unwrap_or(0);
+            self.framework.get_or_insert(TestFramework::Cargo);
+            return None;
+        }
+        for (pattern, framework) in [
+            (&patterns.pytest_result, TestFramework::Pytest),
+            (&patterns.jest_result, TestFramework::Jest),
+        ] {
+            if let Some(c) = pattern.captures(&line) {
+                self.reported = Some(summary_counts(&c[1]));
+                self.framework.get_or_insert(framework);
+                return None;
+            }
+        }
+
+        let (framework, name, status) = if let Some(c) = patterns.cargo_case.captures(&line) {
+            let status = match &c[2] {
+                "ok" => TestStatus::Passed,
+                "FAILED" => TestStatus::Failed,
+                _ => TestStatus::Skipped,
+            };
+            (TestFramework::Cargo, c[1].to_string(), status)
+        } else if let Some(c) = patterns.pytest_case.captures(&line) {
+            let status = match &c[2] {
+                "PASSED" | "XPASS" => TestStatus::Passed,
+                "FAILED" | "ERROR" => TestStatus::Failed,
+                _ => TestStatus::Skipped,
+            };
+            (TestFramework::Pytest, c[1].to_string(), status)
+        } else if let Some(c) = patterns.go_case.captures(&line) {
+            let status = match &c[1] {
+                "PASS" => TestStatus::Passed,
+                "FAIL" => TestStatus::Failed,
+                _ => TestStatus::Skipped,
+            };
+            (TestFramework::Go, c[2].to_string(), status)
+        } else if let Some(c) = patterns.jest_case.captures(&line) {
+            let status = match &c[1] {
+                "✓" | "√" => TestStatus::Passed,
+                "✕" | "×" => TestStatus::Failed,
+                _ => TestStatus::Skipped,
+            };
+            (TestFramework::Jest, c[2].trim().to_string(), status)
+        } else {
+            return None;
+        };
+
+        match status {
+            TestStatus::Passed => self.counted.passed += 1,
+            TestStatus::Failed => {
+                self.counted.failed += 1;
+                if self.failures.len() < MAX_FAILURES {
+                    self.failures.push(name.clone());
+                }
+            }
+            TestStatus::Skipped => self.counted.skipped += 1,
+        }
+        self.framework.get_or_insert(framework);
+        Some(TestCase {
+            framework,
+            name,
+            status,
+        })
+    }
+
+    /// Cases from output that ended without a newline.
+    pub(crate) fn finish(&mut self) -> Vec<TestCase> {
+        let rest: Vec<String> = self.partial.iter_mut().map(std::mem::take).collect();
+        rest.iter().filter_map(|line| self.line(line)).collect()
+    }
+
+    /// The totals, if the output had any test results at all.
+    pub(crate) fn summary(&self) -> Option<TestSummary> {
+        let framework = self.framework?;
+        let counts = self.reported.unwrap_or(self.counted);
+        Some(TestSummary {
+            framework,
+            passed: counts.passed,
+            failed: counts.failed,
+            skipped: counts.skipped,
+            failures: self.failures.clone(),
+        })
+    }
+}
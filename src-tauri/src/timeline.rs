@@ -0,0 +1,234 @@
+use rusqlite::{params, params_from_iter, ToSql};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State, WebviewWindow};
+
+use crate::audit::{name_of, parse};
+use crate::sandbox::Sandbox;
+
+/// Default and maximum number of entries returned by `timeline_query`.
+const DEFAULT_LIMIT: usize = 500;
+const MAX_LIMIT: usize = 5000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TimelineKind {
+    TerminalSpawned,
+    AgentStarted,
+    AgentFinished,
+    /// Reported by the frontend: commits are made by the server and agents.
+    CommitCreated,
+    PrOpened,
+    /// A flagged command the user let run.
+    ApprovalGranted,
+}
+
+/// One thing that happened, for the "what happened today" view. Unlike the
+/// audit log this is about milestones, not every operation.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimelineEntry {
+    pub id: i64,
+    pub timestamp: String,
+    pub kind: TimelineKind,
+    /// The project root it happened in.
+    pub project: Option<String>,
+    /// What it is about: a terminal or run id, a commit sha, a PR URL, a
+    /// command.
+    pub subject: String,
+    pub title: String,
+    pub detail: Option<String>,
+}
+
+/// What callers hand to `record`; id and timestamp are added there.
+pub(crate) struct TimelineEvent<'a> {
+    pub kind: TimelineKind,
+    pub project: Option<&'a str>,
+    pub subject: &'a str,
+    pub title: &'a str,
+    pub detail: Option<String>,
+}
+
+/// Add `event` to the timeline and send it to the main window as
+/// `timeline:event`. Failures are logged, never returned.
+pub(crate) fn record(app: &AppHandle, event: TimelineEvent<'_>) {
+    let timestamp = chrono::Utc::now().to_rfc3339();
+    let result = crate::db::with(app, |conn| {
+        conn.execute(
+            "INSERT INTO timeline_events (timestamp, kind, project, subject, title, detail)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                timestamp,
+                name_of(event.kind),
+                event.project,
+                event.subject,
+                event.title,
+                event.detail,
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    });
+    let id = match result {
+        Ok(id) => id,
+        Err(e) => {
+            tracing::debug!("timeline event for {} not recorded: {e}", event.subject);
+            return;
+        }
+    };
+    let entry = TimelineEntry {
+        id,
+        timestamp,
+        kind: event.kind,
+        project: event.project.map(str::to_string),
+        subject: event.subject.to_string(),
+        title: event.title.to_string(),
+        detail: event.detail,
+    };
+    let _ = app.emit_to("main", "timeline:event", entry);
+}
+
+/// The registered project `path` lies in, for events that only know a
+/// directory.
+pub(crate) fn project_of(app: &AppHandle, path: &str) -> Option<String> {
+    let canonical = std::fs::canonicalize(path).ok()?;
+    app.state::<Sandbox>()
+        .root_of(&canonical)
+        .map(|root| root.to_string_lossy().to_string())
+}
+
+/// RFC 3339 bounds, inclusive.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeRange {
+    pub since: Option<String>,
+    pub until: Option<String>,
+}
+
+/// Timeline entries of `project` (all projects when `None`) within
+/// `range`, oldest first; the newest `limit` when given.
+pub(crate) fn query(
+    app: &AppHandle,
+    project: Option<String>,
+    range: TimeRange,
+    kinds: Option<Vec<TimelineKind>>,
+    limit: Option<usize>,
+) -> Result<Vec<TimelineEntry>, String> {
+    let mut clauses: Vec<String> = Vec::new();
+    let mut values: Vec<Box<dyn ToSql>> = Vec::new();
+    let mut bind = |clause: &str, value: Box<dyn ToSql>| {
+        values.push(value);
+        clauses.push(clause.replace('?', &format!("?{}", values.len())));
+    };
+    if let Some(since) = range.since {
+        bind("timestamp >= ?", Box::new(since));
+    }
+    if let Some(until) = range.until {
+        bind("timestamp <= ?", Box::new(until));
+    }
+    if let Some(project) = project {
+        bind("project = ?", Box::new(project));
+    }
+    if let Some(kinds) = kinds {
+        let names: Vec<String> = kinds.into_iter().map(name_of).collect();
+        bind(
+            "kind IN (SELECT value FROM json_each(?))",
+            Box::new(serde_json::to_string(&names).unwrap_or_default()),
+        );
+    }
+    // The newest `limit` (-1: all), put back in order.
+    let sql = format!(
+        "SELECT * FROM (
+             SELECT id, timestamp, kind, project, subject, title, detail
+             FROM timeline_events {} ORDER BY timestamp DESC, id DESC LIMIT {}
+         ) ORDER BY timestamp, id",
+        if clauses.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", clauses.join(" AND "))
+        },
+        limit.map_or(-1, |l| l as i64),
+    );
+    crate::db::with(app, |conn| {
+        let mut statement = conn.prepare(&sql)?;
+        let rows = statement.query_map(params_from_iter(values.iter()), |row| {
+            Ok(TimelineEntry {
+                id: row.get(0)?,
+                timestamp: row.get(1)?,
+                kind: parse(row.get(2)?)?,
+                project: row.get(3)?,
+                subject: row.get(4)?,
+                title: row.get(5)?,
+                detail: row.get(6)?,
+            })
+        })?;
+        rows.collect()
+    })
+}
+
+/// Timeline entries of `project` (all projects when unset) within `range`,
+/// oldest first.
+#[tauri::command]
+pub async fn timeline_query(
+    window: WebviewWindow,
+    app: AppHandle,
+    project: Option<String>,
+    range: Option<TimeRange>,
+    kinds: Option<Vec<TimelineKind>>,
+    limit: Option<u32>,
+) -> Result<Vec<TimelineEntry>, String> {
+    crate::require_app_window(&window)?;
+    let limit = limit
+        .map(|l| l as usize)
+        .unwrap_or(DEFAULT_LIMIT)
+        .min(MAX_LIMIT);
+    tauri::async_runtime::spawn_blocking(move || {
+        query(&app, project, range.unwrap_or_default(), kinds, Some(limit))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportedEvent {
+    pub kind: TimelineKind,
+    /// A directory inside the project it happened in.
+    pub cwd: Option<String>,
+    pub subject: String,
+    pub title: String,
+    pub detail: Option<String>,
+}
+
+/// Add an event the backend doesn't see itself, like a commit an agent made
+/// through the server.
+#[tauri::command]
+pub async fn timeline_record(
+    window: WebviewWindow,
+    app: AppHandle,
+    sandbox: State<'_, Sandbox>,
+    event: ReportedEvent,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    let project = match &event.cwd {
+        Some(cwd) => {
+            let dir = sandbox.check_dir(cwd)?;
+            sandbox
+                .root_of(&dir)
+                .map(|root| root.to_string_lossy().to_string())
+        }
+        None => None,
+    };
+    tauri::async_runtime::spawn_blocking(move || {
+        record(
+            &app,
+            TimelineEvent {
+                kind: event.kind,
+                project: project.as_deref(),
+                subject: &event.subject,
+                title: &event.title,
+                detail: event.detail,
+            },
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
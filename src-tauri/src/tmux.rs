@@ -0,0 +1,688 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Manager, State, WebviewWindow};
+
+use crate::redact::StreamRedactor;
+
+/// How long a command sent to tmux may take to answer.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Input bytes sent per `send-keys`, so a large paste doesn't make one huge
+/// command line.
+const SEND_CHUNK: usize = 256;
+
+/// Separates the fields of `list-*` output; names and titles may hold tabs.
+const FIELD_SEP: char = '\x1f';
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TmuxSession {
+    pub name: String,
+    pub windows: u32,
+    pub attached: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TmuxWindow {
+    /// `@3`.
+    pub id: String,
+    pub index: u32,
+    pub name: String,
+    pub active: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TmuxPane {
+    /// `%5`.
+    pub id: String,
+    pub window_id: String,
+    pub index: u32,
+    pub width: u32,
+    pub height: u32,
+    pub active: bool,
+    /// Its process has exited but the pane is kept.
+    pub dead: bool,
+    pub command: String,
+    pub cwd: String,
+    pub title: String,
+}
+
+/// The windows and panes of the attached session.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TmuxState {
+    pub session: String,
+    pub windows: Vec<TmuxWindow>,
+    pub panes: Vec<TmuxPane>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "event",
+    content = "data"
+)]
+pub enum TmuxEvent {
+    /// Output of a pane.
+    Output { pane: String, data: String },
+    /// Windows or panes were added, closed, renamed or resized.
+    State(TmuxState),
+    /// The control client is gone: detached, the session ended, or tmux or
+    /// the connection failed.
+    Exit { reason: Option<String> },
+}
+
+type Reply = Result<Vec<String>, String>;
+
+/// One control-mode client (`tmux -C`), attached to a session for a
+/// window.
+struct Connection {
+    window: String,
+    stdin: ChildStdin,
+    child: Child,
+    /// Waiting for the `%begin`…`%end` block answering each command sent,
+    /// in order.
+    pending: Arc<Mutex<VecDeque<Sender<Reply>>>>,
+}
+
+/// Attached tmux sessions by connection id.
+pub struct TmuxManager {
+    connections: Mutex<HashMap<String, Connection>>,
+}
+
+impl TmuxManager {
+    pub fn new() -> Self {
+        Self {
+            connections: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+/// Host names go to `ssh` as an argument and must not look like options.
+fn check_host(host: &str) -> Result<(), String> {
+    let valid = !host.is_empty()
+        && !host.starts_with('-')
+        && host
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '@' | ':'));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("invalid host {host}"))
+    }
+}
+
+/// Pane and window ids are put in tmux command lines.
+fn check_target(target: &str, sigil: char) -> Result<(), String> {
+    match target.strip_prefix(sigil) {
+        Some(n) if !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()) => Ok(()),
+        _ => Err(format!("invalid tmux target {target}")),
+    }
+}
+
+/// `value` as one word for tmux's parser or a POSIX shell.
+pub(crate) fn quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+/// `tmux` with `args`, locally or on `host` over ssh.
+fn tmux_command(host: Option<&str>, args: &[String]) -> Result<Command, String> {
+    match host {
+        None => {
+            let mut command = Command::new("tmux");
+            command.args(args);
+            Ok(command)
+        }
+        Some(host) => {
+            check_host(host)?;
+            let mut command = Command::new("ssh");
+            // The remote shell parses the command again.
+            let remote: Vec<String> = std::iter::once("tmux".to_string())
+                .chain(args.iter().map(|a| quote(a)))
+                .collect();
+            command.args(["-T", "-o", "BatchMode=yes", host, "--"]);
+            command.arg(remote.join(" "));
+            Ok(command)
+        }
+    }
+}
+
+/// Undo the octal escapes (`\ooo`) of a `%output` line.
+fn unescape(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut i = 0;
+    while i < data.len() {
+        let octal = data
+            .get(i + 1..i + 4)
+            .filter(|d| data[i] == b'\\' && d.iter().all(|b| (b'0'..=b'7').contains(b)));
+        match octal {
+            Some(d) => {
+                out.push(d.iter().fold(0u8, |n, b| (n << 3) | (b - b'0')));
+                i += 4;
+            }
+            None => {
+                out.push(data[i]);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// Text of `carry` plus `bytes`, keeping a UTF-8 sequence cut at the end
+/// for the next chunk.
+fn decode(carry: &mut Vec<u8>, bytes: &[u8]) -> String {
+    carry.extend_from_slice(bytes);
+    let keep = match std::str::from_utf8(carry) {
+        Ok(_) => 0,
+        Err(e) if e.error_len().is_none() => carry.len() - e.valid_up_to(),
+        Err(_) => 0,
+    };
+    let rest = carry.split_off(carry.len() - keep);
+    let text = String::from_utf8_lossy(carry).into_owned();
+    *carry = rest;
+    text
+}
+
+/// Send `command` on connection `id` and wait for its answer.
+fn run(app: &AppHandle, id: &str, command: &str) -> Reply {
+    let (tx, rx) = mpsc::channel();
+    {
+        let state = app.state::<TmuxManager>();
+        let mut connections = state.connections.lock().map_err(|e| e.to_string())?;
+        let connection = connections
+            .get_mut(id)
+            .ok_or_else(|| format!("no tmux connection {id}"))?;
+        // Queued under the same lock as the write, so answers stay in order.
+        let mut pending = connection.pending.lock().map_err(|e| e.to_string())?;
+        writeln!(connection.stdin, "{command}").map_err(|e| e.to_string())?;
+        connection.stdin.flush().map_err(|e| e.to_string())?;
+        pending.push_back(tx);
+    }
+    rx.recv_timeout(REPLY_TIMEOUT)
+        .map_err(|_| format!("tmux did not answer {command}"))?
+}
+
+fn fields(line: &str, n: usize) -> Vec<&str> {
+    line.splitn(n, FIELD_SEP).collect()
+}
+
+fn list(app: &AppHandle, id: &str) -> Result<TmuxState, String> {
+    let sep = FIELD_SEP;
+    let session = run(app, id, "display-message -p '#{session_name}'")?
+        .into_iter()
+        .next()
+        .unwrap_or_default();
+    let windows = run(
+        app,
+        id,
+        &format!("list-windows -F '#{{window_id}}{sep}#{{window_index}}{sep}#{{window_active}}{sep}#{{window_name}}'"),
+    )?
+    .iter()
+    .filter_map(|line| match fields(line, 4)[..] {
+        [id, index, active, name] => Some(TmuxWindow {
+            id: id.to_string(),
+            index: index.parse().ok()?,
+            name: name.to_string(),
+            active: active == "1",
+        }),
+        _ => None,
+    })
+    .collect();
+    let panes = run(
+        app,
+        id,
+        &format!(
+            "list-panes -s -F '#{{pane_id}}{sep}#{{window_id}}{sep}#{{pane_index}}{sep}#{{pane_width}}{sep}#{{pane_height}}{sep}#{{pane_active}}{sep}#{{pane_dead}}{sep}#{{pane_current_command}}{sep}#{{pane_current_path}}{sep}#{{pane_title}}'"
+        ),
+    )?
+    .iter()
+    .filter_map(|line| match fields(line, 10)[..] {
+        [id, window_id, index, width, height, active, dead, command, cwd, title] => {
+            Some(TmuxPane {
+                id: id.to_string(),
+                window_id: window_id.to_string(),
+                index: index.parse().ok()?,
+                width: width.parse().ok()?,
+                height: height.parse().ok()?,
+                active: active == "1",
+                dead: dead == "1",
+                command: command.to_string(),
+                cwd: cwd.to_string(),
+                title: title.to_string(),
+            })
+        }
+        _ => None,
+    })
+    .collect();
+    Ok(TmuxState {
+        session,
+        windows,
+        panes,
+    })
+}
+
+/// Send the session's windows and panes as they are now.
+fn refresh(app: &AppHandle, id: &str, on_event: &Channel<TmuxEvent>) {
+    let app = app.clone();
+    let id = id.to_string();
+    let on_event = on_event.clone();
+    // Off the reader thread, which has to read the answers.
+    std::thread::spawn(move || match list(&app, &id) {
+        Ok(state) => {
+            let _ = on_event.send(TmuxEvent::State(state));
+        }
+        Err(e) => tracing::debug!("tmux {id}: listing failed: {e}"),
+    });
+}
+
+/// Read the control client's output: answers go to the commands waiting
+/// for them, notifications become events.
+fn read(
+    app: AppHandle,
+    id: String,
+    stdout: impl std::io::Read,
+    pending: Arc<Mutex<VecDeque<Sender<Reply>>>>,
+    on_event: Channel<TmuxEvent>,
+) {
+    let mut reader = BufReader::new(stdout);
+    let mut line = Vec::new();
+    let mut block: Option<Vec<String>> = None;
+    let mut reason = None;
+    let mut carries: HashMap<String, Vec<u8>> = HashMap::new();
+    let mut redactors: HashMap<String, StreamRedactor> = HashMap::new();
+    let redact = crate::settings::current(&app).terminal.redact_secrets;
+    loop {
+        line.clear();
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(e) => {
+                reason = Some(e.to_string());
+                break;
+            }
+        }
+        let raw = line.strip_suffix(b"\n").unwrap_or(&line);
+        let raw = raw.strip_suffix(b"\r").unwrap_or(raw);
+        if let Some(lines) = &mut block {
+            let text = String::from_utf8_lossy(raw);
+            let end = text.starts_with("%end ");
+            if end || text.starts_with("%error ") {
+                let lines = std::mem::take(lines);
+                block = None;
+                let reply = if end {
+                    Ok(lines)
+                } else {
+                    Err(lines.join("\n"))
+                };
+                if let Some(tx) = pending.lock().ok().and_then(|mut p| p.pop_front()) {
+                    let _ = tx.send(reply);
+                }
+            } else {
+                lines.push(text.into_owned());
+            }
+            continue;
+        }
+        if let Some(rest) = raw.strip_prefix(b"%output ") {
+            let (pane, data) = match rest.iter().position(|b| *b == b' ') {
+                Some(i) => (&rest[..i], &rest[i + 1..]),
+                None => continue,
+            };
+            let pane = String::from_utf8_lossy(pane).into_owned();
+            let carry = carries.entry(pane.clone()).or_default();
+            let text = decode(carry, &unescape(data));
+            let text = if redact {
+                redactors.entry(pane.clone()).or_default().feed(&text)
+            } else {
+                text
+            };
+            if !text.is_empty() {
+                let _ = on_event.send(TmuxEvent::Output { pane, data: text });
+            }
+            continue;
+        }
+        let text = String::from_utf8_lossy(raw);
+        let notification = text.split(' ').next().unwrap_or_default();
+        match notification {
+            "%begin" => block = Some(Vec::new()),
+            "%exit" => {
+                reason = text
+                    .strip_prefix("%exit ")
+                    .map(str::to_string)
+                    .filter(|r| !r.is_empty());
+                break;
+            }
+            "%window-add"
+            | "%window-close"
+            | "%window-renamed"
+            | "%layout-change"
+            | "%window-pane-changed"
+            | "%session-window-changed"
+            | "%session-changed"
+            | "%unlinked-window-close" => refresh(&app, &id, &on_event),
+            _ => {}
+        }
+    }
+    for (pane, mut redactor) in redactors {
+        let data = redactor.finish();
+        if !data.is_empty() {
+            let _ = on_event.send(TmuxEvent::Output { pane, data });
+        }
+    }
+    // Fail whatever is still waiting.
+    if let Ok(mut pending) = pending.lock() {
+        pending.clear();
+    }
+    let connection = app
+        .state::<TmuxManager>()
+        .connections
+        .lock()
+        .ok()
+        .and_then(|mut c| c.remove(&id));
+    if let Some(mut connection) = connection {
+        let _ = connection.child.kill();
+        let _ = connection.child.wait();
+    }
+    tracing::info!("tmux {id} detached");
+    let _ = on_event.send(TmuxEvent::Exit { reason });
+}
+
+/// Sessions on this machine, or on `host` over ssh.
+#[tauri::command]
+pub async fn tmux_sessions(
+    window: WebviewWindow,
+    host: Option<String>,
+) -> Result<Vec<TmuxSession>, String> {
+    crate::require_app_window(&window)?;
+    let sep = FIELD_SEP;
+    let args = vec![
+        "list-sessions".to_string(),
+        "-F".to_string(),
+        format!("#{{session_windows}}{sep}#{{session_attached}}{sep}#{{session_name}}"),
+    ];
+    let mut command = tmux_command(host.as_deref(), &args)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let out = crate::forge::run_capture(&mut command, None)?;
+        Ok(out
+            .lines()
+            .filter_map(|line| match fields(line, 3)[..] {
+                [windows, attached, name] => Some(TmuxSession {
+                    name: name.to_string(),
+                    windows: windows.parse().ok()?,
+                    attached: attached.parse::<u32>().ok()? > 0,
+                }),
+                _ => None,
+            })
+            .collect())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TmuxAttach {
+    /// Chosen by the frontend; names the connection in the other commands.
+    pub id: String,
+    pub session: String,
+    /// Attach over ssh instead of locally.
+    pub host: Option<String>,
+    pub cols: u16,
+    pub rows: u16,
+}
+
+/// Attach to tmux session `request.session` in control mode. Pane output,
+/// layout changes and the end of the connection arrive over `on_event`;
+/// returns the session's windows and panes.
+#[tauri::command]
+pub async fn tmux_attach(
+    window: WebviewWindow,
+    app: AppHandle,
+    request: TmuxAttach,
+    on_event: Channel<TmuxEvent>,
+) -> Result<TmuxState, String> {
+    crate::require_app_window(&window)?;
+    if request.session.is_empty() || request.session.starts_with('-') {
+        return Err(format!("invalid tmux session {}", request.session));
+    }
+    let args = [
+        "-C".to_string(),
+        "attach-session".to_string(),
+        "-t".to_string(),
+        format!("={}", request.session),
+    ];
+    let mut command = tmux_command(request.host.as_deref(), &args)?;
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("failed to start tmux: {e}"))?;
+    let (stdin, stdout) = match (child.stdin.take(), child.stdout.take()) {
+        (Some(stdin), Some(stdout)) => (stdin, stdout),
+        _ => return Err("tmux has no pipes".to_string()),
+    };
+    // The first block answers the attach itself.
+    let (attached, _) = mpsc::channel();
+    let pending = Arc::new(Mutex::new(VecDeque::from([attached])));
+    {
+        let state = app.state::<TmuxManager>();
+        let mut connections = state.connections.lock().map_err(|e| e.to_string())?;
+        if connections.contains_key(&request.id) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(format!("tmux connection {} exists", request.id));
+        }
+        connections.insert(
+            request.id.clone(),
+            Connection {
+                window: window.label().to_string(),
+                stdin,
+                child,
+                pending: pending.clone(),
+            },
+        );
+    }
+    let (reader_app, id) = (app.clone(), request.id.clone());
+    std::thread::spawn(move || read(reader_app, id, stdout, pending, on_event));
+    tracing::info!("tmux {} attached to {}", request.id, request.session);
+
+    let id = request.id;
+    let (cols, rows) = (request.cols, request.rows);
+    tauri::async_runtime::spawn_blocking(move || {
+        run(&app, &id, &format!("refresh-client -C {cols},{rows}"))?;
+        list(&app, &id)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// The attached session's windows and panes.
+#[tauri::command]
+pub async fn tmux_state(
+    window: WebviewWindow,
+    app: AppHandle,
+    id: String,
+) -> Result<TmuxState, String> {
+    crate::require_app_window(&window)?;
+    tauri::async_runtime::spawn_blocking(move || list(&app, &id))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Type `data` into `pane`.
+#[tauri::command]
+pub async fn tmux_send(
+    window: WebviewWindow,
+    app: AppHandle,
+    id: String,
+    pane: String,
+    data: String,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    check_target(&pane, '%')?;
+    tauri::async_runtime::spawn_blocking(move || {
+        for chunk in data.as_bytes().chunks(SEND_CHUNK) {
+            let hex: Vec<String> = chunk.iter().map(|b| format!("{b:02x}")).collect();
+            run(
+                &app,
+                &id,
+                &format!("send-keys -t {pane} -H {}", hex.join(" ")),
+            )?;
+        }
+        Ok(())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Tell tmux the size the app shows the session at.
+#[tauri::command]
+pub async fn tmux_resize(
+    window: WebviewWindow,
+    app: AppHandle,
+    id: String,
+    cols: u16,
+    rows: u16,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        run(&app, &id, &format!("refresh-client -C {cols},{rows}")).map(|_| ())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// What `pane` shows now, with colors, to fill a view before output
+/// arrives.
+#[tauri::command]
+pub async fn tmux_capture(
+    window: WebviewWindow,
+    app: AppHandle,
+    id: String,
+    pane: String,
+) -> Result<String, String> {
+    crate::require_app_window(&window)?;
+    check_target(&pane, '%')?;
+    tauri::async_runtime::spawn_blocking(move || {
+        run(&app, &id, &format!("capture-pane -p -e -t {pane}")).map(|lines| lines.join("\r\n"))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase",
+    tag = "action"
+)]
+pub enum TmuxAction {
+    NewWindow,
+    SelectWindow {
+        window: String,
+    },
+    KillWindow {
+        window: String,
+    },
+    /// Below `pane`, or beside it with `horizontal`.
+    SplitPane {
+        pane: String,
+        horizontal: bool,
+    },
+    SelectPane {
+        pane: String,
+    },
+    KillPane {
+        pane: String,
+    },
+}
+
+/// Change the attached session's windows and panes. The new layout arrives
+/// as a `state` event.
+#[tauri::command]
+pub async fn tmux_action(
+    window: WebviewWindow,
+    app: AppHandle,
+    id: String,
+    action: TmuxAction,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    let command = match &action {
+        TmuxAction::NewWindow => "new-window".to_string(),
+        TmuxAction::SelectWindow { window } => {
+            check_target(window, '@')?;
+            format!("select-window -t {window}")
+        }
+        TmuxAction::KillWindow { window } => {
+            check_target(window, '@')?;
+            format!("kill-window -t {window}")
+        }
+        TmuxAction::SplitPane { pane, horizontal } => {
+            check_target(pane, '%')?;
+            let flag = if *horizontal { "-h" } else { "-v" };
+            format!("split-window {flag} -t {pane}")
+        }
+        TmuxAction::SelectPane { pane } => {
+            check_target(pane, '%')?;
+            format!("select-pane -t {pane}")
+        }
+        TmuxAction::KillPane { pane } => {
+            check_target(pane, '%')?;
+            format!("kill-pane -t {pane}")
+        }
+    };
+    tauri::async_runtime::spawn_blocking(move || run(&app, &id, &command).map(|_| ()))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Detach `connection`, leaving the session and its programs running.
+fn detach(connection: &mut Connection) {
+    let _ = writeln!(connection.stdin, "detach-client");
+    let _ = connection.stdin.flush();
+}
+
+/// Detach from the session; `exit` follows on the event channel.
+#[tauri::command]
+pub fn tmux_detach(
+    window: WebviewWindow,
+    state: State<'_, TmuxManager>,
+    id: String,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    let mut connections = state.connections.lock().map_err(|e| e.to_string())?;
+    let connection = connections
+        .get_mut(&id)
+        .ok_or_else(|| format!("no tmux connection {id}"))?;
+    detach(connection);
+    Ok(())
+}
+
+/// Detach the sessions a closed window had attached.
+pub fn close_window(state: &TmuxManager, label: &str) {
+    if let Ok(mut connections) = state.connections.lock() {
+        for connection in connections.values_mut().filter(|c| c.window == label) {
+            detach(connection);
+        }
+    }
+}
+
+/// Detach everything — called on app exit. tmux keeps the sessions.
+pub fn detach_all(state: &TmuxManager) {
+    if let Ok(mut connections) = state.connections.lock() {
+        for (_, mut connection) in connections.drain() {
+            detach(&mut connection);
+            let _ = connection.child.kill();
+        }
+    }
+}
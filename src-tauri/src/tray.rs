@@ -0,0 +1,204 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::menu::{Menu, MenuItem, PredefinedMenuItem};
+use tauri::tray::TrayIconBuilder;
+use tauri::{AppHandle, Emitter, Manager, Runtime, State, WebviewWindow};
+
+const TRAY_ID: &str = "main";
+
+/// Tasks listed in the tray menu; the rest are summarized in the header.
+const MAX_MENU_TASKS: usize = 15;
+
+const MENU_SHOW: &str = "show";
+const MENU_PAUSE_ALL: &str = "pause-all";
+const MENU_QUIT: &str = "quit";
+const MENU_TASK_PREFIX: &str = "task:";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrayTask {
+    pub id: String,
+    pub title: String,
+    /// `running` or `waiting` (for input or approval).
+    pub status: String,
+    /// Client route to open when the task is picked from the menu.
+    pub route: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrayStatus {
+    pub running: u32,
+    pub waiting: u32,
+    #[serde(default)]
+    pub tasks: Vec<TrayTask>,
+}
+
+/// Tasks currently in the tray menu, by id, so menu clicks can be routed.
+pub struct TrayState {
+    tasks: Mutex<HashMap<String, TrayTask>>,
+}
+
+impl TrayState {
+    pub fn new() -> Self {
+        Self {
+            tasks: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// A task waiting for the user's input, if any.
+    pub(crate) fn waiting_task(&self) -> Option<TrayTask> {
+        let tasks = self.tasks.lock().ok()?;
+        tasks.values().find(|t| t.status == "waiting").cloned()
+    }
+}
+
+fn summary(status: &TrayStatus) -> String {
+    match (status.running, status.waiting) {
+        (0, 0) => "No agents running".to_string(),
+        (running, 0) => format!("{running} running"),
+        (0, waiting) => format!("{waiting} waiting for input"),
+        (running, waiting) => format!("{running} running · {waiting} waiting for input"),
+    }
+}
+
+fn build_menu<R: Runtime>(app: &AppHandle<R>, status: &TrayStatus) -> tauri::Result<Menu<R>> {
+    let menu = Menu::new(app)?;
+    menu.append(&MenuItem::with_id(
+        app,
+        "summary",
+        summary(status),
+        false,
+        None::<&str>,
+    )?)?;
+
+    let mut tasks: Vec<&TrayTask> = status.tasks.iter().collect();
+    // Tasks that need the user come first.
+    tasks.sort_by_key(|t| t.status != "waiting");
+    if !tasks.is_empty() {
+        menu.append(&PredefinedMenuItem::separator(app)?)?;
+    }
+    for task in tasks.iter().take(MAX_MENU_TASKS) {
+        let marker = if task.status == "waiting" { "● " } else { "" };
+        menu.append(&MenuItem::with_id(
+            app,
+            format!("{MENU_TASK_PREFIX}{}", task.id),
+            format!("{marker}{}", task.title),
+            true,
+            None::<&str>,
+        )?)?;
+    }
+    if tasks.len() > MAX_MENU_TASKS {
+        let more = format!("{} more…", tasks.len() - MAX_MENU_TASKS);
+        menu.append(&MenuItem::with_id(app, "more", more, false, None::<&str>)?)?;
+    }
+
+    menu.append(&PredefinedMenuItem::separator(app)?)?;
+    menu.append(&MenuItem::with_id(
+        app,
+        MENU_SHOW,
+        "Show funny",
+        true,
+        None::<&str>,
+    )?)?;
+    menu.append(&MenuItem::with_id(
+        app,
+        MENU_PAUSE_ALL,
+        "Pause all agents",
+        status.running > 0,
+        None::<&str>,
+    )?)?;
+    menu.append(&MenuItem::with_id(
+        app,
+        MENU_QUIT,
+        "Quit funny",
+        true,
+        None::<&str>,
+    )?)?;
+    Ok(menu)
+}
+
+fn show_main(app: &AppHandle) {
+    if let Some(main) = app.get_webview_window("main") {
+        let _ = main.unminimize();
+        let _ = main.show();
+        let _ = main.set_focus();
+    }
+}
+
+fn on_menu_event(app: &AppHandle, id: &str) {
+    match id {
+        MENU_SHOW => show_main(app),
+        // The server owns the agents; the frontend forwards the request.
+        MENU_PAUSE_ALL => {
+            let _ = app.emit_to("main", "tray:pause-all", ());
+        }
+        // `exit` runs the RunEvent::Exit cleanup (PTYs, watchers, sidecar).
+        MENU_QUIT => app.exit(0),
+        _ => {
+            let Some(task_id) = id.strip_prefix(MENU_TASK_PREFIX) else {
+                return;
+            };
+            let task = app
+                .state::<TrayState>()
+                .tasks
+                .lock()
+                .ok()
+                .and_then(|tasks| tasks.get(task_id).cloned());
+            if let Some(task) = task {
+                show_main(app);
+                let _ = app.emit_to("main", "tray:open-task", task);
+            }
+        }
+    }
+}
+
+/// Create the tray icon. Called once from `setup`.
+pub fn init(app: &AppHandle) -> tauri::Result<()> {
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip("funny")
+        .menu(&build_menu(app, &TrayStatus::default())?)
+        .show_menu_on_left_click(true)
+        .on_menu_event(|app, event| on_menu_event(app, event.id().as_ref()));
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    builder.build(app)?;
+    Ok(())
+}
+
+/// Refresh the tray from the frontend's view of agent statuses. On macOS the
+/// counts are also shown next to the menu bar icon, and the waiting count
+/// becomes the dock badge. Running agents keep the system from sleeping.
+#[tauri::command]
+pub fn update_tray_status(
+    window: WebviewWindow,
+    app: AppHandle,
+    state: State<'_, TrayState>,
+    status: TrayStatus,
+) -> Result<(), String> {
+    crate::require_window(&window, "main")?;
+    crate::power::set_agents_running(&app, status.running);
+    let tray = app
+        .tray_by_id(TRAY_ID)
+        .ok_or("tray icon is not available")?;
+    let menu = build_menu(&app, &status).map_err(|e| e.to_string())?;
+    tray.set_menu(Some(menu)).map_err(|e| e.to_string())?;
+    tray.set_tooltip(Some(format!("funny — {}", summary(&status))))
+        .map_err(|e| e.to_string())?;
+    let title = match (status.running, status.waiting) {
+        (0, 0) => None,
+        (running, 0) => Some(format!("{running}")),
+        (running, waiting) => Some(format!("{running} · {waiting}!")),
+    };
+    tray.set_title(title).map_err(|e| e.to_string())?;
+    crate::badge::set_badge(&app, status.waiting)?;
+
+    *state.tasks.lock().map_err(|e| e.to_string())? = status
+        .tasks
+        .into_iter()
+        .map(|t| (t.id.clone(), t))
+        .collect();
+    Ok(())
+}
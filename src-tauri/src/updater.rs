@@ -0,0 +1,493 @@
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State, WebviewWindow};
+use tauri_plugin_shell::process::Command;
+use tauri_plugin_shell::ShellExt;
+use tauri_plugin_updater::{Update, UpdaterExt};
+
+/// Releases are published on GitHub: stable ones as regular releases, betas
+/// by re-uploading the manifests to the rolling `beta` release.
+const RELEASES_URL: &str = "https://github.com/ironmussa/funny/releases";
+
+/// Manifest written by the Tauri bundler for app updates.
+const APP_MANIFEST: &str = "latest.json";
+
+/// Manifest for server-only updates, shipped between app releases:
+/// `{ "version": "0.2.1", "platforms": { "<target triple>": { "url", "signature" } } }`.
+/// Signatures are made with the same key as the app bundles.
+const SIDECAR_MANIFEST: &str = "sidecar.json";
+
+/// The public key comes from the release build's environment, falling back
+/// to `plugins.updater.pubkey` in tauri.conf.json.
+const PUBKEY: Option<&str> = option_env!("FUNNY_UPDATER_PUBKEY");
+
+/// How long the server gets to shut down after SIGTERM before it is killed.
+const SERVER_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+const UPDATER_FILE: &str = "updater.json";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReleaseChannel {
+    #[default]
+    Stable,
+    Beta,
+}
+
+impl ReleaseChannel {
+    fn manifest_url(self, manifest: &str) -> String {
+        match self {
+            ReleaseChannel::Stable => format!("{RELEASES_URL}/latest/download/{manifest}"),
+            ReleaseChannel::Beta => format!("{RELEASES_URL}/download/beta/{manifest}"),
+        }
+    }
+}
+
+/// A server binary downloaded by a sidecar update, used instead of the
+/// bundled one until an app update catches up with its version.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StagedSidecar {
+    version: String,
+    path: PathBuf,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdaterConfig {
+    #[serde(default)]
+    channel: ReleaseChannel,
+    sidecar: Option<StagedSidecar>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct SidecarPlatform {
+    url: String,
+    signature: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct SidecarManifest {
+    version: String,
+    platforms: HashMap<String, SidecarPlatform>,
+}
+
+/// Updates found by the last `check_for_updates`, waiting to be installed.
+pub struct Updates {
+    app: Mutex<Option<Update>>,
+    sidecar: Mutex<Option<(String, SidecarPlatform)>>,
+}
+
+impl Updates {
+    pub fn new() -> Self {
+        Self {
+            app: Mutex::new(None),
+            sidecar: Mutex::new(None),
+        }
+    }
+}
+
+fn config_path(app: &AppHandle) -> Option<PathBuf> {
+    app.path()
+        .app_data_dir()
+        .ok()
+        .map(|dir| dir.join(UPDATER_FILE))
+}
+
+fn load_config(app: &AppHandle) -> UpdaterConfig {
+    config_path(app)
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_config(app: &AppHandle, config: &UpdaterConfig) -> Result<(), String> {
+    let path = config_path(app).ok_or("no app data directory")?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_vec_pretty(config).map_err(|e| e.to_string())?;
+    std::fs::write(path, json).map_err(|e| e.to_string())
+}
+
+fn is_newer(version: &str, than: &str) -> bool {
+    match (
+        semver::Version::parse(version),
+        semver::Version::parse(than),
+    ) {
+        (Ok(version), Ok(than)) => version > than,
+        _ => false,
+    }
+}
+
+pub(crate) fn release_channel(app: &AppHandle) -> ReleaseChannel {
+    load_config(app).channel
+}
+
+fn app_version(app: &AppHandle) -> String {
+    app.package_info().version.to_string()
+}
+
+/// The staged server binary, if it is still newer than the bundled one.
+/// Once an app update ships the same or a later server, the staged copy is
+/// deleted.
+fn staged_sidecar(app: &AppHandle) -> Option<StagedSidecar> {
+    let mut config = load_config(app);
+    let staged = config.sidecar.clone()?;
+    if is_newer(&staged.version, &app_version(app)) && staged.path.is_file() {
+        return Some(staged);
+    }
+    if let Some(dir) = staged.path.parent() {
+        let _ = std::fs::remove_dir_all(dir);
+    }
+    config.sidecar = None;
+    let _ = save_config(app, &config);
+    None
+}
+
+/// Version of the server that is (or will be) running.
+pub(crate) fn sidecar_version(app: &AppHandle) -> String {
+    staged_sidecar(app)
+        .map(|s| s.version)
+        .unwrap_or_else(|| app_version(app))
+}
+
+/// Command that starts the server: the staged binary from a sidecar update
+/// if there is one, the bundled sidecar otherwise.
+pub(crate) fn server_command(app: &AppHandle) -> Result<Command, String> {
+    match staged_sidecar(app) {
+        Some(staged) => {
+            tracing::info!("starting staged server {}", staged.version);
+            Ok(app.shell().command(staged.path))
+        }
+        None => app
+            .shell()
+            .sidecar("funny-server")
+            .map_err(|e| e.to_string()),
+    }
+    .map(|command| {
+        command
+            .envs(crate::secrets::env(app))
+            .envs(crate::egress::proxy_env(app))
+    })
+}
+
+fn configured_pubkey(app: &AppHandle) -> Option<String> {
+    PUBKEY
+        .map(str::to_string)
+        .or_else(|| {
+            app.config()
+                .plugins
+                .0
+                .get("updater")
+                .and_then(|c| c.get("pubkey"))
+                .and_then(|k| k.as_str())
+                .map(str::to_string)
+        })
+        .filter(|k| !k.trim().is_empty())
+}
+
+/// Check a minisign signature the way the Tauri updater does: both the key
+/// and the signature are base64-encoded minisign files.
+fn verify_signature(data: &[u8], signature: &str, pubkey: &str) -> Result<(), String> {
+    let decode = |value: &str| {
+        base64::engine::general_purpose::STANDARD
+            .decode(value)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or_else(|| "invalid base64 in update signature".to_string())
+    };
+    let key = minisign_verify::PublicKey::decode(&decode(pubkey)?).map_err(|e| e.to_string())?;
+    let signature =
+        minisign_verify::Signature::decode(&decode(signature)?).map_err(|e| e.to_string())?;
+    key.verify(data, &signature, true)
+        .map_err(|e| format!("server update signature is invalid: {e}"))
+}
+
+/// Ask the running server to shut down (SIGTERM, so it can finish pending
+/// writes), kill it if it hasn't exited in time.
+fn stop_server(app: &AppHandle) {
+    let child = match app.try_state::<crate::ServerProcess>() {
+        Some(state) => match state.0.lock() {
+            Ok(mut guard) => guard.take(),
+            Err(_) => None,
+        },
+        None => None,
+    };
+    let child = match child {
+        Some(child) => child,
+        None => return,
+    };
+
+    #[cfg(unix)]
+    {
+        let pid = child.pid().to_string();
+        let signalled = std::process::Command::new("kill")
+            .args(["-TERM", &pid])
+            .status()
+            .is_ok_and(|s| s.success());
+        let started = std::time::Instant::now();
+        while signalled && started.elapsed() < SERVER_SHUTDOWN_TIMEOUT {
+            let alive = std::process::Command::new("kill")
+                .args(["-0", &pid])
+                .status()
+                .is_ok_and(|s| s.success());
+            if !alive {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(200));
+        }
+    }
+    let _ = child.kill();
+}
+
+/// Swap the running server for whatever `server_command` now resolves to.
+pub(crate) fn restart_server(app: &AppHandle) -> Result<(), String> {
+    stop_server(app);
+    let (rx, child) = server_command(app)?
+        .spawn()
+        .map_err(|e| format!("failed to start server: {e}"))?;
+    let pid = child.pid();
+    if let Some(state) = app.try_state::<crate::ServerProcess>() {
+        *state.0.lock().map_err(|e| e.to_string())? = Some(child);
+    }
+    crate::crash::watch_server(app, pid, rx);
+    let _ = app.emit_to("main", "updater:server-restarted", ());
+    Ok(())
+}
+
+fn fetch_sidecar_manifest(channel: ReleaseChannel) -> Result<Option<SidecarManifest>, String> {
+    match ureq::get(&channel.manifest_url(SIDECAR_MANIFEST)).call() {
+        Ok(response) => response.into_json().map(Some).map_err(|e| e.to_string()),
+        // Releases without server-only updates have no sidecar manifest.
+        Err(ureq::Error::Status(404, _)) => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn download(url: &str, app: &AppHandle) -> Result<Vec<u8>, String> {
+    let response = ureq::get(url).call().map_err(|e| e.to_string())?;
+    let total = response
+        .header("Content-Length")
+        .and_then(|len| len.parse::<u64>().ok());
+    let mut reader = response.into_reader();
+    let mut bytes = Vec::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf).map_err(|e| e.to_string())?;
+        if n == 0 {
+            break;
+        }
+        bytes.extend_from_slice(&buf[..n]);
+        emit_progress(app, bytes.len() as u64, total);
+    }
+    Ok(bytes)
+}
+
+/// Download, verify and stage a server binary, then restart the server on it.
+fn install_sidecar(
+    app: &AppHandle,
+    version: &str,
+    platform: &SidecarPlatform,
+) -> Result<(), String> {
+    let pubkey = configured_pubkey(app).ok_or("this build has no updater key")?;
+    let bytes = download(&platform.url, app)?;
+    verify_signature(&bytes, &platform.signature, &pubkey)?;
+
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?
+        .join("sidecar")
+        .join(version);
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let path = dir.join(format!("funny-server{}", std::env::consts::EXE_SUFFIX));
+    let tmp = path.with_extension("download");
+    std::fs::write(&tmp, &bytes).map_err(|e| e.to_string())?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&tmp, std::fs::Permissions::from_mode(0o755))
+            .map_err(|e| e.to_string())?;
+    }
+    std::fs::rename(&tmp, &path).map_err(|e| e.to_string())?;
+
+    let mut config = load_config(app);
+    let previous = config.sidecar.replace(StagedSidecar {
+        version: version.to_string(),
+        path: path.clone(),
+    });
+    save_config(app, &config)?;
+    restart_server(app)?;
+
+    if let Some(dir) = previous.and_then(|p| p.path.parent().map(|d| d.to_path_buf())) {
+        if dir.as_path() != path.parent().unwrap_or(&dir) {
+            let _ = std::fs::remove_dir_all(dir);
+        }
+    }
+    tracing::info!("server updated to {version}");
+    Ok(())
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateProgress {
+    downloaded: u64,
+    total: Option<u64>,
+}
+
+fn emit_progress(app: &AppHandle, downloaded: u64, total: Option<u64>) {
+    let _ = app.emit_to(
+        "main",
+        "updater:progress",
+        UpdateProgress { downloaded, total },
+    );
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AvailableUpdate {
+    pub version: String,
+    pub current_version: String,
+    pub notes: Option<String>,
+    pub date: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateCheck {
+    pub channel: ReleaseChannel,
+    /// A new app release; installing it restarts the app.
+    pub app: Option<AvailableUpdate>,
+    /// A server-only update; installing it restarts just the server. Not
+    /// offered when an app update is available, which ships its own server.
+    pub sidecar: Option<AvailableUpdate>,
+}
+
+/// Look for app and server updates on the selected release channel. Found
+/// updates are remembered for `install_update`.
+#[tauri::command]
+pub async fn check_for_updates(
+    window: WebviewWindow,
+    app: AppHandle,
+    state: State<'_, Updates>,
+) -> Result<UpdateCheck, String> {
+    crate::require_window(&window, "main")?;
+    let pubkey = configured_pubkey(&app).ok_or("this build has no updater key")?;
+    let channel = load_config(&app).channel;
+
+    let endpoint = channel
+        .manifest_url(APP_MANIFEST)
+        .parse()
+        .map_err(|e| format!("{e}"))?;
+    let update = app
+        .updater_builder()
+        .pubkey(pubkey)
+        .endpoints(vec![endpoint])
+        .and_then(|b| b.build())
+        .map_err(|e| e.to_string())?
+        .check()
+        .await
+        .map_err(|e| e.to_string())?;
+    let app_update = update.as_ref().map(|u| AvailableUpdate {
+        version: u.version.clone(),
+        current_version: u.current_version.clone(),
+        notes: u.body.clone(),
+        date: u.date.map(|d| d.to_string()),
+    });
+    *state.app.lock().map_err(|e| e.to_string())? = update;
+
+    let mut sidecar = None;
+    if app_update.is_none() {
+        let manifest =
+            tauri::async_runtime::spawn_blocking(move || fetch_sidecar_manifest(channel))
+                .await
+                .map_err(|e| e.to_string())??;
+        let current = sidecar_version(&app);
+        sidecar = manifest
+            .filter(|m| is_newer(&m.version, &current))
+            .and_then(|m| {
+                let platform = m.platforms.get(env!("TAURI_ENV_TARGET_TRIPLE"))?.clone();
+                Some((m.version, platform))
+            });
+    }
+    let sidecar_update = sidecar.as_ref().map(|(version, _)| AvailableUpdate {
+        version: version.clone(),
+        current_version: sidecar_version(&app),
+        notes: None,
+        date: None,
+    });
+    *state.sidecar.lock().map_err(|e| e.to_string())? = sidecar;
+
+    Ok(UpdateCheck {
+        channel,
+        app: app_update,
+        sidecar: sidecar_update,
+    })
+}
+
+/// Install what the last `check_for_updates` found. App updates are
+/// downloaded and installed, then the app restarts; server updates are
+/// staged and the server is restarted gracefully while the app keeps running.
+/// Download progress is reported as `updater:progress` events.
+#[tauri::command]
+pub async fn install_update(
+    window: WebviewWindow,
+    app: AppHandle,
+    state: State<'_, Updates>,
+) -> Result<(), String> {
+    crate::require_window(&window, "main")?;
+    let update = state.app.lock().map_err(|e| e.to_string())?.take();
+    if let Some(update) = update {
+        let mut downloaded = 0u64;
+        update
+            .download_and_install(
+                |chunk, total| {
+                    downloaded += chunk as u64;
+                    emit_progress(&app, downloaded, total);
+                },
+                || {},
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+        // `restart` skips the exit handler, so stop the server here.
+        stop_server(&app);
+        app.restart();
+    }
+
+    let sidecar = state.sidecar.lock().map_err(|e| e.to_string())?.take();
+    let (version, platform) = sidecar.ok_or("no update to install")?;
+    tauri::async_runtime::spawn_blocking(move || install_sidecar(&app, &version, &platform))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub fn get_update_channel(window: WebviewWindow, app: AppHandle) -> Result<ReleaseChannel, String> {
+    crate::require_window(&window, "main")?;
+    Ok(load_config(&app).channel)
+}
+
+/// Switch release channels. Pending updates from the old channel are
+/// dropped; moving from beta to stable takes effect with the next stable
+/// release rather than downgrading.
+#[tauri::command]
+pub fn set_update_channel(
+    window: WebviewWindow,
+    app: AppHandle,
+    state: State<'_, Updates>,
+    channel: ReleaseChannel,
+) -> Result<(), String> {
+    crate::require_window(&window, "main")?;
+    let mut config = load_config(&app);
+    config.channel = channel;
+    save_config(&app, &config)?;
+    *state.app.lock().map_err(|e| e.to_string())? = None;
+    *state.sidecar.lock().map_err(|e| e.to_string())? = None;
+    Ok(())
+}
@@ -0,0 +1,328 @@
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex};
+use tauri::ipc::Channel;
+use tauri::{AppHandle, Manager, WebviewWindow};
+
+use crate::exec::{ExecEvent, ExecOptions};
+use crate::problems::{Diagnostic, Severity};
+use crate::sandbox::Sandbox;
+use crate::test_report::TestSummary;
+
+/// Diagnostics kept with a verification; the rest are only streamed.
+const MAX_PROBLEMS: usize = 100;
+/// Characters of output kept from the end of a verification.
+const OUTPUT_TAIL: usize = 8 * 1024;
+/// Failed tests and diagnostics listed in the feedback for the agent.
+const FEEDBACK_ITEMS: usize = 20;
+/// Characters of output quoted in the feedback for the agent.
+const FEEDBACK_OUTPUT: usize = 4 * 1024;
+
+/// A command run in the task's worktree once the agent is done, e.g.
+/// `["cargo", "test"]`. Set on the agent defaults, per project or globally.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct VerifyConfig {
+    pub command: Vec<String>,
+    /// Like `ExecOptions::timeout_ms`.
+    pub timeout_ms: Option<u64>,
+    /// Give the agent the failures and run it again.
+    pub retry_on_failure: bool,
+    /// Verifications per run, including the first.
+    pub max_attempts: u32,
+}
+
+impl Default for VerifyConfig {
+    fn default() -> Self {
+        Self {
+            command: Vec::new(),
+            timeout_ms: None,
+            retry_on_failure: false,
+            max_attempts: 3,
+        }
+    }
+}
+
+pub(crate) fn validate_config(config: &VerifyConfig) -> Result<(), String> {
+    if config.command.first().is_none_or(|c| c.trim().is_empty()) {
+        return Err("verify.command must not be empty".to_string());
+    }
+    if config.max_attempts == 0 {
+        return Err("verify.maxAttempts must be at least 1".to_string());
+    }
+    Ok(())
+}
+
+/// The outcome of verifying one attempt of a run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Verification {
+    pub run_id: String,
+    /// 1 for the agent's first try, counting up with each retry.
+    pub attempt: u32,
+    pub command: Vec<String>,
+    /// Exited with 0 and no test failed.
+    pub passed: bool,
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+    pub elapsed_ms: u64,
+    pub tests: Option<TestSummary>,
+    /// At most `MAX_PROBLEMS` diagnostics.
+    pub problems: Vec<Diagnostic>,
+    /// The end of the output, stdout and stderr interleaved.
+    pub output: String,
+    /// RFC 3339.
+    pub started_at: String,
+    /// What to tell the agent, if it should try again.
+    pub retry_prompt: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyRequest {
+    pub run_id: String,
+    /// The worktree the agent worked in.
+    pub worktree: String,
+    pub attempt: u32,
+}
+
+#[derive(Default)]
+struct Collected {
+    problems: Vec<Diagnostic>,
+    output: String,
+}
+
+/// Keep the last `max` bytes of `text`, on a character boundary.
+pub(crate) fn tail(text: &str, max: usize) -> &str {
+    let mut start = text.len().saturating_sub(max);
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    &text[start..]
+}
+
+/// The verify config for `run_id`: its project's, else the global one.
+fn config_for(app: &AppHandle, run_id: &str) -> Result<Option<VerifyConfig>, String> {
+    let project: Option<String> = crate::db::with(app, |conn| {
+        conn.query_row(
+            "SELECT project FROM agent_runs WHERE id = ?1",
+            params![run_id],
+            |row| row.get(0),
+        )
+        .optional()
+    })?
+    .flatten();
+    let config = project
+        .and_then(|project| crate::project_config::get(app, &project))
+        .map(|config| config.agent.verify)
+        .unwrap_or_else(|| crate::settings::current(app).agents.verify);
+    Ok(config)
+}
+
+fn retry_prompt(verification: &Verification) -> String {
+    let mut prompt = format!("Verification failed: `{}` ", verification.command.join(" "));
+    match verification.exit_code {
+        _ if verification.timed_out => prompt.push_str("timed out."),
+        Some(code) => {
+            let _ = write!(prompt, "exited with code {code}.");
+        }
+        None => prompt.push_str("was killed."),
+    }
+    prompt.push_str(" Fix the problems below, then finish again.\n");
+    if let Some(tests) = &verification.tests {
+        if tests.failed > 0 {
+            let _ = write!(prompt, "\n{} failing tests:\n", tests.failed);
+            for name in tests.failures.iter().take(FEEDBACK_ITEMS) {
+                let _ = writeln!(prompt, "- {name}");
+            }
+        }
+    }
+    let errors: Vec<&Diagnostic> = verification
+        .problems
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .take(FEEDBACK_ITEMS)
+        .collect();
+    if !errors.is_empty() {
+        prompt.push_str("\nErrors:\n");
+        for d in errors {
+            let _ = write!(prompt, "- {}", d.file);
+            if let Some(line) = d.line {
+                let _ = write!(prompt, ":{line}");
+            }
+            let _ = writeln!(prompt, ": {}", d.message);
+        }
+    }
+    let output = tail(&verification.output, FEEDBACK_OUTPUT).trim();
+    if !output.is_empty() {
+        let _ = write!(prompt, "\nEnd of the output:\n```\n{output}\n```\n");
+    }
+    prompt
+}
+
+fn record(app: &AppHandle, verification: &Verification) {
+    let result = serde_json::to_string(verification)
+        .map_err(|e| e.to_string())
+        .and_then(|json| {
+            crate::db::with(app, |conn| {
+                conn.execute(
+                    "INSERT OR REPLACE INTO verifications
+                         (run_id, attempt, passed, started_at, result)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        verification.run_id,
+                        verification.attempt,
+                        verification.passed,
+                        verification.started_at,
+                        json,
+                    ],
+                )
+            })
+        });
+    if let Err(e) = result {
+        tracing::warn!(
+            "verification of run {} not recorded: {e}",
+            verification.run_id
+        );
+    }
+}
+
+/// Run the configured verification command in `request.worktree` after the
+/// agent of `request.run_id` has finished, and store the result with the
+/// run. Output, test results and diagnostics stream over `on_event` as with
+/// `exec_command`. `None` if no verification is configured. When it fails
+/// and retries are on, `retryPrompt` says what to send the agent for the
+/// next attempt. Stop it with `cancel(request_id)`.
+#[tauri::command]
+pub async fn agent_verify(
+    window: WebviewWindow,
+    app: AppHandle,
+    request: VerifyRequest,
+    request_id: Option<String>,
+    on_event: Channel<ExecEvent>,
+) -> Result<Option<Verification>, String> {
+    crate::require_app_window(&window)?;
+    // Worktrees a runner made on an SSH host are verified there.
+    let sandbox = app.state::<Sandbox>();
+    let worktree = match sandbox.remote(&request.worktree)? {
+        Some(remote) => Err(remote),
+        None => Ok(sandbox.check_dir(&request.worktree)?),
+    };
+    let lookup = app.clone();
+    let run_id = request.run_id.clone();
+    let config = match tauri::async_runtime::spawn_blocking(move || config_for(&lookup, &run_id))
+        .await
+        .map_err(|e| e.to_string())??
+    {
+        Some(config) => config,
+        None => return Ok(None),
+    };
+
+    let started_at = chrono::Utc::now().to_rfc3339();
+    let collected = Arc::new(Mutex::new(Collected::default()));
+    let sink = collected.clone();
+    let options = ExecOptions {
+        env: Default::default(),
+        timeout_ms: config.timeout_ms,
+    };
+    let on_output = move |event: ExecEvent| {
+        if let Ok(mut collected) = sink.lock() {
+            match &event {
+                ExecEvent::Stdout(text) | ExecEvent::Stderr(text) => {
+                    collected.output.push_str(text);
+                    if collected.output.len() > 2 * OUTPUT_TAIL {
+                        collected.output = tail(&collected.output, OUTPUT_TAIL).to_string();
+                    }
+                }
+                ExecEvent::Problem(diagnostic) if collected.problems.len() < MAX_PROBLEMS => {
+                    collected.problems.push(diagnostic.clone());
+                }
+                _ => {}
+            }
+        }
+        let _ = on_event.send(event);
+    };
+    let argv = config.command.clone();
+    let result = match worktree {
+        Ok(dir) => crate::exec::execute(&app, dir, argv, options, request_id, on_output).await?,
+        Err(remote) => {
+            crate::exec::execute_remote(&app, remote, argv, options, request_id, on_output).await?
+        }
+    };
+
+    let collected = std::mem::take(&mut *collected.lock().map_err(|e| e.to_string())?);
+    let passed = result.exit_code == Some(0) && result.tests.as_ref().is_none_or(|t| t.failed == 0);
+    let mut verification = Verification {
+        run_id: request.run_id,
+        attempt: request.attempt,
+        command: config.command.clone(),
+        passed,
+        exit_code: result.exit_code,
+        timed_out: result.timed_out,
+        elapsed_ms: result.elapsed_ms,
+        tests: result.tests,
+        problems: collected.problems,
+        output: crate::redact::redact_tokens(tail(&collected.output, OUTPUT_TAIL)),
+        started_at,
+        retry_prompt: None,
+    };
+    if !passed && config.retry_on_failure && request.attempt < config.max_attempts {
+        verification.retry_prompt =
+            Some(crate::redact::redact_tokens(&retry_prompt(&verification)));
+    }
+    let recorder = app.clone();
+    let stored = verification.clone();
+    tauri::async_runtime::spawn_blocking(move || record(&recorder, &stored))
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(Some(verification))
+}
+
+/// When each verification of `project`'s runs (any project when `None`)
+/// started and whether it passed, for those started after `since` and no
+/// later than `until`.
+pub(crate) fn results_between(
+    app: &AppHandle,
+    project: Option<&str>,
+    since: &str,
+    until: &str,
+) -> Result<Vec<(String, bool)>, String> {
+    crate::db::with(app, |conn| {
+        let mut statement = conn.prepare(
+            "SELECT v.started_at, v.passed FROM verifications v
+             JOIN agent_runs r ON r.id = v.run_id
+             WHERE (?1 IS NULL OR r.project = ?1)
+               AND julianday(v.started_at) > julianday(?2)
+               AND julianday(v.started_at) <= julianday(?3)",
+        )?;
+        let rows = statement.query_map(params![project, since, until], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?;
+        rows.collect()
+    })
+}
+
+/// A run's verifications, first attempt first.
+#[tauri::command]
+pub async fn agent_verifications(
+    window: WebviewWindow,
+    app: AppHandle,
+    run_id: String,
+) -> Result<Vec<Verification>, String> {
+    crate::require_app_window(&window)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let rows: Vec<String> = crate::db::with(&app, |conn| {
+            let mut statement = conn
+                .prepare("SELECT result FROM verifications WHERE run_id = ?1 ORDER BY attempt")?;
+            let rows = statement.query_map(params![run_id], |row| row.get(0))?;
+            rows.collect()
+        })?;
+        Ok(rows
+            .iter()
+            .filter_map(|json| serde_json::from_str(json).ok())
+            .collect())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
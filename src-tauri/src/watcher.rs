@@ -0,0 +1,361 @@
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State, WebviewWindow};
+
+use crate::events::EventRouter;
+use crate::sandbox::Sandbox;
+
+/// Quiet period after the last raw event before a batch is flushed.
+const DEBOUNCE: Duration = Duration::from_millis(150);
+
+/// Upper bound on how long a batch may keep growing while events keep
+/// arriving (e.g. during `npm install` or a large checkout).
+const MAX_BATCH_DELAY: Duration = Duration::from_secs(1);
+
+/// Beyond this many distinct paths a batch is reported as `overflow` so the
+/// frontend does a full refresh instead of processing every path.
+const MAX_BATCH_PATHS: usize = 500;
+
+struct WatchInstance {
+    // Dropping the watcher stops the OS watch and closes the event channel,
+    // which ends the debounce thread.
+    _watcher: RecommendedWatcher,
+}
+
+pub struct FileWatcher {
+    watches: Mutex<HashMap<String, WatchInstance>>,
+}
+
+impl FileWatcher {
+    pub fn new() -> Self {
+        Self {
+            watches: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub(crate) fn watch_count(&self) -> usize {
+        self.watches.lock().map(|w| w.len()).unwrap_or(0)
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchOptions {
+    /// Watch subdirectories too. Defaults to true.
+    pub recursive: Option<bool>,
+    /// Drop `.gitignore`d paths. Defaults to true.
+    pub gitignore: Option<bool>,
+    /// Extra glob patterns (relative to the watched root) to drop, on top of
+    /// `.gitignore` rules.
+    #[serde(default)]
+    pub ignore_globs: Vec<String>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FsChangedPayload {
+    pub id: String,
+    pub root: String,
+    /// Changed paths relative to `root`, with `/` separators.
+    pub paths: Vec<String>,
+    /// True when too many paths changed at once and `paths` was dropped.
+    pub overflow: bool,
+}
+
+/// Gitignore matchers for every directory between a changed path and the
+/// watched root, built lazily and dropped when a `.gitignore` changes.
+struct IgnoreRules {
+    root: PathBuf,
+    globs: GlobSet,
+    per_dir: HashMap<PathBuf, Option<Gitignore>>,
+    exclude: Option<Gitignore>,
+    gitignore: bool,
+}
+
+impl IgnoreRules {
+    fn new(root: &Path, globs: GlobSet, gitignore: bool) -> Self {
+        let exclude = {
+            let mut builder = GitignoreBuilder::new(root);
+            builder.add(root.join(".git").join("info").join("exclude"));
+            builder.build().ok()
+        };
+        Self {
+            root: root.to_path_buf(),
+            globs,
+            per_dir: HashMap::new(),
+            exclude,
+            gitignore,
+        }
+    }
+
+    fn dir_rules(&mut self, dir: &Path) -> Option<&Gitignore> {
+        self.per_dir
+            .entry(dir.to_path_buf())
+            .or_insert_with(|| {
+                let file = dir.join(".gitignore");
+                if !file.is_file() {
+                    return None;
+                }
+                let mut builder = GitignoreBuilder::new(dir);
+                builder.add(file);
+                builder.build().ok()
+            })
+            .as_ref()
+    }
+
+    fn is_ignored(&mut self, path: &Path) -> bool {
+        let rel = match path.strip_prefix(&self.root) {
+            Ok(rel) => rel,
+            Err(_) => return true,
+        };
+        // Git's own bookkeeping churns on every status call.
+        if rel.components().next().map(|c| c.as_os_str()) == Some(".git".as_ref()) {
+            return true;
+        }
+        if self.globs.is_match(rel) {
+            return true;
+        }
+        if !self.gitignore {
+            return false;
+        }
+        let is_dir = path.is_dir();
+        if let Some(exclude) = &self.exclude {
+            if exclude
+                .matched_path_or_any_parents(path, is_dir)
+                .is_ignore()
+            {
+                return true;
+            }
+        }
+        // Check from the root downwards; later (deeper) files win, as in git.
+        let mut dirs: Vec<PathBuf> = path
+            .ancestors()
+            .skip(1)
+            .take_while(|d| d.starts_with(&self.root))
+            .map(Path::to_path_buf)
+            .collect();
+        dirs.reverse();
+        let mut ignored = false;
+        for dir in dirs {
+            if let Some(rules) = self.dir_rules(&dir) {
+                let m = rules.matched_path_or_any_parents(path, is_dir);
+                if m.is_ignore() {
+                    ignored = true;
+                } else if m.is_whitelist() {
+                    ignored = false;
+                }
+            }
+        }
+        ignored
+    }
+
+    fn invalidate(&mut self, path: &Path) {
+        if path.file_name().map(|n| n == ".gitignore").unwrap_or(false) {
+            if let Some(dir) = path.parent() {
+                self.per_dir.remove(dir);
+            }
+        }
+    }
+}
+
+fn build_globs(patterns: &[String]) -> Result<GlobSet, String> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern).map_err(|e| format!("invalid ignore glob {pattern}: {e}"))?;
+        builder.add(glob);
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+fn relative(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+/// Collects raw notify events and emits one `fs:changed` per burst.
+fn run_debouncer(
+    app: AppHandle,
+    id: String,
+    root: PathBuf,
+    mut rules: IgnoreRules,
+    rx: mpsc::Receiver<notify::Result<notify::Event>>,
+) {
+    let root_str = root.to_string_lossy().to_string();
+    let mut pending: BTreeSet<String> = BTreeSet::new();
+    let mut overflow = false;
+    let mut batch_started: Option<Instant> = None;
+
+    loop {
+        let event = rx.recv_timeout(DEBOUNCE);
+        match event {
+            Ok(Ok(event)) => {
+                if matches!(event.kind, notify::EventKind::Access(_)) {
+                    continue;
+                }
+                for path in &event.paths {
+                    rules.invalidate(path);
+                    if rules.is_ignored(path) {
+                        continue;
+                    }
+                    if pending.len() >= MAX_BATCH_PATHS {
+                        overflow = true;
+                    } else {
+                        pending.insert(relative(&root, path));
+                    }
+                }
+                // Events the OS couldn't attribute to a path (queue overflow).
+                if event.need_rescan() {
+                    overflow = true;
+                }
+                if (!pending.is_empty() || overflow) && batch_started.is_none() {
+                    batch_started = Some(Instant::now());
+                }
+                let waited_too_long = batch_started
+                    .map(|t| t.elapsed() >= MAX_BATCH_DELAY)
+                    .unwrap_or(false);
+                if !waited_too_long {
+                    continue;
+                }
+            }
+            Ok(Err(_)) => continue,
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        if pending.is_empty() && !overflow {
+            continue;
+        }
+        let paths = if overflow {
+            Vec::new()
+        } else {
+            std::mem::take(&mut pending).into_iter().collect()
+        };
+        pending.clear();
+        // Project config watches only trigger a reload.
+        if crate::project_config::on_watch_event(&app, &id) {
+            overflow = false;
+            batch_started = None;
+            continue;
+        }
+        // Update the tree index first so listeners refreshing on this event
+        // already see the new state.
+        crate::file_index::apply_changes(&app, &root, &paths, overflow);
+        crate::recent::record(&app, &root, &paths, crate::recent::TouchSource::Fs);
+        // Internal watches (the file index) have no subscribers.
+        crate::events::emit_subscribed_throttled(
+            &app,
+            &watch_topic(&id),
+            crate::events::FS_CHANGED,
+            "fs:changed",
+            crate::events::Coalesce::Merge,
+            FsChangedPayload {
+                id: id.clone(),
+                root: root_str.clone(),
+                paths,
+                overflow,
+            },
+        );
+        overflow = false;
+        batch_started = None;
+    }
+}
+
+/// Event-router topic for the watch `id`.
+pub(crate) fn watch_topic(id: &str) -> String {
+    format!("{WATCH_TOPIC_PREFIX}{id}")
+}
+
+pub(crate) const WATCH_TOPIC_PREFIX: &str = "watch:";
+
+/// Start (or replace) the watch `id` on `path`. Returns the canonical root.
+pub(crate) fn start_watch(
+    app: &AppHandle,
+    state: &FileWatcher,
+    id: String,
+    path: &str,
+    options: WatchOptions,
+) -> Result<PathBuf, String> {
+    let root = app.state::<Sandbox>().check_dir(path)?;
+    if !root.is_dir() {
+        return Err(format!("cannot watch {path}: not a directory"));
+    }
+    let globs = build_globs(&options.ignore_globs)?;
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |event| {
+        let _ = tx.send(event);
+    })
+    .map_err(|e| e.to_string())?;
+    let mode = if options.recursive.unwrap_or(true) {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    };
+    watcher.watch(&root, mode).map_err(|e| e.to_string())?;
+
+    let rules = IgnoreRules::new(&root, globs, options.gitignore.unwrap_or(true));
+    let thread_app = app.clone();
+    let thread_id = id.clone();
+    let thread_root = root.clone();
+    std::thread::spawn(move || run_debouncer(thread_app, thread_id, thread_root, rules, rx));
+
+    let mut watches = state.watches.lock().map_err(|e| e.to_string())?;
+    watches.insert(id, WatchInstance { _watcher: watcher });
+    Ok(root)
+}
+
+/// Start watching `path` under the subscription `id`. Changes are coalesced
+/// and emitted as `fs:changed` events to the calling window, skipping
+/// anything `.gitignore`d. Watching an id that already exists replaces the
+/// previous subscription; the watch stops when its window closes.
+#[tauri::command]
+pub fn watch_path(
+    window: WebviewWindow,
+    app: AppHandle,
+    state: State<'_, FileWatcher>,
+    id: String,
+    path: String,
+    options: Option<WatchOptions>,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    let topic = watch_topic(&id);
+    start_watch(&app, &state, id, &path, options.unwrap_or_default())?;
+    let router = app.state::<EventRouter>();
+    router.remove_topic(&topic);
+    router.subscribe(&topic, window.label());
+    Ok(())
+}
+
+#[tauri::command]
+pub fn unwatch_path(
+    window: WebviewWindow,
+    state: State<'_, FileWatcher>,
+    router: State<'_, EventRouter>,
+    id: String,
+) -> Result<(), String> {
+    crate::require_app_window(&window)?;
+    router.remove_topic(&watch_topic(&id));
+    stop_watch(&state, &id)
+}
+
+pub(crate) fn stop_watch(state: &FileWatcher, id: &str) -> Result<(), String> {
+    let mut watches = state.watches.lock().map_err(|e| e.to_string())?;
+    watches.remove(id);
+    Ok(())
+}
+
+/// Drop every watch — called on app exit
+pub fn unwatch_all(state: &FileWatcher) {
+    if let Ok(mut watches) = state.watches.lock() {
+        watches.clear();
+    }
+}
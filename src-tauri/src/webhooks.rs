@@ -0,0 +1,189 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+use tauri::{AppHandle, WebviewWindow};
+
+use crate::notifications::{Milestone, NotificationTarget};
+
+const POST_TIMEOUT: Duration = Duration::from_secs(15);
+/// Discord rejects embed descriptions longer than this.
+const MAX_BODY_CHARS: usize = 4000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WebhookKind {
+    Slack,
+    Discord,
+}
+
+fn default_events() -> Vec<Milestone> {
+    vec![
+        Milestone::AgentFinished,
+        Milestone::AwaitingApproval,
+        Milestone::TestsFailed,
+    ]
+}
+
+/// An incoming-webhook URL milestones are posted to, so they reach a team
+/// channel as well as the desktop. The URL is a credential, so only the
+/// name of the secret holding it is kept in the settings.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Webhook {
+    pub kind: WebhookKind,
+    pub secret: String,
+    #[serde(default = "default_events")]
+    pub events: Vec<Milestone>,
+}
+
+pub(crate) fn validate_webhooks(webhooks: &[Webhook]) -> Result<(), String> {
+    for webhook in webhooks {
+        if webhook.secret.trim().is_empty() {
+            return Err("a webhook needs the name of the secret holding its URL".to_string());
+        }
+        if webhook.events.contains(&Milestone::TerminalBell) {
+            return Err("terminal bells are not posted to webhooks".to_string());
+        }
+    }
+    Ok(())
+}
+
+/// `funny://open?project=…&task=…` for the project and thread of `target`.
+fn deep_link(target: &NotificationTarget) -> Option<String> {
+    if target.project.is_none() && target.thread_id.is_none() {
+        return None;
+    }
+    let mut url = url::Url::parse("funny://open").ok()?;
+    {
+        let mut query = url.query_pairs_mut();
+        if let Some(project) = &target.project {
+            query.append_pair("project", project);
+        }
+        if let Some(thread) = &target.thread_id {
+            query.append_pair("task", thread);
+        }
+    }
+    Some(url.to_string())
+}
+
+/// `&`, `<` and `>` are control characters in Slack messages.
+fn slack_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn message(kind: WebhookKind, title: &str, body: &str, link: Option<&str>) -> Value {
+    let title = crate::redact::redact(title);
+    let mut body = crate::redact::redact(body);
+    if let Some((i, _)) = body.char_indices().nth(MAX_BODY_CHARS) {
+        body.truncate(i);
+        body.push('…');
+    }
+    match kind {
+        WebhookKind::Slack => {
+            let mut text = format!("*{}*", slack_escape(&title));
+            if !body.is_empty() {
+                text.push_str(&format!("\n{}", slack_escape(&body)));
+            }
+            if let Some(link) = link {
+                text.push_str(&format!("\n<{link}|Open in funny>"));
+            }
+            json!({ "text": text })
+        }
+        WebhookKind::Discord => {
+            let mut description = body;
+            if let Some(link) = link {
+                // Discord only links http(s) URLs; this one is for copying.
+                description.push_str(&format!("\n\nOpen in funny: `{link}`"));
+            }
+            json!({
+                "username": "funny",
+                "embeds": [{ "title": title, "description": description }],
+            })
+        }
+    }
+}
+
+fn send(secret: &str, payload: &Value) -> Result<(), String> {
+    let url = crate::secrets::get(secret)
+        .ok_or_else(|| format!("no secret {secret} for the webhook URL"))?;
+    let parsed = url::Url::parse(url.trim()).map_err(|e| format!("webhook URL: {e}"))?;
+    if parsed.scheme() != "https" {
+        return Err("webhook URLs must use https".to_string());
+    }
+    ureq::post(parsed.as_str())
+        .timeout(POST_TIMEOUT)
+        .send_json(payload)
+        .map_err(|e| match e {
+            // The URL is the credential; keep it out of the error.
+            ureq::Error::Status(code, _) => format!("webhook returned {code}"),
+            ureq::Error::Transport(t) => format!("webhook unreachable: {}", t.kind()),
+        })?;
+    Ok(())
+}
+
+/// Post a message to the webhook whose URL is in secret `secret`.
+pub(crate) fn post_message(
+    kind: WebhookKind,
+    secret: &str,
+    title: &str,
+    body: &str,
+) -> Result<(), String> {
+    send(secret, &message(kind, title, body, None))
+}
+
+/// Post `milestone` to the webhooks subscribed to it, off the calling
+/// thread. Failures are logged.
+pub(crate) fn post(
+    app: &AppHandle,
+    milestone: Milestone,
+    title: &str,
+    body: &str,
+    target: &NotificationTarget,
+) {
+    let webhooks: Vec<Webhook> = crate::settings::current(app)
+        .notifications
+        .webhooks
+        .into_iter()
+        .filter(|w| w.events.contains(&milestone))
+        .collect();
+    if webhooks.is_empty() {
+        return;
+    }
+    let link = deep_link(target);
+    let (title, body) = (title.to_string(), body.to_string());
+    std::thread::spawn(move || {
+        for webhook in webhooks {
+            let payload = message(webhook.kind, &title, &body, link.as_deref());
+            if let Err(e) = send(&webhook.secret, &payload) {
+                tracing::warn!("{:?} webhook {}: {e}", webhook.kind, webhook.secret);
+            }
+        }
+    });
+}
+
+/// Post a test message to webhook `index` of the notification rules.
+#[tauri::command]
+pub async fn notification_webhook_test(
+    window: WebviewWindow,
+    app: AppHandle,
+    index: usize,
+) -> Result<(), String> {
+    crate::require_window(&window, "main")?;
+    let webhook = crate::settings::current(&app)
+        .notifications
+        .webhooks
+        .get(index)
+        .cloned()
+        .ok_or_else(|| format!("no webhook {index}"))?;
+    let payload = message(
+        webhook.kind,
+        "Test notification",
+        "Milestones will be posted here.",
+        None,
+    );
+    tauri::async_runtime::spawn_blocking(move || send(&webhook.secret, &payload))
+        .await
+        .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,187 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, Monitor, PhysicalPosition, PhysicalSize, WebviewWindow, Window};
+
+const STATE_FILE: &str = "window-state.json";
+/// How much of the title bar must be on a monitor for a saved position to be
+/// used as is.
+const MIN_VISIBLE: i32 = 64;
+
+/// Where a window was and how big, in physical pixels. Position and size are
+/// of the window when last neither maximized nor minimized, so a maximized
+/// window un-maximizes back to them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WindowGeometry {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    maximized: bool,
+    /// Name of the monitor it was on.
+    monitor: Option<String>,
+}
+
+/// Geometry of the main and project windows by label, kept up to date as
+/// they move and written out when one closes and on exit.
+pub struct WindowStates {
+    states: Mutex<Option<HashMap<String, WindowGeometry>>>,
+}
+
+impl WindowStates {
+    pub fn new() -> Self {
+        Self {
+            states: Mutex::new(None),
+        }
+    }
+}
+
+fn state_path(app: &AppHandle) -> Option<PathBuf> {
+    app.path()
+        .app_data_dir()
+        .ok()
+        .map(|dir| dir.join(STATE_FILE))
+}
+
+fn load(app: &AppHandle) -> HashMap<String, WindowGeometry> {
+    state_path(app)
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Write the geometry of every window seen so far.
+pub(crate) fn save(app: &AppHandle) {
+    let result = (|| {
+        let state = app.try_state::<WindowStates>().ok_or("not ready")?;
+        let states = state.states.lock().map_err(|e| e.to_string())?;
+        let states = match states.as_ref() {
+            Some(states) => states,
+            None => return Ok(()),
+        };
+        let path = state_path(app).ok_or("no app data directory")?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_vec_pretty(states).map_err(|e| e.to_string())?;
+        std::fs::write(path, json).map_err(|e| e.to_string())
+    })();
+    if let Err(e) = result {
+        tracing::warn!("failed to save window state: {e}");
+    }
+}
+
+/// Record `window`'s geometry after it moved or was resized.
+pub(crate) fn track(window: &Window) {
+    let app = window.app_handle();
+    let state = match app.try_state::<WindowStates>() {
+        Some(state) => state,
+        None => return,
+    };
+    let mut states = match state.states.lock() {
+        Ok(states) => states,
+        Err(_) => return,
+    };
+    let states = states.get_or_insert_with(|| load(app));
+    let maximized = window.is_maximized().unwrap_or(false);
+    let minimized = window.is_minimized().unwrap_or(false);
+    let monitor = window
+        .current_monitor()
+        .ok()
+        .flatten()
+        .and_then(|m| m.name().cloned());
+    if minimized {
+        return;
+    }
+    let (position, size) = match (window.outer_position(), window.inner_size()) {
+        (Ok(position), Ok(size)) => (position, size),
+        _ => return,
+    };
+    if maximized {
+        // Keep the size to restore to, if there is one.
+        if let Some(geometry) = states.get_mut(window.label()) {
+            geometry.maximized = true;
+            geometry.monitor = monitor.or(geometry.monitor.take());
+            return;
+        }
+    }
+    states.insert(
+        window.label().to_string(),
+        WindowGeometry {
+            x: position.x,
+            y: position.y,
+            width: size.width,
+            height: size.height,
+            maximized,
+            monitor,
+        },
+    );
+}
+
+/// Whether enough of a window at `geometry` would be on `monitor` to grab
+/// it by the title bar.
+fn visible_on(geometry: &WindowGeometry, monitor: &Monitor) -> bool {
+    let (origin, size) = (monitor.position(), monitor.size());
+    let right = (geometry.x + geometry.width as i32).min(origin.x + size.width as i32);
+    let left = geometry.x.max(origin.x);
+    let top_inside = geometry.y >= origin.y && geometry.y < origin.y + size.height as i32;
+    right - left >= MIN_VISIBLE && top_inside
+}
+
+/// Put `window` where it was last time, then show it. A window whose
+/// monitor is gone, or that would end up off-screen, is centered on the
+/// primary monitor at its saved size, shrunk to fit.
+pub(crate) fn restore(window: &WebviewWindow) {
+    let app = window.app_handle();
+    let geometry = app.try_state::<WindowStates>().and_then(|state| {
+        let mut states = state.states.lock().ok()?;
+        states
+            .get_or_insert_with(|| load(app))
+            .get(window.label())
+            .cloned()
+    });
+    if let Some(geometry) = geometry {
+        place(window, &geometry);
+    }
+    if let Err(e) = window.show() {
+        tracing::warn!("failed to show window {}: {e}", window.label());
+    }
+}
+
+fn place(window: &WebviewWindow, geometry: &WindowGeometry) {
+    let monitors = window.available_monitors().unwrap_or_default();
+    let on_screen = monitors.iter().any(|m| {
+        visible_on(geometry, m)
+            && (geometry.monitor.is_none() || m.name() == geometry.monitor.as_ref())
+    });
+    let size = PhysicalSize::new(geometry.width, geometry.height);
+    if on_screen {
+        let _ = window.set_size(size);
+        let _ = window.set_position(PhysicalPosition::new(geometry.x, geometry.y));
+    } else {
+        let fallback = window
+            .primary_monitor()
+            .ok()
+            .flatten()
+            .or_else(|| monitors.into_iter().next());
+        if let Some(monitor) = fallback {
+            let (origin, area) = (monitor.position(), monitor.size());
+            let size = PhysicalSize::new(size.width.min(area.width), size.height.min(area.height));
+            let _ = window.set_size(size);
+            let _ = window.set_position(PhysicalPosition::new(
+                origin.x + (area.width - size.width) as i32 / 2,
+                origin.y + (area.height - size.height) as i32 / 2,
+            ));
+            tracing::info!(
+                "monitor of window {} is gone; centered it on {}",
+                window.label(),
+                monitor.name().map_or("the primary monitor", String::as_str)
+            );
+        }
+    }
+    if geometry.maximized {
+        let _ = window.maximize();
+    }
+}
@@ -0,0 +1,76 @@
+use serde::Serialize;
+use tauri::{AppHandle, Manager, WebviewUrl, WebviewWindow, WebviewWindowBuilder};
+
+use crate::sandbox::Sandbox;
+
+/// Labels of per-project windows start with this; the capability file grants
+/// them the same permissions as `main` via a `project-*` glob.
+pub const PROJECT_WINDOW_PREFIX: &str = "project-";
+
+/// Window labels only allow `a-zA-Z0-9-/:_`, so the project path is hashed
+/// (FNV-1a) into a stable label instead.
+fn project_label(path: &str) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in path.bytes() {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{PROJECT_WINDOW_PREFIX}{hash:016x}")
+}
+
+pub(crate) fn is_project_window(label: &str) -> bool {
+    label.starts_with(PROJECT_WINDOW_PREFIX)
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectWindow {
+    pub label: String,
+    pub project: String,
+}
+
+/// Open `project` in its own native window, or focus the one already showing
+/// it. The window loads the regular UI with `window.__PROJECT_WINDOW__` set
+/// to the project path so it renders just that project. Terminals it spawns
+/// are owned by it and killed when it closes.
+#[tauri::command]
+pub async fn open_project_window(
+    window: WebviewWindow,
+    app: AppHandle,
+    project: String,
+) -> Result<ProjectWindow, String> {
+    crate::require_window(&window, "main")?;
+    let root = app.state::<Sandbox>().check_dir(&project)?;
+    let project = root.to_string_lossy().to_string();
+    let label = project_label(&project);
+
+    if let Some(existing) = app.get_webview_window(&label) {
+        existing.set_focus().map_err(|e| format!("{e}"))?;
+        return Ok(ProjectWindow { label, project });
+    }
+
+    let name = root
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| project.clone());
+    let script = format!(
+        "window.__PROJECT_WINDOW__ = {};",
+        serde_json::to_string(&project).map_err(|e| e.to_string())?
+    );
+    let created = WebviewWindowBuilder::new(
+        &app,
+        &label,
+        WebviewUrl::App(std::path::PathBuf::from("index.html")),
+    )
+    .initialization_script(&script)
+    .title(format!("funny — {name}"))
+    .inner_size(1280.0, 800.0)
+    .min_inner_size(900.0, 600.0)
+    .visible(false)
+    .build()
+    .map_err(|e| format!("{e}"))?;
+    crate::window_state::restore(&created);
+    crate::app_menu::project_opened(&app, &project);
+
+    Ok(ProjectWindow { label, project })
+}
@@ -0,0 +1,197 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::OnceLock;
+use tauri::{AppHandle, WebviewWindow};
+
+use crate::layouts::Layout;
+use crate::settings::Settings;
+
+const FORMAT: &str = "funny-workspace";
+/// Bumped when the file layout changes incompatibly; settings carry their own
+/// schema version.
+const FORMAT_VERSION: u32 = 1;
+/// Workspace files larger than this are refused.
+const MAX_FILE_SIZE: u64 = 16 * 1024 * 1024;
+
+/// What `workspace_export` writes: everything needed to set funny up the
+/// same way elsewhere, minus secret values.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WorkspaceFile {
+    format: String,
+    version: u32,
+    /// RFC 3339.
+    exported_at: String,
+    app_version: String,
+    settings: Value,
+    /// Stored secret names and their environment variables. The values stay
+    /// in the keychain.
+    #[serde(default)]
+    secrets: BTreeMap<String, String>,
+    /// Saved layouts, by project root.
+    #[serde(default)]
+    layouts: BTreeMap<String, Vec<Layout>>,
+    /// The frontend's part: projects, agent profiles, open terminals.
+    #[serde(default)]
+    state: Value,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceImport {
+    /// The settings now in effect.
+    pub settings: Settings,
+    /// The frontend state from the file, for the frontend to apply.
+    pub state: Value,
+    /// Secrets the workspace used that aren't stored on this machine, to be
+    /// entered again.
+    pub missing_secrets: Vec<String>,
+    pub exported_at: String,
+    pub app_version: String,
+}
+
+/// Keys whose values are dropped from exported state, whatever they hold:
+/// `apiKey`, `githubToken`, `db_password`, ….
+fn is_secret_key(key: &str) -> bool {
+    static SECRET_KEY: OnceLock<Regex> = OnceLock::new();
+    SECRET_KEY
+        .get_or_init(|| {
+            Regex::new(r"(?i)(secret|token|passw(or)?d|api_?key|credentials?|private_?key)$")
+                .expect("valid pattern")
+        })
+        .is_match(key)
+}
+
+/// Drop secret-named fields and mask tokens in strings, recursively.
+fn scrub(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            object.retain(|key, _| !is_secret_key(key));
+            object.values_mut().for_each(scrub);
+        }
+        Value::Array(items) => items.iter_mut().for_each(scrub),
+        Value::String(text) => *text = crate::redact::redact(text),
+        _ => {}
+    }
+}
+
+/// Write `file` to `path` through a temporary file, so a failed write
+/// doesn't leave half a workspace behind.
+fn write_file(path: &Path, file: &WorkspaceFile) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(file).map_err(|e| e.to_string())?;
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, json).map_err(|e| e.to_string())?;
+    std::fs::rename(&tmp, path).map_err(|e| e.to_string())
+}
+
+fn read_file(path: &Path) -> Result<WorkspaceFile, String> {
+    let meta = std::fs::metadata(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    if meta.len() > MAX_FILE_SIZE {
+        return Err(format!("{} is too large", path.display()));
+    }
+    let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let file: WorkspaceFile =
+        serde_json::from_str(&content).map_err(|e| format!("not a workspace file: {e}"))?;
+    if file.format != FORMAT {
+        return Err("not a workspace file".to_string());
+    }
+    if file.version > FORMAT_VERSION {
+        return Err("the workspace file is from a newer version of funny".to_string());
+    }
+    Ok(file)
+}
+
+fn absolute(path: &str) -> Result<&Path, String> {
+    let path = Path::new(path);
+    if path.is_absolute() {
+        Ok(path)
+    } else {
+        Err(format!("{} must be an absolute path", path.display()))
+    }
+}
+
+/// Save the whole setup to `path`, a location the user picked: the settings
+/// (agent defaults, command and network policies, notification rules, …),
+/// which secrets exist, saved layouts, and `state` from the frontend
+/// (projects, agent profiles, open terminals). Secret values are never written; in `state`,
+/// fields named like secrets are dropped and token-like strings masked.
+#[tauri::command]
+pub async fn workspace_export(
+    window: WebviewWindow,
+    app: AppHandle,
+    path: String,
+    state: Option<Value>,
+) -> Result<(), String> {
+    crate::require_window(&window, "main")?;
+    let path = absolute(&path)?.to_path_buf();
+    tauri::async_runtime::spawn_blocking(move || {
+        // Settings never hold secret values; those live in the keychain.
+        let settings =
+            serde_json::to_value(crate::settings::current(&app)).map_err(|e| e.to_string())?;
+        let mut state = state.unwrap_or(Value::Null);
+        scrub(&mut state);
+        let mut layouts: BTreeMap<String, Vec<Layout>> = BTreeMap::new();
+        for (project, layout) in crate::layouts::load_all(&app, None)? {
+            layouts.entry(project).or_default().push(layout);
+        }
+        let file = WorkspaceFile {
+            format: FORMAT.to_string(),
+            version: FORMAT_VERSION,
+            exported_at: chrono::Utc::now().to_rfc3339(),
+            app_version: app.package_info().version.to_string(),
+            settings,
+            secrets: crate::secrets::names(&app),
+            layouts,
+            state,
+        };
+        write_file(&path, &file)?;
+        tracing::info!("exported workspace to {}", path.display());
+        Ok(())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Load a file written by `workspace_export`: its settings replace the
+/// current ones, its layouts are saved over ones with the same name, and its
+/// frontend state is returned to be applied. Nothing changes if the file or
+/// its settings are invalid.
+#[tauri::command]
+pub async fn workspace_import(
+    window: WebviewWindow,
+    app: AppHandle,
+    path: String,
+) -> Result<WorkspaceImport, String> {
+    crate::require_window(&window, "main")?;
+    let path = absolute(&path)?.to_path_buf();
+    tauri::async_runtime::spawn_blocking(move || {
+        let file = read_file(&path)?;
+        let settings = crate::settings::replace(&app, file.settings)?;
+        for (project, layouts) in &file.layouts {
+            for layout in layouts {
+                if let Err(e) = crate::layouts::store(&app, project, layout) {
+                    tracing::warn!("layout {} for {project} not imported: {e}", layout.name);
+                }
+            }
+        }
+        let stored = crate::secrets::names(&app);
+        let missing_secrets = file
+            .secrets
+            .into_keys()
+            .filter(|name| !stored.contains_key(name))
+            .collect();
+        tracing::info!("imported workspace from {}", path.display());
+        Ok(WorkspaceImport {
+            settings,
+            state: file.state,
+            missing_secrets,
+            exported_at: file.exported_at,
+            app_version: file.app_version,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
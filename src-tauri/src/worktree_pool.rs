@@ -0,0 +1,424 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State, WebviewWindow};
+
+use crate::forge::run_capture;
+use crate::jobs::{JobHandle, JobInfo};
+use crate::sandbox::{Sandbox, WORKTREE_DIR_NAME};
+
+/// Pooled worktrees are `<worktree dir>/.pool-<id>`: branch names can't
+/// start with a dot, so they never collide with a task's worktree.
+const POOL_PREFIX: &str = ".pool-";
+
+const MAX_POOL_SIZE: u32 = 8;
+
+/// Ignored dependency directories new worktrees get a copy of, and whether
+/// a hardlinked one is safe: package managers replace files in
+/// `node_modules` rather than write into them, while builds and pip do
+/// write into `target` and venvs, so those are only ever reflinked.
+const DEPENDENCY_DIRS: &[(&str, bool)] = &[
+    ("node_modules", true),
+    ("target", false),
+    (".venv", false),
+    ("venv", false),
+];
+
+/// Worktrees made ahead of time, so a task doesn't wait for a checkout
+/// and a dependency install. Kept in the settings file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WorktreePoolSettings {
+    /// Pooled worktrees kept per project once it has used one; 0 turns the
+    /// pool off.
+    pub size: u32,
+    /// Give new worktrees reflinked or hardlinked copies of the project's
+    /// dependency directories.
+    pub link_dependencies: bool,
+}
+
+impl Default for WorktreePoolSettings {
+    fn default() -> Self {
+        Self {
+            size: 0,
+            link_dependencies: true,
+        }
+    }
+}
+
+pub(crate) fn validate_settings(settings: &WorktreePoolSettings) -> Result<(), String> {
+    if settings.size > MAX_POOL_SIZE {
+        return Err(format!("worktreePool.size must be at most {MAX_POOL_SIZE}"));
+    }
+    Ok(())
+}
+
+/// Pooled worktrees being handed out or made, and projects being refilled,
+/// so two tasks never get the same one.
+pub struct WorktreePool {
+    busy: Mutex<HashSet<PathBuf>>,
+    next_id: AtomicU64,
+}
+
+impl WorktreePool {
+    pub fn new() -> Self {
+        Self {
+            busy: Mutex::new(HashSet::new()),
+            next_id: AtomicU64::new(1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PooledWorktree {
+    pub path: String,
+    pub commit: String,
+    /// Behind the project's HEAD; brought up to date when taken.
+    pub stale: bool,
+    /// The dependency directories it has.
+    pub dependencies: Vec<String>,
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<String, String> {
+    run_capture(Command::new("git").args(args).current_dir(dir), None)
+}
+
+fn reserve(app: &AppHandle, path: &Path) -> bool {
+    let pool = app.state::<WorktreePool>();
+    let reserved = pool
+        .busy
+        .lock()
+        .is_ok_and(|mut busy| busy.insert(path.to_path_buf()));
+    reserved
+}
+
+fn release(app: &AppHandle, path: &Path) {
+    if let Ok(mut busy) = app.state::<WorktreePool>().busy.lock() {
+        busy.remove(path);
+    }
+}
+
+/// `<parent>/.funny-worktrees/<project name>`, where the pool lives next to
+/// the project's task worktrees.
+fn base_dir(root: &Path) -> Result<PathBuf, String> {
+    match (root.parent(), root.file_name()) {
+        (Some(parent), Some(name)) => Ok(parent.join(WORKTREE_DIR_NAME).join(name)),
+        _ => Err(format!("cannot pool worktrees of {}", root.display())),
+    }
+}
+
+/// The pooled worktrees of `root` on disk, including ones from earlier
+/// sessions.
+fn pooled(root: &Path) -> Vec<PathBuf> {
+    let Ok(entries) =
+        base_dir(root).and_then(|base| std::fs::read_dir(base).map_err(|e| e.to_string()))
+    else {
+        return Vec::new();
+    };
+    let mut dirs: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(POOL_PREFIX))
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+/// Hardlink every file under `from` into `to`, recreating directories and
+/// symlinks.
+fn hardlink_tree(from: &Path, to: &Path) -> std::io::Result<()> {
+    let meta = std::fs::symlink_metadata(from)?;
+    if meta.is_dir() {
+        std::fs::create_dir_all(to)?;
+        for entry in std::fs::read_dir(from)? {
+            let entry = entry?;
+            hardlink_tree(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        return Ok(());
+    }
+    #[cfg(unix)]
+    if meta.file_type().is_symlink() {
+        return std::os::unix::fs::symlink(std::fs::read_link(from)?, to);
+    }
+    std::fs::hard_link(from, to)
+}
+
+/// A copy-on-write clone of `from` at `to`, where the filesystem has them.
+fn reflink(from: &Path, to: &Path) -> Result<(), String> {
+    if cfg!(target_os = "macos") {
+        // `cp -c` clones with clonefile(2); it fails off APFS.
+        run_capture(Command::new("cp").arg("-cR").arg(from).arg(to), None).map(|_| ())
+    } else if cfg!(target_os = "linux") {
+        run_capture(
+            Command::new("cp")
+                .args(["-a", "--reflink=always"])
+                .arg(from)
+                .arg(to),
+            None,
+        )
+        .map(|_| ())
+    } else {
+        Err("no reflinks on this platform".to_string())
+    }
+}
+
+/// Give `worktree` the dependency directories `root` has and git ignores,
+/// by reflink, or by hardlink where that is safe. Ones neither works for
+/// are left for the task to install: a full copy would cost more than it
+/// saves. Returns what was linked and how, e.g. `node_modules (hardlink)`.
+pub(crate) fn link_dependencies(root: &Path, worktree: &Path) -> Vec<String> {
+    let mut linked = Vec::new();
+    for &(name, hardlink_ok) in DEPENDENCY_DIRS {
+        let (from, to) = (root.join(name), worktree.join(name));
+        if !from.is_dir() || to.exists() || git(root, &["check-ignore", "-q", name]).is_err() {
+            continue;
+        }
+        let method = match reflink(&from, &to) {
+            Ok(()) => "reflink",
+            Err(e) if hardlink_ok => {
+                let _ = std::fs::remove_dir_all(&to);
+                match hardlink_tree(&from, &to) {
+                    Ok(()) => "hardlink",
+                    Err(link) => {
+                        tracing::debug!("not linking {name}: {e}; {link}");
+                        let _ = std::fs::remove_dir_all(&to);
+                        continue;
+                    }
+                }
+            }
+            Err(e) => {
+                tracing::debug!("not linking {name}: {e}");
+                let _ = std::fs::remove_dir_all(&to);
+                continue;
+            }
+        };
+        linked.push(format!("{name} ({method})"));
+    }
+    linked
+}
+
+/// Make one pooled worktree of `root`: detached at HEAD and locked, so git
+/// and cleanup leave it alone until it is taken. It stays reserved while it
+/// is being made, so `take` and `drain` skip it.
+fn create(app: &AppHandle, root: &Path) -> Result<PathBuf, String> {
+    let id = format!(
+        "{POOL_PREFIX}{}-{}",
+        chrono::Utc::now().format("%Y%m%d%H%M%S"),
+        app.state::<WorktreePool>()
+            .next_id
+            .fetch_add(1, Ordering::Relaxed)
+    );
+    let dir = base_dir(root)?.join(id);
+    if !reserve(app, &dir) {
+        return Err(format!("{} is already being made", dir.display()));
+    }
+    let made = populate(app, root, &dir);
+    release(app, &dir);
+    made.map(|()| dir)
+}
+
+fn populate(app: &AppHandle, root: &Path, dir: &Path) -> Result<(), String> {
+    crate::disk::ensure_space(app, dir, crate::disk::checkout_bytes(root))?;
+    run_capture(
+        Command::new("git")
+            .args(["worktree", "add", "--detach", "--lock"])
+            .arg(dir)
+            .arg("HEAD")
+            .current_dir(root),
+        None,
+    )?;
+    let linked = if crate::settings::current(app)
+        .worktree_pool
+        .link_dependencies
+    {
+        link_dependencies(root, dir)
+    } else {
+        Vec::new()
+    };
+    tracing::info!("pooled worktree {} with {linked:?}", dir.display());
+    Ok(())
+}
+
+/// Fill `root`'s pool up to `count`. Returns how many were made.
+fn warm(app: &AppHandle, root: &Path, count: u32, job: Option<&JobHandle>) -> Result<u32, String> {
+    let have = pooled(root).len() as u32;
+    let missing = count.saturating_sub(have);
+    for made in 0..missing {
+        if let Some(job) = job {
+            job.check()?;
+            job.progress(u64::from(made), Some(u64::from(missing)), Some("creating"));
+        }
+        create(app, root)?;
+    }
+    Ok(missing)
+}
+
+/// Top the pool of `root` back up to the configured size in the background,
+/// unless a refill is already running.
+fn refill(app: &AppHandle, root: &Path) {
+    let size = crate::settings::current(app).worktree_pool.size;
+    let Ok(base) = base_dir(root) else {
+        return;
+    };
+    if size == 0 || !reserve(app, &base) {
+        return;
+    }
+    let (app, root) = (app.clone(), root.to_path_buf());
+    std::thread::spawn(move || {
+        if let Err(e) = warm(&app, &root, size, None) {
+            tracing::warn!("failed to refill worktree pool of {}: {e}", root.display());
+        }
+        release(&app, &base);
+    });
+}
+
+/// Remove pooled worktree `dir` of `root`.
+fn remove(root: &Path, dir: &Path) -> Result<(), String> {
+    let path = dir.to_string_lossy();
+    let _ = git(root, &["worktree", "unlock", &path]);
+    git(root, &["worktree", "remove", "--force", &path]).map(|_| ())
+}
+
+/// Turn a pooled worktree of `root` into `target` on new branch `branch`
+/// off HEAD, as `git worktree add -b` would. `None` when the pool has none
+/// to give, for the caller to create one itself.
+pub(crate) fn take(app: &AppHandle, root: &Path, branch: &str, target: &Path) -> Option<PathBuf> {
+    let head = git(root, &["rev-parse", "HEAD"]).ok()?;
+    let Some(dir) = pooled(root).into_iter().find(|dir| reserve(app, dir)) else {
+        refill(app, root);
+        return None;
+    };
+    let path = dir.to_string_lossy().to_string();
+    let target_path = target.to_string_lossy();
+    let mut branched = false;
+    let taken = (|| {
+        let status = git(&dir, &["status", "--porcelain"])?;
+        if !status.is_empty() {
+            return Err("it has changes".to_string());
+        }
+        git(&dir, &["checkout", "-q", "-b", branch, &head])?;
+        branched = true;
+        git(root, &["worktree", "unlock", &path])?;
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        git(root, &["worktree", "move", &path, &target_path])
+    })();
+    release(app, &dir);
+    refill(app, root);
+    match taken {
+        Ok(_) => {
+            tracing::info!("took pooled worktree {path} for {branch}");
+            Some(target.to_path_buf())
+        }
+        Err(e) => {
+            tracing::warn!("not using pooled worktree {path}: {e}");
+            if let Err(e) = remove(root, &dir) {
+                tracing::warn!("failed to remove pooled worktree {path}: {e}");
+            }
+            // Left behind, the branch would fail the caller's own attempt.
+            if branched {
+                let _ = git(root, &["branch", "-D", branch]);
+            }
+            None
+        }
+    }
+}
+
+/// Create pooled worktrees of `project` until it has `count` (default: the
+/// configured size, at least one). Runs as a `worktree-pool.warm` job whose
+/// result is how many were made.
+#[tauri::command]
+pub fn worktree_pool_warm(
+    window: WebviewWindow,
+    app: AppHandle,
+    sandbox: State<'_, Sandbox>,
+    project: String,
+    count: Option<u32>,
+) -> Result<JobInfo, String> {
+    crate::require_app_window(&window)?;
+    let root = sandbox.check_dir(&project)?;
+    let count = count
+        .unwrap_or_else(|| crate::settings::current(&app).worktree_pool.size.max(1))
+        .min(MAX_POOL_SIZE);
+    let label = root
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let worker = app.clone();
+    crate::jobs::spawn(&app, &window, "worktree-pool.warm", label, move |job| {
+        let base = base_dir(&root)?;
+        if !reserve(&worker, &base) {
+            return Err("the pool is already being filled".to_string());
+        }
+        let made = warm(&worker, &root, count, Some(job));
+        release(&worker, &base);
+        made
+    })
+}
+
+/// The pooled worktrees of `project`.
+#[tauri::command]
+pub async fn worktree_pool_status(
+    window: WebviewWindow,
+    sandbox: State<'_, Sandbox>,
+    project: String,
+) -> Result<Vec<PooledWorktree>, String> {
+    crate::require_app_window(&window)?;
+    let root = sandbox.check_dir(&project)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let head = git(&root, &["rev-parse", "HEAD"]).unwrap_or_default();
+        Ok(pooled(&root)
+            .into_iter()
+            .map(|dir| {
+                let commit = git(&dir, &["rev-parse", "HEAD"]).unwrap_or_default();
+                PooledWorktree {
+                    path: dir.to_string_lossy().to_string(),
+                    stale: commit != head,
+                    commit,
+                    dependencies: DEPENDENCY_DIRS
+                        .iter()
+                        .filter(|(name, _)| dir.join(name).is_dir())
+                        .map(|(name, _)| name.to_string())
+                        .collect(),
+                }
+            })
+            .collect())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Remove the pooled worktrees of `project` that aren't being handed out.
+/// Returns how many were removed.
+#[tauri::command]
+pub async fn worktree_pool_drain(
+    window: WebviewWindow,
+    app: AppHandle,
+    sandbox: State<'_, Sandbox>,
+    project: String,
+) -> Result<usize, String> {
+    crate::require_app_window(&window)?;
+    let root = sandbox.check_dir(&project)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut removed = 0;
+        for dir in pooled(&root) {
+            if !reserve(&app, &dir) {
+                continue;
+            }
+            match remove(&root, &dir) {
+                Ok(()) => removed += 1,
+                Err(e) => tracing::warn!("failed to remove {}: {e}", dir.display()),
+            }
+            release(&app, &dir);
+        }
+        Ok(removed)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}